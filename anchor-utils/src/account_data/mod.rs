@@ -1,7 +1,10 @@
 pub mod associated_token;
 pub mod idl;
+pub mod pda;
+pub mod stake;
 pub mod system_account;
 pub mod token;
+pub mod vote;
 
 use anchor_lang::{error::Error, AccountDeserialize, AccountSerialize, Owner};
 use solana_program::rent::Rent;
@@ -11,8 +14,11 @@ use solana_sdk::{
 };
 
 pub use associated_token::AssociatedTokenAccount;
+pub use pda::PdaAccount;
+pub use stake::StakeAccount;
 pub use system_account::{SigningSystemAccount, SystemAccount};
 pub use token::{Mint, TokenAccount};
+pub use vote::VoteAccount;
 
 pub trait ToAnchorAccount {
     type Error;
@@ -0,0 +1,92 @@
+use crate::account_data::ToAnchorAccount;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::Account;
+
+/// An account placed at the program derived address of its seeds,
+/// rather than at [Pubkey::new_unique], so that fixtures live at their on-chain addresses.
+#[derive(Debug, Clone)]
+pub struct PdaAccount<T> {
+    pub inner: T,
+    pub seeds: Vec<Vec<u8>>,
+    pub program_id: Pubkey,
+    pub address: Pubkey,
+    pub bump: u8,
+}
+
+impl<T> PdaAccount<T> {
+    pub fn new(inner: T, seeds: &[&[u8]], program_id: &Pubkey) -> Self {
+        Self::with_bump(seeds, program_id, |_| inner)
+    }
+
+    /// For account data that stores its own bump.
+    pub fn with_bump(seeds: &[&[u8]], program_id: &Pubkey, inner: impl FnOnce(u8) -> T) -> Self {
+        let (address, bump) = Pubkey::find_program_address(seeds, program_id);
+        Self {
+            inner: inner(bump),
+            seeds: seeds.iter().map(|seed| seed.to_vec()).collect(),
+            program_id: *program_id,
+            address,
+            bump,
+        }
+    }
+
+    pub fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    /// The seeds followed by the bump, as passed to `invoke_signed`.
+    pub fn signer_seeds(&self) -> Vec<Vec<u8>> {
+        let mut seeds = self.seeds.clone();
+        seeds.push(vec![self.bump]);
+        seeds
+    }
+}
+
+impl<T: ToAnchorAccount> ToAnchorAccount for PdaAccount<T> {
+    type Error = T::Error;
+
+    fn generate_account_data(&self) -> Result<Vec<u8>, Self::Error> {
+        self.inner.generate_account_data()
+    }
+
+    fn owner(&self) -> Pubkey {
+        self.inner.owner()
+    }
+
+    fn to_keyed_account(&self) -> Result<(Pubkey, Account), Self::Error> {
+        Ok((self.address, self.to_account()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account_data::TokenAccount;
+
+    #[test]
+    fn pda_accounts_are_placed_at_their_derived_address() {
+        let program_id = Pubkey::new_unique();
+        let (mint, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let pda = PdaAccount::new(
+            TokenAccount::new(mint, owner, 5),
+            &[b"vault", mint.as_ref()],
+            &program_id,
+        );
+        let (address, bump) = Pubkey::find_program_address(&[b"vault", mint.as_ref()], &program_id);
+        assert_eq!((pda.address(), pda.bump), (address, bump));
+        assert_eq!(
+            Pubkey::create_program_address(
+                &pda.signer_seeds()
+                    .iter()
+                    .map(Vec::as_slice)
+                    .collect::<Vec<_>>(),
+                &program_id
+            ),
+            Ok(address)
+        );
+
+        let (keyed_address, account) = pda.to_keyed_account().unwrap();
+        assert_eq!(keyed_address, address);
+        assert_eq!(account.owner, spl_token::ID);
+    }
+}
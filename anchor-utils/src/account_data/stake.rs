@@ -0,0 +1,177 @@
+use solana_program::clock::Epoch;
+use solana_program::rent::Rent;
+use solana_program::stake::stake_flags::StakeFlags;
+use solana_program::stake::state::{Authorized, Delegation, Lockup, Meta, Stake, StakeStateV2};
+use solana_sdk::pubkey::Pubkey;
+use std::io::Write;
+use std::ops::{Deref, DerefMut};
+
+/// A stake account, with builder methods for its authorities, lockup and delegation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StakeAccount(StakeStateV2);
+
+impl StakeAccount {
+    pub const LEN: usize = StakeStateV2::size_of();
+
+    /// An initialized, undelegated stake account.
+    pub fn new(staker: Pubkey, withdrawer: Pubkey) -> Self {
+        Self(StakeStateV2::Initialized(Meta {
+            rent_exempt_reserve: Rent::default().minimum_balance(Self::LEN),
+            authorized: Authorized { staker, withdrawer },
+            lockup: Lockup::default(),
+        }))
+    }
+
+    /// A stake account delegating `stake` lamports to a vote account,
+    /// activating at `activation_epoch`.
+    pub fn new_delegated(
+        staker: Pubkey,
+        withdrawer: Pubkey,
+        voter_pubkey: Pubkey,
+        stake: u64,
+        activation_epoch: Epoch,
+    ) -> Self {
+        Self::new(staker, withdrawer).delegate(voter_pubkey, stake, activation_epoch)
+    }
+
+    /// Delegate `stake` lamports to a vote account, activating at `activation_epoch`.
+    pub fn delegate(self, voter_pubkey: Pubkey, stake: u64, activation_epoch: Epoch) -> Self {
+        let meta = self.meta().unwrap_or_default();
+        Self(StakeStateV2::Stake(
+            meta,
+            Stake {
+                delegation: Delegation::new(&voter_pubkey, stake, activation_epoch),
+                credits_observed: 0,
+            },
+            StakeFlags::empty(),
+        ))
+    }
+
+    /// Deactivate the delegated stake at `deactivation_epoch`.
+    /// Has no effect if the stake is not delegated.
+    pub fn deactivation_epoch(mut self, deactivation_epoch: Epoch) -> Self {
+        if let StakeStateV2::Stake(_, stake, _) = &mut self.0 {
+            stake.delegation.deactivation_epoch = deactivation_epoch;
+        }
+        self
+    }
+
+    /// Has no effect if the stake is not delegated.
+    pub fn credits_observed(mut self, credits_observed: u64) -> Self {
+        if let StakeStateV2::Stake(_, stake, _) = &mut self.0 {
+            stake.credits_observed = credits_observed;
+        }
+        self
+    }
+
+    pub fn staker(mut self, staker: Pubkey) -> Self {
+        if let Some(meta) = self.meta_mut() {
+            meta.authorized.staker = staker;
+        }
+        self
+    }
+
+    pub fn withdrawer(mut self, withdrawer: Pubkey) -> Self {
+        if let Some(meta) = self.meta_mut() {
+            meta.authorized.withdrawer = withdrawer;
+        }
+        self
+    }
+
+    pub fn lockup(mut self, lockup: Lockup) -> Self {
+        if let Some(meta) = self.meta_mut() {
+            meta.lockup = lockup;
+        }
+        self
+    }
+
+    /// The rent exempt reserve plus any delegated stake,
+    /// for use with [crate::account_data::ToAnchorAccount::to_account_with_lamports].
+    pub fn lamports(&self) -> u64 {
+        let reserve = self.meta().map_or(0, |meta| meta.rent_exempt_reserve);
+        let stake = self.0.stake().map_or(0, |stake| stake.delegation.stake);
+        reserve + stake
+    }
+
+    fn meta_mut(&mut self) -> Option<&mut Meta> {
+        match &mut self.0 {
+            StakeStateV2::Initialized(meta) | StakeStateV2::Stake(meta, _, _) => Some(meta),
+            _ => None,
+        }
+    }
+}
+
+impl anchor_lang::AccountDeserialize for StakeAccount {
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        bincode1::deserialize(buf)
+            .map(StakeAccount)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
+
+impl anchor_lang::AccountSerialize for StakeAccount {
+    fn try_serialize<W: Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
+        let mut data = vec![0; Self::LEN];
+        bincode1::serialize_into(&mut data[..], &self.0)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotSerialize)?;
+        writer
+            .write(&data)
+            .map_err(Into::<anchor_lang::error::Error>::into)?;
+        Ok(())
+    }
+}
+
+impl anchor_lang::Owner for StakeAccount {
+    fn owner() -> Pubkey {
+        solana_program::stake::program::ID
+    }
+}
+
+impl Deref for StakeAccount {
+    type Target = StakeStateV2;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for StakeAccount {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<StakeStateV2> for StakeAccount {
+    fn from(value: StakeStateV2) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account_data::{FromAnchorAccount, ToAnchorAccount};
+
+    #[test]
+    fn delegated_stake_round_trips() {
+        let authority = Pubkey::new_unique();
+        let voter = Pubkey::new_unique();
+        let stake = StakeAccount::new_delegated(authority, authority, voter, 1_000_000, 5)
+            .deactivation_epoch(10)
+            .credits_observed(42);
+        let account = stake.to_account_with_lamports(stake.lamports()).unwrap();
+        assert_eq!(account.data.len(), StakeAccount::LEN);
+        assert_eq!(account.owner, solana_program::stake::program::ID);
+
+        let deserialized = StakeAccount::from_account(&account).unwrap();
+        assert_eq!(deserialized, stake);
+        let delegation = deserialized.delegation().unwrap();
+        assert_eq!(delegation.voter_pubkey, voter);
+        assert_eq!(delegation.activation_epoch, 5);
+        assert_eq!(delegation.deactivation_epoch, 10);
+        assert_eq!(
+            account.lamports,
+            Rent::default().minimum_balance(StakeAccount::LEN) + 1_000_000
+        );
+    }
+}
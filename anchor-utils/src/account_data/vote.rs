@@ -0,0 +1,114 @@
+use solana_program::clock::{Clock, Epoch};
+use solana_program::vote::state::{VoteInit, VoteState, VoteStateVersions};
+use solana_sdk::pubkey::Pubkey;
+use std::io::Write;
+use std::ops::{Deref, DerefMut};
+
+/// A vote account, with builder methods for its authorities, commission and credits.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VoteAccount(VoteState);
+
+impl VoteAccount {
+    pub const LEN: usize = VoteState::size_of();
+
+    pub fn new(
+        node_pubkey: Pubkey,
+        authorized_voter: Pubkey,
+        authorized_withdrawer: Pubkey,
+        commission: u8,
+    ) -> Self {
+        Self(VoteState::new(
+            &VoteInit {
+                node_pubkey,
+                authorized_voter,
+                authorized_withdrawer,
+                commission,
+            },
+            &Clock::default(),
+        ))
+    }
+
+    pub fn commission(mut self, commission: u8) -> Self {
+        self.0.commission = commission;
+        self
+    }
+
+    pub fn authorized_withdrawer(mut self, authorized_withdrawer: Pubkey) -> Self {
+        self.0.authorized_withdrawer = authorized_withdrawer;
+        self
+    }
+
+    /// Earn `credits` in `epoch`, on top of the credits of earlier epochs.
+    /// Epochs must be added in increasing order.
+    pub fn epoch_credits(mut self, epoch: Epoch, credits: u64) -> Self {
+        self.0.increment_credits(epoch, credits);
+        self
+    }
+}
+
+impl anchor_lang::AccountDeserialize for VoteAccount {
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        VoteState::deserialize(buf)
+            .map(VoteAccount)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
+
+impl anchor_lang::AccountSerialize for VoteAccount {
+    fn try_serialize<W: Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
+        let mut data = vec![0; Self::LEN];
+        VoteState::serialize(&VoteStateVersions::new_current(self.0.clone()), &mut data)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotSerialize)?;
+        writer
+            .write(&data)
+            .map_err(Into::<anchor_lang::error::Error>::into)?;
+        Ok(())
+    }
+}
+
+impl anchor_lang::Owner for VoteAccount {
+    fn owner() -> Pubkey {
+        solana_program::vote::program::ID
+    }
+}
+
+impl Deref for VoteAccount {
+    type Target = VoteState;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for VoteAccount {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<VoteState> for VoteAccount {
+    fn from(value: VoteState) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account_data::{FromAnchorAccount, ToAnchorAccount};
+
+    #[test]
+    fn vote_credits_round_trip() {
+        let node = Pubkey::new_unique();
+        let vote = VoteAccount::new(node, node, node, 10)
+            .epoch_credits(1, 100)
+            .epoch_credits(2, 50);
+        let account = vote.to_account().unwrap();
+        assert_eq!(account.data.len(), VoteAccount::LEN);
+
+        let deserialized = VoteAccount::from_account(&account).unwrap();
+        assert_eq!(deserialized, vote);
+        assert_eq!(deserialized.credits(), 150);
+        assert_eq!(deserialized.epoch_credits, vec![(1, 100, 0), (2, 150, 100)]);
+    }
+}
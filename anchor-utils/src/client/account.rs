@@ -1,8 +1,13 @@
 use anchor_lang::prelude::AccountDeserialize;
+use anchor_lang::Discriminator;
+use solana_account_decoder::UiAccountEncoding;
 use solana_client::client_error::{ClientError, ClientErrorKind};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_client::rpc_client;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
 use solana_program::pubkey::Pubkey;
+use solana_sdk::account::Account;
 use std::thread::sleep;
 use std::time::Duration;
 
@@ -70,3 +75,66 @@ pub async fn get_anchor_accounts<T: AccountDeserialize>(
     }
     Ok(accounts)
 }
+
+/// Matches accounts whose data begins with an account discriminator.
+pub fn discriminator_filter(discriminator: &[u8; 8]) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, discriminator))
+}
+
+/// Base64 encoded accounts matching all of the `filters`, if any.
+pub fn program_accounts_config(filters: Vec<RpcFilterType>) -> RpcProgramAccountsConfig {
+    RpcProgramAccountsConfig {
+        filters: (!filters.is_empty()).then_some(filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// `RpcClient::get_program_accounts_with_config`, restricted to accounts with the
+/// given discriminator, and any other `filters`.
+pub async fn get_program_accounts_with_discriminator(
+    program_id: &Pubkey,
+    discriminator: &[u8; 8],
+    filters: Vec<RpcFilterType>,
+    client: &RpcClient,
+) -> Result<Vec<(Pubkey, Account)>, ClientError> {
+    let mut all_filters = vec![discriminator_filter(discriminator)];
+    all_filters.extend(filters);
+    client
+        .get_program_accounts_with_config(program_id, program_accounts_config(all_filters))
+        .await
+}
+
+/// Every account owned by the program that matches the `filters`.
+pub fn get_program_accounts_blocking(
+    program_id: &Pubkey,
+    filters: Vec<RpcFilterType>,
+    client: &rpc_client::RpcClient,
+) -> Result<Vec<(Pubkey, Account)>, ClientError> {
+    client.get_program_accounts_with_config(program_id, program_accounts_config(filters))
+}
+
+/// Fetch and deserialize every account of type `T` owned by the program,
+/// filtered by `T`'s discriminator and any other `filters`.
+pub async fn get_program_anchor_accounts<T: AccountDeserialize + Discriminator>(
+    program_id: &Pubkey,
+    filters: Vec<RpcFilterType>,
+    client: &RpcClient,
+) -> Result<Vec<(Pubkey, T)>, ClientError> {
+    get_program_accounts_with_discriminator(program_id, &T::DISCRIMINATOR, filters, client)
+        .await?
+        .into_iter()
+        .map(|(pubkey, account)| {
+            let act = T::try_deserialize(&mut &account.data[..]).map_err(|_| {
+                ClientError::from(ClientErrorKind::Custom(format!(
+                    "failed to deserialize account {}",
+                    pubkey
+                )))
+            })?;
+            Ok((pubkey, act))
+        })
+        .collect()
+}
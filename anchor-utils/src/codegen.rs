@@ -0,0 +1,609 @@
+//! Generate a Rust client module from an IDL, in the layout of the modules Anchor generates
+//! for a program crate: account and type definitions, `instruction` data structs and `accounts`
+//! structs, plus a `builder` struct per instruction that combines the two into an
+//! [Instruction](solana_program::instruction::Instruction), and a `pda` module with
+//! a function for each program derived address whose seeds are in the IDL.
+//!
+//! The generated code depends on `anchor-lang` only. Each `builder` struct converts into
+//! `Vec<Instruction>`, which makes it a `solana_devtools_tx::TransactionSchema`.
+//! Accounts are assumed to be borsh-serialized, as zero-copy accounts are not marked in the IDL.
+use crate::deserialize::discriminator::{account_discriminator, ix_discriminator, Discriminator};
+use anchor_syn::idl::types::{
+    EnumFields, Idl, IdlAccountItem, IdlField, IdlPda, IdlSeed, IdlType, IdlTypeDefinition,
+    IdlTypeDefinitionTy,
+};
+use anyhow::{anyhow, Result};
+use heck::{CamelCase, SnakeCase};
+use serde_json::Value;
+use solana_program::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::str::FromStr;
+
+const DERIVE_DATA: &str = "#[derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize, Clone, Debug, PartialEq)]";
+const PUBKEY: &str = "anchor_lang::prelude::Pubkey";
+
+/// Generate the source of a client module for the program described by `idl`. The program ID
+/// is taken from `program_id`, or else from the `address` in the IDL's metadata.
+pub fn generate_client(idl: &Idl, program_id: Option<Pubkey>) -> Result<String> {
+    let program_id = match program_id {
+        Some(program_id) => program_id,
+        None => idl
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("address"))
+            .and_then(Value::as_str)
+            .map(Pubkey::from_str)
+            .ok_or(anyhow!(
+                "IDL has no address in its metadata, pass a program ID"
+            ))??,
+    };
+    let mut out = String::new();
+    writeln!(
+        out,
+        "//! A client for the `{}` program, generated from its IDL.",
+        idl.name
+    )?;
+    writeln!(out, "#![allow(dead_code)]")?;
+    writeln!(
+        out,
+        "// The borsh derives refer to the `borsh` version of `anchor-lang`."
+    )?;
+    writeln!(out, "use anchor_lang::prelude::borsh;")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "pub const ID: {} = anchor_lang::solana_program::pubkey!(\"{}\");",
+        PUBKEY, program_id
+    )?;
+
+    for type_definition in &idl.types {
+        writeln!(out)?;
+        write_type_definition(&mut out, type_definition, "")?;
+    }
+    for account in &idl.accounts {
+        writeln!(out)?;
+        write_type_definition(&mut out, account, "")?;
+        write_account_impls(
+            &mut out,
+            &account.name,
+            &account_discriminator(&account.name),
+        )?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "/// Instruction data.")?;
+    writeln!(out, "pub mod instruction {{")?;
+    writeln!(out, "    use super::*;")?;
+    for ix in &idl.instructions {
+        let name = ix.name.to_camel_case();
+        writeln!(out)?;
+        write_docs(&mut out, &ix.docs, "    ")?;
+        writeln!(out, "    {}", DERIVE_DATA)?;
+        writeln!(out, "    pub struct {} {{", name)?;
+        write_fields(&mut out, &ix.args, "        ")?;
+        writeln!(out, "    }}")?;
+        writeln!(out)?;
+        write_discriminator(&mut out, &name, &ix_discriminator(&ix.name), "    ")?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "    impl anchor_lang::InstructionData for {} {{}}",
+            name
+        )?;
+    }
+    writeln!(out, "}}")?;
+
+    writeln!(out)?;
+    writeln!(out, "/// The accounts of each instruction, in order.")?;
+    writeln!(out, "pub mod accounts {{")?;
+    writeln!(out, "    use super::*;")?;
+    for ix in &idl.instructions {
+        let name = ix.name.to_camel_case();
+        let accounts = flatten_accounts(&ix.accounts, "");
+        writeln!(out)?;
+        writeln!(out, "    #[derive(Clone, Debug, PartialEq)]")?;
+        writeln!(out, "    pub struct {} {{", name)?;
+        for account in &accounts {
+            write_docs(&mut out, &account.docs, "        ")?;
+            match account.is_optional {
+                true => writeln!(out, "        pub {}: Option<{}>,", account.field, PUBKEY)?,
+                false => writeln!(out, "        pub {}: {},", account.field, PUBKEY)?,
+            }
+        }
+        writeln!(out, "    }}")?;
+        writeln!(out)?;
+        writeln!(out, "    impl anchor_lang::ToAccountMetas for {} {{", name)?;
+        writeln!(
+            out,
+            "        fn to_account_metas(\n            &self,\n            is_signer: Option<bool>,\n        \
+            ) -> Vec<anchor_lang::prelude::AccountMeta> {{"
+        )?;
+        writeln!(out, "            vec![")?;
+        for account in &accounts {
+            let constructor = match account.is_mut {
+                true => "new",
+                false => "new_readonly",
+            };
+            let signer = match account.is_signer {
+                true => "is_signer.unwrap_or(true)",
+                false => "false",
+            };
+            let meta = |address: &str| {
+                format!(
+                    "anchor_lang::prelude::AccountMeta::{}({}, {})",
+                    constructor, address, signer
+                )
+            };
+            match account.is_optional {
+                // Anchor passes the program ID in place of a missing optional account.
+                true => {
+                    writeln!(out, "                match self.{} {{", account.field)?;
+                    writeln!(
+                        out,
+                        "                    Some(pubkey) => {},",
+                        meta("pubkey")
+                    )?;
+                    writeln!(
+                        out,
+                        "                    None => anchor_lang::prelude::AccountMeta::new_readonly(ID, false),"
+                    )?;
+                    writeln!(out, "                }},")?;
+                }
+                false => writeln!(
+                    out,
+                    "                {},",
+                    meta(&format!("self.{}", account.field))
+                )?,
+            }
+        }
+        writeln!(out, "            ]")?;
+        writeln!(out, "        }}")?;
+        writeln!(out, "    }}")?;
+    }
+    writeln!(out, "}}")?;
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "/// Instruction builders, which convert into `Vec<Instruction>`."
+    )?;
+    writeln!(out, "pub mod builder {{")?;
+    writeln!(out, "    use super::*;")?;
+    writeln!(
+        out,
+        "    use anchor_lang::solana_program::instruction::Instruction;"
+    )?;
+    writeln!(
+        out,
+        "    use anchor_lang::{{InstructionData, ToAccountMetas}};"
+    )?;
+    for ix in &idl.instructions {
+        let name = ix.name.to_camel_case();
+        writeln!(out)?;
+        write_docs(&mut out, &ix.docs, "    ")?;
+        writeln!(out, "    #[derive(Clone, Debug, PartialEq)]")?;
+        writeln!(out, "    pub struct {} {{", name)?;
+        writeln!(out, "        pub accounts: accounts::{},", name)?;
+        writeln!(out, "        pub args: instruction::{},", name)?;
+        writeln!(out, "    }}")?;
+        writeln!(out)?;
+        writeln!(out, "    impl {} {{", name)?;
+        writeln!(out, "        pub fn instruction(&self) -> Instruction {{")?;
+        writeln!(
+            out,
+            "            Instruction::new_with_bytes(ID, &self.args.data(), \
+            self.accounts.to_account_metas(None))"
+        )?;
+        writeln!(out, "        }}")?;
+        writeln!(out, "    }}")?;
+        writeln!(out)?;
+        writeln!(out, "    impl From<{}> for Vec<Instruction> {{", name)?;
+        writeln!(out, "        fn from(value: {}) -> Self {{", name)?;
+        writeln!(out, "            vec![value.instruction()]")?;
+        writeln!(out, "        }}")?;
+        writeln!(out, "    }}")?;
+    }
+    writeln!(out, "}}")?;
+
+    writeln!(out)?;
+    writeln!(out, "/// Program derived addresses, with their bumps.")?;
+    writeln!(out, "pub mod pda {{")?;
+    writeln!(out, "    use super::*;")?;
+    write_pdas(&mut out, idl)?;
+    writeln!(out, "}}")?;
+    Ok(out)
+}
+
+/// An account of an instruction, with the names of nested account structs joined into its name.
+struct FlatAccount {
+    field: String,
+    is_mut: bool,
+    is_signer: bool,
+    is_optional: bool,
+    docs: Option<Vec<String>>,
+    pda: Option<IdlPda>,
+}
+
+fn flatten_accounts(items: &[IdlAccountItem], prefix: &str) -> Vec<FlatAccount> {
+    items
+        .iter()
+        .flat_map(|item| match item {
+            IdlAccountItem::IdlAccount(account) => vec![FlatAccount {
+                field: field_name(&format!("{}{}", prefix, account.name.to_snake_case())),
+                is_mut: account.is_mut,
+                is_signer: account.is_signer,
+                is_optional: account.is_optional.unwrap_or_default(),
+                docs: account.docs.clone(),
+                pda: account.pda.clone(),
+            }],
+            IdlAccountItem::IdlAccounts(accounts) => flatten_accounts(
+                &accounts.accounts,
+                &format!("{}{}_", prefix, accounts.name.to_snake_case()),
+            ),
+        })
+        .collect()
+}
+
+fn write_type_definition(
+    out: &mut String,
+    type_definition: &IdlTypeDefinition,
+    indent: &str,
+) -> Result<()> {
+    let name = type_definition.name.to_camel_case();
+    if type_definition.generics.is_some() {
+        return Err(anyhow!("Generic types are not supported: {}", name));
+    }
+    write_docs(out, &type_definition.docs, indent)?;
+    match &type_definition.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            writeln!(out, "{}{}", indent, DERIVE_DATA)?;
+            writeln!(out, "{}pub struct {} {{", indent, name)?;
+            write_fields(out, fields, &format!("{}    ", indent))?;
+            writeln!(out, "{}}}", indent)?;
+        }
+        IdlTypeDefinitionTy::Enum { variants } => {
+            writeln!(out, "{}{}", indent, DERIVE_DATA)?;
+            writeln!(out, "{}pub enum {} {{", indent, name)?;
+            for variant in variants {
+                let variant_name = variant.name.to_camel_case();
+                match &variant.fields {
+                    None => writeln!(out, "{}    {},", indent, variant_name)?,
+                    Some(EnumFields::Named(fields)) => {
+                        writeln!(out, "{}    {} {{", indent, variant_name)?;
+                        write_enum_fields(out, fields, &format!("{}        ", indent))?;
+                        writeln!(out, "{}    }},", indent)?;
+                    }
+                    Some(EnumFields::Tuple(types)) => {
+                        let types = types.iter().map(rust_type).collect::<Result<Vec<_>>>()?;
+                        writeln!(out, "{}    {}({}),", indent, variant_name, types.join(", "))?;
+                    }
+                }
+            }
+            writeln!(out, "{}}}", indent)?;
+        }
+        IdlTypeDefinitionTy::Alias { value } => {
+            writeln!(out, "{}pub type {} = {};", indent, name, rust_type(value)?)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_fields(out: &mut String, fields: &[IdlField], indent: &str) -> Result<()> {
+    for field in fields {
+        write_docs(out, &field.docs, indent)?;
+        writeln!(
+            out,
+            "{}pub {}: {},",
+            indent,
+            field_name(&field.name),
+            rust_type(&field.ty)?
+        )?;
+    }
+    Ok(())
+}
+
+/// Enum variant fields take no visibility.
+fn write_enum_fields(out: &mut String, fields: &[IdlField], indent: &str) -> Result<()> {
+    for field in fields {
+        writeln!(
+            out,
+            "{}{}: {},",
+            indent,
+            field_name(&field.name),
+            rust_type(&field.ty)?
+        )?;
+    }
+    Ok(())
+}
+
+fn write_docs(out: &mut String, docs: &Option<Vec<String>>, indent: &str) -> Result<()> {
+    for line in docs.iter().flatten() {
+        writeln!(out, "{}/// {}", indent, line)?;
+    }
+    Ok(())
+}
+
+fn write_discriminator(
+    out: &mut String,
+    name: &str,
+    discriminator: &Discriminator,
+    indent: &str,
+) -> Result<()> {
+    writeln!(
+        out,
+        "{}impl anchor_lang::Discriminator for {} {{",
+        indent, name
+    )?;
+    writeln!(
+        out,
+        "{}    const DISCRIMINATOR: [u8; 8] = {:?};",
+        indent, discriminator
+    )?;
+    writeln!(out, "{}}}", indent)?;
+    Ok(())
+}
+
+/// The traits Anchor's `#[account]` implements, so the account can be read and written
+/// the same way, e.g. with `AccountDeserialize::try_deserialize`.
+fn write_account_impls(out: &mut String, name: &str, discriminator: &Discriminator) -> Result<()> {
+    let name = name.to_camel_case();
+    writeln!(out)?;
+    write_discriminator(out, &name, discriminator, "")?;
+    writeln!(
+        out,
+        "
+impl anchor_lang::Owner for {name} {{
+    fn owner() -> {PUBKEY} {{
+        ID
+    }}
+}}
+
+impl anchor_lang::AccountSerialize for {name} {{
+    fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {{
+        if writer.write_all(&<Self as anchor_lang::Discriminator>::DISCRIMINATOR).is_err() {{
+            return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+        }}
+        if anchor_lang::AnchorSerialize::serialize(self, writer).is_err() {{
+            return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+        }}
+        Ok(())
+    }}
+}}
+
+impl anchor_lang::AccountDeserialize for {name} {{
+    fn try_deserialize(buf: &mut &[u8]) -> anchor_lang::Result<Self> {{
+        if buf.len() < 8 {{
+            return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound.into());
+        }}
+        if buf[..8] != <Self as anchor_lang::Discriminator>::DISCRIMINATOR {{
+            return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into());
+        }}
+        Self::try_deserialize_unchecked(buf)
+    }}
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {{
+        let mut data: &[u8] = &buf[8..];
+        anchor_lang::AnchorDeserialize::deserialize(&mut data)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+    }}
+}}"
+    )?;
+    Ok(())
+}
+
+/// A `find_<account>_address` function for each PDA account of each instruction. Accounts of
+/// the same name with different seeds in different instructions are suffixed with the
+/// instruction name.
+fn write_pdas(out: &mut String, idl: &Idl) -> Result<()> {
+    let mut pdas: Vec<(String, String, IdlPda)> = vec![];
+    for ix in &idl.instructions {
+        for account in flatten_accounts(&ix.accounts, "") {
+            let Some(pda) = account.pda else { continue };
+            if !pdas
+                .iter()
+                .any(|(field, _, seeds)| *field == account.field && *seeds == pda)
+            {
+                pdas.push((account.field, ix.name.to_snake_case(), pda));
+            }
+        }
+    }
+    let mut names = HashSet::new();
+    for (field, ix_name, pda) in &pdas {
+        let ambiguous = pdas.iter().filter(|(other, ..)| other == field).count() > 1;
+        let name = match ambiguous {
+            true => format!(
+                "find_{}_{}_address",
+                ix_name,
+                field.trim_start_matches("r#")
+            ),
+            false => format!("find_{}_address", field.trim_start_matches("r#")),
+        };
+        if !names.insert(name.clone()) {
+            continue;
+        }
+        let mut params: Vec<String> = vec![];
+        let mut seeds: Vec<String> = vec![];
+        for seed in &pda.seeds {
+            let (param, seed) = seed_expression(seed)?;
+            if let Some(param) = param {
+                if !params.contains(&param) {
+                    params.push(param);
+                }
+            }
+            seeds.push(seed);
+        }
+        let program_id = match &pda.program_id {
+            None => "ID".to_string(),
+            Some(IdlSeed::Const(program_id)) => {
+                let bytes: Vec<u8> = serde_json::from_value(program_id.value.clone())?;
+                let program_id = Pubkey::try_from(bytes.as_slice())
+                    .map_err(|_| anyhow!("Invalid PDA program ID: {}", name))?;
+                format!("anchor_lang::solana_program::pubkey!(\"{}\")", program_id)
+            }
+            Some(seed) => {
+                let (param, _) = seed_expression(seed)?;
+                let param = param.ok_or(anyhow!("Invalid PDA program ID: {}", name))?;
+                let program_id = param.split(':').next().unwrap().to_string();
+                params.push(param);
+                format!("*{}", program_id)
+            }
+        };
+        writeln!(out)?;
+        writeln!(
+            out,
+            "    pub fn {}({}) -> ({}, u8) {{",
+            name,
+            params.join(", "),
+            PUBKEY
+        )?;
+        writeln!(
+            out,
+            "        {}::find_program_address(&[{}], &{})",
+            PUBKEY,
+            seeds.join(", "),
+            program_id
+        )?;
+        writeln!(out, "    }}")?;
+    }
+    Ok(())
+}
+
+/// The function parameter a seed needs, if any, and the expression of its bytes.
+fn seed_expression(seed: &IdlSeed) -> Result<(Option<String>, String)> {
+    let (ty, path) = match seed {
+        IdlSeed::Const(seed) => {
+            let bytes: Vec<u8> = match &seed.value {
+                Value::String(value) => value.as_bytes().to_vec(),
+                value => serde_json::from_value(value.clone())
+                    .map_err(|_| anyhow!("Unsupported constant seed: {}", value))?,
+            };
+            return Ok((None, format!("&{:?}", bytes)));
+        }
+        IdlSeed::Arg(seed) => (&seed.ty, &seed.path),
+        IdlSeed::Account(seed) => (&seed.ty, &seed.path),
+    };
+    let name = field_name(&path.replace('.', "_").to_snake_case());
+    let (param_type, expression) = match ty {
+        IdlType::PublicKey => (format!("&{}", PUBKEY), format!("{}.as_ref()", name)),
+        IdlType::String => ("&str".to_string(), format!("{}.as_bytes()", name)),
+        IdlType::Bytes => ("&[u8]".to_string(), name.clone()),
+        IdlType::Array(inner, len) if **inner == IdlType::U8 => {
+            (format!("&[u8; {}]", len), format!("{}.as_ref()", name))
+        }
+        IdlType::U8
+        | IdlType::I8
+        | IdlType::U16
+        | IdlType::I16
+        | IdlType::U32
+        | IdlType::I32
+        | IdlType::U64
+        | IdlType::I64
+        | IdlType::U128
+        | IdlType::I128 => (rust_type(ty)?, format!("&{}.to_le_bytes()", name)),
+        ty => return Err(anyhow!("Unsupported seed type for {}: {:?}", path, ty)),
+    };
+    Ok((Some(format!("{}: {}", name, param_type)), expression))
+}
+
+fn rust_type(ty: &IdlType) -> Result<String> {
+    Ok(match ty {
+        IdlType::Bool => "bool".to_string(),
+        IdlType::U8 => "u8".to_string(),
+        IdlType::I8 => "i8".to_string(),
+        IdlType::U16 => "u16".to_string(),
+        IdlType::I16 => "i16".to_string(),
+        IdlType::U32 => "u32".to_string(),
+        IdlType::I32 => "i32".to_string(),
+        IdlType::F32 => "f32".to_string(),
+        IdlType::U64 => "u64".to_string(),
+        IdlType::I64 => "i64".to_string(),
+        IdlType::F64 => "f64".to_string(),
+        IdlType::U128 => "u128".to_string(),
+        IdlType::I128 => "i128".to_string(),
+        IdlType::Bytes => "Vec<u8>".to_string(),
+        IdlType::String => "String".to_string(),
+        IdlType::PublicKey => PUBKEY.to_string(),
+        IdlType::Defined(name) => name.rsplit("::").next().unwrap().to_camel_case(),
+        IdlType::Option(inner) => format!("Option<{}>", rust_type(inner)?),
+        IdlType::Vec(inner) => format!("Vec<{}>", rust_type(inner)?),
+        IdlType::Array(inner, len) => format!("[{}; {}]", rust_type(inner)?, len),
+        ty => return Err(anyhow!("Unsupported IDL type: {:?}", ty)),
+    })
+}
+
+/// A snake case field name, escaped if it is a keyword.
+fn field_name(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else",
+        "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+        "move", "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type",
+        "unsafe", "use", "where", "while", "yield",
+    ];
+    let name = name.to_snake_case();
+    match KEYWORDS.contains(&name.as_str()) {
+        true => format!("r#{}", name),
+        false => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_is_generated() {
+        let idl: Idl = serde_json::from_str(include_str!("../tests/codegen/auction.json")).unwrap();
+
+        let client = generate_client(&idl, None).unwrap();
+        // The integration tests compile this copy of the client.
+        assert_eq!(
+            client,
+            include_str!("../tests/codegen/auction.rs"),
+            "tests/codegen/auction.rs is out of date, regenerate it from tests/codegen/auction.json"
+        );
+        let expected = [
+            "pub const ID: anchor_lang::prelude::Pubkey = \
+            anchor_lang::solana_program::pubkey!(\"Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS\");",
+            "pub enum BidType {\n    Open,\n    Sealed {\n        hash: [u8; 32],\n    },\n    Limit(u64),\n}",
+            "pub struct Auction {\n    pub seller: anchor_lang::prelude::Pubkey,\n    \
+            pub bids: Vec<[u8; 4]>,\n    pub memo: Option<String>,\n}",
+            &format!(
+                "impl anchor_lang::Discriminator for Auction {{\n    const DISCRIMINATOR: [u8; 8] = {:?};",
+                account_discriminator("Auction")
+            ),
+            &format!(
+                "    /// Bid on an open auction.\n    {}\n    pub struct PlaceBid {{\n        \
+                pub auction_id: u64,\n        pub r#type: BidType,\n    }}",
+                DERIVE_DATA
+            ),
+            &format!(
+                "const DISCRIMINATOR: [u8; 8] = {:?};",
+                ix_discriminator("placeBid")
+            ),
+            "        pub bidder_referrer: Option<anchor_lang::prelude::Pubkey>,",
+            "                anchor_lang::prelude::AccountMeta::new(self.bidder_authority, is_signer.unwrap_or(true)),",
+            "        pub accounts: accounts::PlaceBid,\n        pub args: instruction::PlaceBid,",
+            "    pub fn find_auction_address(seller: &anchor_lang::prelude::Pubkey, auction_id: u64) \
+            -> (anchor_lang::prelude::Pubkey, u8) {\n        \
+            anchor_lang::prelude::Pubkey::find_program_address(\
+            &[&[97, 117, 99, 116, 105, 111, 110], seller.as_ref(), &auction_id.to_le_bytes()], &ID)",
+        ];
+        for snippet in expected {
+            assert!(
+                client.contains(snippet),
+                "{}\n\nnot found in:\n\n{}",
+                snippet,
+                client
+            );
+        }
+
+        let mut idl = idl;
+        idl.metadata = None;
+        assert!(generate_client(&idl, None).is_err());
+        let program_id = Pubkey::new_unique();
+        assert!(generate_client(&idl, Some(program_id))
+            .unwrap()
+            .contains(&program_id.to_string()));
+    }
+}
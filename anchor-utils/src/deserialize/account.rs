@@ -1,4 +1,5 @@
 use crate::deserialize::discriminator::partition_discriminator_from_data;
+use crate::deserialize::token_account::{token_program_name, try_deserialize_token_account};
 use crate::deserialize::{AnchorDeserializer, IdlWithDiscriminators};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
@@ -51,7 +52,10 @@ impl IdlWithDiscriminators {
 
 impl AnchorDeserializer {
     /// Tries to deserialize an account, first trying with any IDL cached from the account's owner,
-    /// and failing that, tries to deserialize using all other caches IDLs (order is indeterminate).
+    /// and failing that, tries to deserialize using all other caches IDLs (order is indeterminate,
+    /// unless output normalization is enabled). A custom decoder registered for the owner is
+    /// tried before the other IDLs. Token program accounts without a matching IDL
+    /// are decoded as SPL Token or Token-2022 accounts.
     pub fn try_deserialize_account(
         &self,
         pubkey: Pubkey,
@@ -62,12 +66,48 @@ impl AnchorDeserializer {
                 return Ok(json);
             }
         }
+        if let Some((program_name, Ok((account_type, deserialized)))) =
+            self.decoders.decode_account(account)
+        {
+            return Ok(DeserializedAccount {
+                ui_account: UiAccount::encode(
+                    &pubkey,
+                    account,
+                    UiAccountEncoding::Base64,
+                    None,
+                    None,
+                ),
+                program_name: program_name.to_string(),
+                account_type,
+                deserialized,
+            });
+        }
         // Brute force search all cached IDLs, trying to deserialize
-        for (_, idl) in &self.idl_cache {
+        let mut idls: Vec<_> = self.idl_cache.iter().collect();
+        if self.normalization.is_enabled() {
+            idls.sort_by_key(|(program_id, _)| **program_id);
+        }
+        for (_, idl) in idls {
             if let Ok(json) = idl.try_deserialize_account_to_json(&pubkey, account) {
                 return Ok(json);
             }
         }
+        if let Some(program_name) = token_program_name(&account.owner) {
+            if let Ok((account_type, deserialized)) = try_deserialize_token_account(account) {
+                return Ok(DeserializedAccount {
+                    ui_account: UiAccount::encode(
+                        &pubkey,
+                        account,
+                        UiAccountEncoding::Base64,
+                        None,
+                        None,
+                    ),
+                    program_name: program_name.to_string(),
+                    account_type,
+                    deserialized,
+                });
+            }
+        }
         return Err(anyhow!(
             "could not deserialize account from any cached IDLs"
         ));
@@ -1,12 +1,18 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::Path;
 
+use crate::client::account::get_program_accounts_with_discriminator;
 use crate::deserialize::account::DeserializedAccount;
+use crate::deserialize::idl_dir::IdlManifest;
 use crate::deserialize::AnchorDeserializer;
 use crate::deserialize::IdlWithDiscriminators;
 use anchor_lang::idl::IdlAccount;
 use anyhow::{anyhow, Result};
 use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_filter::RpcFilterType;
 use solana_devtools_tx::inner_instructions::{DecompiledMessageAndInnerIx, HistoricalTransaction};
+use solana_program::clock::Slot;
 use solana_program::pubkey::Pubkey;
 
 impl AnchorDeserializer {
@@ -31,30 +37,82 @@ impl AnchorDeserializer {
         Ok(())
     }
 
-    /// Fails quietly for any programs it doesn't find.
+    /// Fetch and deserialize every account of the given type owned by the program,
+    /// see [IdlWithDiscriminators::get_program_accounts].
+    /// The program's IDL is fetched and cached if it isn't already.
+    pub async fn get_program_accounts(
+        &mut self,
+        client: &RpcClient,
+        program_id: &Pubkey,
+        account_type: &str,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, DeserializedAccount)>> {
+        if !self.idl_cache.contains_key(program_id) {
+            self.fetch_and_cache_idl_for_program(client, program_id)
+                .await?;
+        }
+        self.idl_cache[program_id]
+            .get_program_accounts(client, program_id, account_type, filters)
+            .await
+    }
+
+    /// Skips any programs without an IDL account, but fails on RPC errors
+    /// and IDL accounts that can't be decoded.
     pub async fn fetch_and_cache_any_idls(
         &mut self,
         client: &RpcClient,
         message_and_inner_ix: HistoricalTransaction,
     ) -> Result<()> {
-        let decompiled = DecompiledMessageAndInnerIx::from(message_and_inner_ix);
-        let mut programs = HashSet::new();
-        for program in decompiled.programs() {
-            programs.insert(program);
-        }
-        for inner_ix in decompiled.inner_instructions.values().flatten() {
-            programs.insert(inner_ix.program_id);
-        }
-        for program in programs {
-            if let Err(_) = self.fetch_and_cache_idl_for_program(client, &program).await {
-                // TODO think how you want to handle debug printing?
-                //eprintln!("Failed to fetch an IDL for program: {program}: {e}");
+        for program in invoked_programs(message_and_inner_ix) {
+            if let Some((_, idl)) =
+                IdlWithDiscriminators::try_fetch_for_program_with_slot(client, &program).await?
+            {
+                self.cache_idl(program, idl);
             }
         }
         Ok(())
     }
 }
 
+/// Every program invoked by a transaction's instructions and inner instructions.
+pub fn invoked_programs(message_and_inner_ix: HistoricalTransaction) -> HashSet<Pubkey> {
+    let decompiled = DecompiledMessageAndInnerIx::from(message_and_inner_ix);
+    let mut programs = HashSet::new();
+    for program in decompiled.programs() {
+        programs.insert(program);
+    }
+    for inner_ix in decompiled.inner_instructions.values().flatten() {
+        programs.insert(inner_ix.program_id);
+    }
+    programs
+}
+
+/// Fetch the on-chain IDLs of the programs, and write them to a directory with a manifest,
+/// for offline decoding with [AnchorDeserializer::cache_idls_from_dir].
+/// Programs without an IDL account are listed as missing in the manifest,
+/// while RPC errors and IDL accounts that can't be decoded fail the export.
+pub async fn export_idls(
+    client: &RpcClient,
+    program_ids: impl IntoIterator<Item = Pubkey>,
+    dir: impl AsRef<Path>,
+) -> Result<IdlManifest> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    let mut manifest = IdlManifest::default();
+    let program_ids: BTreeSet<Pubkey> = program_ids.into_iter().collect();
+    for program_id in program_ids {
+        match IdlWithDiscriminators::try_fetch_for_program_with_slot(client, &program_id)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch the IDL of {}: {}", program_id, e))?
+        {
+            Some((slot, idl)) => manifest.add_idl(dir, program_id, Some(slot), &idl)?,
+            None => manifest.missing.push(program_id),
+        }
+    }
+    manifest.write(dir)?;
+    Ok(manifest)
+}
+
 impl IdlWithDiscriminators {
     pub async fn fetch_from_account(
         client: &RpcClient,
@@ -79,6 +137,56 @@ impl IdlWithDiscriminators {
         Self::try_from(account)
     }
 
+    /// Also returns the slot the IDL account was fetched at.
+    pub async fn fetch_for_program_with_slot(
+        client: &RpcClient,
+        program_id: &Pubkey,
+    ) -> Result<(Slot, IdlWithDiscriminators)> {
+        Self::try_fetch_for_program_with_slot(client, program_id)
+            .await?
+            .ok_or(anyhow!("IDL account not found"))
+    }
+
+    /// Like [IdlWithDiscriminators::fetch_for_program_with_slot],
+    /// but `None` if the program has no IDL account.
+    pub async fn try_fetch_for_program_with_slot(
+        client: &RpcClient,
+        program_id: &Pubkey,
+    ) -> Result<Option<(Slot, IdlWithDiscriminators)>> {
+        let idl_addr = IdlAccount::address(program_id);
+        let response = client
+            .get_account_with_commitment(&idl_addr, client.commitment())
+            .await?;
+        response
+            .value
+            .map(|account| Ok((response.context.slot, Self::try_from(account)?)))
+            .transpose()
+    }
+
+    /// Fetch every account of the given type owned by the program, using a filter on
+    /// the type's discriminator along with any other `filters`, and deserialize them.
+    pub async fn get_program_accounts(
+        &self,
+        client: &RpcClient,
+        program_id: &Pubkey,
+        account_type: &str,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, DeserializedAccount)>> {
+        let discriminator = self
+            .account_discriminator_by_name(account_type)
+            .ok_or(anyhow!("IDL has no account type named {}", account_type))?;
+        get_program_accounts_with_discriminator(program_id, &discriminator, filters, client)
+            .await?
+            .into_iter()
+            .map(|(pubkey, account)| {
+                Ok((
+                    pubkey,
+                    self.try_deserialize_account_to_json(&pubkey, &account)?,
+                ))
+            })
+            .collect()
+    }
+
     pub async fn get_deserialized_account(
         &self,
         client: &RpcClient,
@@ -88,3 +196,131 @@ impl IdlWithDiscriminators {
         self.try_deserialize_account_to_json(pubkey, &account)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::discriminator::account_discriminator;
+    use crate::idl_sdk::serialize_idl_json_account;
+    use anchor_syn::idl::types::Idl;
+    use serde_json::json;
+    use solana_account_decoder::{UiAccount, UiAccountEncoding};
+    use solana_client::rpc_request::RpcRequest;
+    use solana_client::rpc_response::{Response, RpcKeyedAccount, RpcResponseContext};
+    use solana_sdk::account::Account;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn program_accounts_are_fetched_by_account_type() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [],
+            "accounts": [{
+                "name": "Vault",
+                "type": { "kind": "struct", "fields": [
+                    { "name": "owner", "type": "publicKey" },
+                    { "name": "amount", "type": "u64" },
+                ]},
+            }],
+        }))
+        .unwrap();
+        let idl = IdlWithDiscriminators::new(idl);
+        let (program_id, address, owner) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut data = account_discriminator("Vault").to_vec();
+        data.extend(owner.to_bytes());
+        data.extend(9u64.to_le_bytes());
+        let account = Account {
+            lamports: 1,
+            data,
+            owner: program_id,
+            ..Default::default()
+        };
+        let keyed = RpcKeyedAccount {
+            pubkey: address.to_string(),
+            account: UiAccount::encode(&address, &account, UiAccountEncoding::Base64, None, None),
+        };
+        let client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            HashMap::from([(
+                RpcRequest::GetProgramAccounts,
+                serde_json::to_value(vec![keyed]).unwrap(),
+            )]),
+        );
+
+        assert!(idl
+            .get_program_accounts(&client, &program_id, "Missing", vec![])
+            .await
+            .is_err());
+        let accounts = idl
+            .get_program_accounts(&client, &program_id, "Vault", vec![])
+            .await
+            .unwrap();
+        assert_eq!(accounts.len(), 1);
+        let (pubkey, vault) = &accounts[0];
+        assert_eq!(*pubkey, address);
+        assert_eq!(vault.account_type, "Vault");
+        assert_eq!(
+            vault.deserialized,
+            json!({ "owner": owner.to_string(), "amount": 9 })
+        );
+    }
+
+    #[tokio::test]
+    async fn only_programs_without_an_idl_account_are_missing() {
+        let idl_json = serde_json::to_vec(&json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [],
+        }))
+        .unwrap();
+        let mut program_ids = [Pubkey::new_unique(), Pubkey::new_unique()];
+        program_ids.sort();
+        let [with_idl, without_idl] = program_ids;
+        let account = Account {
+            lamports: 1,
+            data: serialize_idl_json_account(&idl_json, None).unwrap(),
+            owner: with_idl,
+            ..Default::default()
+        };
+        let idl_addr = IdlAccount::address(&with_idl);
+        // The mock answers the first request, the following ones find no account.
+        let client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            HashMap::from([(
+                RpcRequest::GetAccountInfo,
+                serde_json::to_value(Response {
+                    context: RpcResponseContext {
+                        slot: 5,
+                        api_version: None,
+                    },
+                    value: Some(UiAccount::encode(
+                        &idl_addr,
+                        &account,
+                        UiAccountEncoding::Base64,
+                        None,
+                        None,
+                    )),
+                })
+                .unwrap(),
+            )]),
+        );
+        let dir = std::env::temp_dir().join(format!("export-idls-{}", Pubkey::new_unique()));
+
+        let manifest = export_idls(&client, program_ids, &dir).await.unwrap();
+        assert_eq!(manifest.idls.len(), 1);
+        assert_eq!(manifest.idls[0].program_id, with_idl);
+        assert_eq!(manifest.idls[0].slot, Some(5));
+        assert_eq!(manifest.missing, vec![without_idl]);
+        assert!(dir.join(format!("{}-5.json", with_idl)).exists());
+
+        // RPC failures are errors, not missing IDLs.
+        let client = RpcClient::new_mock("fails".to_string());
+        assert!(export_idls(&client, [without_idl], &dir).await.is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
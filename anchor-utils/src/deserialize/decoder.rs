@@ -0,0 +1,170 @@
+//! User-supplied decoders for programs that have no IDL, such as native programs
+//! or programs that hand-roll their Borsh serialization.
+use anyhow::Result;
+use serde_json::Value;
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::Account;
+use std::collections::HashMap;
+
+/// Decodes a program's instructions into their name and data.
+pub trait InstructionDecoder: Send + Sync {
+    fn decode_instruction(&self, ix: &Instruction) -> Result<(String, Value)>;
+}
+
+impl<F> InstructionDecoder for F
+where
+    F: Fn(&Instruction) -> Result<(String, Value)> + Send + Sync,
+{
+    fn decode_instruction(&self, ix: &Instruction) -> Result<(String, Value)> {
+        self(ix)
+    }
+}
+
+/// Decodes the accounts owned by a program into their type name and data.
+pub trait AccountDecoder: Send + Sync {
+    fn decode_account(&self, account: &Account) -> Result<(String, Value)>;
+}
+
+impl<F> AccountDecoder for F
+where
+    F: Fn(&Account) -> Result<(String, Value)> + Send + Sync,
+{
+    fn decode_account(&self, account: &Account) -> Result<(String, Value)> {
+        self(account)
+    }
+}
+
+struct Registered<D: ?Sized> {
+    program_name: String,
+    decoder: Box<D>,
+}
+
+/// Custom decoders keyed by program ID. The [crate::deserialize::AnchorDeserializer]
+/// consults these for programs without a cached IDL.
+#[derive(Default)]
+pub struct DecoderRegistry {
+    instructions: HashMap<Pubkey, Registered<dyn InstructionDecoder>>,
+    accounts: HashMap<Pubkey, Registered<dyn AccountDecoder>>,
+}
+
+impl DecoderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces any instruction decoder already registered for the program.
+    pub fn register_instruction_decoder(
+        &mut self,
+        program_id: Pubkey,
+        program_name: impl Into<String>,
+        decoder: impl InstructionDecoder + 'static,
+    ) {
+        self.instructions.insert(
+            program_id,
+            Registered {
+                program_name: program_name.into(),
+                decoder: Box::new(decoder),
+            },
+        );
+    }
+
+    /// Replaces any account decoder already registered for the program.
+    pub fn register_account_decoder(
+        &mut self,
+        program_id: Pubkey,
+        program_name: impl Into<String>,
+        decoder: impl AccountDecoder + 'static,
+    ) {
+        self.accounts.insert(
+            program_id,
+            Registered {
+                program_name: program_name.into(),
+                decoder: Box::new(decoder),
+            },
+        );
+    }
+
+    /// The program name and decoded instruction, if a decoder is registered for its program.
+    pub fn decode_instruction(&self, ix: &Instruction) -> Option<(&str, Result<(String, Value)>)> {
+        let registered = self.instructions.get(&ix.program_id)?;
+        Some((
+            registered.program_name.as_str(),
+            registered.decoder.decode_instruction(ix),
+        ))
+    }
+
+    /// The program name and decoded account, if a decoder is registered for its owner.
+    pub fn decode_account(&self, account: &Account) -> Option<(&str, Result<(String, Value)>)> {
+        let registered = self.accounts.get(&account.owner)?;
+        Some((
+            registered.program_name.as_str(),
+            registered.decoder.decode_account(account),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::transaction::instruction::DeserializedInstructionData;
+    use crate::deserialize::AnchorDeserializer;
+    use anyhow::anyhow;
+    use serde_json::json;
+
+    #[test]
+    fn registered_decoders_are_consulted_for_programs_without_idls() {
+        let program_id = Pubkey::new_unique();
+        let mut deserializer = AnchorDeserializer::new();
+        deserializer.register_instruction_decoder(
+            program_id,
+            "counter",
+            |ix: &Instruction| match ix.data.as_slice() {
+                [0, by] => Ok(("increment".to_string(), json!({ "by": by }))),
+                _ => Err(anyhow!("unknown counter instruction")),
+            },
+        );
+        deserializer.register_account_decoder(program_id, "counter", |account: &Account| {
+            let count = u64::from_le_bytes(account.data[..8].try_into()?);
+            Ok(("Counter".to_string(), json!({ "count": count })))
+        });
+
+        let mut ix = Instruction::new_with_bytes(program_id, &[0, 5], vec![]);
+        let decoded = deserializer
+            .try_deserialize_instruction(0, &mut ix, None)
+            .unwrap();
+        assert_eq!(decoded.program_name, "counter");
+        assert_eq!(
+            decoded.parsed,
+            DeserializedInstructionData::Ok {
+                name: "increment".to_string(),
+                data: json!({ "by": 5 }),
+                accounts: vec![],
+            }
+        );
+
+        let mut ix = Instruction::new_with_bytes(program_id, &[1], vec![]);
+        let decoded = deserializer
+            .try_deserialize_instruction(0, &mut ix, None)
+            .unwrap();
+        assert_eq!(decoded.program_name, "counter");
+        assert_eq!(
+            decoded.parsed,
+            DeserializedInstructionData::Err {
+                deserialize_error: "unknown counter instruction".to_string(),
+            }
+        );
+
+        let account = Account {
+            data: 7u64.to_le_bytes().to_vec(),
+            owner: program_id,
+            ..Account::default()
+        };
+        let decoded = deserializer
+            .try_deserialize_account(Pubkey::new_unique(), &account)
+            .unwrap();
+        assert_eq!(decoded.program_name, "counter");
+        assert_eq!(decoded.account_type, "Counter");
+        assert_eq!(decoded.deserialized, json!({ "count": 7 }));
+    }
+}
@@ -0,0 +1,169 @@
+//! Compare two versions of an account, field by field where an IDL can decode both.
+use crate::deserialize::AnchorDeserializer;
+use crate::diff::AccountDiff;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::Account;
+
+/// A field that differs, at a path such as `positions[2].amount`.
+/// `None` on one side means the field only exists on the other.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldDiff {
+    pub path: String,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+/// The decoded fields that differ, when both sides decode as the same account type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldsDiff {
+    pub account_type: String,
+    pub fields: Vec<FieldDiff>,
+}
+
+/// The differences between two versions of an account. The byte ranges of `diff`
+/// are only filled in when the data can't be decoded as `fields`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecodedAccountDiff {
+    pub address: String,
+    #[serde(flatten)]
+    pub diff: AccountDiff,
+    pub fields: Option<FieldsDiff>,
+}
+
+impl DecodedAccountDiff {
+    pub fn is_empty(&self) -> bool {
+        self.diff.is_empty()
+            && self
+                .fields
+                .as_ref()
+                .is_none_or(|fields| fields.fields.is_empty())
+    }
+}
+
+impl AnchorDeserializer {
+    /// Diffs the decoded fields if the cached IDLs decode both sides as the same account type,
+    /// and the raw bytes otherwise, see [AccountDiff].
+    pub fn diff_accounts(
+        &self,
+        address: Pubkey,
+        left: &Account,
+        right: &Account,
+    ) -> DecodedAccountDiff {
+        let decoded = self
+            .try_deserialize_account(address, left)
+            .ok()
+            .zip(self.try_deserialize_account(address, right).ok())
+            .filter(|(l, r)| l.program_name == r.program_name && l.account_type == r.account_type);
+        let mut diff = AccountDiff::new(left, right);
+        let fields = decoded.map(|(l, r)| {
+            diff.data.clear();
+            FieldsDiff {
+                fields: diff_json(&l.deserialized, &r.deserialized),
+                account_type: l.account_type,
+            }
+        });
+        DecodedAccountDiff {
+            address: address.to_string(),
+            diff,
+            fields,
+        }
+    }
+}
+
+/// The leaf values that differ between two JSON values, in the order of the left value's fields,
+/// followed by fields only on the right.
+pub fn diff_json(left: &Value, right: &Value) -> Vec<FieldDiff> {
+    let mut diffs = vec![];
+    diff_json_at(String::new(), Some(left), Some(right), &mut diffs);
+    diffs
+}
+
+fn diff_json_at(
+    path: String,
+    left: Option<&Value>,
+    right: Option<&Value>,
+    diffs: &mut Vec<FieldDiff>,
+) {
+    match (left, right) {
+        (Some(Value::Object(l)), Some(Value::Object(r))) => {
+            for (key, value) in l {
+                diff_json_at(join(&path, key), Some(value), r.get(key), diffs);
+            }
+            for (key, value) in r.iter().filter(|(key, _)| !l.contains_key(*key)) {
+                diff_json_at(join(&path, key), None, Some(value), diffs);
+            }
+        }
+        (Some(Value::Array(l)), Some(Value::Array(r))) => {
+            for i in 0..l.len().max(r.len()) {
+                diff_json_at(format!("{}[{}]", path, i), l.get(i), r.get(i), diffs);
+            }
+        }
+        (l, r) if l != r => diffs.push(FieldDiff {
+            path,
+            left: l.cloned(),
+            right: r.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Change;
+    use serde_json::json;
+
+    #[test]
+    fn differences_are_found_by_field() {
+        let left =
+            json!({"authority": "a", "positions": [{"amount": 1}, {"amount": 2}], "closed": false});
+        let right = json!({"authority": "a", "positions": [{"amount": 1}, {"amount": 3}, {"amount": 4}], "bump": 255});
+        let paths: Vec<(String, Option<Value>, Option<Value>)> = diff_json(&left, &right)
+            .into_iter()
+            .map(|diff| (diff.path, diff.left, diff.right))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                (
+                    "positions[1].amount".to_string(),
+                    Some(json!(2)),
+                    Some(json!(3))
+                ),
+                ("positions[2]".to_string(), None, Some(json!({"amount": 4}))),
+                ("closed".to_string(), Some(json!(false)), None),
+                ("bump".to_string(), None, Some(json!(255))),
+            ]
+        );
+
+        let account = Account::new(1, 3, &Pubkey::new_unique());
+        let mut changed = account.clone();
+        changed.lamports = 2;
+        changed.data[2] = 7;
+        let diff =
+            AnchorDeserializer::new().diff_accounts(Pubkey::new_unique(), &account, &changed);
+        assert_eq!(
+            diff.diff.lamports,
+            Some(Change {
+                before: 1,
+                after: 2
+            })
+        );
+        assert_eq!(diff.diff.owner, None);
+        // Without an IDL, the data is diffed byte by byte.
+        assert_eq!(diff.fields, None);
+        assert_eq!(diff.diff.data.len(), 1);
+        assert!(!diff.is_empty());
+    }
+}
@@ -12,6 +12,14 @@ pub fn account_discriminator(name: &str) -> Discriminator {
         .unwrap()
 }
 
+/// Calculates the discriminator for an event based on its name,
+/// which would be found in an IDL.
+pub fn event_discriminator(name: &str) -> Discriminator {
+    hash(format!("event:{}", name).as_bytes()).to_bytes()[0..8]
+        .try_into()
+        .unwrap()
+}
+
 /// Calculates the discriminator for an instruction based on its name,
 /// which would be found in an IDL.
 pub fn ix_discriminator(name: &str) -> Discriminator {
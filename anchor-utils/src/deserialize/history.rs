@@ -0,0 +1,188 @@
+//! Fetch and decode the transaction history of an address.
+use crate::deserialize::transaction::DeserializedTransaction;
+use crate::deserialize::AnchorDeserializer;
+use anyhow::{anyhow, Result};
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_devtools_tx::inner_instructions::HistoricalTransaction;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::UiTransactionEncoding;
+use std::str::FromStr;
+
+/// The most signatures `getSignaturesForAddress` returns in one page.
+const SIGNATURES_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct TransactionHistoryConfig {
+    /// Start from the transaction before this one, rather than the most recent.
+    pub before: Option<Signature>,
+    /// Stop at this transaction, exclusive.
+    pub until: Option<Signature>,
+    /// The most transactions to fetch, `None` pages through the entire history.
+    pub limit: Option<usize>,
+    /// The most transactions fetched at once.
+    pub concurrency: usize,
+    pub commitment: Option<CommitmentConfig>,
+}
+
+impl Default for TransactionHistoryConfig {
+    fn default() -> Self {
+        Self {
+            before: None,
+            until: None,
+            limit: None,
+            concurrency: 8,
+            commitment: None,
+        }
+    }
+}
+
+/// Page through an address's transaction signatures, most recent first,
+/// and fetch each transaction. Transactions are yielded in signature order,
+/// with up to [TransactionHistoryConfig::concurrency] requests in flight.
+pub fn transaction_history<'a>(
+    client: &'a RpcClient,
+    address: Pubkey,
+    config: TransactionHistoryConfig,
+) -> impl Stream<Item = Result<(Signature, HistoricalTransaction)>> + 'a {
+    let limit = config.limit.unwrap_or(usize::MAX);
+    let commitment = config.commitment;
+    let pages = stream::try_unfold((config.before, false), move |(before, done)| async move {
+        if done {
+            return Ok(None);
+        }
+        let page = client
+            .get_signatures_for_address_with_config(
+                &address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: config.until,
+                    limit: Some(limit.min(SIGNATURES_PAGE_SIZE)),
+                    commitment,
+                },
+            )
+            .await?;
+        let signatures = page
+            .iter()
+            .map(|status| Signature::from_str(&status.signature))
+            .collect::<Result<Vec<_>, _>>()?;
+        let done = signatures.len() < SIGNATURES_PAGE_SIZE;
+        let before = signatures.last().copied();
+        anyhow::Ok(Some((signatures, (before, done))))
+    });
+    pages
+        .map_ok(|signatures| stream::iter(signatures.into_iter().map(anyhow::Ok)))
+        .try_flatten()
+        .take(limit)
+        .map(move |signature: Result<Signature>| async move {
+            let signature = signature?;
+            let tx = fetch_transaction(client, &signature, commitment).await?;
+            Ok((signature, tx))
+        })
+        .buffered(config.concurrency.max(1))
+}
+
+async fn fetch_transaction(
+    client: &RpcClient,
+    signature: &Signature,
+    commitment: Option<CommitmentConfig>,
+) -> Result<HistoricalTransaction> {
+    let tx = client
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment,
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await?;
+    HistoricalTransaction::try_from(tx)
+        .map_err(|e| anyhow!("failed to decode transaction {}: {}", signature, e))
+}
+
+impl AnchorDeserializer {
+    /// Deserialize each transaction of [transaction_history] with the cached IDLs.
+    pub fn deserialize_transaction_history<'a>(
+        &'a self,
+        client: &'a RpcClient,
+        address: Pubkey,
+        config: TransactionHistoryConfig,
+    ) -> impl Stream<Item = Result<(Signature, DeserializedTransaction)>> + 'a {
+        transaction_history(client, address, config).map(move |result| {
+            let (signature, tx) = result?;
+            Ok((signature, self.try_deserialize_transaction(tx)?))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use solana_client::rpc_request::RpcRequest;
+    use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+    use solana_program::message::{Message, VersionedMessage};
+    use solana_program::system_instruction;
+    use solana_sdk::transaction::VersionedTransaction;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn history_is_fetched_and_deserialized() {
+        let from = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let message = Message::new(&[system_instruction::transfer(&from, &to, 42)], Some(&from));
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        let signatures: Vec<_> = (0..3)
+            .map(|i| RpcConfirmedTransactionStatusWithSignature {
+                signature: Signature::from([i; 64]).to_string(),
+                slot: 1,
+                err: None,
+                memo: None,
+                block_time: None,
+                confirmation_status: None,
+            })
+            .collect();
+        let client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            HashMap::from([
+                (
+                    RpcRequest::GetSignaturesForAddress,
+                    serde_json::to_value(signatures).unwrap(),
+                ),
+                (
+                    RpcRequest::GetTransaction,
+                    json!({
+                        "slot": 1,
+                        "transaction": [base64::encode(bincode1::serialize(&tx).unwrap()), "base64"],
+                        "meta": null,
+                        "blockTime": null,
+                    }),
+                ),
+            ]),
+        );
+
+        // Mocked responses are only returned once, so only fetch a single transaction.
+        let deser = AnchorDeserializer::new();
+        let config = TransactionHistoryConfig {
+            limit: Some(1),
+            ..Default::default()
+        };
+        let history: Vec<_> = deser
+            .deserialize_transaction_history(&client, from, config)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].0, Signature::from([0; 64]));
+        assert_eq!(history[0].1.len(), 1);
+        assert!(history[0].1.meta.is_none());
+    }
+}
@@ -1,9 +1,13 @@
 use crate::deserialize::discriminator;
 use crate::deserialize::discriminator::Discriminator;
-use crate::idl_sdk::account::deserialize_idl_account;
-use anchor_syn::idl::types::{Idl, IdlInstruction, IdlTypeDefinition};
+use crate::deserialize::idl_spec::{is_spec_idl, SpecIdl};
+use crate::idl_sdk::account::decompress_idl_account;
+use anchor_syn::idl::types::{Idl, IdlEvent, IdlInstruction, IdlTypeDefinition};
 use anyhow::anyhow;
+use heck::CamelCase;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_devtools_errors::resolver::ProgramErrors;
 use solana_sdk::account::Account;
 use std::collections::BTreeMap;
 use std::fs;
@@ -15,6 +19,7 @@ use std::path::Path;
 /// Discriminators are calculated taking one of the following strings:
 /// - Accounts -- `"account:<AccountStructName>"`
 /// - Instructions -- `"global:<IxName>"` or `"state:<IxName>"`
+/// - Events -- `"event:<EventName>"`
 ///
 /// hashing it, and keeping only the first 8 bytes.
 #[derive(Debug, Clone)]
@@ -22,7 +27,7 @@ pub struct IdlDefinitions {
     pub instructions: BTreeMap<Discriminator, IdlInstruction>,
     pub accounts: BTreeMap<Discriminator, IdlTypeDefinition>,
     pub types: BTreeMap<Discriminator, IdlTypeDefinition>,
-    // TODO events
+    pub events: BTreeMap<Discriminator, IdlEvent>,
 }
 
 impl From<&Idl> for IdlDefinitions {
@@ -54,6 +59,7 @@ impl From<&Idl> for IdlDefinitions {
                 .iter()
                 .map(|act| (discriminator::account_discriminator(&act.name), act.clone()))
                 .collect(),
+            events: event_definitions(idl),
         }
     }
 }
@@ -80,7 +86,7 @@ pub struct IdlWithDiscriminators {
     pub instruction_definitions: BTreeMap<Discriminator, IdlInstruction>,
     pub account_definitions: BTreeMap<Discriminator, IdlTypeDefinition>,
     pub type_definitions: BTreeMap<Discriminator, IdlTypeDefinition>,
-    // TODO events
+    pub event_definitions: BTreeMap<Discriminator, IdlEvent>,
 }
 
 impl IdlWithDiscriminators {
@@ -89,8 +95,21 @@ impl IdlWithDiscriminators {
     }
 
     pub fn from_file(p: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let idl = fs::read_to_string(&p)?;
-        let idl: Idl = serde_json::from_str(&idl)
+        let idl = fs::read(&p)?;
+        Self::from_json(&idl)
+    }
+
+    /// Parse IDL JSON in either the legacy layout or the Anchor 0.30 spec,
+    /// see [crate::deserialize::idl_spec].
+    pub fn from_json(json: &[u8]) -> anyhow::Result<Self> {
+        let idl: Value = serde_json::from_slice(json)
+            .map_err(|_| anyhow!("Could not deserialize decompressed IDL data"))?;
+        if is_spec_idl(&idl) {
+            let idl: SpecIdl = serde_json::from_value(idl)
+                .map_err(|e| anyhow!("Could not deserialize Anchor 0.30 IDL: {e}"))?;
+            return Self::try_from(idl);
+        }
+        let idl: Idl = serde_json::from_value(idl)
             .map_err(|_| anyhow!("Could not deserialize decompressed IDL data"))?;
         Ok(idl.into())
     }
@@ -136,7 +155,32 @@ impl IdlWithDiscriminators {
             .map(|entry| entry.1)
     }
 
-    // TODO Events
+    /// The discriminator of an account type, as found in account data.
+    pub fn account_discriminator_by_name(&self, name: &str) -> Option<Discriminator> {
+        self.account_definitions
+            .iter()
+            .find(|entry| entry.1.name == name)
+            .map(|entry| *entry.0)
+    }
+
+    pub fn get_event_definition(&self, discriminator: &Discriminator) -> Option<&IdlEvent> {
+        self.event_definitions.get(discriminator)
+    }
+
+    pub fn get_event_definition_by_name(&self, name: &str) -> Option<&IdlEvent> {
+        self.event_definitions
+            .iter()
+            .find(|entry| entry.1.name == name)
+            .map(|entry| entry.1)
+    }
+
+    /// The IDL's custom errors, under the program name in upper camel case.
+    pub fn program_errors(&self) -> ProgramErrors {
+        self.idl.errors.iter().flatten().fold(
+            ProgramErrors::new(self.idl.name.to_camel_case()),
+            |errors, error| errors.error(error.code, &error.name, error.msg.clone()),
+        )
+    }
 }
 
 impl Deref for IdlWithDiscriminators {
@@ -176,17 +220,31 @@ impl From<Idl> for IdlWithDiscriminators {
                 .iter()
                 .map(|act| (discriminator::account_discriminator(&act.name), act.clone()))
                 .collect(),
+            event_definitions: event_definitions(&idl),
             idl,
         }
     }
 }
 
+fn event_definitions(idl: &Idl) -> BTreeMap<Discriminator, IdlEvent> {
+    idl.events
+        .iter()
+        .flatten()
+        .map(|event| {
+            (
+                discriminator::event_discriminator(&event.name),
+                event.clone(),
+            )
+        })
+        .collect()
+}
+
 impl TryFrom<Account> for IdlWithDiscriminators {
     type Error = anyhow::Error;
 
     fn try_from(account: Account) -> Result<Self, Self::Error> {
-        let idl = deserialize_idl_account(&account.data)
+        let json = decompress_idl_account(&account.data)
             .map_err(|e| anyhow!("failed to deserialize IDL: {e}"))?;
-        Ok(Self::from(idl))
+        Self::from_json(&json)
     }
 }
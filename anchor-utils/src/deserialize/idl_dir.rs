@@ -0,0 +1,132 @@
+//! A directory of IDL files with a manifest, to decode offline with IDLs fetched in advance.
+use crate::deserialize::{AnchorDeserializer, IdlWithDiscriminators};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_devtools_serde::{pubkey, pubkey_vec};
+use solana_program::clock::Slot;
+use solana_program::pubkey::Pubkey;
+use std::fs;
+use std::path::Path;
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlManifestEntry {
+    #[serde(with = "pubkey")]
+    pub program_id: Pubkey,
+    /// The IDL's `name`.
+    pub name: String,
+    /// The slot the IDL account was fetched at, if it was fetched on-chain.
+    pub slot: Option<Slot>,
+    /// Relative to the directory.
+    pub file: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlManifest {
+    pub idls: Vec<IdlManifestEntry>,
+    /// Programs for which no IDL was found.
+    #[serde(with = "pubkey_vec")]
+    pub missing: Vec<Pubkey>,
+}
+
+impl IdlManifest {
+    pub fn read(dir: impl AsRef<Path>) -> Result<Self> {
+        let path = dir.as_ref().join(MANIFEST_FILE_NAME);
+        let manifest = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("could not read {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&manifest)?)
+    }
+
+    /// Write an IDL to `<program_id>-<slot>.json`, or `<program_id>.json` if there is no slot,
+    /// and add it to the manifest. The manifest itself is written by [IdlManifest::write].
+    pub fn add_idl(
+        &mut self,
+        dir: impl AsRef<Path>,
+        program_id: Pubkey,
+        slot: Option<Slot>,
+        idl: &IdlWithDiscriminators,
+    ) -> Result<()> {
+        let file = match slot {
+            Some(slot) => format!("{}-{}.json", program_id, slot),
+            None => format!("{}.json", program_id),
+        };
+        fs::write(
+            dir.as_ref().join(&file),
+            serde_json::to_string_pretty(&**idl)?,
+        )?;
+        self.idls.retain(|entry| entry.program_id != program_id);
+        self.idls.push(IdlManifestEntry {
+            program_id,
+            name: idl.name.clone(),
+            slot,
+            file,
+        });
+        Ok(())
+    }
+
+    pub fn write(&self, dir: impl AsRef<Path>) -> Result<()> {
+        fs::write(
+            dir.as_ref().join(MANIFEST_FILE_NAME),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+}
+
+impl AnchorDeserializer {
+    /// Cache every IDL listed in a directory's manifest.
+    pub fn cache_idls_from_dir(&mut self, dir: impl AsRef<Path>) -> Result<IdlManifest> {
+        let dir = dir.as_ref();
+        let manifest = IdlManifest::read(dir)?;
+        for entry in &manifest.idls {
+            self.cache_idl_from_file(entry.program_id, dir.join(&entry.file))?;
+        }
+        Ok(manifest)
+    }
+
+    /// Write every cached IDL to a directory, with a manifest.
+    pub fn export_cached_idls(&self, dir: impl AsRef<Path>) -> Result<IdlManifest> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let mut program_ids: Vec<&Pubkey> = self.idl_cache.keys().collect();
+        program_ids.sort();
+        let mut manifest = IdlManifest::default();
+        for program_id in program_ids {
+            manifest.add_idl(dir, *program_id, None, &self.idl_cache[program_id])?;
+        }
+        manifest.write(dir)?;
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_syn::idl::types::Idl;
+    use serde_json::json;
+
+    #[test]
+    fn exported_idls_are_loaded_from_manifest() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "exported",
+            "instructions": [],
+        }))
+        .unwrap();
+        let program_id = Pubkey::new_unique();
+        let mut deser = AnchorDeserializer::new();
+        deser.cache_idl(program_id, idl.into());
+
+        let dir = std::env::temp_dir().join(format!("idl-dir-{}", Pubkey::new_unique()));
+        let manifest = deser.export_cached_idls(&dir).unwrap();
+        assert_eq!(manifest.idls[0].file, format!("{}.json", program_id));
+
+        let mut offline = AnchorDeserializer::new();
+        assert_eq!(offline.cache_idls_from_dir(&dir).unwrap(), manifest);
+        assert_eq!(offline.idl_cache[&program_id].name, "exported");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,695 @@
+//! The IDL spec introduced in Anchor 0.30, which embeds discriminators, moves account
+//! and event layouts into `types`, and adds the program `address`.
+//!
+//! Spec IDLs are converted to the legacy [anchor_syn] layout, so the rest of this crate
+//! handles both. Their embedded discriminators are kept in the [IdlWithDiscriminators]
+//! lookup tables, rather than derived from names.
+use crate::deserialize::discriminator::Discriminator;
+use crate::deserialize::IdlWithDiscriminators;
+use anchor_syn::idl::types as legacy;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Whether an IDL's JSON is in the Anchor 0.30 spec, rather than the legacy layout.
+pub fn is_spec_idl(idl: &Value) -> bool {
+    idl.get("address").is_some() && idl.pointer("/metadata/spec").is_some()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdl {
+    pub address: String,
+    pub metadata: SpecIdlMetadata,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    pub instructions: Vec<SpecIdlInstruction>,
+    #[serde(default)]
+    pub accounts: Vec<SpecIdlDiscriminated>,
+    #[serde(default)]
+    pub events: Vec<SpecIdlDiscriminated>,
+    #[serde(default)]
+    pub errors: Vec<legacy::IdlErrorCode>,
+    #[serde(default)]
+    pub types: Vec<SpecIdlTypeDef>,
+    #[serde(default)]
+    pub constants: Vec<SpecIdlConst>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdlMetadata {
+    pub name: String,
+    pub version: String,
+    pub spec: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdlInstruction {
+    pub name: String,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    pub discriminator: Vec<u8>,
+    pub accounts: Vec<SpecIdlInstructionAccountItem>,
+    pub args: Vec<SpecIdlField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub returns: Option<SpecIdlType>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SpecIdlInstructionAccountItem {
+    Composite(SpecIdlInstructionAccounts),
+    Single(SpecIdlInstructionAccount),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdlInstructionAccount {
+    pub name: String,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    #[serde(default)]
+    pub writable: bool,
+    #[serde(default)]
+    pub signer: bool,
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub relations: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdlInstructionAccounts {
+    pub name: String,
+    pub accounts: Vec<SpecIdlInstructionAccountItem>,
+}
+
+/// An account or event, whose layout is the type of the same name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdlDiscriminated {
+    pub name: String,
+    pub discriminator: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdlField {
+    pub name: String,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    #[serde(rename = "type")]
+    pub ty: SpecIdlType,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdlConst {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: SpecIdlType,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdlTypeDef {
+    pub name: String,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub serialization: SpecIdlSerialization,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repr: Option<SpecIdlRepr>,
+    #[serde(default)]
+    pub generics: Vec<SpecIdlTypeDefGeneric>,
+    #[serde(rename = "type")]
+    pub ty: SpecIdlTypeDefTy,
+}
+
+/// How a type is laid out in account data. Zero-copy accounts use `bytemuck`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecIdlSerialization {
+    #[default]
+    Borsh,
+    Bytemuck,
+    BytemuckUnsafe,
+    Custom(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum SpecIdlRepr {
+    Rust(SpecIdlReprModifier),
+    C(SpecIdlReprModifier),
+    Transparent,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdlReprModifier {
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub packed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub align: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum SpecIdlTypeDefGeneric {
+    Type {
+        name: String,
+    },
+    Const {
+        name: String,
+        #[serde(rename = "type")]
+        ty: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum SpecIdlTypeDefTy {
+    Struct {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fields: Option<SpecIdlDefinedFields>,
+    },
+    Enum {
+        variants: Vec<SpecIdlEnumVariant>,
+    },
+    Type {
+        alias: SpecIdlType,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecIdlEnumVariant {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<SpecIdlDefinedFields>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SpecIdlDefinedFields {
+    Named(Vec<SpecIdlField>),
+    Tuple(Vec<SpecIdlType>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecIdlType {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    U64,
+    I64,
+    F64,
+    U128,
+    I128,
+    U256,
+    I256,
+    Bytes,
+    String,
+    Pubkey,
+    Option(Box<SpecIdlType>),
+    /// A `COption`, which has a 4 byte tag and is not supported by the legacy layout.
+    COption(Box<SpecIdlType>),
+    Vec(Box<SpecIdlType>),
+    Array(Box<SpecIdlType>, SpecIdlArrayLen),
+    Defined {
+        name: String,
+        #[serde(default)]
+        generics: Vec<SpecIdlGenericArg>,
+    },
+    Generic(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SpecIdlArrayLen {
+    Generic { generic: String },
+    Value(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum SpecIdlGenericArg {
+    Type {
+        #[serde(rename = "type")]
+        ty: SpecIdlType,
+    },
+    Const {
+        value: String,
+    },
+}
+
+impl SpecIdlType {
+    fn to_legacy(&self) -> Result<legacy::IdlType> {
+        use legacy::IdlType;
+        Ok(match self {
+            Self::Bool => IdlType::Bool,
+            Self::U8 => IdlType::U8,
+            Self::I8 => IdlType::I8,
+            Self::U16 => IdlType::U16,
+            Self::I16 => IdlType::I16,
+            Self::U32 => IdlType::U32,
+            Self::I32 => IdlType::I32,
+            Self::F32 => IdlType::F32,
+            Self::U64 => IdlType::U64,
+            Self::I64 => IdlType::I64,
+            Self::F64 => IdlType::F64,
+            Self::U128 => IdlType::U128,
+            Self::I128 => IdlType::I128,
+            Self::U256 => IdlType::U256,
+            Self::I256 => IdlType::I256,
+            Self::Bytes => IdlType::Bytes,
+            Self::String => IdlType::String,
+            Self::Pubkey => IdlType::PublicKey,
+            Self::Option(ty) => IdlType::Option(Box::new(ty.to_legacy()?)),
+            Self::COption(_) => bail!("COption types are not supported"),
+            Self::Vec(ty) => IdlType::Vec(Box::new(ty.to_legacy()?)),
+            Self::Array(ty, SpecIdlArrayLen::Value(len)) => {
+                IdlType::Array(Box::new(ty.to_legacy()?), *len)
+            }
+            Self::Array(ty, SpecIdlArrayLen::Generic { generic }) => {
+                IdlType::GenericLenArray(Box::new(ty.to_legacy()?), generic.clone())
+            }
+            Self::Defined { name, generics } if generics.is_empty() => {
+                IdlType::Defined(name.clone())
+            }
+            Self::Defined { name, generics } => IdlType::DefinedWithTypeArgs {
+                name: name.clone(),
+                args: generics
+                    .iter()
+                    .map(|arg| match arg {
+                        SpecIdlGenericArg::Type { ty } => {
+                            Ok(legacy::IdlDefinedTypeArg::Type(ty.to_legacy()?))
+                        }
+                        SpecIdlGenericArg::Const { value } => {
+                            Ok(legacy::IdlDefinedTypeArg::Value(value.clone()))
+                        }
+                    })
+                    .collect::<Result<_>>()?,
+            },
+            Self::Generic(name) => IdlType::Generic(name.clone()),
+        })
+    }
+}
+
+impl SpecIdlField {
+    fn to_legacy(&self) -> Result<legacy::IdlField> {
+        Ok(legacy::IdlField {
+            name: self.name.clone(),
+            docs: docs(&self.docs),
+            ty: self.ty.to_legacy()?,
+        })
+    }
+}
+
+impl SpecIdlDefinedFields {
+    /// Tuple struct fields are named by their position, as in Rust.
+    fn to_legacy_named(&self) -> Result<Vec<legacy::IdlField>> {
+        match self {
+            Self::Named(fields) => fields.iter().map(SpecIdlField::to_legacy).collect(),
+            Self::Tuple(types) => types
+                .iter()
+                .enumerate()
+                .map(|(position, ty)| {
+                    Ok(legacy::IdlField {
+                        name: position.to_string(),
+                        docs: None,
+                        ty: ty.to_legacy()?,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn to_legacy(&self) -> Result<legacy::EnumFields> {
+        Ok(match self {
+            Self::Named(_) => legacy::EnumFields::Named(self.to_legacy_named()?),
+            Self::Tuple(types) => legacy::EnumFields::Tuple(
+                types
+                    .iter()
+                    .map(SpecIdlType::to_legacy)
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+}
+
+impl SpecIdlTypeDef {
+    /// The legacy layout is decoded as Borsh, which reads fields in order with no padding.
+    /// `Pod` types can't have padding, so `bytemuck` types decode the same way, but
+    /// `bytemuckunsafe` types only do when packed.
+    fn check_layout(&self) -> Result<()> {
+        let packed = matches!(
+            &self.repr,
+            Some(SpecIdlRepr::Rust(modifier) | SpecIdlRepr::C(modifier)) if modifier.packed
+        );
+        match &self.serialization {
+            SpecIdlSerialization::Borsh | SpecIdlSerialization::Bytemuck => Ok(()),
+            SpecIdlSerialization::BytemuckUnsafe if packed => Ok(()),
+            SpecIdlSerialization::BytemuckUnsafe => bail!(
+                "Type {} uses bytemuckunsafe serialization without a packed repr, \
+                so its padding is unknown",
+                self.name
+            ),
+            SpecIdlSerialization::Custom(serialization) => bail!(
+                "Type {} uses unsupported custom serialization {}",
+                self.name,
+                serialization
+            ),
+        }
+    }
+
+    fn to_legacy(&self) -> Result<legacy::IdlTypeDefinition> {
+        self.check_layout()?;
+        let ty = match &self.ty {
+            SpecIdlTypeDefTy::Struct { fields } => legacy::IdlTypeDefinitionTy::Struct {
+                fields: match fields {
+                    Some(fields) => fields.to_legacy_named()?,
+                    None => vec![],
+                },
+            },
+            SpecIdlTypeDefTy::Enum { variants } => legacy::IdlTypeDefinitionTy::Enum {
+                variants: variants
+                    .iter()
+                    .map(|variant| {
+                        Ok(legacy::IdlEnumVariant {
+                            name: variant.name.clone(),
+                            fields: variant
+                                .fields
+                                .as_ref()
+                                .map(SpecIdlDefinedFields::to_legacy)
+                                .transpose()?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            },
+            SpecIdlTypeDefTy::Type { alias } => legacy::IdlTypeDefinitionTy::Alias {
+                value: alias.to_legacy()?,
+            },
+        };
+        let generics: Vec<String> = self
+            .generics
+            .iter()
+            .map(|generic| match generic {
+                SpecIdlTypeDefGeneric::Type { name }
+                | SpecIdlTypeDefGeneric::Const { name, .. } => name.clone(),
+            })
+            .collect();
+        Ok(legacy::IdlTypeDefinition {
+            name: self.name.clone(),
+            docs: docs(&self.docs),
+            generics: (!generics.is_empty()).then_some(generics),
+            ty,
+        })
+    }
+}
+
+impl SpecIdlInstructionAccountItem {
+    fn to_legacy(&self) -> legacy::IdlAccountItem {
+        match self {
+            Self::Composite(accounts) => legacy::IdlAccountItem::IdlAccounts(legacy::IdlAccounts {
+                name: accounts.name.clone(),
+                accounts: accounts.accounts.iter().map(Self::to_legacy).collect(),
+            }),
+            Self::Single(account) => legacy::IdlAccountItem::IdlAccount(legacy::IdlAccount {
+                name: account.name.clone(),
+                is_mut: account.writable,
+                is_signer: account.signer,
+                is_optional: account.optional.then_some(true),
+                docs: docs(&account.docs),
+                pda: None,
+                relations: account.relations.clone(),
+            }),
+        }
+    }
+}
+
+impl SpecIdl {
+    fn type_def(&self, name: &str) -> Result<&SpecIdlTypeDef> {
+        self.types
+            .iter()
+            .find(|ty_def| ty_def.name == name)
+            .ok_or(anyhow!("IDL has no type definition for {}", name))
+    }
+
+    /// Convert to the legacy layout, keeping the program address in its `metadata`.
+    /// Account types move from `types` to `accounts`, and event types are inlined.
+    pub fn to_legacy(&self) -> Result<legacy::Idl> {
+        let is_account = |name: &str| self.accounts.iter().any(|act| act.name == name);
+        let events = self
+            .events
+            .iter()
+            .map(|event| {
+                let fields = match &self.type_def(&event.name)?.ty {
+                    SpecIdlTypeDefTy::Struct {
+                        fields: Some(fields),
+                    } => fields.to_legacy_named()?,
+                    SpecIdlTypeDefTy::Struct { fields: None } => vec![],
+                    _ => bail!("Event {} is not a struct", event.name),
+                };
+                Ok(legacy::IdlEvent {
+                    name: event.name.clone(),
+                    fields: fields
+                        .into_iter()
+                        .map(|field| legacy::IdlEventField {
+                            name: field.name,
+                            ty: field.ty,
+                            index: false,
+                        })
+                        .collect(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(legacy::Idl {
+            version: self.metadata.version.clone(),
+            name: self.metadata.name.clone(),
+            docs: docs(&self.docs),
+            constants: self
+                .constants
+                .iter()
+                .map(|constant| {
+                    Ok(legacy::IdlConst {
+                        name: constant.name.clone(),
+                        ty: constant.ty.to_legacy()?,
+                        value: constant.value.clone(),
+                    })
+                })
+                .collect::<Result<_>>()?,
+            instructions: self
+                .instructions
+                .iter()
+                .map(|ix| {
+                    Ok(legacy::IdlInstruction {
+                        name: ix.name.clone(),
+                        docs: docs(&ix.docs),
+                        accounts: ix
+                            .accounts
+                            .iter()
+                            .map(SpecIdlInstructionAccountItem::to_legacy)
+                            .collect(),
+                        args: ix
+                            .args
+                            .iter()
+                            .map(SpecIdlField::to_legacy)
+                            .collect::<Result<_>>()?,
+                        returns: ix
+                            .returns
+                            .as_ref()
+                            .map(SpecIdlType::to_legacy)
+                            .transpose()?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+            accounts: self
+                .accounts
+                .iter()
+                .map(|act| self.type_def(&act.name)?.to_legacy())
+                .collect::<Result<_>>()?,
+            types: self
+                .types
+                .iter()
+                .filter(|ty_def| !is_account(&ty_def.name))
+                .map(SpecIdlTypeDef::to_legacy)
+                .collect::<Result<_>>()?,
+            events: (!events.is_empty()).then_some(events),
+            errors: (!self.errors.is_empty()).then(|| self.errors.clone()),
+            metadata: Some(serde_json::json!({
+                "address": self.address,
+                "spec": self.metadata.spec,
+            })),
+        })
+    }
+}
+
+impl TryFrom<SpecIdl> for IdlWithDiscriminators {
+    type Error = anyhow::Error;
+
+    fn try_from(spec: SpecIdl) -> Result<Self> {
+        let mut idl = Self::from(spec.to_legacy()?);
+        idl.instruction_definitions = spec
+            .instructions
+            .iter()
+            .zip(&idl.instructions)
+            .map(|(spec_ix, ix)| Ok((discriminator(&spec_ix.discriminator)?, ix.clone())))
+            .collect::<Result<_>>()?;
+        idl.account_definitions = spec
+            .accounts
+            .iter()
+            .zip(&idl.accounts)
+            .map(|(spec_act, act)| Ok((discriminator(&spec_act.discriminator)?, act.clone())))
+            .collect::<Result<_>>()?;
+        idl.event_definitions = spec
+            .events
+            .iter()
+            .zip(idl.events.iter().flatten())
+            .map(|(spec_event, event)| {
+                Ok((discriminator(&spec_event.discriminator)?, event.clone()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(idl)
+    }
+}
+
+fn discriminator(bytes: &[u8]) -> Result<Discriminator> {
+    bytes.try_into().map_err(|_| {
+        anyhow!(
+            "Only 8 byte discriminators are supported, found {} bytes",
+            bytes.len()
+        )
+    })
+}
+
+fn docs(docs: &[String]) -> Option<Vec<String>> {
+    (!docs.is_empty()).then(|| docs.to_vec())
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::discriminator::{account_discriminator, ix_discriminator};
+    use serde_json::json;
+    use solana_program::pubkey::Pubkey;
+    use solana_sdk::account::Account;
+
+    #[test]
+    fn spec_idls_are_detected_and_converted() {
+        let owner = Pubkey::new_unique();
+        let spec = json!({
+            "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "metadata": { "name": "vault", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "deposit",
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                "accounts": [
+                    { "name": "owner", "writable": true, "signer": true },
+                    { "name": "vault", "accounts": [{ "name": "state", "writable": true }] },
+                ],
+                "args": [
+                    { "name": "amount", "type": "u64" },
+                    { "name": "memo", "type": { "option": "string" } },
+                ],
+            }],
+            "accounts": [{
+                "name": "Vault",
+                "discriminator": account_discriminator("Vault"),
+            }],
+            "types": [
+                {
+                    "name": "Vault",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "owner", "type": "pubkey" },
+                            { "name": "limits", "type": { "defined": { "name": "Limits" } } },
+                        ],
+                    },
+                },
+                {
+                    "name": "Limits",
+                    "serialization": "bytemuck",
+                    "repr": { "kind": "c" },
+                    "type": { "kind": "struct", "fields": [{ "array": ["u16", 2] }] },
+                },
+            ],
+        });
+        assert!(is_spec_idl(&spec));
+        let idl = IdlWithDiscriminators::from_json(spec.to_string().as_bytes()).unwrap();
+        assert_eq!(idl.name, "vault");
+        assert_eq!(
+            idl.metadata.as_ref().unwrap()["address"],
+            "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"
+        );
+        assert!(!is_spec_idl(&serde_json::to_value(&*idl).unwrap()));
+        assert_eq!(idl.types.len(), 1);
+
+        // The embedded discriminator is used, rather than one derived from the name.
+        let mut ix_data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        ix_data.extend(5u64.to_le_bytes());
+        ix_data.push(0);
+        let (ix, args) = idl.try_deserialize_instruction_data(&ix_data).unwrap();
+        assert_eq!(ix.name, "deposit");
+        assert_eq!(args, json!({ "amount": 5, "memo": null }));
+        assert!(idl
+            .try_deserialize_instruction_data(&ix_discriminator("deposit"))
+            .is_err());
+
+        let mut data = account_discriminator("Vault").to_vec();
+        data.extend(owner.to_bytes());
+        data.extend([1, 0, 2, 0]);
+        let account = Account {
+            data,
+            ..Default::default()
+        };
+        let (name, vault) = idl.try_deserialize_account(&account).unwrap();
+        assert_eq!(name, "Vault");
+        assert_eq!(
+            vault,
+            json!({ "owner": owner.to_string(), "limits": { "0": [1, 2] } })
+        );
+    }
+
+    #[test]
+    fn unknown_layouts_are_rejected() {
+        let spec = |serialization: Value, repr: Value| {
+            serde_json::from_value::<SpecIdl>(json!({
+                "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+                "metadata": { "name": "vault", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [],
+                "types": [{
+                    "name": "Padded",
+                    "serialization": serialization,
+                    "repr": repr,
+                    "type": { "kind": "struct", "fields": ["u8", "u64"] },
+                }],
+            }))
+            .unwrap()
+        };
+        let packed = json!({ "kind": "c", "packed": true });
+        assert!(spec(json!("bytemuckunsafe"), packed).to_legacy().is_ok());
+
+        let err = spec(json!("bytemuckunsafe"), json!({ "kind": "c" }))
+            .to_legacy()
+            .unwrap_err();
+        assert!(err.to_string().contains("padding is unknown"), "{}", err);
+        let err = spec(json!({ "custom": "bincode" }), Value::Null)
+            .to_legacy()
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("custom serialization bincode"),
+            "{}",
+            err
+        );
+    }
+}
@@ -168,6 +168,10 @@ impl IdlWithDiscriminators {
 
     /// Deserialize an enum variant,
     /// whether it is a struct variant, a tuple variant, or unit variant.
+    ///
+    /// Every kind of variant is given as `{"name": <variant>, "fields": <object, array or null>}`,
+    /// the form [crate::serialize] accepts. Struct variants used to be given as just their fields,
+    /// so variants with the same field names couldn't be told apart or encoded again.
     pub fn deserialize_enum_variant(
         &self,
         name: &str,
@@ -177,9 +181,10 @@ impl IdlWithDiscriminators {
         if let Some(enum_fields) = fields {
             match enum_fields {
                 // A variant with struct fields.
-                EnumFields::Named(idl_fields) => {
-                    Ok(self.deserialize_named_fields(idl_fields, data)?)
-                }
+                EnumFields::Named(idl_fields) => Ok(json!({
+                    "name": name,
+                    "fields": self.deserialize_named_fields(idl_fields, data)?
+                })),
                 // A variant with unnamed tuple fields.
                 EnumFields::Tuple(idl_types) => {
                     let deserialized = idl_types
@@ -201,3 +206,56 @@ impl IdlWithDiscriminators {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_syn::idl::types::Idl;
+
+    #[test]
+    fn struct_variants_keep_their_name() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "limits",
+            "instructions": [],
+            "types": [{
+                "name": "Limit",
+                "type": { "kind": "enum", "variants": [
+                    { "name": "Off" },
+                    { "name": "Capped", "fields": [{ "name": "max", "type": "u64" }] },
+                    { "name": "Floored", "fields": [{ "name": "max", "type": "u64" }] },
+                    { "name": "Fixed", "fields": ["u8"] },
+                ]},
+            }],
+        }))
+        .unwrap();
+        let idl = IdlWithDiscriminators::new(idl);
+        let limit = idl.types[0].clone();
+        let decode = |data: &[u8]| {
+            idl.deserialize_struct_or_enum(&limit, &mut &data[..])
+                .unwrap()
+        };
+
+        let (mut capped, mut floored) = (vec![1], vec![2]);
+        capped.extend(3u64.to_le_bytes());
+        floored.extend(3u64.to_le_bytes());
+        // Previously both decoded to `{"max": 3}`.
+        assert_eq!(
+            decode(&capped),
+            json!({ "name": "Capped", "fields": { "max": 3 } })
+        );
+        assert_eq!(
+            decode(&floored),
+            json!({ "name": "Floored", "fields": { "max": 3 } })
+        );
+
+        // Tuple and unit variants are unchanged.
+        assert_eq!(decode(&[3, 7]), json!({ "name": "Fixed", "fields": [7] }));
+        assert_eq!(decode(&[0]), json!({ "name": "Off", "fields": null }));
+
+        let mut encoded = vec![];
+        idl.serialize_struct_or_enum(&limit, &decode(&floored), &mut encoded)
+            .unwrap();
+        assert_eq!(encoded, floored);
+    }
+}
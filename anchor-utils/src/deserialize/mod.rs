@@ -1,17 +1,31 @@
 use anchor_syn::idl::types::Idl;
+use serde::Serialize;
+use serde_json::Value;
+use solana_devtools_errors::resolver::ErrorResolver;
 use solana_program::pubkey::Pubkey;
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::OnceLock;
 
 pub mod account;
 #[cfg(feature = "client")]
 pub mod client;
+pub mod decoder;
+pub mod diff;
 pub mod discriminator;
+#[cfg(feature = "client")]
+pub mod history;
 pub mod idl;
+pub mod idl_dir;
+pub mod idl_spec;
 pub mod idl_types;
+pub mod normalize;
+pub mod token_account;
 pub mod transaction;
 
+pub use decoder::{AccountDecoder, DecoderRegistry, InstructionDecoder};
 pub use idl::IdlWithDiscriminators;
+pub use normalize::JsonNormalization;
 
 /// Wraps client calls and optionally caches the IDLs that it fetches.
 /// This is the preferred means of fetching on-chain IDLs.
@@ -23,7 +37,16 @@ pub use idl::IdlWithDiscriminators;
 /// of program IDL accounts. These are found on chain, and they store
 /// an Anchor IDL JSON file in compressed form.
 pub struct AnchorDeserializer {
+    /// Add IDLs with [AnchorDeserializer::cache_idl], so that the
+    /// [AnchorDeserializer::error_resolver] is rebuilt to include them.
     pub idl_cache: HashMap<Pubkey, IdlWithDiscriminators>,
+    /// Applied by [AnchorDeserializer::to_json]. When enabled, cached IDLs are also
+    /// tried in a deterministic order.
+    pub normalization: JsonNormalization,
+    /// Decoders for programs that have no IDL, consulted before reporting an unknown program.
+    pub decoders: DecoderRegistry,
+    /// Built from `idl_cache` on first use, and cleared whenever an IDL is cached.
+    error_resolver: OnceLock<ErrorResolver>,
 }
 
 impl AnchorDeserializer {
@@ -32,6 +55,9 @@ impl AnchorDeserializer {
     pub fn new() -> Self {
         Self {
             idl_cache: HashMap::new(),
+            normalization: JsonNormalization::default(),
+            decoders: DecoderRegistry::new(),
+            error_resolver: OnceLock::new(),
         }
     }
 
@@ -40,7 +66,23 @@ impl AnchorDeserializer {
             idls.into_iter()
                 .map(|(pubkey, idl)| (pubkey, IdlWithDiscriminators::new(idl))),
         );
-        Self { idl_cache }
+        Self {
+            idl_cache,
+            normalization: JsonNormalization::default(),
+            decoders: DecoderRegistry::new(),
+            error_resolver: OnceLock::new(),
+        }
+    }
+
+    /// Sort JSON object keys and write pubkeys as base58 in output, see [JsonNormalization::stable].
+    pub fn with_stable_output(mut self) -> Self {
+        self.normalization = JsonNormalization::stable();
+        self
+    }
+
+    /// Serialize any decoded output, applying the configured [JsonNormalization].
+    pub fn to_json<T: Serialize + ?Sized>(&self, value: &T) -> serde_json::Result<Value> {
+        self.normalization.to_value(value)
     }
 
     pub fn cache_idl(
@@ -49,6 +91,7 @@ impl AnchorDeserializer {
         idl: IdlWithDiscriminators,
     ) -> &IdlWithDiscriminators {
         self.idl_cache.insert(program_id, idl);
+        self.error_resolver = OnceLock::new();
         self.idl_cache.get(&program_id).unwrap()
     }
 
@@ -61,4 +104,38 @@ impl AnchorDeserializer {
         self.cache_idl(program_id, idl);
         Ok(self.idl_cache.get(&program_id).unwrap())
     }
+
+    /// Decode instructions of a program that has no IDL.
+    pub fn register_instruction_decoder(
+        &mut self,
+        program_id: Pubkey,
+        program_name: impl Into<String>,
+        decoder: impl InstructionDecoder + 'static,
+    ) {
+        self.decoders
+            .register_instruction_decoder(program_id, program_name, decoder);
+    }
+
+    /// Decode accounts owned by a program that has no IDL.
+    pub fn register_account_decoder(
+        &mut self,
+        program_id: Pubkey,
+        program_name: impl Into<String>,
+        decoder: impl AccountDecoder + 'static,
+    ) {
+        self.decoders
+            .register_account_decoder(program_id, program_name, decoder);
+    }
+
+    /// Resolves the custom errors of every cached IDL's program.
+    /// Built once, and reused until the next call to [AnchorDeserializer::cache_idl].
+    pub fn error_resolver(&self) -> &ErrorResolver {
+        self.error_resolver.get_or_init(|| {
+            let mut resolver = ErrorResolver::new();
+            for (program_id, idl) in &self.idl_cache {
+                resolver.register(*program_id, idl.program_errors());
+            }
+            resolver
+        })
+    }
 }
@@ -0,0 +1,354 @@
+//! Normalization of decoded JSON, so that output is byte-stable across runs and SDK versions,
+//! e.g. to hash or diff decoded artifacts in CI.
+//!
+//! Fields the IDL types as `publicKey` are always decoded to base58 strings. Values serialized
+//! by [JsonNormalization::to_value] can also hold a [Pubkey], whose derived `Serialize` writes
+//! a 32-byte array. [JsonNormalization::canonical_pubkeys] writes those as base58 strings too,
+//! telling them apart by type, so other 32-byte arrays, such as hashes, are left as is.
+use serde::ser::{
+    Error as _, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant, Serializer,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use solana_program::pubkey::Pubkey;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonNormalization {
+    /// Sort object keys, rather than keeping the order of the IDL or struct definition.
+    pub sort_keys: bool,
+    /// Write a serialized [Pubkey] as base58, rather than as an array of its bytes.
+    /// Only applies to [Self::to_value], since a [Value] can't tell pubkeys from other arrays.
+    pub canonical_pubkeys: bool,
+}
+
+impl JsonNormalization {
+    /// All normalizations enabled.
+    pub fn stable() -> Self {
+        Self {
+            sort_keys: true,
+            canonical_pubkeys: true,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.sort_keys || self.canonical_pubkeys
+    }
+
+    pub fn to_value<T: Serialize + ?Sized>(&self, value: &T) -> serde_json::Result<Value> {
+        let value = if self.canonical_pubkeys {
+            value.serialize(PubkeysAsBase58(serde_json::value::Serializer))?
+        } else {
+            serde_json::to_value(value)?
+        };
+        Ok(self.normalize(value))
+    }
+
+    pub fn normalize(&self, value: Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let mut entries: Vec<(String, Value)> = map
+                    .into_iter()
+                    .map(|(key, value)| (key, self.normalize(value)))
+                    .collect();
+                if self.sort_keys {
+                    entries.sort_by(|a, b| a.0.cmp(&b.0));
+                }
+                Value::Object(Map::from_iter(entries))
+            }
+            Value::Array(values) => {
+                Value::Array(values.into_iter().map(|v| self.normalize(v)).collect())
+            }
+            value => value,
+        }
+    }
+}
+
+/// Serializes like the wrapped serializer, except that a [Pubkey] is written as base58.
+struct PubkeysAsBase58<S>(S);
+
+/// Serializes a nested value with [PubkeysAsBase58].
+struct Canonical<'a, T: ?Sized>(&'a T);
+
+impl<T: Serialize + ?Sized> Serialize for Canonical<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(PubkeysAsBase58(serializer))
+    }
+}
+
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(fn $method(self, $($arg: $ty),*) -> Result<Self::Ok, Self::Error> {
+            self.0.$method($($arg),*)
+        })*
+    };
+}
+
+impl<S: Serializer> Serializer for PubkeysAsBase58<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = PubkeysAsBase58<S::SerializeSeq>;
+    type SerializeTuple = PubkeysAsBase58<S::SerializeTuple>;
+    type SerializeTupleStruct = PubkeysAsBase58<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = PubkeysAsBase58<S::SerializeTupleVariant>;
+    type SerializeMap = PubkeysAsBase58<S::SerializeMap>;
+    type SerializeStruct = PubkeysAsBase58<S::SerializeStruct>;
+    type SerializeStructVariant = PubkeysAsBase58<S::SerializeStructVariant>;
+
+    forward! {
+        serialize_bool(v: bool);
+        serialize_i8(v: i8);
+        serialize_i16(v: i16);
+        serialize_i32(v: i32);
+        serialize_i64(v: i64);
+        serialize_i128(v: i128);
+        serialize_u8(v: u8);
+        serialize_u16(v: u16);
+        serialize_u32(v: u32);
+        serialize_u64(v: u64);
+        serialize_u128(v: u128);
+        serialize_f32(v: f32);
+        serialize_f64(v: f64);
+        serialize_char(v: char);
+        serialize_str(v: &str);
+        serialize_bytes(v: &[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(name: &'static str);
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str);
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+        self.0.serialize_some(&Canonical(value))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        if name == "Pubkey" {
+            // Any other newtype named `Pubkey` won't parse as 32 bytes, and is kept as is.
+            if let Ok(bytes) = serde_json::from_value::<[u8; 32]>(
+                serde_json::to_value(value).map_err(S::Error::custom)?,
+            ) {
+                return self.0.serialize_str(&Pubkey::from(bytes).to_string());
+            }
+        }
+        self.0.serialize_newtype_struct(name, &Canonical(value))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        self.0
+            .serialize_newtype_variant(name, index, variant, &Canonical(value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        self.0.serialize_seq(len).map(PubkeysAsBase58)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        self.0.serialize_tuple(len).map(PubkeysAsBase58)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, S::Error> {
+        self.0
+            .serialize_tuple_struct(name, len)
+            .map(PubkeysAsBase58)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        self.0
+            .serialize_tuple_variant(name, index, variant, len)
+            .map(PubkeysAsBase58)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        self.0.serialize_map(len).map(PubkeysAsBase58)
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, S::Error> {
+        self.0.serialize_struct(name, len).map(PubkeysAsBase58)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        self.0
+            .serialize_struct_variant(name, index, variant, len)
+            .map(PubkeysAsBase58)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+}
+
+macro_rules! compound {
+    ($($trait:ident::$method:ident($($key:ident)?);)*) => {
+        $(impl<S: $trait> $trait for PubkeysAsBase58<S> {
+            type Ok = S::Ok;
+            type Error = S::Error;
+
+            fn $method<T: Serialize + ?Sized>(
+                &mut self,
+                $($key: &'static str,)?
+                value: &T,
+            ) -> Result<(), S::Error> {
+                self.0.$method($($key,)? &Canonical(value))
+            }
+
+            fn end(self) -> Result<S::Ok, S::Error> {
+                self.0.end()
+            }
+        })*
+    };
+}
+
+compound! {
+    SerializeSeq::serialize_element();
+    SerializeTuple::serialize_element();
+    SerializeTupleStruct::serialize_field();
+    SerializeTupleVariant::serialize_field();
+    SerializeStruct::serialize_field(key);
+    SerializeStructVariant::serialize_field(key);
+}
+
+impl<S: SerializeMap> SerializeMap for PubkeysAsBase58<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), S::Error> {
+        self.0.serialize_key(&Canonical(key))
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), S::Error> {
+        self.0.serialize_value(&Canonical(value))
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.0.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::IdlWithDiscriminators;
+    use anchor_syn::idl::types::{Idl, IdlField, IdlType};
+    use borsh::BorshSerialize;
+    use serde_json::json;
+    use solana_program::pubkey::Pubkey;
+
+    #[test]
+    fn stable_output_is_independent_of_key_order() {
+        let a = json!({ "amount": 1, "nested": { "z": [3, 2, 1], "a": "x" } });
+        let b = json!({ "nested": { "a": "x", "z": [3, 2, 1] }, "amount": 1 });
+        let stable = JsonNormalization::stable();
+        let (a, b) = (stable.normalize(a), stable.normalize(b));
+        assert_eq!(
+            serde_json::to_string(&a).unwrap(),
+            serde_json::to_string(&b).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&a).unwrap(),
+            r#"{"amount":1,"nested":{"a":"x","z":[3,2,1]}}"#
+        );
+    }
+
+    #[test]
+    fn only_idl_pubkeys_are_encoded_as_pubkeys() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "keys",
+            "instructions": [],
+        }))
+        .unwrap();
+        let idl = IdlWithDiscriminators::new(idl);
+        let fields = [
+            IdlField {
+                name: "owner".to_string(),
+                docs: None,
+                ty: IdlType::PublicKey,
+            },
+            IdlField {
+                name: "hash".to_string(),
+                docs: None,
+                ty: IdlType::Array(Box::new(IdlType::U8), 32),
+            },
+            IdlField {
+                name: "label".to_string(),
+                docs: None,
+                ty: IdlType::String,
+            },
+        ];
+        let owner = Pubkey::new_unique();
+        let hash = Pubkey::new_unique().to_bytes();
+        let label = format!(" {owner} ");
+        let mut data = owner.to_bytes().to_vec();
+        data.extend(hash);
+        data.extend(label.try_to_vec().unwrap());
+
+        let decoded = idl
+            .deserialize_named_fields(&fields, &mut &data[..])
+            .unwrap();
+        let expected = json!({ "owner": owner.to_string(), "hash": hash, "label": label });
+        assert_eq!(JsonNormalization::stable().normalize(decoded), expected);
+    }
+
+    #[test]
+    fn serialized_pubkeys_are_written_as_base58() {
+        #[derive(Serialize)]
+        struct Holder {
+            owner: Pubkey,
+            delegate: Option<Pubkey>,
+            signers: Vec<Pubkey>,
+            hash: [u8; 32],
+        }
+        let holder = Holder {
+            owner: Pubkey::new_unique(),
+            delegate: Some(Pubkey::new_unique()),
+            signers: vec![Pubkey::new_unique()],
+            hash: Pubkey::new_unique().to_bytes(),
+        };
+        let expected = json!({
+            "owner": holder.owner.to_string(),
+            "delegate": holder.delegate.unwrap().to_string(),
+            "signers": [holder.signers[0].to_string()],
+            "hash": holder.hash,
+        });
+        let stable = JsonNormalization::stable();
+        assert_eq!(stable.to_value(&holder).unwrap(), expected);
+
+        let raw = JsonNormalization {
+            canonical_pubkeys: false,
+            ..stable
+        };
+        let value = raw.to_value(&holder).unwrap();
+        assert_eq!(value["owner"], json!(holder.owner.to_bytes()));
+    }
+}
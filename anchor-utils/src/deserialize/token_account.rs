@@ -0,0 +1,125 @@
+//! SPL Token and Token-2022 account decoding, including Token-2022 extensions,
+//! for token program accounts that no IDL describes.
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use solana_account_decoder::parse_token::{parse_token, TokenAccountType, UiAccountState};
+use solana_account_decoder::parse_token_extension::parse_extension;
+use solana_program::program_option::COption;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::Account;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+
+/// The program name of a token program, as used in decoded output.
+pub fn token_program_name(program_id: &Pubkey) -> Option<&'static str> {
+    if *program_id == spl_token::ID {
+        Some("spl_token_program")
+    } else if *program_id == spl_token_2022::ID {
+        Some("spl_token_2022_program")
+    } else {
+        None
+    }
+}
+
+/// Decode a mint, token account or multisig owned by either token program, returning
+/// its type and its fields in the shape of the RPC's `jsonParsed` encoding.
+/// Token account amounts are left in base units, as the mint's decimals are unknown.
+pub fn try_deserialize_token_account(account: &Account) -> Result<(String, Value)> {
+    if token_program_name(&account.owner).is_none() {
+        return Err(anyhow!("{} is not a token program", account.owner));
+    }
+    if let Ok(token_account) =
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+    {
+        let base = &token_account.base;
+        let extensions: Vec<_> = token_account
+            .get_extension_types()
+            .unwrap_or_default()
+            .iter()
+            .map(|extension_type| parse_extension(extension_type, &token_account))
+            .collect();
+        let pubkey = |pubkey: COption<Pubkey>| match pubkey {
+            COption::Some(pubkey) => Some(pubkey.to_string()),
+            COption::None => None,
+        };
+        return Ok((
+            "account".to_string(),
+            json!({
+                "mint": base.mint.to_string(),
+                "owner": base.owner.to_string(),
+                "amount": base.amount.to_string(),
+                "delegate": pubkey(base.delegate),
+                "state": UiAccountState::from(base.state),
+                "isNative": base.is_native(),
+                "delegatedAmount": base.delegated_amount.to_string(),
+                "closeAuthority": pubkey(base.close_authority),
+                "extensions": extensions,
+            }),
+        ));
+    }
+    match parse_token(&account.data, None)? {
+        TokenAccountType::Mint(mint) => Ok(("mint".to_string(), serde_json::to_value(mint)?)),
+        TokenAccountType::Multisig(multisig) => {
+            Ok(("multisig".to_string(), serde_json::to_value(multisig)?))
+        }
+        TokenAccountType::Account(account) => {
+            Ok(("account".to_string(), serde_json::to_value(account)?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_token_2022::extension::{
+        memo_transfer::MemoTransfer, ExtensionType, StateWithExtensionsMut,
+    };
+    use spl_token_2022::state::AccountState;
+
+    #[test]
+    fn token_2022_accounts_are_decoded_with_extensions() {
+        let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&[
+            ExtensionType::MemoTransfer,
+        ])
+        .unwrap();
+        let mut data = vec![0; len];
+        let mut state =
+            StateWithExtensionsMut::<spl_token_2022::state::Account>::unpack_uninitialized(
+                &mut data,
+            )
+            .unwrap();
+        let (mint, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        state.base.mint = mint;
+        state.base.owner = owner;
+        state.base.amount = 1_000_000_000_000;
+        state.base.state = AccountState::Initialized;
+        state.pack_base();
+        state.init_account_type().unwrap();
+        state
+            .init_extension::<MemoTransfer>(true)
+            .unwrap()
+            .require_incoming_transfer_memos = true.into();
+
+        let account = Account {
+            data,
+            owner: spl_token_2022::ID,
+            ..Account::default()
+        };
+        let (account_type, value) = try_deserialize_token_account(&account).unwrap();
+        assert_eq!(account_type, "account");
+        assert_eq!(value["mint"], json!(mint.to_string()));
+        assert_eq!(value["amount"], json!("1000000000000"));
+        assert_eq!(
+            value["extensions"],
+            json!([{
+                "extension": "memoTransfer",
+                "state": { "requireIncomingTransferMemos": true },
+            }])
+        );
+
+        let not_token = Account {
+            owner: Pubkey::new_unique(),
+            ..account
+        };
+        assert!(try_deserialize_token_account(&not_token).is_err());
+    }
+}
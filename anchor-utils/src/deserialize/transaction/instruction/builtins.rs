@@ -1,10 +1,20 @@
 use anyhow::Result;
+use serde::de::DeserializeOwned;
 use solana_sdk::{
+    address_lookup_table::{
+        self, instruction::ProgramInstruction as AddressLookupTableInstruction,
+    },
     borsh0_10::try_from_slice_unchecked,
+    bpf_loader_upgradeable,
     compute_budget::{self, ComputeBudgetInstruction},
     instruction::Instruction,
+    loader_upgradeable_instruction::UpgradeableLoaderInstruction,
+    loader_v4,
+    loader_v4_instruction::LoaderV4Instruction,
+    stake::{self, instruction::StakeInstruction},
     system_instruction::SystemInstruction,
     system_program,
+    vote::{self, instruction::VoteInstruction},
 };
 
 use super::DeserializedInstruction;
@@ -47,6 +57,84 @@ pub fn system_instruction_name(ix: &SystemInstruction) -> &'static str {
     }
 }
 
+/// Deserializes the bincode-encoded instruction data of a native program.
+pub fn native_program_instruction<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+    Ok(bincode1::deserialize(data)?)
+}
+
+pub fn stake_instruction_name(ix: &StakeInstruction) -> &'static str {
+    match ix {
+        StakeInstruction::Initialize(..) => "initialize",
+        StakeInstruction::Authorize(..) => "authorize",
+        StakeInstruction::DelegateStake => "delegate_stake",
+        StakeInstruction::Split(_) => "split",
+        StakeInstruction::Withdraw(_) => "withdraw",
+        StakeInstruction::Deactivate => "deactivate",
+        StakeInstruction::SetLockup(_) => "set_lockup",
+        StakeInstruction::Merge => "merge",
+        StakeInstruction::AuthorizeWithSeed(_) => "authorize_with_seed",
+        StakeInstruction::InitializeChecked => "initialize_checked",
+        StakeInstruction::AuthorizeChecked(_) => "authorize_checked",
+        StakeInstruction::AuthorizeCheckedWithSeed(_) => "authorize_checked_with_seed",
+        StakeInstruction::SetLockupChecked(_) => "set_lockup_checked",
+        StakeInstruction::GetMinimumDelegation => "get_minimum_delegation",
+        StakeInstruction::DeactivateDelinquent => "deactivate_delinquent",
+        StakeInstruction::Redelegate => "redelegate",
+    }
+}
+
+pub fn vote_instruction_name(ix: &VoteInstruction) -> &'static str {
+    match ix {
+        VoteInstruction::InitializeAccount(_) => "initialize_account",
+        VoteInstruction::Authorize(..) => "authorize",
+        VoteInstruction::Vote(_) => "vote",
+        VoteInstruction::Withdraw(_) => "withdraw",
+        VoteInstruction::UpdateValidatorIdentity => "update_validator_identity",
+        VoteInstruction::UpdateCommission(_) => "update_commission",
+        VoteInstruction::VoteSwitch(..) => "vote_switch",
+        VoteInstruction::AuthorizeChecked(_) => "authorize_checked",
+        VoteInstruction::UpdateVoteState(_) => "update_vote_state",
+        VoteInstruction::UpdateVoteStateSwitch(..) => "update_vote_state_switch",
+        VoteInstruction::AuthorizeWithSeed(_) => "authorize_with_seed",
+        VoteInstruction::AuthorizeCheckedWithSeed(_) => "authorize_checked_with_seed",
+        VoteInstruction::CompactUpdateVoteState(_) => "compact_update_vote_state",
+        VoteInstruction::CompactUpdateVoteStateSwitch(..) => "compact_update_vote_state_switch",
+    }
+}
+
+pub fn upgradeable_loader_instruction_name(ix: &UpgradeableLoaderInstruction) -> &'static str {
+    match ix {
+        UpgradeableLoaderInstruction::InitializeBuffer => "initialize_buffer",
+        UpgradeableLoaderInstruction::Write { .. } => "write",
+        UpgradeableLoaderInstruction::DeployWithMaxDataLen { .. } => "deploy_with_max_data_len",
+        UpgradeableLoaderInstruction::Upgrade => "upgrade",
+        UpgradeableLoaderInstruction::SetAuthority => "set_authority",
+        UpgradeableLoaderInstruction::Close => "close",
+        UpgradeableLoaderInstruction::ExtendProgram { .. } => "extend_program",
+        UpgradeableLoaderInstruction::SetAuthorityChecked => "set_authority_checked",
+    }
+}
+
+pub fn loader_v4_instruction_name(ix: &LoaderV4Instruction) -> &'static str {
+    match ix {
+        LoaderV4Instruction::Write { .. } => "write",
+        LoaderV4Instruction::Truncate { .. } => "truncate",
+        LoaderV4Instruction::Deploy => "deploy",
+        LoaderV4Instruction::Retract => "retract",
+        LoaderV4Instruction::TransferAuthority => "transfer_authority",
+    }
+}
+
+pub fn address_lookup_table_instruction_name(ix: &AddressLookupTableInstruction) -> &'static str {
+    match ix {
+        AddressLookupTableInstruction::CreateLookupTable { .. } => "create_lookup_table",
+        AddressLookupTableInstruction::FreezeLookupTable => "freeze_lookup_table",
+        AddressLookupTableInstruction::ExtendLookupTable { .. } => "extend_lookup_table",
+        AddressLookupTableInstruction::DeactivateLookupTable => "deactivate_lookup_table",
+        AddressLookupTableInstruction::CloseLookupTable => "close_lookup_table",
+    }
+}
+
 impl DeserializedInstruction {
     pub fn try_compute_budget_instruction(ix: &Instruction, ix_num: u8) -> Option<Self> {
         if ix.program_id == compute_budget::ID {
@@ -81,4 +169,125 @@ impl DeserializedInstruction {
         }
         None
     }
+
+    /// Decodes instructions of the stake, vote, upgradeable loader, loader-v4
+    /// and address lookup table programs.
+    pub fn try_native_program_instruction(ix: &Instruction, ix_num: u8) -> Option<Self> {
+        let (program_name, name, ix_data) = if ix.program_id == stake::program::ID {
+            let ix = native_program_instruction::<StakeInstruction>(&ix.data).ok()?;
+            (
+                "stake_program",
+                stake_instruction_name(&ix),
+                serde_json::to_value(&ix),
+            )
+        } else if ix.program_id == vote::program::ID {
+            let ix = native_program_instruction::<VoteInstruction>(&ix.data).ok()?;
+            (
+                "vote_program",
+                vote_instruction_name(&ix),
+                serde_json::to_value(&ix),
+            )
+        } else if ix.program_id == bpf_loader_upgradeable::ID {
+            let ix = native_program_instruction::<UpgradeableLoaderInstruction>(&ix.data).ok()?;
+            (
+                "bpf_loader_upgradeable_program",
+                upgradeable_loader_instruction_name(&ix),
+                serde_json::to_value(&ix),
+            )
+        } else if ix.program_id == loader_v4::ID {
+            let ix = native_program_instruction::<LoaderV4Instruction>(&ix.data).ok()?;
+            (
+                "loader_v4_program",
+                loader_v4_instruction_name(&ix),
+                serde_json::to_value(&ix),
+            )
+        } else if ix.program_id == address_lookup_table::program::ID {
+            let ix = native_program_instruction::<AddressLookupTableInstruction>(&ix.data).ok()?;
+            (
+                "address_lookup_table_program",
+                address_lookup_table_instruction_name(&ix),
+                serde_json::to_value(&ix),
+            )
+        } else {
+            return None;
+        };
+        Some(DeserializedInstruction::ok(
+            ix.program_id,
+            program_name.to_string(),
+            ix_num,
+            name.to_string(),
+            ix_data.ok()?,
+            vec![],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::transaction::instruction::DeserializedInstructionData;
+    use serde_json::json;
+    use solana_sdk::{
+        bpf_loader_upgradeable::set_upgrade_authority, pubkey::Pubkey, vote::state::VoteStateUpdate,
+    };
+
+    fn decoded_name(ix: &Instruction) -> (String, String) {
+        let ix = DeserializedInstruction::try_native_program_instruction(ix, 0).unwrap();
+        match ix.parsed {
+            DeserializedInstructionData::Ok { name, .. } => (ix.program_name, name),
+            _ => panic!("instruction was not decoded"),
+        }
+    }
+
+    #[test]
+    fn native_program_instructions_are_named() {
+        let (stake_account, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let split =
+            stake::instruction::split(&stake_account, &authority, 10, &Pubkey::new_unique());
+        let ix = DeserializedInstruction::try_native_program_instruction(split.last().unwrap(), 0)
+            .unwrap();
+        assert_eq!(ix.program_name, "stake_program");
+        match ix.parsed {
+            DeserializedInstructionData::Ok { name, data, .. } => {
+                assert_eq!(name, "split");
+                assert_eq!(data, json!({ "Split": 10 }));
+            }
+            _ => panic!("instruction was not decoded"),
+        }
+
+        let ix = vote::instruction::compact_update_vote_state(
+            &Pubkey::new_unique(),
+            &authority,
+            VoteStateUpdate::from(vec![(1, 1)]),
+        );
+        assert_eq!(
+            decoded_name(&ix),
+            (
+                "vote_program".to_string(),
+                "compact_update_vote_state".to_string()
+            )
+        );
+
+        let ix = set_upgrade_authority(&Pubkey::new_unique(), &authority, None);
+        assert_eq!(
+            decoded_name(&ix),
+            (
+                "bpf_loader_upgradeable_program".to_string(),
+                "set_authority".to_string()
+            )
+        );
+
+        let (ix, _) =
+            address_lookup_table::instruction::create_lookup_table(authority, authority, 1);
+        assert_eq!(
+            decoded_name(&ix),
+            (
+                "address_lookup_table_program".to_string(),
+                "create_lookup_table".to_string()
+            )
+        );
+
+        let ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[0], vec![]);
+        assert!(DeserializedInstruction::try_native_program_instruction(&ix, 0).is_none());
+    }
 }
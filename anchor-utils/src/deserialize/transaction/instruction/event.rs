@@ -0,0 +1,343 @@
+//! Anchor events, either logged with `emit!` as `Program data:` logs, or emitted with
+//! `emit_cpi!` as self-CPI inner instructions signed by the program's event authority PDA.
+use crate::deserialize::discriminator::partition_discriminator_from_data;
+use crate::deserialize::{AnchorDeserializer, IdlWithDiscriminators};
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_syn::idl::types::IdlField;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_devtools_errors::program_log::ProgramLog;
+use solana_devtools_serde::pubkey;
+use solana_devtools_tx::inner_instructions::{DecompiledMessageAndInnerIx, HistoricalTransaction};
+use solana_program::instruction::Instruction;
+use solana_program::message::VersionedMessage;
+use solana_program::pubkey::Pubkey;
+use std::collections::BTreeMap;
+
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+/// The PDA that signs a program's `emit_cpi!` self-invocations.
+pub fn event_authority(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], program_id).0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeserializedEvent {
+    #[serde(with = "pubkey")]
+    pub program_id: Pubkey,
+    pub name: String,
+    pub data: Value,
+    /// The top-level instruction during which the event was emitted.
+    pub instruction_index: u8,
+    /// The index of the inner instruction that emitted the event with `emit_cpi!`,
+    /// or `None` if the event was logged with `emit!`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inner_instruction_index: Option<u8>,
+}
+
+impl IdlWithDiscriminators {
+    /// Deserialize the data of an `emit_cpi!` instruction,
+    /// i.e. the event instruction tag followed by the event's discriminator and fields.
+    pub fn try_deserialize_cpi_event(&self, ix_data: &[u8]) -> anyhow::Result<(String, Value)> {
+        let event_data = ix_data.strip_prefix(&EVENT_IX_TAG_LE).ok_or(anyhow!(
+            "Instruction data does not begin with the event tag"
+        ))?;
+        self.try_deserialize_event(event_data)
+    }
+
+    /// Deserialize an event's discriminator and fields, as logged by `emit!`.
+    pub fn try_deserialize_event(&self, event_data: &[u8]) -> anyhow::Result<(String, Value)> {
+        let (discriminator, data) = partition_discriminator_from_data(event_data);
+        let event = self
+            .get_event_definition(&discriminator)
+            .ok_or(anyhow!("Could not match event against any discriminator"))?;
+        let fields: Vec<IdlField> = event
+            .fields
+            .iter()
+            .map(|field| IdlField {
+                name: field.name.clone(),
+                docs: None,
+                ty: field.ty.clone(),
+            })
+            .collect();
+        Ok((
+            event.name.clone(),
+            self.deserialize_named_fields(&fields, &mut &data[..])?,
+        ))
+    }
+}
+
+impl AnchorDeserializer {
+    /// Decode an inner instruction as an `emit_cpi!` event, if it invokes a program
+    /// with a cached IDL, and passes that program's event authority as its first account.
+    pub fn try_deserialize_cpi_event(
+        &self,
+        ix_num: usize,
+        inner_ix_num: usize,
+        ix: &Instruction,
+    ) -> Option<DeserializedEvent> {
+        if !ix.data.starts_with(&EVENT_IX_TAG_LE) {
+            return None;
+        }
+        if ix.accounts.first()?.pubkey != event_authority(&ix.program_id) {
+            return None;
+        }
+        let idl = self.idl_cache.get(&ix.program_id)?;
+        let (name, data) = idl.try_deserialize_cpi_event(&ix.data).ok()?;
+        Some(DeserializedEvent {
+            program_id: ix.program_id,
+            name,
+            data,
+            instruction_index: ix_num as u8,
+            inner_instruction_index: Some(inner_ix_num as u8),
+        })
+    }
+
+    /// Decode every event of a transaction, from programs with a cached IDL.
+    /// For each top-level instruction in turn, events logged with `emit!` come first,
+    /// followed by those emitted with `emit_cpi!`.
+    pub fn try_deserialize_events(&self, tx: &HistoricalTransaction) -> Vec<DeserializedEvent> {
+        let mut logged = self
+            .try_deserialize_logged_events(&tx.log_messages, &top_level_program_ids(&tx.message));
+        let decompiled = DecompiledMessageAndInnerIx::from(tx);
+        let mut events = vec![];
+        for ix_num in 0..decompiled.top_level_instructions.len() {
+            events.extend(logged.remove(&(ix_num as u8)).unwrap_or_default());
+            let inner = decompiled.inner_instructions.get(&(ix_num as u8));
+            events.extend(inner.into_iter().flatten().enumerate().filter_map(
+                |(inner_ix_num, ix)| self.try_deserialize_cpi_event(ix_num, inner_ix_num, ix),
+            ));
+        }
+        events
+    }
+
+    /// Decode the `Program data:` logs of programs with a cached IDL,
+    /// keyed by the top-level instruction that was executing.
+    /// Logs that don't match an event in the logging program's IDL are skipped.
+    ///
+    /// `program_ids` are the programs of the message's top-level instructions, in order.
+    /// Each top-level invocation in the logs is matched to the next instruction of the same
+    /// program, since precompiles like ed25519 and secp256k1 execute without logging.
+    pub fn try_deserialize_logged_events(
+        &self,
+        logs: &[String],
+        program_ids: &[Pubkey],
+    ) -> BTreeMap<u8, Vec<DeserializedEvent>> {
+        let mut events: BTreeMap<u8, Vec<DeserializedEvent>> = BTreeMap::new();
+        let mut invoked: Vec<Pubkey> = vec![];
+        let mut ix_num: Option<u8> = None;
+        let mut next_ix = 0;
+        for log in logs {
+            match ProgramLog::parse(log) {
+                ProgramLog::Data(data) => {
+                    let (Some(program_id), Some(ix_num)) = (invoked.last(), ix_num) else {
+                        continue;
+                    };
+                    let Some(idl) = self.idl_cache.get(program_id) else {
+                        continue;
+                    };
+                    // `emit!` logs a single base64 segment.
+                    let Some(Ok(event_data)) = data.split_whitespace().next().map(base64::decode)
+                    else {
+                        continue;
+                    };
+                    if let Ok((name, data)) = idl.try_deserialize_event(&event_data) {
+                        events.entry(ix_num).or_default().push(DeserializedEvent {
+                            program_id: *program_id,
+                            name,
+                            data,
+                            instruction_index: ix_num,
+                            inner_instruction_index: None,
+                        });
+                    }
+                }
+                ProgramLog::Invoke {
+                    program_id,
+                    stack_height,
+                } => {
+                    if stack_height == 1 {
+                        invoked.clear();
+                        ix_num = program_ids[next_ix..]
+                            .iter()
+                            .position(|id| *id == program_id)
+                            .map(|offset| (next_ix + offset) as u8);
+                        next_ix = ix_num.map_or(program_ids.len(), |n| n as usize + 1);
+                    }
+                    invoked.push(program_id);
+                }
+                ProgramLog::Success(_) | ProgramLog::Failed { .. } => {
+                    invoked.pop();
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// The programs invoked by a message's top-level instructions, in order.
+/// Program ids are never loaded from lookup tables, so the static keys suffice.
+pub(crate) fn top_level_program_ids(message: &VersionedMessage) -> Vec<Pubkey> {
+    message
+        .instructions()
+        .iter()
+        .map(|ix| message.static_account_keys()[ix.program_id_index as usize])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::discriminator::event_discriminator;
+    use anchor_syn::idl::types::Idl;
+    use serde_json::json;
+    use solana_program::instruction::AccountMeta;
+    use solana_program::message::Message;
+
+    fn events_idl() -> Idl {
+        serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "events",
+            "instructions": [],
+            "events": [{
+                "name": "Deposited",
+                "fields": [
+                    { "name": "amount", "type": "u64", "index": false },
+                    { "name": "owner", "type": "publicKey", "index": false },
+                ],
+            }],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn cpi_events_attach_to_owning_instruction() {
+        let program_id = Pubkey::new_unique();
+        let mut deser = AnchorDeserializer::new();
+        deser.cache_idl(program_id, events_idl().into());
+
+        let owner = Pubkey::new_unique();
+        let mut data = EVENT_IX_TAG_LE.to_vec();
+        data.extend(event_discriminator("Deposited"));
+        data.extend(42u64.to_le_bytes());
+        data.extend(owner.to_bytes());
+        let event_ix = Instruction::new_with_bytes(
+            program_id,
+            &data,
+            vec![AccountMeta::new_readonly(
+                event_authority(&program_id),
+                true,
+            )],
+        );
+        // The same data, without the event authority, is not an event.
+        let spoofed = Instruction::new_with_bytes(program_id, &data, vec![]);
+        let mut outer = Instruction::new_with_bytes(program_id, &[0; 8], vec![]);
+
+        let deserialized = deser
+            .try_deserialize_instruction(0, &mut outer, Some(vec![spoofed, event_ix]))
+            .unwrap();
+        let expected = json!({ "amount": 42, "owner": owner.to_string() });
+        assert_eq!(
+            deserialized.events,
+            vec![DeserializedEvent {
+                program_id,
+                name: "Deposited".to_string(),
+                data: expected.clone(),
+                instruction_index: 0,
+                inner_instruction_index: Some(1),
+            }]
+        );
+        let inner = deserialized.inner_instructions.unwrap();
+        assert!(matches!(
+            &inner[0].parsed,
+            super::super::DeserializedInstructionData::Err { .. }
+        ));
+        assert!(matches!(
+            &inner[1].parsed,
+            super::super::DeserializedInstructionData::Ok { name, data, .. }
+                if name == "Deposited" && *data == expected
+        ));
+    }
+
+    #[test]
+    fn logged_events_are_decoded_by_instruction() {
+        let program_id = Pubkey::new_unique();
+        let unknown_program = Pubkey::new_unique();
+        let mut deser = AnchorDeserializer::new();
+        deser.cache_idl(program_id, events_idl().into());
+
+        let owner = Pubkey::new_unique();
+        let mut data = event_discriminator("Deposited").to_vec();
+        data.extend(7u64.to_le_bytes());
+        data.extend(owner.to_bytes());
+        let logged = format!("Program data: {}", base64::encode(&data));
+        let logs = [
+            format!("Program {unknown_program} invoke [1]"),
+            logged.clone(),
+            format!("Program {unknown_program} success"),
+            format!("Program {program_id} invoke [1]"),
+            "Program log: Instruction: Deposit".to_string(),
+            format!("Program {unknown_program} invoke [2]"),
+            logged.clone(),
+            format!("Program {unknown_program} success"),
+            logged,
+            format!("Program {program_id} consumed 1000 of 200000 compute units"),
+            format!("Program {program_id} success"),
+        ]
+        .to_vec();
+        let message = Message::new(
+            &[
+                Instruction::new_with_bytes(unknown_program, &[], vec![]),
+                Instruction::new_with_bytes(program_id, &[0; 8], vec![]),
+            ],
+            Some(&Pubkey::new_unique()),
+        );
+        let mut tx = HistoricalTransaction::new(VersionedMessage::Legacy(message), None);
+        tx.log_messages = logs;
+
+        let expected = DeserializedEvent {
+            program_id,
+            name: "Deposited".to_string(),
+            data: json!({ "amount": 7, "owner": owner.to_string() }),
+            instruction_index: 1,
+            inner_instruction_index: None,
+        };
+        assert_eq!(deser.try_deserialize_events(&tx), vec![expected.clone()]);
+        let deserialized = deser.try_deserialize_transaction(tx).unwrap();
+        assert!(deserialized[0].events.is_empty());
+        assert_eq!(deserialized[1].events, vec![expected]);
+    }
+
+    #[test]
+    fn logged_events_skip_precompile_instructions() {
+        let program_id = Pubkey::new_unique();
+        let mut deser = AnchorDeserializer::new();
+        deser.cache_idl(program_id, events_idl().into());
+
+        let owner = Pubkey::new_unique();
+        let mut data = event_discriminator("Deposited").to_vec();
+        data.extend(7u64.to_le_bytes());
+        data.extend(owner.to_bytes());
+        // The ed25519 program executes without logging its invocation.
+        let logs = [
+            format!("Program {program_id} invoke [1]"),
+            format!("Program data: {}", base64::encode(&data)),
+            format!("Program {program_id} success"),
+        ]
+        .to_vec();
+        let message = Message::new(
+            &[
+                Instruction::new_with_bytes(solana_program::ed25519_program::id(), &[0], vec![]),
+                Instruction::new_with_bytes(program_id, &[0; 8], vec![]),
+            ],
+            Some(&Pubkey::new_unique()),
+        );
+        let mut tx = HistoricalTransaction::new(VersionedMessage::Legacy(message), None);
+        tx.log_messages = logs;
+
+        let events = deser.try_deserialize_events(&tx);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].instruction_index, 1);
+    }
+}
@@ -1,11 +1,13 @@
 pub mod account_metas;
 pub mod builtins;
 pub mod data;
+pub mod event;
 pub mod spl_programs;
 
 use crate::deserialize::AnchorDeserializer;
 pub use account_metas::*;
 use anyhow::Result;
+pub use event::{event_authority, DeserializedEvent};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use solana_devtools_serde::pubkey;
@@ -23,11 +25,27 @@ impl AnchorDeserializer {
         ix: &mut Instruction,
         inner_instructions: Option<Vec<Instruction>>,
     ) -> Result<DeserializedInstruction> {
-        // Try to deserialize the inner instructions up front.
+        // Try to deserialize the inner instructions up front,
+        // collecting any events emitted through self-CPI.
+        let mut events = vec![];
         let inner_ix = {
             let mut deserialized_inner_ix = vec![];
             if let Some(mut instructions) = inner_instructions {
                 for (inner_ix_num, inner_ix) in instructions.iter_mut().enumerate() {
+                    if let Some(event) =
+                        self.try_deserialize_cpi_event(ix_num, inner_ix_num, inner_ix)
+                    {
+                        deserialized_inner_ix.push(DeserializedInstruction::ok(
+                            inner_ix.program_id,
+                            self.idl_cache[&inner_ix.program_id].name.to_string(),
+                            inner_ix_num as u8,
+                            event.name.clone(),
+                            event.data.clone(),
+                            vec![],
+                        ));
+                        events.push(event);
+                        continue;
+                    }
                     deserialized_inner_ix.push(self.try_deserialize_instruction(
                         inner_ix_num,
                         inner_ix,
@@ -47,11 +65,18 @@ impl AnchorDeserializer {
         if let Some(ix) = DeserializedInstruction::try_token_program_instruction(ix, ix_num as u8) {
             return Ok(ix);
         }
+        if let Some(ix) = DeserializedInstruction::try_token_2022_instruction(ix, ix_num as u8) {
+            return Ok(ix);
+        }
         if let Some(ix) =
             DeserializedInstruction::try_associated_token_instruction(ix, ix_num as u8)
         {
             return Ok(ix);
         }
+        if let Some(ix) = DeserializedInstruction::try_native_program_instruction(ix, ix_num as u8)
+        {
+            return Ok(ix);
+        }
         // Get program ID, find IDL
         let idl = self.idl_cache.get(&ix.program_id);
         // Try fetching the IDL and deserializing.
@@ -93,6 +118,24 @@ impl AnchorDeserializer {
                     )
                 }
             }
+        } else if let Some((program_name, decoded)) = self.decoders.decode_instruction(ix) {
+            // Otherwise, a custom decoder may have been registered for the program
+            match decoded {
+                Ok((name, ix_data)) => DeserializedInstruction::ok(
+                    ix.program_id,
+                    program_name.to_string(),
+                    ix_num as u8,
+                    name,
+                    ix_data,
+                    vec![],
+                ),
+                Err(e) => DeserializedInstruction::err(
+                    ix.program_id,
+                    Some(program_name.to_string()),
+                    ix_num as u8,
+                    format!("{}", e),
+                ),
+            }
         } else {
             // If there's no IDL, we cannot deserialize
             DeserializedInstruction::err(
@@ -106,6 +149,7 @@ impl AnchorDeserializer {
         if !inner_ix.is_empty() {
             deserialized.inner_instructions = Some(inner_ix);
         }
+        deserialized.events = events;
         Ok(deserialized)
     }
 }
@@ -119,6 +163,10 @@ pub struct DeserializedInstruction {
     pub parsed: DeserializedInstructionData,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub inner_instructions: Option<Vec<DeserializedInstruction>>,
+    /// Events emitted by this instruction or its inner instructions. Events logged with `emit!`
+    /// come first, and are only decoded from historical transactions, which carry logs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<DeserializedEvent>,
 }
 
 impl DeserializedInstruction {
@@ -140,6 +188,7 @@ impl DeserializedInstruction {
                 accounts,
             },
             inner_instructions: None,
+            events: vec![],
         }
     }
 
@@ -157,6 +206,7 @@ impl DeserializedInstruction {
                 deserialize_error: error_message,
             },
             inner_instructions: None,
+            events: vec![],
         }
     }
 }
@@ -1,9 +1,23 @@
 use anchor_lang::AnchorDeserialize;
 use anyhow::Result;
+use heck::SnakeCase;
+use serde::Serialize;
 use serde_json::{json, Value};
 use solana_sdk::instruction::Instruction;
 use spl_associated_token_account::instruction::AssociatedTokenAccountInstruction;
 use spl_token::{self, instruction::TokenInstruction};
+use spl_token_2022::extension::{
+    cpi_guard::instruction::CpiGuardInstruction,
+    default_account_state::instruction::DefaultAccountStateInstruction,
+    interest_bearing_mint::instruction::InterestBearingMintInstruction,
+    memo_transfer::instruction::RequiredMemoTransfersInstruction,
+    metadata_pointer::instruction::MetadataPointerInstruction,
+    transfer_hook::instruction::TransferHookInstruction,
+};
+use spl_token_2022::instruction::{
+    decode_instruction_type, TokenInstruction as Token2022Instruction,
+};
+use spl_token_metadata_interface::instruction::TokenMetadataInstruction;
 
 use super::DeserializedInstruction;
 
@@ -139,6 +153,65 @@ pub fn token_program_ix_to_value<'a>(ix: &'a TokenInstruction) -> Value {
     }
 }
 
+/// The name and data of a Token-2022 instruction, including Token Metadata interface
+/// instructions. Extension instructions are named after the extension, e.g.
+/// `transfer_checked_with_fee` or `enable_memo_transfer`. Only the name of an extension
+/// instruction is decoded when its data is encrypted or zero-copy, e.g. confidential transfers.
+pub fn token_2022_instruction(ix_data: &[u8]) -> Result<(String, Value)> {
+    if let Ok(ix) = TokenMetadataInstruction::unpack(ix_data) {
+        return Ok(split_variant(serde_json::to_value(ix)?));
+    }
+    let ix = Token2022Instruction::unpack(ix_data)?;
+    let extension_data = ix_data.get(1..).unwrap_or_default();
+    let extension = match &ix {
+        Token2022Instruction::TransferFeeExtension(ix) => {
+            return Ok(split_variant(serde_json::to_value(ix)?))
+        }
+        Token2022Instruction::MemoTransferExtension => {
+            extension_instruction::<RequiredMemoTransfersInstruction>(extension_data)
+        }
+        Token2022Instruction::DefaultAccountStateExtension => {
+            extension_instruction::<DefaultAccountStateInstruction>(extension_data)
+        }
+        Token2022Instruction::InterestBearingMintExtension => {
+            extension_instruction::<InterestBearingMintInstruction>(extension_data)
+        }
+        Token2022Instruction::CpiGuardExtension => {
+            extension_instruction::<CpiGuardInstruction>(extension_data)
+        }
+        Token2022Instruction::TransferHookExtension => {
+            extension_instruction::<TransferHookInstruction>(extension_data)
+        }
+        Token2022Instruction::MetadataPointerExtension => {
+            extension_instruction::<MetadataPointerInstruction>(extension_data)
+        }
+        _ => return Ok(split_variant(serde_json::to_value(&ix)?)),
+    };
+    let (extension_name, _) = split_variant(serde_json::to_value(&ix)?);
+    let name = extension_name.trim_end_matches("_extension");
+    match extension {
+        Some(sub_name) => Ok((format!("{}_{}", sub_name, name), Value::Null)),
+        None => Ok((extension_name, Value::Null)),
+    }
+}
+
+fn extension_instruction<T: TryFrom<u8> + Serialize>(data: &[u8]) -> Option<String> {
+    let ix = decode_instruction_type::<T>(data).ok()?;
+    Some(split_variant(serde_json::to_value(ix).ok()?).0)
+}
+
+/// An externally tagged enum variant's snake case name and its fields.
+fn split_variant(value: Value) -> (String, Value) {
+    match value {
+        Value::String(name) => (name.to_snake_case(), Value::Null),
+        Value::Object(map) if map.len() == 1 => {
+            let (name, fields) = map.into_iter().next().unwrap();
+            (name.to_snake_case(), fields)
+        }
+        value => ("unknown".to_string(), value),
+    }
+}
+
 pub fn associated_token_instruction(ix_data: &[u8]) -> Result<AssociatedTokenAccountInstruction> {
     Ok(AssociatedTokenAccountInstruction::deserialize(
         &mut &ix_data[..],
@@ -171,6 +244,22 @@ impl DeserializedInstruction {
         None
     }
 
+    pub fn try_token_2022_instruction(ix: &Instruction, ix_num: u8) -> Option<Self> {
+        if ix.program_id == spl_token_2022::ID {
+            if let Ok((name, ix_data)) = token_2022_instruction(&ix.data) {
+                return Some(DeserializedInstruction::ok(
+                    spl_token_2022::ID,
+                    "spl_token_2022_program".to_string(),
+                    ix_num,
+                    name,
+                    ix_data,
+                    vec![],
+                ));
+            }
+        }
+        None
+    }
+
     pub fn try_associated_token_instruction(ix: &Instruction, ix_num: u8) -> Option<Self> {
         if ix.program_id == spl_associated_token_account::ID {
             if let Ok(ix) = associated_token_instruction(&ix.data) {
@@ -187,3 +276,48 @@ impl DeserializedInstruction {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+    use spl_token_2022::extension::memo_transfer::instruction::enable_required_transfer_memos;
+    use spl_token_2022::extension::transfer_fee::instruction::transfer_checked_with_fee;
+
+    #[test]
+    fn token_2022_extension_instructions_are_named() {
+        let (account, mint, owner) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let ix = transfer_checked_with_fee(
+            &spl_token_2022::ID,
+            &account,
+            &mint,
+            &account,
+            &owner,
+            &[],
+            100,
+            6,
+            1,
+        )
+        .unwrap();
+        let (name, data) = token_2022_instruction(&ix.data).unwrap();
+        assert_eq!(name, "transfer_checked_with_fee");
+        assert_eq!(data["amount"], json!(100));
+
+        let ix =
+            enable_required_transfer_memos(&spl_token_2022::ID, &account, &owner, &[]).unwrap();
+        assert_eq!(
+            token_2022_instruction(&ix.data).unwrap(),
+            ("enable_memo_transfer".to_string(), Value::Null)
+        );
+
+        let ix = spl_token_2022::instruction::sync_native(&spl_token_2022::ID, &account).unwrap();
+        assert_eq!(
+            token_2022_instruction(&ix.data).unwrap(),
+            ("sync_native".to_string(), Value::Null)
+        );
+    }
+}
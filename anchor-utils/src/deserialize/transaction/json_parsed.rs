@@ -0,0 +1,213 @@
+//! Decoded instructions in the RPC's `jsonParsed` shape, so that consumers of parsed
+//! RPC data can read IDL-decoded instructions without any schema changes.
+use super::{DeserializedAccountMetas, DeserializedInstruction, DeserializedInstructionData};
+use crate::deserialize::AnchorDeserializer;
+use anyhow::Result;
+use heck::MixedCase;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use solana_devtools_tx::inner_instructions::{DecompiledMessageAndInnerIx, HistoricalTransaction};
+use solana_program::instruction::{CompiledInstruction, Instruction};
+use solana_program::message::AccountKeys;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::bs58;
+use solana_transaction_status::parse_instruction::{parse, ParsedInstruction};
+use solana_transaction_status::{
+    UiInnerInstructions, UiInstruction, UiParsedInstruction, UiPartiallyDecodedInstruction,
+};
+
+/// The `instructions` of a `jsonParsed` transaction message,
+/// and the `innerInstructions` of its transaction meta.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonParsedInstructions {
+    pub instructions: Vec<UiInstruction>,
+    pub inner_instructions: Vec<UiInnerInstructions>,
+}
+
+impl AnchorDeserializer {
+    /// Like [AnchorDeserializer::try_deserialize_transaction], but in the RPC's `jsonParsed` shape.
+    pub fn try_deserialize_transaction_json_parsed(
+        &self,
+        tx: HistoricalTransaction,
+    ) -> Result<JsonParsedInstructions> {
+        let mut decompiled: DecompiledMessageAndInnerIx = tx.into();
+        let mut instructions = vec![];
+        let mut inner_instructions = vec![];
+        for (ix_num, ix) in decompiled.top_level_instructions.iter_mut().enumerate() {
+            instructions.push(self.try_deserialize_instruction_json_parsed(ix_num, ix)?);
+            if let Some(inner) = decompiled.inner_instructions.get_mut(&(ix_num as u8)) {
+                inner_instructions.push(UiInnerInstructions {
+                    index: ix_num as u8,
+                    instructions: inner
+                        .iter_mut()
+                        .enumerate()
+                        .map(|(i, ix)| self.try_deserialize_instruction_json_parsed(i, ix))
+                        .collect::<Result<_>>()?,
+                });
+            }
+        }
+        Ok(JsonParsedInstructions {
+            instructions,
+            inner_instructions,
+        })
+    }
+
+    /// Programs the RPC can parse are parsed exactly as the RPC would. Otherwise the IDL,
+    /// or this crate's builtin decoding, is used, and instructions that can't be decoded
+    /// are left partially decoded.
+    pub fn try_deserialize_instruction_json_parsed(
+        &self,
+        ix_num: usize,
+        ix: &mut Instruction,
+    ) -> Result<UiInstruction> {
+        if let Some(parsed) = rpc_parse(ix) {
+            return Ok(UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)));
+        }
+        let deserialized = self.try_deserialize_instruction(ix_num, ix, None)?;
+        Ok(UiInstruction::Parsed(match deserialized.to_json_parsed() {
+            Some(parsed) => UiParsedInstruction::Parsed(parsed),
+            None => UiParsedInstruction::PartiallyDecoded(UiPartiallyDecodedInstruction {
+                program_id: ix.program_id.to_string(),
+                accounts: ix.accounts.iter().map(|m| m.pubkey.to_string()).collect(),
+                data: bs58::encode(&ix.data).into_string(),
+                stack_height: None,
+            }),
+        }))
+    }
+}
+
+impl DeserializedInstruction {
+    /// The `jsonParsed` form of a successfully decoded instruction. The instruction name
+    /// becomes `parsed.type`, and its accounts and arguments become the fields of `parsed.info`.
+    pub fn to_json_parsed(&self) -> Option<ParsedInstruction> {
+        let DeserializedInstructionData::Ok {
+            name,
+            data,
+            accounts,
+        } = &self.parsed
+        else {
+            return None;
+        };
+        let mut info = Map::new();
+        insert_accounts(&mut info, accounts);
+        match unwrap_enum_variant(name, data) {
+            Value::Object(args) => info.extend(args.clone()),
+            Value::Null => {}
+            value => {
+                info.insert("value".to_string(), value.clone());
+            }
+        }
+        Some(ParsedInstruction {
+            program: self.program_name.clone(),
+            program_id: self.program_id.to_string(),
+            parsed: serde_json::json!({
+                "type": name.to_mixed_case(),
+                "info": info,
+            }),
+            stack_height: None,
+        })
+    }
+}
+
+fn rpc_parse(ix: &Instruction) -> Option<ParsedInstruction> {
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|m| m.pubkey).collect();
+    let compiled = CompiledInstruction {
+        program_id_index: 0,
+        accounts: (0..keys.len() as u8).collect(),
+        data: ix.data.clone(),
+    };
+    parse(
+        &ix.program_id,
+        &compiled,
+        &AccountKeys::new(&keys, None),
+        None,
+    )
+    .ok()
+}
+
+fn insert_accounts(info: &mut Map<String, Value>, accounts: &[DeserializedAccountMetas]) {
+    for account in accounts {
+        match account {
+            DeserializedAccountMetas::One(meta) => {
+                info.insert(meta.name.to_mixed_case(), meta.pubkey.to_string().into());
+            }
+            DeserializedAccountMetas::Nested { name, accounts } => {
+                let mut nested = Map::new();
+                insert_accounts(&mut nested, accounts);
+                info.insert(name.to_mixed_case(), nested.into());
+            }
+        }
+    }
+}
+
+/// Builtin instructions are serialized as externally tagged enums, e.g.
+/// `{"SetComputeUnitLimit": 200000}`, whose variant is already the instruction name.
+fn unwrap_enum_variant<'a>(name: &str, data: &'a Value) -> &'a Value {
+    match data.as_object() {
+        Some(map) if map.len() == 1 => match map.iter().next() {
+            Some((variant, value)) if variant.to_mixed_case() == name.to_mixed_case() => value,
+            _ => data,
+        },
+        _ => data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use solana_sdk::{compute_budget::ComputeBudgetInstruction, system_instruction};
+
+    #[test]
+    fn instructions_are_json_parsed() {
+        let deser = AnchorDeserializer::new();
+        let from = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let mut transfer = system_instruction::transfer(&from, &to, 42);
+        let parsed = deser
+            .try_deserialize_instruction_json_parsed(0, &mut transfer)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(parsed).unwrap(),
+            json!({
+                "program": "system",
+                "programId": solana_program::system_program::ID.to_string(),
+                "parsed": {
+                    "type": "transfer",
+                    "info": {
+                        "source": from.to_string(),
+                        "destination": to.to_string(),
+                        "lamports": 42,
+                    },
+                },
+                "stackHeight": null,
+            })
+        );
+
+        let mut limit = ComputeBudgetInstruction::set_compute_unit_limit(200_000);
+        let parsed = deser
+            .try_deserialize_instruction_json_parsed(1, &mut limit)
+            .unwrap();
+        let parsed = serde_json::to_value(parsed).unwrap();
+        assert_eq!(
+            parsed["parsed"],
+            json!({ "type": "setComputeUnitLimit", "info": { "value": 200_000 } })
+        );
+
+        let program_id = Pubkey::new_unique();
+        let mut unknown = Instruction::new_with_bytes(program_id, &[1, 2, 3], vec![]);
+        let parsed = deser
+            .try_deserialize_instruction_json_parsed(2, &mut unknown)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(parsed).unwrap(),
+            json!({
+                "programId": program_id.to_string(),
+                "accounts": [],
+                "data": bs58::encode([1, 2, 3]).into_string(),
+                "stackHeight": null,
+            })
+        );
+    }
+}
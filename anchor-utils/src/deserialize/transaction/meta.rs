@@ -0,0 +1,183 @@
+//! The parts of a historical transaction's status meta that are shown alongside
+//! its deserialized instructions, keyed by account address rather than account index.
+use serde::{Deserialize, Serialize};
+use solana_devtools_errors::resolver::ErrorResolver;
+use solana_devtools_serde::pubkey;
+use solana_devtools_tx::inner_instructions::HistoricalTransaction;
+use solana_program::pubkey::Pubkey;
+use solana_transaction_status::UiTransactionTokenBalance;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeserializedTransactionMeta {
+    pub fee: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compute_units_consumed: Option<u64>,
+    /// The transaction's error, naming the custom program error if the program is known,
+    /// see [ErrorResolver::report].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// One entry per account key, in the order of the message's account keys.
+    pub balances: Vec<BalanceChange>,
+    pub token_balances: Vec<TokenBalanceChange>,
+    pub log_messages: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceChange {
+    #[serde(with = "pubkey")]
+    pub account: Pubkey,
+    pub pre: u64,
+    pub post: u64,
+}
+
+/// Token amounts are raw, undivided amounts as strings, as in the RPC's token balances.
+/// An account missing from either side, i.e. created or closed by the transaction,
+/// is given an amount of zero on that side.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenBalanceChange {
+    #[serde(with = "pubkey")]
+    pub account: Pubkey,
+    pub mint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub decimals: u8,
+    pub pre: String,
+    pub post: String,
+}
+
+impl DeserializedTransactionMeta {
+    /// Returns `None` if the transaction carries no status meta, e.g. one built from
+    /// a bare message. Executed transactions always record their lamport balances.
+    pub fn from_historical_transaction(
+        tx: &HistoricalTransaction,
+        resolver: &ErrorResolver,
+    ) -> Option<Self> {
+        if tx.pre_balances.is_empty() && tx.post_balances.is_empty() {
+            return None;
+        }
+        let account_keys = tx.account_keys();
+        let balances = account_keys
+            .iter()
+            .zip(tx.pre_balances.iter().zip(&tx.post_balances))
+            .map(|(account, (pre, post))| BalanceChange {
+                account: *account,
+                pre: *pre,
+                post: *post,
+            })
+            .collect();
+
+        let mut token_balances: BTreeMap<u8, TokenBalanceChange> = BTreeMap::new();
+        let sides = [
+            (&tx.pre_token_balances, true),
+            (&tx.post_token_balances, false),
+        ];
+        for (balances, is_pre) in sides {
+            for balance in balances {
+                let Some(account) = account_keys.get(balance.account_index as usize) else {
+                    continue;
+                };
+                let change = token_balances
+                    .entry(balance.account_index)
+                    .or_insert_with(|| token_balance_change(*account, balance));
+                let amount = balance.ui_token_amount.amount.clone();
+                if is_pre {
+                    change.pre = amount;
+                } else {
+                    change.post = amount;
+                }
+            }
+        }
+
+        Some(Self {
+            fee: tx.fee,
+            compute_units_consumed: tx.compute_units_consumed,
+            error: tx
+                .err
+                .as_ref()
+                .map(|err| resolver.report(err, &tx.log_messages).to_string()),
+            balances,
+            token_balances: token_balances.into_values().collect(),
+            log_messages: tx.log_messages.clone(),
+        })
+    }
+}
+
+fn token_balance_change(
+    account: Pubkey,
+    balance: &UiTransactionTokenBalance,
+) -> TokenBalanceChange {
+    let owner: Option<String> = balance.owner.clone().into();
+    TokenBalanceChange {
+        account,
+        mint: balance.mint.clone(),
+        owner,
+        decimals: balance.ui_token_amount.decimals,
+        pre: "0".to_string(),
+        post: "0".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_account_decoder::parse_token::UiTokenAmount;
+    use solana_program::message::{Message, VersionedMessage};
+    use solana_program::system_instruction;
+    use solana_transaction_status::option_serializer::OptionSerializer;
+
+    fn token_balance(account_index: u8, amount: &str) -> UiTransactionTokenBalance {
+        UiTransactionTokenBalance {
+            account_index,
+            mint: Pubkey::new_unique().to_string(),
+            ui_token_amount: UiTokenAmount {
+                ui_amount: None,
+                decimals: 6,
+                amount: amount.to_string(),
+                ui_amount_string: String::new(),
+            },
+            owner: OptionSerializer::None,
+            program_id: OptionSerializer::None,
+        }
+    }
+
+    #[test]
+    fn meta_is_keyed_by_account() {
+        let from = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let message = Message::new(&[system_instruction::transfer(&from, &to, 42)], Some(&from));
+        let mut tx = HistoricalTransaction::new(VersionedMessage::Legacy(message), None);
+        let resolver = ErrorResolver::new();
+        assert!(DeserializedTransactionMeta::from_historical_transaction(&tx, &resolver).is_none());
+
+        tx.fee = 5000;
+        tx.pre_balances = vec![100_000, 0, 1];
+        tx.post_balances = vec![94_958, 42, 1];
+        tx.pre_token_balances = vec![token_balance(1, "10")];
+        tx.post_token_balances = vec![token_balance(1, "7"), token_balance(2, "3")];
+        tx.compute_units_consumed = Some(150);
+        let meta =
+            DeserializedTransactionMeta::from_historical_transaction(&tx, &resolver).unwrap();
+        assert_eq!(meta.fee, 5000);
+        assert_eq!(meta.compute_units_consumed, Some(150));
+        assert_eq!(meta.error, None);
+        assert_eq!(
+            meta.balances[1],
+            BalanceChange {
+                account: to,
+                pre: 0,
+                post: 42
+            }
+        );
+        assert_eq!(meta.token_balances.len(), 2);
+        assert_eq!(meta.token_balances[0].account, to);
+        assert_eq!(
+            (&*meta.token_balances[0].pre, &*meta.token_balances[0].post),
+            ("10", "7")
+        );
+        assert_eq!(
+            (&*meta.token_balances[1].pre, &*meta.token_balances[1].post),
+            ("0", "3")
+        );
+    }
+}
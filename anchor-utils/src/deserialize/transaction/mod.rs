@@ -1,11 +1,15 @@
 pub mod instruction;
+pub mod json_parsed;
+pub mod meta;
 
 use std::ops::Deref;
 
 use crate::deserialize::AnchorDeserializer;
 use anyhow::Result;
 pub use instruction::*;
-use serde::{Deserialize, Serialize};
+pub use json_parsed::JsonParsedInstructions;
+pub use meta::*;
+use serde::{Deserialize, Serialize, Serializer};
 use solana_devtools_tx::inner_instructions::{DecompiledMessageAndInnerIx, HistoricalTransaction};
 use solana_program::message::v0::LoadedAddresses;
 use solana_program::message::VersionedMessage;
@@ -20,19 +24,38 @@ impl AnchorDeserializer {
     /// that flags whether a message's account meta signer + mutable flag disagrees with the IDL.
     /// This is not necessarily a privilege escalation error, unless the IDL calls for a higher
     /// privilege than the message grants on the account in question.
+    ///
+    /// Events in the transaction's logs are attached to the instruction that logged them,
+    /// see [AnchorDeserializer::try_deserialize_events].
+    ///
+    /// The transaction's fee, balance changes, compute units and logs are carried along
+    /// in [DeserializedTransaction::meta], if it was executed.
     pub fn try_deserialize_transaction(
         &self,
         tx: HistoricalTransaction,
     ) -> Result<DeserializedTransaction> {
         let mut instructions_deserialized = vec![];
+        let mut logged_events = self.try_deserialize_logged_events(
+            &tx.log_messages,
+            &instruction::event::top_level_program_ids(&tx.message),
+        );
+        let meta =
+            DeserializedTransactionMeta::from_historical_transaction(&tx, self.error_resolver());
         let mut decompiled: DecompiledMessageAndInnerIx = tx.into();
 
         for (ix_num, ix) in decompiled.top_level_instructions.iter_mut().enumerate() {
             let inner_ixs = decompiled.inner_instructions.get(&(ix_num as u8)).cloned();
-            instructions_deserialized
-                .push(self.try_deserialize_instruction(ix_num, ix, inner_ixs)?);
+            let mut deserialized = self.try_deserialize_instruction(ix_num, ix, inner_ixs)?;
+            if let Some(mut events) = logged_events.remove(&(ix_num as u8)) {
+                events.append(&mut deserialized.events);
+                deserialized.events = events;
+            }
+            instructions_deserialized.push(deserialized);
         }
-        Ok(DeserializedTransaction(instructions_deserialized))
+        Ok(DeserializedTransaction {
+            instructions: instructions_deserialized,
+            meta,
+        })
     }
 
     /// Deserialize just a transaction message, no inner instructions.
@@ -46,13 +69,127 @@ impl AnchorDeserializer {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct DeserializedTransaction(Vec<DeserializedInstruction>);
+/// Serializes as the array of instructions when there is no `meta`, as it always has,
+/// and as an `{"instructions", "meta"}` object when there is.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "DeserializedTransactionRepr")]
+pub struct DeserializedTransaction {
+    pub instructions: Vec<DeserializedInstruction>,
+    pub meta: Option<DeserializedTransactionMeta>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DeserializedTransactionRepr {
+    Instructions(Vec<DeserializedInstruction>),
+    WithMeta {
+        instructions: Vec<DeserializedInstruction>,
+        #[serde(default)]
+        meta: Option<DeserializedTransactionMeta>,
+    },
+}
+
+impl From<DeserializedTransactionRepr> for DeserializedTransaction {
+    fn from(repr: DeserializedTransactionRepr) -> Self {
+        match repr {
+            DeserializedTransactionRepr::Instructions(instructions) => Self {
+                instructions,
+                meta: None,
+            },
+            DeserializedTransactionRepr::WithMeta { instructions, meta } => {
+                Self { instructions, meta }
+            }
+        }
+    }
+}
+
+impl Serialize for DeserializedTransaction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct WithMeta<'a> {
+            instructions: &'a [DeserializedInstruction],
+            meta: &'a DeserializedTransactionMeta,
+        }
+        match &self.meta {
+            None => self.instructions.serialize(serializer),
+            Some(meta) => WithMeta {
+                instructions: &self.instructions,
+                meta,
+            }
+            .serialize(serializer),
+        }
+    }
+}
 
 impl Deref for DeserializedTransaction {
     type Target = [DeserializedInstruction];
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.instructions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use solana_program::pubkey::Pubkey;
+
+    #[test]
+    fn error_resolver_is_rebuilt_when_idls_are_cached() {
+        let idl = |name: &str| -> anchor_syn::idl::types::Idl {
+            serde_json::from_value(json!({
+                "version": "0.1.0",
+                "name": name,
+                "instructions": [],
+                "errors": [{ "code": 6000, "name": "Unauthorized" }],
+            }))
+            .unwrap()
+        };
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut deser = AnchorDeserializer::new();
+        deser.cache_idl(first, idl("first").into());
+        let resolver: *const _ = deser.error_resolver();
+        assert!(std::ptr::eq(resolver, deser.error_resolver()));
+        assert!(deser.error_resolver().resolve(&second, 6000).is_none());
+
+        deser.cache_idl(second, idl("second").into());
+        let (program_name, error) = deser.error_resolver().resolve(&second, 6000).unwrap();
+        assert_eq!(
+            (program_name, error.name.as_str()),
+            ("Second", "Unauthorized")
+        );
+        assert!(deser.error_resolver().resolve(&first, 6000).is_some());
+    }
+
+    #[test]
+    fn transactions_without_meta_serialize_as_arrays() {
+        let unexecuted = DeserializedTransaction {
+            instructions: vec![],
+            meta: None,
+        };
+        let executed = DeserializedTransaction {
+            instructions: vec![],
+            meta: Some(DeserializedTransactionMeta {
+                fee: 5000,
+                compute_units_consumed: None,
+                error: None,
+                balances: vec![],
+                token_balances: vec![],
+                log_messages: vec![],
+            }),
+        };
+        assert_eq!(serde_json::to_value(&unexecuted).unwrap(), json!([]));
+        assert_eq!(
+            serde_json::to_value(&executed).unwrap()["meta"]["fee"],
+            json!(5000)
+        );
+        for tx in [unexecuted, executed] {
+            let json = serde_json::to_string(&tx).unwrap();
+            assert_eq!(
+                serde_json::from_str::<DeserializedTransaction>(&json).unwrap(),
+                tx
+            );
+        }
     }
 }
@@ -0,0 +1,244 @@
+//! Byte-level account diffs, for accounts that cannot be deserialized with an IDL.
+use serde::{Deserialize, Serialize};
+use solana_devtools_serde::pubkey;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::ReadableAccount;
+use std::fmt::{Display, Formatter};
+
+/// Changed bytes that are separated by at most this many unchanged bytes
+/// are grouped into the same [ByteRangeDiff].
+pub const DEFAULT_MERGE_GAP: usize = 8;
+
+const BYTES_PER_ROW: usize = 16;
+
+/// A before and after value for a changed field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change<T> {
+    pub before: T,
+    pub after: T,
+}
+
+/// A contiguous range of account data that changed.
+/// `before` and `after` only differ in length when the account data was resized.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRangeDiff {
+    pub offset: usize,
+    pub before: Vec<u8>,
+    pub after: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountDiff {
+    pub lamports: Option<Change<u64>>,
+    #[serde(with = "owner_change")]
+    pub owner: Option<Change<Pubkey>>,
+    pub executable: Option<Change<bool>>,
+    pub rent_epoch: Option<Change<u64>>,
+    pub data_len: Option<Change<usize>>,
+    pub data: Vec<ByteRangeDiff>,
+}
+
+impl AccountDiff {
+    pub fn new(before: &impl ReadableAccount, after: &impl ReadableAccount) -> Self {
+        Self::new_with_merge_gap(before, after, DEFAULT_MERGE_GAP)
+    }
+
+    pub fn new_with_merge_gap(
+        before: &impl ReadableAccount,
+        after: &impl ReadableAccount,
+        merge_gap: usize,
+    ) -> Self {
+        Self {
+            lamports: change(before.lamports(), after.lamports()),
+            owner: change(*before.owner(), *after.owner()),
+            executable: change(before.executable(), after.executable()),
+            rent_epoch: change(before.rent_epoch(), after.rent_epoch()),
+            data_len: change(before.data().len(), after.data().len()),
+            data: diff_bytes(before.data(), after.data(), merge_gap),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lamports.is_none()
+            && self.owner.is_none()
+            && self.executable.is_none()
+            && self.rent_epoch.is_none()
+            && self.data_len.is_none()
+            && self.data.is_empty()
+    }
+}
+
+fn change<T: PartialEq>(before: T, after: T) -> Option<Change<T>> {
+    (before != after).then_some(Change { before, after })
+}
+
+/// Find the ranges at which `before` and `after` differ. Bytes past the end
+/// of the shorter slice always count as changed.
+pub fn diff_bytes(before: &[u8], after: &[u8], merge_gap: usize) -> Vec<ByteRangeDiff> {
+    let len = before.len().max(after.len());
+    let differs = |i: usize| before.get(i) != after.get(i);
+
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for i in (0..len).filter(|i| differs(*i)) {
+        match ranges.last_mut() {
+            Some((_, end)) if i - *end <= merge_gap => *end = i + 1,
+            _ => ranges.push((i, i + 1)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(start, end)| ByteRangeDiff {
+            offset: start,
+            before: slice_clamped(before, start, end).to_vec(),
+            after: slice_clamped(after, start, end).to_vec(),
+        })
+        .collect()
+}
+
+fn slice_clamped(data: &[u8], start: usize, end: usize) -> &[u8] {
+    &data[start.min(data.len())..end.min(data.len())]
+}
+
+impl Display for AccountDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no changes");
+        }
+        if let Some(Change { before, after }) = &self.lamports {
+            writeln!(f, "lamports: {} -> {}", before, after)?;
+        }
+        if let Some(Change { before, after }) = &self.owner {
+            writeln!(f, "owner: {} -> {}", before, after)?;
+        }
+        if let Some(Change { before, after }) = &self.executable {
+            writeln!(f, "executable: {} -> {}", before, after)?;
+        }
+        if let Some(Change { before, after }) = &self.rent_epoch {
+            writeln!(f, "rent_epoch: {} -> {}", before, after)?;
+        }
+        if let Some(Change { before, after }) = &self.data_len {
+            writeln!(f, "data length: {} -> {}", before, after)?;
+        }
+        for range in &self.data {
+            write!(f, "{}", range)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ByteRangeDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let len = self.before.len().max(self.after.len());
+        writeln!(
+            f,
+            "data[{:#x}..{:#x}] ({} bytes)",
+            self.offset,
+            self.offset + len,
+            len
+        )?;
+        for row in (0..len).step_by(BYTES_PER_ROW) {
+            let offset = self.offset + row;
+            for (sign, bytes) in [("-", &self.before), ("+", &self.after)] {
+                let bytes = slice_clamped(bytes, row, row + BYTES_PER_ROW);
+                if !bytes.is_empty() {
+                    writeln!(f, "  {} {:#010x}: {}", sign, offset, hex_row(bytes))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn hex_row(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+mod owner_change {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct OwnerChange {
+        #[serde(with = "pubkey")]
+        before: Pubkey,
+        #[serde(with = "pubkey")]
+        after: Pubkey,
+    }
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Change<Pubkey>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value
+            .as_ref()
+            .map(|c| OwnerChange {
+                before: c.before,
+                after: c.after,
+            })
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Change<Pubkey>>, D::Error> {
+        Ok(
+            Option::<OwnerChange>::deserialize(deserializer)?.map(|c| Change {
+                before: c.before,
+                after: c.after,
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::account::Account;
+
+    #[test]
+    fn byte_ranges_are_merged_and_rendered() {
+        let owner = Pubkey::new_unique();
+        let mut before = Account::new(100, 0x60, &owner);
+        let mut after = before.clone();
+        after.lamports = 200;
+        after.data[0x48] = 1;
+        after.data[0x4c] = 2;
+        after.data[0x5f] = 3;
+        after.data.extend_from_slice(&[4, 5]);
+        before.data[0] = 9;
+
+        let diff = AccountDiff::new(&before, &after);
+        assert_eq!(
+            diff.lamports,
+            Some(Change {
+                before: 100,
+                after: 200
+            })
+        );
+        assert_eq!(diff.owner, None);
+        assert_eq!(
+            diff.data_len,
+            Some(Change {
+                before: 0x60,
+                after: 0x62
+            })
+        );
+        assert_eq!(
+            diff.data
+                .iter()
+                .map(|r| (r.offset, r.before.len(), r.after.len()))
+                .collect::<Vec<_>>(),
+            vec![(0, 1, 1), (0x48, 5, 5), (0x5f, 1, 3)]
+        );
+
+        let rendered = diff.to_string();
+        assert!(rendered.contains("lamports: 100 -> 200"));
+        assert!(rendered.contains("data[0x48..0x4d] (5 bytes)"));
+        assert!(rendered.contains("+ 0x0000005f: 03 04 05"));
+        assert!(AccountDiff::new(&before, &before).is_empty());
+    }
+}
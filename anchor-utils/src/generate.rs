@@ -0,0 +1,390 @@
+//! Property-test strategies that produce random, schema-valid data from an IDL.
+//! All generated data is borsh-encoded in the same layout read by
+//! [IdlWithDiscriminators::deserialize_idl_type].
+use crate::deserialize::discriminator::{account_discriminator, ix_discriminator};
+use crate::deserialize::IdlWithDiscriminators;
+use anchor_syn::idl::types::{
+    EnumFields, IdlField, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy,
+};
+use anyhow::anyhow;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::strategy::{BoxedStrategy, Just, Union};
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_sdk::account::Account;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// Configures the values produced by the strategies on [IdlWithDiscriminators].
+#[derive(Debug, Clone)]
+pub struct IdlStrategyConfig {
+    /// Maximum number of elements in generated `Vec` and `Bytes` values.
+    pub max_vec_len: usize,
+    /// Maximum number of characters in generated `String` values.
+    pub max_string_len: usize,
+    /// Maximum nesting of defined types before recursive types are cut off
+    /// with `None` options and empty vectors.
+    pub max_depth: usize,
+    /// Value ranges for integer fields, keyed by field name.
+    /// Applies to every integer field with that name, including fields of nested types.
+    /// Ranges are clamped to the bounds of the field's integer type.
+    /// Building a strategy fails if a range reached by it is empty.
+    pub field_ranges: HashMap<String, RangeInclusive<i128>>,
+}
+
+impl Default for IdlStrategyConfig {
+    fn default() -> Self {
+        Self {
+            max_vec_len: 8,
+            max_string_len: 32,
+            max_depth: 8,
+            field_ranges: HashMap::new(),
+        }
+    }
+}
+
+impl IdlStrategyConfig {
+    pub fn with_field_range(mut self, field: &str, range: RangeInclusive<i128>) -> Self {
+        self.field_ranges.insert(field.to_string(), range);
+        self
+    }
+}
+
+macro_rules! int_strategy {
+    ($ty:ty, $min:expr, $max:expr, $range:expr) => {
+        match $range {
+            Some(range) => {
+                let start = (*range.start()).clamp($min, $max) as $ty;
+                let end = (*range.end()).clamp($min, $max) as $ty;
+                (start..=end).prop_map(|v| v.to_le_bytes().to_vec()).boxed()
+            }
+            None => any::<$ty>().prop_map(|v| v.to_le_bytes().to_vec()).boxed(),
+        }
+    };
+}
+
+impl IdlWithDiscriminators {
+    /// Random account data for the named account, prefixed with its discriminator.
+    pub fn account_data_strategy(
+        &self,
+        account_name: &str,
+        config: &IdlStrategyConfig,
+    ) -> anyhow::Result<BoxedStrategy<Vec<u8>>> {
+        let type_def = self
+            .get_account_definition_by_name(account_name)
+            .ok_or(anyhow!("Account not found in IDL: {}", account_name))?;
+        let discriminator = account_discriminator(account_name);
+        Ok(self
+            .type_definition_strategy(type_def, config)?
+            .prop_map(move |data| [&discriminator[..], &data].concat())
+            .boxed())
+    }
+
+    /// Random, rent-exempt accounts owned by `owner`,
+    /// holding data generated by [IdlWithDiscriminators::account_data_strategy].
+    pub fn account_strategy(
+        &self,
+        account_name: &str,
+        owner: Pubkey,
+        config: &IdlStrategyConfig,
+    ) -> anyhow::Result<BoxedStrategy<Account>> {
+        Ok(self
+            .account_data_strategy(account_name, config)?
+            .prop_map(move |data| Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner,
+                executable: false,
+                rent_epoch: 0,
+            })
+            .boxed())
+    }
+
+    /// Random instruction data for the named instruction,
+    /// prefixed with its discriminator.
+    pub fn instruction_data_strategy(
+        &self,
+        instruction_name: &str,
+        config: &IdlStrategyConfig,
+    ) -> anyhow::Result<BoxedStrategy<Vec<u8>>> {
+        let instruction = self
+            .instructions
+            .iter()
+            .find(|ix| ix.name == instruction_name)
+            .ok_or(anyhow!(
+                "Instruction not found in IDL: {}",
+                instruction_name
+            ))?;
+        let discriminator = ix_discriminator(instruction_name);
+        Ok(self
+            .named_fields_strategy(&instruction.args, config, 0)?
+            .prop_map(move |data| [&discriminator[..], &data].concat())
+            .boxed())
+    }
+
+    /// Random data for a struct, enum or alias defined in the IDL.
+    pub fn type_definition_strategy(
+        &self,
+        type_definition: &IdlTypeDefinition,
+        config: &IdlStrategyConfig,
+    ) -> anyhow::Result<BoxedStrategy<Vec<u8>>> {
+        self.type_definition_strategy_at_depth(type_definition, config, 0)
+    }
+
+    fn type_definition_strategy_at_depth(
+        &self,
+        type_definition: &IdlTypeDefinition,
+        config: &IdlStrategyConfig,
+        depth: usize,
+    ) -> anyhow::Result<BoxedStrategy<Vec<u8>>> {
+        match &type_definition.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                self.named_fields_strategy(fields, config, depth)
+            }
+            IdlTypeDefinitionTy::Enum { variants } => {
+                if variants.is_empty() {
+                    return Err(anyhow!("Enum has no variants: {}", type_definition.name));
+                }
+                let variants = variants
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, variant)| {
+                        let fields = match &variant.fields {
+                            Some(EnumFields::Named(fields)) => {
+                                self.named_fields_strategy(fields, config, depth)?
+                            }
+                            Some(EnumFields::Tuple(types)) => {
+                                self.tuple_strategy(types, config, depth)?
+                            }
+                            None => Just(vec![]).boxed(),
+                        };
+                        Ok(fields
+                            .prop_map(move |data| [&[idx as u8][..], &data].concat())
+                            .boxed())
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok(Union::new(variants).boxed())
+            }
+            IdlTypeDefinitionTy::Alias { value } => {
+                self.idl_type_strategy(value, None, config, depth)
+            }
+        }
+    }
+
+    /// Random data for an [IdlType]. The `field_name` is used to
+    /// look up a range in [IdlStrategyConfig::field_ranges].
+    fn idl_type_strategy(
+        &self,
+        idl_type: &IdlType,
+        field_name: Option<&str>,
+        config: &IdlStrategyConfig,
+        depth: usize,
+    ) -> anyhow::Result<BoxedStrategy<Vec<u8>>> {
+        let range = field_name.and_then(|name| config.field_ranges.get(name));
+        if let Some(range) = range.filter(|range| range.is_empty()) {
+            return Err(anyhow!(
+                "Empty range for field {}: {:?}",
+                field_name.unwrap_or_default(),
+                range
+            ));
+        }
+        let strategy = match idl_type {
+            IdlType::Bool => any::<bool>().prop_map(|v| vec![v as u8]).boxed(),
+            IdlType::U8 => int_strategy!(u8, 0, u8::MAX as i128, range),
+            IdlType::I8 => int_strategy!(i8, i8::MIN as i128, i8::MAX as i128, range),
+            IdlType::U16 => int_strategy!(u16, 0, u16::MAX as i128, range),
+            IdlType::I16 => int_strategy!(i16, i16::MIN as i128, i16::MAX as i128, range),
+            IdlType::U32 => int_strategy!(u32, 0, u32::MAX as i128, range),
+            IdlType::I32 => int_strategy!(i32, i32::MIN as i128, i32::MAX as i128, range),
+            IdlType::U64 => int_strategy!(u64, 0, u64::MAX as i128, range),
+            IdlType::I64 => int_strategy!(i64, i64::MIN as i128, i64::MAX as i128, range),
+            IdlType::U128 => int_strategy!(u128, 0, i128::MAX, range),
+            IdlType::I128 => int_strategy!(i128, i128::MIN, i128::MAX, range),
+            IdlType::F32 => any::<f32>().prop_map(|v| v.to_le_bytes().to_vec()).boxed(),
+            IdlType::F64 => any::<f64>().prop_map(|v| v.to_le_bytes().to_vec()).boxed(),
+            IdlType::Bytes => vec(any::<u8>(), 0..=config.max_vec_len)
+                .prop_map(|bytes| length_prefixed(&bytes))
+                .boxed(),
+            IdlType::String => vec(any::<char>(), 0..=config.max_string_len)
+                .prop_map(|chars| length_prefixed(String::from_iter(chars).as_bytes()))
+                .boxed(),
+            IdlType::PublicKey => any::<[u8; 32]>().prop_map(|v| v.to_vec()).boxed(),
+            IdlType::Defined(defined_type) => {
+                if depth >= config.max_depth {
+                    return Err(anyhow!(
+                        "Exceeded maximum type depth at defined type: {}",
+                        defined_type
+                    ));
+                }
+                let (_, ty_def) = self
+                    .find_type_definition_by_name(defined_type)
+                    .ok_or(anyhow!("Couldn't find defined type: {}", defined_type))?;
+                self.type_definition_strategy_at_depth(ty_def, config, depth + 1)?
+            }
+            IdlType::Option(inner) => {
+                if depth + 1 >= config.max_depth {
+                    return Ok(Just(vec![0]).boxed());
+                }
+                let inner = self.idl_type_strategy(inner, field_name, config, depth + 1)?;
+                prop_oneof![
+                    Just(vec![0]),
+                    inner.prop_map(|data| [&[1][..], &data].concat()),
+                ]
+                .boxed()
+            }
+            IdlType::Vec(inner) => {
+                if depth + 1 >= config.max_depth {
+                    return Ok(Just(0u32.to_le_bytes().to_vec()).boxed());
+                }
+                let inner = self.idl_type_strategy(inner, field_name, config, depth + 1)?;
+                vec(inner, 0..=config.max_vec_len)
+                    .prop_map(|elements| {
+                        let len = (elements.len() as u32).to_le_bytes();
+                        [&len[..], &elements.concat()].concat()
+                    })
+                    .boxed()
+            }
+            IdlType::Array(inner, len) => {
+                let inner = self.idl_type_strategy(inner, field_name, config, depth)?;
+                vec(inner, *len)
+                    .prop_map(|elements| elements.concat())
+                    .boxed()
+            }
+            _ => return Err(anyhow!("U256 and I256 not yet supported")),
+        };
+        Ok(strategy)
+    }
+
+    fn named_fields_strategy(
+        &self,
+        fields: &[IdlField],
+        config: &IdlStrategyConfig,
+        depth: usize,
+    ) -> anyhow::Result<BoxedStrategy<Vec<u8>>> {
+        let strategies = fields
+            .iter()
+            .map(|field| self.idl_type_strategy(&field.ty, Some(&field.name), config, depth))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(strategies.prop_map(|fields| fields.concat()).boxed())
+    }
+
+    fn tuple_strategy(
+        &self,
+        types: &[IdlType],
+        config: &IdlStrategyConfig,
+        depth: usize,
+    ) -> anyhow::Result<BoxedStrategy<Vec<u8>>> {
+        let strategies = types
+            .iter()
+            .map(|ty| self.idl_type_strategy(ty, None, config, depth))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(strategies.prop_map(|fields| fields.concat()).boxed())
+    }
+}
+
+/// Borsh encoding of a `Vec<u8>` or `String`.
+fn length_prefixed(bytes: &[u8]) -> Vec<u8> {
+    [&(bytes.len() as u32).to_le_bytes()[..], bytes].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_syn::idl::types::Idl;
+    use proptest::test_runner::TestRunner;
+
+    fn test_idl() -> IdlWithDiscriminators {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "auction",
+            "instructions": [{
+                "name": "placeBid",
+                "accounts": [],
+                "args": [{ "name": "amount", "type": "u64" }]
+            }],
+            "accounts": [{
+                "name": "Auction",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "authority", "type": "publicKey" },
+                        { "name": "amount", "type": "u64" },
+                        { "name": "bids", "type": { "vec": { "defined": "Bid" } } },
+                        { "name": "state", "type": { "defined": "AuctionState" } },
+                        { "name": "memo", "type": { "option": "string" } }
+                    ]
+                }
+            }],
+            "types": [{
+                "name": "Bid",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "bidder", "type": "publicKey" },
+                        { "name": "amount", "type": "u64" }
+                    ]
+                }
+            }, {
+                "name": "AuctionState",
+                "type": {
+                    "kind": "enum",
+                    "variants": [
+                        { "name": "Open" },
+                        { "name": "Settled", "fields": ["i64"] }
+                    ]
+                }
+            }]
+        }))
+        .unwrap();
+        idl.into()
+    }
+
+    #[test]
+    fn generated_accounts_deserialize_with_field_ranges() {
+        let idl = test_idl();
+        let config = IdlStrategyConfig::default().with_field_range("amount", 10..=20);
+        let strategy = idl
+            .account_strategy("Auction", Pubkey::new_unique(), &config)
+            .unwrap();
+        TestRunner::default()
+            .run(&strategy, |account| {
+                let (name, value) = idl.try_deserialize_account(&account).unwrap();
+                prop_assert_eq!(name, "Auction");
+                let amount = value["amount"].as_u64().unwrap();
+                prop_assert!((10..=20).contains(&amount));
+                for bid in value["bids"].as_array().unwrap() {
+                    prop_assert!((10..=20).contains(&bid["amount"].as_u64().unwrap()));
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn empty_field_ranges_are_rejected() {
+        let idl = test_idl();
+        #[allow(clippy::reversed_empty_ranges)]
+        let config = IdlStrategyConfig::default().with_field_range("amount", 20..=10);
+        let error = idl
+            .account_strategy("Auction", Pubkey::new_unique(), &config)
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "Empty range for field amount: 20..=10");
+    }
+
+    #[test]
+    fn generated_instruction_data_has_discriminator() {
+        let idl = test_idl();
+        let strategy = idl
+            .instruction_data_strategy("placeBid", &IdlStrategyConfig::default())
+            .unwrap();
+        TestRunner::default()
+            .run(&strategy, |data| {
+                prop_assert_eq!(&data[..8], &ix_discriminator("placeBid")[..]);
+                prop_assert_eq!(data.len(), 16);
+                Ok(())
+            })
+            .unwrap();
+    }
+}
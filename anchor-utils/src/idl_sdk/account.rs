@@ -7,6 +7,14 @@ use std::io::{Read, Write};
 
 /// Deserialize (and decompress) an IDL account, excluding its header.
 pub fn deserialize_idl_account(data: &[u8]) -> Result<Idl, AnchorIdlSdkError> {
+    let json = decompress_idl_account(data)?;
+    let idl: Idl =
+        serde_json::from_slice(&json[..]).map_err(|_| AnchorIdlSdkError::DeserializeError)?;
+    Ok(idl)
+}
+
+/// Decompress the IDL JSON of an IDL account, in whichever format it was written.
+pub fn decompress_idl_account(data: &[u8]) -> Result<Vec<u8>, AnchorIdlSdkError> {
     if data.len() < 8 {
         return Err(AnchorIdlSdkError::DeserializeError);
     }
@@ -19,9 +27,7 @@ pub fn deserialize_idl_account(data: &[u8]) -> Result<Idl, AnchorIdlSdkError> {
     let mut s = Vec::new();
     z.read_to_end(&mut s)
         .map_err(|_| AnchorIdlSdkError::DecompressionError)?;
-    let idl: Idl =
-        serde_json::from_slice(&s[..]).map_err(|_| AnchorIdlSdkError::DeserializeError)?;
-    Ok(idl)
+    Ok(s)
 }
 
 /// Serialize an IDL account, including the header.
@@ -29,7 +35,22 @@ pub fn serialize_idl_account(
     idl: &Idl,
     authority: Option<Pubkey>,
 ) -> Result<Vec<u8>, AnchorIdlSdkError> {
-    let idl_data = serialize_and_compress_idl(idl)?;
+    idl_account_data(serialize_and_compress_idl(idl)?, authority)
+}
+
+/// Serialize an IDL account from IDL JSON, including the header. The JSON is stored as-is,
+/// so that an Anchor 0.30 spec IDL keeps its layout.
+pub fn serialize_idl_json_account(
+    idl_json: &[u8],
+    authority: Option<Pubkey>,
+) -> Result<Vec<u8>, AnchorIdlSdkError> {
+    idl_account_data(compress_idl_json(idl_json)?, authority)
+}
+
+fn idl_account_data(
+    idl_data: Vec<u8>,
+    authority: Option<Pubkey>,
+) -> Result<Vec<u8>, AnchorIdlSdkError> {
     let header = IdlAccount {
         authority: authority.unwrap_or(Pubkey::new_unique()),
         data_len: idl_data.len() as u32,
@@ -45,8 +66,13 @@ pub fn serialize_idl_account(
 /// Serialize and compress an [Idl] (not the entire account data, excludes the header).
 pub fn serialize_and_compress_idl(idl: &Idl) -> Result<Vec<u8>, AnchorIdlSdkError> {
     let json_bytes = serde_json::to_vec(idl).map_err(|_| AnchorIdlSdkError::SerializeError)?;
+    compress_idl_json(&json_bytes)
+}
+
+/// Compress IDL JSON as-is, in whichever format it was written.
+pub fn compress_idl_json(json: &[u8]) -> Result<Vec<u8>, AnchorIdlSdkError> {
     let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
-    e.write_all(&json_bytes)
+    e.write_all(json)
         .map_err(|_| AnchorIdlSdkError::CompressionError)?;
     let data = e
         .finish()
@@ -1,4 +1,5 @@
 use crate::idl_sdk::account::serialize_and_compress_idl;
+use crate::idl_sdk::AnchorIdlSdkError;
 use anchor_lang::idl::{IdlAccount, IdlInstruction};
 use anchor_lang::{system_program, AnchorSerialize};
 use anchor_syn::idl::types::Idl;
@@ -6,6 +7,9 @@ use solana_program::instruction::{AccountMeta, Instruction};
 use solana_program::pubkey::Pubkey;
 use std::error::Error;
 
+/// The most IDL data written by a single `idl_write` instruction.
+pub const MAX_WRITE_SIZE: usize = 1000;
+
 /// Get several `idl_write` instructions to successively write data to an IDL account.
 pub fn idl_write_instructions(
     program_id: Pubkey,
@@ -13,28 +17,28 @@ pub fn idl_write_instructions(
     authority: Pubkey,
     idl: &Idl,
 ) -> Result<Vec<Instruction>, Box<dyn Error>> {
-    // Remove the metadata
+    let idl_data = compressed_idl_data(idl)?;
+    Ok(idl_write_chunks(program_id, buffer, authority, &idl_data))
+}
+
+/// The compressed IDL account data for an IDL, with its metadata removed.
+pub(crate) fn compressed_idl_data(idl: &Idl) -> Result<Vec<u8>, AnchorIdlSdkError> {
     let mut idl = idl.clone();
     idl.metadata = None;
-    // Serialize and compress the idl.
-    let idl_data = serialize_and_compress_idl(&idl)?;
+    serialize_and_compress_idl(&idl)
+}
 
-    // Create instructions
-    let mut instructions = vec![];
-    const MAX_WRITE_SIZE: usize = 1000;
-    let mut offset = 0;
-    while offset < idl_data.len() {
-        let start = offset;
-        let end = std::cmp::min(offset + MAX_WRITE_SIZE, idl_data.len());
-        instructions.push(idl_write(
-            program_id,
-            buffer,
-            authority,
-            idl_data[start..end].to_vec(),
-        ));
-        offset += MAX_WRITE_SIZE;
-    }
-    Ok(instructions)
+/// Split already compressed IDL data into `idl_write` instructions.
+pub(crate) fn idl_write_chunks(
+    program_id: Pubkey,
+    buffer: Pubkey,
+    authority: Pubkey,
+    idl_data: &[u8],
+) -> Vec<Instruction> {
+    idl_data
+        .chunks(MAX_WRITE_SIZE)
+        .map(|chunk| idl_write(program_id, buffer, authority, chunk.to_vec()))
+        .collect()
 }
 
 /// Create a program's IDL account.
@@ -112,6 +116,27 @@ pub fn close_account(
     }
 }
 
+/// Close an IDL buffer, e.g. once it has been copied into the IDL account with [set_buffer].
+pub fn close_buffer(
+    program_id: Pubkey,
+    buffer: Pubkey,
+    authority: Pubkey,
+    sol_destination: Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(buffer, false),
+        AccountMeta::new_readonly(authority, true),
+        AccountMeta::new(sol_destination, false),
+    ];
+    let mut data = anchor_lang::idl::IDL_IX_TAG.to_le_bytes().to_vec();
+    data.append(&mut IdlInstruction::Close.try_to_vec().unwrap());
+    Instruction {
+        program_id,
+        accounts,
+        data,
+    }
+}
+
 /// The "finishing" instruction of an IDL upgrade.
 /// Copies the trailing data from a source IDL buffer to a target IDL buffer.
 /// Does not directly modify the IDL account's header which stores the authority and data length.
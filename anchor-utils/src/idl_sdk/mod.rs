@@ -11,9 +11,13 @@ use thiserror::Error;
 
 pub mod account;
 pub mod instructions;
+pub mod publish;
 
 use crate::deserialize::AnchorDeserializer;
-pub use account::{deserialize_idl_account, serialize_idl_account};
+pub use account::{
+    decompress_idl_account, deserialize_idl_account, serialize_idl_account,
+    serialize_idl_json_account,
+};
 
 /// Verify that an IDL successfully deserializes a set of instructions and accounts.
 /// This is useful in tests to ensure that your data types are all accurately represented
@@ -0,0 +1,203 @@
+//! Instruction sequences that publish an IDL to a program's canonical IDL account,
+//! equivalent to `anchor idl init` and `anchor idl upgrade`.
+//!
+//! The IDL is taken as JSON bytes and published as-is, so that an Anchor 0.30 spec IDL
+//! is not rewritten in the legacy layout on its way on-chain.
+use crate::idl_sdk::account::compress_idl_json;
+use crate::idl_sdk::instructions::{
+    close_buffer, create_buffer, create_idl_account, idl_write_chunks, resize_account, set_buffer,
+};
+use crate::idl_sdk::AnchorIdlSdkError;
+use anchor_lang::idl::IdlAccount;
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_instruction;
+
+/// Account discriminator, authority and the length prefix of the compressed IDL data.
+pub const IDL_ACCOUNT_HEADER_LEN: usize = 8 + 32 + 4;
+
+/// The most space an IDL account can be created with, or grow by in a single resize.
+pub const MAX_IDL_ACCOUNT_GROWTH: usize = 10_000;
+
+/// The account space needed to hold the compressed IDL JSON, without room to grow.
+pub fn idl_account_space(idl_json: &[u8]) -> Result<usize, AnchorIdlSdkError> {
+    Ok(IDL_ACCOUNT_HEADER_LEN + compress_idl_json(idl_json)?.len())
+}
+
+/// Create the program's canonical IDL account, resize it and write the IDL to it.
+/// As with `anchor idl init`, the account is given twice the space of the compressed IDL,
+/// so that later upgrades fit. Each instruction must go in its own transaction, in order,
+/// all signed by the `authority`, which becomes the IDL authority.
+pub fn idl_init_instructions(
+    program_id: Pubkey,
+    authority: Pubkey,
+    idl_json: &[u8],
+) -> Result<Vec<Instruction>, AnchorIdlSdkError> {
+    let idl_data = compress_idl_json(idl_json)?;
+    let data_len = idl_data.len() * 2;
+    let space = IDL_ACCOUNT_HEADER_LEN + data_len;
+
+    let mut instructions = vec![create_idl_account(program_id, authority, data_len as u64)];
+    let mut allocated = space.min(MAX_IDL_ACCOUNT_GROWTH);
+    while allocated < space {
+        instructions.push(resize_account(program_id, authority, space as u64));
+        allocated += MAX_IDL_ACCOUNT_GROWTH;
+    }
+    instructions.extend(idl_write_chunks(
+        program_id,
+        IdlAccount::address(&program_id),
+        authority,
+        &idl_data,
+    ));
+    Ok(instructions)
+}
+
+/// Replace the IDL in the program's canonical IDL account, by way of a new `buffer` account.
+/// Returns one group of instructions per transaction, to be sent in order and signed
+/// by the IDL `authority`. The first transaction creates the buffer, and must also be signed
+/// by the `buffer` keypair. The last one copies the buffer into the IDL account, and closes it.
+///
+/// The existing IDL account must have at least [idl_account_space] bytes, otherwise
+/// it has to be closed with [crate::idl_sdk::instructions::close_account] and re-initialized
+/// with [idl_init_instructions].
+pub fn idl_upgrade_instructions(
+    program_id: Pubkey,
+    buffer: Pubkey,
+    authority: Pubkey,
+    idl_json: &[u8],
+    rent: &Rent,
+) -> Result<Vec<Vec<Instruction>>, AnchorIdlSdkError> {
+    let idl_data = compress_idl_json(idl_json)?;
+    let space = IDL_ACCOUNT_HEADER_LEN + idl_data.len();
+
+    let mut transactions = vec![vec![
+        system_instruction::create_account(
+            &authority,
+            &buffer,
+            rent.minimum_balance(space),
+            space as u64,
+            &program_id,
+        ),
+        create_buffer(program_id, buffer, authority),
+    ]];
+    transactions.extend(
+        idl_write_chunks(program_id, buffer, authority, &idl_data)
+            .into_iter()
+            .map(|ix| vec![ix]),
+    );
+    transactions.push(vec![
+        set_buffer(program_id, buffer, authority),
+        close_buffer(program_id, buffer, authority, authority),
+    ]);
+    Ok(transactions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::idl_sdk::instructions::MAX_WRITE_SIZE;
+    use anchor_lang::idl::IdlInstruction;
+    use anchor_lang::AnchorDeserialize;
+    use anchor_syn::idl::types::{Idl, IdlField, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy};
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    fn large_idl() -> Vec<u8> {
+        // Unique names defeat compression, so that the IDL needs several resizes.
+        let types = (0..3000)
+            .map(|i| IdlTypeDefinition {
+                name: format!("Type{}", Pubkey::new_unique()),
+                generics: None,
+                docs: None,
+                ty: IdlTypeDefinitionTy::Struct {
+                    fields: vec![IdlField {
+                        name: format!("field{i}"),
+                        docs: None,
+                        ty: IdlType::U64,
+                    }],
+                },
+            })
+            .collect();
+        let idl = Idl {
+            version: "0.1.0".to_string(),
+            name: "large".to_string(),
+            docs: None,
+            constants: vec![],
+            instructions: vec![],
+            accounts: vec![],
+            types,
+            events: None,
+            errors: None,
+            metadata: None,
+        };
+        serde_json::to_vec(&idl).unwrap()
+    }
+
+    fn idl_instruction(ix: &Instruction) -> IdlInstruction {
+        IdlInstruction::deserialize(&mut &ix.data[8..]).unwrap()
+    }
+
+    #[test]
+    fn init_resizes_and_writes_the_compressed_idl() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let idl = large_idl();
+        let idl_data = compress_idl_json(&idl).unwrap();
+        let space = IDL_ACCOUNT_HEADER_LEN + idl_data.len() * 2;
+        assert!(space > 2 * MAX_IDL_ACCOUNT_GROWTH);
+
+        let instructions = idl_init_instructions(program_id, authority, &idl).unwrap();
+        assert!(matches!(
+            idl_instruction(&instructions[0]),
+            IdlInstruction::Create { data_len } if data_len as usize == idl_data.len() * 2
+        ));
+        let resizes = instructions[1..]
+            .iter()
+            .take_while(|ix| matches!(idl_instruction(ix), IdlInstruction::Resize { .. }))
+            .count();
+        assert_eq!(resizes, (space - 1) / MAX_IDL_ACCOUNT_GROWTH);
+
+        let mut written = vec![];
+        for ix in &instructions[1 + resizes..] {
+            assert_eq!(ix.accounts[0].pubkey, IdlAccount::address(&program_id));
+            let IdlInstruction::Write { data } = idl_instruction(ix) else {
+                panic!("expected a write instruction");
+            };
+            assert!(data.len() <= MAX_WRITE_SIZE);
+            written.extend(data);
+        }
+        assert_eq!(written, idl_data);
+    }
+
+    #[test]
+    fn spec_idls_are_published_as_is() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let buffer = Pubkey::new_unique();
+        let idl_json = serde_json::to_vec(&serde_json::json!({
+            "address": program_id.to_string(),
+            "metadata": {"name": "spec", "version": "0.1.0", "spec": "0.1.0"},
+            "instructions": [],
+            "accounts": [],
+            "types": [],
+        }))
+        .unwrap();
+
+        let transactions =
+            idl_upgrade_instructions(program_id, buffer, authority, &idl_json, &Rent::default())
+                .unwrap();
+        let mut written = vec![];
+        for ix in transactions[1..transactions.len() - 1].iter().flatten() {
+            let IdlInstruction::Write { data } = idl_instruction(ix) else {
+                panic!("expected a write instruction");
+            };
+            written.extend(data);
+        }
+        let mut published = vec![];
+        ZlibDecoder::new(&written[..])
+            .read_to_end(&mut published)
+            .unwrap();
+        assert_eq!(published, idl_json);
+    }
+}
@@ -1,5 +1,11 @@
 pub mod account_data;
 #[cfg(feature = "client")]
 pub mod client;
+pub mod codegen;
 pub mod deserialize;
+pub mod diff;
+#[cfg(feature = "proptest")]
+pub mod generate;
 pub mod idl_sdk;
+pub mod migrate;
+pub mod serialize;
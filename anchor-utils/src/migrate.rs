@@ -0,0 +1,288 @@
+//! Re-encode account data written under one version of a program's IDL for another version,
+//! e.g. to clone a mainnet account into a test validator running an upgraded program.
+//!
+//! The account is decoded with the old IDL, its fields are matched by name to the new layout,
+//! and the result is serialized with the new IDL, see [crate::serialize].
+use crate::deserialize::discriminator::partition_discriminator_from_data;
+use crate::deserialize::IdlWithDiscriminators;
+use anchor_syn::idl::types::{IdlType, IdlTypeDefinitionTy};
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// How fields of the new layout get their values. Fields are addressed by their dotted path
+/// in the new layout, e.g. `"config.fee_bps"`. Elements of vectors and arrays share the path
+/// of the vector or array.
+///
+/// By default a field takes the value of the old field of the same name.
+/// Old fields without a counterpart in the new layout are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct FieldMapping {
+    /// The account type in the new IDL. Defaults to the name of the old account type.
+    pub account_type: Option<String>,
+    /// New field paths to the name of the old field, in the same parent, that they replace.
+    pub renamed: HashMap<String, String>,
+    /// Values for new fields without an old counterpart. Optional fields default to `null`.
+    pub defaults: HashMap<String, Value>,
+}
+
+impl FieldMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn account_type(mut self, name: &str) -> Self {
+        self.account_type = Some(name.to_string());
+        self
+    }
+
+    pub fn rename(mut self, new_path: &str, old_name: &str) -> Self {
+        self.renamed
+            .insert(new_path.to_string(), old_name.to_string());
+        self
+    }
+
+    pub fn default_value(mut self, new_path: &str, value: Value) -> Self {
+        self.defaults.insert(new_path.to_string(), value);
+        self
+    }
+}
+
+/// Decode Anchor account data with `old`, and re-encode it, discriminator included,
+/// in the layout of the matching account type of `new`. The output is not padded,
+/// so it may be shorter than the space allocated for the account.
+pub fn migrate_account_data(
+    old: &IdlWithDiscriminators,
+    new: &IdlWithDiscriminators,
+    data: &[u8],
+    mapping: &FieldMapping,
+) -> Result<Vec<u8>> {
+    let (discriminator, body) = partition_discriminator_from_data(data);
+    let old_type = old.get_account_definition(&discriminator).ok_or(anyhow!(
+        "Could not match account data against any discriminator"
+    ))?;
+    let value = old.deserialize_struct_or_enum(old_type, &mut &body[..])?;
+
+    let new_name = mapping.account_type.as_deref().unwrap_or(&old_type.name);
+    let new_discriminator = new
+        .account_discriminator_by_name(new_name)
+        .ok_or(anyhow!("New IDL has no account type named {}", new_name))?;
+    let new_type = new
+        .get_account_definition(&new_discriminator)
+        .ok_or(anyhow!("New IDL has no account type named {}", new_name))?;
+    let value = match &new_type.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            let fields: Vec<(&str, &IdlType)> = fields
+                .iter()
+                .map(|field| (field.name.as_str(), &field.ty))
+                .collect();
+            migrate_fields(new, mapping, "", &fields, value)?
+        }
+        _ => value,
+    };
+
+    let mut out = new_discriminator.to_vec();
+    new.serialize_struct_or_enum(new_type, &value, &mut out)?;
+    Ok(out)
+}
+
+fn migrate_fields(
+    new: &IdlWithDiscriminators,
+    mapping: &FieldMapping,
+    prefix: &str,
+    fields: &[(&str, &IdlType)],
+    value: Value,
+) -> Result<Value> {
+    let mut old = match value {
+        Value::Object(object) => object,
+        value => return Err(anyhow!("Expected an object at {}, found {}", prefix, value)),
+    };
+    let mut migrated = Map::new();
+    for (name, ty) in fields {
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        let old_name = mapping.renamed.get(&path).map_or(*name, String::as_str);
+        let value = match (old.remove(old_name), mapping.defaults.get(&path)) {
+            (Some(value), _) => migrate_value(new, mapping, &path, ty, value)?,
+            (None, Some(default)) => default.clone(),
+            (None, None) if matches!(ty, IdlType::Option(_)) => Value::Null,
+            (None, None) => return Err(anyhow!("No value for new field {}", path)),
+        };
+        migrated.insert(name.to_string(), value);
+    }
+    Ok(Value::Object(migrated))
+}
+
+fn migrate_value(
+    new: &IdlWithDiscriminators,
+    mapping: &FieldMapping,
+    path: &str,
+    ty: &IdlType,
+    value: Value,
+) -> Result<Value> {
+    match (ty, value) {
+        (IdlType::Option(_), Value::Null) => Ok(Value::Null),
+        (IdlType::Option(ty), value) => migrate_value(new, mapping, path, ty, value),
+        (IdlType::Vec(ty) | IdlType::Array(ty, _), Value::Array(values)) => values
+            .into_iter()
+            .map(|value| migrate_value(new, mapping, path, ty, value))
+            .collect::<Result<_>>()
+            .map(Value::Array),
+        (IdlType::Defined(name), value) => {
+            match new
+                .find_type_definition_by_name(name)
+                .map(|(_, def)| &def.ty)
+            {
+                Some(IdlTypeDefinitionTy::Struct { fields }) => {
+                    let fields: Vec<(&str, &IdlType)> = fields
+                        .iter()
+                        .map(|field| (field.name.as_str(), &field.ty))
+                        .collect();
+                    migrate_fields(new, mapping, path, &fields, value)
+                }
+                _ => Ok(value),
+            }
+        }
+        (_, value) => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_syn::idl::types::Idl;
+    use serde_json::json;
+    use solana_sdk::account::Account;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn idl(pool_fields: Value, config_fields: Value) -> IdlWithDiscriminators {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "pools",
+            "instructions": [],
+            "accounts": [{
+                "name": "Pool",
+                "type": { "kind": "struct", "fields": pool_fields },
+            }],
+            "types": [{
+                "name": "Config",
+                "type": { "kind": "struct", "fields": config_fields },
+            }, {
+                "name": "Mode",
+                "type": { "kind": "enum", "variants": [
+                    { "name": "Off" },
+                    { "name": "Capped", "fields": [{ "name": "max", "type": "u64" }] },
+                ]},
+            }],
+        }))
+        .unwrap();
+        IdlWithDiscriminators::new(idl)
+    }
+
+    #[test]
+    fn accounts_are_migrated_by_field_name() {
+        let old = idl(
+            json!([
+                { "name": "authority", "type": "publicKey" },
+                { "name": "fee", "type": "u16" },
+                { "name": "configs", "type": { "vec": { "defined": "Config" } } },
+                { "name": "legacy", "type": "bool" },
+            ]),
+            json!([{ "name": "limit", "type": "u64" }]),
+        );
+        let new = idl(
+            json!([
+                { "name": "feeBps", "type": "u16" },
+                { "name": "authority", "type": "publicKey" },
+                { "name": "configs", "type": { "vec": { "defined": "Config" } } },
+                { "name": "bump", "type": "u8" },
+                { "name": "delegate", "type": { "option": "publicKey" } },
+            ]),
+            json!([
+                { "name": "limit", "type": "u64" },
+                { "name": "paused", "type": "bool" },
+            ]),
+        );
+        let authority = Pubkey::new_unique();
+        let mut data = old.account_discriminator_by_name("Pool").unwrap().to_vec();
+        old.serialize_named_fields(
+            match &old.get_account_definition_by_name("Pool").unwrap().ty {
+                IdlTypeDefinitionTy::Struct { fields } => fields,
+                _ => unreachable!(),
+            },
+            &json!({
+                "authority": authority.to_string(),
+                "fee": 30,
+                "configs": [{ "limit": 5 }, { "limit": 6 }],
+                "legacy": true,
+            }),
+            &mut data,
+        )
+        .unwrap();
+
+        let unmapped = migrate_account_data(&old, &new, &data, &FieldMapping::new());
+        assert!(unmapped.unwrap_err().to_string().contains("feeBps"));
+
+        let mapping = FieldMapping::new()
+            .rename("feeBps", "fee")
+            .default_value("bump", json!(254))
+            .default_value("configs.paused", json!(false));
+        let migrated = migrate_account_data(&old, &new, &data, &mapping).unwrap();
+        let (account_type, value) = new
+            .try_deserialize_account(&Account {
+                data: migrated,
+                ..Account::default()
+            })
+            .unwrap();
+        assert_eq!(account_type, "Pool");
+        assert_eq!(
+            value,
+            json!({
+                "feeBps": 30,
+                "authority": authority.to_string(),
+                "configs": [{ "limit": 5, "paused": false }, { "limit": 6, "paused": false }],
+                "bump": 254,
+                "delegate": null,
+            })
+        );
+    }
+
+    #[test]
+    fn enum_variants_are_migrated() {
+        let old = idl(
+            json!([{ "name": "mode", "type": { "defined": "Mode" } }]),
+            json!([]),
+        );
+        let new = idl(
+            json!([
+                { "name": "mode", "type": { "defined": "Mode" } },
+                { "name": "bump", "type": "u8" },
+            ]),
+            json!([]),
+        );
+        let mode = json!({ "name": "Capped", "fields": { "max": 7 } });
+        let mut data = old.account_discriminator_by_name("Pool").unwrap().to_vec();
+        old.serialize_named_fields(
+            match &old.get_account_definition_by_name("Pool").unwrap().ty {
+                IdlTypeDefinitionTy::Struct { fields } => fields,
+                _ => unreachable!(),
+            },
+            &json!({ "mode": mode }),
+            &mut data,
+        )
+        .unwrap();
+
+        let mapping = FieldMapping::new().default_value("bump", json!(1));
+        let migrated = migrate_account_data(&old, &new, &data, &mapping).unwrap();
+        let (_, value) = new
+            .try_deserialize_account(&Account {
+                data: migrated,
+                ..Account::default()
+            })
+            .unwrap();
+        assert_eq!(value, json!({ "mode": mode, "bump": 1 }));
+    }
+}
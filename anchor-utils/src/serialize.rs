@@ -0,0 +1,396 @@
+//! Serialize JSON values according to an IDL, the inverse of [crate::deserialize].
+//! Values take the same shape as deserialized output, so that decoded instructions
+//! and accounts can be edited and re-encoded.
+use crate::deserialize::discriminator::{ix_discriminator, Discriminator};
+use crate::deserialize::IdlWithDiscriminators;
+use anchor_syn::idl::types::{
+    EnumFields, IdlAccountItem, IdlField, IdlInstruction, IdlType, IdlTypeDefinition,
+    IdlTypeDefinitionTy,
+};
+use anyhow::{anyhow, bail, Result};
+use heck::SnakeCase;
+use serde_json::{Map, Value};
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+macro_rules! serialize_int {
+    ($ty:ty, $value:expr, $out:expr) => {{
+        let value: $ty = match $value {
+            Value::Number(n) => {
+                let n = n
+                    .as_u64()
+                    .map(i128::from)
+                    .or_else(|| n.as_i64().map(i128::from))
+                    .ok_or(anyhow!("{} is not an integer", n))?;
+                <$ty>::try_from(n)
+                    .map_err(|_| anyhow!("{} is out of range for {}", n, stringify!($ty)))?
+            }
+            Value::String(s) => s
+                .parse()
+                .map_err(|_| anyhow!("{} is not a valid {}", s, stringify!($ty)))?,
+            value => bail!("Expected an integer, found {}", value),
+        };
+        $out.extend(value.to_le_bytes());
+    }};
+}
+
+macro_rules! serialize_float {
+    ($ty:ty, $value:expr, $out:expr) => {{
+        let value: $ty = match $value {
+            Value::Number(n) => n.as_f64().ok_or(anyhow!("{} is not a float", n))? as $ty,
+            Value::String(s) => s
+                .parse()
+                .map_err(|_| anyhow!("{} is not a valid {}", s, stringify!($ty)))?,
+            value => bail!("Expected a float, found {}", value),
+        };
+        $out.extend(value.to_le_bytes());
+    }};
+}
+
+impl IdlWithDiscriminators {
+    /// Build an instruction from its name in the IDL, its arguments as a JSON object
+    /// keyed by argument name, and its accounts keyed by name.
+    ///
+    /// Accounts in a nested accounts struct are looked up by their dotted path, e.g.
+    /// `"vault.state"`, and then by their name alone. Missing optional accounts are
+    /// passed as the program ID, as Anchor expects.
+    pub fn build_instruction(
+        &self,
+        program_id: &Pubkey,
+        name: &str,
+        args: &Value,
+        accounts: &HashMap<String, Pubkey>,
+    ) -> Result<Instruction> {
+        let (discriminator, ix) = self
+            .find_instruction(name)
+            .ok_or(anyhow!("IDL has no instruction named {}", name))?;
+        let args = match args {
+            Value::Object(args) => args.clone(),
+            Value::Null => Map::new(),
+            args => bail!("Expected instruction args as an object, found {}", args),
+        };
+        if let Some(unknown) = args
+            .keys()
+            .find(|key| !ix.args.iter().any(|arg| &arg.name == *key))
+        {
+            bail!("Instruction {} has no argument named {}", ix.name, unknown);
+        }
+        let mut data = discriminator.to_vec();
+        self.serialize_named_fields(&ix.args, &Value::Object(args), &mut data)?;
+        let mut metas = vec![];
+        account_metas(program_id, "", &ix.accounts, accounts, &mut metas)?;
+        Ok(Instruction {
+            program_id: *program_id,
+            accounts: metas,
+            data,
+        })
+    }
+
+    /// Find an instruction by name, in either snake case or camel case.
+    fn find_instruction(&self, name: &str) -> Option<(Discriminator, &IdlInstruction)> {
+        let name = name.to_snake_case();
+        let global = ix_discriminator(&name);
+        match self.instruction_definitions.get(&global) {
+            Some(ix) if ix.name.to_snake_case() == name => Some((global, ix)),
+            _ => self
+                .instruction_definitions
+                .iter()
+                .find(|(_, ix)| ix.name.to_snake_case() == name)
+                .map(|(discriminator, ix)| (*discriminator, ix)),
+        }
+    }
+
+    /// Serialize a value as a struct or enum. Enum variants are given as
+    /// `{"name": <variant>, "fields": <object, array or null>}`, or just the variant
+    /// name for variants without fields.
+    pub fn serialize_struct_or_enum(
+        &self,
+        type_definition: &IdlTypeDefinition,
+        value: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        match &type_definition.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                self.serialize_named_fields(fields, value, out)
+            }
+            IdlTypeDefinitionTy::Enum { variants } => {
+                let (name, fields) = match value {
+                    Value::String(name) => (name.as_str(), &Value::Null),
+                    Value::Object(variant) => (
+                        variant
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .ok_or(anyhow!("Enum variant is missing its name"))?,
+                        variant.get("fields").unwrap_or(&Value::Null),
+                    ),
+                    value => bail!("Expected an enum variant, found {}", value),
+                };
+                let (index, variant) = variants
+                    .iter()
+                    .enumerate()
+                    .find(|(_, variant)| variant.name == name)
+                    .ok_or(anyhow!(
+                        "{} has no variant named {}",
+                        type_definition.name,
+                        name
+                    ))?;
+                out.push(index as u8);
+                match &variant.fields {
+                    None => Ok(()),
+                    Some(EnumFields::Named(named)) => {
+                        self.serialize_named_fields(named, fields, out)
+                    }
+                    Some(EnumFields::Tuple(types)) => {
+                        let values = fields
+                            .as_array()
+                            .filter(|values| values.len() == types.len())
+                            .ok_or(anyhow!(
+                                "Expected {} fields for variant {}",
+                                types.len(),
+                                name
+                            ))?;
+                        types
+                            .iter()
+                            .zip(values)
+                            .try_for_each(|(ty, value)| self.serialize_idl_type(ty, value, out))
+                    }
+                }
+            }
+            IdlTypeDefinitionTy::Alias { value: ty } => self.serialize_idl_type(ty, value, out),
+        }
+    }
+
+    /// Serialize the fields of a JSON object in the order given by the IDL.
+    pub fn serialize_named_fields(
+        &self,
+        fields: &[IdlField],
+        value: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let object = value
+            .as_object()
+            .ok_or(anyhow!("Expected an object, found {}", value))?;
+        for field in fields {
+            let value = object
+                .get(&field.name)
+                .ok_or(anyhow!("Missing field {}", field.name))?;
+            self.serialize_idl_type(&field.ty, value, out)
+                .map_err(|e| anyhow!("{}: {}", field.name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Serialize a value in the borsh layout of an [IdlType]. Integers may be given
+    /// as JSON numbers or strings, and pubkeys as base58 strings.
+    pub fn serialize_idl_type(
+        &self,
+        idl_type: &IdlType,
+        value: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        match idl_type {
+            IdlType::Bool => {
+                let value = value
+                    .as_bool()
+                    .ok_or(anyhow!("Expected a bool, found {}", value))?;
+                out.push(value as u8);
+            }
+            IdlType::U8 => serialize_int!(u8, value, out),
+            IdlType::I8 => serialize_int!(i8, value, out),
+            IdlType::U16 => serialize_int!(u16, value, out),
+            IdlType::I16 => serialize_int!(i16, value, out),
+            IdlType::U32 => serialize_int!(u32, value, out),
+            IdlType::I32 => serialize_int!(i32, value, out),
+            IdlType::U64 => serialize_int!(u64, value, out),
+            IdlType::I64 => serialize_int!(i64, value, out),
+            IdlType::U128 => serialize_int!(u128, value, out),
+            IdlType::I128 => serialize_int!(i128, value, out),
+            IdlType::F32 => serialize_float!(f32, value, out),
+            IdlType::F64 => serialize_float!(f64, value, out),
+            IdlType::Bytes => {
+                let bytes = value
+                    .as_array()
+                    .ok_or(anyhow!("Expected an array of bytes, found {}", value))?;
+                out.extend((bytes.len() as u32).to_le_bytes());
+                for byte in bytes {
+                    serialize_int!(u8, byte, out);
+                }
+            }
+            IdlType::String => {
+                let value = value
+                    .as_str()
+                    .ok_or(anyhow!("Expected a string, found {}", value))?;
+                out.extend((value.len() as u32).to_le_bytes());
+                out.extend(value.as_bytes());
+            }
+            IdlType::PublicKey => {
+                let pubkey = value
+                    .as_str()
+                    .and_then(|s| Pubkey::from_str(s).ok())
+                    .ok_or(anyhow!("Expected a pubkey, found {}", value))?;
+                out.extend(pubkey.to_bytes());
+            }
+            IdlType::Defined(defined_type) => {
+                let (_, ty_def) = self
+                    .find_type_definition_by_name(defined_type)
+                    .ok_or(anyhow!("Couldn't find defined type: {}", defined_type))?;
+                self.serialize_struct_or_enum(ty_def, value, out)?;
+            }
+            IdlType::Option(idl_type) => match value {
+                Value::Null => out.push(0),
+                value => {
+                    out.push(1);
+                    self.serialize_idl_type(idl_type, value, out)?;
+                }
+            },
+            IdlType::Vec(idl_type) => {
+                let values = value
+                    .as_array()
+                    .ok_or(anyhow!("Expected an array, found {}", value))?;
+                out.extend((values.len() as u32).to_le_bytes());
+                for value in values {
+                    self.serialize_idl_type(idl_type, value, out)?;
+                }
+            }
+            IdlType::Array(idl_type, len) => {
+                let values = value
+                    .as_array()
+                    .filter(|values| values.len() == *len)
+                    .ok_or(anyhow!("Expected an array of {}, found {}", len, value))?;
+                for value in values {
+                    self.serialize_idl_type(idl_type, value, out)?;
+                }
+            }
+            _ => bail!("U256, I256 and generic types are not yet supported"),
+        }
+        Ok(())
+    }
+}
+
+fn account_metas(
+    program_id: &Pubkey,
+    prefix: &str,
+    items: &[IdlAccountItem],
+    accounts: &HashMap<String, Pubkey>,
+    metas: &mut Vec<AccountMeta>,
+) -> Result<()> {
+    for item in items {
+        match item {
+            IdlAccountItem::IdlAccount(act) => {
+                let path = format!("{}{}", prefix, act.name);
+                let meta = match accounts.get(&path).or_else(|| accounts.get(&act.name)) {
+                    Some(pubkey) => AccountMeta {
+                        pubkey: *pubkey,
+                        is_signer: act.is_signer,
+                        is_writable: act.is_mut,
+                    },
+                    // Anchor reads a missing optional account as the program id, passed readonly.
+                    None if act.is_optional == Some(true) => {
+                        AccountMeta::new_readonly(*program_id, false)
+                    }
+                    None => bail!("Missing account {}", path),
+                };
+                metas.push(meta);
+            }
+            IdlAccountItem::IdlAccounts(nested) => {
+                let prefix = format!("{}{}.", prefix, nested.name);
+                account_metas(program_id, &prefix, &nested.accounts, accounts, metas)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_syn::idl::types::Idl;
+    use serde_json::json;
+
+    #[test]
+    fn instructions_are_built_from_json_args() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [{
+                "name": "configure",
+                "accounts": [
+                    { "name": "authority", "isMut": false, "isSigner": true },
+                    { "name": "vault", "accounts": [
+                        { "name": "state", "isMut": true, "isSigner": false },
+                    ]},
+                    { "name": "oracle", "isMut": true, "isSigner": false, "isOptional": true },
+                ],
+                "args": [
+                    { "name": "limit", "type": "u128" },
+                    { "name": "owner", "type": "publicKey" },
+                    { "name": "memo", "type": { "option": "string" } },
+                    { "name": "mode", "type": { "defined": "Mode" } },
+                    { "name": "weights", "type": { "vec": { "array": ["u16", 2] } } },
+                ],
+            }],
+            "types": [{
+                "name": "Mode",
+                "type": { "kind": "enum", "variants": [
+                    { "name": "Off" },
+                    { "name": "Fixed", "fields": ["u8", "bool"] },
+                    { "name": "Capped", "fields": [{ "name": "max", "type": "u64" }] },
+                ]},
+            }],
+        }))
+        .unwrap();
+        let idl = IdlWithDiscriminators::new(idl);
+        let program_id = Pubkey::new_unique();
+        let (authority, state, owner) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let args = json!({
+            "limit": "340282366920938463463374607431768211455",
+            "owner": owner.to_string(),
+            "memo": null,
+            "mode": { "name": "Fixed", "fields": [3, true] },
+            "weights": [[1, 2], [3, 4]],
+        });
+        let accounts = HashMap::from([
+            ("authority".to_string(), authority),
+            ("vault.state".to_string(), state),
+        ]);
+
+        let ix = idl
+            .build_instruction(&program_id, "configure", &args, &accounts)
+            .unwrap();
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(state, false),
+                AccountMeta::new_readonly(program_id, false),
+            ]
+        );
+        let (decoded_ix, decoded) = idl.try_deserialize_instruction_data(&ix.data).unwrap();
+        assert_eq!(decoded_ix.name, "configure");
+        assert_eq!(decoded, args);
+
+        let missing = HashMap::from([("authority".to_string(), authority)]);
+        assert!(idl
+            .build_instruction(&program_id, "configure", &args, &missing)
+            .is_err());
+        let mut capped = args.clone();
+        capped["mode"] = json!({ "name": "Capped", "fields": { "max": 9 } });
+        let ix = idl
+            .build_instruction(&program_id, "configure", &capped, &accounts)
+            .unwrap();
+        let (_, decoded) = idl.try_deserialize_instruction_data(&ix.data).unwrap();
+        assert_eq!(decoded, capped);
+
+        let mut typo = args.clone();
+        typo["limits"] = json!(1);
+        assert!(idl
+            .build_instruction(&program_id, "configure", &typo, &accounts)
+            .is_err());
+    }
+}
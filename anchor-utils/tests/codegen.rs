@@ -0,0 +1,51 @@
+//! Compiles the client generated from `codegen/auction.json`, which
+//! `codegen::tests::client_is_generated` keeps up to date with the generator.
+// Kept exactly as generated.
+#[rustfmt::skip]
+#[path = "codegen/auction.rs"]
+mod auction;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, AccountSerialize, Discriminator};
+use solana_program::instruction::Instruction;
+
+#[test]
+fn generated_client_builds_instructions_and_accounts() {
+    let seller = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let (auction_address, _) = auction::pda::find_auction_address(&seller, 7);
+    let builder = auction::builder::PlaceBid {
+        accounts: auction::accounts::PlaceBid {
+            auction: auction_address,
+            seller,
+            bidder_authority: authority,
+            bidder_referrer: None,
+        },
+        args: auction::instruction::PlaceBid {
+            auction_id: 7,
+            r#type: auction::BidType::Limit(100),
+        },
+    };
+    let instructions: Vec<Instruction> = builder.into();
+    let ix = &instructions[0];
+    assert_eq!(ix.program_id, auction::ID);
+    assert_eq!(
+        ix.data[..8],
+        <auction::instruction::PlaceBid as Discriminator>::DISCRIMINATOR
+    );
+    assert_eq!(ix.accounts.len(), 4);
+    assert!(ix.accounts[2].is_signer && ix.accounts[2].is_writable);
+    assert_eq!(ix.accounts[3].pubkey, auction::ID);
+
+    let account = auction::Auction {
+        seller,
+        bids: vec![[1, 2, 3, 4]],
+        memo: Some("reserve met".to_string()),
+    };
+    let mut data = vec![];
+    account.try_serialize(&mut data).unwrap();
+    assert_eq!(
+        auction::Auction::try_deserialize(&mut data.as_slice()).unwrap(),
+        account
+    );
+}
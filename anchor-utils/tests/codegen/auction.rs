@@ -0,0 +1,145 @@
+//! A client for the `auction` program, generated from its IDL.
+#![allow(dead_code)]
+// The borsh derives refer to the `borsh` version of `anchor-lang`.
+use anchor_lang::prelude::borsh;
+
+pub const ID: anchor_lang::prelude::Pubkey = anchor_lang::solana_program::pubkey!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum BidType {
+    Open,
+    Sealed {
+        hash: [u8; 32],
+    },
+    Limit(u64),
+}
+
+#[derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct Auction {
+    pub seller: anchor_lang::prelude::Pubkey,
+    pub bids: Vec<[u8; 4]>,
+    pub memo: Option<String>,
+}
+
+impl anchor_lang::Discriminator for Auction {
+    const DISCRIMINATOR: [u8; 8] = [218, 94, 247, 242, 126, 233, 131, 81];
+}
+
+impl anchor_lang::Owner for Auction {
+    fn owner() -> anchor_lang::prelude::Pubkey {
+        ID
+    }
+}
+
+impl anchor_lang::AccountSerialize for Auction {
+    fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
+        if writer.write_all(&<Self as anchor_lang::Discriminator>::DISCRIMINATOR).is_err() {
+            return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+        }
+        if anchor_lang::AnchorSerialize::serialize(self, writer).is_err() {
+            return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+        }
+        Ok(())
+    }
+}
+
+impl anchor_lang::AccountDeserialize for Auction {
+    fn try_deserialize(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        if buf.len() < 8 {
+            return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        if buf[..8] != <Self as anchor_lang::Discriminator>::DISCRIMINATOR {
+            return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        let mut data: &[u8] = &buf[8..];
+        anchor_lang::AnchorDeserialize::deserialize(&mut data)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
+
+/// Instruction data.
+pub mod instruction {
+    use super::*;
+
+    /// Bid on an open auction.
+    #[derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize, Clone, Debug, PartialEq)]
+    pub struct PlaceBid {
+        pub auction_id: u64,
+        pub r#type: BidType,
+    }
+
+    impl anchor_lang::Discriminator for PlaceBid {
+        const DISCRIMINATOR: [u8; 8] = [238, 77, 148, 91, 200, 151, 92, 146];
+    }
+
+    impl anchor_lang::InstructionData for PlaceBid {}
+}
+
+/// The accounts of each instruction, in order.
+pub mod accounts {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct PlaceBid {
+        pub auction: anchor_lang::prelude::Pubkey,
+        pub seller: anchor_lang::prelude::Pubkey,
+        pub bidder_authority: anchor_lang::prelude::Pubkey,
+        pub bidder_referrer: Option<anchor_lang::prelude::Pubkey>,
+    }
+
+    impl anchor_lang::ToAccountMetas for PlaceBid {
+        fn to_account_metas(
+            &self,
+            is_signer: Option<bool>,
+        ) -> Vec<anchor_lang::prelude::AccountMeta> {
+            vec![
+                anchor_lang::prelude::AccountMeta::new(self.auction, false),
+                anchor_lang::prelude::AccountMeta::new_readonly(self.seller, false),
+                anchor_lang::prelude::AccountMeta::new(self.bidder_authority, is_signer.unwrap_or(true)),
+                match self.bidder_referrer {
+                    Some(pubkey) => anchor_lang::prelude::AccountMeta::new_readonly(pubkey, false),
+                    None => anchor_lang::prelude::AccountMeta::new_readonly(ID, false),
+                },
+            ]
+        }
+    }
+}
+
+/// Instruction builders, which convert into `Vec<Instruction>`.
+pub mod builder {
+    use super::*;
+    use anchor_lang::solana_program::instruction::Instruction;
+    use anchor_lang::{InstructionData, ToAccountMetas};
+
+    /// Bid on an open auction.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct PlaceBid {
+        pub accounts: accounts::PlaceBid,
+        pub args: instruction::PlaceBid,
+    }
+
+    impl PlaceBid {
+        pub fn instruction(&self) -> Instruction {
+            Instruction::new_with_bytes(ID, &self.args.data(), self.accounts.to_account_metas(None))
+        }
+    }
+
+    impl From<PlaceBid> for Vec<Instruction> {
+        fn from(value: PlaceBid) -> Self {
+            vec![value.instruction()]
+        }
+    }
+}
+
+/// Program derived addresses, with their bumps.
+pub mod pda {
+    use super::*;
+
+    pub fn find_auction_address(seller: &anchor_lang::prelude::Pubkey, auction_id: u64) -> (anchor_lang::prelude::Pubkey, u8) {
+        anchor_lang::prelude::Pubkey::find_program_address(&[&[97, 117, 99, 116, 105, 111, 110], seller.as_ref(), &auction_id.to_le_bytes()], &ID)
+    }
+}
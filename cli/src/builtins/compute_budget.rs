@@ -0,0 +1,34 @@
+use crate::context::CliContext;
+use crate::plugin::PluginSubcommand;
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Subcommand;
+use solana_devtools_tx::compute_budget::priority_fee;
+use solana_sdk::borsh0_10;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+
+#[derive(Debug, Subcommand)]
+pub enum ComputeBudgetCommand {
+    DeserializeComputeIx { hex_data: String },
+    CalculatePriorityFee { microlamports: u64, budget: u32 },
+}
+
+#[async_trait(?Send)]
+impl PluginSubcommand for ComputeBudgetCommand {
+    async fn process(self, _ctx: &CliContext) -> Result<()> {
+        match self {
+            ComputeBudgetCommand::DeserializeComputeIx { hex_data } => {
+                let bytes = hex::decode(hex_data.as_bytes())?;
+                let ix: ComputeBudgetInstruction = borsh0_10::try_from_slice_unchecked(&bytes)?;
+                println!("{:?}", ix);
+            }
+            ComputeBudgetCommand::CalculatePriorityFee {
+                microlamports,
+                budget,
+            } => {
+                println!("{}", priority_fee(microlamports, budget));
+            }
+        }
+        Ok(())
+    }
+}
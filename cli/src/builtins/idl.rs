@@ -0,0 +1,156 @@
+use crate::context::CliContext;
+use crate::plugin::PluginSubcommand;
+use anchor_lang::idl::IdlAccount;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use clap::Subcommand;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_devtools_anchor_utils::codegen::generate_client;
+use solana_devtools_anchor_utils::deserialize::IdlWithDiscriminators;
+use solana_devtools_anchor_utils::idl_sdk::publish::{
+    idl_account_space, idl_init_instructions, idl_upgrade_instructions,
+};
+use solana_sdk::account::from_account;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::sysvar;
+use solana_sdk::transaction::Transaction;
+use std::fs;
+use std::str::FromStr;
+
+#[derive(Debug, Subcommand)]
+pub enum IdlCommand {
+    /// Write an IDL to a program's canonical IDL account, with the configured signer
+    /// as the IDL authority. Creates the IDL account unless `--upgrade` is passed.
+    PublishIdl {
+        /// Path to the IDL JSON file.
+        idl: String,
+        /// The program the IDL describes.
+        program_id: String,
+        /// Replace the IDL in an existing IDL account, by way of a new buffer account.
+        #[clap(long)]
+        upgrade: bool,
+    },
+    /// Generate a Rust client module from an IDL, with typed instruction builders,
+    /// account structs and PDA derivations.
+    GenerateClient {
+        /// Path to the IDL JSON file.
+        idl: String,
+        /// The program ID, if the IDL has no address in its metadata.
+        #[clap(long)]
+        program_id: Option<String>,
+        /// Write the module to this file instead of stdout.
+        #[clap(long, short)]
+        output: Option<String>,
+    },
+}
+
+#[async_trait(?Send)]
+impl PluginSubcommand for IdlCommand {
+    async fn process(self, ctx: &CliContext) -> Result<()> {
+        let (idl, program_id, upgrade) = match self {
+            IdlCommand::PublishIdl {
+                idl,
+                program_id,
+                upgrade,
+            } => (idl, program_id, upgrade),
+            IdlCommand::GenerateClient {
+                idl,
+                program_id,
+                output,
+            } => return generate_client_module(&idl, program_id.as_deref(), output.as_deref()),
+        };
+        // The file's bytes are published as-is, parsing only checks that they are an IDL.
+        let idl_json =
+            fs::read(&idl).map_err(|e| anyhow!("could not read IDL file {}: {}", idl, e))?;
+        IdlWithDiscriminators::from_json(&idl_json)
+            .map_err(|e| anyhow!("invalid IDL file {}: {}", idl, e))?;
+        let program_id =
+            Pubkey::from_str(&program_id).map_err(|_| anyhow!("Invalid program ID"))?;
+        let client = ctx.rpc_client().await?;
+        let signer = ctx.signer()?;
+        let authority = signer.pubkey();
+        let idl_address = IdlAccount::address(&program_id);
+        let existing = client
+            .get_account_with_commitment(&idl_address, client.commitment())
+            .await?
+            .value;
+
+        if !upgrade {
+            if existing.is_some() {
+                return Err(anyhow!(
+                    "IDL account {} already exists, pass --upgrade to replace its IDL",
+                    idl_address
+                ));
+            }
+            for ix in idl_init_instructions(program_id, authority, &idl_json)? {
+                send(&client, &[ix], &[&signer]).await?;
+            }
+        } else {
+            let existing =
+                existing.ok_or_else(|| anyhow!("IDL account {} does not exist", idl_address))?;
+            let space = idl_account_space(&idl_json)?;
+            if existing.data.len() < space {
+                return Err(anyhow!(
+                    "IDL account {} has {} bytes but the IDL needs {}, \
+                    it must be closed and initialized again",
+                    idl_address,
+                    existing.data.len(),
+                    space
+                ));
+            }
+            let rent: Rent = from_account(&client.get_account(&sysvar::rent::ID).await?)
+                .ok_or_else(|| anyhow!("could not deserialize the rent sysvar"))?;
+            let buffer = Keypair::new();
+            let transactions =
+                idl_upgrade_instructions(program_id, buffer.pubkey(), authority, &idl_json, &rent)?;
+            for (i, instructions) in transactions.iter().enumerate() {
+                if i == 0 {
+                    send(&client, instructions, &[&signer, &buffer]).await?;
+                } else {
+                    send(&client, instructions, &[&signer]).await?;
+                }
+            }
+        }
+        println!("{}", idl_address);
+        Ok(())
+    }
+}
+
+fn generate_client_module(idl: &str, program_id: Option<&str>, output: Option<&str>) -> Result<()> {
+    let idl = IdlWithDiscriminators::from_file(idl)
+        .map_err(|e| anyhow!("could not read IDL file {}: {}", idl, e))?;
+    let program_id = program_id
+        .map(|program_id| Pubkey::from_str(program_id).map_err(|_| anyhow!("Invalid program ID")))
+        .transpose()?;
+    let client = generate_client(&idl, program_id)?;
+    match output {
+        Some(path) => fs::write(path, client)
+            .map_err(|e| anyhow!("could not write client module {}: {}", path, e)),
+        None => {
+            print!("{}", client);
+            Ok(())
+        }
+    }
+}
+
+async fn send(
+    client: &RpcClient,
+    instructions: &[Instruction],
+    signers: &[&dyn Signer],
+) -> Result<()> {
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&signers[0].pubkey()),
+        signers,
+        client.get_latest_blockhash().await?,
+    );
+    client
+        .send_and_confirm_transaction(&tx)
+        .await
+        .inspect_err(|e| println!("{:#?}", e))?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use crate::context::CliContext;
+use crate::plugin::PluginSubcommand;
+use anchor_spl::associated_token::get_associated_token_address;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use clap::Subcommand;
+use rpassword::prompt_password;
+use solana_clap_v3_utils::keypair::pubkey_from_path;
+use solana_devtools_signers::encrypted_keypair::encrypt_keypair_file;
+use solana_sdk::signer::Signer;
+
+#[derive(Debug, Subcommand)]
+pub enum KeyCommand {
+    Address,
+    /// Display the owner's associated token address for a given mint. Owner defaults
+    /// to the configured signer.
+    Ata {
+        mint: String,
+        owner: Option<String>,
+    },
+    /// Encrypt a keypair file with a passphrase. The result can be used as a signer
+    /// with `encrypted://<outfile>`. The plaintext keypair file is not removed.
+    Encrypt {
+        keypair: String,
+        outfile: String,
+    },
+    // TODO Pubkey subcommand,
+}
+
+#[async_trait(?Send)]
+impl PluginSubcommand for KeyCommand {
+    async fn process(self, ctx: &CliContext) -> Result<()> {
+        match self {
+            KeyCommand::Address => {
+                println!("{}", ctx.signer()?.pubkey());
+            }
+            KeyCommand::Ata { mint, owner } => {
+                let matches = ctx.matches();
+                let owner = if let Some(path) = owner {
+                    pubkey_from_path(matches, &path, "keypair", &mut None)
+                        .map_err(|_| anyhow!("Invalid pubkey or path: {}", path))?
+                } else {
+                    ctx.signer()?.pubkey()
+                };
+                let mint = pubkey_from_path(matches, &mint, "keypair", &mut None)
+                    .map_err(|_| anyhow!("Invalid pubkey or path: {}", mint))?;
+                println!("{}", get_associated_token_address(&owner, &mint));
+            }
+            KeyCommand::Encrypt { keypair, outfile } => {
+                let passphrase = prompt_password("passphrase: ")?;
+                if passphrase != prompt_password("confirm passphrase: ")? {
+                    return Err(anyhow!("Passphrases do not match"));
+                }
+                let pubkey = encrypt_keypair_file(&keypair, &outfile, &passphrase)?;
+                println!("Wrote encrypted keypair for {} to {}", pubkey, outfile);
+            }
+        }
+        Ok(())
+    }
+}
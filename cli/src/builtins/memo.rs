@@ -0,0 +1,70 @@
+use crate::context::CliContext;
+use crate::plugin::PluginSubcommand;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use clap::Subcommand;
+use solana_clap_v3_utils::keypair::signer_from_path;
+use solana_sdk::hash::Hasher;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::Transaction;
+use spl_memo::build_memo;
+use std::fs;
+
+#[derive(Debug, Subcommand)]
+pub enum MemoCommand {
+    /// Execute a memo transaction.
+    Memo {
+        /// Message
+        msg: String,
+        /// If included, reinterprets `MSG` as a filepath,
+        /// and hashes the contents of the file to use as a memo message.
+        #[clap(long)]
+        hash_file: bool,
+        /// Additional signers of the memo
+        #[clap(short, long)]
+        signer: Vec<String>,
+    },
+}
+
+#[async_trait(?Send)]
+impl PluginSubcommand for MemoCommand {
+    async fn process(self, ctx: &CliContext) -> Result<()> {
+        let MemoCommand::Memo {
+            msg,
+            signer,
+            hash_file,
+        } = self;
+        let client = ctx.rpc_client().await?;
+        let mut signers: Vec<Box<dyn Signer>> = vec![];
+        for path in signer {
+            signers.push(
+                signer_from_path(ctx.matches(), &path, "keypair", &mut None)
+                    .map_err(|_| anyhow!("Invalid signer path: {}", path))?,
+            );
+        }
+        signers.push(Box::new(ctx.signer()?));
+        let signer_pubkeys: Vec<Pubkey> = signers.iter().map(|s| s.pubkey()).collect();
+        let pubkey_refs: Vec<&Pubkey> = signer_pubkeys.iter().collect();
+        let msg = if hash_file {
+            let mut hasher = Hasher::default();
+            hasher.hash(&fs::read(msg)?);
+            hasher.result().to_string()
+        } else {
+            msg
+        };
+        let ix = build_memo(msg.as_bytes(), &pubkey_refs);
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(signer_pubkeys.last().unwrap()),
+            &signers,
+            client.get_latest_blockhash().await?,
+        );
+        let signature = client
+            .send_transaction(&tx)
+            .await
+            .inspect_err(|e| println!("{:#?}", e))?;
+        println!("{}", signature);
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+mod compute_budget;
+mod idl;
+mod keys;
+mod memo;
+mod multisig;
+mod program;
+mod simulate;
+mod token;
+mod transaction;
+mod watch;
+
+pub use compute_budget::ComputeBudgetCommand;
+pub use idl::IdlCommand;
+pub use keys::KeyCommand;
+pub use memo::MemoCommand;
+pub use multisig::MultisigCommand;
+pub use program::ProgramCommand;
+pub use simulate::SimulateCommand;
+pub use token::TokenCommand;
+pub use transaction::TransactionCommand;
+pub use watch::WatchCommand;
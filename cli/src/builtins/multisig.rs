@@ -0,0 +1,126 @@
+use crate::builtins::simulate::read_instructions;
+use crate::context::CliContext;
+use crate::plugin::PluginSubcommand;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use clap::Subcommand;
+use solana_devtools_signers::concrete_signer::ConcreteSigner;
+use solana_devtools_signers::offline::{OfflineMessage, OfflineSignature};
+use solana_sdk::bs58;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::{Message, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use std::str::FromStr;
+
+#[derive(Debug, Subcommand)]
+pub enum MultisigCommand {
+    /// Write an unsigned message file, to collect signatures with `partial-sign`.
+    BuildUnsigned {
+        /// JSON file of instructions, in the format of `simulate --from-instructions`.
+        instructions: String,
+        /// Defaults to the configured signer.
+        #[clap(long)]
+        fee_payer: Option<String>,
+        /// Defaults to the latest blockhash. Required with `--offline`.
+        /// A durable nonce can be passed here, with an advance-nonce instruction first.
+        #[clap(long)]
+        blockhash: Option<String>,
+        #[clap(short, long)]
+        outfile: String,
+    },
+    /// Sign an unsigned message file, and write the signature to a file.
+    /// Only the message is read, so this works offline, and with any signer URI.
+    PartialSign {
+        message: String,
+        /// Signer URI, e.g. `usb://ledger` or `encrypted://<path>`. Defaults to the configured signer.
+        #[clap(long)]
+        signer: Option<String>,
+        #[clap(short, long)]
+        outfile: String,
+    },
+    /// Combine the signature files of an unsigned message file into a transaction, checking that
+    /// every required signer signed. Prints the Base58-encoded transaction, or sends it with `--send`.
+    CombineSignatures {
+        message: String,
+        #[clap(required = true)]
+        signatures: Vec<String>,
+        #[clap(long)]
+        send: bool,
+    },
+}
+
+#[async_trait(?Send)]
+impl PluginSubcommand for MultisigCommand {
+    async fn process(self, ctx: &CliContext) -> Result<()> {
+        match self {
+            MultisigCommand::BuildUnsigned {
+                instructions,
+                fee_payer,
+                blockhash,
+                outfile,
+            } => {
+                let fee_payer = match fee_payer {
+                    Some(pubkey) => Pubkey::from_str(&pubkey)
+                        .map_err(|_| anyhow!("Invalid fee payer: {}", pubkey))?,
+                    None => ctx.signer()?.pubkey(),
+                };
+                let blockhash = match blockhash {
+                    Some(hash) => {
+                        Hash::from_str(&hash).map_err(|_| anyhow!("Invalid blockhash: {}", hash))?
+                    }
+                    None => ctx.rpc_client().await?.get_latest_blockhash().await?,
+                };
+                let message = Message::new_with_blockhash(
+                    &read_instructions(&instructions)?,
+                    Some(&fee_payer),
+                    &blockhash,
+                );
+                let message = OfflineMessage::new(VersionedMessage::Legacy(message));
+                message.write(&outfile)?;
+                println!("Wrote {}, to be signed by:", outfile);
+                for signer in message.required_signers() {
+                    println!("  {}", signer);
+                }
+            }
+            MultisigCommand::PartialSign {
+                message,
+                signer,
+                outfile,
+            } => {
+                let message = OfflineMessage::read(&message)?;
+                let signer = match signer {
+                    Some(uri) => ConcreteSigner::from_str(&uri)
+                        .map_err(|e| anyhow!("Invalid signer {}: {}", uri, e))?,
+                    None => ctx.signer()?,
+                };
+                message.sign(&signer)?.write(&outfile)?;
+                println!("Wrote the signature of {} to {}", signer.pubkey(), outfile);
+            }
+            MultisigCommand::CombineSignatures {
+                message,
+                signatures,
+                send,
+            } => {
+                let message = OfflineMessage::read(&message)?;
+                let signatures = signatures
+                    .iter()
+                    .map(OfflineSignature::read)
+                    .collect::<Result<Vec<_>>>()?;
+                let tx = message.combine(signatures)?;
+                if send {
+                    let signature = ctx
+                        .rpc_client()
+                        .await?
+                        .send_and_confirm_transaction(&tx)
+                        .await
+                        .inspect_err(|e| println!("{:#?}", e))?;
+                    println!("{}", signature);
+                } else {
+                    println!("{}", bs58::encode(bincode::serialize(&tx)?).into_string());
+                }
+            }
+        }
+        Ok(())
+    }
+}
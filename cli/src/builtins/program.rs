@@ -0,0 +1,344 @@
+use crate::context::CliContext;
+use crate::plugin::PluginSubcommand;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use clap::Subcommand;
+use solana_clap_v3_utils::keypair::{pubkey_from_path, signer_from_path};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::hash::{hash, Hash};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::Transaction;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Subcommand)]
+pub enum ProgramCommand {
+    /// Deploy a program to the upgradeable BPF loader, or upgrade it if it is already deployed,
+    /// then verify that the deployed program matches the local file.
+    Deploy {
+        /// Path to the program's `.so` file.
+        program: String,
+        /// The program's keypair, required for a first deploy. An upgrade only needs its pubkey.
+        /// Defaults to `<name>-keypair.json` next to `<name>.so`, as written by `cargo build-sbf`.
+        #[clap(long)]
+        program_id: Option<String>,
+        /// Defaults to the configured signer.
+        #[clap(long)]
+        upgrade_authority: Option<String>,
+        /// Maximum program size on a first deploy, in bytes. Defaults to twice the program's size.
+        #[clap(long)]
+        max_len: Option<usize>,
+        /// Keep the buffer account when a deploy fails, instead of closing it to recover its rent.
+        #[clap(long)]
+        keep_buffer: bool,
+    },
+    /// Verify that a deployed program matches a local `.so` file.
+    VerifyProgram {
+        program_id: String,
+        /// Path to the program's `.so` file.
+        program: String,
+    },
+}
+
+#[async_trait(?Send)]
+impl PluginSubcommand for ProgramCommand {
+    async fn process(self, ctx: &CliContext) -> Result<()> {
+        match self {
+            ProgramCommand::Deploy {
+                program,
+                program_id,
+                upgrade_authority,
+                max_len,
+                keep_buffer,
+            } => {
+                let elf = fs::read(&program)
+                    .map_err(|e| anyhow!("could not read program file {}: {}", program, e))?;
+                let program_id = program_id.unwrap_or_else(|| default_program_keypair(&program));
+                let matches = ctx.matches();
+                let program_signer = signer_from_path(matches, &program_id, "program", &mut None);
+                let program_address = match &program_signer {
+                    Ok(signer) => signer.pubkey(),
+                    Err(_) => pubkey_from_path(matches, &program_id, "program", &mut None)
+                        .map_err(|_| {
+                            anyhow!("Invalid program keypair or pubkey: {}", program_id)
+                        })?,
+                };
+                let client = ctx.rpc_client().await?;
+                let payer = ctx.signer()?;
+                let authority: Box<dyn Signer> = match upgrade_authority {
+                    Some(path) => signer_from_path(matches, &path, "upgrade-authority", &mut None)
+                        .map_err(|_| anyhow!("Invalid upgrade authority: {}", path))?,
+                    None => Box::new(ctx.signer()?),
+                };
+                let existing = client
+                    .get_account_with_commitment(&program_address, client.commitment())
+                    .await?
+                    .value;
+                let upgrading = match existing {
+                    Some(account) if account.owner == bpf_loader_upgradeable::ID => true,
+                    Some(account) => {
+                        return Err(anyhow!(
+                            "{} is owned by {}, not the upgradeable BPF loader",
+                            program_address,
+                            account.owner
+                        ))
+                    }
+                    None => false,
+                };
+                let program_signer = match program_signer {
+                    Ok(signer) => Some(signer),
+                    Err(_) if upgrading => None,
+                    Err(_) => {
+                        return Err(anyhow!(
+                            "{} is not deployed yet, a first deploy needs the program's keypair",
+                            program_address
+                        ))
+                    }
+                };
+
+                let buffer = Keypair::new();
+                println!("Writing {} bytes to buffer {}", elf.len(), buffer.pubkey());
+                let result = async {
+                    write_buffer(&client, &payer, authority.as_ref(), &buffer, &elf).await?;
+                    if let Some(program_signer) = program_signer {
+                        let program_lamports = client
+                            .get_minimum_balance_for_rent_exemption(
+                                UpgradeableLoaderState::size_of_program(),
+                            )
+                            .await?;
+                        let instructions = bpf_loader_upgradeable::deploy_with_max_program_len(
+                            &payer.pubkey(),
+                            &program_address,
+                            &buffer.pubkey(),
+                            &authority.pubkey(),
+                            program_lamports,
+                            max_len.unwrap_or(elf.len() * 2).max(elf.len()),
+                        )?;
+                        send(
+                            &client,
+                            &instructions,
+                            &[&payer, program_signer.as_ref(), authority.as_ref()],
+                        )
+                        .await
+                    } else {
+                        let instruction = bpf_loader_upgradeable::upgrade(
+                            &program_address,
+                            &buffer.pubkey(),
+                            &authority.pubkey(),
+                            &payer.pubkey(),
+                        );
+                        send(&client, &[instruction], &[&payer, authority.as_ref()]).await
+                    }
+                }
+                .await;
+                if let Err(e) = result {
+                    if !keep_buffer {
+                        close_buffer(&client, &payer, authority.as_ref(), &buffer.pubkey()).await;
+                    }
+                    return Err(e);
+                }
+                println!(
+                    "{} {}",
+                    if upgrading { "Upgraded" } else { "Deployed" },
+                    program_address
+                );
+                verify(&client, &program_address, &elf).await?;
+            }
+            ProgramCommand::VerifyProgram {
+                program_id,
+                program,
+            } => {
+                let program_address =
+                    pubkey_from_path(ctx.matches(), &program_id, "program", &mut None)
+                        .map_err(|_| anyhow!("Invalid program ID: {}", program_id))?;
+                let elf = fs::read(&program)
+                    .map_err(|e| anyhow!("could not read program file {}: {}", program, e))?;
+                verify(&ctx.rpc_client().await?, &program_address, &elf).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `<dir>/<name>-keypair.json` for a program at `<dir>/<name>.so`.
+fn default_program_keypair(program: &str) -> String {
+    let path = Path::new(program);
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}-keypair.json", name))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// The largest chunk of program bytes that fits in one buffer write transaction.
+fn write_chunk_size(payer: &Pubkey, authority: &Pubkey, buffer: &Pubkey) -> usize {
+    let tx = Transaction::new_with_payer(
+        &[bpf_loader_upgradeable::write(buffer, authority, 0, vec![])],
+        Some(payer),
+    );
+    let overhead = bincode::serialized_size(&tx).unwrap_or(PACKET_DATA_SIZE as u64) as usize;
+    // The length prefix of the bytes grows from one byte to two.
+    PACKET_DATA_SIZE.saturating_sub(overhead + 1)
+}
+
+async fn write_buffer(
+    client: &RpcClient,
+    payer: &dyn Signer,
+    authority: &dyn Signer,
+    buffer: &Keypair,
+    elf: &[u8],
+) -> Result<()> {
+    let lamports = client
+        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_buffer(elf.len()))
+        .await?;
+    let instructions = bpf_loader_upgradeable::create_buffer(
+        &payer.pubkey(),
+        &buffer.pubkey(),
+        &authority.pubkey(),
+        lamports,
+        elf.len(),
+    )?;
+    send(client, &instructions, &[payer, buffer]).await?;
+
+    let chunk_size = write_chunk_size(&payer.pubkey(), &authority.pubkey(), &buffer.pubkey());
+    let mut written = 0;
+    for chunk in elf.chunks(chunk_size) {
+        let instruction = bpf_loader_upgradeable::write(
+            &buffer.pubkey(),
+            &authority.pubkey(),
+            written as u32,
+            chunk.to_vec(),
+        );
+        send(client, &[instruction], &[payer, authority]).await?;
+        written += chunk.len();
+        print!("\rWrote {}/{} bytes", written, elf.len());
+        std::io::stdout().flush()?;
+    }
+    println!();
+    Ok(())
+}
+
+/// Closing the buffer is best-effort, the deploy error is what gets reported.
+async fn close_buffer(
+    client: &RpcClient,
+    payer: &dyn Signer,
+    authority: &dyn Signer,
+    buffer: &Pubkey,
+) {
+    let exists = matches!(
+        client.get_account_with_commitment(buffer, client.commitment()).await,
+        Ok(response) if response.value.is_some()
+    );
+    if !exists {
+        return;
+    }
+    let instruction = bpf_loader_upgradeable::close(buffer, &payer.pubkey(), &authority.pubkey());
+    match send(client, &[instruction], &[payer, authority]).await {
+        Ok(()) => println!("Closed buffer {}", buffer),
+        Err(e) => println!("Could not close buffer {}: {}", buffer, e),
+    }
+}
+
+async fn verify(client: &RpcClient, program_address: &Pubkey, elf: &[u8]) -> Result<()> {
+    let (programdata_address, _) =
+        Pubkey::find_program_address(&[program_address.as_ref()], &bpf_loader_upgradeable::ID);
+    let programdata = client.get_account(&programdata_address).await?;
+    let deployed = programdata
+        .data
+        .get(UpgradeableLoaderState::size_of_programdata_metadata()..)
+        .ok_or_else(|| anyhow!("{} is not a program data account", programdata_address))?;
+    let local_hash = hash(elf);
+    match deployed_hash(deployed, elf.len()) {
+        Some(deployed_hash) if deployed_hash == local_hash => {
+            println!("Verified {}: {}", program_address, local_hash);
+            Ok(())
+        }
+        Some(deployed_hash) => Err(anyhow!(
+            "deployed program hash {} does not match local program hash {}",
+            deployed_hash,
+            local_hash
+        )),
+        None => Err(anyhow!(
+            "deployed program has {} bytes, fewer than the local program's {}",
+            deployed.len(),
+            elf.len()
+        )),
+    }
+}
+
+/// The hash of the deployed program, which is zero-padded to the program data account's size.
+/// `None` if the padding is not all zeros, or the deployed program is shorter than `len`.
+fn deployed_hash(deployed: &[u8], len: usize) -> Option<Hash> {
+    if deployed.len() < len || deployed[len..].iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    Some(hash(&deployed[..len]))
+}
+
+pub(crate) async fn send(
+    client: &RpcClient,
+    instructions: &[Instruction],
+    signers: &[&dyn Signer],
+) -> Result<()> {
+    // The payer may also be the upgrade authority.
+    let mut unique_signers: Vec<&dyn Signer> = vec![];
+    for signer in signers {
+        if !unique_signers.iter().any(|s| s.pubkey() == signer.pubkey()) {
+            unique_signers.push(*signer);
+        }
+    }
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&signers[0].pubkey()),
+        &unique_signers,
+        client.get_latest_blockhash().await?,
+    );
+    client
+        .send_and_confirm_transaction(&tx)
+        .await
+        .inspect_err(|e| println!("{:#?}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_writes_fit_in_a_packet() {
+        let (payer, authority) = (Keypair::new(), Keypair::new());
+        let buffer = Pubkey::new_unique();
+        let chunk_size = write_chunk_size(&payer.pubkey(), &authority.pubkey(), &buffer);
+        let tx = Transaction::new_signed_with_payer(
+            &[bpf_loader_upgradeable::write(
+                &buffer,
+                &authority.pubkey(),
+                0,
+                vec![1; chunk_size],
+            )],
+            Some(&payer.pubkey()),
+            &[&payer, &authority],
+            Hash::default(),
+        );
+        assert_eq!(
+            bincode::serialized_size(&tx).unwrap() as usize,
+            PACKET_DATA_SIZE
+        );
+
+        let elf = vec![1, 2, 3];
+        assert_eq!(deployed_hash(&[1, 2, 3, 0, 0], 3), Some(hash(&elf)));
+        assert_eq!(deployed_hash(&[1, 2, 3, 0, 1], 3), None);
+        assert_eq!(
+            default_program_keypair("target/deploy/vault.so"),
+            "target/deploy/vault-keypair.json"
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use crate::builtins::transaction::{decode_bytes, decode_message};
+use crate::context::CliContext;
+use crate::plugin::PluginSubcommand;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_devtools_anchor_utils::deserialize::account::DeserializedAccount;
+use solana_devtools_anchor_utils::deserialize::transaction::DeserializedTransaction;
+use solana_devtools_anchor_utils::deserialize::AnchorDeserializer;
+use solana_devtools_execution_result::ProcessedMessage;
+use solana_devtools_simulator::TransactionSimulator;
+use solana_devtools_tx::decompile_instructions::lookup_addresses_from_tables;
+use solana_devtools_tx::inner_instructions::HistoricalTransaction;
+use solana_sdk::account::{Account, ReadableAccount};
+use solana_sdk::address_lookup_table::state::AddressLookupTable;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::bs58;
+use solana_sdk::instruction::{AccountMeta, CompiledInstruction, Instruction};
+use solana_sdk::message::{Message, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use solana_sdk::{native_loader, sysvar};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+#[derive(Debug, Subcommand)]
+pub enum SimulateCommand {
+    /// Run a message in the in-process simulator, against accounts fetched from RPC,
+    /// and print its decoded instructions and logs, compute units, and account changes.
+    Simulate {
+        /// Base58-encoded transaction message.
+        #[clap(required_unless_present = "from-instructions")]
+        b58_message: Option<String>,
+        /// Optionally parse the message data as base64
+        #[clap(long)]
+        base64: bool,
+        /// Optionally parse the message data as a serialized transaction, instead of a message
+        #[clap(long)]
+        as_transaction: bool,
+        /// Build the message from a JSON file of instructions instead, paid for by the
+        /// configured signer. Each instruction is
+        /// `{"programId", "accounts": [{"pubkey", "isSigner", "isWritable"}], "data"}`,
+        /// with Base58-encoded data.
+        #[clap(long, conflicts_with = "b58-message")]
+        from_instructions: Option<String>,
+        /// Optionally supply the IDL filepath, used to decode instructions and accounts.
+        #[clap(long)]
+        idl: Option<String>,
+        /// A directory of IDLs written by `export-idls`.
+        #[clap(long)]
+        idl_dir: Option<String>,
+        /// Optionally write the data to a file as JSON.
+        #[clap(long)]
+        outfile: Option<String>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstructionJson {
+    program_id: String,
+    accounts: Vec<AccountMetaJson>,
+    data: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountMetaJson {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SimulationOutput {
+    success: bool,
+    /// Resolved against the errors in the cached IDLs, where possible.
+    error: Option<String>,
+    compute_units: u64,
+    transaction: DeserializedTransaction,
+    account_changes: Vec<AccountChange>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountChange {
+    address: String,
+    pre_lamports: u64,
+    post_lamports: u64,
+    pre_owner: Option<String>,
+    post_owner: String,
+    data_changed: bool,
+    /// Decoded with the cached IDLs, where possible.
+    pre: Option<DeserializedAccount>,
+    post: Option<DeserializedAccount>,
+}
+
+#[async_trait(?Send)]
+impl PluginSubcommand for SimulateCommand {
+    async fn process(self, ctx: &CliContext) -> Result<()> {
+        let SimulateCommand::Simulate {
+            b58_message,
+            base64,
+            as_transaction,
+            from_instructions,
+            idl,
+            idl_dir,
+            outfile,
+        } = self;
+        let deser = ctx.anchor_deserializer(idl, idl_dir)?;
+        let message = match (from_instructions, b58_message) {
+            (Some(path), _) => {
+                let payer = ctx.signer()?.pubkey();
+                VersionedMessage::Legacy(Message::new(&read_instructions(&path)?, Some(&payer)))
+            }
+            (None, Some(message)) => {
+                decode_message(&decode_bytes(&message, base64)?, as_transaction)?
+            }
+            (None, None) => return Err(anyhow!("A message or --from-instructions is required")),
+        };
+        let client = ctx.rpc_client().await?;
+
+        let mut accounts = fetch_accounts(&client, message.static_account_keys()).await?;
+        let table_keys: Vec<Pubkey> = message
+            .address_table_lookups()
+            .unwrap_or_default()
+            .iter()
+            .map(|lookup| lookup.account_key)
+            .collect();
+        let mut tables = HashMap::new();
+        let mut last_extended_slot = 0;
+        for (key, account) in fetch_accounts(&client, &table_keys).await? {
+            let table = AddressLookupTable::deserialize(&account.data)
+                .map_err(|_| anyhow!("Invalid address lookup table: {}", key))?;
+            last_extended_slot = last_extended_slot.max(table.meta.last_extended_slot);
+            tables.insert(key, table.addresses.to_vec());
+            accounts.insert(key, account);
+        }
+        let loaded_addresses = if table_keys.is_empty() {
+            vec![]
+        } else {
+            lookup_addresses_from_tables(&message, &tables)?
+        };
+        let loaded_keys: Vec<Pubkey> = loaded_addresses
+            .iter()
+            .flat_map(|loaded| loaded.writable.iter().chain(&loaded.readonly))
+            .copied()
+            .collect();
+        accounts.extend(fetch_accounts(&client, &loaded_keys).await?);
+        let programdata_keys: Vec<Pubkey> = accounts
+            .values()
+            .filter(|account| account.owner == bpf_loader_upgradeable::ID)
+            .filter_map(|account| match bincode::deserialize(&account.data) {
+                Ok(UpgradeableLoaderState::Program {
+                    programdata_address,
+                }) => Some(programdata_address),
+                _ => None,
+            })
+            .collect();
+        accounts.extend(fetch_accounts(&client, &programdata_keys).await?);
+
+        let simulator = TransactionSimulator::builder()
+            .accounts(accounts.iter())
+            .build()?;
+        // Addresses are only usable from lookup tables in slots after they were added.
+        if !tables.is_empty() && last_extended_slot >= simulator.working_bank().slot() {
+            simulator.warp_to_slot(last_extended_slot + 1)?;
+        }
+        let processed = simulator
+            .process_message(message.clone())
+            .map_err(|e| anyhow!("Failed to simulate message: {}", e))?;
+
+        let error = processed
+            .error_report(deser.error_resolver())
+            .map(|report| report.to_string());
+        let transaction = deser.try_deserialize_transaction(historical_transaction(
+            message,
+            loaded_addresses,
+            &processed,
+        ))?;
+        let output = SimulationOutput {
+            success: processed.success(),
+            error,
+            compute_units: processed.compute_units,
+            transaction,
+            account_changes: account_changes(&deser, &processed),
+        };
+        ctx.output_json(&output, outfile)
+    }
+}
+
+pub(crate) fn read_instructions(path: &str) -> Result<Vec<Instruction>> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read instructions file {}: {}", path, e))?;
+    let instructions: Vec<InstructionJson> = serde_json::from_str(&json)
+        .map_err(|e| anyhow!("Invalid instructions file {}: {}", path, e))?;
+    let parse = |s: &str| Pubkey::from_str(s).map_err(|_| anyhow!("Invalid address: {}", s));
+    instructions
+        .into_iter()
+        .map(|ix| {
+            Ok(Instruction {
+                program_id: parse(&ix.program_id)?,
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(|meta| {
+                        Ok(AccountMeta {
+                            pubkey: parse(&meta.pubkey)?,
+                            is_signer: meta.is_signer,
+                            is_writable: meta.is_writable,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+                data: bs58::decode(&ix.data)
+                    .into_vec()
+                    .map_err(|_| anyhow!("Invalid Base58 instruction data: {}", ix.data))?,
+            })
+        })
+        .collect()
+}
+
+/// Fetches the accounts that exist, skipping builtins and sysvars, which the simulator provides.
+async fn fetch_accounts(client: &RpcClient, keys: &[Pubkey]) -> Result<HashMap<Pubkey, Account>> {
+    let mut accounts = HashMap::new();
+    for chunk in keys.chunks(100) {
+        for (key, account) in chunk.iter().zip(client.get_multiple_accounts(chunk).await?) {
+            if let Some(account) = account {
+                if account.owner != native_loader::ID && account.owner != sysvar::ID {
+                    accounts.insert(*key, account);
+                }
+            }
+        }
+    }
+    Ok(accounts)
+}
+
+/// The processed message as a [HistoricalTransaction], so that it decodes like a fetched one.
+fn historical_transaction(
+    message: VersionedMessage,
+    loaded_addresses: Vec<solana_sdk::message::v0::LoadedAddresses>,
+    processed: &ProcessedMessage,
+) -> HistoricalTransaction {
+    let mut tx = HistoricalTransaction::new(message, Some(loaded_addresses));
+    let keys = tx.account_keys();
+    let index = |key: &Pubkey| keys.iter().position(|k| k == key).map(|i| i as u8);
+    for invoked in &processed.invoked_instructions {
+        let ix = &invoked.instruction;
+        let (Some(program_id_index), Some(accounts)) = (
+            index(&ix.program_id),
+            ix.accounts
+                .iter()
+                .map(|meta| index(&meta.pubkey))
+                .collect::<Option<Vec<u8>>>(),
+        ) else {
+            continue;
+        };
+        tx.inner_instructions
+            .entry(invoked.instruction_index)
+            .or_default()
+            .push(CompiledInstruction {
+                program_id_index,
+                accounts,
+                data: ix.data.clone(),
+            });
+    }
+    let lamports = |accounts: &HashMap<Pubkey, _>, key: &Pubkey| {
+        accounts
+            .get(key)
+            .map(|account: &solana_sdk::account::AccountSharedData| account.lamports())
+            .unwrap_or_default()
+    };
+    tx.pre_balances = keys
+        .iter()
+        .map(|key| lamports(&processed.pre_accounts, key))
+        .collect();
+    tx.post_balances = keys
+        .iter()
+        .map(|key| lamports(&processed.accounts, key))
+        .collect();
+    tx.log_messages = processed.logs.clone();
+    tx.compute_units_consumed = Some(processed.compute_units);
+    tx.err = processed.execution_error.clone();
+    tx
+}
+
+/// Non-executable accounts whose lamports, owner or data changed, in address order.
+fn account_changes(deser: &AnchorDeserializer, processed: &ProcessedMessage) -> Vec<AccountChange> {
+    let changed: BTreeMap<&Pubkey, _> = processed
+        .accounts
+        .iter()
+        .filter(|(pubkey, post)| !post.executable() && processed.account_changed(pubkey))
+        .collect();
+    changed
+        .into_iter()
+        .map(|(pubkey, post)| {
+            let pre = processed
+                .pre_accounts
+                .get(pubkey)
+                .map(|pre| Account::from(pre.clone()));
+            let post = Account::from(post.clone());
+            AccountChange {
+                address: pubkey.to_string(),
+                pre_lamports: pre.as_ref().map(|pre| pre.lamports).unwrap_or_default(),
+                post_lamports: post.lamports,
+                pre_owner: pre.as_ref().map(|pre| pre.owner.to_string()),
+                post_owner: post.owner.to_string(),
+                data_changed: pre.as_ref().map(|pre| &pre.data) != Some(&post.data),
+                pre: pre.and_then(|pre| deser.try_deserialize_account(*pubkey, &pre).ok()),
+                post: deser.try_deserialize_account(*pubkey, &post).ok(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instructions_are_read_from_json() {
+        let (program_id, account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let path = std::env::temp_dir().join(format!("instructions-{}.json", program_id));
+        std::fs::write(
+            &path,
+            serde_json::json!([{
+                "programId": program_id.to_string(),
+                "accounts": [{"pubkey": account.to_string(), "isSigner": true, "isWritable": false}],
+                "data": bs58::encode([1, 2, 3]).into_string(),
+            }])
+            .to_string(),
+        )
+        .unwrap();
+        let instructions = read_instructions(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            instructions,
+            vec![Instruction {
+                program_id,
+                accounts: vec![AccountMeta::new_readonly(account, true)],
+                data: vec![1, 2, 3],
+            }]
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use super::program::send;
+use crate::context::CliContext;
+use crate::plugin::PluginSubcommand;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use clap::Subcommand;
+use solana_clap_v3_utils::keypair::{pubkey_from_path, signer_from_path};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::native_token::sol_to_lamports;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::system_instruction;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use spl_token_2022::extension::StateWithExtensions;
+use spl_token_2022::instruction as token_instruction;
+use spl_token_2022::state::Mint;
+use std::str::FromStr;
+
+#[derive(Debug, Subcommand)]
+pub enum TokenCommand {
+    /// SPL Token and Token-2022 utilities. Token accounts are associated token accounts,
+    /// created as needed, and the configured signer is the payer and default authority.
+    Token {
+        #[clap(subcommand)]
+        command: TokenSubcommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TokenSubcommand {
+    /// Create a new mint, and print its address.
+    CreateMint {
+        #[clap(long, default_value = "9")]
+        decimals: u8,
+        /// The mint's keypair. Defaults to a new random keypair.
+        #[clap(long)]
+        mint_keypair: Option<String>,
+        /// Defaults to the configured signer.
+        #[clap(long)]
+        mint_authority: Option<String>,
+        #[clap(long)]
+        freeze_authority: Option<String>,
+        /// Create the mint under the Token-2022 program instead of the SPL Token program.
+        #[clap(long)]
+        token_2022: bool,
+    },
+    /// Create an associated token account, if it doesn't already exist, and print its address.
+    CreateAccount {
+        mint: String,
+        /// Defaults to the configured signer.
+        #[clap(long)]
+        owner: Option<String>,
+    },
+    /// Mint tokens, as the configured signer, to an owner's associated token account.
+    MintTo {
+        mint: String,
+        /// In whole tokens, e.g. `1.5`.
+        amount: String,
+        /// Defaults to the configured signer.
+        #[clap(long)]
+        recipient: Option<String>,
+    },
+    /// Transfer tokens from the configured signer's associated token account
+    /// to the recipient's associated token account.
+    Transfer {
+        mint: String,
+        /// In whole tokens, e.g. `1.5`.
+        amount: String,
+        /// The recipient's wallet address, not its token account.
+        recipient: String,
+    },
+    /// Burn tokens from the configured signer's associated token account.
+    Burn {
+        mint: String,
+        /// In whole tokens, e.g. `1.5`.
+        amount: String,
+    },
+    /// Wrap SOL into the configured signer's associated wrapped SOL account.
+    Wrap {
+        /// In SOL, e.g. `1.5`.
+        amount: f64,
+    },
+    /// Close the configured signer's associated wrapped SOL account, unwrapping all of its SOL.
+    Unwrap,
+}
+
+#[async_trait(?Send)]
+impl PluginSubcommand for TokenCommand {
+    async fn process(self, ctx: &CliContext) -> Result<()> {
+        let TokenCommand::Token { command } = self;
+        let client = ctx.rpc_client().await?;
+        let payer = ctx.signer()?;
+        let wallet = payer.pubkey();
+        match command {
+            TokenSubcommand::CreateMint {
+                decimals,
+                mint_keypair,
+                mint_authority,
+                freeze_authority,
+                token_2022,
+            } => {
+                let mint: Box<dyn Signer> = match mint_keypair {
+                    Some(path) => signer_from_path(ctx.matches(), &path, "mint", &mut None)
+                        .map_err(|_| anyhow!("Invalid mint keypair: {}", path))?,
+                    None => Box::new(Keypair::new()),
+                };
+                let mint_authority = match mint_authority {
+                    Some(path) => pubkey_arg(ctx, &path, "mint-authority")?,
+                    None => wallet,
+                };
+                let freeze_authority = freeze_authority
+                    .map(|path| pubkey_arg(ctx, &path, "freeze-authority"))
+                    .transpose()?;
+                let token_program = if token_2022 {
+                    spl_token_2022::ID
+                } else {
+                    spl_token::ID
+                };
+                let lamports = client
+                    .get_minimum_balance_for_rent_exemption(Mint::LEN)
+                    .await?;
+                let instructions = vec![
+                    system_instruction::create_account(
+                        &wallet,
+                        &mint.pubkey(),
+                        lamports,
+                        Mint::LEN as u64,
+                        &token_program,
+                    ),
+                    token_instruction::initialize_mint2(
+                        &token_program,
+                        &mint.pubkey(),
+                        &mint_authority,
+                        freeze_authority.as_ref(),
+                        decimals,
+                    )?,
+                ];
+                send(&client, &instructions, &[&payer, mint.as_ref()]).await?;
+                println!("{}", mint.pubkey());
+            }
+            TokenSubcommand::CreateAccount { mint, owner } => {
+                let mint = parse_pubkey(&mint, "mint")?;
+                let owner = match owner {
+                    Some(path) => pubkey_arg(ctx, &path, "owner")?,
+                    None => wallet,
+                };
+                let (token_program, _) = fetch_mint(&client, &mint).await?;
+                let instruction = create_associated_token_account_idempotent(
+                    &wallet,
+                    &owner,
+                    &mint,
+                    &token_program,
+                );
+                send(&client, &[instruction], &[&payer]).await?;
+                println!(
+                    "{}",
+                    get_associated_token_address_with_program_id(&owner, &mint, &token_program)
+                );
+            }
+            TokenSubcommand::MintTo {
+                mint,
+                amount,
+                recipient,
+            } => {
+                let mint = parse_pubkey(&mint, "mint")?;
+                let recipient = match recipient {
+                    Some(path) => pubkey_arg(ctx, &path, "recipient")?,
+                    None => wallet,
+                };
+                let (token_program, decimals) = fetch_mint(&client, &mint).await?;
+                let amount = parse_amount(&amount, decimals)?;
+                let account =
+                    get_associated_token_address_with_program_id(&recipient, &mint, &token_program);
+                let instructions = vec![
+                    create_associated_token_account_idempotent(
+                        &wallet,
+                        &recipient,
+                        &mint,
+                        &token_program,
+                    ),
+                    token_instruction::mint_to_checked(
+                        &token_program,
+                        &mint,
+                        &account,
+                        &wallet,
+                        &[],
+                        amount,
+                        decimals,
+                    )?,
+                ];
+                send(&client, &instructions, &[&payer]).await?;
+            }
+            TokenSubcommand::Transfer {
+                mint,
+                amount,
+                recipient,
+            } => {
+                let mint = parse_pubkey(&mint, "mint")?;
+                let recipient = pubkey_arg(ctx, &recipient, "recipient")?;
+                let (token_program, decimals) = fetch_mint(&client, &mint).await?;
+                let amount = parse_amount(&amount, decimals)?;
+                let instructions = vec![
+                    create_associated_token_account_idempotent(
+                        &wallet,
+                        &recipient,
+                        &mint,
+                        &token_program,
+                    ),
+                    token_instruction::transfer_checked(
+                        &token_program,
+                        &get_associated_token_address_with_program_id(
+                            &wallet,
+                            &mint,
+                            &token_program,
+                        ),
+                        &mint,
+                        &get_associated_token_address_with_program_id(
+                            &recipient,
+                            &mint,
+                            &token_program,
+                        ),
+                        &wallet,
+                        &[],
+                        amount,
+                        decimals,
+                    )?,
+                ];
+                send(&client, &instructions, &[&payer]).await?;
+            }
+            TokenSubcommand::Burn { mint, amount } => {
+                let mint = parse_pubkey(&mint, "mint")?;
+                let (token_program, decimals) = fetch_mint(&client, &mint).await?;
+                let amount = parse_amount(&amount, decimals)?;
+                let instruction = token_instruction::burn_checked(
+                    &token_program,
+                    &get_associated_token_address_with_program_id(&wallet, &mint, &token_program),
+                    &mint,
+                    &wallet,
+                    &[],
+                    amount,
+                    decimals,
+                )?;
+                send(&client, &[instruction], &[&payer]).await?;
+            }
+            TokenSubcommand::Wrap { amount } => {
+                let mint = spl_token::native_mint::ID;
+                let account =
+                    get_associated_token_address_with_program_id(&wallet, &mint, &spl_token::ID);
+                let instructions = vec![
+                    create_associated_token_account_idempotent(
+                        &wallet,
+                        &wallet,
+                        &mint,
+                        &spl_token::ID,
+                    ),
+                    system_instruction::transfer(&wallet, &account, sol_to_lamports(amount)),
+                    token_instruction::sync_native(&spl_token::ID, &account)?,
+                ];
+                send(&client, &instructions, &[&payer]).await?;
+                println!("{}", account);
+            }
+            TokenSubcommand::Unwrap => {
+                let account = get_associated_token_address_with_program_id(
+                    &wallet,
+                    &spl_token::native_mint::ID,
+                    &spl_token::ID,
+                );
+                let instruction = token_instruction::close_account(
+                    &spl_token::ID,
+                    &account,
+                    &wallet,
+                    &wallet,
+                    &[],
+                )?;
+                send(&client, &[instruction], &[&payer]).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_pubkey(pubkey: &str, name: &str) -> Result<Pubkey> {
+    Pubkey::from_str(pubkey).map_err(|_| anyhow!("Invalid {}: {}", name, pubkey))
+}
+
+/// A pubkey, or the pubkey of a keypair path or any other signer URI.
+fn pubkey_arg(ctx: &CliContext, path: &str, name: &str) -> Result<Pubkey> {
+    pubkey_from_path(ctx.matches(), path, name, &mut None)
+        .map_err(|_| anyhow!("Invalid {}: {}", name, path))
+}
+
+/// The mint's token program and decimals.
+async fn fetch_mint(client: &RpcClient, mint: &Pubkey) -> Result<(Pubkey, u8)> {
+    let account = client.get_account(mint).await?;
+    if account.owner != spl_token::ID && account.owner != spl_token_2022::ID {
+        return Err(anyhow!("{} is not owned by a token program", mint));
+    }
+    let state = StateWithExtensions::<Mint>::unpack(&account.data)
+        .map_err(|e| anyhow!("{} is not a mint: {}", mint, e))?;
+    Ok((account.owner, state.base.decimals))
+}
+
+/// Convert an amount in whole tokens to base units, without rounding through a float.
+fn parse_amount(amount: &str, decimals: u8) -> Result<u64> {
+    let invalid = || {
+        anyhow!(
+            "Invalid amount for a mint with {} decimals: {}",
+            decimals,
+            amount
+        )
+    };
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if fraction.len() > decimals as usize
+        || (whole.is_empty() && fraction.is_empty())
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    digits.parse().map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_are_converted_to_base_units() {
+        assert_eq!(parse_amount("1.5", 6).unwrap(), 1_500_000);
+        assert_eq!(parse_amount("2", 9).unwrap(), 2_000_000_000);
+        assert_eq!(parse_amount(".25", 2).unwrap(), 25);
+        assert_eq!(parse_amount("7", 0).unwrap(), 7);
+        assert!(parse_amount("0.001", 2).is_err());
+        assert!(parse_amount("-1", 2).is_err());
+        assert!(parse_amount("1e3", 2).is_err());
+        assert!(parse_amount("18446744073709551616", 0).is_err());
+    }
+}
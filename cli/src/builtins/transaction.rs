@@ -0,0 +1,566 @@
+use crate::context::CliContext;
+use crate::plugin::PluginSubcommand;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Subcommand;
+use futures_util::StreamExt;
+use serde_json::json;
+use solana_account_decoder::UiAccount;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_devtools_anchor_utils::deserialize::client::{export_idls, invoked_programs};
+use solana_devtools_anchor_utils::deserialize::history::{
+    transaction_history, TransactionHistoryConfig,
+};
+use solana_devtools_anchor_utils::deserialize::IdlWithDiscriminators;
+use solana_devtools_cli_config::UrlArg;
+use solana_devtools_rpc::blockhash::{check_message, BlockhashStatus};
+use solana_devtools_tx::decompile_instructions::{lookup_addresses, lookup_addresses_from_tables};
+use solana_devtools_tx::inner_instructions::HistoricalTransaction;
+use solana_sdk::account::Account;
+use solana_sdk::bs58;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+#[derive(Debug, Subcommand)]
+pub enum TransactionCommand {
+    /// A vanilla RPC call to get a confirmed transaction.
+    GetTransaction {
+        /// Transaction signature
+        txid: String,
+        /// Optionally write the data to a file as JSON.
+        outfile: Option<String>,
+    },
+    /// Fetch a confirmed transaction and attempt to deserialize it, and any events
+    /// it emitted, using Anchor IDL data.
+    DeserializeTransaction {
+        /// Optionally supply the IDL filepath. Otherwise, the IDL data is fetched on-chain.
+        #[clap(long)]
+        idl: Option<String>,
+        /// A directory of IDLs written by `export-idls`, used instead of fetching IDLs on-chain.
+        #[clap(long)]
+        idl_dir: Option<String>,
+        /// Optionally write the data to a file as JSON.
+        #[clap(long)]
+        outfile: Option<String>,
+        /// Transaction signature
+        txid: String,
+        /// Output instructions in the RPC's `jsonParsed` format instead.
+        #[clap(long)]
+        json_parsed: bool,
+    },
+    /// Fetch an address's transactions, most recent first, and attempt to deserialize them
+    /// using Anchor IDL data.
+    TransactionHistory {
+        /// Optionally supply the IDL filepath. Otherwise, the IDL data is fetched on-chain.
+        #[clap(long)]
+        idl: Option<String>,
+        /// A directory of IDLs written by `export-idls`, used instead of fetching IDLs on-chain.
+        #[clap(long)]
+        idl_dir: Option<String>,
+        /// Optionally write the data to a file as JSON.
+        #[clap(long)]
+        outfile: Option<String>,
+        /// Account address
+        address: String,
+        /// The most transactions to fetch. Otherwise, the entire history is fetched.
+        #[clap(long)]
+        limit: Option<usize>,
+        /// Start from the transaction before this signature.
+        #[clap(long)]
+        before: Option<String>,
+        /// Stop at this signature, exclusive.
+        #[clap(long)]
+        until: Option<String>,
+        /// The most transactions fetched at once.
+        #[clap(long, default_value_t = 8)]
+        concurrency: usize,
+    },
+    /// Fetch account data and attempt to deserialize it using Anchor IDL data.
+    DeserializeAccount {
+        /// Optionally supply the IDL filepath. Otherwise, the IDL data is fetched on-chain.
+        #[clap(long)]
+        idl: Option<String>,
+        /// A directory of IDLs written by `export-idls`, used instead of fetching IDLs on-chain.
+        #[clap(long)]
+        idl_dir: Option<String>,
+        /// Optionally write the data to a file as JSON.
+        #[clap(long)]
+        outfile: Option<String>,
+        /// Account address
+        address: String,
+    },
+    /// Compare an account on the configured cluster against another cluster, or a saved copy,
+    /// field by field if an IDL decodes both sides, and byte by byte otherwise.
+    DiffAccount {
+        /// Optionally supply the IDL filepath. Otherwise, the IDL data is fetched on-chain.
+        #[clap(long)]
+        idl: Option<String>,
+        /// A directory of IDLs written by `export-idls`, used instead of fetching IDLs on-chain.
+        #[clap(long)]
+        idl_dir: Option<String>,
+        /// Optionally write the data to a file as JSON.
+        #[clap(long)]
+        outfile: Option<String>,
+        /// Account address
+        address: String,
+        /// The URL or moniker of the other cluster.
+        #[clap(long, required_unless_present = "against-file")]
+        against_url: Option<String>,
+        /// A JSON file of the account, as written by `solana account --output json`,
+        /// `deserialize-account`, or an RPC `getAccountInfo` response.
+        #[clap(long, conflicts_with = "against-url")]
+        against_file: Option<String>,
+    },
+    /// Deserialize an unsigned transaction message encoded in Base58
+    DeserializeMessage {
+        /// Optionally supply the IDL filepath. Otherwise, the IDL data is fetched on-chain.
+        #[clap(long)]
+        idl: Option<String>,
+        /// A directory of IDLs written by `export-idls`, used instead of fetching IDLs on-chain.
+        #[clap(long)]
+        idl_dir: Option<String>,
+        /// Base58-encoded transaction message.
+        b58_message: String,
+        /// Optionally write the data to a file as JSON.
+        #[clap(long)]
+        outfile: Option<String>,
+        /// Optionally parse the message data as base64
+        #[clap(long)]
+        base64: bool,
+        /// Optionally parse the message data as a serialized transaction, instead of a message
+        #[clap(long)]
+        as_transaction: bool,
+        /// Output instructions in the RPC's `jsonParsed` format instead.
+        #[clap(long)]
+        json_parsed: bool,
+        /// A JSON file mapping address lookup table addresses to their lists of addresses,
+        /// used instead of fetching the tables.
+        #[clap(long)]
+        alt_file: Option<String>,
+    },
+    /// Deserialize an instruction encoded in Base58
+    DeserializeInstruction {
+        /// Optionally supply the IDL filepath. Otherwise, the IDL data is fetched on-chain.
+        #[clap(long)]
+        idl: Option<String>,
+        /// A directory of IDLs written by `export-idls`, used instead of fetching IDLs on-chain.
+        #[clap(long)]
+        idl_dir: Option<String>,
+        /// Base58-encoded instruction.
+        b58_instruction: String,
+        /// Optionally write the data to a file as JSON.
+        #[clap(long)]
+        outfile: Option<String>,
+        /// Output instructions in the RPC's `jsonParsed` format instead.
+        #[clap(long)]
+        json_parsed: bool,
+    },
+    /// Fetch the on-chain IDLs of programs, and of every program invoked by transactions,
+    /// into a directory with a manifest, for offline decoding with `--idl-dir`.
+    ExportIdls {
+        /// The directory to write IDLs and `manifest.json` to.
+        outdir: String,
+        /// Program ids
+        #[clap(long = "program")]
+        programs: Vec<String>,
+        /// Transaction signatures
+        #[clap(long = "txid")]
+        txids: Vec<String>,
+    },
+    /// Report how long a message's recent blockhash or durable nonce remains valid,
+    /// and whether to send it now, re-sign it, or switch to a durable nonce.
+    CheckMessage {
+        /// Base58-encoded transaction message.
+        b58_message: String,
+        /// Optionally parse the message data as base64
+        #[clap(long)]
+        base64: bool,
+        /// Optionally parse the message data as a serialized transaction, instead of a message
+        #[clap(long)]
+        as_transaction: bool,
+        /// The last valid block height returned with the blockhash,
+        /// without which the remaining validity is unknown.
+        #[clap(long)]
+        last_valid_block_height: Option<u64>,
+        /// Recommend re-signing if fewer blocks than this remain.
+        #[clap(long, default_value_t = 20)]
+        min_remaining_blocks: u64,
+        /// The message can't be re-signed, e.g. because it was signed offline.
+        #[clap(long)]
+        cannot_resign: bool,
+    },
+}
+
+#[async_trait(?Send)]
+impl PluginSubcommand for TransactionCommand {
+    async fn process(self, ctx: &CliContext) -> Result<()> {
+        match self {
+            TransactionCommand::GetTransaction { txid, outfile } => {
+                let client = ctx.rpc_client().await?;
+                let tx = client
+                    .get_transaction_with_config(
+                        &Signature::from_str(&txid)?,
+                        RpcTransactionConfig {
+                            commitment: Some(ctx.commitment()?),
+                            max_supported_transaction_version: Some(0),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+                ctx.output_json(&tx, outfile)?;
+            }
+            TransactionCommand::DeserializeTransaction {
+                txid,
+                idl,
+                idl_dir,
+                outfile,
+                json_parsed,
+            } => {
+                if ctx.offline() {
+                    return Err(anyhow!(
+                        "deserialize-transaction fetches the transaction, which --offline disallows. \
+                        Decode the serialized transaction with `deserialize-message --as-transaction` instead."
+                    ));
+                }
+                let client = ctx.rpc_client().await?;
+                let txid = Signature::from_str(&txid)?;
+                let offline = idl_dir.is_some();
+                let mut deser = ctx.anchor_deserializer(idl, idl_dir)?;
+                let tx = HistoricalTransaction::get_nonblocking(&client, &txid).await?;
+                if !offline {
+                    deser.fetch_and_cache_any_idls(&client, tx.clone()).await?;
+                }
+                if json_parsed {
+                    let json = deser.try_deserialize_transaction_json_parsed(tx)?;
+                    ctx.output_json(&json, outfile)?;
+                } else {
+                    let json = deser.try_deserialize_transaction(tx)?;
+                    ctx.output_json(&json, outfile)?;
+                }
+            }
+            TransactionCommand::TransactionHistory {
+                idl,
+                idl_dir,
+                outfile,
+                address,
+                limit,
+                before,
+                until,
+                concurrency,
+            } => {
+                if ctx.offline() {
+                    return Err(anyhow!(
+                        "transaction-history fetches transactions, which --offline disallows"
+                    ));
+                }
+                let client = ctx.rpc_client().await?;
+                let address = Pubkey::from_str(&address)
+                    .map_err(|_| anyhow!("Invalid address: {}", address))?;
+                let config = TransactionHistoryConfig {
+                    before: before.as_deref().map(Signature::from_str).transpose()?,
+                    until: until.as_deref().map(Signature::from_str).transpose()?,
+                    limit,
+                    concurrency,
+                    commitment: Some(ctx.commitment()?),
+                };
+                let offline = idl_dir.is_some();
+                let mut deser = ctx.anchor_deserializer(idl, idl_dir)?;
+                let mut checked_programs = HashSet::new();
+                let mut history = vec![];
+                let mut transactions = Box::pin(transaction_history(&client, address, config));
+                while let Some(result) = transactions.next().await {
+                    let (signature, tx) = result?;
+                    if !offline {
+                        for program in invoked_programs(tx.clone()) {
+                            // Programs without an IDL are skipped, as in `deserialize-transaction`.
+                            if !checked_programs.insert(program) {
+                                continue;
+                            }
+                            if let Some((_, idl)) =
+                                IdlWithDiscriminators::try_fetch_for_program_with_slot(
+                                    &client, &program,
+                                )
+                                .await?
+                            {
+                                deser.cache_idl(program, idl);
+                            }
+                        }
+                    }
+                    history.push(json!({
+                        "signature": signature.to_string(),
+                        "transaction": deser.try_deserialize_transaction(tx)?,
+                    }));
+                }
+                ctx.output_json(&history, outfile)?;
+            }
+            TransactionCommand::DeserializeAccount {
+                address,
+                outfile,
+                idl,
+                idl_dir,
+            } => {
+                if ctx.offline() {
+                    return Err(anyhow!(
+                        "deserialize-account fetches the account, which --offline disallows"
+                    ));
+                }
+                let client = ctx.rpc_client().await?;
+                let deser = ctx.anchor_deserializer(idl, idl_dir)?;
+                let pubkey =
+                    Pubkey::from_str(&address).map_err(|_| anyhow!("Invalid pubkey address"))?;
+                let account = client.get_account(&pubkey).await?;
+                let act = deser.try_deserialize_account(pubkey, &account)?;
+                ctx.output_json(&act, outfile)?;
+            }
+            TransactionCommand::DiffAccount {
+                idl,
+                idl_dir,
+                outfile,
+                address,
+                against_url,
+                against_file,
+            } => {
+                let pubkey =
+                    Pubkey::from_str(&address).map_err(|_| anyhow!("Invalid pubkey address"))?;
+                let fetch_idl = idl.is_none() && idl_dir.is_none();
+                let mut deser = ctx.anchor_deserializer(idl, idl_dir)?;
+                let client = ctx.rpc_client().await?;
+                let left = client.get_account(&pubkey).await?;
+                let right = match (against_url, against_file) {
+                    (_, Some(path)) => read_account_file(&path)?,
+                    (Some(url), None) => {
+                        let url = UrlArg { url: Some(url) }.resolve(None)?;
+                        RpcClient::new_with_commitment(url, ctx.commitment()?)
+                            .get_account(&pubkey)
+                            .await?
+                    }
+                    (None, None) => {
+                        return Err(anyhow!("--against-url or --against-file is required"))
+                    }
+                };
+                if fetch_idl {
+                    for owner in HashSet::from([left.owner, right.owner]) {
+                        // Accounts of programs without an IDL are diffed byte by byte.
+                        let _ = deser.fetch_and_cache_idl_for_program(&client, &owner).await;
+                    }
+                }
+                ctx.output_json(&deser.diff_accounts(pubkey, &left, &right), outfile)?;
+            }
+            TransactionCommand::DeserializeMessage {
+                b58_message,
+                outfile,
+                idl,
+                idl_dir,
+                base64,
+                as_transaction,
+                json_parsed,
+                alt_file,
+            } => {
+                let deser = ctx.anchor_deserializer(idl, idl_dir)?;
+
+                let message = decode_bytes(&b58_message, base64)?;
+                let message = decode_message(&message, as_transaction)?;
+                let loaded_addresses = match (alt_file, message.address_table_lookups()) {
+                    (_, None) => vec![],
+                    (Some(alt_file), Some(lookups)) => {
+                        let tables = read_alt_file(&alt_file)?;
+                        let missing: Vec<String> = lookups
+                            .iter()
+                            .filter(|lookup| !tables.contains_key(&lookup.account_key))
+                            .map(|lookup| lookup.account_key.to_string())
+                            .collect();
+                        if !missing.is_empty() {
+                            return Err(anyhow!(
+                                "Address lookup tables missing from {}: {}",
+                                alt_file,
+                                missing.join(", ")
+                            ));
+                        }
+                        lookup_addresses_from_tables(&message, &tables)?
+                    }
+                    (None, Some(lookups)) if ctx.offline() => {
+                        let tables: Vec<String> = lookups
+                            .iter()
+                            .map(|lookup| lookup.account_key.to_string())
+                            .collect();
+                        return Err(anyhow!(
+                            "The message uses address lookup tables {}, which --offline can't fetch. \
+                            Pass their addresses with --alt-file.",
+                            tables.join(", ")
+                        ));
+                    }
+                    (None, Some(_)) => lookup_addresses(&ctx.rpc_client().await?, &message).await?,
+                };
+
+                let historical_tx = HistoricalTransaction::new(message, Some(loaded_addresses));
+
+                if json_parsed {
+                    let json = deser.try_deserialize_transaction_json_parsed(historical_tx)?;
+                    ctx.output_json(&json, outfile)?;
+                } else {
+                    let json = deser.try_deserialize_transaction(historical_tx)?;
+                    ctx.output_json(&json, outfile)?;
+                }
+            }
+            TransactionCommand::DeserializeInstruction {
+                b58_instruction,
+                outfile,
+                idl,
+                idl_dir,
+                json_parsed,
+            } => {
+                let ix = bs58::decode(b58_instruction)
+                    .into_vec()
+                    .map_err(|e| anyhow!("Failed to deserialize base58 instruction: {}", e))?;
+                let mut ix: Instruction = bincode::deserialize(&ix)?;
+
+                let deser = if idl.is_some() || idl_dir.is_some() {
+                    ctx.anchor_deserializer(idl, idl_dir)?
+                } else if ctx.offline() {
+                    return Err(anyhow!(
+                        "Fetching the program's IDL is disallowed by --offline, pass --idl or --idl-dir"
+                    ));
+                } else {
+                    let client = ctx.rpc_client().await?;
+                    let mut deser = ctx.anchor_deserializer(None, None)?;
+                    deser
+                        .fetch_and_cache_idl_for_program(&client, &ix.program_id)
+                        .await?;
+                    deser
+                };
+
+                if json_parsed {
+                    let json = deser.try_deserialize_instruction_json_parsed(0, &mut ix)?;
+                    ctx.output_json(&json, outfile)?;
+                } else {
+                    let json = deser.try_deserialize_instruction(0, &mut ix, None)?;
+                    ctx.output_json(&json, outfile)?;
+                }
+            }
+            TransactionCommand::ExportIdls {
+                outdir,
+                programs,
+                txids,
+            } => {
+                let client = ctx.rpc_client().await?;
+                let mut program_ids = HashSet::new();
+                for program in programs {
+                    program_ids.insert(
+                        Pubkey::from_str(&program)
+                            .map_err(|_| anyhow!("Invalid program id: {}", program))?,
+                    );
+                }
+                for txid in txids {
+                    let tx = HistoricalTransaction::get_nonblocking(
+                        &client,
+                        &Signature::from_str(&txid)?,
+                    )
+                    .await?;
+                    program_ids.extend(invoked_programs(tx));
+                }
+                let manifest = export_idls(&client, program_ids, &outdir).await?;
+                ctx.output_json(&manifest, None)?;
+            }
+            TransactionCommand::CheckMessage {
+                b58_message,
+                base64,
+                as_transaction,
+                last_valid_block_height,
+                min_remaining_blocks,
+                cannot_resign,
+            } => {
+                let client = ctx.rpc_client().await?;
+                let message = decode_message(&decode_bytes(&b58_message, base64)?, as_transaction)?;
+                let validity = check_message(&client, &message, last_valid_block_height).await?;
+                let nonce_account = match validity.status {
+                    BlockhashStatus::DurableNonce { nonce_account, .. } => {
+                        Some(nonce_account.to_string())
+                    }
+                    _ => None,
+                };
+                let decision = validity.decide(min_remaining_blocks, !cannot_resign);
+                ctx.output_json(
+                    &json!({
+                        "blockhash": validity.blockhash.to_string(),
+                        "blockHeight": validity.block_height,
+                        "valid": validity.is_valid(),
+                        "nonceAccount": nonce_account,
+                        "remainingBlocks": validity.remaining_blocks(),
+                        "estimatedSecondsRemaining": validity
+                            .estimated_time_remaining()
+                            .map(|t| t.as_secs_f64()),
+                        "decision": format!("{:?}", decision),
+                    }),
+                    None,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a JSON object of `{ "<table address>": ["<address>", ...] }`.
+fn read_alt_file(path: &str) -> Result<HashMap<Pubkey, Vec<Pubkey>>> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read address lookup table file {}: {}", path, e))?;
+    let tables: HashMap<String, Vec<String>> = serde_json::from_str(&json)
+        .map_err(|e| anyhow!("Invalid address lookup table file {}: {}", path, e))?;
+    let parse = |s: &String| Pubkey::from_str(s).map_err(|_| anyhow!("Invalid address: {}", s));
+    tables
+        .iter()
+        .map(|(table, addresses)| {
+            Ok((
+                parse(table)?,
+                addresses.iter().map(parse).collect::<Result<_>>()?,
+            ))
+        })
+        .collect()
+}
+
+/// Read an account from a JSON [UiAccount], or an object with one at `account`,
+/// `ui_account` or `value`, as the Solana CLI, `deserialize-account` and RPC responses have.
+fn read_account_file(path: &str) -> Result<Account> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read account file {}: {}", path, e))?;
+    let mut value: serde_json::Value =
+        serde_json::from_str(&json).map_err(|e| anyhow!("Invalid account file {}: {}", path, e))?;
+    while let Some(inner) = ["account", "ui_account", "value"]
+        .iter()
+        .find_map(|key| value.get(key).filter(|inner| inner.is_object()))
+    {
+        value = inner.clone();
+    }
+    let account: UiAccount = serde_json::from_value(value)
+        .map_err(|e| anyhow!("Invalid account file {}: {}", path, e))?;
+    account
+        .decode()
+        .ok_or_else(|| anyhow!("Could not decode the account data in {}", path))
+}
+
+pub(crate) fn decode_bytes(encoded: &str, base64: bool) -> Result<Vec<u8>> {
+    if base64 {
+        STANDARD
+            .decode(encoded)
+            .map_err(|e| anyhow!("Failed to deserialize base64 message: {e}"))
+    } else {
+        bs58::decode(encoded)
+            .into_vec()
+            .map_err(|e| anyhow!("Failed to deserialize base58 message: {}", e))
+    }
+}
+
+pub(crate) fn decode_message(bytes: &[u8], as_transaction: bool) -> Result<VersionedMessage> {
+    if as_transaction {
+        let tx: VersionedTransaction = bincode::deserialize(bytes)?;
+        Ok(tx.message)
+    } else {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
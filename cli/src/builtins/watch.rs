@@ -0,0 +1,280 @@
+use crate::context::CliContext;
+use crate::plugin::PluginSubcommand;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Subcommand;
+use futures_util::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use solana_account_decoder::UiAccountEncoding;
+use solana_cli_config::Config;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_devtools_anchor_utils::deserialize::AnchorDeserializer;
+use solana_devtools_monitoring::pubsub_multiplexer::{
+    Notification, PubsubMultiplexer, SubscriptionRequest,
+};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+#[derive(Debug, Subcommand)]
+pub enum WatchCommand {
+    /// Print each update to an account, or to any account of a program with `--program`,
+    /// deserialized using Anchor IDL data where possible. Runs until interrupted.
+    Watch {
+        /// Account address, or program id with `--program`.
+        address: String,
+        /// Watch every account owned by the program at `ADDRESS`.
+        #[clap(long)]
+        program: bool,
+        /// Optionally supply the IDL filepath. Otherwise, the IDL data is fetched on-chain.
+        #[clap(long)]
+        idl: Option<String>,
+        /// A directory of IDLs written by `export-idls`, used instead of fetching IDLs on-chain.
+        #[clap(long)]
+        idl_dir: Option<String>,
+        /// Defaults to the websocket URL of the configured RPC URL.
+        #[clap(long)]
+        ws_url: Option<String>,
+        /// Poll over RPC every this many seconds instead of subscribing over a websocket.
+        /// Polling is also used if the websocket can't be reached.
+        #[clap(long)]
+        poll_interval: Option<u64>,
+        /// Print one compact JSON object per update, instead of pretty JSON.
+        #[clap(long)]
+        json: bool,
+        /// After the first update of an account, print only the fields that changed.
+        #[clap(long)]
+        changed_fields_only: bool,
+    },
+}
+
+/// An account at the slot it was seen.
+type Update = (Slot, Pubkey, Account);
+
+const DEFAULT_POLL_INTERVAL: u64 = 5;
+
+#[async_trait(?Send)]
+impl PluginSubcommand for WatchCommand {
+    async fn process(self, ctx: &CliContext) -> Result<()> {
+        let WatchCommand::Watch {
+            address,
+            program,
+            idl,
+            idl_dir,
+            ws_url,
+            poll_interval,
+            json,
+            changed_fields_only,
+        } = self;
+        let address = Pubkey::from_str(&address).map_err(|_| anyhow!("Invalid address"))?;
+        let fetch_idl = idl.is_none() && idl_dir.is_none();
+        let mut deser = ctx.anchor_deserializer(idl, idl_dir)?;
+        let client = ctx.rpc_client().await?;
+        let commitment = ctx.commitment()?;
+        if fetch_idl {
+            let program_id = if program {
+                address
+            } else {
+                client.get_account(&address).await?.owner
+            };
+            // Accounts of programs without an IDL are printed undecoded.
+            let _ = deser
+                .fetch_and_cache_idl_for_program(&client, &program_id)
+                .await;
+        }
+
+        let ws_url = ws_url.unwrap_or_else(|| Config::compute_websocket_url(&client.url()));
+        let mut updates = match poll_interval {
+            Some(seconds) => poll(client, address, program, Duration::from_secs(seconds)),
+            None => match PubsubClient::new(&ws_url).await {
+                Ok(_) => subscribe(&ws_url, address, program, commitment).await?,
+                Err(e) => {
+                    eprintln!(
+                        "Could not connect to {}, polling every {}s instead: {}",
+                        ws_url, DEFAULT_POLL_INTERVAL, e
+                    );
+                    poll(
+                        client,
+                        address,
+                        program,
+                        Duration::from_secs(DEFAULT_POLL_INTERVAL),
+                    )
+                }
+            },
+        };
+
+        let mut previous: HashMap<Pubkey, Account> = HashMap::new();
+        while let Some(update) = updates.next().await {
+            let (slot, pubkey, account) = update?;
+            let last = previous.insert(pubkey, account.clone());
+            if last.as_ref() == Some(&account) {
+                continue;
+            }
+            let output = match last {
+                Some(last) if changed_fields_only => json!({
+                    "slot": slot,
+                    "diff": deser.diff_accounts(pubkey, &last, &account),
+                }),
+                _ => json!({
+                    "slot": slot,
+                    "account": decode(&deser, pubkey, &account)?,
+                }),
+            };
+            let output = deser.to_json(&output)?;
+            if json {
+                println!("{}", serde_json::to_string(&output)?);
+            } else {
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The account decoded with the cached IDLs, or its raw Base64 data.
+fn decode(deser: &AnchorDeserializer, pubkey: Pubkey, account: &Account) -> Result<Value> {
+    match deser.try_deserialize_account(pubkey, account) {
+        Ok(decoded) => Ok(serde_json::to_value(decoded)?),
+        Err(_) => Ok(json!({
+            "address": pubkey.to_string(),
+            "lamports": account.lamports,
+            "owner": account.owner.to_string(),
+            "data": STANDARD.encode(&account.data),
+        })),
+    }
+}
+
+async fn subscribe(
+    ws_url: &str,
+    address: Pubkey,
+    program: bool,
+    commitment: CommitmentConfig,
+) -> Result<BoxStream<'static, Result<Update>>> {
+    let account_config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(commitment),
+        ..Default::default()
+    };
+    let request = if program {
+        SubscriptionRequest::Program {
+            program_id: address,
+            config: Some(RpcProgramAccountsConfig {
+                account_config,
+                ..Default::default()
+            }),
+        }
+    } else {
+        SubscriptionRequest::Account {
+            pubkey: address,
+            config: Some(account_config),
+        }
+    };
+    let subscription = PubsubMultiplexer::new(ws_url).subscribe(request).await?;
+    Ok(
+        stream::unfold(subscription, move |mut subscription| async move {
+            loop {
+                let update = match subscription.recv().await {
+                    Ok(Notification::Account(response)) => response
+                        .value
+                        .decode()
+                        .map(|account| (response.context.slot, address, account))
+                        .ok_or_else(|| anyhow!("Could not decode the account update")),
+                    Ok(Notification::Program(response)) => {
+                        match (
+                            Pubkey::from_str(&response.value.pubkey),
+                            response.value.account.decode(),
+                        ) {
+                            (Ok(pubkey), Some(account)) => {
+                                Ok((response.context.slot, pubkey, account))
+                            }
+                            _ => Err(anyhow!("Could not decode the account update")),
+                        }
+                    }
+                    Ok(Notification::Gap {
+                        last_seen_slot,
+                        resumed_at_slot,
+                    }) => {
+                        eprintln!(
+                            "Reconnected, updates between slots {} and {} may have been missed",
+                            last_seen_slot, resumed_at_slot
+                        );
+                        continue;
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        eprintln!("Fell behind, skipped {} updates", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                };
+                return Some((update, subscription));
+            }
+        })
+        .boxed(),
+    )
+}
+
+/// Yields every account on each poll, unchanged accounts are filtered out by the caller.
+fn poll(
+    client: RpcClient,
+    address: Pubkey,
+    program: bool,
+    interval: Duration,
+) -> BoxStream<'static, Result<Update>> {
+    stream::unfold((client, true), move |(client, first)| async move {
+        if !first {
+            tokio::time::sleep(interval).await;
+        }
+        let updates: Result<Vec<Update>> = async {
+            if program {
+                let slot = client.get_slot().await?;
+                Ok(client
+                    .get_program_accounts(&address)
+                    .await?
+                    .into_iter()
+                    .map(|(pubkey, account)| (slot, pubkey, account))
+                    .collect())
+            } else {
+                let response = client
+                    .get_account_with_commitment(&address, client.commitment())
+                    .await?;
+                Ok(response
+                    .value
+                    .map(|account| (response.context.slot, address, account))
+                    .into_iter()
+                    .collect())
+            }
+        }
+        .await;
+        let updates: Vec<Result<Update>> = match updates {
+            Ok(updates) => updates.into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        };
+        Some((stream::iter(updates), (client, false)))
+    })
+    .flatten()
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undecodable_accounts_are_printed_raw() {
+        let pubkey = Pubkey::new_unique();
+        let account = Account::new(1, 3, &Pubkey::new_unique());
+        let decoded = decode(&AnchorDeserializer::new(), pubkey, &account).unwrap();
+        assert_eq!(decoded["address"], json!(pubkey.to_string()));
+        assert_eq!(decoded["data"], json!("AAAA"));
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::{anyhow, Result};
+use clap::{ArgMatches, Parser};
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_devtools_anchor_utils::deserialize::{AnchorDeserializer, JsonNormalization};
+use solana_devtools_cli_config::{CommitmentArg, KeypairArg, UrlArg};
+use solana_devtools_rpc::probe::validate_client;
+use solana_devtools_signers::concrete_signer::ConcreteSigner;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::cell::Cell;
+use std::fs::File;
+use std::io::Write;
+use std::str::FromStr;
+
+/// CLI for an improved Solana DX
+#[derive(Debug, Parser)]
+#[clap(name = "solana-devtools")]
+pub struct GlobalArgs {
+    #[clap(flatten)]
+    pub url: UrlArg,
+    #[clap(flatten)]
+    pub keypair: KeypairArg,
+    #[clap(flatten)]
+    pub commitment: CommitmentArg,
+    /// Skip checking the RPC endpoint's health and version before the first RPC request.
+    #[clap(long, global = true)]
+    pub skip_probe: bool,
+    /// Sort JSON object keys and write every pubkey as base58 in JSON output,
+    /// so that it is byte-stable across runs and versions.
+    #[clap(long, global = true)]
+    pub stable_output: bool,
+    /// Fail instead of making any network request. IDLs and address lookup tables
+    /// must then come from local files, e.g. `--idl-dir` and `--alt-file`.
+    #[clap(long, global = true)]
+    pub offline: bool,
+}
+
+/// Everything a subcommand needs from the top-level CLI invocation.
+/// The global args are resolved lazily, so subcommands that never touch
+/// the network or a signer work without a Solana CLI config file.
+pub struct CliContext {
+    args: GlobalArgs,
+    matches: ArgMatches,
+    probed: Cell<bool>,
+}
+
+impl CliContext {
+    pub fn new(args: GlobalArgs, matches: ArgMatches) -> Self {
+        Self {
+            args,
+            matches,
+            probed: Cell::new(false),
+        }
+    }
+
+    /// The top-level matches, including the matched subcommand.
+    pub fn matches(&self) -> &ArgMatches {
+        &self.matches
+    }
+
+    pub fn signer(&self) -> Result<ConcreteSigner> {
+        let keypair = KeypairArg {
+            keypair: self.args.keypair.keypair.clone(),
+        };
+        Ok(keypair.resolve(None)?)
+    }
+
+    pub fn url(&self) -> Result<String> {
+        Ok(self.args.url.resolve(None)?)
+    }
+
+    pub fn commitment(&self) -> Result<CommitmentConfig> {
+        let commitment = CommitmentArg {
+            commitment: self.args.commitment.commitment.clone(),
+        };
+        Ok(commitment.resolve(None)?)
+    }
+
+    pub fn offline(&self) -> bool {
+        self.args.offline
+    }
+
+    /// An RPC client for the configured URL and commitment, unless `--offline` is passed.
+    /// Unless `--skip-probe` is passed, the endpoint is validated the first time this is called.
+    pub async fn rpc_client(&self) -> Result<RpcClient> {
+        if self.args.offline {
+            return Err(anyhow!(
+                "This command needs an RPC connection, which --offline disallows"
+            ));
+        }
+        let client = RpcClient::new_with_commitment(self.url()?, self.commitment()?);
+        if !self.args.skip_probe && !self.probed.get() {
+            validate_client(&client).await?;
+            self.probed.set(true);
+        }
+        Ok(client)
+    }
+
+    /// An [AnchorDeserializer] with the IDL from an optional `<program-id>:<filepath>`
+    /// argument cached, and every IDL in an optional `--idl-dir`.
+    /// It is deterministic if `--stable-output` is passed.
+    pub fn anchor_deserializer(
+        &self,
+        idl: Option<String>,
+        idl_dir: Option<String>,
+    ) -> Result<AnchorDeserializer> {
+        let mut deser = anchor_deserializer(idl)?;
+        if let Some(dir) = idl_dir {
+            deser
+                .cache_idls_from_dir(&dir)
+                .map_err(|e| anyhow!("could not add IDLs from directory {}: {}", dir, e))?;
+        }
+        if self.args.stable_output {
+            return Ok(deser.with_stable_output());
+        }
+        Ok(deser)
+    }
+
+    /// Print a value as pretty JSON, or write it to `outfile` if one is given.
+    /// If `--stable-output` is passed, the JSON is normalized first.
+    pub fn output_json<T: Serialize + ?Sized>(
+        &self,
+        value: &T,
+        outfile: Option<String>,
+    ) -> Result<()> {
+        let json = if self.args.stable_output {
+            serde_json::to_string_pretty(&JsonNormalization::stable().to_value(value)?)?
+        } else {
+            serde_json::to_string_pretty(value)?
+        };
+        if let Some(outfile) = outfile {
+            let mut file = File::create(outfile)?;
+            file.write_all(json.as_bytes())?;
+        } else {
+            println!("{}", json);
+        }
+        Ok(())
+    }
+}
+
+/// Parse an IDL argument of the form `<program-id>:<filepath>`.
+pub fn parse_idl_arg(arg: &str) -> Result<(Pubkey, String)> {
+    let pieces: Vec<&str> = arg.split(':').collect();
+    if pieces.len() != 2 {
+        return Err(anyhow!(
+            "Invalid idl argument, must be <program-id>:<filepath>"
+        ));
+    }
+    let prog_id = Pubkey::from_str(pieces[0])?;
+    Ok((prog_id, pieces[1].to_string()))
+}
+
+/// An [AnchorDeserializer] with the IDL from an optional `<program-id>:<filepath>` argument cached.
+pub fn anchor_deserializer(idl: Option<String>) -> Result<AnchorDeserializer> {
+    let mut deser = AnchorDeserializer::new();
+    if let Some(arg) = idl {
+        let (prog_id, path) = parse_idl_arg(&arg)?;
+        deser
+            .cache_idl_from_file(prog_id, &path)
+            .map_err(|e| anyhow!("could not add IDL from filepath {}: {}", path, e))?;
+    }
+    Ok(deser)
+}
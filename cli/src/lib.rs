@@ -0,0 +1,9 @@
+//! The `solana-devtools` CLI as a library. Downstream crates can register their own
+//! subcommands with [SolanaDevtoolsCli::plugin], reusing the global
+//! `--url`, `--keypair` and `--commitment` handling of the built-in subcommands.
+pub mod builtins;
+pub mod context;
+pub mod plugin;
+
+pub use context::{CliContext, GlobalArgs};
+pub use plugin::{CliPlugin, PluginSubcommand, SolanaDevtoolsCli, SubcommandPlugin};
@@ -0,0 +1,155 @@
+use crate::context::{CliContext, GlobalArgs};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use clap::{ArgMatches, Command, CommandFactory, FromArgMatches, Subcommand};
+use std::marker::PhantomData;
+
+/// A group of subcommands registered on a [SolanaDevtoolsCli].
+/// Most plugins should derive [clap::Subcommand] on an enum and implement
+/// [PluginSubcommand] instead of implementing this directly.
+#[async_trait(?Send)]
+pub trait CliPlugin {
+    /// Add this plugin's subcommands to the CLI.
+    fn augment_subcommands<'help>(&self, cmd: Command<'help>) -> Command<'help>;
+
+    /// Whether this plugin handles the named subcommand.
+    fn has_subcommand(&self, name: &str) -> bool;
+
+    /// Run the subcommand found in [CliContext::matches].
+    async fn process(&self, ctx: &CliContext) -> Result<()>;
+}
+
+/// A [clap::Subcommand] enum that can be registered with [SolanaDevtoolsCli::plugin].
+#[async_trait(?Send)]
+pub trait PluginSubcommand: Subcommand {
+    async fn process(self, ctx: &CliContext) -> Result<()>;
+}
+
+/// Adapts a [PluginSubcommand] into a [CliPlugin].
+pub struct SubcommandPlugin<T>(PhantomData<T>);
+
+impl<T> Default for SubcommandPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait(?Send)]
+impl<T: PluginSubcommand> CliPlugin for SubcommandPlugin<T> {
+    fn augment_subcommands<'help>(&self, cmd: Command<'help>) -> Command<'help> {
+        T::augment_subcommands(cmd)
+    }
+
+    fn has_subcommand(&self, name: &str) -> bool {
+        T::has_subcommand(name)
+    }
+
+    async fn process(&self, ctx: &CliContext) -> Result<()> {
+        T::from_arg_matches(ctx.matches())?.process(ctx).await
+    }
+}
+
+/// The `solana-devtools` CLI, assembled from plugins.
+/// Every plugin shares the global `--url`, `--keypair` and `--commitment` args.
+pub struct SolanaDevtoolsCli {
+    plugins: Vec<Box<dyn CliPlugin>>,
+}
+
+impl Default for SolanaDevtoolsCli {
+    /// The CLI with all built-in subcommands registered.
+    fn default() -> Self {
+        Self::empty()
+            .plugin::<crate::builtins::KeyCommand>()
+            .plugin::<crate::builtins::ComputeBudgetCommand>()
+            .plugin::<crate::builtins::MemoCommand>()
+            .plugin::<crate::builtins::TransactionCommand>()
+            .plugin::<crate::builtins::IdlCommand>()
+            .plugin::<crate::builtins::ProgramCommand>()
+            .plugin::<crate::builtins::SimulateCommand>()
+            .plugin::<crate::builtins::WatchCommand>()
+            .plugin::<crate::builtins::TokenCommand>()
+            .plugin::<crate::builtins::MultisigCommand>()
+    }
+}
+
+impl SolanaDevtoolsCli {
+    /// A CLI with no subcommands registered.
+    pub fn empty() -> Self {
+        Self { plugins: vec![] }
+    }
+
+    pub fn plugin<T: PluginSubcommand + 'static>(self) -> Self {
+        self.with_plugin(SubcommandPlugin::<T>::default())
+    }
+
+    pub fn with_plugin(mut self, plugin: impl CliPlugin + 'static) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    pub fn command(&self) -> Command<'static> {
+        self.plugins
+            .iter()
+            .fold(GlobalArgs::command(), |cmd, plugin| {
+                plugin.augment_subcommands(cmd)
+            })
+            .subcommand_required(true)
+            .arg_required_else_help(true)
+    }
+
+    /// Parse args from the environment and run the matched subcommand.
+    pub async fn run(&self) -> Result<()> {
+        let matches = self.command().get_matches();
+        self.process(matches).await
+    }
+
+    pub async fn process(&self, matches: ArgMatches) -> Result<()> {
+        let args = GlobalArgs::from_arg_matches(&matches)?;
+        let name = matches
+            .subcommand_name()
+            .ok_or(anyhow!("No subcommand provided"))?
+            .to_string();
+        let plugin = self
+            .plugins
+            .iter()
+            .find(|plugin| plugin.has_subcommand(&name))
+            .ok_or(anyhow!("Unrecognized subcommand: {}", name))?;
+        plugin.process(&CliContext::new(args, matches)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        static GREETED: Cell<u32> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug, Subcommand)]
+    enum GreetCommand {
+        Greet { times: u32 },
+    }
+
+    #[async_trait(?Send)]
+    impl PluginSubcommand for GreetCommand {
+        async fn process(self, _ctx: &CliContext) -> Result<()> {
+            let GreetCommand::Greet { times } = self;
+            GREETED.with(|greeted| greeted.set(times));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn plugin_subcommands_are_dispatched() {
+        let cli = SolanaDevtoolsCli::default().plugin::<GreetCommand>();
+        cli.command().debug_assert();
+        let matches = cli
+            .command()
+            .try_get_matches_from(["solana-devtools", "-u", "localhost", "greet", "3"])
+            .unwrap();
+        cli.process(matches).await.unwrap();
+        assert_eq!(GREETED.with(|greeted| greeted.get()), 3);
+    }
+}
@@ -1,6 +1,8 @@
 #[cfg(feature = "anchor-lang")]
 pub mod anchor_lang_err;
 pub mod instruction_err;
+pub mod program_log;
+pub mod resolver;
 pub mod transaction_err;
 
 #[cfg(feature = "solana-program-test")]
@@ -8,12 +10,11 @@ pub mod banks_client_err;
 #[cfg(feature = "solana-client")]
 pub mod client_err;
 
+pub use solana_devtools_macros::ErrorCodes;
+
 #[cfg(feature = "solana-program")]
 use solana_program;
 
-// TODO Macro for calculating the number of error code variants,
-//     and impl TryFrom<u32>
-
 /// ```rust
 /// use anchor_lang::prelude::error_code;
 /// use solana_devtools_errors::ErrorCause;
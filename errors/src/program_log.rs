@@ -0,0 +1,131 @@
+//! Parse the lines the runtime logs while executing a transaction,
+//! e.g. `Program <id> invoke [1]` or `Program log: <message>`.
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramLog<'a> {
+    /// `Program <id> invoke [<stack height>]`
+    Invoke {
+        program_id: Pubkey,
+        stack_height: usize,
+    },
+    /// `Program <id> consumed <units> of <budget> compute units`
+    Consumed { program_id: Pubkey, units: u64 },
+    /// `Program <id> success`
+    Success(Pubkey),
+    /// `Program <id> failed: <reason>`
+    Failed { program_id: Pubkey, reason: &'a str },
+    /// `Program return: <id> <base64 data>`
+    Return { program_id: Pubkey, data: &'a str },
+    /// `Program data: <base64 segments>`, logged with `sol_log_data`, e.g. by Anchor's `emit!`.
+    Data(&'a str),
+    /// `Program log: <message>`, logged with `msg!`.
+    Message(&'a str),
+    /// Any other line, such as `Log truncated`.
+    Unrecognized(&'a str),
+}
+
+impl<'a> ProgramLog<'a> {
+    pub fn parse(log: &'a str) -> Self {
+        let Some(rest) = log.strip_prefix("Program ") else {
+            return Self::Unrecognized(log);
+        };
+        if let Some(message) = rest.strip_prefix("log: ") {
+            return Self::Message(message);
+        }
+        if let Some(data) = rest.strip_prefix("data: ") {
+            return Self::Data(data);
+        }
+        Self::parse_program_log(rest).unwrap_or(Self::Unrecognized(log))
+    }
+
+    fn parse_program_log(rest: &'a str) -> Option<Self> {
+        if let Some(rest) = rest.strip_prefix("return: ") {
+            let (program_id, data) = rest.split_once(' ')?;
+            return Some(Self::Return {
+                program_id: Pubkey::from_str(program_id).ok()?,
+                data,
+            });
+        }
+        let (program_id, rest) = rest.split_once(' ')?;
+        let program_id = Pubkey::from_str(program_id).ok()?;
+        if let Some(stack_height) = rest.strip_prefix("invoke [") {
+            return Some(Self::Invoke {
+                program_id,
+                stack_height: stack_height.strip_suffix(']')?.parse().ok()?,
+            });
+        }
+        if let Some(units) = rest.strip_prefix("consumed ") {
+            return Some(Self::Consumed {
+                program_id,
+                units: units.split_whitespace().next()?.parse().ok()?,
+            });
+        }
+        if rest == "success" {
+            return Some(Self::Success(program_id));
+        }
+        let reason = rest.strip_prefix("failed: ")?;
+        Some(Self::Failed { program_id, reason })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runtime_logs_are_parsed() {
+        let program_id = Pubkey::new_unique();
+        let cases = [
+            (
+                format!("Program {program_id} invoke [2]"),
+                ProgramLog::Invoke {
+                    program_id,
+                    stack_height: 2,
+                },
+            ),
+            (
+                format!("Program {program_id} consumed 1000 of 200000 compute units"),
+                ProgramLog::Consumed {
+                    program_id,
+                    units: 1000,
+                },
+            ),
+            (
+                format!("Program {program_id} success"),
+                ProgramLog::Success(program_id),
+            ),
+            (
+                format!("Program {program_id} failed: custom program error: 0x1"),
+                ProgramLog::Failed {
+                    program_id,
+                    reason: "custom program error: 0x1",
+                },
+            ),
+            (
+                format!("Program return: {program_id} KgAAAA=="),
+                ProgramLog::Return {
+                    program_id,
+                    data: "KgAAAA==",
+                },
+            ),
+            ("Program data: AQID".to_string(), ProgramLog::Data("AQID")),
+            (
+                "Program log: Instruction: Deposit".to_string(),
+                ProgramLog::Message("Instruction: Deposit"),
+            ),
+            (
+                "Log truncated".to_string(),
+                ProgramLog::Unrecognized("Log truncated"),
+            ),
+            (
+                "Program is not a pubkey success".to_string(),
+                ProgramLog::Unrecognized("Program is not a pubkey success"),
+            ),
+        ];
+        for (log, expected) in &cases {
+            assert_eq!(ProgramLog::parse(log), *expected, "{log}");
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Resolve custom program error codes to the names of the errors they came from.
+use crate::program_log::ProgramLog;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::TransactionError;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramErrorInfo {
+    pub name: String,
+    pub msg: Option<String>,
+}
+
+/// A program's custom errors, keyed by error code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgramErrors {
+    pub program_name: String,
+    pub errors: BTreeMap<u32, ProgramErrorInfo>,
+}
+
+impl ProgramErrors {
+    pub fn new(program_name: impl Into<String>) -> Self {
+        Self {
+            program_name: program_name.into(),
+            errors: BTreeMap::new(),
+        }
+    }
+
+    pub fn error(mut self, code: u32, name: impl Into<String>, msg: Option<String>) -> Self {
+        self.errors.insert(
+            code,
+            ProgramErrorInfo {
+                name: name.into(),
+                msg,
+            },
+        );
+        self
+    }
+
+    /// Register the variants of an error enum, such as one declared with Anchor's `#[error_code]`,
+    /// named after their `Debug` output, with their `Display` output as the message.
+    pub fn error_enum<E>(mut self, variants: impl IntoIterator<Item = E>) -> Self
+    where
+        E: fmt::Debug + fmt::Display + Into<u32>,
+    {
+        for variant in variants {
+            let (name, msg) = (format!("{:?}", variant), variant.to_string());
+            self = self.error(variant.into(), name, Some(msg));
+        }
+        self
+    }
+}
+
+/// Maps [InstructionError::Custom] codes to the error they came from,
+/// using the errors registered for the failing program.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorResolver {
+    programs: HashMap<Pubkey, ProgramErrors>,
+}
+
+impl ErrorResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces any errors already registered for the program.
+    pub fn register(&mut self, program_id: Pubkey, errors: ProgramErrors) {
+        self.programs.insert(program_id, errors);
+    }
+
+    pub fn resolve(&self, program_id: &Pubkey, code: u32) -> Option<(&str, &ProgramErrorInfo)> {
+        let program = self.programs.get(program_id)?;
+        let error = program.errors.get(&code)?;
+        Some((program.program_name.as_str(), error))
+    }
+
+    /// Build a report of a failed transaction. The program that returned the error,
+    /// and the name of the instruction it was processing, are taken from the logs.
+    pub fn report(&self, error: &TransactionError, logs: &[String]) -> ErrorReport {
+        let failure = failed_invocation(logs);
+        let (program_id, instruction_name) = match failure {
+            Some((program_id, name)) => (Some(program_id), name),
+            None => (None, None),
+        };
+        let resolved = match (error, program_id) {
+            (
+                TransactionError::InstructionError(_, InstructionError::Custom(code)),
+                Some(program_id),
+            ) => self
+                .resolve(&program_id, *code)
+                .map(|(program_name, info)| ResolvedError {
+                    program_name: program_name.to_string(),
+                    name: info.name.clone(),
+                    msg: info.msg.clone(),
+                }),
+            _ => None,
+        };
+        ErrorReport {
+            error: error.clone(),
+            program_id,
+            instruction_name,
+            resolved,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedError {
+    pub program_name: String,
+    pub name: String,
+    pub msg: Option<String>,
+}
+
+/// A transaction error, and where it came from.
+/// Displayed as e.g. `instruction 0 (Deposit) failed: MyProgram::InsufficientFunds (0x1771)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    pub error: TransactionError,
+    /// The innermost program that failed, according to the logs.
+    pub program_id: Option<Pubkey>,
+    /// As logged by Anchor programs with `Instruction: <name>`.
+    pub instruction_name: Option<String>,
+    /// `None` unless the error is a custom error registered for [Self::program_id].
+    pub resolved: Option<ResolvedError>,
+}
+
+impl fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let TransactionError::InstructionError(index, error) = &self.error else {
+            return write!(f, "{}", self.error);
+        };
+        write!(f, "instruction {}", index)?;
+        if let Some(name) = &self.instruction_name {
+            write!(f, " ({})", name)?;
+        }
+        match (&self.resolved, error) {
+            (Some(resolved), InstructionError::Custom(code)) => {
+                write!(
+                    f,
+                    " failed: {}::{} ({:#x})",
+                    resolved.program_name, resolved.name, code
+                )?;
+                if let Some(msg) = &resolved.msg {
+                    write!(f, ": {}", msg)?;
+                }
+                Ok(())
+            }
+            _ => write!(f, " failed: {}", error),
+        }
+    }
+}
+
+/// The first program to log a failure is the innermost one, its callers fail after it.
+fn failed_invocation(logs: &[String]) -> Option<(Pubkey, Option<String>)> {
+    let mut stack: Vec<(Pubkey, Option<String>)> = vec![];
+    for log in logs {
+        match ProgramLog::parse(log) {
+            ProgramLog::Message(message) => {
+                if let (Some(name), Some((_, instruction_name))) =
+                    (message.strip_prefix("Instruction: "), stack.last_mut())
+                {
+                    *instruction_name = Some(name.to_string());
+                }
+            }
+            ProgramLog::Invoke { program_id, .. } => stack.push((program_id, None)),
+            ProgramLog::Success(_) => {
+                stack.pop();
+            }
+            ProgramLog::Failed { program_id, .. } => {
+                return match stack.pop() {
+                    Some((invoked, name)) if invoked == program_id => Some((program_id, name)),
+                    _ => Some((program_id, None)),
+                };
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy)]
+    enum VaultError {
+        InsufficientFunds,
+        Locked,
+    }
+
+    impl fmt::Display for VaultError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                VaultError::InsufficientFunds => write!(f, "Insufficient funds"),
+                VaultError::Locked => write!(f, "The vault is locked"),
+            }
+        }
+    }
+
+    impl From<VaultError> for u32 {
+        fn from(error: VaultError) -> u32 {
+            6000 + error as u32
+        }
+    }
+
+    #[test]
+    fn custom_errors_are_resolved_from_the_failing_program() {
+        let (outer, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut resolver = ErrorResolver::new();
+        resolver.register(
+            vault,
+            ProgramErrors::new("Vault")
+                .error_enum([VaultError::InsufficientFunds, VaultError::Locked]),
+        );
+        let logs: Vec<String> = [
+            format!("Program {outer} invoke [1]"),
+            "Program log: Instruction: Rebalance".to_string(),
+            format!("Program {vault} invoke [2]"),
+            "Program log: Instruction: Withdraw".to_string(),
+            format!("Program {vault} failed: custom program error: 0x1770"),
+            format!("Program {outer} failed: custom program error: 0x1770"),
+        ]
+        .into();
+        let error = TransactionError::InstructionError(1, InstructionError::Custom(6000));
+        let report = resolver.report(&error, &logs);
+        assert_eq!(report.program_id, Some(vault));
+        assert_eq!(
+            report.to_string(),
+            "instruction 1 (Withdraw) failed: Vault::InsufficientFunds (0x1770): Insufficient funds"
+        );
+
+        let unknown = TransactionError::InstructionError(1, InstructionError::Custom(1));
+        assert_eq!(
+            resolver.report(&unknown, &logs).to_string(),
+            "instruction 1 (Withdraw) failed: custom program error: 0x1"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Program logs parsed into a tree of program invocations,
+//! see [crate::ProcessedMessage::invoke_tree].
+use solana_devtools_errors::program_log::ProgramLog;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvocationResult {
+    Success,
+    /// The reason given in the `failed:` log.
+    Failed(String),
+    /// The logs end before the invocation does, e.g. because they were truncated.
+    Incomplete,
+}
+
+/// One program invocation, and the invocations it made through CPI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub program_id: Pubkey,
+    /// 1 for a top-level instruction, 2 for an invocation made by one, and so on.
+    pub stack_height: usize,
+    /// Units consumed, including any CPIs. `None` for builtins, which do not log consumption.
+    pub compute_units: Option<u64>,
+    /// `msg!` lines logged by the program, without the `Program log: ` prefix.
+    /// Lines without a recognized prefix are kept whole.
+    pub logs: Vec<String>,
+    /// Base64-encoded `sol_log_data` payloads, such as Anchor events.
+    pub data: Vec<String>,
+    /// Base64-encoded return data set by the program.
+    pub return_data: Option<String>,
+    pub result: InvocationResult,
+    /// Invocations made by the program, in execution order.
+    pub invocations: Vec<Invocation>,
+}
+
+impl Invocation {
+    /// Parse the logs of a transaction into its top-level invocations.
+    /// Logs outside of any invocation are skipped.
+    pub fn parse_logs(logs: &[String]) -> Vec<Self> {
+        let mut top_level: Vec<Self> = vec![];
+        let mut stack: Vec<Self> = vec![];
+        for log in logs {
+            match ProgramLog::parse(log) {
+                ProgramLog::Invoke {
+                    program_id,
+                    stack_height,
+                } => {
+                    // An invocation at or above the current depth means the logs
+                    // skipped the end of the current one.
+                    while stack
+                        .last()
+                        .is_some_and(|current| current.stack_height >= stack_height)
+                    {
+                        finish(&mut stack, &mut top_level);
+                    }
+                    stack.push(Self {
+                        program_id,
+                        stack_height,
+                        compute_units: None,
+                        logs: vec![],
+                        data: vec![],
+                        return_data: None,
+                        result: InvocationResult::Incomplete,
+                        invocations: vec![],
+                    });
+                }
+                log => {
+                    let Some(current) = stack.last_mut() else {
+                        continue;
+                    };
+                    match log {
+                        ProgramLog::Consumed { program_id, units } => {
+                            if current.program_id == program_id {
+                                current.compute_units = Some(units);
+                            }
+                        }
+                        ProgramLog::Success(_) => {
+                            current.result = InvocationResult::Success;
+                            finish(&mut stack, &mut top_level);
+                        }
+                        ProgramLog::Failed { reason, .. } => {
+                            current.result = InvocationResult::Failed(reason.to_string());
+                            finish(&mut stack, &mut top_level);
+                        }
+                        ProgramLog::Return { data, .. } => {
+                            current.return_data = Some(data.to_string())
+                        }
+                        ProgramLog::Data(data) => current.data.push(data.to_string()),
+                        ProgramLog::Message(message) | ProgramLog::Unrecognized(message) => {
+                            current.logs.push(message.to_string())
+                        }
+                        ProgramLog::Invoke { .. } => unreachable!(),
+                    }
+                }
+            }
+        }
+        while !stack.is_empty() {
+            finish(&mut stack, &mut top_level);
+        }
+        top_level
+    }
+
+    /// The invocation and every invocation below it, in execution order.
+    pub fn iter(&self) -> impl Iterator<Item = &Invocation> {
+        let mut pending = vec![self];
+        std::iter::from_fn(move || {
+            let next = pending.pop()?;
+            pending.extend(next.invocations.iter().rev());
+            Some(next)
+        })
+    }
+}
+
+/// Pop the current invocation and attach it to its caller.
+fn finish(stack: &mut Vec<Invocation>, top_level: &mut Vec<Invocation>) {
+    if let Some(invocation) = stack.pop() {
+        match stack.last_mut() {
+            Some(caller) => caller.invocations.push(invocation),
+            None => top_level.push(invocation),
+        }
+    }
+}
+
+/// The `msg!` lines of every invocation, grouped by program in execution order.
+pub fn logs_by_program(invocations: &[Invocation]) -> BTreeMap<Pubkey, Vec<String>> {
+    let mut logs: BTreeMap<Pubkey, Vec<String>> = BTreeMap::new();
+    for invocation in invocations.iter().flat_map(Invocation::iter) {
+        logs.entry(invocation.program_id)
+            .or_default()
+            .extend(invocation.logs.iter().cloned());
+    }
+    logs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_are_parsed_into_nested_invocations() {
+        let (outer, inner, builtin) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let logs: Vec<String> = [
+            format!("Program {outer} invoke [1]"),
+            "Program log: Instruction: Outer".to_string(),
+            format!("Program {inner} invoke [2]"),
+            "Program data: AQID".to_string(),
+            format!("Program {builtin} invoke [3]"),
+            format!("Program {builtin} success"),
+            format!("Program return: {inner} KgAAAA=="),
+            format!("Program {inner} consumed 1000 of 190000 compute units"),
+            format!("Program {inner} success"),
+            "Program log: done".to_string(),
+            format!("Program {outer} consumed 5000 of 200000 compute units"),
+            format!("Program {outer} success"),
+            format!("Program {inner} invoke [1]"),
+            "Program log: Error: not enough funds".to_string(),
+            format!("Program {inner} failed: custom program error: 0x1"),
+        ]
+        .into();
+        let tree = Invocation::parse_logs(&logs);
+
+        assert_eq!(tree.len(), 2);
+        let outer_ix = &tree[0];
+        assert_eq!(outer_ix.compute_units, Some(5000));
+        assert_eq!(outer_ix.logs, vec!["Instruction: Outer", "done"]);
+        assert_eq!(outer_ix.invocations.len(), 1);
+        let cpi = &outer_ix.invocations[0];
+        assert_eq!((cpi.program_id, cpi.stack_height), (inner, 2));
+        assert_eq!(cpi.data, vec!["AQID"]);
+        assert_eq!(cpi.return_data.as_deref(), Some("KgAAAA=="));
+        assert_eq!(cpi.invocations[0].compute_units, None);
+        assert_eq!(cpi.invocations[0].result, InvocationResult::Success);
+        assert_eq!(
+            outer_ix.iter().map(|ix| ix.program_id).collect::<Vec<_>>(),
+            vec![outer, inner, builtin]
+        );
+        assert_eq!(
+            tree[1].result,
+            InvocationResult::Failed("custom program error: 0x1".to_string())
+        );
+        assert_eq!(
+            logs_by_program(&tree)[&inner],
+            vec!["Error: not enough funds"]
+        );
+    }
+}
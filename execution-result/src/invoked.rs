@@ -0,0 +1,86 @@
+//! Instructions invoked through CPI during a processed message,
+//! see [crate::ProcessedMessage::invoked_instructions].
+use solana_sdk::instruction::Instruction;
+
+/// An instruction invoked by a program during a processed message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvokedInstruction {
+    /// The index of the top-level instruction that led to the invocation.
+    pub instruction_index: u8,
+    /// 2 for an invocation made by a top-level instruction, 3 for one made by
+    /// an invoked program, and so on.
+    pub stack_height: u8,
+    /// Account privileges are as given in the message, which can be broader
+    /// than the ones passed to the invoked program.
+    pub instruction: Instruction,
+}
+
+/// Anchor events emitted by a processed message, either logged with `emit!`
+/// or invoked with `emit_cpi!`. Events of other types are skipped.
+/// Logged events come first.
+#[cfg(feature = "anchor")]
+pub fn anchor_events<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+    logs: &[String],
+    invoked: &[InvokedInstruction],
+) -> Vec<T> {
+    use anchor_lang::event::EVENT_IX_TAG_LE;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use solana_devtools_errors::program_log::ProgramLog;
+
+    let decode = |data: &[u8]| -> Option<T> {
+        let event = data.strip_prefix(T::discriminator().as_slice())?;
+        T::deserialize(&mut &event[..]).ok()
+    };
+    let logged = logs
+        .iter()
+        .filter_map(|log| match ProgramLog::parse(log) {
+            ProgramLog::Data(data) => Some(data),
+            _ => None,
+        })
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter_map(|data| decode(&data));
+    let invoked = invoked.iter().filter_map(|invoked| {
+        decode(
+            invoked
+                .instruction
+                .data
+                .strip_prefix(EVENT_IX_TAG_LE.as_slice())?,
+        )
+    });
+    logged.chain(invoked).collect()
+}
+
+#[cfg(all(test, feature = "anchor"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchor_events_are_parsed_from_logs_and_invocations() {
+        use anchor_lang::prelude::*;
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        #[event]
+        #[derive(Debug, PartialEq)]
+        struct Deposited {
+            amount: u64,
+        }
+
+        let event_data = |amount| anchor_lang::Event::data(&Deposited { amount });
+        let logs = vec![
+            "Program log: Instruction: Deposit".to_string(),
+            format!("Program data: {}", STANDARD.encode(event_data(1))),
+            format!("Program data: {}", STANDARD.encode([0; 16])),
+        ];
+        let mut data = anchor_lang::event::EVENT_IX_TAG_LE.to_vec();
+        data.extend(event_data(2));
+        let invoked = vec![InvokedInstruction {
+            instruction_index: 0,
+            stack_height: 2,
+            instruction: Instruction::new_with_bytes(Pubkey::new_unique(), &data, vec![]),
+        }];
+        assert_eq!(
+            anchor_events::<Deposited>(&logs, &invoked),
+            vec![Deposited { amount: 1 }, Deposited { amount: 2 }]
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! The result of executing a message, shared by every backend in `solana-devtools-simulator`.
+pub mod invoke_tree;
+pub mod invoked;
+pub mod profile;
+pub mod token_balances;
+
+pub use invoke_tree::Invocation;
+pub use invoked::InvokedInstruction;
+pub use profile::ProfileReport;
+pub use token_balances::TokenBalance;
+
+use solana_devtools_errors::resolver::{ErrorReport, ErrorResolver};
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    instruction::InstructionError,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::TransactionError,
+};
+use std::collections::HashMap;
+
+/// The result of a simulated transaction execution.
+#[derive(Debug, Clone)]
+pub struct ProcessedMessage {
+    pub accounts: HashMap<Pubkey, AccountSharedData>,
+    /// The message's accounts before execution, leaving out any that did not exist.
+    pub pre_accounts: HashMap<Pubkey, AccountSharedData>,
+    pub compute_units: u64,
+    pub logs: Vec<String>,
+    /// If the transaction successfully loads but fails during execution,
+    /// this will be a non-`None` value.
+    pub execution_error: Option<TransactionError>,
+    pub slot: u64,
+    /// A synthetic signature if the message was processed unsigned.
+    pub signature: Signature,
+    /// Instructions invoked through CPI, in execution order.
+    pub invoked_instructions: Vec<InvokedInstruction>,
+    /// Compute units consumed by each top-level instruction.
+    pub profile: ProfileReport,
+    /// Balances of the message's SPL token accounts before execution.
+    pub pre_token_balances: HashMap<Pubkey, TokenBalance>,
+    /// Balances of the message's SPL token accounts after execution,
+    /// leaving out any that were closed.
+    pub post_token_balances: HashMap<Pubkey, TokenBalance>,
+}
+
+impl ProcessedMessage {
+    pub fn success(&self) -> bool {
+        self.execution_error.is_none()
+    }
+
+    /// The change in a token account's balance, treating an account that
+    /// did not exist or was closed as holding zero tokens.
+    pub fn token_balance_change(&self, token_account: &Pubkey) -> i128 {
+        let amount = |balances: &HashMap<Pubkey, TokenBalance>| {
+            balances
+                .get(token_account)
+                .map_or(0, |balance| balance.amount as i128)
+        };
+        amount(&self.post_token_balances) - amount(&self.pre_token_balances)
+    }
+
+    /// Instructions invoked through CPI by the top-level instruction at `instruction_index`.
+    pub fn invoked_by(&self, instruction_index: u8) -> impl Iterator<Item = &InvokedInstruction> {
+        self.invoked_instructions
+            .iter()
+            .filter(move |invoked| invoked.instruction_index == instruction_index)
+    }
+
+    /// Anchor events of type `T`, whether logged with `emit!` or invoked with `emit_cpi!`.
+    /// Logged events come first.
+    #[cfg(feature = "anchor")]
+    pub fn anchor_events<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(&self) -> Vec<T> {
+        invoked::anchor_events(&self.logs, &self.invoked_instructions)
+    }
+
+    pub fn check_error_code<T: Into<u32>>(
+        &self,
+        instruction_index: u8,
+        error_code: T,
+    ) -> Result<(), &Option<TransactionError>> {
+        if let Some(TransactionError::InstructionError(idx, err)) = &self.execution_error {
+            if *idx != instruction_index {
+                return Err(&self.execution_error);
+            }
+            if let InstructionError::Custom(code) = err {
+                if *code != error_code.into() {
+                    return Err(&self.execution_error);
+                }
+            } else {
+                return Err(&self.execution_error);
+            }
+            Ok(())
+        } else {
+            Err(&self.execution_error)
+        }
+    }
+
+    /// Describe the execution error, if any, naming the custom program error
+    /// and the instruction that returned it.
+    pub fn error_report(&self, resolver: &ErrorResolver) -> Option<ErrorReport> {
+        let error = self.execution_error.as_ref()?;
+        Some(resolver.report(error, &self.logs))
+    }
+
+    /// The message's logs parsed into its top-level program invocations.
+    pub fn invoke_tree(&self) -> Vec<Invocation> {
+        Invocation::parse_logs(&self.logs)
+    }
+
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<&AccountSharedData> {
+        self.accounts.get(pubkey)
+    }
+
+    #[cfg(feature = "anchor")]
+    pub fn get_anchor_account<T: anchor_lang::AccountDeserialize>(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Option<anchor_lang::Result<T>> {
+        self.accounts.get(pubkey).map(|act| {
+            let mut data = act.data();
+            T::try_deserialize(&mut data)
+        })
+    }
+
+    /// Whether the message created, closed or modified the account.
+    /// Accounts outside of the message are never changed.
+    pub fn account_changed(&self, pubkey: &Pubkey) -> bool {
+        let exists = |account: &&AccountSharedData| account.lamports() > 0;
+        let pre = self.pre_accounts.get(pubkey).filter(exists);
+        let post = self.accounts.get(pubkey).filter(exists);
+        pre != post
+    }
+
+    /// Panics with the logs if execution failed.
+    #[track_caller]
+    pub fn assert_success(&self) {
+        if let Some(error) = &self.execution_error {
+            panic!("message failed with {error}, logs: {:#?}", self.logs);
+        }
+    }
+
+    /// Panics with the logs unless a log line contains `needle`.
+    #[track_caller]
+    pub fn assert_log_contains(&self, needle: &str) {
+        if !self.logs.iter().any(|log| log.contains(needle)) {
+            panic!("no log contains {needle:?}, logs: {:#?}", self.logs);
+        }
+    }
+
+    /// Panics unless the message created, closed or modified the account.
+    #[track_caller]
+    pub fn assert_account_changed(&self, pubkey: &Pubkey) {
+        if !self.account_changed(pubkey) {
+            panic!("account {pubkey} did not change");
+        }
+    }
+
+    /// Panics if the message created, closed or modified the account.
+    #[track_caller]
+    pub fn assert_account_unchanged(&self, pubkey: &Pubkey) {
+        if self.account_changed(pubkey) {
+            panic!(
+                "account {pubkey} changed from {:?} to {:?}",
+                self.pre_accounts.get(pubkey),
+                self.accounts.get(pubkey)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{account::Account, system_program};
+
+    #[test]
+    fn assertions_inspect_logs_and_accounts() {
+        let (payer, recipient, untouched) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let account =
+            |lamports| AccountSharedData::from(Account::new(lamports, 0, &system_program::ID));
+        let processed = ProcessedMessage {
+            accounts: HashMap::from([
+                (payer, account(900)),
+                (recipient, account(100)),
+                (untouched, account(5)),
+            ]),
+            pre_accounts: HashMap::from([(payer, account(1_000)), (untouched, account(5))]),
+            compute_units: 150,
+            logs: vec![
+                "Program 11111111111111111111111111111111 invoke [1]".to_string(),
+                "Program 11111111111111111111111111111111 success".to_string(),
+            ],
+            execution_error: None,
+            slot: 1,
+            signature: Signature::default(),
+            invoked_instructions: vec![],
+            profile: ProfileReport::default(),
+            pre_token_balances: HashMap::new(),
+            post_token_balances: HashMap::new(),
+        };
+        processed.assert_success();
+        processed.assert_log_contains("success");
+        processed.assert_account_changed(&payer);
+        processed.assert_account_changed(&recipient);
+        processed.assert_account_unchanged(&untouched);
+        processed.assert_account_unchanged(&Pubkey::new_unique());
+
+        let missing_log = std::panic::catch_unwind(|| processed.assert_log_contains("failed"));
+        assert!(missing_log.is_err());
+    }
+}
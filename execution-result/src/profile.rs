@@ -0,0 +1,182 @@
+//! Per-instruction compute unit consumption of a processed message.
+use solana_devtools_errors::program_log::ProgramLog;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Units consumed by one program, excluding the units of the programs it invoked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProgramUnits {
+    pub invocations: u32,
+    pub units: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionProfile {
+    pub program_id: Pubkey,
+    /// Units consumed by the instruction, including any CPIs it made.
+    pub units: u64,
+    /// The instruction's program and every program it invoked, directly or not.
+    pub programs: BTreeMap<Pubkey, ProgramUnits>,
+}
+
+/// Compute units consumed by each top-level instruction of a processed message,
+/// broken down by program. Parsed from the `invoke` and `consumed` logs of the message.
+/// Builtin programs do not log their consumption, so each of their invocations is
+/// attributed the units given by the backend, if any. Otherwise they count towards their caller.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileReport {
+    /// Units consumed by the whole message.
+    pub total_units: u64,
+    /// Top-level instructions in execution order, up to the one that failed, if any.
+    pub instructions: Vec<InstructionProfile>,
+}
+
+struct Frame {
+    program_id: Pubkey,
+    consumed: Option<u64>,
+    invoked_units: u64,
+}
+
+impl ProfileReport {
+    /// `builtin_units` are the units consumed by each invocation of a builtin program.
+    pub fn from_logs(
+        logs: &[String],
+        builtin_units: &HashMap<Pubkey, u64>,
+        total_units: u64,
+    ) -> Self {
+        let mut instructions: Vec<InstructionProfile> = vec![];
+        let mut stack: Vec<Frame> = vec![];
+        for log in logs {
+            match ProgramLog::parse(log) {
+                ProgramLog::Invoke {
+                    program_id,
+                    stack_height,
+                } => {
+                    if stack_height == 1 {
+                        stack.clear();
+                        instructions.push(InstructionProfile {
+                            program_id,
+                            units: 0,
+                            programs: BTreeMap::new(),
+                        });
+                    }
+                    stack.push(Frame {
+                        program_id,
+                        consumed: None,
+                        invoked_units: 0,
+                    });
+                }
+                ProgramLog::Consumed { program_id, units } => {
+                    if let Some(frame) = stack.last_mut() {
+                        if frame.program_id == program_id {
+                            frame.consumed = Some(units);
+                        }
+                    }
+                }
+                ProgramLog::Success(_) | ProgramLog::Failed { .. } => {
+                    let (Some(frame), Some(instruction)) = (stack.pop(), instructions.last_mut())
+                    else {
+                        continue;
+                    };
+                    let units = frame
+                        .consumed
+                        .or_else(|| builtin_units.get(&frame.program_id).copied())
+                        .unwrap_or(0);
+                    let program = instruction.programs.entry(frame.program_id).or_default();
+                    program.invocations += 1;
+                    program.units += units.saturating_sub(frame.invoked_units);
+                    match stack.last_mut() {
+                        Some(caller) => caller.invoked_units += units,
+                        None => instruction.units = units,
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            total_units,
+            instructions,
+        }
+    }
+
+    /// Units consumed by each program across all instructions.
+    pub fn by_program(&self) -> BTreeMap<Pubkey, ProgramUnits> {
+        let mut programs: BTreeMap<Pubkey, ProgramUnits> = BTreeMap::new();
+        for (program_id, units) in self
+            .instructions
+            .iter()
+            .flat_map(|instruction| &instruction.programs)
+        {
+            let total = programs.entry(*program_id).or_default();
+            total.invocations += units.invocations;
+            total.units += units.units;
+        }
+        programs
+    }
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "total: {} units", self.total_units)?;
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            writeln!(
+                f,
+                "#{} {}: {} units",
+                index, instruction.program_id, instruction.units
+            )?;
+            for (program_id, units) in &instruction.programs {
+                writeln!(
+                    f,
+                    "    {}: {} units, {} invocation(s)",
+                    program_id, units.units, units.invocations
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_invocations_are_attributed_to_their_program() {
+        let (outer, inner, builtin) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let logs: Vec<String> = [
+            format!("Program {outer} invoke [1]"),
+            "Program log: Instruction: Outer".to_string(),
+            format!("Program {inner} invoke [2]"),
+            format!("Program {builtin} invoke [3]"),
+            format!("Program {builtin} success"),
+            format!("Program {inner} consumed 1000 of 190000 compute units"),
+            format!("Program {inner} success"),
+            format!("Program {outer} consumed 5000 of 200000 compute units"),
+            format!("Program {outer} success"),
+            format!("Program {builtin} invoke [1]"),
+            format!("Program {builtin} success"),
+        ]
+        .into();
+        let report = ProfileReport::from_logs(&logs, &HashMap::from([(builtin, 150)]), 5150);
+
+        assert_eq!(report.instructions.len(), 2);
+        let outer_ix = &report.instructions[0];
+        assert_eq!(outer_ix.units, 5000);
+        assert_eq!(outer_ix.programs[&outer].units, 4000);
+        assert_eq!(outer_ix.programs[&inner].units, 850);
+        assert_eq!(outer_ix.programs[&builtin].units, 150);
+        assert_eq!(report.instructions[1].units, 150);
+        assert_eq!(
+            report.by_program()[&builtin],
+            ProgramUnits {
+                invocations: 2,
+                units: 300
+            }
+        );
+    }
+}
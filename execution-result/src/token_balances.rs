@@ -0,0 +1,63 @@
+//! SPL token balances of the accounts in a processed message, as in the
+//! `preTokenBalances` and `postTokenBalances` of RPC transaction metadata.
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    program_pack::Pack,
+    pubkey,
+    pubkey::Pubkey,
+};
+use spl_token::state::{Account as TokenAccount, AccountState};
+use std::collections::HashMap;
+
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// The account type byte that follows the base account layout in token-2022 accounts
+/// with extensions.
+const TOKEN_2022_ACCOUNT_TYPE: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenBalance {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// Either spl-token or token-2022.
+    pub program_id: Pubkey,
+}
+
+impl TokenBalance {
+    /// Decode an initialized spl-token or token-2022 account, or return `None`
+    /// if the account is anything else.
+    pub fn from_account(account: &impl ReadableAccount) -> Option<Self> {
+        let program_id = *account.owner();
+        let data = account.data();
+        let base = data.get(..TokenAccount::LEN)?;
+        let is_token_account = if program_id == spl_token::ID {
+            data.len() == TokenAccount::LEN
+        } else if program_id == TOKEN_2022_PROGRAM_ID {
+            data.len() == TokenAccount::LEN
+                || data.get(TokenAccount::LEN) == Some(&TOKEN_2022_ACCOUNT_TYPE)
+        } else {
+            false
+        };
+        if !is_token_account {
+            return None;
+        }
+        let token_account = TokenAccount::unpack_from_slice(base).ok()?;
+        (token_account.state != AccountState::Uninitialized).then_some(Self {
+            mint: token_account.mint,
+            owner: token_account.owner,
+            amount: token_account.amount,
+            program_id,
+        })
+    }
+}
+
+/// Balances of the token accounts among `accounts`.
+pub fn token_balances<'a>(
+    accounts: impl IntoIterator<Item = (&'a Pubkey, &'a AccountSharedData)>,
+) -> HashMap<Pubkey, TokenBalance> {
+    accounts
+        .into_iter()
+        .filter_map(|(pubkey, account)| Some((*pubkey, TokenBalance::from_account(account)?)))
+        .collect()
+}
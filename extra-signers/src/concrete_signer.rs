@@ -1,5 +1,5 @@
 use base64::{engine::general_purpose::STANDARD, Engine};
-use bip39::{Language, Mnemonic, Seed};
+use bip39::Seed;
 use solana_program::pubkey::Pubkey;
 #[cfg(feature = "remote-wallet")]
 use solana_remote_wallet::{
@@ -30,6 +30,10 @@ const STDIN_URI_PREFIX: &str = "stdin";
 // Keypair variant -- filepath to a keypair file
 const FILE_URI_PREFIX: &str = "file";
 
+// Keypair variant -- interactive, filepath to a passphrase-encrypted keypair file
+#[cfg(feature = "encrypted")]
+const ENCRYPTED_URI_PREFIX: &str = "encrypted";
+
 // RemoteKeypair variant -- interactive, for communicating with hardware wallets
 const USB_URI_PREFIX: &str = "usb";
 
@@ -40,7 +44,7 @@ const PRESIGN_URI_PREFIX: &str = "presigner";
 /// the `solana-cli` crate, but returns a concrete type instead
 /// of a trait object.
 pub enum ConcreteSigner {
-    /// `prompt://` and `file://` and `stdin://`
+    /// `prompt://` and `file://` and `stdin://` and `encrypted://`
     Keypair(Keypair, Option<DerivationPath>),
     /// `usb://`
     #[cfg(feature = "remote-wallet")]
@@ -98,6 +102,13 @@ impl ConcreteSigner {
         ))
     }
 
+    /// Decrypt a keypair file written by [crate::encrypted_keypair::EncryptedKeypair::write].
+    #[cfg(feature = "encrypted")]
+    pub fn from_encrypted_file(p: & impl AsRef<Path>, passphrase: &str) -> Result<Self, SignerError> {
+        let encrypted = crate::encrypted_keypair::EncryptedKeypair::read(p)?;
+        Ok(ConcreteSigner::Keypair(encrypted.decrypt(passphrase)?, None))
+    }
+
     pub fn from_file_with_derivation_path(p: & impl AsRef<Path>, derivation_path: DerivationPath) -> Result<Self, SignerError> {
         let file = File::open(p.as_ref())
             .map_err(|e|
@@ -140,30 +151,7 @@ impl ConcreteSigner {
         passphrase: &str,
         legacy: bool,
     ) -> Result<Self, SignerError> {
-        let sanitized = seed_phrase
-            .split_whitespace()
-            .collect::<Vec<&str>>()
-            .join(" ");
-        let mnemonic = || {
-            for language in &[
-                Language::English,
-                Language::ChineseSimplified,
-                Language::ChineseTraditional,
-                Language::Japanese,
-                Language::Spanish,
-                Language::Korean,
-                Language::French,
-                Language::Italian,
-            ] {
-                if let Ok(mnemonic) = Mnemonic::from_phrase(&sanitized, *language) {
-                    return Ok(mnemonic);
-                }
-            }
-            Err(SignerError::Custom(format!(
-                "Can't get mnemonic from seed phrase"
-            )))
-        };
-        let mnemonic = mnemonic()?;
+        let mnemonic = crate::seed_phrase::mnemonic(seed_phrase)?;
         let seed = Seed::new(&mnemonic, &passphrase);
         Self::from_seed_and_derivation_path(
             seed.as_bytes(),
@@ -212,6 +200,15 @@ impl<'a> TryFrom<URIReference<'a>> for ConcreteSigner {
                     }
                 }
                 FILE_URI_PREFIX => Self::from_file(&uri.path().to_string()),
+                #[cfg(feature = "encrypted")]
+                ENCRYPTED_URI_PREFIX => {
+                    let path = uri.path().to_string();
+                    let passphrase = prompt_password(format!("passphrase for {}: ", path))
+                        .map_err(|e|
+                            SignerError::Custom(format!("Unable to read from stdin: {}", e))
+                        )?;
+                    Self::from_encrypted_file(&path, &passphrase)
+                }
                 #[cfg(feature = "remote-wallet")]
                 USB_URI_PREFIX => {
                     let locator = Locator::new_from_uri(&uri).map_err(|e| {
@@ -0,0 +1,261 @@
+//! Keypair files encrypted with a passphrase, so that secrets are not stored in plaintext.
+//!
+//! The key is derived from the passphrase with Argon2id, and the keypair bytes
+//! are encrypted with AES-256-GCM-SIV. The pubkey is stored in the clear,
+//! and authenticated with the ciphertext.
+use aes_gcm_siv::aead::{Aead, NewAead, Payload};
+use aes_gcm_siv::{Aes256GcmSiv, Key, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::bs58;
+use solana_sdk::signature::{read_keypair_file, Keypair, SignerError};
+use solana_sdk::signer::Signer;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Upper bounds on the parameters read from a file, so that a crafted file
+/// can't make decryption exhaust memory or run for hours.
+const MAX_M_COST: u32 = 4 * 1024 * 1024;
+const MAX_T_COST: u32 = 64;
+const MAX_P_COST: u32 = 16;
+
+/// Argon2id cost parameters, stored with the ciphertext so they can be raised later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KdfParams {
+    /// Memory cost, in KiB.
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl KdfParams {
+    fn check_bounds(&self) -> Result<(), SignerError> {
+        if self.m_cost > MAX_M_COST || self.t_cost > MAX_T_COST || self.p_cost > MAX_P_COST {
+            return Err(SignerError::Custom(format!(
+                "key derivation parameters exceed the maximum of {} KiB, {} passes and {} lanes",
+                MAX_M_COST, MAX_T_COST, MAX_P_COST
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: 64 * 1024,
+            t_cost: 3,
+            p_cost: 1,
+        }
+    }
+}
+
+/// The contents of an encrypted keypair file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedKeypair {
+    pub version: u8,
+    pub pubkey: String,
+    pub kdf: KdfParams,
+    /// Base58-encoded, as are the nonce and ciphertext.
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl EncryptedKeypair {
+    pub fn encrypt(keypair: &Keypair, passphrase: &str) -> Result<Self, SignerError> {
+        Self::encrypt_with_params(keypair, passphrase, KdfParams::default())
+    }
+
+    pub fn encrypt_with_params(
+        keypair: &Keypair,
+        passphrase: &str,
+        kdf: KdfParams,
+    ) -> Result<Self, SignerError> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let pubkey = keypair.pubkey();
+        let cipher = cipher(passphrase, &salt, kdf)?;
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: Zeroizing::new(keypair.to_bytes()).as_ref(),
+                    aad: pubkey.as_ref(),
+                },
+            )
+            .map_err(|_| SignerError::Custom("failed to encrypt keypair".to_string()))?;
+        Ok(Self {
+            version: VERSION,
+            pubkey: pubkey.to_string(),
+            kdf,
+            salt: bs58::encode(salt).into_string(),
+            nonce: bs58::encode(nonce).into_string(),
+            ciphertext: bs58::encode(ciphertext).into_string(),
+        })
+    }
+
+    pub fn pubkey(&self) -> Result<Pubkey, SignerError> {
+        Pubkey::from_str(&self.pubkey)
+            .map_err(|e| SignerError::Custom(format!("invalid pubkey in encrypted keypair: {}", e)))
+    }
+
+    /// Fails if the passphrase is wrong, the file was tampered with,
+    /// or its key derivation parameters are unreasonably expensive.
+    pub fn decrypt(&self, passphrase: &str) -> Result<Keypair, SignerError> {
+        if self.version != VERSION {
+            return Err(SignerError::Custom(format!(
+                "unsupported encrypted keypair version: {}",
+                self.version
+            )));
+        }
+        self.kdf.check_bounds()?;
+        let pubkey = self.pubkey()?;
+        let salt = decode("salt", &self.salt)?;
+        let nonce = decode("nonce", &self.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(SignerError::Custom(
+                "invalid nonce in encrypted keypair".to_string(),
+            ));
+        }
+        let ciphertext = decode("ciphertext", &self.ciphertext)?;
+        let bytes = cipher(passphrase, &salt, self.kdf)?
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: pubkey.as_ref(),
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| {
+                SignerError::Custom("failed to decrypt keypair, wrong passphrase?".to_string())
+            })?;
+        let keypair = Keypair::from_bytes(&bytes)
+            .map_err(|e| SignerError::Custom(format!("invalid decrypted keypair: {}", e)))?;
+        if keypair.pubkey() != pubkey {
+            return Err(SignerError::Custom(
+                "decrypted keypair does not match its pubkey".to_string(),
+            ));
+        }
+        Ok(keypair)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, SignerError> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            SignerError::Custom(format!("could not find or open keypair file: {}", e))
+        })?;
+        serde_json::from_str(&contents).map_err(|e| {
+            SignerError::Custom(format!("could not parse encrypted keypair file: {}", e))
+        })
+    }
+
+    /// On Unix, a new file is only readable and writable by its owner.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SignerError> {
+        let contents =
+            serde_json::to_string_pretty(self).map_err(|e| SignerError::Custom(e.to_string()))?;
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|e| SignerError::Custom(format!("could not write keypair file: {}", e)))
+    }
+}
+
+/// Encrypt a plaintext keypair file. The plaintext file is left in place.
+pub fn encrypt_keypair_file(
+    keypair_file: impl AsRef<Path>,
+    outfile: impl AsRef<Path>,
+    passphrase: &str,
+) -> Result<Pubkey, SignerError> {
+    let keypair = read_keypair_file(keypair_file)
+        .map_err(|e| SignerError::Custom(format!("could not find or parse keypair file: {}", e)))?;
+    EncryptedKeypair::encrypt(&keypair, passphrase)?.write(outfile)?;
+    Ok(keypair.pubkey())
+}
+
+fn cipher(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<Aes256GcmSiv, SignerError> {
+    let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32))
+        .map_err(|e| SignerError::Custom(format!("invalid key derivation parameters: {}", e)))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| SignerError::Custom(format!("failed to derive key: {}", e)))?;
+    Ok(Aes256GcmSiv::new(Key::from_slice(key.as_ref())))
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, SignerError> {
+    bs58::decode(value)
+        .into_vec()
+        .map_err(|_| SignerError::Custom(format!("invalid {} in encrypted keypair", field)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keypairs_decrypt_only_with_their_passphrase() {
+        let keypair = Keypair::new();
+        // Cheap parameters, to keep the test fast.
+        let kdf = KdfParams {
+            m_cost: 1024,
+            t_cost: 1,
+            p_cost: 1,
+        };
+        let encrypted = EncryptedKeypair::encrypt_with_params(&keypair, "hunter2", kdf).unwrap();
+        let json = serde_json::to_string(&encrypted).unwrap();
+        assert!(!json.contains(&bs58::encode(keypair.to_bytes()).into_string()));
+
+        let encrypted: EncryptedKeypair = serde_json::from_str(&json).unwrap();
+        assert_eq!(encrypted.pubkey().unwrap(), keypair.pubkey());
+        assert_eq!(
+            encrypted.decrypt("hunter2").unwrap().to_bytes(),
+            keypair.to_bytes()
+        );
+        assert!(encrypted.decrypt("hunter3").is_err());
+
+        let mut swapped = encrypted.clone();
+        swapped.pubkey = Pubkey::new_unique().to_string();
+        assert!(swapped.decrypt("hunter2").is_err());
+
+        let mut expensive = encrypted.clone();
+        expensive.kdf.m_cost = u32::MAX;
+        let err = expensive.decrypt("hunter2").unwrap_err().to_string();
+        assert!(err.contains("exceed the maximum"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let kdf = KdfParams {
+            m_cost: 1024,
+            t_cost: 1,
+            p_cost: 1,
+        };
+        let encrypted = EncryptedKeypair::encrypt_with_params(&Keypair::new(), "pw", kdf).unwrap();
+        let path = std::env::temp_dir().join(format!("{}.json", Pubkey::new_unique()));
+        encrypted.write(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(EncryptedKeypair::read(&path).unwrap(), encrypted);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
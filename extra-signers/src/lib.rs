@@ -1,5 +1,12 @@
 pub mod concrete_signer;
+#[cfg(feature = "encrypted")]
+pub mod encrypted_keypair;
+#[cfg(feature = "offline")]
+pub mod offline;
+pub mod seed_phrase;
+pub mod signer_registry;
 pub mod threadsafe_signer;
 
 pub use concrete_signer::ConcreteSigner;
+pub use signer_registry::SignerRegistry;
 pub use threadsafe_signer::ThreadsafeSigner;
@@ -0,0 +1,238 @@
+//! Sign a message on an air-gapped machine, and combine the signatures elsewhere.
+//!
+//! 1. Write the unsigned message to a file with [OfflineMessage::write].
+//! 2. On each signing machine, [OfflineMessage::read] the file, check it,
+//!    and write the output of [OfflineMessage::sign] with [OfflineSignature::write].
+//! 3. Back online, [OfflineMessage::combine] the signature files into a transaction.
+//!
+//! Signing only needs the message bytes, so any [Signer] works, including hardware wallets.
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use solana_program::hash::Hash;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::signature::{Signature, Signer};
+use solana_sdk::transaction::VersionedTransaction;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// An unsigned message, and the signers it requires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OfflineMessage {
+    pub message: VersionedMessage,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OfflineMessageFile {
+    /// Base64-encoded serialized message.
+    message: String,
+    /// Listed for review, the message is the source of truth.
+    required_signers: Vec<String>,
+    recent_blockhash: String,
+}
+
+impl OfflineMessage {
+    pub fn new(message: VersionedMessage) -> Self {
+        Self { message }
+    }
+
+    /// The message's required signers, in message order.
+    pub fn required_signers(&self) -> &[Pubkey] {
+        let num_signers = self.message.header().num_required_signatures as usize;
+        let keys = self.message.static_account_keys();
+        &keys[..num_signers.min(keys.len())]
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let file = OfflineMessageFile {
+            message: STANDARD.encode(self.message.serialize()),
+            required_signers: self
+                .required_signers()
+                .iter()
+                .map(|pubkey| pubkey.to_string())
+                .collect(),
+            recent_blockhash: self.message.recent_blockhash().to_string(),
+        };
+        Ok(serde_json::to_string_pretty(&file)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: OfflineMessageFile = serde_json::from_str(json)
+            .map_err(|e| anyhow!("invalid offline message file: {}", e))?;
+        let bytes = STANDARD
+            .decode(&file.message)
+            .map_err(|e| anyhow!("invalid base64 message: {}", e))?;
+        let message: VersionedMessage = bincode::deserialize(&bytes)
+            .map_err(|e| anyhow!("could not deserialize message: {}", e))?;
+        message
+            .sanitize()
+            .map_err(|e| anyhow!("invalid message: {}", e))?;
+        Ok(Self { message })
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(fs::write(path, self.to_json()?)?)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Fails if the signer is not one of the message's required signers.
+    pub fn sign(&self, signer: &dyn Signer) -> Result<OfflineSignature> {
+        let pubkey = signer.try_pubkey()?;
+        if !self.required_signers().contains(&pubkey) {
+            return Err(anyhow!(
+                "{} is not a required signer of the message",
+                pubkey
+            ));
+        }
+        Ok(OfflineSignature {
+            signer: pubkey,
+            signature: signer.try_sign_message(&self.message.serialize())?,
+            message_hash: self.message.hash(),
+        })
+    }
+
+    /// Build the signed transaction, checking that every required signer signed this
+    /// message, and that each signature is valid. Duplicate signatures are ignored.
+    pub fn combine(
+        &self,
+        signatures: impl IntoIterator<Item = OfflineSignature>,
+    ) -> Result<VersionedTransaction> {
+        let message_data = self.message.serialize();
+        let message_hash = self.message.hash();
+        let required_signers = self.required_signers();
+        let mut combined = vec![None; required_signers.len()];
+        for signature in signatures {
+            if signature.message_hash != message_hash {
+                return Err(anyhow!(
+                    "the signature of {} is for a different message",
+                    signature.signer
+                ));
+            }
+            let index = required_signers
+                .iter()
+                .position(|pubkey| *pubkey == signature.signer)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "{} is not a required signer of the message",
+                        signature.signer
+                    )
+                })?;
+            if !signature
+                .signature
+                .verify(signature.signer.as_ref(), &message_data)
+            {
+                return Err(anyhow!("invalid signature from {}", signature.signer));
+            }
+            combined[index] = Some(signature.signature);
+        }
+        let missing: Vec<String> = required_signers
+            .iter()
+            .zip(&combined)
+            .filter(|(_, signature)| signature.is_none())
+            .map(|(pubkey, _)| pubkey.to_string())
+            .collect();
+        if !missing.is_empty() {
+            return Err(anyhow!("missing signatures from {}", missing.join(", ")));
+        }
+        Ok(VersionedTransaction {
+            signatures: combined.into_iter().flatten().collect(),
+            message: self.message.clone(),
+        })
+    }
+}
+
+/// One signer's signature of an [OfflineMessage].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfflineSignature {
+    pub signer: Pubkey,
+    pub signature: Signature,
+    /// Identifies the signed message, so signatures of another message are rejected.
+    pub message_hash: Hash,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OfflineSignatureFile {
+    signer: String,
+    signature: String,
+    message_hash: String,
+}
+
+impl OfflineSignature {
+    pub fn to_json(&self) -> Result<String> {
+        let file = OfflineSignatureFile {
+            signer: self.signer.to_string(),
+            signature: self.signature.to_string(),
+            message_hash: self.message_hash.to_string(),
+        };
+        Ok(serde_json::to_string_pretty(&file)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: OfflineSignatureFile = serde_json::from_str(json)
+            .map_err(|e| anyhow!("invalid offline signature file: {}", e))?;
+        Ok(Self {
+            signer: Pubkey::from_str(&file.signer)
+                .map_err(|_| anyhow!("invalid signer: {}", file.signer))?,
+            signature: Signature::from_str(&file.signature)
+                .map_err(|_| anyhow!("invalid signature: {}", file.signature))?,
+            message_hash: Hash::from_str(&file.message_hash)
+                .map_err(|_| anyhow!("invalid message hash: {}", file.message_hash))?,
+        })
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(fs::write(path, self.to_json()?)?)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::message::Message;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::system_instruction;
+
+    #[test]
+    fn signatures_are_combined_into_a_transaction() {
+        let (payer, sender, outsider) = (Keypair::new(), Keypair::new(), Keypair::new());
+        let message = VersionedMessage::Legacy(Message::new(
+            &[system_instruction::transfer(
+                &sender.pubkey(),
+                &payer.pubkey(),
+                1,
+            )],
+            Some(&payer.pubkey()),
+        ));
+        let exported = OfflineMessage::new(message).to_json().unwrap();
+
+        // Each signer reads the message, and returns a signature file.
+        let offline = OfflineMessage::from_json(&exported).unwrap();
+        assert!(offline.sign(&outsider).is_err());
+        let signatures: Vec<OfflineSignature> = [&payer, &sender]
+            .into_iter()
+            .map(|signer| {
+                let json = offline.sign(signer).unwrap().to_json().unwrap();
+                OfflineSignature::from_json(&json).unwrap()
+            })
+            .collect();
+
+        assert!(offline.combine(signatures[..1].to_vec()).is_err());
+        let mut forged = signatures[1].clone();
+        forged.signature = Signature::default();
+        assert!(offline.combine([signatures[0].clone(), forged]).is_err());
+
+        let transaction = offline.combine(signatures).unwrap();
+        assert!(transaction.verify_with_results().iter().all(|ok| *ok));
+    }
+}
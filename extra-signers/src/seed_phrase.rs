@@ -0,0 +1,160 @@
+//! Derive many keypairs from one seed phrase, and discover which of them are in use.
+use bip39::{Language, Mnemonic, Seed};
+#[cfg(feature = "rpc-client")]
+use solana_client::rpc_client::RpcClient;
+#[cfg(feature = "rpc-client")]
+use solana_devtools_rpc::scan::MAX_MULTIPLE_ACCOUNTS;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::derivation_path::DerivationPath;
+use solana_sdk::signature::{Keypair, SignerError};
+use solana_sdk::signer::{SeedDerivable, Signer};
+use std::ops::Range;
+
+/// The standard Solana derivation path for an account index, `m/44'/501'/<account>'/0'`.
+pub fn derivation_path(account: u32) -> DerivationPath {
+    DerivationPath::new_bip44(Some(account), Some(0))
+}
+
+/// A pubkey derived from a seed phrase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivedAccount {
+    pub account: u32,
+    pub derivation_path: DerivationPath,
+    pub pubkey: Pubkey,
+}
+
+/// A seed phrase, and its passphrase, which derives keypairs along Solana derivation paths.
+pub struct SeedPhrase {
+    seed: Seed,
+}
+
+impl SeedPhrase {
+    pub fn new(seed_phrase: &str, passphrase: &str) -> Result<Self, SignerError> {
+        let mnemonic = mnemonic(seed_phrase)?;
+        Ok(Self {
+            seed: Seed::new(&mnemonic, passphrase),
+        })
+    }
+
+    pub fn keypair_at(&self, derivation_path: DerivationPath) -> Result<Keypair, SignerError> {
+        Keypair::from_seed_and_derivation_path(self.seed.as_bytes(), Some(derivation_path)).map_err(
+            |e| {
+                SignerError::Custom(format!(
+                    "failed to interpet seed phrase or derivation path: {}",
+                    e
+                ))
+            },
+        )
+    }
+
+    pub fn keypair(&self, account: u32) -> Result<Keypair, SignerError> {
+        self.keypair_at(derivation_path(account))
+    }
+
+    /// The accounts at each index in the range, along standard derivation paths.
+    pub fn accounts(&self, accounts: Range<u32>) -> Result<Vec<DerivedAccount>, SignerError> {
+        accounts
+            .map(|account| {
+                Ok(DerivedAccount {
+                    account,
+                    derivation_path: derivation_path(account),
+                    pubkey: self.keypair(account)?.pubkey(),
+                })
+            })
+            .collect()
+    }
+
+    /// Wallet-style account discovery. Scans accounts in order, and stops
+    /// after `gap_limit` consecutive accounts that do not exist on chain.
+    /// Returns the existing accounts and their balances.
+    #[cfg(feature = "rpc-client")]
+    pub fn discover(
+        &self,
+        client: &RpcClient,
+        gap_limit: u32,
+    ) -> anyhow::Result<Vec<(DerivedAccount, u64)>> {
+        let gap_limit = gap_limit.max(1);
+        let mut found = vec![];
+        let mut start = 0;
+        let mut gap = 0;
+        while gap < gap_limit {
+            let batch = self.accounts(start..start.saturating_add(gap_limit))?;
+            let pubkeys: Vec<Pubkey> = batch.iter().map(|account| account.pubkey).collect();
+            let mut accounts = Vec::with_capacity(pubkeys.len());
+            for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+                accounts.extend(client.get_multiple_accounts(chunk)?);
+            }
+            for (derived, account) in batch.into_iter().zip(accounts) {
+                match account {
+                    Some(account) => {
+                        gap = 0;
+                        found.push((derived, account.lamports));
+                    }
+                    None => gap += 1,
+                }
+                if gap >= gap_limit {
+                    break;
+                }
+            }
+            start = start.saturating_add(gap_limit);
+        }
+        Ok(found)
+    }
+}
+
+/// Parse a seed phrase in any of the supported BIP39 languages.
+pub(crate) fn mnemonic(seed_phrase: &str) -> Result<Mnemonic, SignerError> {
+    let sanitized = seed_phrase
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    for language in &[
+        Language::English,
+        Language::ChineseSimplified,
+        Language::ChineseTraditional,
+        Language::Japanese,
+        Language::Spanish,
+        Language::Korean,
+        Language::French,
+        Language::Italian,
+    ] {
+        if let Ok(mnemonic) = Mnemonic::from_phrase(&sanitized, *language) {
+            return Ok(mnemonic);
+        }
+    }
+    Err(SignerError::Custom(
+        "Can't get mnemonic from seed phrase".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConcreteSigner;
+    use bip39::MnemonicType;
+
+    #[test]
+    fn accounts_are_derived_along_standard_paths() {
+        let phrase = Mnemonic::new(MnemonicType::Words12, Language::English)
+            .phrase()
+            .to_string();
+        let seed_phrase = SeedPhrase::new(&phrase, "").unwrap();
+        let accounts = seed_phrase.accounts(0..3).unwrap();
+        assert_eq!(accounts.len(), 3);
+        assert_eq!(
+            accounts[2].derivation_path,
+            DerivationPath::from_absolute_path_str("m/44'/501'/2'/0'").unwrap()
+        );
+        for account in &accounts {
+            let signer = ConcreteSigner::from_seed_phrase_and_derivation_path(
+                &phrase,
+                Some(account.derivation_path.clone()),
+                "",
+                false,
+            )
+            .unwrap();
+            assert_eq!(signer.pubkey(), account.pubkey);
+        }
+        assert_ne!(accounts[0].pubkey, accounts[1].pubkey);
+    }
+}
@@ -0,0 +1,177 @@
+use solana_program::pubkey::Pubkey;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::signature::{Signature, Signer, SignerError};
+use solana_sdk::signer::signers::Signers;
+use solana_sdk::transaction::VersionedTransaction;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// A collection of signers keyed by pubkey, which signs for whichever of them
+/// a message requires. Signers that are [Send] but not [Sync] can be wrapped
+/// in a [crate::ThreadsafeSigner].
+#[derive(Clone, Default)]
+pub struct SignerRegistry {
+    signers: BTreeMap<Pubkey, Arc<dyn Signer + Send + Sync>>,
+}
+
+impl SignerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces any signer already registered for the same pubkey.
+    pub fn insert<T: Signer + Send + Sync + 'static>(&mut self, signer: T) -> Pubkey {
+        let pubkey = signer.pubkey();
+        self.signers.insert(pubkey, Arc::new(signer));
+        pubkey
+    }
+
+    pub fn with_signer<T: Signer + Send + Sync + 'static>(mut self, signer: T) -> Self {
+        self.insert(signer);
+        self
+    }
+
+    pub fn remove(&mut self, pubkey: &Pubkey) -> bool {
+        self.signers.remove(pubkey).is_some()
+    }
+
+    pub fn get(&self, pubkey: &Pubkey) -> Option<&dyn Signer> {
+        self.signers
+            .get(pubkey)
+            .map(|signer| signer.as_ref() as &dyn Signer)
+    }
+
+    pub fn contains(&self, pubkey: &Pubkey) -> bool {
+        self.signers.contains_key(pubkey)
+    }
+
+    pub fn len(&self) -> usize {
+        self.signers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signers.is_empty()
+    }
+
+    /// The message's required signers that are not registered, in message order.
+    pub fn missing_signers(&self, message: &VersionedMessage) -> Vec<Pubkey> {
+        required_signers(message)
+            .iter()
+            .filter(|pubkey| !self.contains(pubkey))
+            .copied()
+            .collect()
+    }
+
+    /// Sign with every required signer, failing if any of them is not registered.
+    pub fn sign(&self, message: VersionedMessage) -> Result<VersionedTransaction, SignerError> {
+        let (transaction, missing) = self.sign_partial(message)?;
+        if !missing.is_empty() {
+            return Err(SignerError::NotEnoughSigners);
+        }
+        Ok(transaction)
+    }
+
+    /// Sign with the registered required signers, leaving a default signature in place of
+    /// each missing one. Also returns the missing signers, in message order.
+    pub fn sign_partial(
+        &self,
+        message: VersionedMessage,
+    ) -> Result<(VersionedTransaction, Vec<Pubkey>), SignerError> {
+        let message_data = message.serialize();
+        let mut missing = vec![];
+        let signatures = required_signers(&message)
+            .iter()
+            .map(|pubkey| match self.get(pubkey) {
+                Some(signer) => signer.try_sign_message(&message_data),
+                None => {
+                    missing.push(*pubkey);
+                    Ok(Signature::default())
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((
+            VersionedTransaction {
+                signatures,
+                message,
+            },
+            missing,
+        ))
+    }
+}
+
+fn required_signers(message: &VersionedMessage) -> &[Pubkey] {
+    let num_signers = message.header().num_required_signatures as usize;
+    let keys = message.static_account_keys();
+    &keys[..num_signers.min(keys.len())]
+}
+
+/// Signs with every registered signer, in pubkey order.
+impl Signers for SignerRegistry {
+    fn pubkeys(&self) -> Vec<Pubkey> {
+        self.signers.keys().copied().collect()
+    }
+
+    fn try_pubkeys(&self) -> Result<Vec<Pubkey>, SignerError> {
+        Ok(self.pubkeys())
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Vec<Signature> {
+        self.signers
+            .values()
+            .map(|signer| signer.sign_message(message))
+            .collect()
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Vec<Signature>, SignerError> {
+        self.signers
+            .values()
+            .map(|signer| signer.try_sign_message(message))
+            .collect()
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.signers.values().any(|signer| signer.is_interactive())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ThreadsafeSigner;
+    use solana_sdk::message::Message;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::system_instruction;
+
+    #[test]
+    fn required_signers_are_resolved_from_the_message() {
+        let (payer, sender, absent) = (Keypair::new(), Keypair::new(), Keypair::new());
+        let message = VersionedMessage::Legacy(Message::new(
+            &[
+                system_instruction::transfer(&sender.pubkey(), &absent.pubkey(), 1),
+                system_instruction::transfer(&absent.pubkey(), &payer.pubkey(), 1),
+            ],
+            Some(&payer.pubkey()),
+        ));
+        let mut registry = SignerRegistry::new()
+            .with_signer(payer)
+            .with_signer(ThreadsafeSigner::new(sender));
+        assert_eq!(registry.missing_signers(&message), vec![absent.pubkey()]);
+        assert!(registry.sign(message.clone()).is_err());
+
+        let (partial, missing) = registry.sign_partial(message.clone()).unwrap();
+        assert_eq!(missing, vec![absent.pubkey()]);
+        let absent_index = partial
+            .message
+            .static_account_keys()
+            .iter()
+            .position(|key| *key == absent.pubkey())
+            .unwrap();
+        for (index, verified) in partial.verify_with_results().into_iter().enumerate() {
+            assert_eq!(verified, index != absent_index);
+        }
+
+        registry.insert(absent);
+        let transaction = registry.sign(message).unwrap();
+        assert!(transaction.verify_with_results().iter().all(|ok| *ok));
+    }
+}
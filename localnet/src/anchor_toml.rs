@@ -0,0 +1,428 @@
+//! Export a [LocalnetConfiguration] as the `[test]` section of an `Anchor.toml`,
+//! so that `anchor test` loads the same accounts and programs, or build one from
+//! an existing Anchor workspace with [LocalnetConfiguration::from_anchor_workspace].
+use crate::error::{LocalnetConfigurationError, Result};
+use crate::localnet_account::{LocalnetAccount, UiAccountWithAddr};
+use crate::LocalnetConfiguration;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Serialize)]
+struct AnchorToml {
+    test: TestSection,
+}
+
+/// The sections of a workspace's `Anchor.toml` read by
+/// [LocalnetConfiguration::from_anchor_workspace].
+#[derive(Debug, Deserialize)]
+struct WorkspaceAnchorToml {
+    /// Program ids by cluster, then by library name.
+    #[serde(default)]
+    programs: HashMap<String, BTreeMap<String, ProgramEntry>>,
+    #[serde(default)]
+    test: TestSection,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ProgramEntry {
+    Address(String),
+    Detailed { address: String },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TestSection {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    genesis: Vec<GenesisEntry>,
+    #[serde(default)]
+    validator: ValidatorSection,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GenesisEntry {
+    address: String,
+    program: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ValidatorSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warp_slot: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slots_per_epoch: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    account: Vec<AccountEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    clone: Vec<CloneEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AccountEntry {
+    address: String,
+    filename: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CloneEntry {
+    address: String,
+}
+
+impl LocalnetConfiguration {
+    /// Load an Anchor workspace the way `anchor test` would against localnet:
+    /// - each program in `[programs.localnet]` from `target/deploy/<name>.so`,
+    ///   along with its IDL account if `target/idl/<name>.json` exists and the `idl` feature
+    ///   is enabled, see [LocalnetConfiguration::with_idl].
+    /// - the `[[test.genesis]]` programs,
+    /// - the `[[test.validator.account]]` JSON files, named after their file names,
+    /// - the `[[test.validator.clone]]` accounts, and the validator `url`.
+    ///
+    /// Paths in `Anchor.toml` are relative to the workspace root, `path`.
+    pub fn from_anchor_workspace<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let root = fs::canonicalize(path.as_ref()).map_err(|e| {
+            LocalnetConfigurationError::FileReadWriteError(path.as_ref().display().to_string(), e)
+        })?;
+        let anchor_toml_path = root.join("Anchor.toml");
+        let anchor_toml = fs::read_to_string(&anchor_toml_path).map_err(|e| {
+            LocalnetConfigurationError::FileReadWriteError(
+                anchor_toml_path.display().to_string(),
+                e,
+            )
+        })?;
+        let mut anchor_toml: WorkspaceAnchorToml = toml::from_str(&anchor_toml)
+            .map_err(|e| LocalnetConfigurationError::InvalidAnchorToml(e.to_string()))?;
+
+        let mut cfg = Self::new();
+        let programs = anchor_toml.programs.remove("localnet").unwrap_or_default();
+        for (name, entry) in programs {
+            let address = match &entry {
+                ProgramEntry::Address(address) | ProgramEntry::Detailed { address } => address,
+            };
+            let program_id = parse_pubkey(address)?;
+            let so_path = root
+                .join("target")
+                .join("deploy")
+                .join(format!("{}.so", name));
+            if !so_path.exists() {
+                return Err(LocalnetConfigurationError::MissingProgramSoFile(
+                    so_path.display().to_string(),
+                ));
+            }
+            cfg = cfg.program_binary_file(program_id, &so_path.display().to_string())?;
+            #[cfg(feature = "idl")]
+            {
+                let idl_path = root
+                    .join("target")
+                    .join("idl")
+                    .join(format!("{}.json", name));
+                if idl_path.exists() {
+                    cfg = cfg.with_idl(program_id, &idl_path)?;
+                }
+            }
+        }
+        for genesis in anchor_toml.test.genesis {
+            let program_id = parse_pubkey(&genesis.address)?;
+            let so_path = root.join(&genesis.program);
+            cfg = cfg.program_binary_file(program_id, &so_path.display().to_string())?;
+        }
+        let validator = anchor_toml.test.validator;
+        for entry in validator.account {
+            let path = root.join(&entry.filename);
+            let ui_account: UiAccountWithAddr = read_json(&path)?;
+            let name = path.file_name().map_or(entry.filename.clone(), |name| {
+                name.to_string_lossy().to_string()
+            });
+            cfg = cfg.accounts([LocalnetAccount::from_ui_account(ui_account, name)?])?;
+        }
+        let clones = validator
+            .clone
+            .iter()
+            .map(|entry| parse_pubkey(&entry.address))
+            .collect::<Result<Vec<_>>>()?;
+        cfg = cfg.clone_accounts(clones);
+        if let Some(url) = validator.url {
+            cfg.add_test_validator_arg("url".to_string(), url);
+        }
+        Ok(cfg)
+    }
+
+    /// The configuration as `Anchor.toml` `[[test.genesis]]`, `[[test.validator.account]]`
+    /// and `[[test.validator.clone]]` entries, plus the validator `url` argument if set,
+    /// and the `warp_slot` and `slots_per_epoch` of any [crate::sysvars::SysvarOverrides].
+    /// Account entries point at the JSON files written by
+    /// [LocalnetConfiguration::write_accounts_json] into `json_outdir`, or the configured
+    /// output directory. Other test validator arguments and flags are not exported.
+    pub fn anchor_toml(&self, json_outdir: Option<&str>) -> Result<String> {
+        let path_prefix = json_outdir
+            .or(self.json_outdir.as_deref())
+            .ok_or(LocalnetConfigurationError::NoOutputDirectory)?;
+        let accounts: BTreeMap<_, _> = self
+            .accounts
+            .iter()
+            .filter(|(pubkey, _)| !self.pubkey_is_program(pubkey))
+            .collect();
+        let programs: BTreeMap<_, _> = self.programs.iter().collect();
+        let sysvar_args = self.sysvars.test_validator_args();
+        let sysvar_arg = |name: &str| {
+            sysvar_args
+                .iter()
+                .find(|(arg, _)| arg == name)
+                .map(|(_, value)| value.clone())
+        };
+        let toml = AnchorToml {
+            test: TestSection {
+                genesis: programs
+                    .into_iter()
+                    .map(|(pubkey, path)| GenesisEntry {
+                        address: pubkey.to_string(),
+                        program: path.clone(),
+                    })
+                    .collect(),
+                validator: ValidatorSection {
+                    url: self.test_validator_args.get("url").cloned(),
+                    warp_slot: sysvar_arg("--warp-slot"),
+                    slots_per_epoch: sysvar_arg("--slots-per-epoch"),
+                    account: accounts
+                        .into_iter()
+                        .map(|(pubkey, act)| AccountEntry {
+                            address: pubkey.to_string(),
+                            filename: act.json_output_path(path_prefix),
+                        })
+                        .collect(),
+                    clone: self
+                        .clones
+                        .iter()
+                        .map(|pubkey| CloneEntry {
+                            address: pubkey.to_string(),
+                        })
+                        .collect(),
+                },
+            },
+        };
+        toml::to_string(&toml).map_err(LocalnetConfigurationError::AnchorTomlError)
+    }
+
+    /// Write [LocalnetConfiguration::anchor_toml] to a file, to be merged into
+    /// or included alongside a workspace's `Anchor.toml`.
+    pub fn write_anchor_toml<P: AsRef<Path>>(
+        &self,
+        path: P,
+        json_outdir: Option<&str>,
+    ) -> Result<()> {
+        fs::write(path.as_ref(), self.anchor_toml(json_outdir)?).map_err(|e| {
+            let path = path.as_ref().to_str().unwrap().to_string();
+            LocalnetConfigurationError::FileReadWriteError(path, e)
+        })
+    }
+}
+
+fn parse_pubkey(address: &str) -> Result<Pubkey> {
+    Pubkey::from_str(address).map_err(|_| {
+        LocalnetConfigurationError::InvalidAnchorToml(format!("invalid address {}", address))
+    })
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let path_str = path.display().to_string();
+    let file = File::open(path)
+        .map_err(|e| LocalnetConfigurationError::FileReadWriteError(path_str.clone(), e))?;
+    serde_json::from_reader(file)
+        .map_err(|e| LocalnetConfigurationError::SerdeFileReadWriteFailure(path_str, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An Anchor 0.30 spec IDL, as `anchor build` writes to `target/idl`.
+    #[cfg(feature = "idl")]
+    fn spec_idl_json(program_id: &Pubkey) -> Vec<u8> {
+        serde_json::to_vec_pretty(&serde_json::json!({
+            "address": program_id.to_string(),
+            "metadata": { "name": "my_program", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "initialize",
+                "discriminator": [175, 175, 109, 31, 13, 152, 155, 237],
+                "accounts": [{ "name": "payer", "writable": true, "signer": true }],
+                "args": [{ "name": "amount", "type": "u64" }],
+            }],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn anchor_workspaces_are_loaded() {
+        let program_id = Pubkey::new_unique();
+        let genesis_id = Pubkey::new_unique();
+        let clone = Pubkey::new_unique();
+        let root = std::env::temp_dir().join(format!("anchor-workspace-{}", program_id));
+        fs::create_dir_all(root.join("target/deploy")).unwrap();
+        fs::create_dir_all(root.join("target/idl")).unwrap();
+        fs::write(root.join("target/deploy/my_program.so"), [1, 2, 3]).unwrap();
+        fs::write(root.join("genesis.so"), [4, 5, 6]).unwrap();
+        #[cfg(feature = "idl")]
+        fs::write(
+            root.join("target/idl/my_program.json"),
+            spec_idl_json(&program_id),
+        )
+        .unwrap();
+        fs::write(
+            root.join("Anchor.toml"),
+            format!(
+                r#"
+[programs.localnet]
+my_program = "{program_id}"
+
+[[test.genesis]]
+address = "{genesis_id}"
+program = "genesis.so"
+
+[test.validator]
+url = "https://api.devnet.solana.com"
+
+[[test.validator.clone]]
+address = "{clone}"
+"#
+            ),
+        )
+        .unwrap();
+
+        let cfg = LocalnetConfiguration::from_anchor_workspace(&root).unwrap();
+        assert!(cfg.programs[&program_id].ends_with("target/deploy/my_program.so"));
+        assert!(cfg.programs[&genesis_id].ends_with("genesis.so"));
+        assert!(cfg.accounts[&program_id].executable);
+        assert_eq!(cfg.clones.iter().collect::<Vec<_>>(), vec![&clone]);
+        assert_eq!(
+            cfg.test_validator_args.get("url").map(String::as_str),
+            Some("https://api.devnet.solana.com")
+        );
+        #[cfg(feature = "idl")]
+        {
+            use solana_devtools_anchor_utils::idl_sdk::decompress_idl_account;
+            let idl_account = &cfg.accounts[&anchor_lang::idl::IdlAccount::address(&program_id)];
+            assert_eq!(idl_account.owner, program_id);
+            assert_eq!(
+                decompress_idl_account(&idl_account.data).unwrap(),
+                spec_idl_json(&program_id)
+            );
+        }
+
+        fs::remove_file(root.join("target/deploy/my_program.so")).unwrap();
+        assert!(matches!(
+            LocalnetConfiguration::from_anchor_workspace(&root),
+            Err(LocalnetConfigurationError::MissingProgramSoFile(_))
+        ));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn configurations_are_exported_as_test_sections() {
+        let program_id = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+        let clone = Pubkey::new_unique();
+        let so_path = std::env::temp_dir().join(format!("{}.so", program_id));
+        fs::write(&so_path, [1, 2, 3]).unwrap();
+        let mut cfg = LocalnetConfiguration::new()
+            .program_binary_file(program_id, &so_path.display().to_string())
+            .unwrap()
+            .accounts([LocalnetAccount::new_raw(
+                wallet,
+                "wallet.json".to_string(),
+                vec![],
+            )])
+            .unwrap()
+            .clock(solana_sdk::clock::Clock {
+                slot: 50,
+                ..Default::default()
+            })
+            .unwrap()
+            .clone_accounts([clone]);
+        cfg.add_test_validator_arg(
+            "url".to_string(),
+            "https://api.devnet.solana.com".to_string(),
+        );
+        assert!(matches!(
+            cfg.anchor_toml(None),
+            Err(LocalnetConfigurationError::NoOutputDirectory)
+        ));
+
+        let exported: toml::Value =
+            toml::from_str(&cfg.anchor_toml(Some("accounts")).unwrap()).unwrap();
+        let test = &exported["test"];
+        let genesis = test["genesis"].as_array().unwrap();
+        assert_eq!(genesis.len(), 1);
+        assert_eq!(
+            genesis[0]["address"].as_str(),
+            Some(program_id.to_string().as_str())
+        );
+        assert_eq!(genesis[0]["program"].as_str(), so_path.to_str());
+        let validator = &test["validator"];
+        assert_eq!(
+            validator["url"].as_str(),
+            Some("https://api.devnet.solana.com")
+        );
+        assert_eq!(validator["warp_slot"].as_str(), Some("50"));
+        assert_eq!(validator.get("slots_per_epoch"), None);
+        // The program's accounts are loaded from genesis, not from JSON files.
+        let mut filenames: Vec<&str> = validator["account"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["filename"].as_str().unwrap())
+            .collect();
+        filenames.sort();
+        assert_eq!(
+            filenames,
+            vec!["accounts/sysvar_clock.json", "accounts/wallet.json"]
+        );
+        assert_eq!(
+            validator["clone"][0]["address"].as_str(),
+            Some(clone.to_string().as_str())
+        );
+        fs::remove_file(so_path).unwrap();
+    }
+
+    #[cfg(feature = "idl")]
+    #[test]
+    fn idl_files_are_stored_as_is() {
+        use anchor_lang::idl::IdlAccount;
+        use solana_devtools_anchor_utils::idl_sdk::decompress_idl_account;
+
+        let spec_program = Pubkey::new_unique();
+        let legacy_program = Pubkey::new_unique();
+        let legacy_json = serde_json::to_vec(&serde_json::json!({
+            "version": "0.1.0",
+            "name": "legacy_program",
+            "instructions": [],
+        }))
+        .unwrap();
+        let dir = std::env::temp_dir().join(format!("idls-{}", spec_program));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("spec.json"), spec_idl_json(&spec_program)).unwrap();
+        fs::write(dir.join("legacy.json"), &legacy_json).unwrap();
+        fs::write(dir.join("invalid.json"), b"{}").unwrap();
+
+        let cfg = LocalnetConfiguration::new()
+            .with_idl(spec_program, dir.join("spec.json"))
+            .unwrap()
+            .with_idl(legacy_program, dir.join("legacy.json"))
+            .unwrap();
+        for (program_id, json) in [
+            (spec_program, spec_idl_json(&spec_program)),
+            (legacy_program, legacy_json),
+        ] {
+            let idl_account = &cfg.accounts[&IdlAccount::address(&program_id)];
+            assert_eq!(decompress_idl_account(&idl_account.data).unwrap(), json);
+        }
+        assert!(matches!(
+            LocalnetConfiguration::new().with_idl(Pubkey::new_unique(), dir.join("invalid.json")),
+            Err(LocalnetConfigurationError::IdlParseError(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,9 @@
 use crate::error::Result;
-use crate::LocalnetConfiguration;
+use crate::snapshot::accounts_dir;
+use crate::{LocalnetConfiguration, ProgramSnapshotter};
 use clap::Parser;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
 
 #[derive(Debug, Parser)]
 pub enum Subcommand {
@@ -28,11 +31,41 @@ pub enum Subcommand {
         /// Additional flags to pass to the test validator.
         flags: Vec<String>,
     },
+    /// Write the accounts, programs and clones as `Anchor.toml` `[test]` entries.
+    BuildAnchorToml {
+        /// Filepath in which to write the TOML.
+        #[clap(long)]
+        outfile: String,
+        /// Directory of the account JSON files that the entries point at.
+        /// If no value is passed, defaults to the destination configured in code.
+        #[clap(long)]
+        json_dir: Option<String>,
+    },
     BuildJsImports {
         /// Filepath in which to write JS import statements.
         #[clap(long)]
         outfile: String,
     },
+    /// Snapshot every account owned by a program into account JSON files.
+    /// Rerun with the same output directory to resume an interrupted snapshot.
+    SnapshotProgram {
+        program_id: Pubkey,
+        /// Directory for the manifest, checkpoint, and account JSON files.
+        #[clap(long)]
+        output_dir: String,
+        /// RPC endpoint to snapshot from.
+        #[clap(long, short)]
+        url: String,
+        /// Number of listing partitions or pages of accounts to fetch at once.
+        #[clap(long, default_value = "8")]
+        concurrency: usize,
+        /// Accounts are listed in partitions by the value of their data byte at this offset.
+        #[clap(long, default_value = "8")]
+        listing_offset: usize,
+        /// Accounts per `getMultipleAccounts` request, at most 100.
+        #[clap(long, default_value = "100")]
+        page_size: usize,
+    },
 }
 
 #[derive(Debug, Parser)]
@@ -77,9 +110,34 @@ impl SolanaLocalnetCli {
                     eprintln!("test validator exited with error code: {:?}", code);
                 }
             }
+            Subcommand::BuildAnchorToml { outfile, json_dir } => {
+                cfg.write_anchor_toml(outfile, json_dir.as_deref())?;
+            }
             Subcommand::BuildJsImports { outfile } => {
                 cfg.write_js_import_file(outfile)?;
             }
+            Subcommand::SnapshotProgram {
+                program_id,
+                output_dir,
+                url,
+                concurrency,
+                listing_offset,
+                page_size,
+            } => {
+                let client = RpcClient::new(url);
+                let manifest = ProgramSnapshotter::new(&client, program_id)
+                    .concurrency(concurrency)
+                    .listing_offset(listing_offset)
+                    .page_size(page_size)
+                    .snapshot(&output_dir)?;
+                println!(
+                    "snapshotted {} accounts of {} from slot {} into {}",
+                    manifest.accounts.len(),
+                    program_id,
+                    manifest.slot,
+                    accounts_dir(&output_dir).display()
+                );
+            }
         }
         Ok(())
     }
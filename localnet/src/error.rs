@@ -14,6 +14,8 @@ pub enum LocalnetConfigurationError {
     DuplicateAccountName(Vec<String>),
     #[error("Duplicate program: {0:?}")]
     DuplicateProgramPubkey(String),
+    #[error("Conflicting {0} when merging configurations")]
+    MergeConflict(String),
     #[error("Could not parse account JSON: {0}")]
     InvalidAccountJson(serde_json::Error),
     #[error("Could not parse base58 account data: {0}")]
@@ -24,6 +26,8 @@ pub enum LocalnetConfigurationError {
     FileReadWriteError(String, std::io::Error),
     #[error("Could not read/write to file: {0}: {1}")]
     SerdeFileReadWriteFailure(String, serde_json::Error),
+    #[error("Could not read/write keypair file: {0}: {1}")]
+    KeypairFileError(String, String),
     #[error("Could not deserialize account data: {0}")]
     AnchorAccountError(anchor_lang::error::Error),
     #[error("Could not fetch account data to clone: {0}")]
@@ -32,6 +36,20 @@ pub enum LocalnetConfigurationError {
     IdlParseError(String),
     #[error("Failed to serialize IDL to JSON bytes: {0}")]
     IdlSerializationError(String),
+    #[error("Snapshot manifest {0} is for a different program: {1}")]
+    SnapshotManifestMismatch(String, String),
+    #[error("Snapshot manifest {0} is invalid: {1}")]
+    InvalidSnapshotManifest(String, String),
+    #[error("Could not parse Anchor.toml: {0}")]
+    InvalidAnchorToml(String),
+    #[error("Failed to serialize Anchor.toml entries: {0}")]
+    AnchorTomlError(toml::ser::Error),
+    #[error("Failed to spawn solana-test-validator: {0}")]
+    TestValidatorSpawnError(std::io::Error),
+    #[error("solana-test-validator exited with code {0:?} before becoming healthy")]
+    TestValidatorExited(Option<i32>),
+    #[error("solana-test-validator did not become healthy within {0:?}")]
+    TestValidatorTimeout(std::time::Duration),
     #[error("Failed to create a BPF runtime environment: {0}")]
     EbpfError(String),
 }
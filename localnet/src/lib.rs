@@ -1,13 +1,21 @@
+pub mod anchor_toml;
 pub mod cli;
 pub mod error;
 pub mod localnet_account;
 pub mod localnet_configuration;
+pub mod merge;
+pub mod snapshot;
+pub mod sysvars;
+pub mod test_validator;
 
 pub use cli::SolanaLocalnetCli;
 pub use localnet_account::{
     trait_based::ClonedAccount, trait_based::GeneratedAccount, LocalnetAccount,
 };
-pub use localnet_configuration::LocalnetConfiguration;
+pub use localnet_configuration::{LocalnetConfiguration, ProgramEntrypoint};
+pub use merge::OnConflict;
+pub use snapshot::ProgramSnapshotter;
+pub use test_validator::{TestValidatorHandle, TestValidatorLauncher};
 
 #[cfg(feature = "solana-devtools-simulator")]
 pub use solana_devtools_simulator::{ProcessedMessage, TransactionSimulator};
@@ -1,7 +1,11 @@
 use crate::error::{LocalnetConfigurationError, Result};
 use crate::LocalnetAccount;
 use anchor_lang::idl::IdlAccount;
-use solana_devtools_anchor_utils::idl_sdk::{idl_parse, serialize_idl_account};
+use anchor_syn::idl::types::Idl;
+use solana_devtools_anchor_utils::deserialize::IdlWithDiscriminators;
+use solana_devtools_anchor_utils::idl_sdk::{
+    idl_parse, serialize_idl_account, serialize_idl_json_account,
+};
 use solana_program::pubkey::Pubkey;
 use solana_program::rent::Rent;
 
@@ -23,6 +27,27 @@ impl LocalIdlAccount {
             .map_err(|e| LocalnetConfigurationError::IdlSerializationError(format!("{e}")))?;
         Ok(Self { data, program_id })
     }
+
+    /// From an IDL that is already built, e.g. `target/idl/<program>.json`.
+    pub fn new_from_idl(idl: &Idl, program_id: Pubkey, authority: Option<Pubkey>) -> Result<Self> {
+        let data = serialize_idl_account(idl, authority)
+            .map_err(|e| LocalnetConfigurationError::IdlSerializationError(format!("{e}")))?;
+        Ok(Self { data, program_id })
+    }
+
+    /// From IDL JSON in either the legacy layout or the Anchor 0.30 spec. The JSON is
+    /// stored as-is, it is only parsed to check that it is an IDL.
+    pub fn new_from_json(
+        idl_json: &[u8],
+        program_id: Pubkey,
+        authority: Option<Pubkey>,
+    ) -> Result<Self> {
+        IdlWithDiscriminators::from_json(idl_json)
+            .map_err(|e| LocalnetConfigurationError::IdlParseError(format!("{e}")))?;
+        let data = serialize_idl_json_account(idl_json, authority)
+            .map_err(|e| LocalnetConfigurationError::IdlSerializationError(format!("{e}")))?;
+        Ok(Self { data, program_id })
+    }
 }
 
 impl Into<LocalnetAccount> for LocalIdlAccount {
@@ -18,15 +18,19 @@ use std::fs::{File, OpenOptions};
 pub mod idl;
 pub mod system_account;
 pub mod token;
+pub mod token_2022;
 pub mod trait_based;
 
+#[cfg(feature = "idl")]
+pub use solana_devtools_anchor_utils::account_data::{PdaAccount, StakeAccount, VoteAccount};
 pub use system_account::SystemAccount;
 pub use token::{Mint, TokenAccount};
+pub use token_2022::{Token2022Account, Token2022Mint};
 
 pub const THOUSAND_SOL: u64 = 1_000_000_000_000;
 
 /// Builds JSON files consumable by `solana-test-validator`.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct LocalnetAccount {
     pub address: Pubkey,
     pub lamports: u64,
@@ -0,0 +1,459 @@
+use solana_program::program_error::ProgramError;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::immutable_owner::ImmutableOwner;
+use spl_token_2022::extension::interest_bearing_mint::InterestBearingConfig;
+use spl_token_2022::extension::metadata_pointer::MetadataPointer;
+use spl_token_2022::extension::non_transferable::{NonTransferable, NonTransferableAccount};
+use spl_token_2022::extension::transfer_fee::{TransferFee, TransferFeeAmount, TransferFeeConfig};
+use spl_token_2022::extension::{
+    BaseState, BaseStateWithExtensions, ExtensionType, StateWithExtensions, StateWithExtensionsMut,
+};
+use spl_token_2022::state::{Account, AccountState, Mint};
+use std::io::Write;
+
+/// A Token-2022 mint, with builder methods for some of its extensions.
+/// Serializes to the mint's full account data, extensions included.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Token2022Mint {
+    pub mint: Mint,
+    pub transfer_fee: Option<TransferFeeConfig>,
+    pub metadata_pointer: Option<MetadataPointer>,
+    pub interest_bearing: Option<InterestBearingConfig>,
+    pub non_transferable: bool,
+}
+
+impl Token2022Mint {
+    pub fn new(mint_authority: Option<Pubkey>, supply: u64, decimals: u8) -> Self {
+        Self {
+            mint: Mint {
+                mint_authority: mint_authority.into(),
+                supply,
+                decimals,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn mint_authority(mut self, mint_authority: Option<Pubkey>) -> Self {
+        self.mint.mint_authority = mint_authority.into();
+        self
+    }
+
+    pub fn supply(mut self, supply: u64) -> Self {
+        self.mint.supply = supply;
+        self
+    }
+
+    pub fn decimals(mut self, decimals: u8) -> Self {
+        self.mint.decimals = decimals;
+        self
+    }
+
+    pub fn freeze_authority(mut self, freeze_authority: Option<Pubkey>) -> Self {
+        self.mint.freeze_authority = freeze_authority.into();
+        self
+    }
+
+    /// Charge a fee of `basis_points` on transfers, up to `maximum_fee`, from epoch 0 onwards.
+    /// Extensions store a missing authority as the default pubkey, so
+    /// `Some(Pubkey::default())` is rejected as an invalid argument.
+    pub fn transfer_fee(
+        mut self,
+        config_authority: Option<Pubkey>,
+        withdraw_withheld_authority: Option<Pubkey>,
+        basis_points: u16,
+        maximum_fee: u64,
+    ) -> Result<Self, ProgramError> {
+        let fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: maximum_fee.into(),
+            transfer_fee_basis_points: basis_points.into(),
+        };
+        self.transfer_fee = Some(TransferFeeConfig {
+            transfer_fee_config_authority: config_authority.try_into()?,
+            withdraw_withheld_authority: withdraw_withheld_authority.try_into()?,
+            withheld_amount: 0.into(),
+            older_transfer_fee: fee,
+            newer_transfer_fee: fee,
+        });
+        Ok(self)
+    }
+
+    /// Point to the account that holds the mint's metadata, which may be the mint itself.
+    /// As with [Self::transfer_fee], `Some(Pubkey::default())` is rejected.
+    pub fn metadata_pointer(
+        mut self,
+        authority: Option<Pubkey>,
+        metadata_address: Option<Pubkey>,
+    ) -> Result<Self, ProgramError> {
+        self.metadata_pointer = Some(MetadataPointer {
+            authority: authority.try_into()?,
+            metadata_address: metadata_address.try_into()?,
+        });
+        Ok(self)
+    }
+
+    /// Accrue interest at `rate` basis points per year, starting at `initialization_timestamp`.
+    /// As with [Self::transfer_fee], `Some(Pubkey::default())` is rejected.
+    pub fn interest_bearing(
+        mut self,
+        rate_authority: Option<Pubkey>,
+        rate: i16,
+        initialization_timestamp: i64,
+    ) -> Result<Self, ProgramError> {
+        self.interest_bearing = Some(InterestBearingConfig {
+            rate_authority: rate_authority.try_into()?,
+            initialization_timestamp: initialization_timestamp.into(),
+            pre_update_average_rate: rate.into(),
+            last_update_timestamp: initialization_timestamp.into(),
+            current_rate: rate.into(),
+        });
+        Ok(self)
+    }
+
+    pub fn non_transferable(mut self, non_transferable: bool) -> Self {
+        self.non_transferable = non_transferable;
+        self
+    }
+
+    pub fn extension_types(&self) -> Vec<ExtensionType> {
+        let mut types = vec![];
+        if self.transfer_fee.is_some() {
+            types.push(ExtensionType::TransferFeeConfig);
+        }
+        if self.metadata_pointer.is_some() {
+            types.push(ExtensionType::MetadataPointer);
+        }
+        if self.interest_bearing.is_some() {
+            types.push(ExtensionType::InterestBearingConfig);
+        }
+        if self.non_transferable {
+            types.push(ExtensionType::NonTransferable);
+        }
+        types
+    }
+
+    fn pack(&self) -> Result<Vec<u8>, ProgramError> {
+        pack_with_extensions(self.mint, &self.extension_types(), |state| {
+            if let Some(config) = self.transfer_fee {
+                *state.init_extension::<TransferFeeConfig>(true)? = config;
+            }
+            if let Some(pointer) = self.metadata_pointer {
+                *state.init_extension::<MetadataPointer>(true)? = pointer;
+            }
+            if let Some(config) = self.interest_bearing {
+                *state.init_extension::<InterestBearingConfig>(true)? = config;
+            }
+            if self.non_transferable {
+                state.init_extension::<NonTransferable>(true)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl anchor_lang::AccountDeserialize for Token2022Mint {
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        let state = StateWithExtensions::<Mint>::unpack(buf)?;
+        Ok(Self {
+            mint: state.base,
+            transfer_fee: state.get_extension::<TransferFeeConfig>().ok().copied(),
+            metadata_pointer: state.get_extension::<MetadataPointer>().ok().copied(),
+            interest_bearing: state.get_extension::<InterestBearingConfig>().ok().copied(),
+            non_transferable: state.get_extension::<NonTransferable>().is_ok(),
+        })
+    }
+}
+
+impl anchor_lang::AccountSerialize for Token2022Mint {
+    fn try_serialize<W: Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
+        writer
+            .write(&self.pack()?)
+            .map_err(Into::<anchor_lang::error::Error>::into)?;
+        Ok(())
+    }
+}
+
+impl anchor_lang::Owner for Token2022Mint {
+    fn owner() -> Pubkey {
+        spl_token_2022::ID
+    }
+}
+
+/// A Token-2022 token account, with builder methods for some of its extensions.
+/// Serializes to the account's full data, extensions included.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Token2022Account {
+    pub account: Account,
+    /// Transfer fees withheld in the account, if its mint charges them.
+    pub transfer_fee_amount: Option<u64>,
+    pub non_transferable: bool,
+    pub immutable_owner: bool,
+}
+
+impl Token2022Account {
+    pub fn new(mint: Pubkey, owner: Pubkey, amount: u64) -> Self {
+        Self {
+            account: Account {
+                mint,
+                owner,
+                amount,
+                delegate: COption::None,
+                state: AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// An account with the extensions that Token-2022 requires for the mint's extensions,
+    /// as when initializing an account for the mint.
+    pub fn for_mint(
+        mint_address: Pubkey,
+        mint: &Token2022Mint,
+        owner: Pubkey,
+        amount: u64,
+    ) -> Self {
+        let mut account = Self::new(mint_address, owner, amount);
+        if mint.transfer_fee.is_some() {
+            account.transfer_fee_amount = Some(0);
+        }
+        account.non_transferable = mint.non_transferable;
+        account
+    }
+
+    pub fn mint(mut self, mint: Pubkey) -> Self {
+        self.account.mint = mint;
+        self
+    }
+
+    pub fn owner(mut self, owner: Pubkey) -> Self {
+        self.account.owner = owner;
+        self
+    }
+
+    pub fn amount(mut self, amount: u64) -> Self {
+        self.account.amount = amount;
+        self
+    }
+
+    pub fn state(mut self, state: AccountState) -> Self {
+        self.account.state = state;
+        self
+    }
+
+    pub fn delegated(mut self, delegate: Option<Pubkey>, delegated_amount: u64) -> Self {
+        self.account.delegate = delegate.into();
+        self.account.delegated_amount = delegated_amount;
+        self
+    }
+
+    pub fn close_authority(mut self, close_authority: Option<Pubkey>) -> Self {
+        self.account.close_authority = close_authority.into();
+        self
+    }
+
+    pub fn withheld_transfer_fees(mut self, withheld_amount: u64) -> Self {
+        self.transfer_fee_amount = Some(withheld_amount);
+        self
+    }
+
+    pub fn non_transferable(mut self, non_transferable: bool) -> Self {
+        self.non_transferable = non_transferable;
+        self
+    }
+
+    pub fn immutable_owner(mut self, immutable_owner: bool) -> Self {
+        self.immutable_owner = immutable_owner;
+        self
+    }
+
+    pub fn extension_types(&self) -> Vec<ExtensionType> {
+        let mut types = vec![];
+        if self.transfer_fee_amount.is_some() {
+            types.push(ExtensionType::TransferFeeAmount);
+        }
+        if self.non_transferable {
+            types.push(ExtensionType::NonTransferableAccount);
+        }
+        if self.immutable_owner {
+            types.push(ExtensionType::ImmutableOwner);
+        }
+        types
+    }
+
+    fn pack(&self) -> Result<Vec<u8>, ProgramError> {
+        pack_with_extensions(self.account, &self.extension_types(), |state| {
+            if let Some(withheld_amount) = self.transfer_fee_amount {
+                state
+                    .init_extension::<TransferFeeAmount>(true)?
+                    .withheld_amount = withheld_amount.into();
+            }
+            if self.non_transferable {
+                state.init_extension::<NonTransferableAccount>(true)?;
+            }
+            if self.immutable_owner {
+                state.init_extension::<ImmutableOwner>(true)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl anchor_lang::AccountDeserialize for Token2022Account {
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        let state = StateWithExtensions::<Account>::unpack(buf)?;
+        Ok(Self {
+            account: state.base,
+            transfer_fee_amount: state
+                .get_extension::<TransferFeeAmount>()
+                .ok()
+                .map(|amount| amount.withheld_amount.into()),
+            non_transferable: state.get_extension::<NonTransferableAccount>().is_ok(),
+            immutable_owner: state.get_extension::<ImmutableOwner>().is_ok(),
+        })
+    }
+}
+
+impl anchor_lang::AccountSerialize for Token2022Account {
+    fn try_serialize<W: Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
+        writer
+            .write(&self.pack()?)
+            .map_err(Into::<anchor_lang::error::Error>::into)?;
+        Ok(())
+    }
+}
+
+impl anchor_lang::Owner for Token2022Account {
+    fn owner() -> Pubkey {
+        spl_token_2022::ID
+    }
+}
+
+/// Pack the base state, and the extensions initialized by `init_extensions`.
+/// Without extensions, the data is the same as the legacy SPL token layout.
+fn pack_with_extensions<S: BaseState + Pack>(
+    base: S,
+    extension_types: &[ExtensionType],
+    init_extensions: impl FnOnce(&mut StateWithExtensionsMut<S>) -> Result<(), ProgramError>,
+) -> Result<Vec<u8>, ProgramError> {
+    let mut data = vec![0; ExtensionType::try_calculate_account_len::<S>(extension_types)?];
+    if extension_types.is_empty() {
+        S::pack(base, &mut data)?;
+        return Ok(data);
+    }
+    let mut state = StateWithExtensionsMut::<S>::unpack_uninitialized(&mut data)?;
+    init_extensions(&mut state)?;
+    state.base = base;
+    state.pack_base();
+    state.init_account_type()?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{AccountDeserialize, AccountSerialize};
+
+    fn serialize(value: &impl AccountSerialize) -> Vec<u8> {
+        let mut data = vec![];
+        value.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn mints_round_trip_with_extensions() {
+        let authority = Pubkey::new_unique();
+        let plain = Token2022Mint::new(Some(authority), 1_000, 6);
+        let data = serialize(&plain);
+        assert_eq!(data.len(), Mint::LEN);
+        assert_eq!(
+            Token2022Mint::try_deserialize(&mut &data[..]).unwrap(),
+            plain
+        );
+
+        let mint = plain
+            .freeze_authority(Some(authority))
+            .transfer_fee(Some(authority), None, 50, 5_000)
+            .unwrap()
+            .metadata_pointer(Some(authority), Some(Pubkey::new_unique()))
+            .unwrap()
+            .interest_bearing(None, 300, 1_700_000_000)
+            .unwrap()
+            .non_transferable(true);
+        let data = serialize(&mint);
+        let state = StateWithExtensions::<Mint>::unpack(&data).unwrap();
+        assert_eq!(state.get_extension_types().unwrap(), mint.extension_types());
+        assert_eq!(
+            u16::from(
+                state
+                    .get_extension::<TransferFeeConfig>()
+                    .unwrap()
+                    .newer_transfer_fee
+                    .transfer_fee_basis_points
+            ),
+            50
+        );
+        assert_eq!(
+            Token2022Mint::try_deserialize(&mut &data[..]).unwrap(),
+            mint
+        );
+
+        // The default pubkey can't be told apart from no authority once stored.
+        assert_eq!(
+            Token2022Mint::default().interest_bearing(Some(Pubkey::default()), 300, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn accounts_get_the_extensions_their_mint_requires() {
+        let mint_address = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let plain = Token2022Account::new(mint_address, owner, 10);
+        let data = serialize(&plain);
+        assert_eq!(data.len(), Account::LEN);
+        assert_eq!(
+            Token2022Account::try_deserialize(&mut &data[..]).unwrap(),
+            plain
+        );
+
+        let mint = Token2022Mint::new(None, 0, 0)
+            .transfer_fee(None, None, 10, 100)
+            .unwrap()
+            .non_transferable(true);
+        let account = Token2022Account::for_mint(mint_address, &mint, owner, 10)
+            .withheld_transfer_fees(7)
+            .immutable_owner(true);
+        assert_eq!(
+            account.extension_types(),
+            vec![
+                ExtensionType::TransferFeeAmount,
+                ExtensionType::NonTransferableAccount,
+                ExtensionType::ImmutableOwner,
+            ]
+        );
+        let data = serialize(&account);
+        let state = StateWithExtensions::<Account>::unpack(&data).unwrap();
+        assert_eq!(state.base.owner, owner);
+        assert_eq!(
+            u64::from(
+                state
+                    .get_extension::<TransferFeeAmount>()
+                    .unwrap()
+                    .withheld_amount
+            ),
+            7
+        );
+        assert_eq!(
+            Token2022Account::try_deserialize(&mut &data[..]).unwrap(),
+            account
+        );
+    }
+}
@@ -72,6 +72,34 @@ impl<T: GeneratedAccount> From<&T> for LocalnetAccount {
     }
 }
 
+/// Places the account data at its program derived address,
+/// funded with the rent-exempt minimum balance.
+#[cfg(feature = "idl")]
+impl<T> GeneratedAccount for crate::localnet_account::PdaAccount<T>
+where
+    T: AccountSerialize + AccountDeserialize + anchor_lang::Owner + Clone,
+{
+    type Data = T;
+
+    fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    fn generate(&self) -> T {
+        self.inner.clone()
+    }
+
+    fn lamports(&self) -> u64 {
+        let mut buf = vec![];
+        self.inner.try_serialize(&mut buf).unwrap();
+        Rent::default().minimum_balance(buf.len())
+    }
+
+    fn owner(&self) -> Pubkey {
+        T::owner()
+    }
+}
+
 /// Clone an account from a cluster, and optionally modify it.
 /// Only works on account types that implement [anchor_lang::AccountSerialize]
 /// and [anchor_lang::AccountDeserialize].
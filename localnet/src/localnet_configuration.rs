@@ -1,13 +1,31 @@
 use crate::error::{LocalnetConfigurationError, Result};
+#[cfg(feature = "idl")]
+use crate::localnet_account::idl::LocalIdlAccount;
 use crate::localnet_account::{LocalnetAccount, UiAccountWithAddr};
+use crate::sysvars::SysvarOverrides;
+use crate::test_validator::{TestValidatorHandle, TestValidatorLauncher};
 #[cfg(feature = "solana-devtools-simulator")]
 pub use crate::TransactionSimulator;
+#[cfg(feature = "idl")]
+use solana_client::{rpc_client::RpcClient, rpc_filter::RpcFilterType};
+#[cfg(feature = "idl")]
+use solana_devtools_anchor_utils::client::account::get_program_accounts_blocking;
+#[cfg(feature = "solana-devtools-simulator")]
+use solana_devtools_simulator::{sbf_program::SbfProgram, spl_artifacts::SplArtifact};
+#[cfg(feature = "processor")]
+use solana_program_runtime::invoke_context::BuiltinFunctionWithContext;
 use solana_program_test::ProgramTest;
 use solana_sdk::{
-    account::AccountSharedData, bpf_loader_upgradeable,
-    bpf_loader_upgradeable::UpgradeableLoaderState, pubkey::Pubkey,
+    account::AccountSharedData,
+    bpf_loader_upgradeable,
+    bpf_loader_upgradeable::UpgradeableLoaderState,
+    clock::Clock,
+    epoch_schedule::EpochSchedule,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{read_keypair_file, write_keypair_file, Keypair, Signer},
 };
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::io::Read;
 use std::{
     fs::{self, read_dir, File},
@@ -27,12 +45,18 @@ pub struct LocalnetConfiguration {
     /// Any accounts to pre-load to the test validator.
     pub accounts: HashMap<Pubkey, LocalnetAccount>,
     /// Used to enforce no duplicate account names.
-    account_names: HashSet<String>,
+    pub(crate) account_names: HashSet<String>,
     /// Paths to programs are retained only for use with a test validator.
     pub programs: HashMap<Pubkey, String>,
+    /// Programs that [ProgramTest] loads by name, see [ProgramEntrypoint].
+    pub program_entrypoints: HashMap<Pubkey, (String, ProgramEntrypoint)>,
     /// BPF Upgradeable program data pubkeys. Pubkeys in this and `self.programs`
     /// are filtered out.
-    program_data_accounts: HashSet<Pubkey>,
+    pub(crate) program_data_accounts: HashSet<Pubkey>,
+    /// Sysvars that are written as accounts, see [LocalnetConfiguration::clock].
+    pub sysvars: SysvarOverrides,
+    /// Accounts that a `solana-test-validator` clones from its `--url` on startup.
+    pub clones: BTreeSet<Pubkey>,
     /// CLI args to `solana-test-validator`. The key should not contain dashes.
     /// e.g. "rpc_port", "8899".
     pub test_validator_args: HashMap<String, String>,
@@ -43,6 +67,28 @@ pub struct LocalnetConfiguration {
     pub json_outdir: Option<String>,
 }
 
+/// How a program added with [LocalnetConfiguration::add_program_with_entrypoint]
+/// is loaded into a [ProgramTest].
+#[derive(Debug, Clone, Copy)]
+pub enum ProgramEntrypoint {
+    /// Load `<program_name>.so` from the `BPF_OUT_DIR`, `SBF_OUT_DIR`,
+    /// or `tests/fixtures` directories.
+    Bpf,
+    /// Run the program natively, unless [ProgramTest::prefer_bpf] is set and
+    /// `<program_name>.so` can be found. See [ProgramEntrypoint::from_processor].
+    #[cfg(feature = "processor")]
+    Processor(BuiltinFunctionWithContext),
+}
+
+#[cfg(feature = "processor")]
+impl ProgramEntrypoint {
+    /// Takes the output of `solana_program_test::processor!`,
+    /// e.g. `ProgramEntrypoint::from_processor(processor!(process_instruction))`.
+    pub fn from_processor(processor: Option<BuiltinFunctionWithContext>) -> Self {
+        processor.map_or(ProgramEntrypoint::Bpf, ProgramEntrypoint::Processor)
+    }
+}
+
 impl LocalnetConfiguration {
     pub fn new() -> Self {
         Self::default()
@@ -125,6 +171,76 @@ impl LocalnetConfiguration {
         Ok(self)
     }
 
+    /// Clone every account owned by `program_id` that matches the `filters`
+    /// from a live cluster, named after their addresses.
+    #[cfg(feature = "idl")]
+    pub fn clone_program_accounts(
+        self,
+        client: &RpcClient,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Self> {
+        self.clone_program_accounts_with(client, program_id, filters, |act| act)
+    }
+
+    /// Like [LocalnetConfiguration::clone_program_accounts], passing each account
+    /// through `modify` before it is added, e.g. to rename it or replace an authority.
+    #[cfg(feature = "idl")]
+    pub fn clone_program_accounts_with<F: FnMut(LocalnetAccount) -> LocalnetAccount>(
+        self,
+        client: &RpcClient,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+        modify: F,
+    ) -> Result<Self> {
+        let accounts = get_program_accounts_blocking(program_id, filters, client)
+            .map_err(LocalnetConfigurationError::ClonedAccountRpcError)?;
+        self.accounts(
+            accounts
+                .into_iter()
+                .map(|(address, account)| {
+                    LocalnetAccount::new_from_readable_account(address, account)
+                })
+                .map(modify),
+        )
+    }
+
+    /// Add system accounts with the given balances, named after their addresses.
+    pub fn add_funded_wallets(self, wallets: &[(Pubkey, u64)]) -> Result<Self> {
+        self.accounts(wallets.iter().map(|(address, lamports)| {
+            LocalnetAccount::new_raw(*address, format!("{}.json", address), vec![])
+                .lamports(*lamports)
+        }))
+    }
+
+    /// Add `n` system accounts with `lamports` each, and return their keypairs.
+    /// The accounts are named `funded_wallet_<i>.json`. If an output directory is configured,
+    /// each keypair is written alongside the account JSON as `funded_wallet_<i>-keypair.json`,
+    /// and an existing keypair file is reused, so that the same wallets are funded
+    /// every time the configuration is built.
+    pub fn generate_funded_keypairs(&mut self, n: usize, lamports: u64) -> Result<Vec<Keypair>> {
+        let mut keypairs = Vec::with_capacity(n);
+        let mut index = 0;
+        while keypairs.len() < n {
+            let name = format!("funded_wallet_{}", index);
+            index += 1;
+            if self.account_names.contains(&format!("{}.json", name)) {
+                continue;
+            }
+            let keypair = match &self.json_outdir {
+                Some(dir) => {
+                    read_or_write_keypair(&Path::new(dir).join(format!("{}-keypair.json", name)))?
+                }
+                None => Keypair::new(),
+            };
+            let act = LocalnetAccount::new_raw(keypair.pubkey(), format!("{}.json", name), vec![])
+                .lamports(lamports);
+            *self = std::mem::take(self).accounts([act])?;
+            keypairs.push(keypair);
+        }
+        Ok(keypairs)
+    }
+
     /// Add raw binary program data as a BPF upgradeable program. For programs that are not
     /// going to change, like dependency programs your program relies on, this is the preferred
     /// way to add programs, because you can use `include_bytes!` and place your binaries
@@ -136,6 +252,7 @@ impl LocalnetConfiguration {
         program_id: Pubkey,
         program_data: &[u8],
     ) -> Result<Self> {
+        self.check_program_id_unused(&program_id)?;
         let programdata_address = Pubkey::new_unique();
         let program = LocalnetAccount {
             address: program_id,
@@ -178,7 +295,7 @@ impl LocalnetConfiguration {
     /// The program is added as a BPF upgradeable program in `self.accounts`.
     /// The filepath is only retained for the case where a `solana-test-validator` will be created.
     pub fn program_binary_file(
-        mut self,
+        self,
         program_id: Pubkey,
         program_binary_file: &str,
     ) -> Result<Self> {
@@ -194,11 +311,7 @@ impl LocalnetConfiguration {
             }
         };
 
-        if self.programs.contains_key(&program_id) {
-            return Err(LocalnetConfigurationError::DuplicateProgramPubkey(
-                program_id.to_string(),
-            ));
-        }
+        self.check_program_id_unused(&program_id)?;
         let mut file = File::open(&path).map_err(|e| {
             eprintln!("Working directory: {:?}", std::env::current_dir());
             LocalnetConfigurationError::FileReadWriteError(path.clone(), e)
@@ -214,8 +327,118 @@ impl LocalnetConfiguration {
         let _ = file
             .read_to_end(&mut data)
             .map_err(|e| LocalnetConfigurationError::FileReadWriteError(path.clone(), e))?;
-        self.programs.insert(program_id, path);
-        self.program_binary_data(&name, program_id, &data)
+        let mut this = self.program_binary_data(&name, program_id, &data)?;
+        this.programs.insert(program_id, path);
+        Ok(this)
+    }
+
+    /// Add one of the SPL programs bundled with [solana_program_test], as a BPF upgradeable
+    /// program named after the artifact's binary, e.g. `spl_token-3.5.0`.
+    #[cfg(feature = "solana-devtools-simulator")]
+    pub fn spl_program(self, artifact: &SplArtifact) -> Result<Self> {
+        let name = artifact.file_name();
+        self.program_binary_data(
+            name.trim_end_matches(".so"),
+            artifact.program_id,
+            artifact.elf(),
+        )
+    }
+
+    /// Add a program built by `cargo build-sbf`, e.g. as found by [SbfProgram::find],
+    /// under its declared program id.
+    #[cfg(feature = "solana-devtools-simulator")]
+    pub fn sbf_program(self, program: &SbfProgram) -> Result<Self> {
+        self.program_binary_file(program.program_id, &program.so_path.display().to_string())
+    }
+
+    /// Add the canonical Anchor IDL account of `program_id`, compressed from the IDL JSON
+    /// at `idl_path`, e.g. `target/idl/<program>.json`, in either the legacy layout or
+    /// the Anchor 0.30 spec. The JSON is stored as-is, so that clients can fetch the IDL
+    /// as they would from a live cluster. Relative paths are interpreted as in
+    /// [LocalnetConfiguration::program_binary_file]. The IDL authority is a random pubkey.
+    #[cfg(feature = "idl")]
+    pub fn with_idl<P: AsRef<Path>>(self, program_id: Pubkey, idl_path: P) -> Result<Self> {
+        let path = idl_path.as_ref();
+        let path = match std::env::var("CARGO_MANIFEST_DIR") {
+            Ok(manifest_dir) if path.is_relative() => Path::new(&manifest_dir).join(path),
+            _ => path.to_path_buf(),
+        };
+        let idl_json = fs::read(&path).map_err(|e| {
+            LocalnetConfigurationError::FileReadWriteError(path.display().to_string(), e)
+        })?;
+        let idl_account = LocalIdlAccount::new_from_json(&idl_json, program_id, None)?;
+        self.accounts([idl_account.into()])
+    }
+
+    /// Add a program that is registered through [ProgramTest::add_program] instead of
+    /// being written into `self.accounts`, so that the same configuration can run
+    /// against either a compiled program or its native processor.
+    /// These programs are not passed to a `solana-test-validator`.
+    pub fn add_program_with_entrypoint(
+        mut self,
+        program_name: &str,
+        program_id: Pubkey,
+        entrypoint: ProgramEntrypoint,
+    ) -> Result<Self> {
+        self.check_program_id_unused(&program_id)?;
+        self.program_entrypoints
+            .insert(program_id, (program_name.to_string(), entrypoint));
+        Ok(self)
+    }
+
+    /// A program id is added once, whether as a binary, whose program account is in
+    /// `self.accounts`, or with an entrypoint.
+    fn check_program_id_unused(&self, program_id: &Pubkey) -> Result<()> {
+        if self.programs.contains_key(program_id)
+            || self.program_entrypoints.contains_key(program_id)
+            || self.accounts.contains_key(program_id)
+        {
+            return Err(LocalnetConfigurationError::DuplicateProgramPubkey(
+                program_id.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Start at the given [Clock]. Its account is written alongside the other accounts,
+    /// and a non-zero slot is passed to `solana-test-validator` as `--warp-slot`.
+    /// The validator recomputes the clock every slot, so only the slot carries over there.
+    /// A [TransactionSimulator] made from this configuration starts with the whole clock.
+    pub fn clock(mut self, clock: Clock) -> Result<Self> {
+        self.sysvars.clock = Some(clock);
+        self.sysvar_accounts()
+    }
+
+    /// Start with the given [Rent], written as the rent sysvar account.
+    pub fn rent(mut self, rent: Rent) -> Result<Self> {
+        self.sysvars.rent = Some(rent);
+        self.sysvar_accounts()
+    }
+
+    /// Start with the given [EpochSchedule], written as the epoch schedule sysvar account,
+    /// and passed to `solana-test-validator` as `--slots-per-epoch`.
+    pub fn epoch_schedule(mut self, epoch_schedule: EpochSchedule) -> Result<Self> {
+        self.sysvars.epoch_schedule = Some(epoch_schedule);
+        self.sysvar_accounts()
+    }
+
+    /// Replace the sysvar accounts with the current overrides.
+    fn sysvar_accounts(mut self) -> Result<Self> {
+        for act in self.sysvars.accounts() {
+            if self.accounts.remove(&act.address).is_some() {
+                self.account_names.remove(&act.name);
+            }
+        }
+        let accounts = self.sysvars.accounts();
+        self.accounts(accounts)
+    }
+
+    /// Have a `solana-test-validator` clone accounts from its `--url` on startup.
+    /// Unlike [LocalnetConfiguration::clone_program_accounts], they are fetched
+    /// each time the validator starts, and are not loaded into a [ProgramTest].
+    pub fn clone_accounts(mut self, addresses: impl IntoIterator<Item = Pubkey>) -> Self {
+        self.clones.extend(addresses);
+        self
     }
 
     /// Add a `solana-test-validator` CLI argument to include on every startup.
@@ -276,6 +499,30 @@ impl LocalnetConfiguration {
         additional_args: Vec<String>,
         json_outdir: Option<&str>,
     ) -> std::io::Result<Child> {
+        std::process::Command::new("solana-test-validator")
+            .args(self.test_validator_cli_args(additional_args, json_outdir))
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .spawn()
+    }
+
+    /// Start a `solana-test-validator` that is killed when the returned handle is dropped,
+    /// once its RPC endpoint is healthy. See [TestValidatorLauncher] for more options.
+    pub fn start_managed_test_validator(
+        &self,
+        additional_args: Vec<String>,
+    ) -> Result<TestValidatorHandle> {
+        TestValidatorLauncher::new(self)
+            .args(additional_args)
+            .start()
+    }
+
+    /// The args to `solana-test-validator` that load this configuration.
+    pub(crate) fn test_validator_cli_args(
+        &self,
+        additional_args: Vec<String>,
+        json_outdir: Option<&str>,
+    ) -> Vec<String> {
         let path_prefix = self
             .json_outdir
             .as_ref()
@@ -292,6 +539,10 @@ impl LocalnetConfiguration {
             args.push(k.clone());
             args.push(v.clone());
         }
+        for (k, v) in self.sysvars.test_validator_args() {
+            args.push(k);
+            args.push(v);
+        }
         args.extend(additional_args);
         for (pubkey, account) in &self.accounts {
             if !self.pubkey_is_program(pubkey) {
@@ -300,16 +551,16 @@ impl LocalnetConfiguration {
                 args.push(account.json_output_path(&path_prefix));
             }
         }
+        for pubkey in &self.clones {
+            args.push("--clone".to_string());
+            args.push(pubkey.to_string());
+        }
         for (pubkey, path) in &self.programs {
             args.push("--bpf-program".to_string());
             args.push(pubkey.to_string());
             args.push(path.to_string());
         }
-        std::process::Command::new("solana-test-validator")
-            .args(args)
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .spawn()
+        args
     }
 
     pub fn get_account(&self, pubkey: &Pubkey) -> Option<&LocalnetAccount> {
@@ -326,20 +577,143 @@ impl LocalnetConfiguration {
     }
 }
 
+fn read_or_write_keypair(path: &Path) -> Result<Keypair> {
+    let keypair_error = |e: Box<dyn std::error::Error>| {
+        LocalnetConfigurationError::KeypairFileError(path.display().to_string(), e.to_string())
+    };
+    if path.exists() {
+        return read_keypair_file(path).map_err(keypair_error);
+    }
+    let keypair = Keypair::new();
+    write_keypair_file(&keypair, path).map_err(keypair_error)?;
+    Ok(keypair)
+}
+
 #[cfg(feature = "solana-devtools-simulator")]
 impl Into<TransactionSimulator> for &LocalnetConfiguration {
     fn into(self) -> TransactionSimulator {
-        TransactionSimulator::new_with_accounts(&self.accounts)
+        let simulator = TransactionSimulator::new_with_accounts(&self.accounts);
+        if let Some(clock) = &self.sysvars.clock {
+            simulator.set_sysvar(clock);
+        }
+        if let Some(rent) = &self.sysvars.rent {
+            simulator.set_sysvar(rent);
+        }
+        if let Some(epoch_schedule) = &self.sysvars.epoch_schedule {
+            simulator.set_sysvar(epoch_schedule);
+        }
+        simulator
     }
 }
 
 impl Into<ProgramTest> for &LocalnetConfiguration {
     fn into(self) -> ProgramTest {
+        let program_test = self.program_entrypoints.iter().fold(
+            ProgramTest::default(),
+            |mut p, (program_id, (name, entrypoint))| {
+                match entrypoint {
+                    ProgramEntrypoint::Bpf => p.add_program(name, *program_id, None),
+                    #[cfg(feature = "processor")]
+                    ProgramEntrypoint::Processor(processor) => {
+                        p.add_program(name, *program_id, Some(*processor))
+                    }
+                }
+                p
+            },
+        );
         self.accounts
             .iter()
-            .fold(ProgramTest::default(), |mut p, (pubkey, act)| {
+            .fold(program_test, |mut p, (pubkey, act)| {
                 p.add_account(*pubkey, act.into());
                 p
             })
     }
 }
+
+#[cfg(all(test, feature = "idl"))]
+mod tests {
+    use super::*;
+    use solana_account_decoder::{UiAccount, UiAccountEncoding};
+    use solana_client::rpc_request::RpcRequest;
+    use solana_client::rpc_response::RpcKeyedAccount;
+    use solana_sdk::account::Account;
+
+    #[test]
+    fn program_accounts_are_cloned() {
+        let program_id = Pubkey::new_unique();
+        let addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let keyed: Vec<RpcKeyedAccount> = addresses
+            .iter()
+            .map(|address| {
+                let account = Account {
+                    lamports: 10,
+                    data: vec![7; 16],
+                    owner: program_id,
+                    ..Default::default()
+                };
+                RpcKeyedAccount {
+                    pubkey: address.to_string(),
+                    account: UiAccount::encode(
+                        address,
+                        &account,
+                        UiAccountEncoding::Base64,
+                        None,
+                        None,
+                    ),
+                }
+            })
+            .collect();
+        let client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            HashMap::from([(
+                RpcRequest::GetProgramAccounts,
+                serde_json::to_value(&keyed).unwrap(),
+            )]),
+        );
+
+        let cfg = LocalnetConfiguration::new()
+            .clone_program_accounts_with(&client, &program_id, vec![], |act| act.lamports(1_000))
+            .unwrap();
+        assert_eq!(cfg.accounts.len(), 2);
+        for address in &addresses {
+            let account = &cfg.accounts[address];
+            assert_eq!(account.name, address.to_string());
+            assert_eq!(account.owner, program_id);
+            assert_eq!(account.data, vec![7; 16]);
+            assert_eq!(account.lamports, 1_000);
+        }
+
+        // Cloning the same accounts again conflicts with the existing ones.
+        let client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            HashMap::from([(
+                RpcRequest::GetProgramAccounts,
+                serde_json::to_value(&keyed).unwrap(),
+            )]),
+        );
+        assert!(matches!(
+            cfg.clone_program_accounts(&client, &program_id, vec![]),
+            Err(LocalnetConfigurationError::DuplicateAccountPubkey(_))
+        ));
+    }
+
+    #[test]
+    fn programs_are_added_once_in_either_order() {
+        let program_id = Pubkey::new_unique();
+        let with_entrypoint = LocalnetConfiguration::default()
+            .add_program_with_entrypoint("program", program_id, ProgramEntrypoint::Bpf)
+            .unwrap();
+        assert!(matches!(
+            with_entrypoint.program_binary_data("program", program_id, &[]),
+            Err(LocalnetConfigurationError::DuplicateProgramPubkey(_))
+        ));
+
+        let with_binary = LocalnetConfiguration::default()
+            .program_binary_data("program", program_id, &[])
+            .unwrap();
+        assert!(matches!(
+            with_binary.add_program_with_entrypoint("program", program_id, ProgramEntrypoint::Bpf),
+            Err(LocalnetConfigurationError::DuplicateProgramPubkey(_))
+        ));
+    }
+}
@@ -0,0 +1,292 @@
+//! Combine [LocalnetConfiguration]s, so that a base configuration of common accounts
+//! and programs can be shared between several suites.
+use crate::error::{LocalnetConfigurationError, Result};
+use crate::LocalnetConfiguration;
+use std::collections::BTreeMap;
+
+/// What [LocalnetConfiguration::merge] does when both configurations define
+/// the same account, account name, program, test validator arg, or sysvar differently.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Fail the merge.
+    #[default]
+    Error,
+    /// Keep the definition from the configuration being merged into.
+    KeepFirst,
+    /// Replace it with the definition from the configuration being merged in.
+    Override,
+}
+
+impl OnConflict {
+    /// Whether to take the other configuration's definition.
+    fn take_other(self, error: impl FnOnce() -> LocalnetConfigurationError) -> Result<bool> {
+        match self {
+            OnConflict::Error => Err(error()),
+            OnConflict::KeepFirst => Ok(false),
+            OnConflict::Override => Ok(true),
+        }
+    }
+}
+
+impl LocalnetConfiguration {
+    /// Add everything from `other` into this configuration. Identical accounts
+    /// and settings are not conflicts. Clones and test validator flags are combined,
+    /// and `other`'s output directory is only used if this configuration has none.
+    pub fn merge(mut self, other: LocalnetConfiguration, on_conflict: OnConflict) -> Result<Self> {
+        let accounts: BTreeMap<_, _> = other.accounts.into_iter().collect();
+        for (pubkey, act) in accounts {
+            if let Some(existing) = self.accounts.get(&pubkey) {
+                if *existing == act
+                    || !on_conflict.take_other(|| {
+                        LocalnetConfigurationError::DuplicateAccountPubkey(vec![pubkey.to_string()])
+                    })?
+                {
+                    continue;
+                }
+                let existing = self.accounts.remove(&pubkey).unwrap();
+                self.account_names.remove(&existing.name);
+            }
+            if self.account_names.contains(&act.name) {
+                if !on_conflict.take_other(|| {
+                    LocalnetConfigurationError::DuplicateAccountName(vec![act.name.clone()])
+                })? {
+                    continue;
+                }
+                self.accounts
+                    .retain(|_, existing| existing.name != act.name);
+            }
+            self.account_names.insert(act.name.clone());
+            self.accounts.insert(pubkey, act);
+        }
+        self.program_data_accounts
+            .extend(other.program_data_accounts);
+
+        for (program_id, path) in other.programs {
+            if self.programs.get(&program_id) == Some(&path) {
+                continue;
+            }
+            if self.programs.contains_key(&program_id)
+                || self.program_entrypoints.contains_key(&program_id)
+            {
+                if !on_conflict.take_other(|| {
+                    LocalnetConfigurationError::DuplicateProgramPubkey(program_id.to_string())
+                })? {
+                    continue;
+                }
+                self.program_entrypoints.remove(&program_id);
+            }
+            self.programs.insert(program_id, path);
+        }
+        for (program_id, entrypoint) in other.program_entrypoints {
+            let same_name = self
+                .program_entrypoints
+                .get(&program_id)
+                .map(|(name, _)| name);
+            if same_name == Some(&entrypoint.0) {
+                continue;
+            }
+            if self.programs.contains_key(&program_id)
+                || self.program_entrypoints.contains_key(&program_id)
+            {
+                if !on_conflict.take_other(|| {
+                    LocalnetConfigurationError::DuplicateProgramPubkey(program_id.to_string())
+                })? {
+                    continue;
+                }
+                self.programs.remove(&program_id);
+            }
+            self.program_entrypoints.insert(program_id, entrypoint);
+        }
+
+        let sysvars = other.sysvars;
+        if let Some(clock) = sysvars.clock {
+            merge_value(&mut self.sysvars.clock, clock, on_conflict, "clock")?;
+        }
+        if let Some(rent) = sysvars.rent {
+            merge_value(&mut self.sysvars.rent, rent, on_conflict, "rent")?;
+        }
+        if let Some(epoch_schedule) = sysvars.epoch_schedule {
+            merge_value(
+                &mut self.sysvars.epoch_schedule,
+                epoch_schedule,
+                on_conflict,
+                "epoch schedule",
+            )?;
+        }
+
+        for (key, value) in other.test_validator_args {
+            let mut existing = self.test_validator_args.get(&key).cloned();
+            merge_value(
+                &mut existing,
+                value,
+                on_conflict,
+                &format!("test validator arg {}", key),
+            )?;
+            self.test_validator_args.insert(key, existing.unwrap());
+        }
+        for flag in other.test_validator_flags {
+            if !self.test_validator_flags.contains(&flag) {
+                self.test_validator_flags.push(flag);
+            }
+        }
+        self.clones.extend(other.clones);
+        if self.json_outdir.is_none() {
+            self.json_outdir = other.json_outdir;
+        }
+        Ok(self)
+    }
+
+    /// Prefix every account name with `<namespace>_`, so that the accounts of several
+    /// configurations can be written to the same output directory without name conflicts.
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        for act in self.accounts.values_mut() {
+            act.name = format!("{}_{}", namespace, act.name);
+        }
+        self.account_names = self.accounts.values().map(|act| act.name.clone()).collect();
+        self
+    }
+}
+
+/// Set `existing` to `value` unless it already holds a different value that is kept.
+fn merge_value<T: PartialEq>(
+    existing: &mut Option<T>,
+    value: T,
+    on_conflict: OnConflict,
+    what: &str,
+) -> Result<()> {
+    match existing {
+        Some(current) if *current != value => {
+            if on_conflict
+                .take_other(|| LocalnetConfigurationError::MergeConflict(what.to_string()))?
+            {
+                *existing = Some(value);
+            }
+        }
+        _ => *existing = Some(value),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalnetAccount;
+    use solana_sdk::clock::Clock;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn account(address: Pubkey, name: &str, data: Vec<u8>) -> LocalnetAccount {
+        LocalnetAccount::new_raw(address, name.to_string(), data)
+    }
+
+    #[test]
+    fn conflicting_accounts_follow_on_conflict() {
+        let shared = Pubkey::new_unique();
+        let base = || {
+            LocalnetConfiguration::new()
+                .accounts([account(shared, "shared.json", vec![1])])
+                .unwrap()
+        };
+        let other = || {
+            LocalnetConfiguration::new()
+                .accounts([
+                    account(shared, "shared.json", vec![2]),
+                    account(Pubkey::new_unique(), "other.json", vec![3]),
+                ])
+                .unwrap()
+        };
+
+        assert!(matches!(
+            base().merge(other(), OnConflict::Error),
+            Err(LocalnetConfigurationError::DuplicateAccountPubkey(_))
+        ));
+        let kept = base().merge(other(), OnConflict::KeepFirst).unwrap();
+        assert_eq!(kept.accounts[&shared].data, vec![1]);
+        assert_eq!(kept.accounts.len(), 2);
+        let overridden = base().merge(other(), OnConflict::Override).unwrap();
+        assert_eq!(overridden.accounts[&shared].data, vec![2]);
+        assert_eq!(overridden.account_names.len(), 2);
+
+        // Identical accounts are not conflicts.
+        let merged = base().merge(base(), OnConflict::Error).unwrap();
+        assert_eq!(merged.accounts.len(), 1);
+    }
+
+    #[test]
+    fn conflicting_account_names_follow_on_conflict() {
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let base = || {
+            LocalnetConfiguration::new()
+                .accounts([account(first, "wallet.json", vec![])])
+                .unwrap()
+        };
+        let other = || {
+            LocalnetConfiguration::new()
+                .accounts([account(second, "wallet.json", vec![])])
+                .unwrap()
+        };
+
+        assert!(matches!(
+            base().merge(other(), OnConflict::Error),
+            Err(LocalnetConfigurationError::DuplicateAccountName(_))
+        ));
+        let kept = base().merge(other(), OnConflict::KeepFirst).unwrap();
+        assert_eq!(kept.accounts.keys().collect::<Vec<_>>(), vec![&first]);
+        let overridden = base().merge(other(), OnConflict::Override).unwrap();
+        assert_eq!(
+            overridden.accounts.keys().collect::<Vec<_>>(),
+            vec![&second]
+        );
+    }
+
+    #[test]
+    fn settings_are_combined() {
+        let (clone_a, clone_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut base = LocalnetConfiguration::new().clone_accounts([clone_a]);
+        base.add_test_validator_arg("url".to_string(), "https://a".to_string());
+        base.add_test_validator_flag("--reset".to_string());
+        let mut other = LocalnetConfiguration::with_outdir("accounts").clone_accounts([clone_b]);
+        other.add_test_validator_arg("url".to_string(), "https://b".to_string());
+        other.add_test_validator_arg("limit-ledger-size".to_string(), "100".to_string());
+        other.add_test_validator_flag("--reset".to_string());
+        other.add_test_validator_flag("--quiet".to_string());
+
+        assert!(matches!(
+            base.clone().merge(other.clone(), OnConflict::Error),
+            Err(LocalnetConfigurationError::MergeConflict(_))
+        ));
+        let merged = base.merge(other, OnConflict::KeepFirst).unwrap();
+        assert_eq!(merged.test_validator_args["url"], "https://a");
+        assert_eq!(merged.test_validator_args["limit-ledger-size"], "100");
+        assert_eq!(merged.test_validator_flags, vec!["--reset", "--quiet"]);
+        assert_eq!(merged.clones.len(), 2);
+        assert_eq!(merged.json_outdir.as_deref(), Some("accounts"));
+    }
+
+    #[test]
+    fn conflicting_sysvars_follow_on_conflict() {
+        let clock = |slot| Clock {
+            slot,
+            ..Default::default()
+        };
+        let base = LocalnetConfiguration::new().clock(clock(10)).unwrap();
+        let other = LocalnetConfiguration::new().clock(clock(20)).unwrap();
+
+        assert!(base
+            .clone()
+            .merge(other.clone(), OnConflict::Error)
+            .is_err());
+        let overridden = base.merge(other, OnConflict::Override).unwrap();
+        assert_eq!(overridden.sysvars.clock.map(|clock| clock.slot), Some(20));
+    }
+
+    #[test]
+    fn namespaces_prefix_account_names() {
+        let address = Pubkey::new_unique();
+        let cfg = LocalnetConfiguration::new()
+            .accounts([account(address, "wallet.json", vec![])])
+            .unwrap()
+            .namespace("suite");
+        assert_eq!(cfg.accounts[&address].name, "suite_wallet.json");
+        assert!(cfg.account_names.contains("suite_wallet.json"));
+    }
+}
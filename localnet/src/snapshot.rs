@@ -0,0 +1,657 @@
+//! Snapshot every account owned by a program into validator JSON files.
+//!
+//! Listing a large program's accounts with a single `getProgramAccounts` call can return
+//! gigabytes, or time out. Instead, only the account addresses are listed, using a zero-length
+//! `dataSlice`, in partitions filtered by the account data (see
+//! [ProgramSnapshotter::listing_offset]). The accounts are then fetched in pages with
+//! `getMultipleAccounts`. Both steps run on several threads, and save their progress after
+//! each partition or page, so an interrupted snapshot resumes where it left off.
+use crate::error::{LocalnetConfigurationError, Result};
+use crate::LocalnetAccount;
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_devtools_serde::{pubkey, pubkey_vec};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// The most accounts `getMultipleAccounts` returns per request.
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// The first byte after an Anchor account discriminator.
+pub const DEFAULT_LISTING_OFFSET: usize = 8;
+
+const LISTING_FILE: &str = "listing.json";
+const MANIFEST_FILE: &str = "manifest.json";
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+const ACCOUNTS_DIR: &str = "accounts";
+
+/// The accounts to snapshot, listed when the snapshot started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotManifest {
+    #[serde(with = "pubkey")]
+    pub program_id: Pubkey,
+    /// The slot before the accounts were listed.
+    pub slot: u64,
+    pub page_size: usize,
+    #[serde(with = "pubkey_vec")]
+    pub accounts: Vec<Pubkey>,
+}
+
+impl SnapshotManifest {
+    pub fn num_pages(&self) -> usize {
+        self.accounts.len().div_ceil(self.page_size)
+    }
+
+    pub fn page(&self, index: usize) -> &[Pubkey] {
+        let start = index * self.page_size;
+        &self.accounts[start..(start + self.page_size).min(self.accounts.len())]
+    }
+}
+
+/// Progress of listing the accounts to snapshot, until the manifest is written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotListing {
+    #[serde(with = "pubkey")]
+    pub program_id: Pubkey,
+    /// The slot before the accounts were listed.
+    pub slot: u64,
+    pub listing_offset: usize,
+    pub completed_partitions: BTreeSet<usize>,
+    #[serde(with = "pubkey_vec")]
+    pub accounts: Vec<Pubkey>,
+}
+
+impl SnapshotListing {
+    /// One partition per data length up to and including `listing_offset`,
+    /// then one per value of the byte at `listing_offset`.
+    pub fn num_partitions(&self) -> usize {
+        self.listing_offset + 1 + 256
+    }
+
+    /// The filter selecting the accounts of a partition.
+    pub fn partition_filter(&self, index: usize) -> RpcFilterType {
+        if index <= self.listing_offset {
+            RpcFilterType::DataSize(index as u64)
+        } else {
+            let byte = (index - self.listing_offset - 1) as u8;
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(self.listing_offset, vec![byte]))
+        }
+    }
+}
+
+/// Progress of a snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotCheckpoint {
+    pub completed_pages: BTreeSet<usize>,
+    /// Listed accounts that were closed before they could be fetched.
+    #[serde(with = "pubkey_vec")]
+    pub missing_accounts: Vec<Pubkey>,
+}
+
+/// Snapshots a program's accounts into a directory, which will contain `listing.json`
+/// until the accounts are listed, then `manifest.json`, `checkpoint.json`, and the
+/// account JSON files in `accounts/`.
+/// Load the accounts with [crate::LocalnetConfiguration::from_dir] on [accounts_dir].
+pub struct ProgramSnapshotter<'a> {
+    client: &'a RpcClient,
+    program_id: Pubkey,
+    filters: Vec<RpcFilterType>,
+    listing_offset: usize,
+    page_size: usize,
+    concurrency: usize,
+    max_retries: usize,
+    retry_backoff: Duration,
+}
+
+impl<'a> ProgramSnapshotter<'a> {
+    pub fn new(client: &'a RpcClient, program_id: Pubkey) -> Self {
+        Self {
+            client,
+            program_id,
+            filters: vec![],
+            listing_offset: DEFAULT_LISTING_OFFSET,
+            page_size: MAX_PAGE_SIZE,
+            concurrency: 8,
+            max_retries: 5,
+            retry_backoff: Duration::from_millis(500),
+        }
+    }
+
+    /// Only snapshot accounts that match these `getProgramAccounts` filters.
+    pub fn filters(mut self, filters: Vec<RpcFilterType>) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Accounts are listed with one `getProgramAccounts` call per value of the byte at this
+    /// offset, plus one per data length up to the offset for shorter accounts. Pick an offset
+    /// where the program's account data varies, [DEFAULT_LISTING_OFFSET] by default.
+    /// Ignored when resuming, in favor of the listing's offset.
+    pub fn listing_offset(mut self, listing_offset: usize) -> Self {
+        self.listing_offset = listing_offset;
+        self
+    }
+
+    /// Accounts per `getMultipleAccounts` request, at most [MAX_PAGE_SIZE].
+    /// Ignored when resuming, in favor of the manifest's page size.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        self
+    }
+
+    /// The number of partitions listed, or pages fetched, at once.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Retries per request, waiting `retry_backoff` and doubling the wait after each failure.
+    pub fn retries(mut self, max_retries: usize, retry_backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// Snapshot into `dir`, resuming from its checkpoint if it contains a manifest,
+    /// or from its listing if the accounts were still being listed.
+    pub fn snapshot(&self, dir: impl AsRef<Path>) -> Result<SnapshotManifest> {
+        let dir = dir.as_ref();
+        let accounts_dir = accounts_dir(dir);
+        fs::create_dir_all(&accounts_dir).map_err(|e| file_error(&accounts_dir, e))?;
+
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let manifest = if manifest_path.exists() {
+            let manifest: SnapshotManifest = read_json(&manifest_path)?;
+            self.check_program_id(&manifest_path, &manifest.program_id)?;
+            if manifest.page_size == 0 || manifest.page_size > MAX_PAGE_SIZE {
+                return Err(LocalnetConfigurationError::InvalidSnapshotManifest(
+                    manifest_path.display().to_string(),
+                    format!(
+                        "page size {} is not in 1..={}",
+                        manifest.page_size, MAX_PAGE_SIZE
+                    ),
+                ));
+            }
+            manifest
+        } else {
+            let manifest = self.list_accounts(dir)?;
+            write_json(&manifest_path, &manifest)?;
+            let listing_path = dir.join(LISTING_FILE);
+            fs::remove_file(&listing_path).map_err(|e| file_error(&listing_path, e))?;
+            manifest
+        };
+
+        let checkpoint_path = dir.join(CHECKPOINT_FILE);
+        let checkpoint: SnapshotCheckpoint = if checkpoint_path.exists() {
+            read_json(&checkpoint_path)?
+        } else {
+            SnapshotCheckpoint::default()
+        };
+        let pending: Vec<usize> = (0..manifest.num_pages())
+            .filter(|page| !checkpoint.completed_pages.contains(page))
+            .collect();
+
+        let checkpoint = Mutex::new(checkpoint);
+        self.run_concurrently(&pending, |page| {
+            let missing = self.fetch_page(manifest.page(page), &accounts_dir)?;
+            let mut checkpoint = checkpoint.lock().unwrap();
+            checkpoint.completed_pages.insert(page);
+            checkpoint.missing_accounts.extend(missing);
+            write_json(&checkpoint_path, &*checkpoint)
+        })?;
+        Ok(manifest)
+    }
+
+    /// Lists the accounts partition by partition, saving the listing after each one.
+    fn list_accounts(&self, dir: &Path) -> Result<SnapshotManifest> {
+        let listing_path = dir.join(LISTING_FILE);
+        let listing = if listing_path.exists() {
+            let listing: SnapshotListing = read_json(&listing_path)?;
+            self.check_program_id(&listing_path, &listing.program_id)?;
+            listing
+        } else {
+            SnapshotListing {
+                program_id: self.program_id,
+                slot: self.with_retries(|| self.client.get_slot())?,
+                listing_offset: self.listing_offset,
+                completed_partitions: BTreeSet::new(),
+                accounts: vec![],
+            }
+        };
+        let pending: Vec<usize> = (0..listing.num_partitions())
+            .filter(|partition| !listing.completed_partitions.contains(partition))
+            .collect();
+
+        let listing = Mutex::new(listing);
+        self.run_concurrently(&pending, |partition| {
+            let filter = listing.lock().unwrap().partition_filter(partition);
+            let accounts = self.list_partition(filter)?;
+            let mut listing = listing.lock().unwrap();
+            listing.completed_partitions.insert(partition);
+            listing.accounts.extend(accounts);
+            write_json(&listing_path, &*listing)
+        })?;
+
+        let listing = listing.into_inner().unwrap();
+        let mut accounts = listing.accounts;
+        accounts.sort();
+        accounts.dedup();
+        Ok(SnapshotManifest {
+            program_id: self.program_id,
+            slot: listing.slot,
+            page_size: self.page_size,
+            accounts,
+        })
+    }
+
+    fn list_partition(&self, partition_filter: RpcFilterType) -> Result<Vec<Pubkey>> {
+        let mut filters = self.filters.clone();
+        filters.push(partition_filter);
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                data_slice: Some(UiDataSliceConfig {
+                    offset: 0,
+                    length: 0,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        Ok(self
+            .with_retries(|| {
+                self.client
+                    .get_program_accounts_with_config(&self.program_id, config.clone())
+            })?
+            .into_iter()
+            .map(|(pubkey, _)| pubkey)
+            .collect())
+    }
+
+    /// Runs `f` on each of `pending` on up to `concurrency` threads, stopping at the first error.
+    fn run_concurrently(
+        &self,
+        pending: &[usize],
+        f: impl Fn(usize) -> Result<()> + Sync,
+    ) -> Result<()> {
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let error: Mutex<Option<LocalnetConfigurationError>> = Mutex::new(None);
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency.min(pending.len()) {
+                scope.spawn(|| {
+                    while !failed.load(Ordering::Relaxed) {
+                        let Some(index) = pending.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                            break;
+                        };
+                        if let Err(e) = f(*index) {
+                            failed.store(true, Ordering::Relaxed);
+                            error.lock().unwrap().get_or_insert(e);
+                        }
+                    }
+                });
+            }
+        });
+        match error.into_inner().unwrap() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// A snapshot directory belongs to one program.
+    fn check_program_id(&self, path: &Path, program_id: &Pubkey) -> Result<()> {
+        if *program_id != self.program_id {
+            return Err(LocalnetConfigurationError::SnapshotManifestMismatch(
+                path.display().to_string(),
+                program_id.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes the page's accounts, and returns those that no longer exist.
+    fn fetch_page(&self, pubkeys: &[Pubkey], accounts_dir: &Path) -> Result<Vec<Pubkey>> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        };
+        let accounts: Vec<Option<Account>> = self
+            .with_retries(|| {
+                self.client
+                    .get_multiple_accounts_with_config(pubkeys, config.clone())
+            })?
+            .value;
+        let path_prefix = accounts_dir.display().to_string();
+        let mut missing = vec![];
+        for (pubkey, account) in pubkeys.iter().zip(accounts) {
+            match account {
+                Some(account) => {
+                    let mut account = LocalnetAccount::new_from_readable_account(*pubkey, account);
+                    account.name = format!("{}.json", pubkey);
+                    // A page may have been partially written before an interruption.
+                    account.write_to_validator_json_file(&path_prefix, true)?;
+                }
+                None => missing.push(*pubkey),
+            }
+        }
+        Ok(missing)
+    }
+
+    fn with_retries<T>(&self, f: impl Fn() -> solana_client::client_error::Result<T>) -> Result<T> {
+        let mut backoff = self.retry_backoff;
+        let mut retries = 0;
+        loop {
+            match f() {
+                Ok(value) => return Ok(value),
+                Err(_) if retries < self.max_retries => {
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    retries += 1;
+                }
+                Err(e) => return Err(LocalnetConfigurationError::ClonedAccountRpcError(e)),
+            }
+        }
+    }
+}
+
+/// The directory of account JSON files within a snapshot directory.
+pub fn accounts_dir(snapshot_dir: impl AsRef<Path>) -> PathBuf {
+    snapshot_dir.as_ref().join(ACCOUNTS_DIR)
+}
+
+fn file_error(path: &Path, e: std::io::Error) -> LocalnetConfigurationError {
+    LocalnetConfigurationError::FileReadWriteError(path.display().to_string(), e)
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    let file = File::open(path).map_err(|e| file_error(path, e))?;
+    serde_json::from_reader(file).map_err(|e| {
+        LocalnetConfigurationError::SerdeFileReadWriteFailure(path.display().to_string(), e)
+    })
+}
+
+/// Writes to a temporary file first, so an interruption never leaves a truncated file.
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    let file = File::create(&tmp).map_err(|e| file_error(&tmp, e))?;
+    serde_json::to_writer(file, value).map_err(|e| {
+        LocalnetConfigurationError::SerdeFileReadWriteFailure(tmp.display().to_string(), e)
+    })?;
+    fs::rename(&tmp, path).map_err(|e| file_error(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_account_decoder::UiAccount;
+    use solana_client::client_error::{ClientError, Result as ClientResult};
+    use solana_client::rpc_client::RpcClientConfig;
+    use solana_client::rpc_request::{RpcError, RpcRequest};
+    use solana_client::rpc_response::{Response, RpcKeyedAccount, RpcResponseContext};
+    use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+    use solana_sdk::account::AccountSharedData;
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    /// A node that answers `getProgramAccounts` by its filters, and `getMultipleAccounts`
+    /// with every account but the `closed` ones.
+    struct FakeNode {
+        accounts: Vec<(Pubkey, Account)>,
+        closed: Vec<Pubkey>,
+        listing_calls: Arc<AtomicUsize>,
+        /// Fail every `getProgramAccounts` call after this many.
+        max_listing_calls: usize,
+    }
+
+    impl FakeNode {
+        fn client(
+            accounts: &[(Pubkey, Account)],
+            closed: &[Pubkey],
+            max_listing_calls: usize,
+        ) -> (RpcClient, Arc<AtomicUsize>) {
+            let listing_calls = Arc::new(AtomicUsize::new(0));
+            let node = FakeNode {
+                accounts: accounts.to_vec(),
+                closed: closed.to_vec(),
+                listing_calls: listing_calls.clone(),
+                max_listing_calls,
+            };
+            let config = RpcClientConfig::with_commitment(CommitmentConfig::default());
+            (RpcClient::new_sender(node, config), listing_calls)
+        }
+
+        fn list(&self, config: RpcProgramAccountsConfig) -> Vec<RpcKeyedAccount> {
+            let data_slice = config.account_config.data_slice;
+            self.accounts
+                .iter()
+                .filter(|(_, account)| {
+                    let account = AccountSharedData::from(account.clone());
+                    let filters = config.filters.as_deref().unwrap_or_default();
+                    filters.iter().all(|filter| filter.allows(&account))
+                })
+                .map(|(address, account)| RpcKeyedAccount {
+                    pubkey: address.to_string(),
+                    account: UiAccount::encode(
+                        address,
+                        account,
+                        UiAccountEncoding::Base64,
+                        None,
+                        data_slice,
+                    ),
+                })
+                .collect()
+        }
+
+        fn fetch(&self, addresses: Vec<String>) -> Vec<Option<UiAccount>> {
+            addresses
+                .iter()
+                .map(|address| Pubkey::from_str(address).unwrap())
+                .map(|address| {
+                    let (_, account) = self.accounts.iter().find(|(a, _)| *a == address)?;
+                    (!self.closed.contains(&address)).then(|| ui_account(&address, account))
+                })
+                .collect()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl RpcSender for FakeNode {
+        async fn send(
+            &self,
+            request: RpcRequest,
+            params: serde_json::Value,
+        ) -> ClientResult<serde_json::Value> {
+            let value = match request {
+                RpcRequest::GetVersion => serde_json::json!({"solana-core": "1.17.13"}),
+                RpcRequest::GetSlot => serde_json::json!(42),
+                RpcRequest::GetProgramAccounts => {
+                    if self.listing_calls.fetch_add(1, Ordering::Relaxed) >= self.max_listing_calls
+                    {
+                        return Err(ClientError::from(RpcError::RpcRequestError(
+                            "node is down".to_string(),
+                        )));
+                    }
+                    let config = serde_json::from_value(params[1].clone()).unwrap();
+                    serde_json::to_value(self.list(config)).unwrap()
+                }
+                RpcRequest::GetMultipleAccounts => {
+                    let addresses = serde_json::from_value(params[0].clone()).unwrap();
+                    serde_json::to_value(Response {
+                        context: RpcResponseContext {
+                            slot: 42,
+                            api_version: None,
+                        },
+                        value: self.fetch(addresses),
+                    })
+                    .unwrap()
+                }
+                _ => panic!("unexpected request {}", request),
+            };
+            Ok(value)
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "fake".to_string()
+        }
+    }
+
+    fn ui_account(address: &Pubkey, account: &Account) -> UiAccount {
+        UiAccount::encode(address, account, UiAccountEncoding::Base64, None, None)
+    }
+
+    /// Accounts shorter than the listing offset, and accounts differing at the offset.
+    fn program_accounts(program_id: Pubkey) -> Vec<(Pubkey, Account)> {
+        let account = |data: Vec<u8>| Account {
+            lamports: 1,
+            data,
+            owner: program_id,
+            ..Default::default()
+        };
+        let mut accounts = vec![
+            (Pubkey::new_unique(), account(vec![])),
+            (Pubkey::new_unique(), account(vec![1, 2, 3])),
+        ];
+        for byte in [0, 1, 1, 255] {
+            let mut data = vec![0; 16];
+            data[DEFAULT_LISTING_OFFSET] = byte;
+            accounts.push((Pubkey::new_unique(), account(data)));
+        }
+        accounts.sort_by_key(|(address, _)| *address);
+        accounts
+    }
+
+    fn snapshot_dir(program_id: &Pubkey) -> PathBuf {
+        std::env::temp_dir().join(format!("program-snapshot-{}", program_id))
+    }
+
+    #[test]
+    fn listings_are_partitioned_and_resumed() {
+        let program_id = Pubkey::new_unique();
+        let accounts = program_accounts(program_id);
+        let addresses: Vec<Pubkey> = accounts.iter().map(|(address, _)| *address).collect();
+        let closed = [addresses[1]];
+        let dir = snapshot_dir(&program_id);
+
+        // The node goes down partway through the listing.
+        let (client, _) = FakeNode::client(&accounts, &closed, 100);
+        let snapshotter = ProgramSnapshotter::new(&client, program_id)
+            .concurrency(1)
+            .retries(0, Duration::ZERO);
+        assert!(matches!(
+            snapshotter.snapshot(&dir),
+            Err(LocalnetConfigurationError::ClonedAccountRpcError(_))
+        ));
+        assert!(!dir.join(MANIFEST_FILE).exists());
+        let listing: SnapshotListing = read_json(&dir.join(LISTING_FILE)).unwrap();
+        assert_eq!(listing.slot, 42);
+        assert_eq!(listing.completed_partitions, (0..100).collect());
+
+        // Resuming only lists the remaining partitions.
+        let (client, listing_calls) = FakeNode::client(&accounts, &closed, usize::MAX);
+        let snapshotter = ProgramSnapshotter::new(&client, program_id)
+            .page_size(2)
+            .retries(0, Duration::ZERO);
+        let manifest = snapshotter.snapshot(&dir).unwrap();
+        assert_eq!(
+            listing_calls.load(Ordering::Relaxed),
+            listing.num_partitions() - 100
+        );
+        assert!(!dir.join(LISTING_FILE).exists());
+        assert_eq!(manifest.slot, 42);
+        assert_eq!(manifest.accounts, addresses);
+        assert_eq!(manifest.num_pages(), 3);
+
+        let checkpoint: SnapshotCheckpoint = read_json(&dir.join(CHECKPOINT_FILE)).unwrap();
+        assert_eq!(checkpoint.completed_pages, BTreeSet::from([0, 1, 2]));
+        assert_eq!(checkpoint.missing_accounts, closed.to_vec());
+        let loaded = crate::LocalnetConfiguration::from_dir(accounts_dir(&dir)).unwrap();
+        assert_eq!(loaded.accounts.len(), accounts.len() - 1);
+        let written =
+            LocalnetAccount::new_from_readable_account(addresses[5], accounts[5].1.clone());
+        assert_eq!(loaded.accounts[&addresses[5]].data, written.data);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pages_are_resumed_from_the_checkpoint() {
+        let program_id = Pubkey::new_unique();
+        let accounts = program_accounts(program_id);
+        let addresses: Vec<Pubkey> = accounts.iter().map(|(address, _)| *address).collect();
+        let dir = snapshot_dir(&program_id);
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = SnapshotManifest {
+            program_id,
+            slot: 42,
+            page_size: 4,
+            accounts: addresses.clone(),
+        };
+        write_json(&dir.join(MANIFEST_FILE), &manifest).unwrap();
+        write_json(
+            &dir.join(CHECKPOINT_FILE),
+            &SnapshotCheckpoint {
+                completed_pages: BTreeSet::from([0]),
+                missing_accounts: vec![addresses[1]],
+            },
+        )
+        .unwrap();
+
+        // Resuming only fetches the pages missing from the checkpoint, without listing again.
+        let (client, _) = FakeNode::client(&accounts, &[], 0);
+        let snapshotter = ProgramSnapshotter::new(&client, program_id).retries(0, Duration::ZERO);
+        assert_eq!(snapshotter.snapshot(&dir).unwrap(), manifest);
+        let checkpoint: SnapshotCheckpoint = read_json(&dir.join(CHECKPOINT_FILE)).unwrap();
+        assert_eq!(checkpoint.completed_pages, BTreeSet::from([0, 1]));
+        assert_eq!(checkpoint.missing_accounts, vec![addresses[1]]);
+        for address in &addresses[..4] {
+            assert!(!accounts_dir(&dir)
+                .join(format!("{}.json", address))
+                .exists());
+        }
+        for address in &addresses[4..] {
+            assert!(accounts_dir(&dir)
+                .join(format!("{}.json", address))
+                .exists());
+        }
+
+        // A snapshot directory belongs to one program.
+        let other = ProgramSnapshotter::new(&client, Pubkey::new_unique());
+        assert!(matches!(
+            other.snapshot(&dir),
+            Err(LocalnetConfigurationError::SnapshotManifestMismatch(..))
+        ));
+
+        // A manifest with no page size is rejected rather than divided by.
+        write_json(
+            &dir.join(MANIFEST_FILE),
+            &SnapshotManifest {
+                page_size: 0,
+                ..manifest
+            },
+        )
+        .unwrap();
+        assert!(matches!(
+            snapshotter.snapshot(&dir),
+            Err(LocalnetConfigurationError::InvalidSnapshotManifest(..))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
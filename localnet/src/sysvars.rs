@@ -0,0 +1,115 @@
+//! Sysvar overrides for a [crate::LocalnetConfiguration], so that a localnet
+//! can start at a chosen slot, timestamp, rent or epoch schedule.
+use crate::LocalnetAccount;
+use serde::Serialize;
+use solana_sdk::clock::Clock;
+use solana_sdk::epoch_schedule::EpochSchedule;
+use solana_sdk::rent::Rent;
+use solana_sdk::sysvar::{self, Sysvar, SysvarId};
+
+/// Sysvars to write as account JSON files, and translate into
+/// `solana-test-validator` args where it has one.
+#[derive(Debug, Clone, Default)]
+pub struct SysvarOverrides {
+    /// A non-zero slot is passed as `--warp-slot`.
+    pub clock: Option<Clock>,
+    pub rent: Option<Rent>,
+    /// Passed as `--slots-per-epoch`.
+    pub epoch_schedule: Option<EpochSchedule>,
+}
+
+impl SysvarOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.clock.is_none() && self.rent.is_none() && self.epoch_schedule.is_none()
+    }
+
+    /// The overridden sysvars as accounts, named e.g. `sysvar_clock.json`.
+    pub fn accounts(&self) -> Vec<LocalnetAccount> {
+        let mut accounts = vec![];
+        if let Some(clock) = &self.clock {
+            accounts.push(sysvar_account(clock, "clock"));
+        }
+        if let Some(rent) = &self.rent {
+            accounts.push(sysvar_account(rent, "rent"));
+        }
+        if let Some(epoch_schedule) = &self.epoch_schedule {
+            accounts.push(sysvar_account(epoch_schedule, "epoch_schedule"));
+        }
+        accounts
+    }
+
+    /// `solana-test-validator` args with the same effect as the overrides, where it has them.
+    pub fn test_validator_args(&self) -> Vec<(String, String)> {
+        let mut args = vec![];
+        if let Some(clock) = self.clock.as_ref().filter(|clock| clock.slot > 0) {
+            args.push(("--warp-slot".to_string(), clock.slot.to_string()));
+        }
+        if let Some(epoch_schedule) = &self.epoch_schedule {
+            args.push((
+                "--slots-per-epoch".to_string(),
+                epoch_schedule.slots_per_epoch.to_string(),
+            ));
+        }
+        args
+    }
+}
+
+fn sysvar_account<T: Sysvar + SysvarId + Serialize>(value: &T, name: &str) -> LocalnetAccount {
+    let mut data = vec![0; T::size_of()];
+    bincode::serialize_into(&mut data[..], value).unwrap();
+    LocalnetAccount::new_raw(T::id(), format!("sysvar_{}.json", name), data)
+        .owner(sysvar::ID)
+        .lamports(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_become_accounts_and_args() {
+        let mut overrides = SysvarOverrides::default();
+        assert!(overrides.is_empty());
+        assert!(overrides.accounts().is_empty());
+
+        overrides.clock = Some(Clock::default());
+        overrides.rent = Some(Rent {
+            lamports_per_byte_year: 1,
+            ..Default::default()
+        });
+        // A zero slot does not need a warp.
+        assert!(overrides.test_validator_args().is_empty());
+
+        overrides.clock = Some(Clock {
+            slot: 1_000,
+            unix_timestamp: 1_700_000_000,
+            ..Default::default()
+        });
+        overrides.epoch_schedule = Some(EpochSchedule::custom(64, 64, false));
+        assert_eq!(
+            overrides.test_validator_args(),
+            vec![
+                ("--warp-slot".to_string(), "1000".to_string()),
+                ("--slots-per-epoch".to_string(), "64".to_string()),
+            ]
+        );
+
+        let accounts = overrides.accounts();
+        let names: Vec<&str> = accounts.iter().map(|act| act.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "sysvar_clock.json",
+                "sysvar_rent.json",
+                "sysvar_epoch_schedule.json"
+            ]
+        );
+        assert!(accounts.iter().all(|act| act.owner == sysvar::ID));
+        assert_eq!(accounts[0].address, sysvar::clock::ID);
+        let clock: Clock = bincode::deserialize(&accounts[0].data).unwrap();
+        assert_eq!(clock, overrides.clock.unwrap());
+        assert_eq!(accounts[1].data.len(), Rent::size_of());
+        let rent: Rent = bincode::deserialize(&accounts[1].data).unwrap();
+        assert_eq!(rent.lamports_per_byte_year, 1);
+    }
+}
@@ -0,0 +1,212 @@
+//! Run a `solana-test-validator` for the lifetime of a [TestValidatorHandle],
+//! e.g. to drive integration tests from Rust.
+use crate::error::{LocalnetConfigurationError, Result};
+use crate::LocalnetConfiguration;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::fs::File;
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The `solana-test-validator` default RPC port.
+pub const DEFAULT_RPC_PORT: u16 = 8899;
+
+/// Starts a `solana-test-validator` with the accounts and programs of a
+/// [LocalnetConfiguration], and waits until its RPC endpoint is healthy.
+pub struct TestValidatorLauncher<'a> {
+    config: &'a LocalnetConfiguration,
+    additional_args: Vec<String>,
+    json_outdir: Option<String>,
+    rpc_port: Option<u16>,
+    log_file: Option<PathBuf>,
+    timeout: Duration,
+    poll_interval: Duration,
+}
+
+impl<'a> TestValidatorLauncher<'a> {
+    pub fn new(config: &'a LocalnetConfiguration) -> Self {
+        Self {
+            config,
+            additional_args: vec![],
+            json_outdir: None,
+            rpc_port: None,
+            log_file: None,
+            timeout: Duration::from_secs(60),
+            poll_interval: Duration::from_millis(200),
+        }
+    }
+
+    /// Additional `solana-test-validator` args, e.g. `--reset`.
+    pub fn args(mut self, args: impl IntoIterator<Item = String>) -> Self {
+        self.additional_args.extend(args);
+        self
+    }
+
+    /// Directory of the account JSON files, if [LocalnetConfiguration::json_outdir] is not set.
+    pub fn json_outdir(mut self, json_outdir: &str) -> Self {
+        self.json_outdir = Some(json_outdir.to_string());
+        self
+    }
+
+    /// Serve RPC on `rpc_port`, and websockets on the port after it.
+    pub fn rpc_port(mut self, rpc_port: u16) -> Self {
+        self.rpc_port = Some(rpc_port);
+        self
+    }
+
+    /// Write the validator's logs to a file. Otherwise they are discarded.
+    pub fn log_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.log_file = Some(path.into());
+        self
+    }
+
+    /// How long to wait for the RPC endpoint to become healthy, one minute by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Spawn the validator, and wait until `getHealth` succeeds.
+    /// The validator is killed if it does not become healthy in time.
+    pub fn start(self) -> Result<TestValidatorHandle> {
+        if self.json_outdir.is_none() && self.config.json_outdir.is_none() {
+            return Err(LocalnetConfigurationError::NoOutputDirectory);
+        }
+        let mut additional_args = self.additional_args;
+        if let Some(port) = self.rpc_port {
+            additional_args.extend(["--rpc-port".to_string(), port.to_string()]);
+        }
+        let (stdout, stderr) = match &self.log_file {
+            Some(path) => {
+                let file = File::create(path).map_err(|e| {
+                    LocalnetConfigurationError::FileReadWriteError(path.display().to_string(), e)
+                })?;
+                let file_err = file.try_clone().map_err(|e| {
+                    LocalnetConfigurationError::FileReadWriteError(path.display().to_string(), e)
+                })?;
+                // Log to stderr instead of a file in the ledger directory.
+                additional_args.push("--log".to_string());
+                (Stdio::from(file), Stdio::from(file_err))
+            }
+            None => (Stdio::null(), Stdio::null()),
+        };
+        let args = self
+            .config
+            .test_validator_cli_args(additional_args, self.json_outdir.as_deref());
+        let rpc_port = rpc_port_from_args(&args);
+        let child = std::process::Command::new("solana-test-validator")
+            .args(args)
+            .stdout(stdout)
+            .stderr(stderr)
+            .spawn()
+            .map_err(LocalnetConfigurationError::TestValidatorSpawnError)?;
+        let mut handle = TestValidatorHandle { child, rpc_port };
+        handle.wait_until_healthy(self.timeout, self.poll_interval)?;
+        Ok(handle)
+    }
+}
+
+/// A running `solana-test-validator`, which is killed when the handle is dropped.
+pub struct TestValidatorHandle {
+    child: Child,
+    rpc_port: u16,
+}
+
+impl TestValidatorHandle {
+    pub fn rpc_url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.rpc_port)
+    }
+
+    /// The websocket URL, which the validator serves on the port after the RPC port.
+    pub fn ws_url(&self) -> String {
+        format!("ws://127.0.0.1:{}", self.rpc_port + 1)
+    }
+
+    /// An RPC client for the validator, at `confirmed` commitment.
+    pub fn rpc_client(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.rpc_url(), CommitmentConfig::confirmed())
+    }
+
+    pub fn child(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    fn wait_until_healthy(&mut self, timeout: Duration, poll_interval: Duration) -> Result<()> {
+        let client = self.rpc_client();
+        let start = Instant::now();
+        loop {
+            // Probe before checking the child, so that a healthy response from
+            // another validator on the same port is not mistaken for this one.
+            let healthy = client.get_health().is_ok();
+            if let Ok(Some(status)) = self.child.try_wait() {
+                return Err(LocalnetConfigurationError::TestValidatorExited(
+                    status.code(),
+                ));
+            }
+            if healthy {
+                return Ok(());
+            }
+            if start.elapsed() > timeout {
+                return Err(LocalnetConfigurationError::TestValidatorTimeout(timeout));
+            }
+            thread::sleep(poll_interval);
+        }
+    }
+}
+
+impl Drop for TestValidatorHandle {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The last `--rpc-port` among the args, or the default port.
+fn rpc_port_from_args(args: &[String]) -> u16 {
+    args.windows(2)
+        .rev()
+        .find(|pair| pair[0] == "--rpc-port")
+        .and_then(|pair| pair[1].parse().ok())
+        .unwrap_or(DEFAULT_RPC_PORT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpc_port_is_the_last_one_passed() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(rpc_port_from_args(&[]), DEFAULT_RPC_PORT);
+        assert_eq!(
+            rpc_port_from_args(&args(&["--reset", "--rpc-port", "9000"])),
+            9000
+        );
+        assert_eq!(
+            rpc_port_from_args(&args(&["--rpc-port", "9000", "--rpc-port", "9100"])),
+            9100
+        );
+        // A missing or invalid value falls back to the default.
+        assert_eq!(rpc_port_from_args(&args(&["--rpc-port"])), DEFAULT_RPC_PORT);
+        assert_eq!(
+            rpc_port_from_args(&args(&["--rpc-port", "port"])),
+            DEFAULT_RPC_PORT
+        );
+    }
+
+    #[test]
+    fn exited_validators_are_reported() {
+        let child = std::process::Command::new("sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+        // Nothing listens on port 1, so only the child's exit ends the wait.
+        let mut handle = TestValidatorHandle { child, rpc_port: 1 };
+        assert!(matches!(
+            handle.wait_until_healthy(Duration::from_secs(10), Duration::from_millis(10)),
+            Err(LocalnetConfigurationError::TestValidatorExited(Some(3)))
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Expr, ExprLit, Fields, Lit, LitInt, Meta, Result};
+
+struct ErrorCodesAttrs {
+    offset: u32,
+    anchor: bool,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let attrs = parse_enum_attrs(&input)?;
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ErrorCodes can only be derived for enums",
+        ));
+    };
+
+    let mut variants = vec![];
+    let mut codes = vec![];
+    let mut messages = vec![];
+    let mut next_discriminant: u32 = 0;
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "ErrorCodes variants cannot have fields",
+            ));
+        }
+        let discriminant = match &variant.discriminant {
+            Some((_, expr)) => parse_discriminant(expr)?,
+            None => next_discriminant,
+        };
+        next_discriminant = discriminant.saturating_add(1);
+        let code = attrs
+            .offset
+            .checked_add(discriminant)
+            .ok_or_else(|| syn::Error::new_spanned(variant, "error code does not fit in a u32"))?;
+        variants.push(&variant.ident);
+        codes.push(code);
+        messages.push(doc_message(&variant.attrs).unwrap_or_else(|| variant.ident.to_string()));
+    }
+
+    let name = &input.ident;
+    let count = variants.len();
+    // Anchor's `#[error_code]` already implements these.
+    let conversion_impls = (!attrs.anchor).then(|| {
+        quote! {
+            impl ::core::convert::From<#name> for u32 {
+                fn from(error: #name) -> u32 {
+                    match error {
+                        #(#name::#variants => #codes,)*
+                    }
+                }
+            }
+
+            impl ::core::fmt::Display for #name {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    let message = match self {
+                        #(#name::#variants => #messages,)*
+                    };
+                    f.write_str(message)
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #name {
+            /// Every variant, in declaration order.
+            pub const VARIANTS: [#name; #count] = [#(#name::#variants),*];
+        }
+
+        impl ::core::convert::TryFrom<u32> for #name {
+            type Error = u32;
+
+            fn try_from(code: u32) -> ::core::result::Result<Self, u32> {
+                match code {
+                    #(#codes => ::core::result::Result::Ok(#name::#variants),)*
+                    _ => ::core::result::Result::Err(code),
+                }
+            }
+        }
+
+        #conversion_impls
+
+        impl ::solana_devtools_errors::ErrorCause for #name {}
+    })
+}
+
+/// Parses `#[error_codes(offset = <n>)]` and `#[error_codes(anchor)]`.
+fn parse_enum_attrs(input: &DeriveInput) -> Result<ErrorCodesAttrs> {
+    let mut offset = None;
+    let mut anchor = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("error_codes"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("offset") {
+                let value: LitInt = meta.value()?.parse()?;
+                offset = Some(value.base10_parse()?);
+                Ok(())
+            } else if meta.path.is_ident("anchor") {
+                anchor = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `offset` or `anchor`"))
+            }
+        })?;
+    }
+    let default_offset = if anchor { 6000 } else { 0 };
+    Ok(ErrorCodesAttrs {
+        offset: offset.unwrap_or(default_offset),
+        anchor,
+    })
+}
+
+fn parse_discriminant(expr: &Expr) -> Result<u32> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse(),
+        _ => Err(syn::Error::new_spanned(
+            expr,
+            "ErrorCodes discriminants must be integer literals",
+        )),
+    }
+}
+
+/// The variant's doc comment lines, trimmed and joined with spaces.
+fn doc_message(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
@@ -2,13 +2,15 @@ extern crate core;
 extern crate proc_macro;
 
 mod const_data;
+mod error_codes;
+mod transaction_schema;
 
 use const_data::{ConstValue, StructFields};
 
 use proc_macro::TokenStream;
 use proc_macro2::Ident;
 use quote::quote;
-use solana_devtools_pubkey::get_named_pubkey;
+use solana_devtools_pubkey::{get_named_pubkey, registry};
 use syn::{parse_macro_input, DeriveInput, LitStr, Token};
 
 /// Creates a fake base58 public key via the solana_sdk::pubkey! proc macro, padding
@@ -30,14 +32,34 @@ use syn::{parse_macro_input, DeriveInput, LitStr, Token};
 /// A valid base58 address is anywhere between 32 and 44 chars specified on the
 /// [Solana CLI Docs](https://docs.solana.com/cli/transfer-tokens). The minimum length
 /// pubkey that will be produced is 32.
+///
+/// If the `SOLANA_DEVTOOLS_NAMED_PUBKEY_REGISTRY` environment variable is set to a directory,
+/// each invocation is recorded there, and compilation fails if two different names produce
+/// the same pubkey. See `solana_devtools_pubkey::registry`.
 #[proc_macro]
 pub fn named_pubkey(input: TokenStream) -> TokenStream {
     let input_str = parse_macro_input!(input as LitStr);
     let input_value = input_str.value();
 
     // Generate the fake public key string
-    match get_named_pubkey(input_value) {
+    match get_named_pubkey(input_value.clone()) {
         Ok(key) => {
+            if let Some(dir) = registry::registry_dir() {
+                let message = match registry::register_named_pubkey(&dir, &input_value, &key) {
+                    Ok(None) => None,
+                    Ok(Some(collision)) => Some(collision.to_string()),
+                    Err(e) => Some(format!(
+                        "failed to register named pubkey in {}: {}",
+                        dir.display(),
+                        e
+                    )),
+                };
+                if let Some(message) = message {
+                    return syn::Error::new(input_str.span(), message)
+                        .to_compile_error()
+                        .into();
+                }
+            }
             let bytes = key.to_bytes();
             let expanded = quote! {
                 Pubkey::new_from_array([#(#bytes,)*])
@@ -50,6 +72,12 @@ pub fn named_pubkey(input: TokenStream) -> TokenStream {
     }
 }
 
+/// Declares a constant of the struct for each `"NAME", field values...;` entry, in field order.
+/// A `#[code]` field of type `u32` is set to the entry's index, and a `#[name]` field
+/// of type `&'static str` to its name.
+///
+/// Also generates `NUM_CONSTS`, an `ALL` array of the constants in order,
+/// `from_name(&str) -> Option<Self>` and, with a `#[code]` field, `from_code(u32) -> Option<Self>`.
 #[proc_macro_attribute]
 pub fn const_data(attr: TokenStream, item: TokenStream) -> TokenStream {
     let const_values = parse_macro_input!(attr with syn::punctuated::Punctuated::<ConstValue, Token![;]>::parse_terminated);
@@ -99,9 +127,39 @@ pub fn const_data(attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 
     let consts_count_name = Ident::new("NUM_CONSTS", proc_macro2::Span::call_site());
+    let const_names = const_values
+        .iter()
+        .map(|const_value| Ident::new(&const_value.name.value(), const_value.name.span()))
+        .collect::<Vec<_>>();
+    let name_strs = const_values
+        .iter()
+        .map(|const_value| const_value.name.value())
+        .collect::<Vec<_>>();
+    let from_code = code_field.as_ref().map(|_| {
+        let codes = (0..count as u32).collect::<Vec<_>>();
+        quote! {
+            #vis const fn from_code(code: u32) -> Option<Self> {
+                match code {
+                    #(#codes => Some(#const_names),)*
+                    _ => None,
+                }
+            }
+        }
+    });
     let count_const = quote! {
         impl #typename {
             #vis const #consts_count_name: usize = #count;
+
+            #vis const ALL: [Self; #count] = [#(#const_names),*];
+
+            #vis fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    #(#name_strs => Some(#const_names),)*
+                    _ => None,
+                }
+            }
+
+            #from_code
         }
     };
 
@@ -111,3 +169,70 @@ pub fn const_data(attr: TokenStream, item: TokenStream) -> TokenStream {
         #count_const
     })
 }
+
+/// Implements `Into<Vec<Instruction>>` for a struct, and therefore `solana_devtools_tx::TransactionSchema`,
+/// by concatenating the instructions produced by each of its fields, by default in declaration order.
+/// Use through its re-export from `solana_devtools_tx`.
+///
+/// Fields of type `Instruction` contribute themselves, and fields of any other type must
+/// implement `Into<Vec<Instruction>>`. Fields of type `Option<T>` contribute nothing when `None`.
+///
+/// Attributes:
+/// - `#[schema(order = <n>)]` on a field sets its sort key to `n`. Fields are concatenated
+///   in ascending order of their sort keys, where the key of a field without an explicit order
+///   is its zero-based index among all of the struct's fields, skipped ones included.
+///   Fields with equal keys keep their declaration order, so `order = 0` on a later field
+///   still comes after the first declared field, unless that field is given a higher order.
+/// - `#[schema(skip)]` on a field leaves it out of the instructions.
+/// - `#[schema(by_ref)]` on a `Clone` struct also implements the conversion for `&Self`.
+///
+/// ```ignore
+/// #[derive(Clone, TransactionSchema)]
+/// #[schema(by_ref)]
+/// // Concatenates `create_ata`, with key 1, before `deposit`, with key 2.
+/// struct Deposit {
+///     #[schema(order = 2)]
+///     deposit: Instruction,
+///     create_ata: Option<Instruction>,
+///     #[schema(skip)]
+///     label: String,
+/// }
+/// ```
+#[proc_macro_derive(TransactionSchema, attributes(schema))]
+pub fn transaction_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    transaction_schema::expand(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Implements `TryFrom<u32>`, `From<Self> for u32`, `Display` and
+/// `solana_devtools_errors::ErrorCause` for a fieldless enum of error codes,
+/// along with a `VARIANTS` constant listing every variant in declaration order.
+/// Use through its re-export from `solana_devtools_errors`.
+///
+/// Each variant's code is its discriminant plus an offset, and its `Display` message
+/// is its doc comment, or else its name.
+///
+/// Attributes:
+/// - `#[error_codes(offset = <n>)]` adds `n` to every discriminant.
+/// - `#[error_codes(anchor)]` is for enums declared with Anchor's `#[error_code]`,
+///   which already implements `From<Self> for u32` and `Display`. The offset defaults
+///   to Anchor's custom error offset of 6000.
+///
+/// ```ignore
+/// #[derive(Debug, Clone, Copy, ErrorCodes)]
+/// #[error_codes(offset = 100)]
+/// enum VaultError {
+///     /// Insufficient funds
+///     InsufficientFunds,
+///     Locked = 5,
+/// }
+/// ```
+#[proc_macro_derive(ErrorCodes, attributes(error_codes))]
+pub fn error_codes(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    error_codes::expand(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
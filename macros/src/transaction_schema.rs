@@ -0,0 +1,183 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, GenericArgument, Index, LitInt, PathArguments, Result, Type};
+
+/// How a field is turned into instructions, decided by its declared type.
+enum FieldKind {
+    Instruction,
+    Other,
+}
+
+struct SchemaField {
+    member: TokenStream,
+    optional: bool,
+    kind: FieldKind,
+    order: usize,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let by_ref = parse_struct_attrs(&input)?;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "TransactionSchema can only be derived for structs",
+        ));
+    };
+
+    let mut fields = vec![];
+    let members: Vec<(TokenStream, &syn::Field)> = match &data.fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|f| {
+                let ident = f.ident.as_ref().unwrap();
+                (quote!(#ident), f)
+            })
+            .collect(),
+        Fields::Unnamed(unnamed) => unnamed
+            .unnamed
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let index = Index::from(i);
+                (quote!(#index), f)
+            })
+            .collect(),
+        Fields::Unit => vec![],
+    };
+    for (position, (member, field)) in members.into_iter().enumerate() {
+        let Some(order) = parse_field_attrs(field, position)? else {
+            continue;
+        };
+        let (optional, ty) = match option_inner_type(&field.ty) {
+            Some(inner) => (true, inner),
+            None => (false, &field.ty),
+        };
+        let kind = if is_instruction(ty) {
+            FieldKind::Instruction
+        } else {
+            FieldKind::Other
+        };
+        fields.push(SchemaField {
+            member,
+            optional,
+            kind,
+            order,
+        });
+    }
+    // Stable, so that fields without an explicit order stay in declaration order.
+    fields.sort_by_key(|f| f.order);
+
+    let instruction = quote!(::solana_devtools_tx::__private::Instruction);
+    let extends = fields.iter().map(|field| {
+        let member = &field.member;
+        let value = quote!(value);
+        let convert = match field.kind {
+            FieldKind::Instruction => quote!(instructions.push(#value)),
+            FieldKind::Other => quote!(instructions.extend(
+                ::core::convert::Into::<::std::vec::Vec<#instruction>>::into(#value)
+            )),
+        };
+        if field.optional {
+            quote! {
+                if let ::core::option::Option::Some(#value) = self.#member {
+                    #convert;
+                }
+            }
+        } else {
+            quote! {
+                let #value = self.#member;
+                #convert;
+            }
+        }
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let by_ref_impl = by_ref.then(|| {
+        quote! {
+            #[allow(clippy::from_over_into)]
+            impl #impl_generics ::core::convert::Into<::std::vec::Vec<#instruction>>
+                for &#name #ty_generics #where_clause
+            {
+                fn into(self) -> ::std::vec::Vec<#instruction> {
+                    ::core::clone::Clone::clone(self).into()
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #[allow(clippy::from_over_into)]
+        impl #impl_generics ::core::convert::Into<::std::vec::Vec<#instruction>>
+            for #name #ty_generics #where_clause
+        {
+            fn into(self) -> ::std::vec::Vec<#instruction> {
+                let mut instructions = ::std::vec::Vec::new();
+                #(#extends)*
+                instructions
+            }
+        }
+        #by_ref_impl
+    })
+}
+
+/// Returns whether `#[schema(by_ref)]` is set on the struct.
+fn parse_struct_attrs(input: &DeriveInput) -> Result<bool> {
+    let mut by_ref = false;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("schema")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("by_ref") {
+                by_ref = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `by_ref`"))
+            }
+        })?;
+    }
+    Ok(by_ref)
+}
+
+/// Returns the field's sort key, or `None` if it has `#[schema(skip)]`.
+fn parse_field_attrs(field: &syn::Field, position: usize) -> Result<Option<usize>> {
+    let mut order = Some(position);
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("schema")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                order = None;
+                Ok(())
+            } else if meta.path.is_ident("order") {
+                let value: LitInt = meta.value()?.parse()?;
+                if order.is_some() {
+                    order = Some(value.base10_parse()?);
+                }
+                Ok(())
+            } else {
+                Err(meta.error("expected `skip` or `order = <n>`"))
+            }
+        })?;
+    }
+    Ok(order)
+}
+
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) if args.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
+
+fn is_instruction(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none()
+        && path.path.segments.last().is_some_and(|s| s.ident == "Instruction" && s.arguments.is_empty()))
+}
@@ -89,3 +89,25 @@ fn const_data_works() {
     );
     assert_eq!(VAR9.field_c, 2);
 }
+
+#[test]
+fn const_data_lookups_work() {
+    assert_eq!(TypeWithCode::ALL.map(|t| t.field_a), ["foo", "bar", "baz"]);
+    assert_eq!(TypeWithCode::from_code(1).unwrap().field_b, 87);
+    assert!(TypeWithCode::from_code(3).is_none());
+    assert_eq!(TypeWithCode::from_name("VAR3").unwrap().code, 2);
+    assert!(TypeWithCode::from_name("VAR4").is_none());
+
+    assert_eq!(TypeWithName::from_name("VAR5").unwrap().field_a, "no");
+    assert_eq!(
+        TypeWithNameAndCode::from_code(2).unwrap().foo,
+        TypeWithNameAndCode::from_name("VAR9").unwrap().foo
+    );
+    assert_eq!(
+        type_without_code::TypeWithoutCode::ALL
+            .iter()
+            .map(|t| t.field_b)
+            .sum::<u64>(),
+        169
+    );
+}
@@ -0,0 +1,32 @@
+use solana_devtools_errors::resolver::ProgramErrors;
+use solana_devtools_errors::{ErrorCause, ErrorCodes};
+
+#[derive(Debug, Clone, Copy, PartialEq, ErrorCodes)]
+#[error_codes(offset = 6000)]
+enum VaultError {
+    /// Insufficient funds
+    /// for the withdrawal
+    InsufficientFunds,
+    Locked = 5,
+    Paused,
+}
+
+#[test]
+fn error_codes_are_derived() {
+    assert_eq!(VaultError::VARIANTS.len(), 3);
+    assert_eq!(u32::from(VaultError::InsufficientFunds), 6000);
+    assert_eq!(u32::from(VaultError::Paused), 6006);
+    assert_eq!(VaultError::try_from(6005), Ok(VaultError::Locked));
+    assert_eq!(VaultError::try_from(6001), Err(6001));
+    assert_eq!(
+        VaultError::InsufficientFunds.to_string(),
+        "Insufficient funds for the withdrawal"
+    );
+    assert_eq!(
+        VaultError::Locked.with_cause("until tomorrow").to_string(),
+        "Locked"
+    );
+
+    let errors = ProgramErrors::new("Vault").error_enum(VaultError::VARIANTS);
+    assert_eq!(errors.errors[&6006].name, "Paused");
+}
@@ -0,0 +1,50 @@
+use solana_devtools_tx::TransactionSchema;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+
+fn ix(tag: u8) -> Instruction {
+    Instruction::new_with_bytes(Pubkey::default(), &[tag], vec![])
+}
+
+struct Nested(u8);
+
+impl From<Nested> for Vec<Instruction> {
+    fn from(nested: Nested) -> Self {
+        vec![ix(nested.0), ix(nested.0 + 1)]
+    }
+}
+
+#[derive(TransactionSchema)]
+struct Deposit {
+    #[schema(order = 3)]
+    deposit: Instruction,
+    create_account: Option<Instruction>,
+    setup: Nested,
+    #[schema(skip)]
+    _label: String,
+    memo: Option<Nested>,
+}
+
+#[derive(Clone, TransactionSchema)]
+#[schema(by_ref)]
+struct Pair(Instruction, Vec<Instruction>);
+
+fn tags(ixs: Vec<Instruction>) -> Vec<u8> {
+    ixs.into_iter().map(|ix| ix.data[0]).collect()
+}
+
+#[test]
+fn derived_schemas() {
+    let deposit = Deposit {
+        deposit: ix(0),
+        create_account: Some(ix(1)),
+        setup: Nested(10),
+        _label: "skipped".to_string(),
+        memo: None,
+    };
+    assert_eq!(tags(deposit.instructions()), vec![1, 10, 11, 0]);
+
+    let pair = Pair(ix(5), vec![ix(6), ix(7)]);
+    assert_eq!(tags((&pair).instructions()), vec![5, 6, 7]);
+    assert_eq!(tags(pair.instructions()), vec![5, 6, 7]);
+}
@@ -1,4 +1,5 @@
 pub mod account_polling;
 pub mod log_parsing;
 pub mod event_log_sub;
+pub mod pubsub_multiplexer;
 
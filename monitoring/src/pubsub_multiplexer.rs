@@ -0,0 +1,754 @@
+//! Share one websocket connection per RPC endpoint between any number of consumers.
+//!
+//! Identical subscriptions are only made once upstream, and are only unsubscribed
+//! when the last consumer drops its [MultiplexedSubscription]. If the connection drops,
+//! the multiplexer reconnects and resubscribes on its own, and reports a
+//! [Notification::Gap] if slots were skipped while it was disconnected.
+use anyhow::{anyhow, Result};
+use futures_util::{stream::BoxStream, StreamExt};
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use serde::Serialize;
+use solana_account_decoder::UiAccount;
+use solana_client::{
+    nonblocking::pubsub_client::{PubsubClient, PubsubClientError},
+    rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSignatureSubscribeConfig,
+        RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+    },
+    rpc_response::{Response, RpcKeyedAccount, RpcLogsResponse, RpcSignatureResult, SlotInfo},
+};
+use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::{broadcast, mpsc, oneshot},
+    time::{sleep_until, Instant},
+};
+
+/// How many notifications a slow consumer can fall behind before it starts missing them.
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
+
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    static ref SHARED: Mutex<HashMap<String, PubsubMultiplexer>> = Mutex::new(HashMap::new());
+}
+
+/// A websocket subscription. Two requests that compare equal share an upstream subscription.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum SubscriptionRequest {
+    Account {
+        pubkey: Pubkey,
+        config: Option<RpcAccountInfoConfig>,
+    },
+    Program {
+        program_id: Pubkey,
+        config: Option<RpcProgramAccountsConfig>,
+    },
+    Logs {
+        filter: RpcTransactionLogsFilter,
+        config: RpcTransactionLogsConfig,
+    },
+    Signature {
+        signature: Signature,
+        config: Option<RpcSignatureSubscribeConfig>,
+    },
+    Slot,
+    Root,
+}
+
+impl SubscriptionRequest {
+    fn key(&self) -> String {
+        serde_json::to_string(self).expect("subscription requests are always serializable")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notification {
+    Account(Response<UiAccount>),
+    Program(Response<RpcKeyedAccount>),
+    Logs(Response<RpcLogsResponse>),
+    Signature(Response<RpcSignatureResult>),
+    Slot(SlotInfo),
+    Root(Slot),
+    /// The connection dropped and was re-established, and the first slot seen afterwards
+    /// does not directly follow the last slot seen before. Notifications for the slots
+    /// in between may have been missed, and should be backfilled over RPC if they matter.
+    Gap {
+        last_seen_slot: Slot,
+        resumed_at_slot: Slot,
+    },
+}
+
+impl Notification {
+    /// The slot at which the notification was produced, if it carries one.
+    pub fn slot(&self) -> Option<Slot> {
+        match self {
+            Notification::Account(response) => Some(response.context.slot),
+            Notification::Program(response) => Some(response.context.slot),
+            Notification::Logs(response) => Some(response.context.slot),
+            Notification::Signature(response) => Some(response.context.slot),
+            Notification::Slot(info) => Some(info.slot),
+            Notification::Root(slot) => Some(*slot),
+            Notification::Gap { .. } => None,
+        }
+    }
+}
+
+/// A consumer's handle on a (possibly shared) subscription.
+/// Dropping it releases the consumer's reference to the upstream subscription.
+pub struct MultiplexedSubscription {
+    key: String,
+    entry_id: u64,
+    receiver: broadcast::Receiver<Notification>,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl MultiplexedSubscription {
+    /// Wait for the next notification. Returns [broadcast::error::RecvError::Lagged]
+    /// if this consumer fell too far behind, and [broadcast::error::RecvError::Closed]
+    /// if the RPC server rejected the subscription.
+    pub async fn recv(&mut self) -> Result<Notification, broadcast::error::RecvError> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for MultiplexedSubscription {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Release {
+            key: self.key.clone(),
+            entry_id: self.entry_id,
+        });
+    }
+}
+
+/// A cheaply cloneable handle on the task that owns the websocket connection to one endpoint.
+#[derive(Clone)]
+pub struct PubsubMultiplexer {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl PubsubMultiplexer {
+    /// Must be called from within a Tokio runtime. The connection is opened
+    /// on the first subscription, and closed when there are none left.
+    pub fn new(ws_url: &str) -> Self {
+        Self::with_channel_capacity(ws_url, DEFAULT_CHANNEL_CAPACITY)
+    }
+
+    pub fn with_channel_capacity(ws_url: &str, capacity: usize) -> Self {
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        let (events, event_receiver) = mpsc::unbounded_channel();
+        let driver = Driver {
+            ws_url: ws_url.to_string(),
+            capacity,
+            entries: HashMap::new(),
+            next_entry_id: 0,
+            connection: None,
+            generation: 0,
+            events,
+            last_slot: Arc::new(AtomicU64::new(0)),
+            pending_gap_check: None,
+            retry_at: None,
+            reconnect_delay: INITIAL_RECONNECT_DELAY,
+        };
+        tokio::spawn(driver.run(command_receiver, event_receiver));
+        Self { commands }
+    }
+
+    /// The process-wide multiplexer for `ws_url`, so that independent
+    /// consumers of the same endpoint share a connection without coordinating.
+    pub fn shared(ws_url: &str) -> Self {
+        let mut shared = SHARED.lock().unwrap();
+        match shared.get(ws_url) {
+            Some(multiplexer) if !multiplexer.commands.is_closed() => multiplexer.clone(),
+            _ => {
+                let multiplexer = Self::new(ws_url);
+                shared.insert(ws_url.to_string(), multiplexer.clone());
+                multiplexer
+            }
+        }
+    }
+
+    pub async fn subscribe(&self, request: SubscriptionRequest) -> Result<MultiplexedSubscription> {
+        let key = request.key();
+        let (reply, receiver) = oneshot::channel();
+        self.commands
+            .send(Command::Subscribe { request, reply })
+            .map_err(|_| anyhow!("pubsub multiplexer has shut down"))?;
+        let (entry_id, receiver) = receiver
+            .await
+            .map_err(|_| anyhow!("pubsub multiplexer has shut down"))?;
+        Ok(MultiplexedSubscription {
+            key,
+            entry_id,
+            receiver,
+            commands: self.commands.clone(),
+        })
+    }
+}
+
+enum Command {
+    Subscribe {
+        request: SubscriptionRequest,
+        reply: oneshot::Sender<(u64, broadcast::Receiver<Notification>)>,
+    },
+    /// Ignored unless `entry_id` is still the entry for `key`, since a rejected
+    /// subscription's entry may have been replaced before its consumers drop.
+    Release { key: String, entry_id: u64 },
+}
+
+/// Sent to the driver by the tasks that forward notifications from one connection.
+enum Event {
+    Disconnected {
+        generation: u64,
+    },
+    Rejected {
+        generation: u64,
+        key: String,
+        entry_id: u64,
+    },
+    FirstSlot {
+        generation: u64,
+        slot: Slot,
+    },
+}
+
+struct Entry {
+    /// Distinguishes this entry from earlier entries for the same request.
+    id: u64,
+    request: SubscriptionRequest,
+    sender: broadcast::Sender<Notification>,
+    consumers: usize,
+    /// Dropping this ends the forwarding task and unsubscribes upstream.
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+struct Connection {
+    client: Arc<PubsubClient>,
+    _slot_tracker: oneshot::Sender<()>,
+}
+
+struct Driver {
+    ws_url: String,
+    capacity: usize,
+    entries: HashMap<String, Entry>,
+    next_entry_id: u64,
+    connection: Option<Connection>,
+    generation: u64,
+    events: mpsc::UnboundedSender<Event>,
+    /// Highest slot seen on any subscription of the current connection.
+    last_slot: Arc<AtomicU64>,
+    /// The last slot seen before a reconnect, until the first slot after it arrives.
+    pending_gap_check: Option<Slot>,
+    retry_at: Option<Instant>,
+    reconnect_delay: Duration,
+}
+
+impl Driver {
+    async fn run(
+        mut self,
+        mut commands: mpsc::UnboundedReceiver<Command>,
+        mut events: mpsc::UnboundedReceiver<Event>,
+    ) {
+        loop {
+            let retry_at = self.retry_at.unwrap_or_else(Instant::now);
+            tokio::select! {
+                command = commands.recv() => match command {
+                    Some(command) => self.handle_command(command).await,
+                    None => break,
+                },
+                Some(event) = events.recv() => self.handle_event(event).await,
+                _ = sleep_until(retry_at), if self.retry_at.is_some() => {
+                    self.retry_at = None;
+                    self.connect().await;
+                }
+            }
+        }
+    }
+
+    async fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::Subscribe { request, reply } => {
+                let key = request.key();
+                if let Some(entry) = self.entries.get_mut(&key) {
+                    entry.consumers += 1;
+                    let _ = reply.send((entry.id, entry.sender.subscribe()));
+                    return;
+                }
+                let (sender, receiver) = broadcast::channel(self.capacity);
+                let id = self.next_entry_id;
+                self.next_entry_id += 1;
+                let mut entry = Entry {
+                    id,
+                    request,
+                    sender,
+                    consumers: 1,
+                    cancel: None,
+                };
+                if let Some(connection) = &self.connection {
+                    entry.cancel = Some(self.spawn_forwarder(&connection.client, &key, &entry));
+                }
+                self.entries.insert(key, entry);
+                let _ = reply.send((id, receiver));
+                if self.connection.is_none() && self.retry_at.is_none() {
+                    self.connect().await;
+                }
+            }
+            Command::Release { key, entry_id } => {
+                if let Some(entry) = self.entries.get_mut(&key) {
+                    if entry.id != entry_id {
+                        return;
+                    }
+                    entry.consumers -= 1;
+                    if entry.consumers == 0 {
+                        self.entries.remove(&key);
+                    }
+                }
+                if self.entries.is_empty() {
+                    self.disconnect();
+                }
+            }
+        }
+    }
+
+    async fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Disconnected { generation } if generation == self.generation => {
+                if self.connection.is_none() {
+                    return;
+                }
+                warn!(
+                    "websocket connection to {} closed, reconnecting",
+                    self.ws_url
+                );
+                let last_slot = self.last_slot.load(Ordering::SeqCst);
+                self.disconnect();
+                self.pending_gap_check = (last_slot > 0).then_some(last_slot);
+                self.connect().await;
+            }
+            Event::Rejected {
+                generation,
+                key,
+                entry_id,
+            } if generation == self.generation => {
+                if self.entries.get(&key).map(|entry| entry.id) != Some(entry_id) {
+                    return;
+                }
+                // Dropping the sender tells every consumer the subscription is closed.
+                self.entries.remove(&key);
+                if self.entries.is_empty() {
+                    self.disconnect();
+                }
+            }
+            Event::FirstSlot { generation, slot } if generation == self.generation => {
+                if let Some(last_seen_slot) = self.pending_gap_check.take() {
+                    if slot > last_seen_slot + 1 {
+                        warn!(
+                            "websocket connection to {} missed slots {} to {}",
+                            self.ws_url,
+                            last_seen_slot + 1,
+                            slot - 1
+                        );
+                        for entry in self.entries.values() {
+                            let _ = entry.sender.send(Notification::Gap {
+                                last_seen_slot,
+                                resumed_at_slot: slot,
+                            });
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Open a connection and (re)subscribe every entry,
+    /// or schedule another attempt with exponential backoff.
+    async fn connect(&mut self) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let client = match PubsubClient::new(&self.ws_url).await {
+            Ok(client) => Arc::new(client),
+            Err(e) => {
+                error!(
+                    "failed to connect to {}, retrying in {:?}: {:?}",
+                    self.ws_url, self.reconnect_delay, e
+                );
+                self.retry_at = Some(Instant::now() + self.reconnect_delay);
+                self.reconnect_delay = (self.reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+                return;
+            }
+        };
+        info!("connected to websocket {}", self.ws_url);
+        self.generation += 1;
+        self.reconnect_delay = INITIAL_RECONNECT_DELAY;
+        self.last_slot.store(0, Ordering::SeqCst);
+
+        let slot_tracker = self.spawn_slot_tracker(&client);
+        let cancels: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), self.spawn_forwarder(&client, key, entry)))
+            .collect();
+        for (key, cancel) in cancels {
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.cancel = Some(cancel);
+            }
+        }
+        self.connection = Some(Connection {
+            client,
+            _slot_tracker: slot_tracker,
+        });
+    }
+
+    /// Cancel every forwarding task. The websocket closes once they have all unsubscribed.
+    fn disconnect(&mut self) {
+        self.connection = None;
+        self.retry_at = None;
+        self.pending_gap_check = None;
+        for entry in self.entries.values_mut() {
+            entry.cancel = None;
+        }
+    }
+
+    fn spawn_forwarder(
+        &self,
+        client: &Arc<PubsubClient>,
+        key: &str,
+        entry: &Entry,
+    ) -> oneshot::Sender<()> {
+        let (cancel, mut cancelled) = oneshot::channel();
+        let client = client.clone();
+        let request = entry.request.clone();
+        let sender = entry.sender.clone();
+        let key = key.to_string();
+        let entry_id = entry.id;
+        let generation = self.generation;
+        let events = self.events.clone();
+        let last_slot = self.last_slot.clone();
+        tokio::spawn(async move {
+            let (mut notifications, unsubscribe) = match subscribe(&client, request).await {
+                Ok(subscription) => subscription,
+                Err(PubsubClientError::SubscribeFailed { reason, .. }) => {
+                    error!("websocket subscription was rejected: {}", reason);
+                    let _ = events.send(Event::Rejected {
+                        generation,
+                        key,
+                        entry_id,
+                    });
+                    return;
+                }
+                Err(e) => {
+                    warn!("websocket subscription failed: {:?}", e);
+                    let _ = events.send(Event::Disconnected { generation });
+                    return;
+                }
+            };
+            loop {
+                tokio::select! {
+                    _ = &mut cancelled => {
+                        unsubscribe().await;
+                        return;
+                    }
+                    notification = notifications.next() => match notification {
+                        Some(notification) => {
+                            if let Some(slot) = notification.slot() {
+                                last_slot.fetch_max(slot, Ordering::SeqCst);
+                            }
+                            let _ = sender.send(notification);
+                        }
+                        None => {
+                            let _ = events.send(Event::Disconnected { generation });
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        cancel
+    }
+
+    /// Every connection carries a slot subscription, so that the slot clock
+    /// can be compared across a reconnect even if no consumer subscribed to slots.
+    fn spawn_slot_tracker(&self, client: &Arc<PubsubClient>) -> oneshot::Sender<()> {
+        let (cancel, mut cancelled) = oneshot::channel();
+        let client = client.clone();
+        let generation = self.generation;
+        let events = self.events.clone();
+        let last_slot = self.last_slot.clone();
+        tokio::spawn(async move {
+            let (mut slots, unsubscribe) = match client.slot_subscribe().await {
+                Ok(subscription) => subscription,
+                Err(e) => {
+                    warn!("websocket slot subscription failed: {:?}", e);
+                    let _ = events.send(Event::Disconnected { generation });
+                    return;
+                }
+            };
+            let mut first = true;
+            loop {
+                tokio::select! {
+                    _ = &mut cancelled => {
+                        unsubscribe().await;
+                        return;
+                    }
+                    info = slots.next() => match info {
+                        Some(info) => {
+                            last_slot.fetch_max(info.slot, Ordering::SeqCst);
+                            if first {
+                                first = false;
+                                let _ = events.send(Event::FirstSlot { generation, slot: info.slot });
+                            }
+                        }
+                        None => {
+                            let _ = events.send(Event::Disconnected { generation });
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        cancel
+    }
+}
+
+type UnsubscribeFn =
+    Box<dyn FnOnce() -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> + Send>;
+
+async fn subscribe(
+    client: &PubsubClient,
+    request: SubscriptionRequest,
+) -> Result<(BoxStream<'_, Notification>, UnsubscribeFn), PubsubClientError> {
+    Ok(match request {
+        SubscriptionRequest::Account { pubkey, config } => {
+            let (stream, unsubscribe) = client.account_subscribe(&pubkey, config).await?;
+            (stream.map(Notification::Account).boxed(), unsubscribe)
+        }
+        SubscriptionRequest::Program { program_id, config } => {
+            let (stream, unsubscribe) = client.program_subscribe(&program_id, config).await?;
+            (stream.map(Notification::Program).boxed(), unsubscribe)
+        }
+        SubscriptionRequest::Logs { filter, config } => {
+            let (stream, unsubscribe) = client.logs_subscribe(filter, config).await?;
+            (stream.map(Notification::Logs).boxed(), unsubscribe)
+        }
+        SubscriptionRequest::Signature { signature, config } => {
+            let (stream, unsubscribe) = client.signature_subscribe(&signature, config).await?;
+            (stream.map(Notification::Signature).boxed(), unsubscribe)
+        }
+        SubscriptionRequest::Slot => {
+            let (stream, unsubscribe) = client.slot_subscribe().await?;
+            (stream.map(Notification::Slot).boxed(), unsubscribe)
+        }
+        SubscriptionRequest::Root => {
+            let (stream, unsubscribe) = client.root_subscribe().await?;
+            (stream.map(Notification::Root).boxed(), unsubscribe)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::SinkExt;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    const SLOT_SUBSCRIPTION: u64 = 1;
+    const ACCOUNT_SUBSCRIPTION: u64 = 2;
+    const ROOT_SUBSCRIPTION: u64 = 3;
+
+    /// Each accepted connection reports the methods it receives, and sends
+    /// whatever the test pushes to it. Pushing `None` closes the connection.
+    /// The first `rejections` account subscriptions are rejected.
+    async fn spawn_server(
+        rejections: usize,
+    ) -> (
+        String,
+        mpsc::UnboundedReceiver<(
+            mpsc::UnboundedSender<Option<Value>>,
+            mpsc::UnboundedReceiver<String>,
+        )>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (connections, connection_receiver) = mpsc::unbounded_channel();
+        let rejections = Arc::new(AtomicU64::new(rejections as u64));
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let (push, mut pushed) = mpsc::unbounded_channel::<Option<Value>>();
+                let (methods, method_receiver) = mpsc::unbounded_channel();
+                connections.send((push, method_receiver)).unwrap();
+                let rejections = rejections.clone();
+                tokio::spawn(async move {
+                    loop {
+                        tokio::select! {
+                            message = pushed.recv() => match message.flatten() {
+                                Some(message) => ws.send(Message::Text(message.to_string())).await.unwrap(),
+                                None => {
+                                    let _ = ws.close(None).await;
+                                    return;
+                                }
+                            },
+                            message = ws.next() => {
+                                let Some(Ok(Message::Text(text))) = message else { return };
+                                let request: Value = serde_json::from_str(&text).unwrap();
+                                let method = request["method"].as_str().unwrap().to_string();
+                                let result = match method.as_str() {
+                                    "slotSubscribe" => json!(SLOT_SUBSCRIPTION),
+                                    "accountSubscribe" => json!(ACCOUNT_SUBSCRIPTION),
+                                    "rootSubscribe" => json!(ROOT_SUBSCRIPTION),
+                                    _ => json!(true),
+                                };
+                                let reject = method == "accountSubscribe"
+                                    && rejections
+                                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                                        .is_ok();
+                                let response = if reject {
+                                    json!({"jsonrpc": "2.0", "error": {"code": -32602, "message": "Invalid params"}, "id": request["id"]})
+                                } else {
+                                    json!({"jsonrpc": "2.0", "result": result, "id": request["id"]})
+                                };
+                                ws.send(Message::Text(response.to_string())).await.unwrap();
+                                let _ = methods.send(method);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        (url, connection_receiver)
+    }
+
+    fn slot_notification(slot: Slot) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "slotNotification",
+            "params": {
+                "result": { "slot": slot, "parent": slot - 1, "root": 0 },
+                "subscription": SLOT_SUBSCRIPTION,
+            }
+        })
+    }
+
+    fn account_notification(slot: Slot, lamports: u64) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "accountNotification",
+            "params": {
+                "result": {
+                    "context": { "slot": slot },
+                    "value": {
+                        "lamports": lamports,
+                        "data": ["", "base64"],
+                        "owner": Pubkey::default().to_string(),
+                        "executable": false,
+                        "rentEpoch": 0,
+                        "space": 0,
+                    }
+                },
+                "subscription": ACCOUNT_SUBSCRIPTION,
+            }
+        })
+    }
+
+    async fn expect_methods(methods: &mut mpsc::UnboundedReceiver<String>, expected: &[&str]) {
+        let mut received = vec![];
+        for _ in expected {
+            received.push(methods.recv().await.unwrap());
+        }
+        received.sort();
+        assert_eq!(received, expected);
+    }
+
+    async fn expect_lamports(subscription: &mut MultiplexedSubscription, lamports: u64) {
+        match subscription.recv().await.unwrap() {
+            Notification::Account(response) => assert_eq!(response.value.lamports, lamports),
+            other => panic!("unexpected notification: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn subscriptions_are_shared_and_survive_reconnects() {
+        let (url, mut connections) = spawn_server(0).await;
+        let multiplexer = PubsubMultiplexer::new(&url);
+        let request = SubscriptionRequest::Account {
+            pubkey: Pubkey::new_unique(),
+            config: None,
+        };
+        let mut first = multiplexer.subscribe(request.clone()).await.unwrap();
+        let mut second = multiplexer.subscribe(request.clone()).await.unwrap();
+
+        let (push, mut methods) = connections.recv().await.unwrap();
+        expect_methods(&mut methods, &["accountSubscribe", "slotSubscribe"]).await;
+        push.send(Some(slot_notification(10))).unwrap();
+        push.send(Some(account_notification(10, 1))).unwrap();
+        expect_lamports(&mut first, 1).await;
+        expect_lamports(&mut second, 1).await;
+
+        // The server drops the connection, and slots 11 to 19 pass by unseen.
+        push.send(None).unwrap();
+        let (push, mut methods) = connections.recv().await.unwrap();
+        expect_methods(&mut methods, &["accountSubscribe", "slotSubscribe"]).await;
+        push.send(Some(slot_notification(20))).unwrap();
+        let gap = Notification::Gap {
+            last_seen_slot: 10,
+            resumed_at_slot: 20,
+        };
+        assert_eq!(first.recv().await.unwrap(), gap);
+        assert_eq!(second.recv().await.unwrap(), gap);
+
+        // Only the last consumer to leave unsubscribes upstream.
+        drop(first);
+        push.send(Some(account_notification(21, 2))).unwrap();
+        expect_lamports(&mut second, 2).await;
+        drop(second);
+        expect_methods(&mut methods, &["accountUnsubscribe", "slotUnsubscribe"]).await;
+    }
+
+    #[tokio::test]
+    async fn stale_releases_are_ignored() {
+        let (url, mut connections) = spawn_server(1).await;
+        let multiplexer = PubsubMultiplexer::new(&url);
+        // Keeps the connection open while the account subscription is rejected.
+        let _roots = multiplexer
+            .subscribe(SubscriptionRequest::Root)
+            .await
+            .unwrap();
+        let (push, mut methods) = connections.recv().await.unwrap();
+        expect_methods(&mut methods, &["rootSubscribe", "slotSubscribe"]).await;
+
+        let request = SubscriptionRequest::Account {
+            pubkey: Pubkey::new_unique(),
+            config: None,
+        };
+        let mut rejected = multiplexer.subscribe(request.clone()).await.unwrap();
+        assert_eq!(
+            rejected.recv().await,
+            Err(broadcast::error::RecvError::Closed)
+        );
+        let mut accepted = multiplexer.subscribe(request).await.unwrap();
+        expect_methods(&mut methods, &["accountSubscribe", "accountSubscribe"]).await;
+
+        // The rejected consumer leaving must not release the new subscription.
+        drop(rejected);
+        // Commands are handled in order, so the release has been handled once this returns.
+        let _more_roots = multiplexer
+            .subscribe(SubscriptionRequest::Root)
+            .await
+            .unwrap();
+        push.send(Some(account_notification(10, 1))).unwrap();
+        expect_lamports(&mut accepted, 1).await;
+    }
+}
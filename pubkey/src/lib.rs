@@ -1,3 +1,5 @@
+pub mod names;
 pub mod pubkey;
+pub mod registry;
 
 pub use pubkey::*;
\ No newline at end of file
@@ -0,0 +1,125 @@
+//! Named pubkeys created at runtime, and a registry that maps pubkeys back to their names,
+//! e.g. to make pubkeys in logs, errors and failed test assertions readable.
+use crate::{get_named_pubkey, NamedPubkeyError};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+/// Names for pubkeys. A pubkey has at most one name, and a name belongs to at most one pubkey.
+#[derive(Debug, Clone, Default)]
+pub struct NamedPubkeyRegistry {
+    names: HashMap<Pubkey, String>,
+    pubkeys: HashMap<String, Pubkey>,
+}
+
+impl NamedPubkeyRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The named pubkey for `name`, see [get_named_pubkey]. Registers it on first use, and
+    /// fails if a different name already produced the same pubkey, e.g. `"pool0"` and `"poolo"`.
+    pub fn named(&mut self, name: &str) -> Result<Pubkey, NamedPubkeyError> {
+        let pubkey = get_named_pubkey(name.to_string())?;
+        self.insert(name, pubkey)?;
+        Ok(pubkey)
+    }
+
+    /// Name any pubkey, e.g. a PDA or a random keypair's pubkey.
+    /// Naming a pubkey again with the same name is a no-op.
+    pub fn insert(&mut self, name: &str, pubkey: Pubkey) -> Result<(), NamedPubkeyError> {
+        if let Some(existing) = self.names.get(&pubkey) {
+            if existing != name {
+                return Err(NamedPubkeyError::Collision {
+                    name: name.to_string(),
+                    existing: existing.clone(),
+                    pubkey,
+                });
+            }
+        }
+        if let Some(existing) = self.pubkeys.get(name) {
+            if *existing != pubkey {
+                return Err(NamedPubkeyError::NameTaken(name.to_string(), *existing));
+            }
+        }
+        self.names.insert(pubkey, name.to_string());
+        self.pubkeys.insert(name.to_string(), pubkey);
+        Ok(())
+    }
+
+    pub fn name(&self, pubkey: &Pubkey) -> Option<&str> {
+        self.names.get(pubkey).map(String::as_str)
+    }
+
+    pub fn pubkey(&self, name: &str) -> Option<Pubkey> {
+        self.pubkeys.get(name).copied()
+    }
+
+    /// `name (pubkey)` for named pubkeys, and the pubkey otherwise.
+    pub fn display(&self, pubkey: &Pubkey) -> String {
+        match self.name(pubkey) {
+            Some(name) => format!("{} ({})", name, pubkey),
+            None => pubkey.to_string(),
+        }
+    }
+
+    /// Replace every named pubkey in `text`, such as a log message or an error, with its name.
+    pub fn render(&self, text: &str) -> String {
+        let mut rendered = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(is_base58) {
+            rendered.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(|c| !is_base58(c)).unwrap_or(rest.len());
+            let word = &rest[..end];
+            match word
+                .parse::<Pubkey>()
+                .ok()
+                .and_then(|pubkey| self.name(&pubkey))
+            {
+                Some(name) => rendered.push_str(name),
+                None => rendered.push_str(word),
+            }
+            rest = &rest[end..];
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+}
+
+fn is_base58(c: char) -> bool {
+    c.is_ascii_alphanumeric() && !matches!(c, '0' | 'O' | 'I' | 'l')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pubkeys_are_rendered_with_their_names() {
+        let mut registry = NamedPubkeyRegistry::new();
+        let pool = registry.named("pool0").unwrap();
+        assert_eq!(registry.named("pool0").unwrap(), pool);
+        assert_eq!(
+            registry.named("poolo").unwrap_err(),
+            NamedPubkeyError::Collision {
+                name: "poolo".to_string(),
+                existing: "pool0".to_string(),
+                pubkey: pool,
+            }
+        );
+        let vault = Pubkey::new_unique();
+        registry.insert("vault", vault).unwrap();
+        assert!(registry.insert("vault", Pubkey::new_unique()).is_err());
+
+        let stranger = Pubkey::new_unique();
+        assert_eq!(
+            registry.render(&format!(
+                "Transfer: {} -> {}, fee payer {}.",
+                pool, vault, stranger
+            )),
+            format!("Transfer: pool0 -> vault, fee payer {}.", stranger)
+        );
+        assert_eq!(registry.display(&vault), format!("vault ({})", vault));
+        assert_eq!(registry.pubkey("vault"), Some(vault));
+    }
+}
@@ -8,6 +8,14 @@ pub enum NamedPubkeyError {
     InvalidEncoding(String),
     #[error("Pubkey name prefix is too long when Base58 encoded, encodes to {0} bytes")]
     TooLong(usize),
+    #[error("Pubkey name {name} produces pubkey {pubkey}, which is already named {existing}")]
+    Collision {
+        name: String,
+        existing: String,
+        pubkey: Pubkey,
+    },
+    #[error("Pubkey name {0} already belongs to pubkey {1}")]
+    NameTaken(String, Pubkey),
 }
 
 pub fn get_named_pubkey(prefix: String) -> Result<Pubkey, NamedPubkeyError> {
@@ -30,6 +38,24 @@ pub fn get_named_pubkey(prefix: String) -> Result<Pubkey, NamedPubkeyError> {
     Ok(Pubkey::new_from_array(arr))
 }
 
+/// Names are zero-padded to 32 bytes, so a random pubkey that happens to end in
+/// zero bytes could be mistaken for a named one. Requiring this much padding makes
+/// that a 1 in 2^32 chance, at the cost of not recognizing the longest names.
+pub const MIN_NAME_PADDING_BYTES: usize = 4;
+
+/// Recover the name a pubkey was created from with [get_named_pubkey].
+/// Names that sanitize to the same key are indistinguishable, so the sanitized form is
+/// returned, e.g. `"pooLo"` for a key named `"pool0"`.
+/// Returns `None` if the pubkey does not end in at least [MIN_NAME_PADDING_BYTES] zero bytes.
+pub fn recover_pubkey_name(pubkey: &Pubkey) -> Option<String> {
+    let bytes = pubkey.to_bytes();
+    let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    if PUBKEY_SERIALIZED_SIZE - len < MIN_NAME_PADDING_BYTES || len == 0 {
+        return None;
+    }
+    Some(bs58::encode(&bytes[..len]).into_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,6 +75,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn names_are_recovered() {
+        for name in ["myname", "myname123", "pooL", "1leadingone"] {
+            let pubkey = get_named_pubkey(name.to_string()).unwrap();
+            let recovered = recover_pubkey_name(&pubkey).unwrap();
+            assert_eq!(get_named_pubkey(recovered.clone()).unwrap(), pubkey);
+        }
+        assert_eq!(
+            recover_pubkey_name(&get_named_pubkey("pool0".to_string()).unwrap()).unwrap(),
+            "pooLo"
+        );
+        assert_eq!(recover_pubkey_name(&Pubkey::default()), None);
+        assert_eq!(recover_pubkey_name(&Pubkey::new_from_array([7; 32])), None);
+    }
+
     #[test]
     fn various_names() {
         let _ = get_named_pubkey("myname".to_string()).unwrap();
@@ -0,0 +1,104 @@
+//! A registry of every `named_pubkey!` invocation, used to catch distinct names that
+//! produce the same pubkey, e.g. `"pool0"` and `"poolo"`.
+//!
+//! Registration is opt-in: set [REGISTRY_ENV_VAR] to a directory when building, and the
+//! macro records each name as an empty file at `<dir>/<pubkey>/<name>`. Entries are never
+//! removed, so delete the directory after renaming or removing invocations.
+use crate::recover_pubkey_name;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The environment variable naming the registry directory.
+pub const REGISTRY_ENV_VAR: &str = "SOLANA_DEVTOOLS_NAMED_PUBKEY_REGISTRY";
+
+/// Distinct names that all produce `pubkey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedPubkeyCollision {
+    pub pubkey: Pubkey,
+    pub names: Vec<String>,
+}
+
+impl std::fmt::Display for NamedPubkeyCollision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "names {} all produce pubkey {}",
+            self.names.join(", "),
+            self.pubkey
+        )?;
+        if let Some(name) = recover_pubkey_name(&self.pubkey) {
+            write!(f, " (recovered name \"{}\")", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The registry directory from [REGISTRY_ENV_VAR], if set.
+pub fn registry_dir() -> Option<PathBuf> {
+    std::env::var_os(REGISTRY_ENV_VAR).map(PathBuf::from)
+}
+
+/// Record that `name` produces `pubkey`, and return any collision this causes.
+pub fn register_named_pubkey(
+    dir: impl AsRef<Path>,
+    name: &str,
+    pubkey: &Pubkey,
+) -> io::Result<Option<NamedPubkeyCollision>> {
+    let key_dir = dir.as_ref().join(pubkey.to_string());
+    fs::create_dir_all(&key_dir)?;
+    fs::write(key_dir.join(name), [])?;
+    collision(&key_dir, *pubkey)
+}
+
+/// Every collision in the registry, e.g. to fail a test or a CI step.
+pub fn find_collisions(dir: impl AsRef<Path>) -> io::Result<Vec<NamedPubkeyCollision>> {
+    let mut collisions = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let Some(pubkey) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<Pubkey>().ok())
+        else {
+            continue;
+        };
+        if let Some(collision) = collision(&entry.path(), pubkey)? {
+            collisions.push(collision);
+        }
+    }
+    collisions.sort_by_key(|c| c.pubkey);
+    Ok(collisions)
+}
+
+fn collision(key_dir: &Path, pubkey: Pubkey) -> io::Result<Option<NamedPubkeyCollision>> {
+    let mut names = fs::read_dir(key_dir)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<io::Result<Vec<_>>>()?;
+    names.sort();
+    Ok((names.len() > 1).then_some(NamedPubkeyCollision { pubkey, names }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_named_pubkey;
+
+    #[test]
+    fn collisions_are_found() {
+        let dir = std::env::temp_dir().join(format!("named-pubkeys-{}", Pubkey::new_unique()));
+        let register = |name: &str| {
+            let pubkey = get_named_pubkey(name.to_string()).unwrap();
+            register_named_pubkey(&dir, name, &pubkey).unwrap()
+        };
+        assert_eq!(register("pool0"), None);
+        assert_eq!(register("pool0"), None);
+        assert_eq!(register("vault"), None);
+        let collision = register("poolo").unwrap();
+        assert_eq!(collision.names, vec!["pool0", "poolo"]);
+        assert_eq!(find_collisions(&dir).unwrap(), vec![collision]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+//! Tokens for authenticated RPC providers, sent as headers by a [HeaderMiddleware].
+//! A [SignInProvider] signs in with a keypair, as GenesysGo does, and signs in again
+//! shortly before the JWT it was given expires.
+use crate::middleware::{HeaderMiddleware, HeaderProvider};
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use solana_rpc_client_api::request::RpcRequest;
+use solana_sdk::signer::{Signer, SignerError};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+/// The sign-in endpoint of the GenesysGo Shadow platform.
+pub const GENESYS_GO_SIGN_IN_URL: &str = "https://portal.genesysgo.net/api/signin";
+/// The message GenesysGo expects to be signed.
+pub const GENESYS_GO_SIGN_IN_MESSAGE: &str = "Sign in to GenesysGo Shadow Platform.";
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("Could not sign the sign-in message: {0}")]
+    Signer(#[from] SignerError),
+    #[error("Sign-in request to {url} failed: {source}")]
+    Request { url: String, source: reqwest::Error },
+    #[error("Sign-in to {url} was rejected with status {status}: {body}")]
+    Rejected {
+        url: String,
+        status: StatusCode,
+        body: String,
+    },
+    #[error("Sign-in response from {url} has no token")]
+    MissingToken { url: String },
+    #[error("Token cannot be sent as a header value")]
+    InvalidToken,
+}
+
+impl From<AuthError> for ClientError {
+    fn from(e: AuthError) -> Self {
+        ClientErrorKind::Custom(e.to_string()).into()
+    }
+}
+
+/// Supplies a currently valid token, refreshing it as needed.
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    async fn token(&self) -> Result<String, AuthError>;
+}
+
+/// Lets one provider, and its cached token, be shared by several clients or middleware layers.
+#[async_trait]
+impl<P: AuthProvider + ?Sized> AuthProvider for Arc<P> {
+    async fn token(&self) -> Result<String, AuthError> {
+        (**self).token().await
+    }
+}
+
+/// An API key that never expires.
+#[derive(Debug, Clone)]
+pub struct StaticApiKey(pub String);
+
+#[async_trait]
+impl AuthProvider for StaticApiKey {
+    async fn token(&self) -> Result<String, AuthError> {
+        Ok(self.0.clone())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthToken {
+    pub token: String,
+    /// Taken from the JWT's `exp` claim. Tokens without one are used until the process exits.
+    pub expires_at: Option<SystemTime>,
+}
+
+impl AuthToken {
+    pub fn new(token: String) -> Self {
+        let expires_at = jwt_expiry(&token);
+        Self { token, expires_at }
+    }
+
+    pub fn expires_within(&self, duration: Duration) -> bool {
+        self.expires_at.is_some_and(|expires_at| {
+            SystemTime::now()
+                .checked_add(duration)
+                .is_none_or(|deadline| expires_at <= deadline)
+        })
+    }
+}
+
+/// The `exp` claim of a JWT. The signature is not verified. An `exp` too far in
+/// the future to represent is treated as no expiry.
+pub fn jwt_expiry(token: &str) -> Option<SystemTime> {
+    #[derive(Deserialize)]
+    struct Claims {
+        exp: u64,
+    }
+    let payload = URL_SAFE_NO_PAD.decode(token.split('.').nth(1)?).ok()?;
+    let claims: Claims = serde_json::from_slice(&payload).ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(claims.exp))
+}
+
+/// Signs a message with a keypair, and POSTs the message and its base58 signature
+/// as `{"message", "signature"}` to a sign-in URL, which responds with `{"token"}`.
+/// The token is cached until `refresh_margin` before it expires. Concurrent callers
+/// wait on a single sign-in.
+pub struct SignInProvider<S> {
+    pub client: reqwest::Client,
+    pub url: String,
+    pub message: String,
+    pub signer: S,
+    /// 60 seconds by default.
+    pub refresh_margin: Duration,
+    cached: Mutex<Option<AuthToken>>,
+}
+
+impl<S: Signer> SignInProvider<S> {
+    pub fn new<U: ToString, M: ToString>(url: U, message: M, signer: S) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            message: message.to_string(),
+            signer,
+            refresh_margin: Duration::from_secs(60),
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub fn genesys_go(signer: S) -> Self {
+        Self::new(GENESYS_GO_SIGN_IN_URL, GENESYS_GO_SIGN_IN_MESSAGE, signer)
+    }
+
+    pub fn with_refresh_margin(mut self, refresh_margin: Duration) -> Self {
+        self.refresh_margin = refresh_margin;
+        self
+    }
+
+    /// Sign in, regardless of any cached token.
+    pub async fn sign_in(&self) -> Result<AuthToken, AuthError> {
+        let signature = self.signer.try_sign_message(self.message.as_bytes())?;
+        let request_error = |source| AuthError::Request {
+            url: self.url.clone(),
+            source,
+        };
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&json!({
+                "message": self.message,
+                "signature": signature.to_string(),
+            }))
+            .send()
+            .await
+            .map_err(request_error)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(AuthError::Rejected {
+                url: self.url.clone(),
+                status,
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        let body: Value = response.json().await.map_err(request_error)?;
+        let token = body["token"].as_str().ok_or(AuthError::MissingToken {
+            url: self.url.clone(),
+        })?;
+        Ok(AuthToken::new(token.to_string()))
+    }
+}
+
+#[async_trait]
+impl<S: Signer + Send + Sync> AuthProvider for SignInProvider<S> {
+    async fn token(&self) -> Result<String, AuthError> {
+        let mut cached = self.cached.lock().await;
+        match cached.as_ref() {
+            Some(token) if !token.expires_within(self.refresh_margin) => Ok(token.token.clone()),
+            _ => {
+                let token = self.sign_in().await?;
+                Ok(cached.insert(token).token.clone())
+            }
+        }
+    }
+}
+
+/// Sends an [AuthProvider]'s token as a header on every request.
+pub struct AuthHeaders<P> {
+    pub provider: P,
+    pub header: HeaderName,
+    /// Prepended to the token, e.g. `"Bearer "`.
+    pub prefix: String,
+}
+
+impl<P> AuthHeaders<P> {
+    /// `Authorization: Bearer <token>`.
+    pub fn bearer(provider: P) -> Self {
+        Self {
+            provider,
+            header: AUTHORIZATION,
+            prefix: "Bearer ".to_string(),
+        }
+    }
+
+    /// The token as is, in a header of the provider's choosing, e.g. `x-api-key`.
+    pub fn with_header(provider: P, header: HeaderName) -> Self {
+        Self {
+            provider,
+            header,
+            prefix: String::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl<P: AuthProvider> HeaderProvider for AuthHeaders<P> {
+    async fn headers(&self, _request: RpcRequest) -> Result<HeaderMap, ClientError> {
+        let token = self.provider.token().await?;
+        let value = HeaderValue::from_str(&format!("{}{}", self.prefix, token))
+            .map_err(|_| AuthError::InvalidToken)?;
+        let mut headers = HeaderMap::new();
+        headers.insert(self.header.clone(), value);
+        Ok(headers)
+    }
+}
+
+impl<S> HeaderMiddleware<S> {
+    /// Send the provider's token as a bearer token. See [AuthHeaders] for other headers.
+    pub fn with_auth(self, provider: impl AuthProvider + 'static) -> Self {
+        self.with_provider(AuthHeaders::bearer(provider))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::{Keypair, Signature};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::thread;
+
+    fn jwt(expires_in: Duration) -> String {
+        let exp = (SystemTime::now() + expires_in)
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let claims = URL_SAFE_NO_PAD.encode(json!({ "exp": exp }).to_string());
+        format!("e30.{}.c2ln", claims)
+    }
+
+    /// A sign-in server that verifies signatures from `pubkey`, and issues tokens
+    /// that expire in `expires_in`.
+    fn spawn_sign_in_server(
+        pubkey: Pubkey,
+        expires_in: Duration,
+        sign_ins: Arc<AtomicU64>,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let body: Value = serde_json::from_slice(&body).unwrap();
+                let signature = Signature::from_str(body["signature"].as_str().unwrap()).unwrap();
+                let message = body["message"].as_str().unwrap();
+                let (status, response) = if signature.verify(pubkey.as_ref(), message.as_bytes()) {
+                    sign_ins.fetch_add(1, Ordering::SeqCst);
+                    ("200 OK", json!({ "token": jwt(expires_in) }).to_string())
+                } else {
+                    ("401 Unauthorized", "bad signature".to_string())
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    response.len(),
+                    response
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn jwt_expiry_is_read_from_claims() {
+        let token = AuthToken::new(jwt(Duration::from_secs(30)));
+        assert!(token.expires_within(Duration::from_secs(60)));
+        assert!(!token.expires_within(Duration::from_secs(10)));
+        assert_eq!(AuthToken::new("api-key".to_string()).expires_at, None);
+
+        let claims = URL_SAFE_NO_PAD.encode(json!({ "exp": u64::MAX }).to_string());
+        assert_eq!(jwt_expiry(&format!("e30.{}.c2ln", claims)), None);
+        assert!(token.expires_within(Duration::MAX));
+    }
+
+    #[tokio::test]
+    async fn tokens_are_refreshed_before_expiry() {
+        let keypair = Keypair::new();
+        let sign_ins = Arc::new(AtomicU64::new(0));
+        let url = spawn_sign_in_server(
+            keypair.pubkey(),
+            Duration::from_secs(3600),
+            sign_ins.clone(),
+        );
+        let provider = SignInProvider::new(
+            &url,
+            "sign in",
+            Keypair::from_bytes(&keypair.to_bytes()).unwrap(),
+        );
+        let token = provider.token().await.unwrap();
+        assert_eq!(provider.token().await.unwrap(), token);
+        assert_eq!(sign_ins.load(Ordering::SeqCst), 1);
+
+        // Tokens expiring within the refresh margin are replaced on every use.
+        let provider = provider.with_refresh_margin(Duration::from_secs(7200));
+        provider.token().await.unwrap();
+        provider.token().await.unwrap();
+        assert_eq!(sign_ins.load(Ordering::SeqCst), 3);
+
+        let provider = SignInProvider::new(&url, "sign in", Keypair::new());
+        assert!(matches!(
+            provider.token().await.unwrap_err(),
+            AuthError::Rejected {
+                status: StatusCode::UNAUTHORIZED,
+                ..
+            }
+        ));
+    }
+}
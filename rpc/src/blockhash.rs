@@ -0,0 +1,201 @@
+//! Check how long a message's recent blockhash remains valid, to decide whether to send the
+//! message as is, re-sign it with a fresh blockhash, or switch to a durable nonce.
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Result as ClientResult;
+use solana_sdk::clock::DEFAULT_MS_PER_SLOT;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::nonce::state::{State as NonceState, Versions as NonceVersions};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::system_program;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockhashStatus {
+    /// `remaining_blocks` is only known if the blockhash's last valid block height was given.
+    Valid {
+        remaining_blocks: Option<u64>,
+    },
+    Expired,
+    /// The message advances a durable nonce, so its blockhash is the nonce value, which stays
+    /// valid until the nonce account is advanced.
+    DurableNonce {
+        nonce_account: Pubkey,
+        valid: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendDecision {
+    SendNow,
+    /// Sign again with a fresh blockhash.
+    Resign,
+    /// The message cannot be re-signed in time, e.g. because it was signed offline,
+    /// so it should be rebuilt with a durable nonce.
+    UseNonce,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockhashValidity {
+    pub blockhash: Hash,
+    /// The block height at the client's commitment when checked.
+    pub block_height: u64,
+    pub status: BlockhashStatus,
+}
+
+impl BlockhashValidity {
+    pub fn is_valid(&self) -> bool {
+        match self.status {
+            BlockhashStatus::Valid { .. } => true,
+            BlockhashStatus::Expired => false,
+            BlockhashStatus::DurableNonce { valid, .. } => valid,
+        }
+    }
+
+    pub fn remaining_blocks(&self) -> Option<u64> {
+        match self.status {
+            BlockhashStatus::Valid { remaining_blocks } => remaining_blocks,
+            BlockhashStatus::Expired => Some(0),
+            BlockhashStatus::DurableNonce { .. } => None,
+        }
+    }
+
+    /// Assumes the default slot time.
+    pub fn estimated_time_remaining(&self) -> Option<Duration> {
+        self.remaining_blocks()
+            .map(|blocks| Duration::from_millis(blocks * DEFAULT_MS_PER_SLOT))
+    }
+
+    /// Send now if the blockhash is valid for at least `min_remaining_blocks`, or indefinitely,
+    /// or for an unknown number of blocks. Otherwise re-sign if possible, or use a nonce.
+    pub fn decide(&self, min_remaining_blocks: u64, can_resign: bool) -> SendDecision {
+        let enough_time = self
+            .remaining_blocks()
+            .is_none_or(|blocks| blocks >= min_remaining_blocks);
+        if self.is_valid() && enough_time {
+            SendDecision::SendNow
+        } else if can_resign {
+            SendDecision::Resign
+        } else {
+            SendDecision::UseNonce
+        }
+    }
+}
+
+/// Check a blockhash at the client's commitment, which should be no stronger than the
+/// commitment the blockhash was fetched at.
+pub async fn check_blockhash(
+    client: &RpcClient,
+    blockhash: &Hash,
+    last_valid_block_height: Option<u64>,
+) -> ClientResult<BlockhashValidity> {
+    let commitment = client.commitment();
+    let valid = client.is_blockhash_valid(blockhash, commitment).await?;
+    let block_height = client.get_block_height().await?;
+    let status = match last_valid_block_height {
+        _ if !valid => BlockhashStatus::Expired,
+        Some(last_valid) if block_height > last_valid => BlockhashStatus::Expired,
+        Some(last_valid) => BlockhashStatus::Valid {
+            remaining_blocks: Some(last_valid - block_height),
+        },
+        None => BlockhashStatus::Valid {
+            remaining_blocks: None,
+        },
+    };
+    Ok(BlockhashValidity {
+        blockhash: *blockhash,
+        block_height,
+        status,
+    })
+}
+
+/// Check the message's recent blockhash, or if it advances a durable nonce,
+/// whether the nonce account still holds the same nonce value.
+pub async fn check_message(
+    client: &RpcClient,
+    message: &VersionedMessage,
+    last_valid_block_height: Option<u64>,
+) -> ClientResult<BlockhashValidity> {
+    let blockhash = message.recent_blockhash();
+    let Some(nonce_account) = durable_nonce_account(message) else {
+        return check_blockhash(client, blockhash, last_valid_block_height).await;
+    };
+    let block_height = client.get_block_height().await?;
+    let account = client.get_account(&nonce_account).await?;
+    let valid = match bincode::deserialize::<NonceVersions>(&account.data) {
+        Ok(versions) => match versions.state() {
+            NonceState::Initialized(data) => data.blockhash() == *blockhash,
+            NonceState::Uninitialized => false,
+        },
+        Err(_) => false,
+    };
+    Ok(BlockhashValidity {
+        blockhash: *blockhash,
+        block_height,
+        status: BlockhashStatus::DurableNonce {
+            nonce_account,
+            valid,
+        },
+    })
+}
+
+/// The nonce account, if the message's first instruction advances a durable nonce.
+pub fn durable_nonce_account(message: &VersionedMessage) -> Option<Pubkey> {
+    let keys = message.static_account_keys();
+    let ix = message.instructions().first()?;
+    if !system_program::check_id(keys.get(ix.program_id_index as usize)?) {
+        return None;
+    }
+    match bincode::deserialize(&ix.data) {
+        Ok(SystemInstruction::AdvanceNonceAccount) => {
+            keys.get(*ix.accounts.first()? as usize).copied()
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::message::Message;
+    use solana_sdk::system_instruction;
+
+    #[test]
+    fn decisions_follow_validity() {
+        let validity = |status| BlockhashValidity {
+            blockhash: Hash::default(),
+            block_height: 100,
+            status,
+        };
+        let valid_for = |blocks| {
+            validity(BlockhashStatus::Valid {
+                remaining_blocks: Some(blocks),
+            })
+        };
+        assert_eq!(valid_for(50).decide(10, true), SendDecision::SendNow);
+        assert_eq!(valid_for(5).decide(10, true), SendDecision::Resign);
+        assert_eq!(valid_for(5).decide(10, false), SendDecision::UseNonce);
+        assert_eq!(
+            valid_for(50).estimated_time_remaining(),
+            Some(Duration::from_millis(50 * DEFAULT_MS_PER_SLOT))
+        );
+        assert_eq!(
+            validity(BlockhashStatus::Expired).decide(0, true),
+            SendDecision::Resign
+        );
+
+        let nonce_account = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let message = |instructions: &[_]| {
+            VersionedMessage::Legacy(Message::new(instructions, Some(&authority)))
+        };
+        let advance = system_instruction::advance_nonce_account(&nonce_account, &authority);
+        let transfer = system_instruction::transfer(&authority, &nonce_account, 1);
+        assert_eq!(
+            durable_nonce_account(&message(&[advance.clone(), transfer.clone()])),
+            Some(nonce_account)
+        );
+        assert_eq!(durable_nonce_account(&message(&[transfer, advance])), None);
+    }
+}
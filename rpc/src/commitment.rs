@@ -0,0 +1,231 @@
+//! Read at `processed` for responsiveness, and re-validate at a stronger commitment
+//! before the result is used for a decision that can't be undone, e.g. releasing funds
+//! off-chain. Reads at `processed` can be rolled back if their fork is abandoned.
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Result as ClientResult;
+use solana_rpc_client_api::response::Response;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::future::Future;
+
+/// A value, with the slot at which it was read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slotted<T> {
+    pub slot: Slot,
+    pub value: T,
+}
+
+impl<T> From<Response<T>> for Slotted<T> {
+    fn from(response: Response<T>) -> Self {
+        Self {
+            slot: response.context.slot,
+            value: response.value,
+        }
+    }
+}
+
+/// Whether a read feeds a decision that can be undone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadPurpose {
+    /// e.g. rendering a UI, or choosing which transaction to build.
+    Reversible,
+    /// The read is re-validated at [EscalationPolicy::irreversible].
+    Irreversible,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EscalationPolicy {
+    /// The commitment for every read, `processed` by default.
+    pub read: CommitmentConfig,
+    /// The commitment to re-validate irreversible reads at, `confirmed` by default.
+    pub irreversible: CommitmentConfig,
+    /// Skip the read at [EscalationPolicy::read] if the node reports itself unhealthy,
+    /// since it is likely behind or on a minority fork.
+    pub check_health: bool,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        Self {
+            read: CommitmentConfig::processed(),
+            irreversible: CommitmentConfig::confirmed(),
+            check_health: true,
+        }
+    }
+}
+
+/// Both reads of an escalated value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscalatedRead<T> {
+    /// `None` if skipped because the node was unhealthy.
+    pub read: Option<Slotted<T>>,
+    /// Present for irreversible reads, or reads from an unhealthy node.
+    pub validated: Option<Slotted<T>>,
+}
+
+impl<T> EscalatedRead<T> {
+    /// The validated value if there is one, otherwise the read value.
+    pub fn value(&self) -> &Slotted<T> {
+        self.validated
+            .as_ref()
+            .or(self.read.as_ref())
+            .expect("an escalated read has at least one value")
+    }
+
+    pub fn is_validated(&self) -> bool {
+        self.validated.is_some()
+    }
+
+    /// How many slots the validated value trails the read value by.
+    pub fn slot_lag(&self) -> Option<Slot> {
+        match (&self.read, &self.validated) {
+            (Some(read), Some(validated)) => Some(read.slot.saturating_sub(validated.slot)),
+            _ => None,
+        }
+    }
+}
+
+impl<T: PartialEq> EscalatedRead<T> {
+    /// Whether the read value has not yet reached the validated commitment,
+    /// e.g. because a recent transaction is not yet confirmed, or was on an abandoned fork.
+    pub fn diverged(&self) -> bool {
+        match (&self.read, &self.validated) {
+            (Some(read), Some(validated)) => read.value != validated.value,
+            _ => false,
+        }
+    }
+}
+
+impl EscalationPolicy {
+    /// Read with `fetch`, which is called with the commitment to read at.
+    pub async fn read<T, F, Fut>(
+        &self,
+        client: &RpcClient,
+        purpose: ReadPurpose,
+        fetch: F,
+    ) -> ClientResult<EscalatedRead<T>>
+    where
+        F: Fn(CommitmentConfig) -> Fut,
+        Fut: Future<Output = ClientResult<Response<T>>>,
+    {
+        let healthy = !self.check_health || client.get_health().await.is_ok();
+        let read = if healthy {
+            Some(fetch(self.read).await?.into())
+        } else {
+            None
+        };
+        let validated = if !healthy || purpose == ReadPurpose::Irreversible {
+            Some(fetch(self.irreversible).await?.into())
+        } else {
+            None
+        };
+        Ok(EscalatedRead { read, validated })
+    }
+
+    pub async fn get_account(
+        &self,
+        client: &RpcClient,
+        pubkey: &Pubkey,
+        purpose: ReadPurpose,
+    ) -> ClientResult<EscalatedRead<Option<Account>>> {
+        self.read(client, purpose, |commitment| {
+            client.get_account_with_commitment(pubkey, commitment)
+        })
+        .await
+    }
+
+    pub async fn get_balance(
+        &self,
+        client: &RpcClient,
+        pubkey: &Pubkey,
+        purpose: ReadPurpose,
+    ) -> ClientResult<EscalatedRead<u64>> {
+        self.read(client, purpose, |commitment| {
+            client.get_balance_with_commitment(pubkey, commitment)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use jsonrpc_core::{futures::future, Error, IoHandler, Params};
+    use serde_json::{json, Value};
+
+    /// The methods of a node whose processed balance is ahead of its confirmed balance.
+    fn node_io(healthy: bool) -> IoHandler {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        io.add_method("getHealth", move |_: Params| {
+            if healthy {
+                future::ok(json!("ok"))
+            } else {
+                future::err(Error::new(jsonrpc_core::ErrorCode::ServerError(-32005)))
+            }
+        });
+        io.add_method("getBalance", |params: Params| {
+            let params: Vec<Value> = params.parse().unwrap();
+            let (slot, balance) = match params[1]["commitment"].as_str() {
+                Some("processed") => (105, 10),
+                _ => (100, 5),
+            };
+            future::ok(json!({ "context": { "slot": slot }, "value": balance }))
+        });
+        io
+    }
+
+    #[tokio::test]
+    async fn irreversible_reads_are_revalidated() {
+        let policy = EscalationPolicy::default();
+        let pubkey = Pubkey::new_unique();
+        let client = RpcClient::new(test_node::spawn_node(node_io(true)));
+
+        let read = policy
+            .get_balance(&client, &pubkey, ReadPurpose::Reversible)
+            .await
+            .unwrap();
+        assert!(!read.is_validated());
+        assert_eq!(
+            read.value(),
+            &Slotted {
+                slot: 105,
+                value: 10
+            }
+        );
+
+        let read = policy
+            .get_balance(&client, &pubkey, ReadPurpose::Irreversible)
+            .await
+            .unwrap();
+        assert_eq!(
+            read.value(),
+            &Slotted {
+                slot: 100,
+                value: 5
+            }
+        );
+        assert!(read.diverged());
+        assert_eq!(read.slot_lag(), Some(5));
+
+        // An unhealthy node's processed state isn't trusted, even for reversible reads.
+        let client = RpcClient::new(test_node::spawn_node(node_io(false)));
+        let read = policy
+            .get_balance(&client, &pubkey, ReadPurpose::Reversible)
+            .await
+            .unwrap();
+        assert_eq!(read.read, None);
+        assert_eq!(
+            read.value(),
+            &Slotted {
+                slot: 100,
+                value: 5
+            }
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Read accounts after a transaction without reading the state from before it.
+//! A load-balanced endpoint can confirm a signature on one node, then serve the next read from
+//! another node that hasn't reached that slot yet. [ReadAfterWrite] waits for the signature, then
+//! reads with a `minContextSlot` of the confirmation slot, retrying while the node is behind.
+use crate::commitment::Slotted;
+use crate::scan::MAX_MULTIPLE_ACCOUNTS;
+use solana_account_decoder::UiAccountEncoding;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client::rpc_client::SerializableTransaction;
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use solana_rpc_client_api::config::RpcAccountInfoConfig;
+use solana_rpc_client_api::custom_error::JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED;
+use solana_rpc_client_api::request::RpcError;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::TransactionError;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::time::sleep;
+
+#[derive(Debug, Error)]
+pub enum ConsistencyError {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error("Transaction {signature} failed: {error}")]
+    TransactionFailed {
+        signature: Signature,
+        error: TransactionError,
+    },
+    #[error("Transaction {signature} did not reach {commitment:?} commitment in time")]
+    NotConfirmed {
+        signature: Signature,
+        commitment: CommitmentLevel,
+    },
+    #[error("Accounts could not be read at slot {min_slot} or later in time")]
+    Stale { min_slot: Slot },
+}
+
+/// Accounts read after a transaction confirmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmedRead {
+    /// The slot the transaction was processed in.
+    pub confirmation_slot: Slot,
+    /// In the order requested. The slot is at least the confirmation slot. When more than
+    /// [MAX_MULTIPLE_ACCOUNTS] accounts are read, it is the lowest slot of any batch.
+    pub accounts: Slotted<Vec<Option<Account>>>,
+}
+
+pub struct ReadAfterWrite {
+    client: Arc<RpcClient>,
+    /// The commitment to wait for, and to read at. `confirmed` by default.
+    pub commitment: CommitmentConfig,
+    pub poll_interval: Duration,
+    /// How long to wait for the confirmation, and then for a fresh enough read.
+    pub timeout: Duration,
+}
+
+impl ReadAfterWrite {
+    pub fn new(client: impl Into<Arc<RpcClient>>) -> Self {
+        Self {
+            client: client.into(),
+            commitment: CommitmentConfig::confirmed(),
+            poll_interval: Duration::from_millis(500),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    pub fn client(&self) -> &RpcClient {
+        &self.client
+    }
+
+    /// Send a transaction, then [ReadAfterWrite::confirm_and_fetch].
+    pub async fn send_and_fetch(
+        &self,
+        transaction: &impl SerializableTransaction,
+        accounts: &[Pubkey],
+    ) -> Result<ConfirmedRead, ConsistencyError> {
+        let signature = self.client.send_transaction(transaction).await?;
+        self.confirm_and_fetch(&signature, accounts).await
+    }
+
+    /// Wait for `signature` to reach the commitment, then read `accounts`
+    /// at the slot it was processed in or later.
+    pub async fn confirm_and_fetch(
+        &self,
+        signature: &Signature,
+        accounts: &[Pubkey],
+    ) -> Result<ConfirmedRead, ConsistencyError> {
+        let confirmation_slot = self.wait_for_confirmation(signature).await?;
+        let accounts = self.fetch_at_slot(accounts, confirmation_slot).await?;
+        Ok(ConfirmedRead {
+            confirmation_slot,
+            accounts,
+        })
+    }
+
+    /// The slot `signature` was processed in, once it reaches the commitment.
+    pub async fn wait_for_confirmation(
+        &self,
+        signature: &Signature,
+    ) -> Result<Slot, ConsistencyError> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let status = self
+                .client
+                .get_signature_statuses(&[*signature])
+                .await?
+                .value
+                .pop()
+                .flatten();
+            if let Some(status) = status.filter(|s| s.satisfies_commitment(self.commitment)) {
+                return match status.err {
+                    None => Ok(status.slot),
+                    Some(error) => Err(ConsistencyError::TransactionFailed {
+                        signature: *signature,
+                        error,
+                    }),
+                };
+            }
+            if Instant::now() >= deadline {
+                return Err(ConsistencyError::NotConfirmed {
+                    signature: *signature,
+                    commitment: self.commitment.commitment,
+                });
+            }
+            sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Read `accounts` at `min_slot` or later, retrying while the node serving the read is behind.
+    pub async fn fetch_at_slot(
+        &self,
+        accounts: &[Pubkey],
+        min_slot: Slot,
+    ) -> Result<Slotted<Vec<Option<Account>>>, ConsistencyError> {
+        let deadline = Instant::now() + self.timeout;
+        let mut read = Slotted {
+            slot: Slot::MAX,
+            value: Vec::with_capacity(accounts.len()),
+        };
+        for batch in accounts.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let batch = loop {
+                if let Some(batch) = self.try_fetch_at_slot(batch, min_slot).await? {
+                    break batch;
+                }
+                if Instant::now() >= deadline {
+                    return Err(ConsistencyError::Stale { min_slot });
+                }
+                sleep(self.poll_interval).await;
+            };
+            read.slot = read.slot.min(batch.slot);
+            read.value.extend(batch.value);
+        }
+        if accounts.is_empty() {
+            read.slot = min_slot;
+        }
+        Ok(read)
+    }
+
+    /// `None` if the node has not reached `min_slot`.
+    async fn try_fetch_at_slot(
+        &self,
+        accounts: &[Pubkey],
+        min_slot: Slot,
+    ) -> Result<Option<Slotted<Vec<Option<Account>>>>, ConsistencyError> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64Zstd),
+            data_slice: None,
+            commitment: Some(self.commitment),
+            min_context_slot: Some(min_slot),
+        };
+        match self
+            .client
+            .get_multiple_accounts_with_config(accounts, config)
+            .await
+        {
+            // Not every node honors `minContextSlot`, so check the slot as well.
+            Ok(response) if response.context.slot >= min_slot => Ok(Some(response.into())),
+            Ok(_) => Ok(None),
+            Err(e) if is_min_context_slot_not_reached(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn is_min_context_slot_not_reached(error: &ClientError) -> bool {
+    matches!(
+        error.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. })
+            if *code == JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use jsonrpc_core::{futures::future, Error, ErrorCode, IoHandler, Params};
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// The methods of a node that confirms the transaction at slot 100 on the second poll. Its first account read
+    /// fails with "min context slot not reached", and its second is served from slot 99 regardless.
+    fn node_io(multiple_accounts_calls: Arc<AtomicU64>) -> IoHandler {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        let polls = AtomicU64::new(0);
+        io.add_method("getSignatureStatuses", move |_: Params| {
+            let status = match polls.fetch_add(1, Ordering::SeqCst) {
+                0 => Value::Null,
+                _ => json!({
+                    "slot": 100,
+                    "confirmations": 0,
+                    "err": null,
+                    "status": { "Ok": null },
+                    "confirmationStatus": "confirmed",
+                }),
+            };
+            future::ok(json!({ "context": { "slot": 100 }, "value": [status] }))
+        });
+        io.add_method("getMultipleAccounts", move |params: Params| {
+            let params: Vec<Value> = params.parse().unwrap();
+            assert_eq!(params[1]["minContextSlot"], json!(100));
+            let slot = match multiple_accounts_calls.fetch_add(1, Ordering::SeqCst) {
+                0 => {
+                    return future::err(Error {
+                        code: ErrorCode::ServerError(
+                            JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED,
+                        ),
+                        message: "Minimum context slot has not been reached".to_string(),
+                        data: None,
+                    })
+                }
+                1 => 99,
+                _ => 101,
+            };
+            let account = json!({
+                "lamports": slot,
+                "data": ["", "base64"],
+                "owner": Pubkey::default().to_string(),
+                "executable": false,
+                "rentEpoch": 0,
+            });
+            future::ok(json!({ "context": { "slot": slot }, "value": [account] }))
+        });
+        io
+    }
+
+    #[tokio::test]
+    async fn accounts_are_read_after_the_confirmation_slot() {
+        let calls = Arc::new(AtomicU64::new(0));
+        let mut reader = ReadAfterWrite::new(RpcClient::new(test_node::spawn_node(node_io(
+            calls.clone(),
+        ))));
+        reader.poll_interval = Duration::from_millis(10);
+
+        let read = reader
+            .confirm_and_fetch(&Signature::default(), &[Pubkey::new_unique()])
+            .await
+            .unwrap();
+        assert_eq!(read.confirmation_slot, 100);
+        assert_eq!(read.accounts.slot, 101);
+        assert_eq!(read.accounts.value[0].as_ref().unwrap().lamports, 101);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,135 @@
+//! Policies for choosing a transaction's compute unit price, in micro-lamports,
+//! on its first broadcast and on each rebroadcast by a [crate::sender::TransactionSender].
+use async_trait::async_trait;
+use solana_devtools_tx::priority_fee::percentile;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Result as ClientResult;
+use solana_sdk::pubkey::Pubkey;
+
+#[async_trait]
+pub trait FeeStrategy: Send + Sync {
+    /// The compute unit price for the given attempt, where `attempt` 0 is the first broadcast.
+    /// `writable_accounts` are the transaction's write-locked accounts, which determine the
+    /// fees it competes with.
+    async fn compute_unit_price(
+        &self,
+        client: &RpcClient,
+        writable_accounts: &[Pubkey],
+        attempt: usize,
+    ) -> ClientResult<u64>;
+}
+
+/// The same price on every attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticPrice(pub u64);
+
+#[async_trait]
+impl FeeStrategy for StaticPrice {
+    async fn compute_unit_price(&self, _: &RpcClient, _: &[Pubkey], _: usize) -> ClientResult<u64> {
+        Ok(self.0)
+    }
+}
+
+/// A percentile of the fees paid in recent slots by transactions that locked the same
+/// writable accounts, as reported by `getRecentPrioritizationFees`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecentFeePercentile {
+    /// From 0 to 100, e.g. 50 for the median.
+    pub percentile: u8,
+    /// Paid if recent fees are lower, or there are none.
+    pub min_price: u64,
+}
+
+impl RecentFeePercentile {
+    pub fn median() -> Self {
+        Self {
+            percentile: 50,
+            min_price: 0,
+        }
+    }
+}
+
+#[async_trait]
+impl FeeStrategy for RecentFeePercentile {
+    async fn compute_unit_price(
+        &self,
+        client: &RpcClient,
+        writable_accounts: &[Pubkey],
+        _: usize,
+    ) -> ClientResult<u64> {
+        let fees = client
+            .get_recent_prioritization_fees(writable_accounts)
+            .await?
+            .into_iter()
+            .map(|fee| fee.prioritization_fee);
+        Ok(percentile(fees, self.percentile).max(self.min_price))
+    }
+}
+
+/// Uses the inner strategy on the first attempt, and raises the price by
+/// `bump_percent` on each rebroadcast, up to `max_price`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscalateOnRetry<S> {
+    pub inner: S,
+    pub bump_percent: u64,
+    pub max_price: u64,
+}
+
+#[async_trait]
+impl<S: FeeStrategy> FeeStrategy for EscalateOnRetry<S> {
+    async fn compute_unit_price(
+        &self,
+        client: &RpcClient,
+        writable_accounts: &[Pubkey],
+        attempt: usize,
+    ) -> ClientResult<u64> {
+        let mut price = self
+            .inner
+            .compute_unit_price(client, writable_accounts, attempt)
+            .await?;
+        for _ in 0..attempt {
+            // Always bump by at least one micro-lamport, so a zero price escalates too.
+            let bump = (price.saturating_mul(self.bump_percent) / 100).max(1);
+            price = price.saturating_add(bump);
+            if price >= self.max_price {
+                break;
+            }
+        }
+        Ok(price.min(self.max_price))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn strategies_choose_prices() {
+        let client = RpcClient::new_mock("succeeds".to_string());
+        let escalate = EscalateOnRetry {
+            inner: StaticPrice(1_000),
+            bump_percent: 50,
+            max_price: 3_000,
+        };
+        let mut prices = vec![];
+        for attempt in 0..5 {
+            prices.push(
+                escalate
+                    .compute_unit_price(&client, &[], attempt)
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert_eq!(prices, vec![1_000, 1_500, 2_250, 3_000, 3_000]);
+
+        let from_zero = EscalateOnRetry {
+            inner: StaticPrice(0),
+            bump_percent: 100,
+            max_price: u64::MAX,
+        };
+        assert_eq!(
+            from_zero.compute_unit_price(&client, &[], 3).await.unwrap(),
+            4
+        );
+    }
+}
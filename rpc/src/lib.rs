@@ -2,7 +2,22 @@
 //! which can then be used to create `RpcClient` instances using `RpcClient::new_sender`.
 //! This gives a greater degree of low-level configurability to a RPC client behavior,
 //! including rate limiting, request filtering, retry logic, and more.
-pub mod service;
+pub mod auth;
+pub mod blockhash;
+pub mod commitment;
+pub mod consistency;
+pub mod fee_strategy;
 pub mod middleware;
+#[cfg(feature = "probe")]
+pub mod probe;
+pub mod replay;
+pub mod scan;
+pub mod schedule;
+pub mod sender;
+pub mod service;
+pub mod tracker;
+
+#[cfg(test)]
+pub(crate) mod test_node;
 
 pub use service::*;
@@ -0,0 +1,299 @@
+//! Cache responses to idempotent requests, keyed by method and params, for a per-method TTL.
+//! A cached response is also dropped once a response at the same commitment reports a newer slot,
+//! so a cached account is never older than the freshest state the client has already seen.
+//! Expired and stale responses are swept whenever a response is cached, and the cache holds
+//! at most [CacheMiddleware::with_max_entries] responses.
+use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use serde_json::Value;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_rpc_client_api::request::RpcRequest;
+use solana_sdk::clock::Slot;
+use std::collections::HashMap;
+use std::future::{ready, Future};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::Service;
+
+/// The commitment nodes use when a request doesn't specify one.
+const DEFAULT_COMMITMENT: &str = "finalized";
+const DEFAULT_MAX_ENTRIES: usize = 1024;
+
+struct CachedResponse {
+    value: Value,
+    /// The commitment of the request.
+    commitment: String,
+    /// The response's `context.slot`, if it has one.
+    slot: Option<Slot>,
+    expires: Instant,
+}
+
+impl CachedResponse {
+    /// Neither expired, nor older than the latest slot seen at its commitment.
+    fn is_live(&self, latest_slots: &HashMap<String, Slot>, now: Instant) -> bool {
+        let latest_slot = latest_slots.get(&self.commitment).copied();
+        let stale = self.slot.is_some_and(|slot| latest_slot > Some(slot));
+        !stale && self.expires > now
+    }
+}
+
+#[derive(Default)]
+struct CacheState {
+    responses: HashMap<(RpcRequest, String), CachedResponse>,
+    /// The newest slot seen in a response, by commitment.
+    latest_slots: HashMap<String, Slot>,
+}
+
+pub struct CacheMiddleware<S> {
+    inner: S,
+    ttls: HashMap<RpcRequest, Duration>,
+    max_entries: usize,
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl<S> CacheMiddleware<S> {
+    /// Caches `getAccountInfo` and `getMultipleAccounts` for 1 second,
+    /// `getProgramAccounts` for 5 seconds, and `getBlock` for 60 seconds,
+    /// holding up to 1024 responses.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            ttls: HashMap::from([
+                (RpcRequest::GetAccountInfo, Duration::from_secs(1)),
+                (RpcRequest::GetMultipleAccounts, Duration::from_secs(1)),
+                (RpcRequest::GetProgramAccounts, Duration::from_secs(5)),
+                (RpcRequest::GetBlock, Duration::from_secs(60)),
+            ]),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            state: Default::default(),
+        }
+    }
+
+    /// The most responses to hold. When the cache is full, caching a response evicts
+    /// the one closest to expiring.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Set how long responses to a method are cached. A zero TTL disables caching for it.
+    /// Only idempotent methods should be cached.
+    pub fn with_ttl(mut self, method: RpcRequest, ttl: Duration) -> Self {
+        if ttl.is_zero() {
+            self.ttls.remove(&method);
+        } else {
+            self.ttls.insert(method, ttl);
+        }
+        self
+    }
+}
+
+/// The commitment in a request's config object, if any.
+fn commitment(params: &Value) -> String {
+    params
+        .as_array()
+        .and_then(|params| {
+            params
+                .iter()
+                .find_map(|param| param.get("commitment").and_then(Value::as_str))
+        })
+        .unwrap_or(DEFAULT_COMMITMENT)
+        .to_string()
+}
+
+/// The slot a response was read at: its `context.slot`, or the result of `getSlot`.
+fn response_slot(request: &RpcRequest, response: &Value) -> Option<Slot> {
+    match request {
+        RpcRequest::GetSlot => response.as_u64(),
+        _ => response.get("context")?.get("slot")?.as_u64(),
+    }
+}
+
+impl CacheState {
+    fn get(&mut self, key: &(RpcRequest, String)) -> Option<Value> {
+        let cached = self.responses.get(key)?;
+        if cached.is_live(&self.latest_slots, Instant::now()) {
+            return Some(cached.value.clone());
+        }
+        self.responses.remove(key);
+        None
+    }
+
+    /// Sweep expired and stale responses, then evict the responses closest to expiring
+    /// until there is room for one more.
+    fn insert(&mut self, key: (RpcRequest, String), cached: CachedResponse, max_entries: usize) {
+        let now = Instant::now();
+        let latest_slots = &self.latest_slots;
+        self.responses
+            .retain(|_, cached| cached.is_live(latest_slots, now));
+        self.responses.remove(&key);
+        while !self.responses.is_empty() && self.responses.len() >= max_entries {
+            let oldest = self
+                .responses
+                .iter()
+                .min_by_key(|(_, cached)| cached.expires)
+                .map(|(key, _)| key.clone())
+                .unwrap();
+            self.responses.remove(&oldest);
+        }
+        if max_entries > 0 {
+            self.responses.insert(key, cached);
+        }
+    }
+
+    fn observe_slot(&mut self, commitment: String, slot: Slot) {
+        let latest = self.latest_slots.entry(commitment).or_default();
+        *latest = slot.max(*latest);
+    }
+}
+
+impl<S> Service<RpcSenderRequest> for CacheMiddleware<S>
+where
+    S: Service<
+            RpcSenderRequest,
+            Error = ClientError,
+            Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+        > + Send
+        + Sync,
+{
+    type Response = Value;
+    type Error = ClientError;
+
+    type Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RpcSenderRequest) -> Self::Future {
+        let commitment = commitment(&req.1);
+        let ttl = self.ttls.get(&req.0).copied();
+        let key = (req.0, req.1.to_string());
+        if ttl.is_some() {
+            if let Some(value) = self.state.lock().unwrap().get(&key) {
+                return Box::pin(ready(Ok(value)));
+            }
+        }
+        let response = self.inner.call(req);
+        let state = self.state.clone();
+        let max_entries = self.max_entries;
+        Box::pin(async move {
+            let value = response.await?;
+            let slot = response_slot(&key.0, &value);
+            let mut state = state.lock().unwrap();
+            if let Some(slot) = slot {
+                state.observe_slot(commitment.clone(), slot);
+            }
+            if let Some(ttl) = ttl {
+                let cached = CachedResponse {
+                    value: value.clone(),
+                    commitment,
+                    slot,
+                    expires: Instant::now() + ttl,
+                };
+                state.insert(key, cached, max_entries);
+            }
+            Ok(value)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use jsonrpc_core::{futures::future, IoHandler, Params};
+    use serde_json::json;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use solana_rpc_client::rpc_client::RpcClientConfig;
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use solana_sdk::pubkey::Pubkey;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use tower::ServiceBuilder;
+
+    /// The methods of a node whose slot advances by one with every `getAccountInfo` call.
+    fn node_io(calls: Arc<AtomicU64>) -> IoHandler {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        io.add_method("getAccountInfo", move |_: Params| {
+            let slot = 100 + calls.fetch_add(1, Ordering::Relaxed);
+            future::ok(json!({
+                "context": { "slot": slot },
+                "value": {
+                    "data": ["", "base64"],
+                    "executable": false,
+                    "lamports": slot,
+                    "owner": Pubkey::default().to_string(),
+                    "rentEpoch": 0,
+                },
+            }))
+        });
+        io.add_method("getBalance", |_: Params| {
+            future::ok(json!({ "context": { "slot": 200 }, "value": 1 }))
+        });
+        io
+    }
+
+    #[tokio::test]
+    async fn responses_are_cached_until_a_newer_slot() {
+        let calls = Arc::new(AtomicU64::new(0));
+        let sender = HttpSenderService::new_from_builder(
+            test_node::spawn_node(node_io(calls.clone())),
+            ServiceBuilder::new().layer_fn(CacheMiddleware::new),
+        );
+        let client = RpcClient::new_sender(
+            sender,
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        let pubkey = Pubkey::new_unique();
+
+        assert_eq!(client.get_account(&pubkey).await.unwrap().lamports, 100);
+        assert_eq!(client.get_account(&pubkey).await.unwrap().lamports, 100);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        // A newer slot at another commitment doesn't invalidate the cache.
+        client
+            .get_balance_with_commitment(&pubkey, CommitmentConfig::processed())
+            .await
+            .unwrap();
+        assert_eq!(client.get_account(&pubkey).await.unwrap().lamports, 100);
+
+        // A newer slot at the same commitment does.
+        client.get_balance(&pubkey).await.unwrap();
+        assert_eq!(client.get_account(&pubkey).await.unwrap().lamports, 101);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn full_caches_sweep_then_evict() {
+        let now = Instant::now();
+        let cached = |commitment: &str, slot, expires_in| CachedResponse {
+            value: Value::Null,
+            commitment: commitment.to_string(),
+            slot: Some(slot),
+            expires: now + Duration::from_secs(expires_in),
+        };
+        let key = |n: u64| (RpcRequest::GetAccountInfo, n.to_string());
+        let mut state = CacheState::default();
+        state.insert(key(0), cached("confirmed", 10, 30), 2);
+        state.insert(key(1), cached("confirmed", 10, 20), 2);
+        // Full, so the response closest to expiring is evicted.
+        state.insert(key(2), cached("finalized", 10, 10), 2);
+        assert!(state.get(&key(0)).is_some());
+        assert!(state.get(&key(1)).is_none());
+
+        // Stale responses are swept first.
+        state.observe_slot("confirmed".to_string(), 11);
+        state.insert(key(3), cached("confirmed", 11, 5), 2);
+        assert_eq!(state.responses.len(), 2);
+        assert!(state.get(&key(2)).is_some());
+        assert!(state.get(&key(3)).is_some());
+
+        state.insert(key(4), cached("confirmed", 11, 5), 0);
+        assert!(state.get(&key(4)).is_none());
+    }
+}
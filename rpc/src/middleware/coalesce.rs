@@ -0,0 +1,349 @@
+//! Coalesce concurrent identical requests, i.e. the same method and params,
+//! into a single call to the inner service, and share its response with every caller.
+//! Requests that change state, like `sendTransaction`, are never coalesced.
+use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use futures_util::future::{FutureExt, Shared};
+use serde_json::Value;
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use solana_rpc_client_api::request::{RpcError, RpcRequest, RpcResponseErrorData};
+use solana_sdk::signature::SignerError;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::oneshot;
+use tower::{Service, ServiceExt};
+
+/// The first caller's response, with `None` for errors that can't be copied.
+type SharedResponse = Shared<oneshot::Receiver<Result<Value, Option<Arc<ClientError>>>>>;
+type InFlight = Arc<Mutex<HashMap<(RpcRequest, String), SharedResponse>>>;
+
+pub struct CoalescingMiddleware<S> {
+    inner: Arc<tokio::sync::Mutex<S>>,
+    in_flight: InFlight,
+    coalesced: Arc<AtomicU64>,
+}
+
+impl<S> CoalescingMiddleware<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner: Arc::new(tokio::sync::Mutex::new(inner)),
+            in_flight: Default::default(),
+            coalesced: Default::default(),
+        }
+    }
+
+    /// A shared count of the requests that were served by another caller's call.
+    pub fn coalesced_count(&self) -> Arc<AtomicU64> {
+        self.coalesced.clone()
+    }
+}
+
+/// Whether a request can be served by another caller's identical request.
+pub fn is_coalescable(request: &RpcRequest) -> bool {
+    !matches!(
+        request,
+        RpcRequest::SendTransaction | RpcRequest::RequestAirdrop
+    )
+}
+
+/// [ClientError] isn't `Clone`, so the callers served by another caller's call receive
+/// a copy of the same kind, or `None` for transport errors, which can't be constructed.
+fn copy_error(error: &ClientError) -> Option<ClientError> {
+    let kind = match error.kind() {
+        ClientErrorKind::Io(e) => ClientErrorKind::Io(io::Error::new(e.kind(), e.to_string())),
+        ClientErrorKind::Reqwest(_) => return None,
+        ClientErrorKind::RpcError(e) => ClientErrorKind::RpcError(copy_rpc_error(e)),
+        ClientErrorKind::SerdeJson(e) => {
+            ClientErrorKind::SerdeJson(serde::de::Error::custom(e.to_string()))
+        }
+        ClientErrorKind::SigningError(e) => {
+            ClientErrorKind::SigningError(SignerError::Custom(e.to_string()))
+        }
+        ClientErrorKind::TransactionError(e) => ClientErrorKind::TransactionError(e.clone()),
+        ClientErrorKind::Custom(e) => ClientErrorKind::Custom(e.clone()),
+    };
+    Some(ClientError {
+        request: error.request,
+        kind,
+    })
+}
+
+fn copy_rpc_error(error: &RpcError) -> RpcError {
+    match error {
+        RpcError::RpcRequestError(e) => RpcError::RpcRequestError(e.clone()),
+        RpcError::RpcResponseError {
+            code,
+            message,
+            data,
+        } => RpcError::RpcResponseError {
+            code: *code,
+            message: message.clone(),
+            data: match data {
+                RpcResponseErrorData::Empty => RpcResponseErrorData::Empty,
+                RpcResponseErrorData::SendTransactionPreflightFailure(result) => {
+                    RpcResponseErrorData::SendTransactionPreflightFailure(result.clone())
+                }
+                RpcResponseErrorData::NodeUnhealthy { num_slots_behind } => {
+                    RpcResponseErrorData::NodeUnhealthy {
+                        num_slots_behind: *num_slots_behind,
+                    }
+                }
+            },
+        },
+        RpcError::ParseError(e) => RpcError::ParseError(e.clone()),
+        RpcError::ForUser(e) => RpcError::ForUser(e.clone()),
+    }
+}
+
+/// Removes a call from the in-flight calls when it completes or is dropped.
+struct InFlightGuard {
+    in_flight: InFlight,
+    key: (RpcRequest, String),
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// Call the inner service once it's ready. The call is only made when the returned future is
+/// polled, so that callers served by another caller's call never reach the inner service.
+async fn call_inner<S>(
+    inner: Arc<tokio::sync::Mutex<S>>,
+    req: RpcSenderRequest,
+) -> RpcSenderResponse
+where
+    S: Service<
+        RpcSenderRequest,
+        Error = ClientError,
+        Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+    >,
+{
+    let response = {
+        let mut service = inner.lock().await;
+        service.ready().await?.call(req)
+    };
+    response.await
+}
+
+impl<S> Service<RpcSenderRequest> for CoalescingMiddleware<S>
+where
+    S: Service<
+            RpcSenderRequest,
+            Error = ClientError,
+            Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+        > + Send
+        + 'static,
+{
+    type Response = Value;
+    type Error = ClientError;
+
+    type Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// The first caller makes the call, and keeps its result. Other callers receive a copy,
+    /// or make their own call if the first caller is dropped, or its error can't be copied.
+    fn call(&mut self, req: RpcSenderRequest) -> Self::Future {
+        let inner = self.inner.clone();
+        if !is_coalescable(&req.0) {
+            return Box::pin(call_inner(inner, req));
+        }
+        let key = (req.0, req.1.to_string());
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if let Some(shared) = in_flight.get(&key).cloned() {
+            drop(in_flight);
+            self.coalesced.fetch_add(1, Ordering::Relaxed);
+            return Box::pin(async move {
+                let error = match shared.await {
+                    Ok(Ok(value)) => return Ok(value),
+                    Ok(Err(Some(error))) => copy_error(&error),
+                    Ok(Err(None)) | Err(_) => None,
+                };
+                match error {
+                    Some(error) => Err(error),
+                    None => call_inner(inner, req).await,
+                }
+            });
+        }
+        let (sender, receiver) = oneshot::channel();
+        in_flight.insert(key.clone(), receiver.shared());
+        drop(in_flight);
+        let guard = InFlightGuard {
+            in_flight: self.in_flight.clone(),
+            key,
+        };
+        Box::pin(async move {
+            let result = call_inner(inner, req).await;
+            drop(guard);
+            let shared = match &result {
+                Ok(value) => Ok(value.clone()),
+                Err(e) => Err(copy_error(e).map(Arc::new)),
+            };
+            let _ = sender.send(shared);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use futures_util::future::join_all;
+    use jsonrpc_core::{futures::future, IoHandler, Params};
+    use jsonrpc_http_server::ServerBuilder;
+    use serde_json::json;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use solana_sdk::pubkey::Pubkey;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+    use tower::ServiceBuilder;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn identical_requests_share_one_call() {
+        let calls = Arc::new(AtomicU64::new(0));
+        let counter = calls.clone();
+        let version_calls = Arc::new(AtomicU64::new(0));
+        let version_counter = version_calls.clone();
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", move |_: Params| {
+            version_counter.fetch_add(1, Ordering::Relaxed);
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        io.add_method("getBalance", move |_: Params| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            // Keep the call in flight while the other callers arrive.
+            thread::sleep(Duration::from_millis(300));
+            future::ok(json!({ "context": { "slot": 100 }, "value": 50 }))
+        });
+        let url = test_node::spawn_server(move || ServerBuilder::new(io).threads(4));
+
+        let sender = HttpSenderService::new_from_builder(
+            url,
+            ServiceBuilder::new().layer_fn(CoalescingMiddleware::new),
+        );
+        let client = RpcClient::new_sender(sender, Default::default());
+        let pubkey = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let balances = join_all(
+            [pubkey, pubkey, pubkey, other]
+                .iter()
+                .map(|pubkey| client.get_balance(pubkey)),
+        )
+        .await;
+        assert!(balances.into_iter().all(|balance| balance.unwrap() == 50));
+        // One call per distinct pubkey.
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        // Coalesced requests never reach the transport.
+        assert_eq!(
+            client.get_transport_stats().request_count as u64,
+            calls.load(Ordering::Relaxed) + version_calls.load(Ordering::Relaxed)
+        );
+
+        // Requests that finished are not reused.
+        client.get_balance(&pubkey).await.unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn coalesced_errors_keep_their_kind() {
+        // Answers `getBalance` with a 500 status, after the other callers arrive.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let calls = Arc::new(AtomicU64::new(0));
+        let counter = calls.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let body: Value = serde_json::from_slice(&body).unwrap();
+                let (status, response) = if body["method"] == "getVersion" {
+                    let version = json!({ "jsonrpc": "2.0", "id": body["id"], "result": { "solana-core": "1.17.13" } });
+                    ("200 OK", version.to_string())
+                } else {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    thread::sleep(Duration::from_millis(300));
+                    ("500 Internal Server Error", String::new())
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    response.len(),
+                    response
+                )
+                .unwrap();
+            }
+        });
+
+        let coalesced = Arc::new(Mutex::new(None));
+        let coalesced_count = coalesced.clone();
+        let sender = HttpSenderService::new_from_builder(
+            url,
+            ServiceBuilder::new().layer_fn(move |inner| {
+                let middleware = CoalescingMiddleware::new(inner);
+                *coalesced_count.lock().unwrap() = Some(middleware.coalesced_count());
+                middleware
+            }),
+        );
+        let client = RpcClient::new_sender(sender, Default::default());
+        let pubkey = Pubkey::new_unique();
+
+        let balances = join_all((0..3).map(|_| client.get_balance(&pubkey))).await;
+        for balance in balances {
+            assert!(matches!(
+                balance.unwrap_err().kind(),
+                ClientErrorKind::Reqwest(e) if e.status().is_some_and(|s| s.is_server_error())
+            ));
+        }
+        // Transport errors can't be copied, so the other callers made their own calls.
+        let coalesced = coalesced.lock().unwrap().clone().unwrap();
+        assert_eq!(coalesced.load(Ordering::Relaxed), 2);
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        let error = ClientError::from(RpcError::RpcResponseError {
+            code: -32005,
+            message: "Node is unhealthy".to_string(),
+            data: RpcResponseErrorData::NodeUnhealthy {
+                num_slots_behind: Some(10),
+            },
+        });
+        assert!(matches!(
+            copy_error(&error).unwrap().kind(),
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: -32005,
+                data: RpcResponseErrorData::NodeUnhealthy {
+                    num_slots_behind: Some(10)
+                },
+                ..
+            })
+        ));
+    }
+}
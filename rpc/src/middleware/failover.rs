@@ -0,0 +1,299 @@
+//! Send each request to one of several RPC endpoints, moving on to the next endpoint
+//! when one fails with a 5xx status, a timeout or connection failure, or reports that
+//! it is unhealthy. Other errors, like invalid params, are returned as is.
+use crate::json_rpc::stats_updater::TransportStats;
+use crate::json_rpc::HttpClientService;
+use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use serde_json::Value;
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use solana_rpc_client_api::custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY;
+use solana_rpc_client_api::request::{RpcError, RpcRequest};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tower::{Service, ServiceExt};
+
+/// Called with each request and the URL of the endpoint that served it.
+pub type OnServed = Arc<dyn Fn(&RpcRequest, &str) + Send + Sync>;
+
+/// The health of one endpoint, as observed from the requests sent to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStatus {
+    pub url: String,
+    pub requests: u64,
+    pub failures: u64,
+    pub consecutive_failures: u64,
+    /// Set after a failure. The endpoint is skipped until then, unless every endpoint is.
+    pub unhealthy_until: Option<Instant>,
+}
+
+impl EndpointStatus {
+    pub fn is_healthy(&self) -> bool {
+        self.unhealthy_until
+            .is_none_or(|unhealthy_until| unhealthy_until <= Instant::now())
+    }
+}
+
+/// A cheap-clone view of the endpoints' health, which remains available
+/// after the [FailoverService] is moved into an `RpcClient`.
+#[derive(Debug, Clone)]
+pub struct FailoverHandle {
+    statuses: Arc<RwLock<Vec<EndpointStatus>>>,
+    last_served: Arc<RwLock<Option<String>>>,
+}
+
+impl FailoverHandle {
+    pub fn endpoint_statuses(&self) -> Vec<EndpointStatus> {
+        self.statuses.read().unwrap().clone()
+    }
+
+    /// The URL of the endpoint that served the most recent successful request.
+    pub fn last_served(&self) -> Option<String> {
+        self.last_served.read().unwrap().clone()
+    }
+}
+
+pub struct FailoverService<S> {
+    endpoints: Arc<Vec<Mutex<S>>>,
+    handle: FailoverHandle,
+    /// How long to skip an endpoint after it fails.
+    cooldown: Duration,
+    on_served: Option<OnServed>,
+    stats: Arc<RwLock<TransportStats>>,
+}
+
+impl FailoverService<HttpClientService> {
+    /// An [HttpClientService] for each URL, all updating the same [TransportStats].
+    pub fn from_urls<U: ToString>(urls: impl IntoIterator<Item = U>) -> Self {
+        let stats: Arc<RwLock<TransportStats>> = Default::default();
+        let mut this = Self::new(urls.into_iter().map(|url| {
+            let mut service = HttpClientService::new(url.to_string());
+            service.stats = stats.clone();
+            (url.to_string(), service)
+        }));
+        this.stats = stats;
+        this
+    }
+}
+
+impl<S> FailoverService<S> {
+    /// Endpoints are tried in order, starting from the first healthy one.
+    pub fn new(endpoints: impl IntoIterator<Item = (String, S)>) -> Self {
+        let (urls, services): (Vec<String>, Vec<Mutex<S>>) = endpoints
+            .into_iter()
+            .map(|(url, service)| (url, Mutex::new(service)))
+            .unzip();
+        assert!(!services.is_empty(), "at least one endpoint is required");
+        let statuses = urls
+            .into_iter()
+            .map(|url| EndpointStatus {
+                url,
+                requests: 0,
+                failures: 0,
+                consecutive_failures: 0,
+                unhealthy_until: None,
+            })
+            .collect();
+        Self {
+            endpoints: Arc::new(services),
+            handle: FailoverHandle {
+                statuses: Arc::new(RwLock::new(statuses)),
+                last_served: Default::default(),
+            },
+            cooldown: Duration::from_secs(30),
+            on_served: None,
+            stats: Default::default(),
+        }
+    }
+
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn with_on_served(mut self, f: impl Fn(&RpcRequest, &str) + Send + Sync + 'static) -> Self {
+        self.on_served = Some(Arc::new(f));
+        self
+    }
+
+    /// The stats to pass to [crate::HttpSenderService::new_from_service].
+    /// Only updated by the endpoints of [FailoverService::from_urls].
+    pub fn stats(&self) -> Arc<RwLock<TransportStats>> {
+        self.stats.clone()
+    }
+
+    pub fn handle(&self) -> FailoverHandle {
+        self.handle.clone()
+    }
+
+    /// The URL of the first endpoint, to pass as the `RpcSender` URL.
+    pub fn primary_url(&self) -> String {
+        self.handle.statuses.read().unwrap()[0].url.clone()
+    }
+}
+
+/// Whether a request failed because of the endpoint, rather than the request itself.
+pub fn is_endpoint_failure(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Reqwest(e) => {
+            e.is_timeout() || e.is_connect() || e.status().is_some_and(|s| s.is_server_error())
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        }
+        _ => false,
+    }
+}
+
+impl<S> Service<RpcSenderRequest> for FailoverService<S>
+where
+    S: Service<
+            RpcSenderRequest,
+            Error = ClientError,
+            Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+        > + Send
+        + 'static,
+{
+    type Response = Value;
+    type Error = ClientError;
+
+    type Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RpcSenderRequest) -> Self::Future {
+        let endpoints = self.endpoints.clone();
+        let handle = self.handle.clone();
+        let cooldown = self.cooldown;
+        let on_served = self.on_served.clone();
+        Box::pin(async move {
+            // Healthy endpoints first, in order, then the rest as a last resort.
+            let order: Vec<usize> = {
+                let statuses = handle.statuses.read().unwrap();
+                let (healthy, unhealthy): (Vec<usize>, Vec<usize>) =
+                    (0..statuses.len()).partition(|i| statuses[*i].is_healthy());
+                healthy.into_iter().chain(unhealthy).collect()
+            };
+            let mut last_error = None;
+            for index in order {
+                let result = {
+                    let mut service = endpoints[index].lock().await;
+                    match service.ready().await {
+                        Ok(service) => service.call(req.clone()),
+                        Err(e) => Box::pin(std::future::ready(Err(e))),
+                    }
+                }
+                .await;
+                let failed = result.as_ref().is_err_and(is_endpoint_failure);
+                let url = {
+                    let mut statuses = handle.statuses.write().unwrap();
+                    let status = &mut statuses[index];
+                    status.requests += 1;
+                    if failed {
+                        status.failures += 1;
+                        status.consecutive_failures += 1;
+                        status.unhealthy_until = Some(Instant::now() + cooldown);
+                    } else {
+                        status.consecutive_failures = 0;
+                        status.unhealthy_until = None;
+                    }
+                    status.url.clone()
+                };
+                if failed {
+                    last_error = result.err();
+                    continue;
+                }
+                if let Some(on_served) = &on_served {
+                    on_served(&req.0, &url);
+                }
+                *handle.last_served.write().unwrap() = Some(url);
+                return result;
+            }
+            Err(last_error.expect("at least one endpoint was tried"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use jsonrpc_core::{futures::future, Error, ErrorCode, IoHandler, Params};
+    use serde_json::json;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use std::net::TcpListener;
+
+    fn node_io(healthy: bool) -> IoHandler {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        io.add_method("getSlot", move |_: Params| {
+            if healthy {
+                future::ok(json!(100))
+            } else {
+                future::err(Error {
+                    code: ErrorCode::ServerError(JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY),
+                    message: "Node is unhealthy".to_string(),
+                    data: Some(json!({ "numSlotsBehind": 42 })),
+                })
+            }
+        });
+        io
+    }
+
+    #[tokio::test]
+    async fn requests_fail_over_to_healthy_endpoints() {
+        // Nothing listens on a port once its listener is dropped.
+        let down = format!(
+            "http://{}",
+            TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+        );
+        let unhealthy = test_node::spawn_node(node_io(false));
+        let healthy = test_node::spawn_node(node_io(true));
+
+        let served = Arc::new(RwLock::new(vec![]));
+        let served_by = served.clone();
+        let service = FailoverService::from_urls([&down, &unhealthy, &healthy]).with_on_served(
+            move |req, url| served_by.write().unwrap().push((*req, url.to_string())),
+        );
+        let handle = service.handle();
+        let stats = service.stats();
+        let url = service.primary_url();
+        let client = RpcClient::new_sender(
+            HttpSenderService::new_from_service(service, url, stats),
+            Default::default(),
+        );
+
+        // The client's version query is served by the unhealthy node, which only fails `getSlot`.
+        assert_eq!(client.get_slot().await.unwrap(), 100);
+        assert_eq!(handle.last_served(), Some(healthy.clone()));
+        assert_eq!(
+            *served.read().unwrap(),
+            vec![
+                (RpcRequest::GetVersion, unhealthy.clone()),
+                (RpcRequest::GetSlot, healthy.clone())
+            ]
+        );
+        let statuses = handle.endpoint_statuses();
+        assert!(!statuses[0].is_healthy() && !statuses[1].is_healthy());
+        assert!(statuses[2].is_healthy());
+
+        // Endpoints that failed are skipped until their cooldown ends.
+        assert_eq!(client.get_slot().await.unwrap(), 100);
+        let statuses = handle.endpoint_statuses();
+        assert_eq!(
+            statuses.iter().map(|s| s.requests).collect::<Vec<_>>(),
+            vec![1, 2, 2]
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Add HTTP headers to requests from within the tower stack, e.g. to authenticate with an RPC
+//! provider. Besides static default headers, a [HeaderProvider] computes headers for each request,
+//! so a short-lived bearer token can be refreshed without rebuilding the client.
+//!
+//! The headers are passed to the [crate::HttpClientService] in a tokio task-local,
+//! so the request type of the stack is unchanged. Nested header middleware add to the
+//! headers of outer layers, with inner layers taking precedence.
+use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_rpc_client_api::request::RpcRequest;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::Service;
+
+tokio::task_local! {
+    static REQUEST_HEADERS: HeaderMap;
+}
+
+/// The headers set by any [HeaderMiddleware] for the request currently being sent.
+pub fn request_headers() -> HeaderMap {
+    REQUEST_HEADERS
+        .try_with(HeaderMap::clone)
+        .unwrap_or_default()
+}
+
+/// Computes headers for each request. An error fails the request without sending it.
+#[async_trait]
+pub trait HeaderProvider: Send + Sync {
+    async fn headers(&self, request: RpcRequest) -> Result<HeaderMap, ClientError>;
+}
+
+#[async_trait]
+impl<F, Fut> HeaderProvider for F
+where
+    F: Fn(RpcRequest) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<HeaderMap, ClientError>> + Send,
+{
+    async fn headers(&self, request: RpcRequest) -> Result<HeaderMap, ClientError> {
+        self(request).await
+    }
+}
+
+pub struct HeaderMiddleware<S> {
+    inner: S,
+    headers: HeaderMap,
+    provider: Option<Arc<dyn HeaderProvider>>,
+}
+
+impl<S> HeaderMiddleware<S> {
+    pub fn new(inner: S, headers: HeaderMap) -> Self {
+        Self {
+            inner,
+            headers,
+            provider: None,
+        }
+    }
+
+    /// Headers from the provider replace any static headers of the same name.
+    pub fn with_provider(mut self, provider: impl HeaderProvider + 'static) -> Self {
+        self.provider = Some(Arc::new(provider));
+        self
+    }
+}
+
+impl<S> Service<RpcSenderRequest> for HeaderMiddleware<S>
+where
+    S: Service<
+            RpcSenderRequest,
+            Error = ClientError,
+            Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+        > + Send
+        + Sync,
+{
+    type Response = Value;
+    type Error = ClientError;
+
+    type Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RpcSenderRequest) -> Self::Future {
+        let request = req.0;
+        let static_headers = self.headers.clone();
+        let provider = self.provider.clone();
+        let inner = self.inner.call(req);
+        Box::pin(async move {
+            // Outer layers call this one before their scope is entered,
+            // so their headers are only visible from within the future.
+            let mut headers = request_headers();
+            headers.extend(static_headers);
+            if let Some(provider) = provider {
+                headers.extend(provider.headers(request).await?);
+            }
+            REQUEST_HEADERS.scope(headers, inner).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use jsonrpc_core::{futures::future, Error, MetaIoHandler, NoopMiddleware, Params};
+    use jsonrpc_http_server::{hyper, ServerBuilder};
+    use reqwest::header::{HeaderValue, AUTHORIZATION};
+    use serde_json::json;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use tower::ServiceBuilder;
+
+    /// A server for a node whose slot is the number at the end of the bearer token, and which
+    /// rejects requests without the `x-api-key` header.
+    fn server() -> ServerBuilder<Arc<HeaderMap>, NoopMiddleware> {
+        let mut io = MetaIoHandler::<Arc<HeaderMap>>::default();
+        io.add_method_with_meta("getSlot", |_: Params, headers: Arc<HeaderMap>| {
+            let token = headers
+                .get(AUTHORIZATION)
+                .and_then(|token| token.to_str().ok()?.strip_prefix("Bearer token-"))
+                .and_then(|n| n.parse::<u64>().ok());
+            match (headers.get("x-api-key"), token) {
+                (Some(_), Some(n)) => future::ok(json!(n)),
+                _ => future::err(Error::invalid_request()),
+            }
+        });
+        ServerBuilder::with_meta_extractor(io, |request: &hyper::Request<hyper::Body>| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in request.headers() {
+                headers.insert(
+                    name.as_str()
+                        .parse::<reqwest::header::HeaderName>()
+                        .unwrap(),
+                    HeaderValue::from_bytes(value.as_bytes()).unwrap(),
+                );
+            }
+            Arc::new(headers)
+        })
+    }
+
+    async fn get_slot(client: &RpcClient) -> Result<u64, ClientError> {
+        client.send(RpcRequest::GetSlot, json!([])).await
+    }
+
+    #[tokio::test]
+    async fn headers_are_computed_per_request() {
+        let url = test_node::spawn_server(server);
+        let mut static_headers = HeaderMap::new();
+        static_headers.insert("x-api-key", HeaderValue::from_static("key"));
+        let refreshes = Arc::new(AtomicU64::new(0));
+        let provider = {
+            let refreshes = refreshes.clone();
+            move |_: RpcRequest| {
+                let n = refreshes.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    let mut headers = HeaderMap::new();
+                    headers.insert(
+                        AUTHORIZATION,
+                        HeaderValue::from_str(&format!("Bearer token-{}", n)).unwrap(),
+                    );
+                    Ok(headers)
+                }
+            }
+        };
+
+        let client = RpcClient::new_sender(
+            HttpSenderService::new_from_builder(
+                url.clone(),
+                ServiceBuilder::new().layer_fn(move |s| {
+                    HeaderMiddleware::new(s, static_headers.clone()).with_provider(provider.clone())
+                }),
+            ),
+            Default::default(),
+        );
+        assert_eq!(get_slot(&client).await.unwrap(), 1);
+        assert_eq!(get_slot(&client).await.unwrap(), 2);
+        assert_eq!(refreshes.load(Ordering::SeqCst), 2);
+
+        let client = RpcClient::new_sender(HttpSenderService::new(url), Default::default());
+        assert!(get_slot(&client).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn nested_layers_add_to_outer_headers() {
+        let url = test_node::spawn_server(server);
+        let header = |name: &'static str, value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_static(value));
+            headers
+        };
+        let outer = {
+            let mut headers = header("x-api-key", "key");
+            headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer token-1"));
+            headers
+        };
+        // The inner layer's token takes precedence.
+        let inner = header("authorization", "Bearer token-2");
+
+        let client = RpcClient::new_sender(
+            HttpSenderService::new_from_builder(
+                url,
+                ServiceBuilder::new()
+                    .layer_fn(move |s| HeaderMiddleware::new(s, outer.clone()))
+                    .layer_fn(move |s| HeaderMiddleware::new(s, inner.clone())),
+            ),
+            Default::default(),
+        );
+        assert_eq!(get_slot(&client).await.unwrap(), 2);
+    }
+}
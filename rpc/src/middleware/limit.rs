@@ -0,0 +1,244 @@
+//! Limit the concurrency and rate of requests separately for each method, as providers often
+//! allow far fewer `getProgramAccounts` calls than other calls. Requests over a limit wait in
+//! a first-in, first-out queue rather than failing.
+use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use serde_json::Value;
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use solana_rpc_client_api::request::RpcRequest;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::time::{sleep_until, Instant};
+use tower::{Service, ServiceExt};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MethodLimit {
+    /// How many requests may be in flight at once.
+    pub max_concurrent: Option<usize>,
+    /// At most `.0` requests are started per `.1`, evenly spaced.
+    pub rate: Option<(u32, Duration)>,
+}
+
+impl MethodLimit {
+    pub fn concurrency(max_concurrent: usize) -> Self {
+        Self::default().with_concurrency(max_concurrent)
+    }
+
+    pub fn rate(requests: u32, per: Duration) -> Self {
+        Self::default().with_rate(requests, per)
+    }
+
+    pub fn with_concurrency(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = Some(max_concurrent.max(1));
+        self
+    }
+
+    pub fn with_rate(mut self, requests: u32, per: Duration) -> Self {
+        self.rate = Some((requests.max(1), per));
+        self
+    }
+}
+
+/// The shared state enforcing one [MethodLimit].
+#[derive(Debug)]
+struct Budget {
+    semaphore: Option<Arc<Semaphore>>,
+    interval: Option<Duration>,
+    /// The earliest time the next request may start.
+    next_start: Mutex<Instant>,
+}
+
+impl Budget {
+    fn new(limit: MethodLimit) -> Self {
+        Self {
+            semaphore: limit
+                .max_concurrent
+                .map(|permits| Arc::new(Semaphore::new(permits))),
+            interval: limit.rate.map(|(requests, per)| per / requests),
+            next_start: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Reserve the next start time, so that waiting requests start in the order they arrived.
+    fn reserve_start(&self) -> Option<Instant> {
+        let interval = self.interval?;
+        let mut next_start = self.next_start.lock().unwrap();
+        let start = (*next_start).max(Instant::now());
+        *next_start = start + interval;
+        Some(start)
+    }
+}
+
+/// Enforces a [MethodLimit] per method. Methods without their own limit share the default
+/// limit, if there is one, and are otherwise unlimited.
+pub struct MethodLimitMiddleware<S> {
+    /// Only called once a request is within its limit, so it never starts work early.
+    inner: Arc<tokio::sync::Mutex<S>>,
+    default: Option<Arc<Budget>>,
+    budgets: Arc<HashMap<RpcRequest, Arc<Budget>>>,
+}
+
+impl<S> MethodLimitMiddleware<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner: Arc::new(tokio::sync::Mutex::new(inner)),
+            default: None,
+            budgets: Default::default(),
+        }
+    }
+
+    /// A budget shared by every method without a limit of its own.
+    pub fn with_default_limit(mut self, limit: MethodLimit) -> Self {
+        self.default = Some(Arc::new(Budget::new(limit)));
+        self
+    }
+
+    pub fn with_method_limit(mut self, method: RpcRequest, limit: MethodLimit) -> Self {
+        Arc::make_mut(&mut self.budgets).insert(method, Arc::new(Budget::new(limit)));
+        self
+    }
+}
+
+impl<S> Service<RpcSenderRequest> for MethodLimitMiddleware<S>
+where
+    S: Service<
+            RpcSenderRequest,
+            Error = ClientError,
+            Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+        > + Send
+        + 'static,
+{
+    type Response = Value;
+    type Error = ClientError;
+
+    type Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RpcSenderRequest) -> Self::Future {
+        let request = req.0;
+        let budget = self
+            .budgets
+            .get(&request)
+            .or(self.default.as_ref())
+            .cloned();
+        let inner = self.inner.clone();
+        Box::pin(async move {
+            let Some(budget) = budget else {
+                return call_inner(&inner, req).await;
+            };
+            let _permit = match &budget.semaphore {
+                Some(semaphore) => Some(semaphore.clone().acquire_owned().await.map_err(|_| {
+                    ClientError::new_with_request(
+                        ClientErrorKind::Custom("Request queue was closed".to_string()),
+                        request,
+                    )
+                })?),
+                None => None,
+            };
+            if let Some(start) = budget.reserve_start() {
+                sleep_until(start).await;
+            }
+            call_inner(&inner, req).await
+        })
+    }
+}
+
+/// Holds the lock only while the inner service is readied and called, not for the response.
+async fn call_inner<S>(inner: &tokio::sync::Mutex<S>, req: RpcSenderRequest) -> RpcSenderResponse
+where
+    S: Service<
+        RpcSenderRequest,
+        Error = ClientError,
+        Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+    >,
+{
+    let response = {
+        let mut service = inner.lock().await;
+        service.ready().await?.call(req)
+    };
+    response.await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HttpSenderService;
+    use futures_util::future::join_all;
+    use serde_json::json;
+    use solana_rpc_client::rpc_sender::RpcSender;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::service_fn;
+
+    /// The most requests of each method that were ever in flight at once,
+    /// counted from when the node is called rather than polled.
+    struct InFlight {
+        current: HashMap<RpcRequest, AtomicUsize>,
+        max: HashMap<RpcRequest, AtomicUsize>,
+    }
+
+    #[tokio::test]
+    async fn methods_have_separate_budgets() {
+        let methods = [
+            RpcRequest::GetProgramAccounts,
+            RpcRequest::GetSlot,
+            RpcRequest::GetBalance,
+        ];
+        let in_flight = Arc::new(InFlight {
+            current: methods.iter().map(|m| (*m, AtomicUsize::new(0))).collect(),
+            max: methods.iter().map(|m| (*m, AtomicUsize::new(0))).collect(),
+        });
+        let node = {
+            let in_flight = in_flight.clone();
+            service_fn(move |(request, _): RpcSenderRequest| {
+                let in_flight = in_flight.clone();
+                let current = in_flight.current[&request].fetch_add(1, Ordering::SeqCst) + 1;
+                in_flight.max[&request].fetch_max(current, Ordering::SeqCst);
+                Box::pin(async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    in_flight.current[&request].fetch_sub(1, Ordering::SeqCst);
+                    Ok(json!(0))
+                }) as Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>
+            })
+        };
+        let sender = HttpSenderService::new_from_service(
+            MethodLimitMiddleware::new(node)
+                .with_method_limit(RpcRequest::GetProgramAccounts, MethodLimit::concurrency(1))
+                .with_method_limit(
+                    RpcRequest::GetBalance,
+                    MethodLimit::rate(2, Duration::from_millis(200)),
+                ),
+            "http://localhost:8899",
+            Default::default(),
+        );
+
+        let start = Instant::now();
+        let requests = methods
+            .iter()
+            .flat_map(|method| [*method; 3])
+            .map(|method| sender.send(method, json!([])));
+        for response in join_all(requests).await {
+            response.unwrap();
+        }
+        assert_eq!(
+            in_flight.max[&RpcRequest::GetProgramAccounts].load(Ordering::SeqCst),
+            1
+        );
+        assert_eq!(
+            in_flight.max[&RpcRequest::GetSlot].load(Ordering::SeqCst),
+            3
+        );
+        // `getBalance` calls are started 100ms apart, so they never overlap.
+        assert_eq!(
+            in_flight.max[&RpcRequest::GetBalance].load(Ordering::SeqCst),
+            1
+        );
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
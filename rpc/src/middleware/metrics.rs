@@ -0,0 +1,320 @@
+//! Record request counts, latencies and error categories for each RPC method.
+use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use serde_json::Value;
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use solana_rpc_client_api::request::RpcError;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::Service;
+
+/// Upper bounds of the latency histogram buckets, in milliseconds.
+pub const LATENCY_BUCKETS_MS: [u64; 11] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorCategory {
+    Timeout,
+    Connection,
+    /// A non-success HTTP status, e.g. 429 or 503.
+    HttpStatus,
+    /// A JSON-RPC error response, e.g. invalid params or an unhealthy node.
+    RpcResponse,
+    /// The response could not be parsed.
+    Deserialize,
+    Other,
+}
+
+impl ErrorCategory {
+    pub fn of(error: &ClientError) -> Self {
+        match error.kind() {
+            ClientErrorKind::Reqwest(e) if e.is_timeout() => Self::Timeout,
+            ClientErrorKind::Reqwest(e) if e.is_connect() => Self::Connection,
+            ClientErrorKind::Reqwest(e) if e.status().is_some() => Self::HttpStatus,
+            ClientErrorKind::Io(_) => Self::Connection,
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { .. }) => Self::RpcResponse,
+            ClientErrorKind::RpcError(RpcError::ParseError(_)) | ClientErrorKind::SerdeJson(_) => {
+                Self::Deserialize
+            }
+            _ => Self::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::Connection => "connection",
+            Self::HttpStatus => "http_status",
+            Self::RpcResponse => "rpc_response",
+            Self::Deserialize => "deserialize",
+            Self::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// The number of requests in each of [LATENCY_BUCKETS_MS], not cumulative,
+    /// followed by the number of requests slower than the last bucket.
+    pub buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    pub count: u64,
+    pub sum: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| latency <= Duration::from_millis(*bound))
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum += latency;
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.sum.div_f64(self.count as f64))
+    }
+
+    /// An upper bound on the latency of the given fraction of requests, e.g. `0.99`.
+    /// `None` if there are no requests, or the quantile falls past the last bucket.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let target = (quantile * self.count as f64).ceil() as u64;
+        let mut seen = 0;
+        for (count, bound) in self.buckets.iter().zip(LATENCY_BUCKETS_MS) {
+            seen += count;
+            if seen >= target {
+                return Some(Duration::from_millis(bound));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodMetrics {
+    pub requests: u64,
+    pub errors: BTreeMap<ErrorCategory, u64>,
+    pub latency: LatencyHistogram,
+}
+
+impl MethodMetrics {
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+}
+
+/// Metrics by method name, e.g. `getAccountInfo`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RpcMetrics {
+    pub methods: BTreeMap<String, MethodMetrics>,
+}
+
+impl RpcMetrics {
+    /// Render in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# TYPE solana_rpc_requests_total counter\n");
+        for (method, metrics) in &self.methods {
+            writeln!(
+                out,
+                "solana_rpc_requests_total{{method=\"{}\"}} {}",
+                method, metrics.requests
+            )
+            .unwrap();
+        }
+        out.push_str("# TYPE solana_rpc_errors_total counter\n");
+        for (method, metrics) in &self.methods {
+            for (category, count) in &metrics.errors {
+                writeln!(
+                    out,
+                    "solana_rpc_errors_total{{method=\"{}\",category=\"{}\"}} {}",
+                    method,
+                    category.as_str(),
+                    count
+                )
+                .unwrap();
+            }
+        }
+        out.push_str("# TYPE solana_rpc_request_duration_seconds histogram\n");
+        for (method, metrics) in &self.methods {
+            let latency = &metrics.latency;
+            let mut cumulative = 0;
+            for (count, bound) in latency.buckets.iter().zip(LATENCY_BUCKETS_MS) {
+                cumulative += count;
+                writeln!(
+                    out,
+                    "solana_rpc_request_duration_seconds_bucket{{method=\"{}\",le=\"{}\"}} {}",
+                    method,
+                    bound as f64 / 1000.0,
+                    cumulative
+                )
+                .unwrap();
+            }
+            writeln!(
+                out,
+                "solana_rpc_request_duration_seconds_bucket{{method=\"{}\",le=\"+Inf\"}} {}",
+                method, latency.count
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "solana_rpc_request_duration_seconds_sum{{method=\"{}\"}} {}",
+                method,
+                latency.sum.as_secs_f64()
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "solana_rpc_request_duration_seconds_count{{method=\"{}\"}} {}",
+                method, latency.count
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+/// A cheap-clone view of the metrics, which remains available
+/// after the [MetricsMiddleware] is moved into an `RpcClient`.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsHandle {
+    metrics: Arc<RwLock<RpcMetrics>>,
+}
+
+impl MetricsHandle {
+    pub fn snapshot(&self) -> RpcMetrics {
+        self.metrics.read().unwrap().clone()
+    }
+
+    pub fn reset(&self) {
+        *self.metrics.write().unwrap() = RpcMetrics::default();
+    }
+}
+
+pub struct MetricsMiddleware<S> {
+    inner: S,
+    handle: MetricsHandle,
+}
+
+impl<S> MetricsMiddleware<S> {
+    pub fn new(inner: S) -> Self {
+        Self::with_handle(inner, MetricsHandle::default())
+    }
+
+    /// Record into an existing handle, e.g. one created before building the `RpcClient`.
+    pub fn with_handle(inner: S, handle: MetricsHandle) -> Self {
+        Self { inner, handle }
+    }
+
+    pub fn handle(&self) -> MetricsHandle {
+        self.handle.clone()
+    }
+}
+
+impl<S> Service<RpcSenderRequest> for MetricsMiddleware<S>
+where
+    S: Service<
+            RpcSenderRequest,
+            Error = ClientError,
+            Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+        > + Send
+        + Sync,
+{
+    type Response = Value;
+    type Error = ClientError;
+
+    type Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RpcSenderRequest) -> Self::Future {
+        let method = req.0.to_string();
+        let handle = self.handle.clone();
+        let start = Instant::now();
+        let response = self.inner.call(req);
+        Box::pin(async move {
+            let result = response.await;
+            let mut metrics = handle.metrics.write().unwrap();
+            let method = metrics.methods.entry(method).or_default();
+            method.requests += 1;
+            method.latency.record(start.elapsed());
+            if let Err(e) = &result {
+                *method.errors.entry(ErrorCategory::of(e)).or_default() += 1;
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use jsonrpc_core::{futures::future, Error, IoHandler, Params};
+    use serde_json::json;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use tower::ServiceBuilder;
+
+    #[test]
+    fn latencies_past_a_bound_fall_in_the_next_bucket() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_millis(5));
+        histogram.record(Duration::from_micros(5_900));
+        assert_eq!(&histogram.buckets[..2], &[1, 1]);
+        assert_eq!(histogram.quantile(1.0), Some(Duration::from_millis(10)));
+    }
+
+    #[tokio::test]
+    async fn requests_are_recorded_by_method() {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        io.add_method("getSlot", |_: Params| future::ok(json!(100)));
+        io.add_method("getHealth", |_: Params| {
+            future::err(Error::new(jsonrpc_core::ErrorCode::ServerError(-32005)))
+        });
+        let url = test_node::spawn_node(io);
+
+        let metrics = MetricsHandle::default();
+        let handle = metrics.clone();
+        let sender = HttpSenderService::new_from_builder(
+            url,
+            ServiceBuilder::new()
+                .layer_fn(move |s| MetricsMiddleware::with_handle(s, handle.clone())),
+        );
+        let client = RpcClient::new_sender(sender, Default::default());
+        client.get_slot().await.unwrap();
+        client.get_slot().await.unwrap();
+        client.get_health().await.unwrap_err();
+
+        let snapshot = metrics.snapshot();
+        let get_slot = &snapshot.methods["getSlot"];
+        assert_eq!(get_slot.requests, 2);
+        assert_eq!(get_slot.error_count(), 0);
+        assert_eq!(get_slot.latency.count, 2);
+        assert_eq!(
+            snapshot.methods["getHealth"].errors,
+            BTreeMap::from([(ErrorCategory::RpcResponse, 1)])
+        );
+
+        let prometheus = snapshot.to_prometheus();
+        assert!(prometheus.contains("solana_rpc_requests_total{method=\"getSlot\"} 2\n"));
+        assert!(prometheus.contains(
+            "solana_rpc_errors_total{method=\"getHealth\",category=\"rpc_response\"} 1\n"
+        ));
+        assert!(prometheus.contains(
+            "solana_rpc_request_duration_seconds_bucket{method=\"getSlot\",le=\"+Inf\"} 2\n"
+        ));
+    }
+}
@@ -1,12 +1,34 @@
+pub mod cache;
+pub mod coalesce;
+pub mod failover;
+pub mod headers;
+pub mod limit;
+pub mod metrics;
+pub mod record;
+pub mod response_size;
+pub mod retry;
+pub mod tracing;
+
 use crate::service::{RpcSenderRequest, RpcSenderResponse};
 use serde_json::Value;
-use solana_client::client_error::ClientError;
-use solana_client::rpc_request::RpcRequest;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_rpc_client_api::request::RpcRequest;
 use std::future::{ready, Future};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tower::Service;
 
+pub use cache::CacheMiddleware;
+pub use coalesce::CoalescingMiddleware;
+pub use failover::{FailoverHandle, FailoverService};
+pub use headers::{HeaderMiddleware, HeaderProvider};
+pub use limit::{MethodLimit, MethodLimitMiddleware};
+pub use metrics::{MetricsHandle, MetricsMiddleware};
+pub use record::{Recorder, RecordingMiddleware};
+pub use response_size::ResponseSizeMiddleware;
+pub use retry::{RetryMiddleware, RetryPolicy, RetryableError};
+pub use tracing::{Redaction, TracingMiddleware};
+
 /// Filter Solana RPC requests, and conditionally return an error.
 /// Takes a function that takes the request method and params as input,
 /// and returns a [Result<(), solana_client::client_error::ClientError].
@@ -0,0 +1,349 @@
+//! Record every request and its response, to be served later by a [crate::replay::ReplaySender].
+//! Exchanges are recorded in the order they were requested, even when concurrent requests
+//! get their responses in a different order.
+use crate::replay::{save_recording, RecordedExchange, RecordingFormat};
+use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use log::warn;
+use serde_json::Value;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use std::fs::File;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tower::Service;
+
+#[derive(Debug)]
+enum Slot {
+    Pending,
+    Recorded(Box<RecordedExchange>),
+    /// The request was dropped before its response arrived.
+    Abandoned,
+}
+
+#[derive(Debug)]
+struct RecordingFile {
+    path: PathBuf,
+    file: File,
+    format: RecordingFormat,
+}
+
+#[derive(Debug, Default)]
+struct RecorderState {
+    slots: Vec<Slot>,
+    /// Slots before this one have been written to the file.
+    written: usize,
+    file: Option<RecordingFile>,
+}
+
+impl RecorderState {
+    fn exchanges(&self) -> impl Iterator<Item = &RecordedExchange> {
+        self.slots.iter().filter_map(|slot| match slot {
+            Slot::Recorded(exchange) => Some(exchange.as_ref()),
+            _ => None,
+        })
+    }
+
+    /// Write the slots that are no longer waiting on an earlier pending request.
+    /// A failure to write the file is logged rather than failing the request.
+    fn flush(&mut self) {
+        while let Some(slot) = self.slots.get(self.written) {
+            match (slot, self.file.as_mut()) {
+                (Slot::Pending, _) => break,
+                (Slot::Recorded(exchange), Some(file)) => {
+                    if let Err(e) = file.format.write(&mut file.file, exchange) {
+                        warn!(
+                            "Failed to write RPC recording to {}: {}",
+                            file.path.display(),
+                            e
+                        );
+                    }
+                }
+                _ => (),
+            }
+            self.written += 1;
+        }
+    }
+}
+
+/// A shared handle to the exchanges recorded by one or more [RecordingMiddleware].
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    state: Arc<Mutex<RecorderState>>,
+}
+
+impl Recorder {
+    /// Records in memory only. See [Recorder::save].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also appends each exchange to a file as it is recorded, so the recording survives
+    /// a crash of the code under test. An existing file is overwritten. The file is written
+    /// in the [RecordingFormat] of its extension.
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = RecordingFile {
+            file: File::create(&path)?,
+            format: RecordingFormat::from_path(&path),
+            path,
+        };
+        let state = RecorderState {
+            file: Some(file),
+            ..RecorderState::default()
+        };
+        Ok(Self {
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    /// The exchanges that have their response, in the order they were requested.
+    pub fn exchanges(&self) -> Vec<RecordedExchange> {
+        self.state.lock().unwrap().exchanges().cloned().collect()
+    }
+
+    /// Write the exchanges that have their response, in the [RecordingFormat] of the path's extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let exchanges = self.exchanges();
+        save_recording(path, &exchanges)
+    }
+
+    /// Hold a place for an exchange in request order, to be filled once its response arrives.
+    fn reserve(&self) -> Reservation {
+        let mut state = self.state.lock().unwrap();
+        state.slots.push(Slot::Pending);
+        Reservation {
+            recorder: self.clone(),
+            index: state.slots.len() - 1,
+        }
+    }
+
+    fn fill(&self, index: usize, slot: Slot) {
+        let mut state = self.state.lock().unwrap();
+        if matches!(state.slots[index], Slot::Pending) {
+            state.slots[index] = slot;
+            state.flush();
+        }
+    }
+}
+
+/// A place in the recording, which is abandoned if its request is dropped before completing.
+struct Reservation {
+    recorder: Recorder,
+    index: usize,
+}
+
+impl Reservation {
+    fn record(self, exchange: RecordedExchange) {
+        self.recorder
+            .fill(self.index, Slot::Recorded(Box::new(exchange)));
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.recorder.fill(self.index, Slot::Abandoned);
+    }
+}
+
+pub struct RecordingMiddleware<S> {
+    inner: S,
+    recorder: Recorder,
+}
+
+impl<S> RecordingMiddleware<S> {
+    pub fn new(inner: S, recorder: Recorder) -> Self {
+        Self { inner, recorder }
+    }
+
+    pub fn recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+}
+
+impl<S> Service<RpcSenderRequest> for RecordingMiddleware<S>
+where
+    S: Service<
+            RpcSenderRequest,
+            Error = ClientError,
+            Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+        > + Send
+        + Sync,
+{
+    type Response = Value;
+    type Error = ClientError;
+
+    type Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RpcSenderRequest) -> Self::Future {
+        let (request, params) = (req.0, req.1.clone());
+        let reservation = self.recorder.reserve();
+        let response = self.inner.call(req);
+        Box::pin(async move {
+            let response = response.await;
+            reservation.record(RecordedExchange::new(request, params, &response));
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::{load_recording, RecordedResponse, ReplaySender};
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use jsonrpc_core::{futures::future, IoHandler, Params};
+    use serde_json::json;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use solana_rpc_client::rpc_sender::RpcSender;
+    use solana_rpc_client_api::client_error::ErrorKind as ClientErrorKind;
+    use solana_rpc_client_api::request::{RpcError, RpcRequest, RpcResponseErrorData};
+    use solana_rpc_client_api::response::RpcSimulateTransactionResult;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::transaction::TransactionError;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+    use tower::ServiceBuilder;
+
+    /// The methods of a node whose balances go up by one with every request, and which doesn't serve `getSlot`.
+    fn node_io() -> IoHandler {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        let balance = AtomicU64::new(0);
+        io.add_method("getBalance", move |_: Params| {
+            let balance = balance.fetch_add(1, Ordering::SeqCst) + 1;
+            future::ok(json!({ "context": { "slot": 100 }, "value": balance }))
+        });
+        io
+    }
+
+    #[tokio::test]
+    async fn recorded_traffic_is_replayed() {
+        let path =
+            std::env::temp_dir().join(format!("rpc-recording-{}.jsonl", Pubkey::new_unique()));
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let recorder = Recorder::to_file(&path).unwrap();
+        let client = RpcClient::new_sender(
+            HttpSenderService::new_from_builder(
+                test_node::spawn_node(node_io()),
+                ServiceBuilder::new().layer_fn(|s| RecordingMiddleware::new(s, recorder.clone())),
+            ),
+            Default::default(),
+        );
+        assert_eq!(client.get_balance(&alice).await.unwrap(), 1);
+        assert_eq!(client.get_balance(&bob).await.unwrap(), 2);
+        assert_eq!(client.get_balance(&alice).await.unwrap(), 3);
+        assert!(client
+            .send::<u64>(RpcRequest::GetSlot, json!([]))
+            .await
+            .is_err());
+
+        let recording = load_recording(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording, recorder.exchanges());
+        assert_eq!(recording.len(), 5);
+        assert_eq!(recording[1].method, "getBalance");
+        assert!(matches!(
+            recording[4].response,
+            RecordedResponse::RpcError { code: -32601, .. }
+        ));
+
+        // Repeated requests replay their responses in order, then repeat the last one.
+        let client =
+            RpcClient::new_sender(ReplaySender::new(recording.clone()), Default::default());
+        assert_eq!(client.get_balance(&bob).await.unwrap(), 2);
+        assert_eq!(client.get_balance(&alice).await.unwrap(), 1);
+        assert_eq!(client.get_balance(&alice).await.unwrap(), 3);
+        assert_eq!(client.get_balance(&alice).await.unwrap(), 3);
+        let err = client
+            .send::<u64>(RpcRequest::GetSlot, json!([]))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code: -32601, .. })
+        ));
+        assert!(client.get_balance(&Pubkey::new_unique()).await.is_err());
+
+        let sender = ReplaySender::new(recording).with_strict_ordering();
+        assert_eq!(sender.remaining(), 5);
+        let client = RpcClient::new_sender(sender, Default::default());
+        assert_eq!(client.get_balance(&alice).await.unwrap(), 1);
+        let err = client.get_balance(&alice).await.unwrap_err();
+        assert!(
+            err.to_string().contains("Replay diverged at request 2"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn exchanges_are_recorded_in_request_order_with_error_data() {
+        let path =
+            std::env::temp_dir().join(format!("rpc-recording-{}.cbor", Pubkey::new_unique()));
+        let preflight = RpcSimulateTransactionResult {
+            err: Some(TransactionError::AccountNotFound),
+            logs: Some(vec!["Program log: insufficient funds".to_string()]),
+            accounts: None,
+            units_consumed: Some(1_000),
+            return_data: None,
+        };
+        let failure = preflight.clone();
+        let node = tower::service_fn(
+            move |(request, _): RpcSenderRequest| -> Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>> {
+                let failure = failure.clone();
+                Box::pin(async move {
+                    match request {
+                        RpcRequest::GetSlot => {
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            Ok(json!(100))
+                        }
+                        _ => Err(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                            code: -32002,
+                            message: "Transaction simulation failed".to_string(),
+                            data: RpcResponseErrorData::SendTransactionPreflightFailure(failure),
+                        })
+                        .into()),
+                    }
+                })
+            },
+        );
+        let recorder = Recorder::to_file(&path).unwrap();
+        let mut middleware = RecordingMiddleware::new(node, recorder.clone());
+        // A dropped request is left out, without holding up the ones after it.
+        drop(middleware.call((RpcRequest::GetSlot, json!([]))));
+        let slot = middleware.call((RpcRequest::GetSlot, json!([])));
+        let send = middleware.call((RpcRequest::SendTransaction, json!(["tx"])));
+        let (slot, send) = futures::join!(slot, send);
+        assert_eq!(slot.unwrap(), 100);
+        assert!(send.is_err());
+
+        let recording = load_recording(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording, recorder.exchanges());
+        let methods: Vec<&str> = recording.iter().map(|e| e.method.as_str()).collect();
+        assert_eq!(methods, ["getSlot", "sendTransaction"]);
+
+        let err = ReplaySender::new(recording)
+            .send(RpcRequest::SendTransaction, json!(["tx"]))
+            .await
+            .unwrap_err();
+        match err.kind() {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: -32002,
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+                ..
+            }) => assert_eq!(*result, preflight),
+            kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+}
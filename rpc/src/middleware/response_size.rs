@@ -0,0 +1,114 @@
+//! Limit the size of response bodies from within the tower stack, e.g. to protect a long-running
+//! service from an unexpectedly large `getProgramAccounts` result.
+//!
+//! A middleware only sees responses once they are parsed, so the limit is passed to the
+//! [crate::HttpClientService] in a tokio task-local, like the headers of a
+//! [crate::middleware::HeaderMiddleware]. The body is counted as it downloads, and fails
+//! before any bytes past the limit reach the parser, see [crate::json_rpc::body_limit].
+//! With nested middleware, or a limit set on the service itself, the smallest limit applies.
+use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use serde_json::Value;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::Service;
+
+tokio::task_local! {
+    static MAX_RESPONSE_SIZE: usize;
+}
+
+/// The limit set by any [ResponseSizeMiddleware] for the request currently being sent.
+pub fn max_response_size() -> Option<usize> {
+    MAX_RESPONSE_SIZE.try_with(|max_size| *max_size).ok()
+}
+
+pub struct ResponseSizeMiddleware<S> {
+    inner: S,
+    max_size: usize,
+}
+
+impl<S> ResponseSizeMiddleware<S> {
+    pub fn new(inner: S, max_size: usize) -> Self {
+        Self { inner, max_size }
+    }
+}
+
+impl<S> Service<RpcSenderRequest> for ResponseSizeMiddleware<S>
+where
+    S: Service<
+            RpcSenderRequest,
+            Error = ClientError,
+            Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+        > + Send
+        + Sync,
+{
+    type Response = Value;
+    type Error = ClientError;
+
+    type Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RpcSenderRequest) -> Self::Future {
+        let max_size = self.max_size;
+        let inner = self.inner.call(req);
+        Box::pin(async move {
+            // Outer layers call this one before their scope is entered,
+            // so their limit is only visible from within the future.
+            let max_size = max_response_size().map_or(max_size, |outer| outer.min(max_size));
+            MAX_RESPONSE_SIZE.scope(max_size, inner).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use jsonrpc_core::{futures::future, IoHandler, Params};
+    use serde_json::json;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use solana_rpc_client_api::request::RpcRequest;
+    use tower::ServiceBuilder;
+
+    #[tokio::test]
+    async fn the_smallest_limit_applies() {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        io.add_method("getGenesisHash", |_: Params| {
+            future::ok(json!("a".repeat(10_000)))
+        });
+        let url = test_node::spawn_node(io);
+        let client = |outer, inner| {
+            RpcClient::new_sender(
+                HttpSenderService::new_from_builder(
+                    &url,
+                    ServiceBuilder::new()
+                        .layer_fn(move |s| ResponseSizeMiddleware::new(s, outer))
+                        .layer_fn(move |s| ResponseSizeMiddleware::new(s, inner)),
+                ),
+                Default::default(),
+            )
+        };
+
+        let get = |client: RpcClient| async move {
+            client
+                .send::<String>(RpcRequest::GetGenesisHash, Value::Null)
+                .await
+        };
+        assert_eq!(get(client(1 << 20, 1 << 20)).await.unwrap().len(), 10_000);
+        for (outer, inner) in [(1 << 20, 5_000), (5_000, 1 << 20)] {
+            let err = get(client(outer, inner)).await.unwrap_err();
+            assert!(
+                err.to_string().contains("maximum size of 5000 bytes"),
+                "{err}"
+            );
+        }
+    }
+}
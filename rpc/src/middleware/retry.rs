@@ -0,0 +1,339 @@
+//! Retry failed requests with exponential backoff and jitter, with a [RetryPolicy] per method.
+use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use rand::Rng;
+use reqwest::StatusCode;
+use serde_json::Value;
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use solana_rpc_client_api::custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY;
+use solana_rpc_client_api::request::{RpcError, RpcRequest, RpcResponseErrorData};
+use solana_sdk::transaction::TransactionError;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+use tower::{Service, ServiceExt};
+
+/// The classes of errors a [RetryPolicy] can retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryableError {
+    /// The request timed out, or the connection failed.
+    Timeout,
+    /// A 5xx HTTP status.
+    ServerError,
+    /// A `429 Too Many Requests` status. [crate::json_rpc::HttpClientService] only retries these
+    /// itself if its `too_many_requests_retries` is set.
+    RateLimited,
+    /// The node reported that it is unhealthy, i.e. behind the cluster.
+    NodeUnhealthy,
+    /// A transaction's blockhash was not found, e.g. because the node is behind.
+    BlockhashNotFound,
+}
+
+impl RetryableError {
+    pub fn of(error: &ClientError) -> Option<Self> {
+        match error.kind() {
+            ClientErrorKind::Reqwest(e) if e.is_timeout() || e.is_connect() => Some(Self::Timeout),
+            ClientErrorKind::Reqwest(e) => match e.status() {
+                Some(StatusCode::TOO_MANY_REQUESTS) => Some(Self::RateLimited),
+                Some(status) if status.is_server_error() => Some(Self::ServerError),
+                _ => None,
+            },
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code, data, .. }) => {
+                match data {
+                    RpcResponseErrorData::SendTransactionPreflightFailure(result)
+                        if result.err == Some(TransactionError::BlockhashNotFound) =>
+                    {
+                        Some(Self::BlockhashNotFound)
+                    }
+                    _ if *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY => Some(Self::NodeUnhealthy),
+                    _ => None,
+                }
+            }
+            ClientErrorKind::TransactionError(TransactionError::BlockhashNotFound) => {
+                Some(Self::BlockhashNotFound)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// The delay before the first retry.
+    pub initial_backoff: Duration,
+    /// The delay is multiplied by this after each retry, at least 1.
+    multiplier: f64,
+    pub max_backoff: Duration,
+    /// The fraction, from 0 to 1, by which each delay is randomly shortened or lengthened.
+    jitter: f64,
+    pub retry_on: HashSet<RetryableError>,
+}
+
+impl Default for RetryPolicy {
+    /// 3 retries, from 500ms doubling up to 10s with 20% jitter, on timeouts,
+    /// 5xx and 429 statuses, and unhealthy nodes.
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            multiplier: 2.0,
+            max_backoff: Duration::from_secs(10),
+            jitter: 0.2,
+            retry_on: HashSet::from([
+                RetryableError::Timeout,
+                RetryableError::ServerError,
+                RetryableError::RateLimited,
+                RetryableError::NodeUnhealthy,
+            ]),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// A `multiplier` below 1, or NaN, is taken as 1, i.e. a constant backoff.
+    pub fn with_backoff(mut self, initial: Duration, multiplier: f64, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.multiplier = if multiplier.is_nan() {
+            1.0
+        } else {
+            multiplier.clamp(1.0, f64::MAX)
+        };
+        self.max_backoff = max;
+        self
+    }
+
+    /// A `jitter` outside 0 to 1, or NaN, is taken as the nearest bound, or 0.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = if jitter.is_nan() {
+            0.0
+        } else {
+            jitter.clamp(0.0, 1.0)
+        };
+        self
+    }
+
+    pub fn multiplier(&self) -> f64 {
+        self.multiplier
+    }
+
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    pub fn with_retry_on(mut self, retry_on: impl IntoIterator<Item = RetryableError>) -> Self {
+        self.retry_on = retry_on.into_iter().collect();
+        self
+    }
+
+    pub fn is_retryable(&self, error: &ClientError) -> bool {
+        RetryableError::of(error).is_some_and(|class| self.retry_on.contains(&class))
+    }
+
+    /// The delay before retry number `retry`, starting from 0, before jitter.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self.initial_backoff.as_secs_f64()
+            * self.multiplier.powi(retry.min(i32::MAX as u32) as i32);
+        // Past `Duration::MAX`, the backoff is capped anyway.
+        Duration::try_from_secs_f64(backoff).map_or(self.max_backoff, |b| b.min(self.max_backoff))
+    }
+
+    fn backoff_with_jitter(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+        if self.jitter <= 0.0 {
+            return backoff;
+        }
+        let factor = rand::thread_rng().gen_range(1.0 - self.jitter..=1.0 + self.jitter);
+        backoff.mul_f64(factor)
+    }
+}
+
+pub struct RetryMiddleware<S> {
+    inner: Arc<Mutex<S>>,
+    policy: RetryPolicy,
+    method_policies: Arc<HashMap<RpcRequest, RetryPolicy>>,
+}
+
+impl<S> RetryMiddleware<S> {
+    /// Retry every method with the default [RetryPolicy].
+    pub fn new(inner: S) -> Self {
+        Self::with_policy(inner, RetryPolicy::default())
+    }
+
+    pub fn with_policy(inner: S, policy: RetryPolicy) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+            policy,
+            method_policies: Default::default(),
+        }
+    }
+
+    /// Use a different policy for one method, e.g. [RetryPolicy::none] for `sendTransaction`.
+    pub fn with_method_policy(mut self, method: RpcRequest, policy: RetryPolicy) -> Self {
+        Arc::make_mut(&mut self.method_policies).insert(method, policy);
+        self
+    }
+
+    fn policy(&self, method: &RpcRequest) -> RetryPolicy {
+        self.method_policies
+            .get(method)
+            .unwrap_or(&self.policy)
+            .clone()
+    }
+}
+
+impl<S> Service<RpcSenderRequest> for RetryMiddleware<S>
+where
+    S: Service<
+            RpcSenderRequest,
+            Error = ClientError,
+            Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+        > + Send
+        + 'static,
+{
+    type Response = Value;
+    type Error = ClientError;
+
+    type Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RpcSenderRequest) -> Self::Future {
+        let inner = self.inner.clone();
+        let policy = self.policy(&req.0);
+        Box::pin(async move {
+            let mut retry = 0;
+            loop {
+                let result = {
+                    let mut service = inner.lock().await;
+                    match service.ready().await {
+                        Ok(service) => service.call(req.clone()),
+                        Err(e) => Box::pin(std::future::ready(Err(e))),
+                    }
+                }
+                .await;
+                match result {
+                    Err(e) if retry < policy.max_retries && policy.is_retryable(&e) => {
+                        let backoff = policy.backoff_with_jitter(retry);
+                        log::debug!("{} failed with {}, retrying in {:?}", req.0, e, backoff);
+                        sleep(backoff).await;
+                        retry += 1;
+                    }
+                    result => return result,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use jsonrpc_core::{futures::future, Error, ErrorCode, IoHandler, Params};
+    use serde_json::json;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use tower::ServiceBuilder;
+
+    /// The methods of a node that is unhealthy for its first two `getSlot` and `getBlockHeight` calls.
+    fn node_io() -> IoHandler {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        for method in ["getSlot", "getBlockHeight"] {
+            let calls = AtomicU64::new(0);
+            io.add_method(method, move |_: Params| {
+                if calls.fetch_add(1, Ordering::Relaxed) < 2 {
+                    future::err(Error {
+                        code: ErrorCode::ServerError(JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY),
+                        message: "Node is unhealthy".to_string(),
+                        data: None,
+                    })
+                } else {
+                    future::ok(json!(100))
+                }
+            });
+        }
+        io
+    }
+
+    #[test]
+    fn backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy::default().with_backoff(
+            Duration::from_millis(100),
+            3.0,
+            Duration::from_secs(1),
+        );
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(300));
+        assert_eq!(policy.backoff(3), Duration::from_secs(1));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn backoff_and_jitter_are_validated() {
+        for multiplier in [-2.0, 0.5, f64::NAN] {
+            let policy = RetryPolicy::default().with_backoff(
+                Duration::from_millis(100),
+                multiplier,
+                Duration::from_secs(1),
+            );
+            assert_eq!(policy.multiplier(), 1.0);
+            assert_eq!(policy.backoff(2), Duration::from_millis(100));
+        }
+        let policy = RetryPolicy::default().with_backoff(
+            Duration::from_millis(100),
+            f64::INFINITY,
+            Duration::from_secs(1),
+        );
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+
+        assert_eq!(RetryPolicy::default().with_jitter(-1.0).jitter(), 0.0);
+        assert_eq!(RetryPolicy::default().with_jitter(2.0).jitter(), 1.0);
+        assert_eq!(RetryPolicy::default().with_jitter(f64::NAN).jitter(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn retries_are_configured_per_method() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_millis(1), 2.0, Duration::from_millis(10))
+            .with_jitter(0.0);
+        let sender = HttpSenderService::new_from_builder(
+            test_node::spawn_node(node_io()),
+            ServiceBuilder::new().layer_fn(move |s| {
+                RetryMiddleware::with_policy(s, policy.clone())
+                    .with_method_policy(RpcRequest::GetBlockHeight, RetryPolicy::none())
+            }),
+        );
+        let client = RpcClient::new_sender(sender, Default::default());
+
+        assert_eq!(client.get_slot().await.unwrap(), 100);
+        let error = client.get_block_height().await.unwrap_err();
+        assert_eq!(
+            RetryableError::of(&error),
+            Some(RetryableError::NodeUnhealthy)
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! Emit a [tracing] span for every RPC call, with the method, the size of the params and of the
+//! result, the latency, and any error. The params themselves are logged in a `debug` event,
+//! after [Redaction] of any that are sensitive, and requests slower than a threshold are
+//! logged as warnings.
+use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use serde_json::Value;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_rpc_client_api::request::RpcRequest;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::Service;
+use tracing::{debug, field, info_span, warn, Instrument, Span};
+
+/// Logged in place of redacted params.
+pub const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Redaction {
+    /// Redact all params.
+    All,
+    /// Redact the params at these positions, e.g. `[0]` for the transaction of `sendTransaction`.
+    Positions(Vec<usize>),
+}
+
+impl Redaction {
+    pub fn apply(&self, params: &Value) -> Value {
+        match (self, params) {
+            (Self::Positions(positions), Value::Array(params)) => params
+                .iter()
+                .enumerate()
+                .map(|(i, param)| match positions.contains(&i) {
+                    true => Value::from(REDACTED),
+                    false => param.clone(),
+                })
+                .collect(),
+            _ => Value::from(REDACTED),
+        }
+    }
+}
+
+/// The length of a value serialized as JSON.
+fn json_size(value: &Value) -> usize {
+    serde_json::to_vec(value).map_or(0, |bytes| bytes.len())
+}
+
+pub struct TracingMiddleware<S> {
+    inner: S,
+    redactions: Arc<HashMap<RpcRequest, Redaction>>,
+    slow_threshold: Option<Duration>,
+}
+
+impl<S> TracingMiddleware<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            redactions: Default::default(),
+            slow_threshold: None,
+        }
+    }
+
+    pub fn with_redaction(mut self, method: RpcRequest, redaction: Redaction) -> Self {
+        Arc::make_mut(&mut self.redactions).insert(method, redaction);
+        self
+    }
+
+    /// Log a warning for requests that take longer than `threshold`.
+    pub fn with_slow_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+}
+
+impl<S> Service<RpcSenderRequest> for TracingMiddleware<S>
+where
+    S: Service<
+            RpcSenderRequest,
+            Error = ClientError,
+            Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>,
+        > + Send
+        + Sync,
+{
+    type Response = Value;
+    type Error = ClientError;
+
+    type Future = Pin<Box<dyn Future<Output = RpcSenderResponse> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RpcSenderRequest) -> Self::Future {
+        let request = req.0;
+        let span = info_span!(
+            "rpc_request",
+            method = %request,
+            param_bytes = field::Empty,
+            latency_ms = field::Empty,
+            result_bytes = field::Empty,
+            error = field::Empty,
+        );
+        // Sizing re-serializes the payload, only do so when a subscriber records it.
+        if !span.is_disabled() {
+            span.record("param_bytes", json_size(&req.1));
+        }
+        span.in_scope(|| match self.redactions.get(&request) {
+            Some(redaction) => debug!(params = %redaction.apply(&req.1), "RPC request"),
+            None => debug!(params = %req.1, "RPC request"),
+        });
+        let slow_threshold = self.slow_threshold;
+        let response = self.inner.call(req);
+        Box::pin(
+            async move {
+                let start = Instant::now();
+                let response = response.await;
+                let latency = start.elapsed();
+                let span = Span::current();
+                span.record("latency_ms", latency.as_millis() as u64);
+                match &response {
+                    Ok(result) if !span.is_disabled() => {
+                        span.record("result_bytes", json_size(result));
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        span.record("error", field::display(e));
+                    }
+                }
+                if let Some(threshold) = slow_threshold.filter(|threshold| latency > *threshold) {
+                    warn!(
+                        latency_ms = latency.as_millis() as u64,
+                        threshold_ms = threshold.as_millis() as u64,
+                        "Slow RPC request: {}",
+                        request
+                    );
+                }
+                response
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use jsonrpc_core::{futures::future, IoHandler, Params};
+    use serde_json::json;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use std::fmt::Debug;
+    use std::sync::Mutex;
+    use std::thread;
+    use tower::ServiceBuilder;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    /// Collects every field of every span and event as `name=value`.
+    #[derive(Clone, Default)]
+    struct FieldCollector(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for FieldCollector {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for FieldCollector {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _: &Id, _: LayerContext<'_, S>) {
+            attrs.record(&mut self.clone());
+        }
+
+        fn on_record(&self, _: &Id, values: &Record<'_>, _: LayerContext<'_, S>) {
+            values.record(&mut self.clone());
+        }
+
+        fn on_event(&self, event: &Event<'_>, _: LayerContext<'_, S>) {
+            event.record(&mut self.clone());
+        }
+    }
+
+    /// The methods of a node whose `getSlot` takes 100ms.
+    fn node_io() -> IoHandler {
+        let mut io = IoHandler::default();
+        io.add_method("getBalance", |_: Params| {
+            future::ok(json!({ "context": { "slot": 100 }, "value": 5 }))
+        });
+        io.add_method("getSlot", |_: Params| {
+            thread::sleep(Duration::from_millis(100));
+            future::ok(json!(100))
+        });
+        io
+    }
+
+    #[tokio::test]
+    async fn requests_are_traced_with_redaction() {
+        let collector = FieldCollector::default();
+        let _guard = tracing::subscriber::set_default(
+            tracing_subscriber::registry().with(collector.clone()),
+        );
+        let client = RpcClient::new_sender(
+            HttpSenderService::new_from_builder(
+                test_node::spawn_node(node_io()),
+                ServiceBuilder::new().layer_fn(|s| {
+                    TracingMiddleware::new(s)
+                        .with_redaction(RpcRequest::GetBalance, Redaction::Positions(vec![0]))
+                        .with_slow_threshold(Duration::from_millis(50))
+                }),
+            ),
+            Default::default(),
+        );
+
+        let balance: Value = client
+            .send(
+                RpcRequest::GetBalance,
+                json!(["secret", { "commitment": "confirmed" }]),
+            )
+            .await
+            .unwrap();
+        let fields = collector.0.lock().unwrap().clone();
+        assert!(fields.contains(&"method=getBalance".to_string()));
+        assert!(fields.contains(&format!(
+            "param_bytes={}",
+            json_size(&json!(["secret", { "commitment": "confirmed" }]))
+        )));
+        assert!(fields.contains(&format!("result_bytes={}", json_size(&balance))));
+        assert!(fields.iter().any(|field| field.starts_with("latency_ms=")));
+        assert!(fields.contains(&format!(
+            "params={}",
+            json!([REDACTED, { "commitment": "confirmed" }])
+        )));
+        assert!(!fields.iter().any(|field| field.contains("secret")));
+        assert!(!fields
+            .iter()
+            .any(|field| field.contains("Slow RPC request")));
+
+        let _: u64 = client.send(RpcRequest::GetSlot, json!([])).await.unwrap();
+        assert!(client
+            .send::<Value>(RpcRequest::GetEpochInfo, json!([]))
+            .await
+            .is_err());
+        let fields = collector.0.lock().unwrap().clone();
+        assert!(fields.contains(&"message=Slow RPC request: getSlot".to_string()));
+        assert!(fields
+            .iter()
+            .any(|field| field.starts_with("error=") && field.contains("Method not found")));
+    }
+}
@@ -0,0 +1,188 @@
+//! Validate an RPC endpoint before use, so that an unhealthy or incompatible node
+//! is reported up front instead of surfacing later as a confusing deserialization error.
+use crate::HttpSenderService;
+use solana_account_decoder::UiAccountEncoding;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_rpc_client_api::config::RpcAccountInfoConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::sysvar;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EndpointError {
+    #[error(
+        "Could not reach RPC endpoint {url}, check the URL and your network connection: {source}"
+    )]
+    Unreachable { url: String, source: ClientError },
+    #[error("RPC endpoint {url} reports that it is unhealthy, try again later or use another endpoint: {source}")]
+    Unhealthy { url: String, source: ClientError },
+    #[error("RPC endpoint {url} runs Solana {node_version}, which is older than the SDK version {sdk_version} this crate is built against. Use an endpoint running {sdk_version} or later.")]
+    IncompatibleVersion {
+        url: String,
+        node_version: String,
+        sdk_version: String,
+    },
+    #[error("RPC endpoint {url} does not support {commitment} commitment: {source}")]
+    UnsupportedCommitment {
+        url: String,
+        commitment: String,
+        source: ClientError,
+    },
+    #[error("RPC endpoint {url} does not support {encoding:?} account encoding: {source}")]
+    UnsupportedEncoding {
+        url: String,
+        encoding: UiAccountEncoding,
+        source: ClientError,
+    },
+}
+
+/// What was learned about an endpoint that passed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointInfo {
+    pub url: String,
+    /// The `solana-core` version reported by `getVersion`.
+    pub node_version: String,
+    /// The Solana SDK version this crate is built against.
+    pub sdk_version: String,
+}
+
+/// Validate the endpoint at `url` with `confirmed` commitment. See [validate_client].
+pub async fn validate_endpoint<U: ToString>(url: U) -> Result<EndpointInfo, EndpointError> {
+    let client = RpcClient::new_sender(
+        HttpSenderService::new(url),
+        solana_rpc_client::rpc_client::RpcClientConfig::with_commitment(
+            CommitmentConfig::confirmed(),
+        ),
+    );
+    validate_client(&client).await
+}
+
+/// Checks that the client's endpoint:
+/// 1. Responds to `getHealth` as healthy.
+/// 2. Runs a Solana version at least as recent as the pinned SDK (major and minor).
+/// 3. Accepts the client's commitment level.
+/// 4. Supports `base64+zstd` account encoding.
+pub async fn validate_client(client: &RpcClient) -> Result<EndpointInfo, EndpointError> {
+    let url = client.url();
+
+    let version = client
+        .get_version()
+        .await
+        .map_err(|source| EndpointError::Unreachable {
+            url: url.clone(),
+            source,
+        })?;
+    client
+        .get_health()
+        .await
+        .map_err(|source| EndpointError::Unhealthy {
+            url: url.clone(),
+            source,
+        })?;
+
+    let sdk = solana_version::Version::default();
+    let sdk_version = format!("{}.{}.{}", sdk.major, sdk.minor, sdk.patch);
+    let node_version = version.solana_core;
+    if !is_compatible(&node_version, (sdk.major, sdk.minor)) {
+        return Err(EndpointError::IncompatibleVersion {
+            url,
+            node_version,
+            sdk_version,
+        });
+    }
+
+    client
+        .get_latest_blockhash_with_commitment(client.commitment())
+        .await
+        .map_err(|source| EndpointError::UnsupportedCommitment {
+            url: url.clone(),
+            commitment: client.commitment().commitment.to_string(),
+            source,
+        })?;
+
+    let encoding = UiAccountEncoding::Base64Zstd;
+    client
+        .get_account_with_config(
+            &sysvar::clock::ID,
+            RpcAccountInfoConfig {
+                encoding: Some(encoding),
+                commitment: Some(client.commitment()),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|source| EndpointError::UnsupportedEncoding {
+            url: url.clone(),
+            encoding,
+            source,
+        })?;
+
+    Ok(EndpointInfo {
+        url,
+        node_version,
+        sdk_version,
+    })
+}
+
+/// Node versions are compatible if their `major.minor` is at least that of the SDK.
+/// Unparseable versions are given the benefit of the doubt.
+fn is_compatible(node_version: &str, (sdk_major, sdk_minor): (u16, u16)) -> bool {
+    let mut pieces = node_version.split('.').map(|p| p.parse::<u16>());
+    match (pieces.next(), pieces.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => (major, minor) >= (sdk_major, sdk_minor),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use futures_util::future;
+    use jsonrpc_core::{IoHandler, Params};
+    use serde_json::json;
+
+    fn node_io(version: &'static str) -> IoHandler {
+        let mut io = IoHandler::default();
+        io.add_method("getHealth", |_: Params| future::ok(json!("ok")));
+        io.add_method("getVersion", move |_: Params| {
+            future::ok(json!({ "solana-core": version }))
+        });
+        io.add_method("getLatestBlockhash", |_: Params| {
+            future::ok(json!({
+                "context": { "slot": 100 },
+                "value": {
+                    "blockhash": "deadbeefXjn8o3yroDHxUtKsZZgoy4GPkPPXfouKNHh",
+                    "lastValidBlockHeight": 100
+                }
+            }))
+        });
+        io.add_method("getAccountInfo", |_: Params| {
+            future::ok(json!({ "context": { "slot": 100 }, "value": null }))
+        });
+        io
+    }
+
+    #[test]
+    fn version_compatibility() {
+        assert!(is_compatible("1.17.13", (1, 17)));
+        assert!(is_compatible("1.18.0", (1, 17)));
+        assert!(is_compatible("2.0.1", (1, 17)));
+        assert!(!is_compatible("1.16.23", (1, 17)));
+        assert!(is_compatible("unknown", (1, 17)));
+    }
+
+    #[tokio::test]
+    async fn validates_endpoints() {
+        let info = validate_endpoint(test_node::spawn_node(node_io("99.0.0")))
+            .await
+            .unwrap();
+        assert_eq!(info.node_version, "99.0.0");
+
+        let err = validate_endpoint(test_node::spawn_node(node_io("1.0.0")))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EndpointError::IncompatibleVersion { .. }));
+    }
+}
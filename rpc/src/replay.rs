@@ -0,0 +1,315 @@
+//! Serve RPC responses recorded by a [crate::middleware::RecordingMiddleware], so client code
+//! can be tested without a node, and a bug report can ship with the traffic that triggered it.
+//!
+//! Recordings are stored as a sequence of [RecordedExchange], in the order they were requested.
+//! See [RecordingFormat] for the file formats.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_rpc_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_rpc_client_api::client_error::{
+    Error as ClientError, ErrorKind as ClientErrorKind, Result as ClientResult,
+};
+use solana_rpc_client_api::request::{RpcError, RpcRequest, RpcResponseErrorData};
+use solana_rpc_client_api::response::RpcSimulateTransactionResult;
+use solana_sdk::clock::Slot;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// How a recording file is encoded, chosen by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// One JSON object per line. Used for any extension other than `.cbor`.
+    JsonLines,
+    /// A CBOR sequence, i.e. one CBOR item after another, for files ending in `.cbor`.
+    Cbor,
+}
+
+impl RecordingFormat {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("cbor") => Self::Cbor,
+            _ => Self::JsonLines,
+        }
+    }
+
+    pub(crate) fn write<W: Write>(
+        &self,
+        writer: &mut W,
+        exchange: &RecordedExchange,
+    ) -> io::Result<()> {
+        match self {
+            Self::JsonLines => writeln!(writer, "{}", serde_json::to_string(exchange)?),
+            Self::Cbor => {
+                ciborium::into_writer(exchange, writer).map_err(|e| io::Error::other(e.to_string()))
+            }
+        }
+    }
+
+    fn read<R: BufRead>(&self, mut reader: R) -> io::Result<Vec<RecordedExchange>> {
+        match self {
+            Self::JsonLines => reader
+                .lines()
+                .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+                .map(|line| Ok(serde_json::from_str(&line?)?))
+                .collect(),
+            Self::Cbor => {
+                let mut exchanges = vec![];
+                while !reader.fill_buf()?.is_empty() {
+                    exchanges.push(
+                        ciborium::from_reader(&mut reader).map_err(|e| {
+                            io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+                        })?,
+                    );
+                }
+                Ok(exchanges)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    /// The JSON-RPC method name, e.g. `getBalance`.
+    pub method: String,
+    pub params: Value,
+    pub response: RecordedResponse,
+}
+
+/// Node errors keep their code, message and data, like the logs of a failed preflight
+/// simulation. Other errors, like a failed connection, are recorded by their message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecordedResponse {
+    Ok(Value),
+    RpcError {
+        code: i64,
+        message: String,
+        #[serde(default, skip_serializing_if = "RecordedErrorData::is_empty")]
+        data: RecordedErrorData,
+    },
+    Error(String),
+}
+
+/// A serializable [RpcResponseErrorData].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecordedErrorData {
+    #[default]
+    Empty,
+    SendTransactionPreflightFailure(RpcSimulateTransactionResult),
+    #[serde(rename_all = "camelCase")]
+    NodeUnhealthy {
+        num_slots_behind: Option<Slot>,
+    },
+}
+
+impl RecordedErrorData {
+    fn is_empty(&self) -> bool {
+        matches!(self, Self::Empty)
+    }
+}
+
+impl From<&RpcResponseErrorData> for RecordedErrorData {
+    fn from(data: &RpcResponseErrorData) -> Self {
+        match data {
+            RpcResponseErrorData::Empty => Self::Empty,
+            RpcResponseErrorData::SendTransactionPreflightFailure(result) => {
+                Self::SendTransactionPreflightFailure(result.clone())
+            }
+            RpcResponseErrorData::NodeUnhealthy { num_slots_behind } => Self::NodeUnhealthy {
+                num_slots_behind: *num_slots_behind,
+            },
+        }
+    }
+}
+
+impl From<&RecordedErrorData> for RpcResponseErrorData {
+    fn from(data: &RecordedErrorData) -> Self {
+        match data {
+            RecordedErrorData::Empty => Self::Empty,
+            RecordedErrorData::SendTransactionPreflightFailure(result) => {
+                Self::SendTransactionPreflightFailure(result.clone())
+            }
+            RecordedErrorData::NodeUnhealthy { num_slots_behind } => Self::NodeUnhealthy {
+                num_slots_behind: *num_slots_behind,
+            },
+        }
+    }
+}
+
+impl RecordedExchange {
+    pub fn new(request: RpcRequest, params: Value, response: &ClientResult<Value>) -> Self {
+        let response = match response {
+            Ok(value) => RecordedResponse::Ok(value.clone()),
+            Err(error) => match error.kind() {
+                ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                    code,
+                    message,
+                    data,
+                }) => RecordedResponse::RpcError {
+                    code: *code,
+                    message: message.clone(),
+                    data: data.into(),
+                },
+                kind => RecordedResponse::Error(kind.to_string()),
+            },
+        };
+        Self {
+            method: request.to_string(),
+            params,
+            response,
+        }
+    }
+
+    pub fn matches(&self, request: RpcRequest, params: &Value) -> bool {
+        self.method == request.to_string() && self.params == *params
+    }
+}
+
+/// Read a recording written by a [crate::middleware::Recorder], in the [RecordingFormat]
+/// of its extension.
+pub fn load_recording<P: AsRef<Path>>(path: P) -> io::Result<Vec<RecordedExchange>> {
+    let format = RecordingFormat::from_path(&path);
+    format.read(BufReader::new(File::open(path)?))
+}
+
+/// Write a recording in the [RecordingFormat] of the path's extension.
+pub fn save_recording<P: AsRef<Path>>(path: P, exchanges: &[RecordedExchange]) -> io::Result<()> {
+    let format = RecordingFormat::from_path(&path);
+    let mut file = io::BufWriter::new(File::create(path)?);
+    for exchange in exchanges {
+        format.write(&mut file, exchange)?;
+    }
+    file.flush()
+}
+
+#[derive(Default)]
+struct ReplayState {
+    next: usize,
+    served: Vec<bool>,
+    stats: RpcTransportStats,
+}
+
+/// An [RpcSender] that answers each request with a recorded response.
+///
+/// By default, a request gets the response of the first exchange with the same method and
+/// params that hasn't been served yet, so repeated requests replay their responses in order.
+/// Once every matching exchange has been served, the last one is served again, e.g. to a
+/// confirmation loop polling for longer than it did while recording.
+///
+/// With [ReplaySender::with_strict_ordering], requests must arrive in exactly the recorded
+/// order, and the first one that doesn't fails with an error describing the divergence.
+pub struct ReplaySender {
+    exchanges: Vec<RecordedExchange>,
+    strict: bool,
+    state: Mutex<ReplayState>,
+}
+
+impl ReplaySender {
+    pub fn new(exchanges: Vec<RecordedExchange>) -> Self {
+        let state = ReplayState {
+            served: vec![false; exchanges.len()],
+            ..ReplayState::default()
+        };
+        Self {
+            exchanges,
+            strict: false,
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(load_recording(path)?))
+    }
+
+    pub fn with_strict_ordering(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// How many recorded exchanges have not been served, e.g. to assert that
+    /// the code under test made every request it made while recording.
+    pub fn remaining(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.served.iter().filter(|served| !**served).count()
+    }
+
+    /// The exchange to serve, or why there is none.
+    fn replay(&self, request: RpcRequest, params: &Value) -> Result<&RecordedExchange, String> {
+        let mut state = self.state.lock().unwrap();
+        state.stats.request_count += 1;
+        let index = if self.strict {
+            let index = state.next;
+            match self.exchanges.get(index) {
+                Some(exchange) if exchange.matches(request, params) => index,
+                Some(exchange) => {
+                    return Err(format!(
+                        "Replay diverged at request {}: recorded {} {}, got {} {}",
+                        index, exchange.method, exchange.params, request, params
+                    ))
+                }
+                None => {
+                    return Err(format!(
+                        "Replay exhausted after {} requests, got {} {}",
+                        index, request, params
+                    ))
+                }
+            }
+        } else {
+            let matching: Vec<usize> = self
+                .exchanges
+                .iter()
+                .enumerate()
+                .filter(|(_, exchange)| exchange.matches(request, params))
+                .map(|(index, _)| index)
+                .collect();
+            let unserved = matching.iter().find(|index| !state.served[**index]);
+            match unserved.or(matching.last()) {
+                Some(index) => *index,
+                None => return Err(format!("No recorded response for {} {}", request, params)),
+            }
+        };
+        state.next = index + 1;
+        state.served[index] = true;
+        Ok(&self.exchanges[index])
+    }
+}
+
+impl From<Vec<RecordedExchange>> for ReplaySender {
+    fn from(exchanges: Vec<RecordedExchange>) -> Self {
+        Self::new(exchanges)
+    }
+}
+
+#[async_trait::async_trait]
+impl RpcSender for ReplaySender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let exchange = self.replay(request, &params).map_err(|message| {
+            ClientError::new_with_request(ClientErrorKind::Custom(message), request)
+        })?;
+        let kind = match &exchange.response {
+            RecordedResponse::Ok(value) => return Ok(value.clone()),
+            RecordedResponse::RpcError {
+                code,
+                message,
+                data,
+            } => ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: *code,
+                message: message.clone(),
+                data: data.into(),
+            }),
+            RecordedResponse::Error(message) => ClientErrorKind::Custom(message.clone()),
+        };
+        Err(ClientError::new_with_request(kind, request))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.state.lock().unwrap().stats.clone()
+    }
+
+    fn url(&self) -> String {
+        "replay".to_string()
+    }
+}
@@ -0,0 +1,216 @@
+//! Scan a program's accounts without asking the node for every account's data at once.
+//! `getProgramAccounts` is first called with an empty `dataSlice` to list the matching keys,
+//! then the accounts are fetched with batched `getMultipleAccounts` calls. Batches are only
+//! requested as the stream is polled, so a slow consumer, or rate limiting middleware
+//! on the client's sender, naturally slows the scan down.
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Result as ClientResult;
+use solana_rpc_client_api::config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_rpc_client_api::filter::RpcFilterType;
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+
+/// The most accounts `getMultipleAccounts` will return in one call.
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct ProgramAccountScan {
+    pub program_id: Pubkey,
+    pub filters: Vec<RpcFilterType>,
+    /// Defaults to the client's commitment.
+    pub commitment: Option<CommitmentConfig>,
+    /// Accounts per `getMultipleAccounts` call, at most [MAX_MULTIPLE_ACCOUNTS].
+    pub batch_size: usize,
+    /// How many `getMultipleAccounts` calls may be in flight at once.
+    pub concurrency: usize,
+}
+
+impl ProgramAccountScan {
+    pub fn new(program_id: Pubkey) -> Self {
+        Self {
+            program_id,
+            filters: vec![],
+            commitment: None,
+            batch_size: MAX_MULTIPLE_ACCOUNTS,
+            concurrency: 4,
+        }
+    }
+
+    pub fn filter(mut self, filter: RpcFilterType) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = Some(commitment);
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, MAX_MULTIPLE_ACCOUNTS);
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// The keys of every account matching the filters, without their data.
+    pub async fn keys(&self, client: &RpcClient) -> ClientResult<Vec<Pubkey>> {
+        let config = RpcProgramAccountsConfig {
+            filters: (!self.filters.is_empty()).then(|| self.filters.clone()),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                data_slice: Some(UiDataSliceConfig {
+                    offset: 0,
+                    length: 0,
+                }),
+                commitment: Some(self.commitment.unwrap_or(client.commitment())),
+                min_context_slot: None,
+            },
+            with_context: None,
+        };
+        let accounts = client
+            .get_program_accounts_with_config(&self.program_id, config)
+            .await?;
+        Ok(accounts.into_iter().map(|(pubkey, _)| pubkey).collect())
+    }
+
+    /// Stream every account matching the filters, in the order their keys were listed.
+    /// Accounts closed between listing and fetching are skipped.
+    pub fn stream<'a>(
+        &'a self,
+        client: &'a RpcClient,
+    ) -> impl Stream<Item = ClientResult<(Pubkey, Account)>> + 'a {
+        stream::once(self.keys(client))
+            .map_ok(move |keys| {
+                let batches: Vec<Vec<Pubkey>> = keys
+                    .chunks(self.batch_size)
+                    .map(<[Pubkey]>::to_vec)
+                    .collect();
+                stream::iter(batches)
+                    .map(move |batch| self.fetch_batch(client, batch))
+                    .buffered(self.concurrency)
+            })
+            .try_flatten()
+            .map_ok(|accounts| stream::iter(accounts.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    async fn fetch_batch(
+        &self,
+        client: &RpcClient,
+        batch: Vec<Pubkey>,
+    ) -> ClientResult<Vec<(Pubkey, Account)>> {
+        let accounts = client
+            .get_multiple_accounts_with_commitment(
+                &batch,
+                self.commitment.unwrap_or(client.commitment()),
+            )
+            .await?
+            .value;
+        Ok(batch
+            .into_iter()
+            .zip(accounts)
+            .filter_map(|(pubkey, account)| Some((pubkey, account?)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use jsonrpc_core::{futures::future, IoHandler, Params};
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// The methods of a node listing `keys` as program accounts, where every third account has been closed
+    /// by the time its data is fetched.
+    fn node_io(keys: Vec<Pubkey>, batch_calls: Arc<AtomicUsize>) -> IoHandler {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        let listed = keys.clone();
+        io.add_method("getProgramAccounts", move |params: Params| {
+            let params: Vec<Value> = params.parse().unwrap();
+            assert_eq!(params[1]["dataSlice"], json!({ "offset": 0, "length": 0 }));
+            let accounts: Vec<Value> = listed
+                .iter()
+                .map(|pubkey| {
+                    json!({
+                        "pubkey": pubkey.to_string(),
+                        "account": {
+                            "lamports": 1,
+                            "data": ["", "base64"],
+                            "owner": Pubkey::default().to_string(),
+                            "executable": false,
+                            "rentEpoch": 0,
+                        }
+                    })
+                })
+                .collect();
+            future::ok(json!(accounts))
+        });
+        io.add_method("getMultipleAccounts", move |params: Params| {
+            batch_calls.fetch_add(1, Ordering::SeqCst);
+            let params: Vec<Value> = params.parse().unwrap();
+            let accounts: Vec<Value> = params[0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|pubkey| {
+                    let pubkey: Pubkey = pubkey.as_str().unwrap().parse().unwrap();
+                    let index = keys.iter().position(|key| *key == pubkey).unwrap();
+                    if index % 3 == 2 {
+                        return Value::Null;
+                    }
+                    json!({
+                        "lamports": index + 1,
+                        "data": ["", "base64"],
+                        "owner": Pubkey::default().to_string(),
+                        "executable": false,
+                        "rentEpoch": 0,
+                    })
+                })
+                .collect();
+            future::ok(json!({ "context": { "slot": 100 }, "value": accounts }))
+        });
+        io
+    }
+
+    #[tokio::test]
+    async fn scans_accounts_in_batches() {
+        let keys: Vec<Pubkey> = (0..10).map(|_| Pubkey::new_unique()).collect();
+        let batch_calls = Arc::new(AtomicUsize::new(0));
+        let client = RpcClient::new(test_node::spawn_node(node_io(
+            keys.clone(),
+            batch_calls.clone(),
+        )));
+        let scan = ProgramAccountScan::new(Pubkey::new_unique())
+            .batch_size(4)
+            .concurrency(2);
+
+        let accounts: Vec<(Pubkey, Account)> = scan.stream(&client).try_collect().await.unwrap();
+        let expected: Vec<(Pubkey, u64)> = keys
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| index % 3 != 2)
+            .map(|(index, pubkey)| (*pubkey, index as u64 + 1))
+            .collect();
+        assert_eq!(
+            accounts
+                .iter()
+                .map(|(pubkey, account)| (*pubkey, account.lamports))
+                .collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(batch_calls.load(Ordering::SeqCst), 3);
+    }
+}
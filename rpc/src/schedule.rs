@@ -0,0 +1,272 @@
+//! Schedule independent transactions into waves that can be sent in parallel, based on
+//! the accounts each transaction locks. Two transactions conflict if one writes an account
+//! that the other reads or writes, and conflicting transactions keep their relative order.
+use crate::fee_strategy::FeeStrategy;
+use crate::sender::{SendError, SendOutcome, SendReport, TransactionSender};
+use futures_util::future::join_all;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::SanitizedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use std::collections::{HashMap, HashSet};
+
+/// The accounts a transaction locks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountLocks {
+    pub writable: HashSet<Pubkey>,
+    pub readonly: HashSet<Pubkey>,
+}
+
+impl AccountLocks {
+    /// Locks taken by the instructions' accounts and programs. The fee payer is not included,
+    /// since fee deductions from a shared payer don't depend on the order of transactions.
+    pub fn from_instructions(instructions: &[Instruction]) -> Self {
+        let mut locks = Self::default();
+        for ix in instructions {
+            locks.readonly.insert(ix.program_id);
+            for meta in &ix.accounts {
+                if meta.is_writable {
+                    locks.writable.insert(meta.pubkey);
+                } else {
+                    locks.readonly.insert(meta.pubkey);
+                }
+            }
+        }
+        locks
+            .readonly
+            .retain(|pubkey| !locks.writable.contains(pubkey));
+        locks
+    }
+
+    /// Every lock the message takes, including the fee payer and any loaded addresses.
+    pub fn from_message(message: &SanitizedMessage) -> Self {
+        let mut locks = Self::default();
+        for (i, pubkey) in message.account_keys().iter().enumerate() {
+            if message.is_writable(i) {
+                locks.writable.insert(*pubkey);
+            } else {
+                locks.readonly.insert(*pubkey);
+            }
+        }
+        locks
+    }
+
+    pub fn conflicts_with(&self, other: &Self) -> bool {
+        self.writable
+            .iter()
+            .any(|pubkey| other.writable.contains(pubkey) || other.readonly.contains(pubkey))
+            || other
+                .writable
+                .iter()
+                .any(|pubkey| self.readonly.contains(pubkey))
+    }
+}
+
+/// For each transaction in a list, the earlier transactions that must land before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyGraph {
+    dependencies: Vec<Vec<usize>>,
+}
+
+impl DependencyGraph {
+    /// Only the nearest conflicting transactions are recorded as dependencies, e.g. a
+    /// transaction that writes an account depends on the readers since its last write,
+    /// but not on that write, which the readers already depend on.
+    pub fn new(locks: &[AccountLocks]) -> Self {
+        let mut last_writer: HashMap<Pubkey, usize> = HashMap::new();
+        let mut readers: HashMap<Pubkey, Vec<usize>> = HashMap::new();
+        let mut dependencies = vec![];
+        for (index, locks) in locks.iter().enumerate() {
+            let mut deps = HashSet::new();
+            for pubkey in &locks.readonly {
+                deps.extend(last_writer.get(pubkey));
+                readers.entry(*pubkey).or_default().push(index);
+            }
+            for pubkey in &locks.writable {
+                match readers.remove(pubkey) {
+                    Some(readers) if !readers.is_empty() => deps.extend(readers),
+                    _ => deps.extend(last_writer.get(pubkey)),
+                }
+                last_writer.insert(*pubkey, index);
+            }
+            let mut deps: Vec<usize> = deps.into_iter().collect();
+            deps.sort_unstable();
+            dependencies.push(deps);
+        }
+        Self { dependencies }
+    }
+
+    pub fn len(&self) -> usize {
+        self.dependencies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty()
+    }
+
+    pub fn dependencies(&self, index: usize) -> &[usize] {
+        &self.dependencies[index]
+    }
+
+    /// Transaction indices grouped into waves, where no two transactions in a wave conflict,
+    /// and every transaction comes after the waves of its dependencies.
+    pub fn waves(&self) -> Vec<Vec<usize>> {
+        let mut wave_of: Vec<usize> = Vec::with_capacity(self.len());
+        let mut waves: Vec<Vec<usize>> = vec![];
+        for deps in &self.dependencies {
+            let wave = deps.iter().map(|d| wave_of[*d] + 1).max().unwrap_or(0);
+            wave_of.push(wave);
+            if waves.len() <= wave {
+                waves.push(vec![]);
+            }
+            waves[wave].push(wave_of.len() - 1);
+        }
+        waves
+    }
+}
+
+/// A transaction to schedule with [TransactionSender::send_in_waves].
+pub struct ScheduledTransaction<'a> {
+    pub instructions: Vec<Instruction>,
+    pub payer: Pubkey,
+    pub signers: Vec<&'a dyn Signer>,
+}
+
+impl<F: FeeStrategy> TransactionSender<F> {
+    /// Send the transactions in waves, all transactions in a wave at once, waiting for each
+    /// wave to land before sending the next. Each transaction gets its own result, so a send
+    /// that errors doesn't abort the rest of the batch, but counts as unconfirmed.
+    /// Transactions that depend on one that did not confirm are skipped, and have no result.
+    /// Transactions found to be already processed without error count as confirmed.
+    pub async fn send_in_waves(
+        &self,
+        transactions: &[ScheduledTransaction<'_>],
+    ) -> Vec<Option<Result<SendReport, SendError>>> {
+        let locks: Vec<AccountLocks> = transactions
+            .iter()
+            .map(|tx| AccountLocks::from_instructions(&tx.instructions))
+            .collect();
+        let graph = DependencyGraph::new(&locks);
+        let mut reports: Vec<Option<Result<SendReport, SendError>>> =
+            (0..transactions.len()).map(|_| None).collect();
+        let mut unconfirmed: HashSet<usize> = HashSet::new();
+        for wave in graph.waves() {
+            let (ready, skipped): (Vec<usize>, Vec<usize>) = wave.into_iter().partition(|i| {
+                !graph
+                    .dependencies(*i)
+                    .iter()
+                    .any(|d| unconfirmed.contains(d))
+            });
+            unconfirmed.extend(skipped);
+            let sent = join_all(ready.iter().map(|i| {
+                let tx = &transactions[*i];
+                self.send(&tx.instructions, &tx.payer, &tx.signers)
+            }))
+            .await;
+            for (i, report) in ready.into_iter().zip(sent) {
+                if !matches!(
+                    report,
+                    Ok(SendReport {
+                        outcome: SendOutcome::Confirmed(_)
+                            | SendOutcome::AlreadyProcessed { error: None, .. },
+                        ..
+                    })
+                ) {
+                    unconfirmed.insert(i);
+                }
+                reports[i] = Some(report);
+            }
+        }
+        reports
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fee_strategy::StaticPrice;
+    use crate::sender::tests::node_io;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use solana_rpc_client::rpc_client::RpcClientConfig;
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use solana_sdk::instruction::AccountMeta;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::system_instruction;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn conflicting_transactions_are_ordered() {
+        let program = Pubkey::new_unique();
+        let [a, b, c] = [(); 3].map(|_| Pubkey::new_unique());
+        let ix = |writable: &[Pubkey], readonly: &[Pubkey]| {
+            let mut accounts: Vec<AccountMeta> = writable
+                .iter()
+                .map(|p| AccountMeta::new(*p, false))
+                .collect();
+            accounts.extend(
+                readonly
+                    .iter()
+                    .map(|p| AccountMeta::new_readonly(*p, false)),
+            );
+            vec![Instruction::new_with_bytes(program, &[], accounts)]
+        };
+        let transactions = [
+            ix(&[a], &[]),
+            ix(&[b], &[]),
+            ix(&[], &[a]),
+            ix(&[], &[a, c]),
+            ix(&[a], &[]),
+            ix(&[c], &[b]),
+        ];
+        let locks: Vec<AccountLocks> = transactions
+            .iter()
+            .map(|ixs| AccountLocks::from_instructions(ixs))
+            .collect();
+        assert!(locks[0].conflicts_with(&locks[2]));
+        assert!(!locks[2].conflicts_with(&locks[3]));
+
+        let graph = DependencyGraph::new(&locks);
+        assert_eq!(graph.dependencies(2), &[0]);
+        assert_eq!(graph.dependencies(4), &[2, 3]);
+        assert_eq!(graph.dependencies(5), &[1, 3]);
+        assert_eq!(graph.waves(), vec![vec![0, 1], vec![2, 3], vec![4, 5]]);
+
+        let client = RpcClient::new_sender(
+            HttpSenderService::new(test_node::spawn_node(node_io(0))),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        let mut sender = TransactionSender::new(client, StaticPrice(1));
+        sender.poll_interval = Duration::from_millis(20);
+        let payer = Keypair::new();
+        let transfer = |to: &Pubkey, lamports| ScheduledTransaction {
+            instructions: vec![system_instruction::transfer(&payer.pubkey(), to, lamports)],
+            payer: payer.pubkey(),
+            signers: vec![&payer],
+        };
+        let reports = sender
+            .send_in_waves(&[transfer(&a, 1), transfer(&b, 1), transfer(&a, 2)])
+            .await;
+        assert!(reports.iter().all(|report| matches!(
+            report,
+            Some(Ok(SendReport {
+                outcome: SendOutcome::Confirmed(_),
+                ..
+            }))
+        )));
+
+        // The first send fails to sign, which skips its dependent, but not the rest.
+        let unsigned = ScheduledTransaction {
+            instructions: ix(&[a], &[]),
+            payer: Pubkey::new_unique(),
+            signers: vec![],
+        };
+        let reports = sender
+            .send_in_waves(&[unsigned, transfer(&b, 1), transfer(&a, 2)])
+            .await;
+        assert!(matches!(reports[0], Some(Err(SendError::Signer(_)))));
+        assert!(matches!(reports[1], Some(Ok(_))));
+        assert!(reports[2].is_none());
+    }
+}
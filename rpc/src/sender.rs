@@ -0,0 +1,501 @@
+//! Broadcast a transaction until it confirms, rebroadcasting with a fresh blockhash and
+//! a compute unit price chosen by a [FeeStrategy] on each attempt.
+use crate::fee_strategy::FeeStrategy;
+use solana_devtools_tx::compute_budget::is_set_compute_unit_price;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_rpc_client_api::config::{RpcSendTransactionConfig, RpcTransactionConfig};
+use solana_sdk::clock::Slot;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::SignerError;
+use solana_sdk::signers::Signers;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use solana_transaction_status::UiTransactionEncoding;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SendError {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+}
+
+/// One broadcast of the transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendAttempt {
+    pub signature: Signature,
+    pub compute_unit_price: u64,
+    /// The last block height at which the attempt's blockhash is valid.
+    pub last_valid_block_height: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendOutcome {
+    Confirmed(Signature),
+    /// The transaction landed, but failed.
+    Failed {
+        signature: Signature,
+        error: TransactionError,
+        /// `None` if the transaction could not be fetched.
+        logs: Option<Vec<String>>,
+    },
+    /// The signed transaction was found on chain before it was broadcast, e.g. because an
+    /// identical transaction was sent by an earlier call that gave up waiting for it.
+    AlreadyProcessed {
+        signature: Signature,
+        slot: Slot,
+        error: Option<TransactionError>,
+    },
+    /// The last attempt's blockhash expired before any attempt confirmed,
+    /// so none of them can land.
+    Expired {
+        last_valid_block_height: u64,
+    },
+    /// No attempt confirmed before the sender gave up, but the last one may still land.
+    NotConfirmed,
+}
+
+/// The outcome of a send, and every attempt made, in order, which records
+/// how the compute unit price escalated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendReport {
+    pub outcome: SendOutcome,
+    pub attempts: Vec<SendAttempt>,
+}
+
+pub struct TransactionSender<F> {
+    client: Arc<RpcClient>,
+    fee_strategy: F,
+    /// The number of broadcasts before giving up.
+    pub max_attempts: usize,
+    /// How long to wait for confirmation before rebroadcasting,
+    /// unless the attempt's blockhash expires first.
+    pub attempt_timeout: Duration,
+    pub poll_interval: Duration,
+    pub send_config: RpcSendTransactionConfig,
+}
+
+impl<F: FeeStrategy> TransactionSender<F> {
+    pub fn new(client: impl Into<Arc<RpcClient>>, fee_strategy: F) -> Self {
+        Self {
+            client: client.into(),
+            fee_strategy,
+            max_attempts: 5,
+            attempt_timeout: Duration::from_secs(10),
+            poll_interval: Duration::from_millis(500),
+            send_config: RpcSendTransactionConfig::default(),
+        }
+    }
+
+    pub fn client(&self) -> &RpcClient {
+        &self.client
+    }
+
+    /// Sign and broadcast `instructions` with a fresh blockhash and a compute unit price from
+    /// the fee strategy, until a broadcast confirms at the client's commitment. Rebroadcasts
+    /// when an attempt times out or its blockhash expires. Earlier broadcasts remain eligible
+    /// to land. Any `SetComputeUnitPrice` instructions passed in
+    /// are replaced. A transaction that signs to a signature already known to the cluster, but
+    /// not broadcast by an earlier attempt of this send, is not broadcast, and reported as
+    /// [SendOutcome::AlreadyProcessed], so retrying a send is idempotent while the blockhash is
+    /// unchanged. A retry that signs to one of this send's own signatures is rebroadcast, and
+    /// awaited like any other attempt.
+    pub async fn send(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &impl Signers,
+    ) -> Result<SendReport, SendError> {
+        let instructions: Vec<Instruction> = instructions
+            .iter()
+            .filter(|ix| !is_set_compute_unit_price(ix))
+            .cloned()
+            .collect();
+        let mut writable_accounts = vec![*payer];
+        for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+            if meta.is_writable && !writable_accounts.contains(&meta.pubkey) {
+                writable_accounts.push(meta.pubkey);
+            }
+        }
+
+        let mut attempts: Vec<SendAttempt> = vec![];
+        let mut expired = false;
+        for attempt in 0..self.max_attempts {
+            let compute_unit_price = self
+                .fee_strategy
+                .compute_unit_price(&self.client, &writable_accounts, attempt)
+                .await?;
+            let mut with_price = vec![ComputeBudgetInstruction::set_compute_unit_price(
+                compute_unit_price,
+            )];
+            with_price.extend(instructions.iter().cloned());
+
+            let (blockhash, last_valid_block_height) = self
+                .client
+                .get_latest_blockhash_with_commitment(self.client.commitment())
+                .await?;
+            let mut tx = Transaction::new_with_payer(&with_price, Some(payer));
+            tx.try_sign(signers, blockhash)?;
+            // An earlier attempt that signed identically may be processed, but not yet confirmed.
+            let sent_before = attempts.iter().any(|a| a.signature == tx.signatures[0]);
+            if !sent_before {
+                if let Some(status) = self
+                    .client
+                    .get_signature_statuses(&tx.signatures[..1])
+                    .await?
+                    .value
+                    .remove(0)
+                {
+                    return Ok(SendReport {
+                        outcome: SendOutcome::AlreadyProcessed {
+                            signature: tx.signatures[0],
+                            slot: status.slot,
+                            error: status.err,
+                        },
+                        attempts,
+                    });
+                }
+            }
+            let signature = self
+                .client
+                .send_transaction_with_config(&tx, self.send_config)
+                .await?;
+            attempts.push(SendAttempt {
+                signature,
+                compute_unit_price,
+                last_valid_block_height,
+            });
+
+            match self.await_confirmation(&attempts).await? {
+                Confirmation::Landed(outcome) => return Ok(SendReport { outcome, attempts }),
+                Confirmation::Expired => expired = true,
+                Confirmation::TimedOut => expired = false,
+            }
+        }
+        let outcome = match attempts.last() {
+            Some(attempt) if expired => SendOutcome::Expired {
+                last_valid_block_height: attempt.last_valid_block_height,
+            },
+            _ => SendOutcome::NotConfirmed,
+        };
+        Ok(SendReport { outcome, attempts })
+    }
+
+    /// Poll the signatures of every attempt so far, until one confirms,
+    /// or the latest attempt times out or its blockhash expires.
+    async fn await_confirmation(
+        &self,
+        attempts: &[SendAttempt],
+    ) -> Result<Confirmation, ClientError> {
+        let signatures: Vec<Signature> = attempts.iter().map(|a| a.signature).collect();
+        let last_valid_block_height = attempts
+            .last()
+            .expect("at least one attempt")
+            .last_valid_block_height;
+        let deadline = Instant::now() + self.attempt_timeout;
+        loop {
+            let statuses = self.client.get_signature_statuses(&signatures).await?.value;
+            for (signature, status) in signatures.iter().zip(statuses) {
+                let Some(status) = status else {
+                    continue;
+                };
+                if !status.satisfies_commitment(self.client.commitment()) {
+                    continue;
+                }
+                return Ok(Confirmation::Landed(match status.err {
+                    None => SendOutcome::Confirmed(*signature),
+                    Some(error) => SendOutcome::Failed {
+                        signature: *signature,
+                        error,
+                        logs: self.fetch_logs(signature).await,
+                    },
+                }));
+            }
+            if self.client.get_block_height().await? > last_valid_block_height {
+                return Ok(Confirmation::Expired);
+            }
+            if Instant::now() >= deadline {
+                return Ok(Confirmation::TimedOut);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn fetch_logs(&self, signature: &Signature) -> Option<Vec<String>> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(self.client.commitment()),
+            max_supported_transaction_version: Some(0),
+        };
+        let tx = self
+            .client
+            .get_transaction_with_config(signature, config)
+            .await
+            .ok()?;
+        tx.transaction.meta?.log_messages.into()
+    }
+}
+
+enum Confirmation {
+    Landed(SendOutcome),
+    /// The latest attempt's blockhash expired.
+    Expired,
+    TimedOut,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::fee_strategy::{EscalateOnRetry, StaticPrice};
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use futures_util::future;
+    use jsonrpc_core::{IoHandler, Params};
+    use serde_json::{json, Value};
+    use solana_rpc_client::rpc_client::RpcClientConfig;
+    use solana_sdk::borsh0_10::try_from_slice_unchecked;
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use solana_sdk::signature::{Keypair, Signer};
+    use solana_sdk::{system_instruction, transaction::VersionedTransaction};
+    use std::collections::HashMap;
+    use std::sync::atomic::Ordering;
+    use std::sync::Mutex;
+
+    /// The methods of a node on which only transactions paying at least `min_price` land.
+    pub(crate) fn node_io(min_price: u64) -> IoHandler {
+        node_io_with_block_heights(min_price, 0)
+    }
+
+    /// Like [node_io], with a block height that advances by `blocks_per_poll` on every query.
+    /// Blockhashes are valid until block height 100.
+    fn node_io_with_block_heights(min_price: u64, blocks_per_poll: u64) -> IoHandler {
+        configured_node_io(min_price, blocks_per_poll, 0)
+    }
+
+    /// Like [node_io_with_block_heights], where landed transactions are only `processed`
+    /// for the first `processed_polls` signature status queries.
+    fn configured_node_io(min_price: u64, blocks_per_poll: u64, processed_polls: u64) -> IoHandler {
+        let prices: Arc<Mutex<HashMap<String, u64>>> = Arc::default();
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        io.add_method("getLatestBlockhash", |_: Params| {
+            future::ok(json!({
+                "context": { "slot": 100 },
+                "value": {
+                    "blockhash": "deadbeefXjn8o3yroDHxUtKsZZgoy4GPkPPXfouKNHh",
+                    "lastValidBlockHeight": 100
+                }
+            }))
+        });
+        let block_height = std::sync::atomic::AtomicU64::new(0);
+        io.add_method("getBlockHeight", move |_: Params| {
+            let height = block_height.fetch_add(blocks_per_poll, Ordering::Relaxed);
+            future::ok(json!(height + blocks_per_poll))
+        });
+        let sent = prices.clone();
+        io.add_method("sendTransaction", move |params: Params| {
+            let params: Vec<Value> = params.parse().unwrap();
+            let bytes = STANDARD.decode(params[0].as_str().unwrap()).unwrap();
+            let tx: VersionedTransaction = bincode::deserialize(&bytes).unwrap();
+            let price = match try_from_slice_unchecked(&tx.message.instructions()[0].data) {
+                Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => price,
+                _ => panic!("expected a compute unit price"),
+            };
+            let signature = tx.signatures[0].to_string();
+            sent.lock().unwrap().insert(signature.clone(), price);
+            future::ok(json!(signature))
+        });
+        let status_polls = std::sync::atomic::AtomicU64::new(0);
+        io.add_method("getSignatureStatuses", move |params: Params| {
+            let params: Vec<Vec<String>> = params.parse().unwrap();
+            let sent = prices.lock().unwrap();
+            let confirmation_status = match status_polls.fetch_add(1, Ordering::Relaxed) {
+                poll if poll < processed_polls => "processed",
+                _ => "finalized",
+            };
+            let statuses: Vec<Value> = params[0]
+                .iter()
+                .map(|signature| match sent.get(signature) {
+                    Some(price) if *price >= min_price => json!({
+                        "slot": 100,
+                        "confirmations": null,
+                        "err": null,
+                        "status": { "Ok": null },
+                        "confirmationStatus": confirmation_status
+                    }),
+                    _ => Value::Null,
+                })
+                .collect();
+            future::ok(json!({ "context": { "slot": 100 }, "value": statuses }))
+        });
+        io
+    }
+
+    #[tokio::test]
+    async fn rebroadcasts_escalate_fees() {
+        let client = RpcClient::new_sender(
+            HttpSenderService::new(test_node::spawn_node(node_io(4_000))),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        let mut sender = TransactionSender::new(
+            client,
+            EscalateOnRetry {
+                inner: StaticPrice(1_000),
+                bump_percent: 100,
+                max_price: 10_000,
+            },
+        );
+        sender.attempt_timeout = Duration::from_millis(100);
+        sender.poll_interval = Duration::from_millis(20);
+
+        let payer = Keypair::new();
+        let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        let report = sender
+            .send(
+                &[
+                    ComputeBudgetInstruction::set_compute_unit_price(1),
+                    transfer,
+                ],
+                &payer.pubkey(),
+                &[&payer],
+            )
+            .await
+            .unwrap();
+        let prices: Vec<u64> = report
+            .attempts
+            .iter()
+            .map(|a| a.compute_unit_price)
+            .collect();
+        assert_eq!(prices, vec![1_000, 2_000, 4_000]);
+        assert_eq!(
+            report.outcome,
+            SendOutcome::Confirmed(report.attempts[2].signature)
+        );
+
+        sender.max_attempts = 2;
+        let report = sender
+            .send(
+                &[system_instruction::transfer(
+                    &payer.pubkey(),
+                    &Pubkey::new_unique(),
+                    1,
+                )],
+                &payer.pubkey(),
+                &[&payer],
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.outcome, SendOutcome::NotConfirmed);
+        assert_eq!(report.attempts.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn processed_transactions_are_not_resent() {
+        let client = RpcClient::new_sender(
+            HttpSenderService::new(test_node::spawn_node(node_io(0))),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        let mut sender = TransactionSender::new(client, StaticPrice(1));
+        sender.poll_interval = Duration::from_millis(20);
+        let payer = Keypair::new();
+        let transfer = [system_instruction::transfer(
+            &payer.pubkey(),
+            &Pubkey::new_unique(),
+            1,
+        )];
+
+        let report = sender
+            .send(&transfer, &payer.pubkey(), &[&payer])
+            .await
+            .unwrap();
+        let signature = report.attempts[0].signature;
+        assert_eq!(report.outcome, SendOutcome::Confirmed(signature));
+
+        // The mock node always returns the same blockhash, so the retry signs identically.
+        let report = sender
+            .send(&transfer, &payer.pubkey(), &[&payer])
+            .await
+            .unwrap();
+        assert!(report.attempts.is_empty());
+        assert_eq!(
+            report.outcome,
+            SendOutcome::AlreadyProcessed {
+                signature,
+                slot: 100,
+                error: None
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn own_processed_attempts_are_not_already_processed() {
+        // The first attempt lands, but stays processed past its timeout.
+        let client = RpcClient::new_sender(
+            HttpSenderService::new(test_node::spawn_node(configured_node_io(0, 0, 4))),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        let mut sender = TransactionSender::new(client, StaticPrice(1));
+        sender.attempt_timeout = Duration::from_millis(40);
+        sender.poll_interval = Duration::from_millis(20);
+        let payer = Keypair::new();
+        let transfer = [system_instruction::transfer(
+            &payer.pubkey(),
+            &Pubkey::new_unique(),
+            1,
+        )];
+
+        let report = sender
+            .send(&transfer, &payer.pubkey(), &[&payer])
+            .await
+            .unwrap();
+        assert!(report.attempts.len() > 1);
+        let signature = report.attempts[0].signature;
+        assert!(report.attempts.iter().all(|a| a.signature == signature));
+        assert_eq!(report.outcome, SendOutcome::Confirmed(signature));
+    }
+
+    #[tokio::test]
+    async fn expired_blockhashes_are_refreshed() {
+        // Nothing lands, and each blockhash expires on the second poll.
+        let client = RpcClient::new_sender(
+            HttpSenderService::new(test_node::spawn_node(node_io_with_block_heights(
+                u64::MAX,
+                60,
+            ))),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        let mut sender = TransactionSender::new(client, StaticPrice(1));
+        sender.max_attempts = 2;
+        sender.poll_interval = Duration::from_millis(20);
+        let payer = Keypair::new();
+        let transfer = [system_instruction::transfer(
+            &payer.pubkey(),
+            &Pubkey::new_unique(),
+            1,
+        )];
+
+        // The attempts don't wait for the 10 second timeout.
+        let start = Instant::now();
+        let report = sender
+            .send(&transfer, &payer.pubkey(), &[&payer])
+            .await
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(report.attempts.len(), 2);
+        assert_eq!(
+            report.outcome,
+            SendOutcome::Expired {
+                last_valid_block_height: 100
+            }
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Parse JSON response bodies as they arrive, enforcing a maximum size.
+//!
+//! The body is read chunk by chunk and streamed into a blocking `serde_json` reader,
+//! so an oversized response, e.g. an unexpectedly large `getProgramAccounts` result,
+//! is rejected as soon as it crosses the limit, and the raw body is never buffered whole.
+//! Chunks are counted before they are handed to the parser, so it never sees bytes past the limit.
+//!
+//! The limit is set with [crate::HttpClientService::with_max_response_size], or from the
+//! tower stack with a [crate::middleware::ResponseSizeMiddleware].
+use bytes::{Buf, Bytes};
+use reqwest::Response;
+use serde_json::Value;
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use std::io::{self, Read};
+use tokio::sync::mpsc;
+
+/// Chunks buffered between the download and the parser.
+const CHUNK_BUFFER: usize = 16;
+
+/// Read and parse the response body, failing once more than `max_size` bytes are received.
+pub async fn read_json_with_limit(
+    mut response: Response,
+    max_size: usize,
+) -> Result<Value, ClientError> {
+    if let Some(len) = response.content_length() {
+        if len > max_size as u64 {
+            return Err(response_too_large(max_size));
+        }
+    }
+    let (sender, receiver) = mpsc::channel(CHUNK_BUFFER);
+    let parser = tokio::task::spawn_blocking(move || {
+        serde_json::from_reader::<_, Value>(ChunkReader {
+            receiver,
+            chunk: Bytes::new(),
+        })
+    });
+    let mut received = 0;
+    while let Some(chunk) = response.chunk().await? {
+        received += chunk.len();
+        if received > max_size {
+            // Dropping the sender ends the parser's input.
+            return Err(response_too_large(max_size));
+        }
+        if sender.send(chunk).await.is_err() {
+            // The parser stopped early, and will report why.
+            break;
+        }
+    }
+    drop(sender);
+    parser
+        .await
+        .map_err(|e| ClientErrorKind::Custom(format!("JSON parser failed: {e}")))?
+        .map_err(ClientError::from)
+}
+
+fn response_too_large(max_size: usize) -> ClientError {
+    ClientErrorKind::Custom(format!(
+        "RPC response exceeded the maximum size of {max_size} bytes"
+    ))
+    .into()
+}
+
+/// Reads the chunks sent by the download task, blocking until each arrives.
+struct ChunkReader {
+    receiver: mpsc::Receiver<Bytes>,
+    chunk: Bytes,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !self.chunk.has_remaining() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => self.chunk = chunk,
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.remaining());
+        self.chunk.copy_to_slice(&mut buf[..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json_rpc::HttpClientService;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use futures_util::future;
+    use jsonrpc_core::{IoHandler, Params};
+    use serde_json::json;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use solana_sdk::pubkey::Pubkey;
+    use tower::ServiceBuilder;
+
+    #[tokio::test]
+    async fn oversized_responses_are_rejected() {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        io.add_method("getProgramAccounts", |_: Params| {
+            let accounts: Vec<_> = (0..1_000)
+                .map(|_| {
+                    json!({
+                        "pubkey": Pubkey::new_unique().to_string(),
+                        "account": {
+                            "lamports": 1,
+                            "data": ["", "base64"],
+                            "owner": Pubkey::default().to_string(),
+                            "executable": false,
+                            "rentEpoch": 0,
+                            "space": 0
+                        }
+                    })
+                })
+                .collect();
+            future::ok(json!(accounts))
+        });
+        let url = test_node::spawn_node(io);
+        let client = |max_size| {
+            RpcClient::new_sender(
+                HttpSenderService::from_service_and_builder(
+                    HttpClientService::new(&url).with_max_response_size(max_size),
+                    ServiceBuilder::new(),
+                ),
+                Default::default(),
+            )
+        };
+
+        let program = Pubkey::new_unique();
+        let accounts = client(1 << 20)
+            .get_program_accounts(&program)
+            .await
+            .unwrap();
+        assert_eq!(accounts.len(), 1_000);
+        let err = client(10_000)
+            .get_program_accounts(&program)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("maximum size of 10000 bytes"),
+            "{err}"
+        );
+    }
+}
@@ -1,14 +1,19 @@
 use crate::json_rpc::stats_updater::TransportStats;
+use crate::middleware::headers::request_headers;
+use crate::middleware::response_size;
 use crate::service::{RpcSenderRequest, RpcSenderResponse};
+use body_limit::read_json_with_limit;
 use log::debug;
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::header::{HeaderMap, CONTENT_TYPE, RETRY_AFTER};
+#[cfg(feature = "probe")]
+use reqwest::header::{HeaderName, HeaderValue};
 use reqwest::{Client, Response, StatusCode};
 use serde::Deserialize;
 use serde_json::{json, Value};
-use solana_client::client_error::ClientError;
-use solana_client::rpc_custom_error;
-use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
-use solana_client::rpc_response::RpcSimulateTransactionResult;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_rpc_client_api::custom_error as rpc_custom_error;
+use solana_rpc_client_api::request::{RpcError, RpcResponseErrorData};
+use solana_rpc_client_api::response::RpcSimulateTransactionResult;
 use stats_updater::StatsUpdater;
 use std::future::Future;
 use std::pin::Pin;
@@ -19,6 +24,7 @@ use std::time::Duration;
 use tokio::time::sleep;
 use tower::Service;
 
+pub mod body_limit;
 pub mod stats_updater;
 
 const JSON_RPC: &'static str = "2.0";
@@ -38,6 +44,12 @@ pub struct HttpClientService {
     pub url: String,
     pub request_id: AtomicU64,
     pub stats: Arc<RwLock<TransportStats>>,
+    /// Responses with larger bodies fail, and bodies are parsed as they arrive.
+    /// A [crate::middleware::ResponseSizeMiddleware] can set a smaller limit.
+    pub max_response_size: Option<usize>,
+    /// How many times to retry a `429 Too Many Requests` response, honoring `Retry-After`.
+    /// 0 by default, so a [crate::middleware::RetryMiddleware] doesn't multiply the retries.
+    pub too_many_requests_retries: usize,
 }
 
 impl HttpClientService {
@@ -51,6 +63,8 @@ impl HttpClientService {
             url: url.to_string(),
             request_id: AtomicU64::new(0),
             stats: Default::default(),
+            max_response_size: None,
+            too_many_requests_retries: 0,
         }
     }
 
@@ -64,6 +78,7 @@ impl HttpClientService {
         headers: Option<HeaderMap>,
     ) -> Self {
         let mut default_headers = HeaderMap::new();
+        #[cfg(feature = "probe")]
         default_headers.append(
             HeaderName::from_static("solana-client"),
             HeaderValue::from_str(format!("rust/{}", solana_version::Version::default()).as_str())
@@ -87,8 +102,21 @@ impl HttpClientService {
             url: url.to_string(),
             request_id: AtomicU64::new(0),
             stats: Default::default(),
+            max_response_size: None,
+            too_many_requests_retries: 0,
         }
     }
+
+    /// Fail responses whose body exceeds `max_size` bytes. See [body_limit].
+    pub fn with_max_response_size(mut self, max_size: usize) -> Self {
+        self.max_response_size = Some(max_size);
+        self
+    }
+
+    pub fn with_too_many_requests_retries(mut self, retries: usize) -> Self {
+        self.too_many_requests_retries = retries;
+        self
+    }
 }
 
 impl Service<RpcSenderRequest> for HttpClientService {
@@ -115,14 +143,22 @@ impl Service<RpcSenderRequest> for HttpClientService {
         .to_string();
         let client = self.client.clone();
         let url = self.url.clone();
+        let max_response_size = self.max_response_size;
+        let mut too_many_requests_retries = self.too_many_requests_retries;
 
         Box::pin(async move {
-            let mut too_many_requests_retries = 5;
+            // Set by any `HeaderMiddleware` or `ResponseSizeMiddleware` this request passed through.
+            let headers = request_headers();
+            let max_response_size = match (max_response_size, response_size::max_response_size()) {
+                (Some(max_size), Some(layer_max_size)) => Some(max_size.min(layer_max_size)),
+                (max_size, layer_max_size) => max_size.or(layer_max_size),
+            };
             loop {
                 let response = {
                     let request_json = request_json.clone();
                     client
                         .post(&url)
+                        .headers(headers.clone())
                         .header(CONTENT_TYPE, "application/json")
                         .body(request_json)
                         .send()
@@ -156,7 +192,12 @@ impl Service<RpcSenderRequest> for HttpClientService {
                     }
                     return Err(response.error_for_status().unwrap_err().into());
                 }
-                return to_solana_rpc_result(response).await;
+                return match max_response_size {
+                    Some(max_size) => {
+                        json_to_solana_rpc_result(read_json_with_limit(response, max_size).await?)
+                    }
+                    None => to_solana_rpc_result(response).await,
+                };
             }
         })
     }
@@ -165,7 +206,11 @@ impl Service<RpcSenderRequest> for HttpClientService {
 /// Convert Reqwest responses and errors to the types
 /// required by higher-level Solana client code.
 pub async fn to_solana_rpc_result(resp: Response) -> RpcSenderResponse {
-    let mut json = resp.json::<Value>().await?;
+    json_to_solana_rpc_result(resp.json::<Value>().await?)
+}
+
+/// Extract the result or error from a parsed JSON-RPC response.
+pub fn json_to_solana_rpc_result(mut json: Value) -> RpcSenderResponse {
     if json["error"].is_object() {
         return match serde_json::from_value::<RpcErrorObject>(json["error"].clone()) {
             Ok(rpc_error_object) => {
@@ -3,9 +3,9 @@ pub mod json_rpc;
 use crate::json_rpc::stats_updater::TransportStats;
 use json_rpc::HttpClientService;
 use serde_json::Value;
-use solana_client::client_error::{ClientError, ClientErrorKind};
-use solana_client::rpc_request::RpcRequest;
 use solana_rpc_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use solana_rpc_client_api::request::RpcRequest;
 use std::fmt::Debug;
 use std::future::Future;
 use std::ops::{Deref, DerefMut};
@@ -96,7 +96,15 @@ impl<T> HttpSenderService<T> {
         U: ToString,
         L: Layer<HttpClientService, Service = T>,
     {
-        let inner = HttpClientService::new(url);
+        Self::from_service_and_builder(HttpClientService::new(url), builder)
+    }
+
+    /// Like [HttpSenderService::new_from_builder], but wraps an already configured
+    /// [HttpClientService], e.g. one with custom headers or a maximum response size.
+    pub fn from_service_and_builder<L>(inner: HttpClientService, builder: ServiceBuilder<L>) -> Self
+    where
+        L: Layer<HttpClientService, Service = T>,
+    {
         let url = inner.url.clone();
         let stats = inner.stats.clone();
         let service = builder.service(inner);
@@ -142,7 +150,7 @@ where
         &self,
         request: RpcRequest,
         params: Value,
-    ) -> solana_client::client_error::Result<Value> {
+    ) -> solana_rpc_client_api::client_error::Result<Value> {
         let fut = {
             let mut lock = self.service.write().await;
             match lock.deref_mut().ready().await {
@@ -173,7 +181,7 @@ where
 mod tests {
     use super::*;
     use crate::middleware::FilterMiddleware;
-    use crossbeam_channel::{unbounded, Receiver};
+    use crate::test_node;
     use futures_util::future;
     use jsonrpc_core::{IoHandler, Params};
     use jsonrpc_http_server::{AccessControlAllowOrigin, DomainsValidation, ServerBuilder};
@@ -185,66 +193,55 @@ mod tests {
     use solana_sdk::hash::Hash;
     use solana_sdk::pubkey;
     use solana_sdk::transport::TransportError;
-    use std::net::SocketAddr;
     use std::str::FromStr;
-    use std::thread;
     use std::time::{Duration, SystemTime};
     use tower::ServiceBuilder;
 
-    fn spawn_test_server(host: &str) -> Receiver<SocketAddr> {
-        let (sender, receiver) = unbounded();
-        let rpc_addr = host.parse().unwrap();
-        thread::spawn(move || {
-            let mut io = IoHandler::default();
-            // Successful request
-            io.add_method("getBalance", |_params: Params| {
-                future::ok(
-                    serde_json::to_value(Response {
-                        context: RpcResponseContext {
-                            slot: 100,
-                            api_version: None,
-                        },
-                        value: 50,
-                    })
-                    .unwrap(),
-                )
-            });
-            io.add_method("getVersion", |_params: Params| {
-                future::ok(
-                    serde_json::to_value(RpcVersionInfo {
-                        solana_core: "1.16.23".to_string(),
-                        feature_set: None,
-                    })
-                    .unwrap(),
-                )
-            });
-            io.add_method("getLatestBlockhash", |_params: Params| {
-                future::ok(
-                    serde_json::to_value(Response {
-                        context: RpcResponseContext {
-                            slot: 100,
-                            api_version: None,
-                        },
-                        value: RpcBlockhash {
-                            blockhash: "deadbeefXjn8o3yroDHxUtKsZZgoy4GPkPPXfouKNHh".to_string(),
-                            last_valid_block_height: 100,
-                        },
-                    })
-                    .unwrap(),
-                )
-            });
-
-            let server = ServerBuilder::new(io)
-                .threads(1)
-                .cors(DomainsValidation::AllowOnly(vec![
-                    AccessControlAllowOrigin::Any,
-                ]))
-                .start_http(&rpc_addr)
-                .expect("Unable to start RPC server");
-            sender.send(server.address().clone()).unwrap();
-            server.wait();
+    fn spawn_test_server() -> String {
+        let mut io = IoHandler::default();
+        // Successful request
+        io.add_method("getBalance", |_params: Params| {
+            future::ok(
+                serde_json::to_value(Response {
+                    context: RpcResponseContext {
+                        slot: 100,
+                        api_version: None,
+                    },
+                    value: 50,
+                })
+                .unwrap(),
+            )
+        });
+        io.add_method("getVersion", |_params: Params| {
+            future::ok(
+                serde_json::to_value(RpcVersionInfo {
+                    solana_core: "1.16.23".to_string(),
+                    feature_set: None,
+                })
+                .unwrap(),
+            )
         });
-        return receiver;
+        io.add_method("getLatestBlockhash", |_params: Params| {
+            future::ok(
+                serde_json::to_value(Response {
+                    context: RpcResponseContext {
+                        slot: 100,
+                        api_version: None,
+                    },
+                    value: RpcBlockhash {
+                        blockhash: "deadbeefXjn8o3yroDHxUtKsZZgoy4GPkPPXfouKNHh".to_string(),
+                        last_valid_block_height: 100,
+                    },
+                })
+                .unwrap(),
+            )
+        });
+
+        test_node::spawn_server(move || {
+            ServerBuilder::new(io).cors(DomainsValidation::AllowOnly(vec![
+                AccessControlAllowOrigin::Any,
+            ]))
+        })
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -275,8 +272,7 @@ mod tests {
     }
 
     async fn _test_send() {
-        let rpc_addr = spawn_test_server("0.0.0.0:0").recv().unwrap();
-        let rpc_addr = format!("http://{}", rpc_addr);
+        let rpc_addr = spawn_test_server();
 
         let sender = HttpSenderService::new(rpc_addr);
         let rpc_client = RpcClient::new_sender(sender, Default::default());
@@ -333,8 +329,7 @@ mod tests {
 
     #[tokio::test]
     async fn respects_inner_service_readiness() {
-        let rpc_addr = spawn_test_server("0.0.0.0:0").recv().unwrap();
-        let rpc_addr = format!("http://{}", rpc_addr);
+        let rpc_addr = spawn_test_server();
 
         let sender = HttpSenderService::new_from_builder(
             rpc_addr,
@@ -369,8 +364,7 @@ mod tests {
 
     #[tokio::test]
     async fn service() {
-        let rpc_addr = spawn_test_server("0.0.0.0:0").recv().unwrap();
-        let rpc_addr = format!("http://{}", rpc_addr);
+        let rpc_addr = spawn_test_server();
 
         let sender = HttpSenderService::new_from_builder(
             rpc_addr,
@@ -0,0 +1,31 @@
+//! A JSON-RPC node for tests, answering with the methods of an [IoHandler].
+use jsonrpc_core::{IoHandler, Metadata, Middleware};
+use jsonrpc_http_server::ServerBuilder;
+use std::thread;
+
+/// Start a node on a free port, returning its URL.
+pub(crate) fn spawn_node(io: IoHandler) -> String {
+    spawn_server(move || ServerBuilder::new(io))
+}
+
+/// Start a node from a [ServerBuilder], e.g. with a meta extractor or more threads,
+/// returning its URL. The server is built on its own thread.
+pub(crate) fn spawn_server<M, S>(
+    builder: impl FnOnce() -> ServerBuilder<M, S> + Send + 'static,
+) -> String
+where
+    M: Metadata + Unpin,
+    S: Middleware<M>,
+    S::Future: Unpin,
+    S::CallFuture: Unpin,
+{
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    thread::spawn(move || {
+        let server = builder()
+            .start_http(&"0.0.0.0:0".parse().unwrap())
+            .expect("Unable to start RPC server");
+        sender.send(*server.address()).unwrap();
+        server.wait();
+    });
+    format!("http://{}", receiver.recv().unwrap())
+}
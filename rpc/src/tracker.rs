@@ -0,0 +1,364 @@
+//! Track the confirmation of sent transactions, and notify sinks of each state transition,
+//! so services can follow submissions without polling for statuses themselves.
+use async_trait::async_trait;
+use serde::{Serialize, Serializer};
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Result as ClientResult;
+use solana_rpc_client_api::request::MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS;
+use solana_sdk::clock::Slot;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::TransactionError;
+use solana_transaction_status::TransactionConfirmationStatus;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::UnboundedSender;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionState {
+    Processed,
+    Confirmed,
+    Finalized,
+    /// The blockhash expired before the transaction was seen.
+    Expired,
+    /// The transaction was seen, but was rolled back with its fork before it was finalized,
+    /// and its blockhash has since expired.
+    Dropped,
+}
+
+impl TransactionState {
+    /// No transitions follow a terminal state, and the transaction is no longer tracked.
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, Self::Processed | Self::Confirmed)
+    }
+}
+
+/// A transition of a tracked transaction's state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusEvent {
+    #[serde(serialize_with = "display")]
+    pub signature: Signature,
+    pub state: TransactionState,
+    pub previous_state: Option<TransactionState>,
+    /// The slot the transaction landed in, if it has been seen.
+    pub slot: Option<Slot>,
+    /// Set if the transaction landed but failed.
+    pub error: Option<TransactionError>,
+    /// Time since tracking started.
+    #[serde(rename = "elapsedMs", serialize_with = "millis")]
+    pub elapsed: Duration,
+    #[serde(rename = "observedAtMs", serialize_with = "unix_millis")]
+    pub observed_at: SystemTime,
+}
+
+/// Receives every [StatusEvent] observed by a [ConfirmationTracker].
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    async fn notify(&self, event: &StatusEvent);
+}
+
+/// Calls an async function with each event.
+pub struct CallbackSink<F>(pub F);
+
+#[async_trait]
+impl<F, Fut> NotificationSink for CallbackSink<F>
+where
+    F: Fn(StatusEvent) -> Fut + Send + Sync,
+    Fut: Future<Output = ()> + Send,
+{
+    async fn notify(&self, event: &StatusEvent) {
+        (self.0)(event.clone()).await
+    }
+}
+
+/// Events are dropped once the receiver is closed.
+#[async_trait]
+impl NotificationSink for UnboundedSender<StatusEvent> {
+    async fn notify(&self, event: &StatusEvent) {
+        let _ = self.send(event.clone());
+    }
+}
+
+/// POSTs each event as JSON to a URL. Failed deliveries are logged, and not retried.
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    pub client: reqwest::Client,
+    pub url: String,
+}
+
+impl WebhookSink {
+    pub fn new<U: ToString>(url: U) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for WebhookSink {
+    async fn notify(&self, event: &StatusEvent) {
+        let result = self
+            .client
+            .post(&self.url)
+            .json(event)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            log::warn!("Failed to deliver {:?} to {}: {}", event.state, self.url, e);
+        }
+    }
+}
+
+struct Tracked {
+    last_valid_block_height: u64,
+    state: Option<TransactionState>,
+    slot: Option<Slot>,
+    started: Instant,
+}
+
+/// Polls the statuses of tracked transactions at the client's commitment, until each one
+/// is finalized, expired or dropped.
+pub struct ConfirmationTracker {
+    client: Arc<RpcClient>,
+    sinks: Vec<Box<dyn NotificationSink>>,
+    tracked: Mutex<HashMap<Signature, Tracked>>,
+    pub poll_interval: Duration,
+}
+
+impl ConfirmationTracker {
+    pub fn new(client: impl Into<Arc<RpcClient>>) -> Self {
+        Self {
+            client: client.into(),
+            sinks: vec![],
+            tracked: Mutex::default(),
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+
+    pub fn with_sink(mut self, sink: impl NotificationSink + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Track a sent transaction, whose blockhash is valid until `last_valid_block_height`.
+    pub fn track(&self, signature: Signature, last_valid_block_height: u64) {
+        self.tracked.lock().unwrap().insert(
+            signature,
+            Tracked {
+                last_valid_block_height,
+                state: None,
+                slot: None,
+                started: Instant::now(),
+            },
+        );
+    }
+
+    /// The number of transactions not yet in a terminal state.
+    pub fn pending(&self) -> usize {
+        self.tracked.lock().unwrap().len()
+    }
+
+    /// Poll every tracked transaction once, notify the sinks of any transitions, and return them.
+    pub async fn poll(&self) -> ClientResult<Vec<StatusEvent>> {
+        let signatures: Vec<Signature> = self.tracked.lock().unwrap().keys().copied().collect();
+        if signatures.is_empty() {
+            return Ok(vec![]);
+        }
+        let block_height = self.client.get_block_height().await?;
+        let mut statuses = Vec::with_capacity(signatures.len());
+        for chunk in signatures.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+            statuses.extend(self.client.get_signature_statuses(chunk).await?.value);
+        }
+
+        let mut events = vec![];
+        {
+            let mut tracked = self.tracked.lock().unwrap();
+            for (signature, status) in signatures.into_iter().zip(statuses) {
+                let Some(entry) = tracked.get_mut(&signature) else {
+                    continue;
+                };
+                let expired = block_height > entry.last_valid_block_height;
+                let (state, error) = match status {
+                    Some(status) if status.satisfies_commitment(self.client.commitment()) => {
+                        entry.slot = Some(status.slot);
+                        let state = match status.confirmation_status() {
+                            TransactionConfirmationStatus::Processed => TransactionState::Processed,
+                            TransactionConfirmationStatus::Confirmed => TransactionState::Confirmed,
+                            TransactionConfirmationStatus::Finalized => TransactionState::Finalized,
+                        };
+                        (state, status.err)
+                    }
+                    Some(_) => continue,
+                    None if !expired => continue,
+                    None if entry.state.is_some() => (TransactionState::Dropped, None),
+                    None => (TransactionState::Expired, None),
+                };
+                if entry.state.is_some_and(|previous| previous >= state) {
+                    continue;
+                }
+                events.push(StatusEvent {
+                    signature,
+                    state,
+                    previous_state: entry.state.replace(state),
+                    slot: entry.slot,
+                    error,
+                    elapsed: entry.started.elapsed(),
+                    observed_at: SystemTime::now(),
+                });
+                if state.is_terminal() {
+                    tracked.remove(&signature);
+                }
+            }
+        }
+        for event in &events {
+            for sink in &self.sinks {
+                sink.notify(event).await;
+            }
+        }
+        Ok(events)
+    }
+
+    /// Poll until no tracked transactions remain.
+    pub async fn run(&self) -> ClientResult<()> {
+        while self.pending() > 0 {
+            self.poll().await?;
+            tokio::time::sleep(self.poll_interval).await;
+        }
+        Ok(())
+    }
+}
+
+fn display<S: Serializer>(value: &impl ToString, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&value.to_string())
+}
+
+fn millis<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(value.as_millis())
+}
+
+fn unix_millis<S: Serializer>(value: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let since_epoch = value.duration_since(UNIX_EPOCH).unwrap_or_default();
+    millis(&since_epoch, serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_node;
+    use crate::HttpSenderService;
+    use futures_util::future;
+    use jsonrpc_core::{IoHandler, Params};
+    use serde_json::{json, Value};
+    use solana_rpc_client::rpc_client::RpcClientConfig;
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use tokio::sync::mpsc;
+
+    #[derive(Default)]
+    struct Node {
+        block_height: u64,
+        statuses: HashMap<String, &'static str>,
+    }
+
+    fn node_io(node: Arc<Mutex<Node>>) -> IoHandler {
+        let mut io = IoHandler::default();
+        io.add_method("getVersion", |_: Params| {
+            future::ok(json!({ "solana-core": "1.17.13" }))
+        });
+        let height = node.clone();
+        io.add_method("getBlockHeight", move |_: Params| {
+            future::ok(json!(height.lock().unwrap().block_height))
+        });
+        io.add_method("getSignatureStatuses", move |params: Params| {
+            let params: Vec<Vec<String>> = params.parse().unwrap();
+            let node = node.lock().unwrap();
+            let statuses: Vec<Value> = params[0]
+                .iter()
+                .map(|signature| match node.statuses.get(signature) {
+                    Some(status) => json!({
+                        "slot": 80,
+                        "confirmations": null,
+                        "err": null,
+                        "status": { "Ok": null },
+                        "confirmationStatus": status
+                    }),
+                    None => Value::Null,
+                })
+                .collect();
+            future::ok(json!({ "context": { "slot": 100 }, "value": statuses }))
+        });
+        io
+    }
+
+    #[tokio::test]
+    async fn transitions_are_notified() {
+        let node: Arc<Mutex<Node>> = Arc::default();
+        let client = RpcClient::new_sender(
+            HttpSenderService::new(test_node::spawn_node(node_io(node.clone()))),
+            RpcClientConfig::with_commitment(CommitmentConfig::processed()),
+        );
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let callbacks: Arc<Mutex<Vec<TransactionState>>> = Arc::default();
+        let seen = callbacks.clone();
+        let tracker = ConfirmationTracker::new(client)
+            .with_sink(sender)
+            .with_sink(CallbackSink(move |event: StatusEvent| {
+                seen.lock().unwrap().push(event.state);
+                future::ready(())
+            }));
+        let [finalized, expired, dropped] = [1, 2, 3].map(|i| Signature::from([i; 64]));
+        for signature in [finalized, expired, dropped] {
+            tracker.track(signature, 100);
+        }
+
+        {
+            let mut node = node.lock().unwrap();
+            node.block_height = 90;
+            node.statuses.insert(finalized.to_string(), "processed");
+            node.statuses.insert(dropped.to_string(), "confirmed");
+        }
+        let events = tracker.poll().await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(tracker.poll().await.unwrap().is_empty());
+
+        {
+            let mut node = node.lock().unwrap();
+            node.block_height = 101;
+            node.statuses.insert(finalized.to_string(), "finalized");
+            node.statuses.remove(&dropped.to_string());
+        }
+        tracker.run().await.unwrap();
+        assert_eq!(tracker.pending(), 0);
+
+        let mut events = HashMap::<Signature, Vec<StatusEvent>>::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.entry(event.signature).or_default().push(event);
+        }
+        let states = |signature| {
+            events[&signature]
+                .iter()
+                .map(|e| (e.previous_state, e.state))
+                .collect::<Vec<_>>()
+        };
+        use TransactionState::*;
+        assert_eq!(
+            states(finalized),
+            vec![(None, Processed), (Some(Processed), Finalized)]
+        );
+        assert_eq!(states(expired), vec![(None, Expired)]);
+        assert_eq!(
+            states(dropped),
+            vec![(None, Confirmed), (Some(Confirmed), Dropped)]
+        );
+        assert_eq!(events[&finalized][1].slot, Some(80));
+        assert_eq!(callbacks.lock().unwrap().len(), 5);
+
+        let json = serde_json::to_value(&events[&expired][0]).unwrap();
+        assert_eq!(json["signature"], expired.to_string());
+        assert_eq!(json["state"], "expired");
+    }
+}
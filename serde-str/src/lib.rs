@@ -23,4 +23,5 @@ pub mod error;
 pub mod option_pubkey;
 pub mod option_signature;
 pub mod pubkey;
+pub mod pubkey_vec;
 pub mod signature;
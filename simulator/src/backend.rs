@@ -0,0 +1,205 @@
+//! A common interface over the [TransactionSimulator] and [solana_program_test],
+//! so a test suite can be written once and run against either.
+use crate::profile::ProfileReport;
+use crate::token_balances::token_balances;
+use crate::{ProcessedMessage, TransactionSimulator};
+use solana_banks_client::BanksClientError;
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    clock::Clock,
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::{TransactionError, VersionedTransaction},
+};
+use std::collections::HashMap;
+use tokio::runtime::Runtime;
+
+pub trait SimulationBackend {
+    type Error: std::fmt::Debug;
+
+    /// Execute the message, and if it succeeds, commit its state changes.
+    /// The message's recent blockhash is replaced with the backend's latest one.
+    fn process_message(
+        &mut self,
+        message: VersionedMessage,
+    ) -> Result<ProcessedMessage, Self::Error>;
+
+    fn get_account(&mut self, pubkey: &Pubkey) -> Option<AccountSharedData>;
+
+    fn update_account(&mut self, pubkey: &Pubkey, account: &AccountSharedData);
+
+    fn get_clock(&mut self) -> Clock;
+
+    fn set_clock(&mut self, clock: Clock);
+}
+
+/// Messages are processed without signature verification or fees.
+impl SimulationBackend for TransactionSimulator {
+    type Error = TransactionError;
+
+    fn process_message(
+        &mut self,
+        message: VersionedMessage,
+    ) -> Result<ProcessedMessage, Self::Error> {
+        self.process_message_and_update_accounts(message)
+    }
+
+    fn get_account(&mut self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        TransactionSimulator::get_account(self, pubkey)
+    }
+
+    fn update_account(&mut self, pubkey: &Pubkey, account: &AccountSharedData) {
+        TransactionSimulator::update_account(self, pubkey, account)
+    }
+
+    fn get_clock(&mut self) -> Clock {
+        TransactionSimulator::get_clock(self)
+    }
+
+    fn set_clock(&mut self, clock: Clock) {
+        TransactionSimulator::set_clock(self, clock)
+    }
+}
+
+/// Runs a started [ProgramTest] on its own runtime, so it cannot be used
+/// from within an async context. Messages are signed with the context's payer
+/// and any signers added with [ProgramTestBackend::with_signer], and pay fees.
+/// Starting a [ProgramTest] replaces the process-wide syscall stubs with its own,
+/// after which [crate::syscalls::SyscallOverrides] no longer apply.
+pub struct ProgramTestBackend {
+    runtime: Runtime,
+    context: ProgramTestContext,
+    signers: Vec<Keypair>,
+}
+
+impl ProgramTestBackend {
+    pub fn new(program_test: ProgramTest) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let context = runtime.block_on(program_test.start_with_context());
+        Ok(Self {
+            runtime,
+            context,
+            signers: vec![],
+        })
+    }
+
+    pub fn with_signer(mut self, signer: Keypair) -> Self {
+        self.signers.push(signer);
+        self
+    }
+
+    pub fn context(&mut self) -> &mut ProgramTestContext {
+        &mut self.context
+    }
+
+    fn get_accounts(
+        &mut self,
+        pubkeys: &[Pubkey],
+    ) -> Result<HashMap<Pubkey, AccountSharedData>, BanksClientError> {
+        let mut accounts = HashMap::new();
+        for pubkey in pubkeys {
+            let account = self
+                .runtime
+                .block_on(self.context.banks_client.get_account(*pubkey))?;
+            if let Some(account) = account {
+                accounts.insert(*pubkey, account.into());
+            }
+        }
+        Ok(accounts)
+    }
+}
+
+/// Identical messages processed in the same slot produce identical transactions,
+/// which the bank rejects as already processed. [ProcessedMessage::accounts] and the
+/// token balances only cover the message's static account keys,
+/// [ProcessedMessage::invoked_instructions] is always empty, and the
+/// [ProcessedMessage::profile] attributes no units to builtin programs.
+impl SimulationBackend for ProgramTestBackend {
+    type Error = BanksClientError;
+
+    fn process_message(
+        &mut self,
+        mut message: VersionedMessage,
+    ) -> Result<ProcessedMessage, Self::Error> {
+        let blockhash = self
+            .runtime
+            .block_on(self.context.banks_client.get_latest_blockhash())?;
+        message.set_recent_blockhash(blockhash);
+        let account_keys = message.static_account_keys().to_vec();
+        let required_signers = &account_keys[..message.header().num_required_signatures as usize];
+        let keypairs = std::iter::once(&self.context.payer).chain(&self.signers);
+        let signers: Vec<&Keypair> = required_signers
+            .iter()
+            .map(|signer| {
+                keypairs
+                    .clone()
+                    .find(|keypair| keypair.pubkey() == *signer)
+                    .ok_or(BanksClientError::ClientError("missing a required signer"))
+            })
+            .collect::<Result<_, _>>()?;
+        let transaction = VersionedTransaction::try_new(message, &signers)
+            .map_err(|_| BanksClientError::ClientError("failed to sign the message"))?;
+        let signature = transaction.signatures[0];
+
+        let pre_accounts = self.get_accounts(&account_keys)?;
+        let result = self.runtime.block_on(
+            self.context
+                .banks_client
+                .process_transaction_with_metadata(transaction),
+        )?;
+        // Transactions that fail to load are not executed, and have no metadata.
+        let Some(metadata) = result.metadata else {
+            return Err(match result.result {
+                Err(error) => BanksClientError::TransactionError(error),
+                Ok(()) => BanksClientError::ClientError("missing transaction metadata"),
+            });
+        };
+        let execution_error = result.result.err();
+        let accounts = self.get_accounts(&account_keys)?;
+        let slot = self
+            .runtime
+            .block_on(self.context.banks_client.get_sysvar::<Clock>())?
+            .slot;
+        Ok(ProcessedMessage {
+            compute_units: metadata.compute_units_consumed,
+            profile: ProfileReport::from_logs(
+                &metadata.log_messages,
+                &HashMap::new(),
+                metadata.compute_units_consumed,
+            ),
+            logs: metadata.log_messages,
+            execution_error,
+            slot,
+            signature,
+            invoked_instructions: vec![],
+            pre_token_balances: token_balances(&pre_accounts),
+            post_token_balances: token_balances(&accounts),
+            accounts,
+            pre_accounts,
+        })
+    }
+
+    fn get_account(&mut self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.get_accounts(std::slice::from_ref(pubkey))
+            .ok()?
+            .remove(pubkey)
+    }
+
+    fn update_account(&mut self, pubkey: &Pubkey, account: &AccountSharedData) {
+        self.context.set_account(pubkey, account)
+    }
+
+    fn get_clock(&mut self) -> Clock {
+        self.runtime
+            .block_on(self.context.banks_client.get_sysvar::<Clock>())
+            .expect("the clock sysvar is always present")
+    }
+
+    fn set_clock(&mut self, clock: Clock) {
+        self.context.set_sysvar(&clock)
+    }
+}
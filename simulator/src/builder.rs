@@ -0,0 +1,134 @@
+//! Configure the bank behind a [TransactionSimulator], e.g. to simulate against
+//! a cluster that hasn't activated a feature yet.
+use crate::error::{Result, SimulatorError};
+use crate::{RuntimeLimits, TransactionSimulator};
+use solana_program::pubkey::Pubkey;
+use solana_program_runtime::compute_budget::ComputeBudget;
+use solana_runtime::runtime_config::RuntimeConfig;
+use solana_sdk::account::{Account, AccountSharedData, ReadableAccount};
+use solana_sdk::feature_set::FEATURE_NAMES;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Default)]
+pub struct TransactionSimulatorBuilder {
+    accounts: Vec<(Pubkey, AccountSharedData)>,
+    deactivated_features: HashSet<Pubkey>,
+    runtime_config: RuntimeConfig,
+    runtime_limits: RuntimeLimits,
+}
+
+impl TransactionSimulatorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn account(mut self, pubkey: Pubkey, account: &impl ReadableAccount) -> Self {
+        let account = AccountSharedData::from(Account {
+            lamports: account.lamports(),
+            data: account.data().to_vec(),
+            owner: *account.owner(),
+            executable: account.executable(),
+            rent_epoch: account.rent_epoch(),
+        });
+        self.accounts.push((pubkey, account));
+        self
+    }
+
+    pub fn accounts<'a, T: ReadableAccount + 'a>(
+        mut self,
+        accounts: impl IntoIterator<Item = (&'a Pubkey, &'a T)>,
+    ) -> Self {
+        for (pubkey, account) in accounts {
+            self = self.account(*pubkey, account);
+        }
+        self
+    }
+
+    /// Every feature is active by default. See [solana_sdk::feature_set] for feature ids.
+    pub fn deactivate_feature(mut self, feature_id: Pubkey) -> Self {
+        self.deactivated_features.insert(feature_id);
+        self
+    }
+
+    pub fn deactivate_features(mut self, feature_ids: impl IntoIterator<Item = Pubkey>) -> Self {
+        self.deactivated_features.extend(feature_ids);
+        self
+    }
+
+    /// The compute budget for every transaction, in place of the one requested
+    /// with compute budget instructions.
+    pub fn compute_budget(mut self, compute_budget: ComputeBudget) -> Self {
+        self.runtime_config.compute_budget = Some(compute_budget);
+        self
+    }
+
+    pub fn runtime_config(mut self, runtime_config: RuntimeConfig) -> Self {
+        self.runtime_config = runtime_config;
+        self
+    }
+
+    /// See [TransactionSimulator::set_runtime_limits].
+    pub fn runtime_limits(mut self, runtime_limits: RuntimeLimits) -> Self {
+        self.runtime_limits = runtime_limits;
+        self
+    }
+
+    /// Fails if a feature to deactivate is not a known feature id.
+    pub fn build(self) -> Result<TransactionSimulator> {
+        if let Some(unknown) = self
+            .deactivated_features
+            .iter()
+            .find(|feature| !FEATURE_NAMES.contains_key(feature))
+        {
+            return Err(SimulatorError::UnknownFeature(*unknown));
+        }
+        let simulator = TransactionSimulator::from_bank_forks(crate::setup_bank(
+            self.accounts
+                .iter()
+                .map(|(pubkey, account)| (pubkey, account)),
+            &self.deactivated_features,
+            self.runtime_config,
+        ));
+        simulator.set_runtime_limits(self.runtime_limits);
+        Ok(simulator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::feature_set;
+
+    #[test]
+    fn features_are_deactivated() {
+        let feature = feature_set::native_programs_consume_cu::id();
+        let payer = Pubkey::new_unique();
+        let payer_account = Account::new(1_000_000_000, 0, &solana_program::system_program::ID);
+
+        let simulator = TransactionSimulator::builder()
+            .account(payer, &payer_account)
+            .build()
+            .unwrap();
+        assert!(simulator.working_bank().feature_set.is_active(&feature));
+
+        let simulator = TransactionSimulator::builder()
+            .account(payer, &payer_account)
+            .deactivate_feature(feature)
+            .runtime_limits(RuntimeLimits::mainnet())
+            .build()
+            .unwrap();
+        assert!(!simulator.working_bank().feature_set.is_active(&feature));
+        assert_eq!(simulator.runtime_limits(), RuntimeLimits::mainnet());
+        assert_eq!(
+            simulator.get_account(&payer).unwrap().lamports(),
+            1_000_000_000
+        );
+
+        assert!(matches!(
+            TransactionSimulator::builder()
+                .deactivate_feature(Pubkey::new_unique())
+                .build(),
+            Err(SimulatorError::UnknownFeature(_))
+        ));
+    }
+}
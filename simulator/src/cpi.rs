@@ -0,0 +1,280 @@
+//! Record the instructions invoked through CPI while processing a message,
+//! see [crate::ProcessedMessage::invoked_instructions].
+use solana_accounts_db::account_overrides::AccountOverrides;
+use solana_accounts_db::transaction_results::{InnerInstructionsList, TransactionExecutionResult};
+use solana_program_runtime::timings::{ExecuteTimings, ProgramTiming};
+use solana_runtime::bank::{Bank, LoadAndExecuteTransactionsOutput, TransactionSimulationResult};
+use solana_runtime::transaction_batch::TransactionBatch;
+use solana_sdk::{
+    account::from_account,
+    clock::{MAX_PROCESSING_AGE, MAX_TRANSACTION_FORWARDING_DELAY},
+    instruction::{AccountMeta, Instruction},
+    message::{AccountKeys, SanitizedMessage},
+    pubkey::Pubkey,
+    slot_history::{Check, SlotHistory},
+    sysvar,
+    transaction::SanitizedTransaction,
+};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+pub use solana_devtools_execution_result::invoked::InvokedInstruction;
+
+/// A simulation result, with what the bank recorded during execution.
+pub(crate) struct RecordedSimulation {
+    pub result: TransactionSimulationResult,
+    pub inner_instructions: InnerInstructionsList,
+    /// Timings of the top-level instructions, by program.
+    pub per_program_timings: HashMap<Pubkey, ProgramTiming>,
+}
+
+/// [Bank::simulate_transaction_unchecked], with CPI recording enabled.
+/// Like it, accounts are not locked, so simulations that share writable accounts can run
+/// concurrently.
+pub(crate) fn simulate_with_recording(
+    bank: &Bank,
+    transaction: &SanitizedTransaction,
+) -> RecordedSimulation {
+    let account_keys = transaction.message().account_keys();
+    let number_of_accounts = account_keys.len();
+    let account_overrides = account_overrides_for_simulation(bank, &account_keys);
+    let lock_result = transaction
+        .get_account_locks(bank.get_transaction_account_lock_limit())
+        .map(|_| ());
+    let mut batch = TransactionBatch::new(
+        vec![lock_result],
+        bank,
+        Cow::Borrowed(std::slice::from_ref(transaction)),
+    );
+    batch.set_needs_unlock(false);
+    let mut timings = ExecuteTimings::default();
+    let LoadAndExecuteTransactionsOutput {
+        loaded_transactions,
+        mut execution_results,
+        ..
+    } = bank.load_and_execute_transactions(
+        &batch,
+        MAX_PROCESSING_AGE - MAX_TRANSACTION_FORWARDING_DELAY,
+        true,
+        true,
+        true,
+        &mut timings,
+        Some(&account_overrides),
+        None,
+    );
+
+    let post_simulation_accounts = loaded_transactions
+        .into_iter()
+        .next()
+        .and_then(|(loaded, _)| loaded.ok())
+        .map(|loaded| {
+            loaded
+                .accounts
+                .into_iter()
+                .take(number_of_accounts)
+                .collect()
+        })
+        .unwrap_or_default();
+    let units_consumed = timings
+        .details
+        .per_program_timings
+        .values()
+        .fold(0, |units: u64, timing| {
+            units.saturating_add(timing.accumulated_units)
+        });
+
+    let execution_result = execution_results.pop().unwrap();
+    let result = execution_result.flattened_result();
+    let (logs, return_data, inner_instructions) = match execution_result {
+        TransactionExecutionResult::Executed { details, .. } => (
+            details.log_messages,
+            details.return_data,
+            details.inner_instructions,
+        ),
+        TransactionExecutionResult::NotExecuted(_) => (None, None, None),
+    };
+    RecordedSimulation {
+        result: TransactionSimulationResult {
+            result,
+            logs: logs.unwrap_or_default(),
+            post_simulation_accounts,
+            units_consumed,
+            return_data,
+        },
+        inner_instructions: inner_instructions.unwrap_or_default(),
+        per_program_timings: timings.details.per_program_timings,
+    }
+}
+
+/// As in [Bank::simulate_transaction_unchecked], a transaction that reads the slot history
+/// sees it as of the parent bank, if the current slot has already been recorded in it.
+fn account_overrides_for_simulation(bank: &Bank, account_keys: &AccountKeys) -> AccountOverrides {
+    let mut account_overrides = AccountOverrides::default();
+    let slot_history_id = sysvar::slot_history::id();
+    if account_keys.iter().any(|pubkey| *pubkey == slot_history_id) {
+        let slot_history = bank
+            .get_account_with_fixed_root(&slot_history_id)
+            .and_then(|account| from_account::<SlotHistory, _>(&account))
+            .unwrap_or_default();
+        if slot_history.check(bank.slot()) == Check::Found {
+            if let Some(account) = bank
+                .parent()
+                .and_then(|parent| parent.get_account_with_fixed_root(&slot_history_id))
+            {
+                account_overrides.set_slot_history(Some(account));
+            }
+        }
+    }
+    account_overrides
+}
+
+/// Decompile recorded inner instructions against the message's account keys, in execution order.
+pub(crate) fn invoked_instructions(
+    inner_instructions: InnerInstructionsList,
+    message: &SanitizedMessage,
+) -> Vec<InvokedInstruction> {
+    let account_keys = message.account_keys();
+    inner_instructions
+        .into_iter()
+        .enumerate()
+        .flat_map(|(instruction_index, inner)| {
+            inner
+                .into_iter()
+                .map(move |inner| (instruction_index, inner))
+        })
+        .map(|(instruction_index, inner)| {
+            let ix = inner.instruction;
+            InvokedInstruction {
+                instruction_index: instruction_index as u8,
+                stack_height: inner.stack_height,
+                instruction: Instruction {
+                    program_id: account_keys[ix.program_id_index as usize],
+                    accounts: ix
+                        .accounts
+                        .iter()
+                        .map(|&index| {
+                            let index = index as usize;
+                            AccountMeta {
+                                pubkey: account_keys[index],
+                                is_signer: message.is_signer(index),
+                                is_writable: message.is_writable(index),
+                            }
+                        })
+                        .collect(),
+                    data: ix.data,
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spl_artifacts::{SPL_ASSOCIATED_TOKEN_ACCOUNT, SPL_TOKEN};
+    use crate::TransactionSimulator;
+    use solana_sdk::{
+        account::Account,
+        message::{Message, VersionedMessage},
+        pubkey::Pubkey,
+        rent::Rent,
+        system_program,
+    };
+
+    #[test]
+    fn associated_token_account_invocations_are_recorded() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_program = SPL_TOKEN.program_id;
+        let ata_program = SPL_ASSOCIATED_TOKEN_ACCOUNT.program_id;
+        let (ata, _) = Pubkey::find_program_address(
+            &[payer.as_ref(), token_program.as_ref(), mint.as_ref()],
+            &ata_program,
+        );
+        let payer_account = Account::new(1_000_000_000, 0, &system_program::ID);
+        // An initialized spl-token mint with no authorities.
+        let mut mint_data = vec![0; 82];
+        mint_data[45] = 1;
+        let mint_account = Account {
+            lamports: Rent::default().minimum_balance(mint_data.len()),
+            data: mint_data,
+            owner: token_program,
+            executable: false,
+            rent_epoch: 0,
+        };
+        let simulator = TransactionSimulator::new_with_accounts([
+            (&payer, &payer_account),
+            (&mint, &mint_account),
+        ]);
+
+        let create = Instruction::new_with_bytes(
+            ata_program,
+            &[],
+            vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new(ata, false),
+                AccountMeta::new_readonly(payer, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(token_program, false),
+            ],
+        );
+        let processed = simulator
+            .process_message(VersionedMessage::Legacy(Message::new(
+                &[create],
+                Some(&payer),
+            )))
+            .unwrap();
+        assert!(processed.success(), "{:?}", processed.logs);
+
+        let invoked = &processed.invoked_instructions;
+        assert!(invoked
+            .iter()
+            .all(|ix| ix.instruction_index == 0 && ix.stack_height == 2));
+        assert!(invoked
+            .iter()
+            .any(|ix| ix.instruction.program_id == system_program::ID));
+        let initialize = invoked
+            .iter()
+            .rfind(|ix| ix.instruction.program_id == token_program)
+            .unwrap();
+        assert_eq!(initialize.instruction.accounts[0].pubkey, ata);
+        assert!(initialize.instruction.accounts[0].is_writable);
+        assert_eq!(processed.invoked_by(0).count(), invoked.len());
+        assert_eq!(processed.invoked_by(1).count(), 0);
+    }
+
+    #[test]
+    fn simulations_do_not_lock_accounts() {
+        let payer = Pubkey::new_unique();
+        let payer_account = Account::new(1_000_000_000, 0, &system_program::ID);
+        let simulator = TransactionSimulator::new_with_accounts([(&payer, &payer_account)]);
+        let transfer =
+            solana_sdk::system_instruction::transfer(&payer, &Pubkey::new_unique(), 1_000_000);
+        let bank = simulator.working_bank();
+        let message = VersionedMessage::Legacy(Message::new_with_blockhash(
+            &[transfer],
+            Some(&payer),
+            &bank.last_blockhash(),
+        ));
+
+        // Another batch holding a write lock on the payer does not block the simulation.
+        let transaction = crate::try_sanitize_unsigned_transaction(
+            solana_sdk::transaction::VersionedTransaction {
+                signatures: vec![],
+                message: message.clone(),
+            },
+            &bank,
+        )
+        .unwrap();
+        let locked = bank.prepare_sanitized_batch(std::slice::from_ref(&transaction));
+        assert!(locked.lock_results()[0].is_ok());
+        let recorded = simulate_with_recording(&bank, &transaction);
+        assert_eq!(recorded.result.result, Ok(()));
+        drop(locked);
+
+        // Nor does the simulation leave the payer locked.
+        let locked = bank.prepare_sanitized_batch(std::slice::from_ref(&transaction));
+        assert!(locked.lock_results()[0].is_ok());
+    }
+}
@@ -0,0 +1,24 @@
+use solana_accounts_db::accounts_index::ScanError;
+use solana_program::clock::Slot;
+use solana_program::pubkey::Pubkey;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, SimulatorError>;
+
+#[derive(Debug, Error)]
+pub enum SimulatorError {
+    #[error("Cannot warp to slot {requested}, the working bank is already at slot {current}")]
+    InvalidWarpSlot { current: Slot, requested: Slot },
+    #[error("Could not find program binary {0}, has `cargo build-sbf` been run?")]
+    SbfProgramNotFound(String),
+    #[error("Could not find a declare_id! or keypair file for program {0}")]
+    ProgramIdNotFound(String),
+    #[error("Invalid keypair file: {0}")]
+    InvalidKeypairFile(String),
+    #[error("{0} is not a known feature id")]
+    UnknownFeature(Pubkey),
+    #[error("Failed to scan the working bank's accounts: {0}")]
+    AccountScan(ScanError),
+    #[error("Could not read file: {0}: {1}")]
+    FileReadError(String, std::io::Error),
+}
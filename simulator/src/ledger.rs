@@ -0,0 +1,169 @@
+//! An in-memory record of the messages processed by a [crate::TransactionSimulator],
+//! to answer `getTransaction`-style queries about locally executed history.
+//! Enable it with [crate::TransactionSimulator::enable_ledger].
+use solana_sdk::{
+    account::AccountSharedData,
+    clock::Slot,
+    instruction::{AccountMeta, Instruction},
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{SanitizedTransaction, TransactionError},
+};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// An account's state before and after a processed message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountChange {
+    /// `None` if the account did not exist.
+    pub pre: Option<AccountSharedData>,
+    pub post: AccountSharedData,
+}
+
+/// A processed message, as recorded in the [Ledger].
+#[derive(Debug, Clone)]
+pub struct LedgerEntry {
+    /// Messages are processed unsigned, so this is a synthetic signature,
+    /// which is also returned in [crate::ProcessedMessage::signature].
+    pub signature: Signature,
+    pub slot: Slot,
+    /// The message as processed, with the simulator's blockhash.
+    pub message: VersionedMessage,
+    /// The message's account keys, including any loaded from lookup tables.
+    pub account_keys: Vec<Pubkey>,
+    /// The top-level instructions, decompiled against `account_keys`.
+    pub instructions: Vec<Instruction>,
+    pub logs: Vec<String>,
+    pub compute_units: u64,
+    pub execution_error: Option<TransactionError>,
+    /// Non-executable accounts whose state the message changed.
+    pub account_changes: HashMap<Pubkey, AccountChange>,
+}
+
+impl LedgerEntry {
+    pub(crate) fn new(
+        transaction: &SanitizedTransaction,
+        slot: Slot,
+        logs: Vec<String>,
+        compute_units: u64,
+        execution_error: Option<TransactionError>,
+        account_changes: HashMap<Pubkey, AccountChange>,
+    ) -> Self {
+        let message = transaction.message();
+        let instructions = message
+            .decompile_instructions()
+            .into_iter()
+            .map(|ix| Instruction {
+                program_id: *ix.program_id,
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(|meta| AccountMeta {
+                        pubkey: *meta.pubkey,
+                        is_signer: meta.is_signer,
+                        is_writable: meta.is_writable,
+                    })
+                    .collect(),
+                data: ix.data.to_vec(),
+            })
+            .collect();
+        Self {
+            signature: *transaction.signature(),
+            slot,
+            message: transaction.to_versioned_transaction().message,
+            account_keys: message.account_keys().iter().copied().collect(),
+            instructions,
+            logs,
+            compute_units,
+            execution_error,
+            account_changes,
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.execution_error.is_none()
+    }
+
+    /// Whether the program was invoked, either by a top-level instruction or through CPI.
+    pub fn invokes_program(&self, program_id: &Pubkey) -> bool {
+        let invoke = format!("Program {} invoke", program_id);
+        self.instructions
+            .iter()
+            .any(|ix| ix.program_id == *program_id)
+            || self.logs.iter().any(|log| log.starts_with(&invoke))
+    }
+}
+
+#[derive(Debug, Default)]
+struct LedgerState {
+    entries: Vec<Arc<LedgerEntry>>,
+    by_signature: HashMap<Signature, usize>,
+    by_account: HashMap<Pubkey, Vec<usize>>,
+}
+
+/// A shared handle to the recorded history. Queries return entries in the order processed.
+#[derive(Debug, Clone, Default)]
+pub struct Ledger {
+    state: Arc<RwLock<LedgerState>>,
+}
+
+impl Ledger {
+    pub(crate) fn record(&self, entry: LedgerEntry) {
+        let mut state = self.state.write().unwrap();
+        let index = state.entries.len();
+        state.by_signature.insert(entry.signature, index);
+        for pubkey in &entry.account_keys {
+            state.by_account.entry(*pubkey).or_default().push(index);
+        }
+        state.entries.push(Arc::new(entry));
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.read().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn entries(&self) -> Vec<Arc<LedgerEntry>> {
+        self.state.read().unwrap().entries.clone()
+    }
+
+    pub fn get_transaction(&self, signature: &Signature) -> Option<Arc<LedgerEntry>> {
+        let state = self.state.read().unwrap();
+        state
+            .by_signature
+            .get(signature)
+            .map(|index| state.entries[*index].clone())
+    }
+
+    /// Entries whose message references the account.
+    pub fn transactions_for_account(&self, pubkey: &Pubkey) -> Vec<Arc<LedgerEntry>> {
+        let state = self.state.read().unwrap();
+        state
+            .by_account
+            .get(pubkey)
+            .into_iter()
+            .flatten()
+            .map(|index| state.entries[*index].clone())
+            .collect()
+    }
+
+    /// Entries that invoke the program, see [LedgerEntry::invokes_program].
+    pub fn transactions_for_program(&self, program_id: &Pubkey) -> Vec<Arc<LedgerEntry>> {
+        self.state
+            .read()
+            .unwrap()
+            .entries
+            .iter()
+            .filter(|entry| entry.invokes_program(program_id))
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        *self.state.write().unwrap() = LedgerState::default();
+    }
+}
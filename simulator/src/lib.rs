@@ -1,29 +1,63 @@
-use solana_accounts_db::accounts_index::ZeroLamport;
+use solana_accounts_db::{accounts_index::ZeroLamport, epoch_accounts_hash::EpochAccountsHash};
 use solana_program::{
     bpf_loader_upgradeable,
     bpf_loader_upgradeable::UpgradeableLoaderState,
-    clock::{Clock, Slot},
+    clock::{Clock, Epoch, Slot},
     instruction::InstructionError,
     message::VersionedMessage,
     pubkey::Pubkey,
+    sysvar::{Sysvar, SysvarId},
 };
 use solana_runtime::{
+    accounts_background_service::{AbsRequestSender, SnapshotRequestKind},
     bank::{Bank, TransactionSimulationResult},
     bank_forks::BankForks,
+    runtime_config::RuntimeConfig,
 };
 use solana_sdk::{
     account::{Account, AccountSharedData, ReadableAccount},
+    hash::Hash,
+    precompiles::verify_if_precompile,
     signature::Signature,
     transaction::{
         MessageHash, Result as TransactionResult, SanitizedTransaction, TransactionError,
         VersionedTransaction,
     },
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 
+pub mod backend;
+pub mod builder;
+pub mod cpi;
+pub mod error;
+pub mod ledger;
+pub mod limits;
+pub mod profile;
 mod program_test_private_items;
+#[cfg(feature = "rpc")]
+pub mod rpc_sender;
+pub mod sbf_program;
+pub mod snapshot;
+pub mod spl_artifacts;
+pub mod syscalls;
+pub mod token_balances;
+pub use backend::{ProgramTestBackend, SimulationBackend};
+pub use builder::TransactionSimulatorBuilder;
+pub use cpi::InvokedInstruction;
+use cpi::RecordedSimulation;
+pub use error::SimulatorError;
+use ledger::{AccountChange, Ledger, LedgerEntry};
+pub use limits::RuntimeLimits;
+pub use profile::ProfileReport;
 use program_test_private_items::setup_bank;
+#[cfg(feature = "rpc")]
+pub use rpc_sender::SimulatedRpcSender;
+pub use snapshot::SimulatorSnapshot;
+pub use solana_devtools_execution_result::ProcessedMessage;
+use solana_devtools_tx::auto_budget::ComputeUnitSimulator;
+use token_balances::token_balances;
+pub use token_balances::TokenBalance;
 
 const RENT_EXEMPT_PROGRAM_METADATA_BALANCE: u64 = 114144;
 
@@ -36,20 +70,45 @@ const RENT_EXEMPT_PROGRAM_METADATA_BALANCE: u64 = 114144;
 /// use [solana_program_test].
 pub struct TransactionSimulator {
     bank_forks: Arc<RwLock<BankForks>>,
+    /// Messages queued for execution at a future slot, see [TransactionSimulator::schedule_message].
+    scheduled_messages: RwLock<BTreeMap<Slot, Vec<VersionedMessage>>>,
+    runtime_limits: RwLock<RuntimeLimits>,
+    /// Records processed messages once enabled, see [TransactionSimulator::enable_ledger].
+    ledger: RwLock<Option<Ledger>>,
 }
 
 impl TransactionSimulator {
     pub fn new() -> Self {
-        let bank_forks = setup_bank::<Account>([]);
-        Self { bank_forks }
+        Self::from_bank_forks(setup_bank::<Account>(
+            [],
+            &HashSet::new(),
+            RuntimeConfig::default(),
+        ))
     }
 
     pub fn new_with_accounts<'a, T>(accounts: impl IntoIterator<Item = (&'a Pubkey, &'a T)>) -> Self
     where
         T: ReadableAccount + Sync + ZeroLamport + 'a,
     {
-        let bank_forks = setup_bank(accounts);
-        Self { bank_forks }
+        Self::from_bank_forks(setup_bank(
+            accounts,
+            &HashSet::new(),
+            RuntimeConfig::default(),
+        ))
+    }
+
+    /// Configure deactivated features, the runtime config and runtime limits.
+    pub fn builder() -> TransactionSimulatorBuilder {
+        TransactionSimulatorBuilder::new()
+    }
+
+    fn from_bank_forks(bank_forks: Arc<RwLock<BankForks>>) -> Self {
+        Self {
+            bank_forks,
+            scheduled_messages: RwLock::new(BTreeMap::new()),
+            runtime_limits: RwLock::new(RuntimeLimits::default()),
+            ledger: RwLock::new(None),
+        }
     }
 
     pub fn working_bank(&self) -> Arc<Bank> {
@@ -99,6 +158,16 @@ impl TransactionSimulator {
         self.update_account(&programdata_address, &program_data.into());
     }
 
+    /// Deploy every program built into the workspace's `target/deploy` as a BPF upgradeable
+    /// program, see [sbf_program::SbfProgram::find_all]. Returns the programs deployed.
+    pub fn add_programs_from_workspace(&self) -> error::Result<Vec<sbf_program::SbfProgram>> {
+        let programs = sbf_program::SbfProgram::find_all()?;
+        for program in &programs {
+            program.add_to_simulator(self)?;
+        }
+        Ok(programs)
+    }
+
     #[cfg(feature = "anchor")]
     pub fn get_anchor_account<T: anchor_lang::AccountDeserialize>(
         &self,
@@ -120,7 +189,7 @@ impl TransactionSimulator {
     }
 
     /// Update the clock slot or unix timestamp. To update the entire [Clock], use
-    /// [MockSolanaRuntime::set_clock].
+    /// [TransactionSimulator::set_clock].
     pub fn update_clock(&self, slot: Option<Slot>, unix_timestamp: Option<i64>) {
         let bank = self.working_bank();
         let mut clock = bank.clock();
@@ -133,11 +202,101 @@ impl TransactionSimulator {
         bank.set_sysvar_for_tests(&clock);
     }
 
+    /// Overwrite any sysvar account, and the cached copy that programs read through syscalls,
+    /// e.g. a custom [solana_program::rent::Rent] or [solana_program::slot_hashes::SlotHashes].
+    /// The bank rewrites some sysvars, such as the [Clock], on each new slot.
+    /// [solana_program::epoch_rewards::EpochRewards] only exists while rewards are being
+    /// distributed, so programs that read it outside of that window need it set here.
+    pub fn set_sysvar<T: Sysvar + SysvarId>(&self, sysvar: &T) {
+        self.working_bank().set_sysvar_for_tests(sysvar);
+    }
+
+    /// Read a sysvar from its account, as programs that take it as an account do.
+    /// The bank maintains the clock, rent, epoch schedule, slot hashes, slot history,
+    /// stake history and last restart slot sysvars from genesis onwards.
+    pub fn get_sysvar<T: Sysvar + SysvarId>(&self) -> Option<T> {
+        let account = self.get_account(&T::id())?;
+        bincode::deserialize(account.data()).ok()
+    }
+
+    pub fn runtime_limits(&self) -> RuntimeLimits {
+        *self.runtime_limits.read().unwrap()
+    }
+
+    /// Tighten the limits checked on every simulated transaction, e.g. to [RuntimeLimits::mainnet].
+    pub fn set_runtime_limits(&self, limits: RuntimeLimits) {
+        *self.runtime_limits.write().unwrap() = limits;
+    }
+
+    /// Start recording every processed message, successful or not, into a [Ledger],
+    /// and return a handle to it. If already recording, returns the existing ledger.
+    pub fn enable_ledger(&self) -> Ledger {
+        self.ledger
+            .write()
+            .unwrap()
+            .get_or_insert_with(Ledger::default)
+            .clone()
+    }
+
+    /// Stop recording. Existing handles to the ledger keep its history.
+    pub fn disable_ledger(&self) {
+        *self.ledger.write().unwrap() = None;
+    }
+
+    pub fn ledger(&self) -> Option<Ledger> {
+        self.ledger.read().unwrap().clone()
+    }
+
+    /// Capture every account in the working bank, except sysvars,
+    /// to roll back to with [TransactionSimulator::restore].
+    pub fn snapshot(&self) -> error::Result<SimulatorSnapshot> {
+        SimulatorSnapshot::capture(&self.working_bank())
+    }
+
+    /// Roll every account back to its state in `snapshot`, closing accounts created since.
+    /// The working bank stays at its current slot, so sysvars such as the [Clock] are not
+    /// rolled back, and neither are scheduled messages or the ledger.
+    /// A snapshot can be restored any number of times.
+    pub fn restore(&self, snapshot: &SimulatorSnapshot) -> error::Result<()> {
+        snapshot.restore(&self.working_bank())
+    }
+
     /// Simulate the execution of a transaction message, bypassing signature verification.
     pub fn process_message(
         &self,
-        mut message: VersionedMessage,
+        message: VersionedMessage,
     ) -> TransactionResult<ProcessedMessage> {
+        let (sanitized_transaction, processed) = self.execute_message(message)?;
+        if let Some(ledger) = self.ledger() {
+            let account_changes = processed
+                .accounts
+                .iter()
+                .filter(|(_, post)| !post.executable())
+                .filter_map(|(pubkey, post)| {
+                    let pre = processed.pre_accounts.get(pubkey).cloned();
+                    (pre.as_ref() != Some(post)).then(|| {
+                        let post = post.clone();
+                        (*pubkey, AccountChange { pre, post })
+                    })
+                })
+                .collect();
+            ledger.record(LedgerEntry::new(
+                &sanitized_transaction,
+                processed.slot,
+                processed.logs.clone(),
+                processed.compute_units,
+                processed.execution_error.clone(),
+                account_changes,
+            ));
+        }
+        Ok(processed)
+    }
+
+    /// [TransactionSimulator::process_message], without recording it in the ledger.
+    fn execute_message(
+        &self,
+        mut message: VersionedMessage,
+    ) -> TransactionResult<(SanitizedTransaction, ProcessedMessage)> {
         match &mut message {
             VersionedMessage::Legacy(m) => {
                 m.recent_blockhash = self.working_bank().confirmed_last_blockhash();
@@ -150,7 +309,8 @@ impl TransactionSimulator {
             signatures: vec![],
             message,
         };
-        let (bank, result) = self.simulate_transaction_unchecked(tx)?;
+        let (bank, sanitized_transaction, recorded) = self.simulate_sanitized(tx)?;
+        let result = recorded.result;
         let accounts = HashMap::from_iter(
             result
                 .post_simulation_accounts
@@ -161,13 +321,41 @@ impl TransactionSimulator {
             Ok(_) => None,
             Err(e) => Some(e),
         };
-        Ok(ProcessedMessage {
+        let pre_accounts: HashMap<_, _> = accounts
+            .keys()
+            .filter_map(|pubkey| Some((*pubkey, bank.get_account(pubkey)?)))
+            .collect();
+        let pre_token_balances = token_balances(&pre_accounts);
+        let post_token_balances = token_balances(&accounts);
+        let profile = ProfileReport::from_logs(
+            &result.logs,
+            &profile::builtin_units(&recorded.per_program_timings),
+            result.units_consumed,
+        );
+        let processed = ProcessedMessage {
             accounts,
+            pre_accounts,
             compute_units: result.units_consumed,
             logs: result.logs,
             execution_error,
             slot: bank.slot(),
-        })
+            signature: *sanitized_transaction.signature(),
+            invoked_instructions: cpi::invoked_instructions(
+                recorded.inner_instructions,
+                sanitized_transaction.message(),
+            ),
+            profile,
+            pre_token_balances,
+            post_token_balances,
+        };
+        Ok((sanitized_transaction, processed))
+    }
+
+    /// Simulate the message and report the compute units consumed by each instruction,
+    /// broken down by program. Simulation is deterministic, so reports can be compared
+    /// across runs to catch compute unit regressions.
+    pub fn profile_message(&self, message: VersionedMessage) -> TransactionResult<ProfileReport> {
+        Ok(self.process_message(message)?.profile)
     }
 
     /// Simulate the execution of a transaction message, bypassing signature verification,
@@ -180,85 +368,232 @@ impl TransactionSimulator {
     ) -> TransactionResult<ProcessedMessage> {
         let result = self.process_message(message)?;
         if result.success() {
-            result.accounts.iter().for_each(|act| {
-                // Loaded transactions store a dummy account for executable accounts.
-                // We therefore cannot update data based on this.
-                if !act.1.executable() {
-                    self.update_account(act.0, act.1);
-                }
-            });
+            self.store_processed_accounts(&result);
         }
         Ok(result)
     }
 
-    /// Skips signature verification. This is obviously not realistic,
-    /// but makes it easier to test a wider array of situations. Use with caution.
-    pub fn simulate_transaction_unchecked(
-        &self,
-        transaction: VersionedTransaction,
-    ) -> TransactionResult<(Arc<Bank>, TransactionSimulationResult)> {
-        let bank = self.working_bank();
-        let sanitized_transaction = try_sanitize_unsigned_transaction(transaction, &*bank)?;
-        let result = bank.simulate_transaction_unchecked(sanitized_transaction);
-        Ok((bank, result))
+    /// Store the post-execution state of the accounts of a processed message.
+    pub(crate) fn store_processed_accounts(&self, processed: &ProcessedMessage) {
+        processed.accounts.iter().for_each(|act| {
+            // Loaded transactions store a dummy account for executable accounts.
+            // We therefore cannot update data based on this.
+            if !act.1.executable() {
+                self.update_account(act.0, act.1);
+            }
+        });
     }
-}
 
-/// The result of a simulated transaction execution.
-#[derive(Debug, Clone)]
-pub struct ProcessedMessage {
-    pub accounts: HashMap<Pubkey, AccountSharedData>,
-    pub compute_units: u64,
-    pub logs: Vec<String>,
-    /// If the transaction successfully loads but fails during execution,
-    /// this will be a non-`None` value.
-    pub execution_error: Option<TransactionError>,
-    pub slot: u64,
-}
+    /// Advance the working bank to `warp_slot` by creating child banks,
+    /// in the same manner as [solana_program_test::ProgramTestContext::warp_to_slot].
+    /// Account state stored on the current working bank carries over to the new one.
+    pub fn warp_to_slot(&self, warp_slot: Slot) -> error::Result<()> {
+        let mut bank_forks = self.bank_forks.write().unwrap();
+        let bank = bank_forks.working_bank();
+        let working_slot = bank.slot();
+        if warp_slot <= working_slot {
+            return Err(SimulatorError::InvalidWarpSlot {
+                current: working_slot,
+                requested: warp_slot,
+            });
+        }
+        // Fill ticks so that the new bank gets a fresh blockhash
+        bank.fill_bank_with_ticks_for_tests();
+        // Accounts updated directly with [Bank::store_account] do not adjust capitalization,
+        // which would otherwise fail the accounts hash verification when warping.
+        bank.set_capitalization();
+
+        // Banks created with [Bank::warp_from_parent] are frozen,
+        // so warp to the slot before and then create an unfrozen child bank.
+        let pre_warp_slot = warp_slot - 1;
+        let warp_bank = if pre_warp_slot == working_slot {
+            bank.freeze();
+            bank
+        } else {
+            bank_forks.insert(Bank::warp_from_parent(
+                bank,
+                &Pubkey::default(),
+                pre_warp_slot,
+                solana_accounts_db::accounts_db::CalcAccountsHashDataSource::IndexForTests,
+            ))
+        };
+
+        set_root(&mut bank_forks, pre_warp_slot);
+
+        bank_forks.insert(Bank::new_from_parent(
+            warp_bank,
+            &Pubkey::default(),
+            warp_slot,
+        ));
+        Ok(())
+    }
+
+    /// Advance the working bank by `slots`, one child bank per slot. Each bank is filled
+    /// with ticks and frozen, which collects rent from the accounts due in that slot.
+    /// Crossing an epoch boundary activates features, updates stake history and pays rewards.
+    /// This is slower than [TransactionSimulator::warp_to_slot] for large jumps.
+    pub fn advance_slots(&self, slots: u64) {
+        if slots == 0 {
+            return;
+        }
+        let mut bank_forks = self.bank_forks.write().unwrap();
+        let mut bank = bank_forks.working_bank();
+        for _ in 0..slots {
+            bank.fill_bank_with_ticks_for_tests();
+            bank.set_capitalization();
+            bank.freeze();
+            let slot = bank.slot() + 1;
+            bank = bank_forks.insert(Bank::new_from_parent(bank, &Pubkey::default(), slot));
+        }
+        let parent_slot = bank.slot() - 1;
+        set_root(&mut bank_forks, parent_slot);
+    }
+
+    /// Warp the working bank to the first slot of `epoch`, see [TransactionSimulator::warp_to_slot].
+    /// The new bank processes the epoch boundary: stake activation and deactivation
+    /// progress, rewards are paid and pending features are activated.
+    pub fn warp_to_epoch(&self, epoch: Epoch) -> error::Result<()> {
+        let slot = self
+            .working_bank()
+            .epoch_schedule()
+            .get_first_slot_in_epoch(epoch);
+        self.warp_to_slot(slot)
+    }
+
+    /// Warp the working bank to the first slot of the next epoch.
+    pub fn advance_epoch(&self) -> error::Result<()> {
+        self.warp_to_epoch(self.working_bank().epoch() + 1)
+    }
 
-impl ProcessedMessage {
-    pub fn success(&self) -> bool {
-        self.execution_error.is_none()
+    /// Queue a message to be executed once the simulator reaches `slot`.
+    /// Messages are only executed by [TransactionSimulator::process_scheduled_messages].
+    /// Messages scheduled for the same slot execute in the order they were queued.
+    pub fn schedule_message(&self, slot: Slot, message: VersionedMessage) -> error::Result<()> {
+        let current = self.working_bank().slot();
+        if slot < current {
+            return Err(SimulatorError::InvalidWarpSlot {
+                current,
+                requested: slot,
+            });
+        }
+        self.scheduled_messages
+            .write()
+            .unwrap()
+            .entry(slot)
+            .or_default()
+            .push(message);
+        Ok(())
     }
 
-    pub fn check_error_code<T: Into<u32>>(
+    /// Execute all queued messages scheduled at or before `slot`, in slot order,
+    /// warping the working bank forward as needed. Each message is executed with
+    /// [TransactionSimulator::process_message_and_update_accounts], so later messages
+    /// observe the state changes of earlier successful ones.
+    /// If warping fails, the messages not yet executed stay queued.
+    pub fn process_scheduled_messages_until(
         &self,
-        instruction_index: u8,
-        error_code: T,
-    ) -> Result<(), &Option<TransactionError>> {
-        if let Some(TransactionError::InstructionError(idx, err)) = &self.execution_error {
-            if *idx != instruction_index {
-                return Err(&self.execution_error);
-            }
-            if let InstructionError::Custom(code) = err {
-                if *code != error_code.into() {
-                    return Err(&self.execution_error);
+        slot: Slot,
+    ) -> error::Result<Vec<(Slot, TransactionResult<ProcessedMessage>)>> {
+        let due = {
+            let mut scheduled = self.scheduled_messages.write().unwrap();
+            let later = match slot.checked_add(1) {
+                Some(next) => scheduled.split_off(&next),
+                None => BTreeMap::new(),
+            };
+            std::mem::replace(&mut *scheduled, later)
+        };
+        let mut results = vec![];
+        let mut due = due.into_iter();
+        while let Some((slot, messages)) = due.next() {
+            if slot > self.working_bank().slot() {
+                if let Err(e) = self.warp_to_slot(slot) {
+                    // Queue the messages that were not executed again, ahead of any
+                    // scheduled for the same slots in the meantime.
+                    let mut scheduled = self.scheduled_messages.write().unwrap();
+                    for (slot, mut messages) in std::iter::once((slot, messages)).chain(due) {
+                        let queued = scheduled.entry(slot).or_default();
+                        messages.append(queued);
+                        *queued = messages;
+                    }
+                    return Err(e);
                 }
-            } else {
-                return Err(&self.execution_error);
             }
-            Ok(())
-        } else {
-            Err(&self.execution_error)
+            for message in messages {
+                results.push((slot, self.process_message_and_update_accounts(message)));
+            }
         }
+        Ok(results)
     }
 
-    pub fn get_account(&self, pubkey: &Pubkey) -> Option<&AccountSharedData> {
-        self.accounts.get(pubkey)
+    /// Execute all queued messages in slot order. See [TransactionSimulator::process_scheduled_messages_until].
+    pub fn process_scheduled_messages(
+        &self,
+    ) -> error::Result<Vec<(Slot, TransactionResult<ProcessedMessage>)>> {
+        self.process_scheduled_messages_until(Slot::MAX)
     }
 
-    #[cfg(feature = "anchor")]
-    pub fn get_anchor_account<T: anchor_lang::AccountDeserialize>(
+    /// Skips signature verification. This is obviously not realistic,
+    /// but makes it easier to test a wider array of situations. Use with caution.
+    /// Precompile instructions are still verified, see [verify_precompiles],
+    /// as are the simulator's [RuntimeLimits].
+    pub fn simulate_transaction_unchecked(
         &self,
-        pubkey: &Pubkey,
-    ) -> Option<anchor_lang::Result<T>> {
-        self.accounts.get(pubkey).map(|act| {
-            let mut data = act.data();
-            T::try_deserialize(&mut data)
-        })
+        transaction: VersionedTransaction,
+    ) -> TransactionResult<(Arc<Bank>, TransactionSimulationResult)> {
+        let (bank, _, recorded) = self.simulate_sanitized(transaction)?;
+        Ok((bank, recorded.result))
+    }
+
+    fn simulate_sanitized(
+        &self,
+        transaction: VersionedTransaction,
+    ) -> TransactionResult<(Arc<Bank>, SanitizedTransaction, RecordedSimulation)> {
+        let bank = self.working_bank();
+        let sanitized_transaction = try_sanitize_unsigned_transaction(transaction, &*bank)?;
+        verify_precompiles(&sanitized_transaction, &bank)?;
+        self.runtime_limits().check(&sanitized_transaction, &bank)?;
+        let recorded = cpi::simulate_with_recording(&bank, &sanitized_transaction);
+        Ok((bank, sanitized_transaction, recorded))
+    }
+}
+
+/// Measures compute units for [solana_devtools_tx::TransactionSchema::instructions_with_compute_unit_limit].
+/// The measuring simulations are not recorded in the ledger.
+impl ComputeUnitSimulator for TransactionSimulator {
+    type Error = TransactionError;
+
+    fn simulate_compute_units(&self, message: VersionedMessage) -> TransactionResult<u64> {
+        let (_, processed) = self.execute_message(message)?;
+        match processed.execution_error {
+            None => Ok(processed.compute_units),
+            Some(e) => Err(e),
+        }
     }
 }
 
+/// Root `slot`, marking any epoch accounts hash it requests as valid. There is no background
+/// service to compute it, so later banks would otherwise hang on freeze.
+fn set_root(bank_forks: &mut BankForks, slot: Slot) {
+    let (snapshot_request_sender, snapshot_request_receiver) = crossbeam_channel::unbounded();
+    let abs_request_sender = AbsRequestSender::new(snapshot_request_sender);
+    bank_forks.set_root(slot, &abs_request_sender, Some(slot));
+    snapshot_request_receiver
+        .try_iter()
+        .filter(|request| request.request_kind == SnapshotRequestKind::EpochAccountsHash)
+        .for_each(|request| {
+            request
+                .snapshot_root_bank
+                .rc
+                .accounts
+                .accounts_db
+                .epoch_accounts_hash_manager
+                .set_valid(
+                    EpochAccountsHash::new(Hash::new_unique()),
+                    request.snapshot_root_bank.slot(),
+                )
+        });
+}
+
 pub fn try_sanitize_unsigned_transaction(
     mut transaction: VersionedTransaction,
     bank: &Bank,
@@ -306,6 +641,29 @@ pub fn try_sanitize_unsigned_transaction(
     }
 }
 
+/// Verify the signatures and offset tables of any ed25519 or secp256k1 instructions.
+/// The bank skips this when simulating, but programs that check these instructions
+/// through `sysvar::instructions` introspection rely on a validator having done it.
+/// Unlike the validator, which rejects the whole transaction with
+/// [TransactionError::InvalidAccountIndex], the failing instruction is reported as
+/// [InstructionError::Custom] with the [solana_sdk::precompiles::PrecompileError] code.
+pub fn verify_precompiles(
+    transaction: &SanitizedTransaction,
+    bank: &Bank,
+) -> TransactionResult<()> {
+    let instructions = transaction.message().instructions();
+    for (index, (program_id, instruction)) in transaction
+        .message()
+        .program_instructions_iter()
+        .enumerate()
+    {
+        verify_if_precompile(program_id, instruction, instructions, &bank.feature_set).map_err(
+            |e| TransactionError::InstructionError(index as u8, InstructionError::Custom(e as u32)),
+        )?;
+    }
+    Ok(())
+}
+
 #[inline]
 pub fn upgradeable_programdata_address(program_id: &Pubkey) -> Pubkey {
     Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID).0
@@ -340,3 +698,322 @@ pub fn upgradeable_program_data(program_data: &[u8], lamports: u64) -> Account {
         rent_epoch: 0,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::{
+        epoch_rewards::EpochRewards, epoch_schedule::EpochSchedule, instruction::Instruction,
+        last_restart_slot::LastRestartSlot, message::Message, slot_hashes::SlotHashes,
+        stake_history::StakeHistory, system_instruction,
+    };
+    use solana_sdk::{
+        ed25519_instruction::{
+            new_ed25519_instruction, DATA_START, PUBKEY_SERIALIZED_SIZE, SIGNATURE_OFFSETS_START,
+        },
+        precompiles::PrecompileError,
+        signature::Keypair,
+    };
+
+    #[test]
+    fn scheduled_messages_execute_at_their_slots() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let payer_account = Account::new(1_000_000_000, 0, &solana_program::system_program::ID);
+        let simulator = TransactionSimulator::new_with_accounts([(&payer, &payer_account)]);
+        let transfer = |lamports| {
+            VersionedMessage::Legacy(Message::new(
+                &[system_instruction::transfer(&payer, &recipient, lamports)],
+                Some(&payer),
+            ))
+        };
+
+        simulator
+            .schedule_message(100, transfer(3_000_000))
+            .unwrap();
+        simulator.schedule_message(10, transfer(1_000_000)).unwrap();
+        simulator.schedule_message(10, transfer(2_000_000)).unwrap();
+
+        let results = simulator.process_scheduled_messages_until(10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results
+            .iter()
+            .all(|(slot, r)| *slot == 10 && r.as_ref().unwrap().slot == 10));
+        assert_eq!(simulator.get_clock().slot, 10);
+        assert_eq!(
+            simulator.get_account(&recipient).unwrap().lamports(),
+            3_000_000
+        );
+
+        let results = simulator.process_scheduled_messages().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1.as_ref().unwrap().slot, 100);
+        assert_eq!(
+            simulator.get_account(&recipient).unwrap().lamports(),
+            6_000_000
+        );
+
+        // An invalid warp leaves the working bank as it was.
+        let tick_height = simulator.working_bank().tick_height();
+        assert!(simulator.warp_to_slot(50).is_err());
+        assert!(!simulator.working_bank().is_frozen());
+        assert_eq!(simulator.working_bank().tick_height(), tick_height);
+        assert!(simulator.schedule_message(50, transfer(1)).is_err());
+    }
+
+    #[test]
+    fn sysvar_accounts_are_maintained() {
+        let simulator = TransactionSimulator::new();
+        assert!(simulator.get_sysvar::<EpochSchedule>().is_some());
+        assert!(simulator.get_sysvar::<StakeHistory>().is_some());
+        assert!(simulator.get_sysvar::<LastRestartSlot>().is_some());
+        assert!(simulator.get_sysvar::<EpochRewards>().is_none());
+
+        let slot = simulator.get_clock().slot;
+        simulator.advance_slots(2);
+        let slot_hashes = simulator.get_sysvar::<SlotHashes>().unwrap();
+        assert!(slot_hashes.get(&slot).is_some());
+        assert!(slot_hashes.get(&(slot + 1)).is_some());
+        assert_eq!(simulator.get_sysvar::<Clock>().unwrap().slot, slot + 2);
+
+        let epoch_rewards = EpochRewards {
+            total_rewards: 1_000,
+            distributed_rewards: 10,
+            distribution_complete_block_height: 100,
+        };
+        simulator.set_sysvar(&epoch_rewards);
+        assert_eq!(simulator.get_sysvar::<EpochRewards>(), Some(epoch_rewards));
+    }
+
+    #[test]
+    fn slots_and_epochs_advance_with_child_banks() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let payer_account = Account::new(1_000_000_000, 0, &solana_program::system_program::ID);
+        let simulator = TransactionSimulator::new_with_accounts([(&payer, &payer_account)]);
+        let transfer = VersionedMessage::Legacy(Message::new(
+            &[system_instruction::transfer(&payer, &recipient, 1_000_000)],
+            Some(&payer),
+        ));
+
+        let slot = simulator.get_clock().slot;
+        let blockhash = simulator.working_bank().last_blockhash();
+        simulator.advance_slots(3);
+        assert_eq!(simulator.get_clock().slot, slot + 3);
+        assert_ne!(simulator.working_bank().last_blockhash(), blockhash);
+        simulator
+            .process_message_and_update_accounts(transfer.clone())
+            .unwrap();
+
+        simulator.advance_epoch().unwrap();
+        let schedule = *simulator.working_bank().epoch_schedule();
+        let clock = simulator.get_clock();
+        assert_eq!(clock.epoch, 1);
+        assert_eq!(clock.slot, schedule.get_first_slot_in_epoch(1));
+
+        simulator.warp_to_epoch(3).unwrap();
+        assert_eq!(simulator.get_clock().epoch, 3);
+        assert!(simulator.warp_to_epoch(2).is_err());
+
+        simulator
+            .process_message_and_update_accounts(transfer)
+            .unwrap();
+        assert_eq!(
+            simulator.get_account(&recipient).unwrap().lamports(),
+            2_000_000
+        );
+    }
+
+    #[test]
+    fn ledger_records_processed_messages() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let payer_account = Account::new(1_000_000_000, 0, &solana_program::system_program::ID);
+        let simulator = TransactionSimulator::new_with_accounts([(&payer, &payer_account)]);
+        let transfer = |lamports| {
+            VersionedMessage::Legacy(Message::new(
+                &[system_instruction::transfer(&payer, &recipient, lamports)],
+                Some(&payer),
+            ))
+        };
+        simulator
+            .process_message_and_update_accounts(transfer(1_000_000))
+            .unwrap();
+        assert!(simulator.ledger().is_none());
+
+        let ledger = simulator.enable_ledger();
+        let first = simulator
+            .process_message_and_update_accounts(transfer(2_000_000))
+            .unwrap();
+        let second = simulator.process_message(transfer(u64::MAX)).unwrap();
+        assert!(!second.success());
+        assert_eq!(ledger.len(), 2);
+
+        let entry = ledger.get_transaction(&first.signature).unwrap();
+        assert_eq!(entry.slot, first.slot);
+        assert_eq!(
+            entry.instructions,
+            vec![system_instruction::transfer(&payer, &recipient, 2_000_000)]
+        );
+        let change = &entry.account_changes[&recipient];
+        assert_eq!(change.pre.as_ref().unwrap().lamports(), 1_000_000);
+        assert_eq!(change.post.lamports(), 3_000_000);
+        assert!(ledger
+            .get_transaction(&second.signature)
+            .unwrap()
+            .execution_error
+            .is_some());
+        assert_eq!(ledger.transactions_for_account(&recipient).len(), 2);
+        assert_eq!(
+            ledger
+                .transactions_for_program(&solana_program::system_program::ID)
+                .len(),
+            2
+        );
+        assert!(ledger
+            .transactions_for_program(&Pubkey::new_unique())
+            .is_empty());
+    }
+
+    #[test]
+    fn precompile_instructions_are_verified() {
+        let payer = Pubkey::new_unique();
+        let payer_account = Account::new(1_000_000_000, 0, &solana_program::system_program::ID);
+        let simulator = TransactionSimulator::new_with_accounts([(&payer, &payer_account)]);
+        let keypair = ed25519_dalek::Keypair::from_bytes(&Keypair::new().to_bytes()).unwrap();
+        let verify = new_ed25519_instruction(&keypair, b"hello");
+        let process = |instructions: &[Instruction]| {
+            simulator.process_message(VersionedMessage::Legacy(Message::new(
+                instructions,
+                Some(&payer),
+            )))
+        };
+
+        assert!(process(&[verify.clone(), verify.clone()])
+            .unwrap()
+            .success());
+
+        let mut bad_signature = verify.clone();
+        bad_signature.data[DATA_START + PUBKEY_SERIALIZED_SIZE] ^= 1;
+        assert_eq!(
+            process(&[verify.clone(), bad_signature]).unwrap_err(),
+            TransactionError::InstructionError(
+                1,
+                InstructionError::Custom(PrecompileError::InvalidSignature as u32)
+            )
+        );
+
+        // The offsets table may point into any instruction, but it must exist.
+        let mut bad_offsets = verify;
+        let offsets = &mut bad_offsets.data[SIGNATURE_OFFSETS_START..DATA_START];
+        offsets[2..4].copy_from_slice(&5u16.to_le_bytes());
+        assert_eq!(
+            process(&[bad_offsets]).unwrap_err(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(PrecompileError::InvalidDataOffsets as u32)
+            )
+        );
+    }
+
+    #[test]
+    fn precompile_offsets_may_point_into_other_instructions() {
+        let payer = Pubkey::new_unique();
+        let payer_account = Account::new(1_000_000_000, 0, &solana_program::system_program::ID);
+        let simulator = TransactionSimulator::new_with_accounts([(&payer, &payer_account)]);
+        let keypair = ed25519_dalek::Keypair::from_bytes(&Keypair::new().to_bytes()).unwrap();
+        let carrier = new_ed25519_instruction(&keypair, b"hello");
+        let process = |instructions: &[Instruction]| {
+            simulator.process_message(VersionedMessage::Legacy(Message::new(
+                instructions,
+                Some(&payer),
+            )))
+        };
+        // Only the offsets table, pointing every field into the instruction at `index`.
+        let pointing_to = |index: u16| {
+            let mut data = carrier.data[..DATA_START].to_vec();
+            let offsets = &mut data[SIGNATURE_OFFSETS_START..];
+            for field in [2, 6, 12] {
+                offsets[field..field + 2].copy_from_slice(&index.to_le_bytes());
+            }
+            Instruction::new_with_bytes(carrier.program_id, &data, vec![])
+        };
+
+        assert!(process(&[pointing_to(1), carrier.clone()])
+            .unwrap()
+            .success());
+        // Instructions are verified in order, so the first one to read the data fails.
+        let mut bad_carrier = carrier.clone();
+        bad_carrier.data[DATA_START + PUBKEY_SERIALIZED_SIZE] ^= 1;
+        assert_eq!(
+            process(&[pointing_to(1), bad_carrier]).unwrap_err(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(PrecompileError::InvalidSignature as u32)
+            )
+        );
+        // Pointing into itself, the offsets table is too short to hold the data.
+        assert_eq!(
+            process(&[carrier.clone(), pointing_to(1)]).unwrap_err(),
+            TransactionError::InstructionError(
+                1,
+                InstructionError::Custom(PrecompileError::InvalidDataOffsets as u32)
+            )
+        );
+        // Trailing data without any signatures is rejected.
+        let empty = Instruction::new_with_bytes(carrier.program_id, &[0, 0, 0], vec![]);
+        assert_eq!(
+            process(&[empty]).unwrap_err(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(PrecompileError::InvalidInstructionDataSize as u32)
+            )
+        );
+    }
+
+    #[test]
+    fn compute_unit_limit_is_simulated() {
+        use solana_devtools_tx::auto_budget::SafetyMargin;
+        use solana_devtools_tx::TransactionSchema;
+        use solana_sdk::compute_budget::ComputeBudgetInstruction;
+
+        let payer = Pubkey::new_unique();
+        let payer_account = Account::new(1_000_000_000, 0, &solana_program::system_program::ID);
+        let simulator = TransactionSimulator::new_with_accounts([(&payer, &payer_account)]);
+        let ledger = simulator.enable_ledger();
+        let transfer = vec![system_instruction::transfer(
+            &payer,
+            &Pubkey::new_unique(),
+            1_000_000,
+        )];
+        let margin = SafetyMargin {
+            percent: 0,
+            extra_units: 0,
+        };
+        let instructions = transfer
+            .instructions_with_compute_unit_limit(&payer, &simulator, margin)
+            .unwrap();
+        // Estimating compute units is not a processed message.
+        assert!(ledger.is_empty());
+        let processed = simulator
+            .process_message(VersionedMessage::Legacy(Message::new(
+                &instructions,
+                Some(&payer),
+            )))
+            .unwrap();
+        assert!(processed.success());
+        assert_eq!(
+            instructions[0],
+            ComputeBudgetInstruction::set_compute_unit_limit(processed.compute_units as u32)
+        );
+
+        let overdraw = vec![system_instruction::transfer(
+            &payer,
+            &Pubkey::new_unique(),
+            u64::MAX,
+        )];
+        assert!(overdraw
+            .instructions_with_compute_unit_limit(&payer, &simulator, margin)
+            .is_err());
+    }
+}
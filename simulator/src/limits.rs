@@ -0,0 +1,211 @@
+//! Runtime limits that a cluster enforces per transaction, which can be tightened
+//! to catch failures like [TransactionError::MaxLoadedAccountsDataSizeExceeded]
+//! during simulation rather than in production.
+use solana_program::{
+    borsh0_10::try_from_slice_unchecked, bpf_loader_upgradeable,
+    bpf_loader_upgradeable::UpgradeableLoaderState, instruction::InstructionError, pubkey::Pubkey,
+    sysvar,
+};
+use solana_program_runtime::compute_budget::{
+    ComputeBudget, MAX_COMPUTE_UNIT_LIMIT, MAX_LOADED_ACCOUNTS_DATA_SIZE_BYTES,
+};
+use solana_runtime::bank::Bank;
+use solana_sdk::{
+    account::ReadableAccount,
+    compute_budget::{self, ComputeBudgetInstruction},
+    feature_set,
+    transaction::{
+        Result as TransactionResult, SanitizedTransaction, TransactionError, MAX_TX_ACCOUNT_LOCKS,
+    },
+};
+use std::collections::HashSet;
+
+/// The largest heap frame a transaction can request.
+pub const MAX_HEAP_FRAME_BYTES: u32 = 256 * 1024;
+
+/// Mainnet-beta locks at most this many accounts per transaction,
+/// fewer than the SDK's [MAX_TX_ACCOUNT_LOCKS].
+pub const MAINNET_MAX_TX_ACCOUNT_LOCKS: usize = 64;
+
+/// Limits checked before a transaction is simulated. The bank always enforces its own
+/// defaults, so these can be lowered to match a stricter cluster, but not raised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeLimits {
+    /// The number of accounts a transaction may lock, including programs.
+    pub max_account_locks: usize,
+    /// Caps the loaded accounts data size, whether or not the transaction requests
+    /// a smaller limit with [ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit].
+    pub max_loaded_accounts_data_size: usize,
+    /// The largest heap frame that may be requested with [ComputeBudgetInstruction::RequestHeapFrame].
+    pub max_heap_frame_bytes: u32,
+}
+
+impl Default for RuntimeLimits {
+    /// The limits the simulator's bank already enforces.
+    fn default() -> Self {
+        Self {
+            max_account_locks: MAX_TX_ACCOUNT_LOCKS,
+            max_loaded_accounts_data_size: MAX_LOADED_ACCOUNTS_DATA_SIZE_BYTES,
+            max_heap_frame_bytes: MAX_HEAP_FRAME_BYTES,
+        }
+    }
+}
+
+impl RuntimeLimits {
+    pub fn mainnet() -> Self {
+        Self {
+            max_account_locks: MAINNET_MAX_TX_ACCOUNT_LOCKS,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the same errors the runtime would, if the transaction exceeds any limit.
+    /// Transactions with invalid compute budget instructions are left for the bank to reject.
+    pub fn check(&self, transaction: &SanitizedTransaction, bank: &Bank) -> TransactionResult<()> {
+        let message = transaction.message();
+        SanitizedTransaction::validate_account_locks(message, self.max_account_locks)?;
+
+        let mut compute_budget = ComputeBudget::new(MAX_COMPUTE_UNIT_LIMIT as u64);
+        if compute_budget
+            .process_instructions(
+                message.program_instructions_iter(),
+                !bank
+                    .feature_set
+                    .is_active(&feature_set::remove_deprecated_request_unit_ix::id()),
+                bank.feature_set.is_active(
+                    &feature_set::add_set_tx_loaded_accounts_data_size_instruction::id(),
+                ),
+            )
+            .is_err()
+        {
+            return Ok(());
+        }
+
+        if compute_budget.heap_size > self.max_heap_frame_bytes {
+            let index = message
+                .program_instructions_iter()
+                .position(|(program_id, ix)| {
+                    compute_budget::check_id(program_id)
+                        && matches!(
+                            try_from_slice_unchecked(&ix.data),
+                            Ok(ComputeBudgetInstruction::RequestHeapFrame(_))
+                        )
+                })
+                .unwrap_or_default();
+            return Err(TransactionError::InstructionError(
+                index as u8,
+                InstructionError::InvalidInstructionData,
+            ));
+        }
+
+        let limit = compute_budget
+            .loaded_accounts_data_size_limit
+            .min(self.max_loaded_accounts_data_size);
+        if loaded_accounts_data_size(transaction, bank) > limit {
+            return Err(TransactionError::MaxLoadedAccountsDataSizeExceeded);
+        }
+        Ok(())
+    }
+}
+
+/// Approximates the runtime's accounting: every account's data, where an upgradeable
+/// program counts its programdata too, plus the loader of every invoked program.
+fn loaded_accounts_data_size(transaction: &SanitizedTransaction, bank: &Bank) -> usize {
+    let message = transaction.message();
+    let mut size = 0usize;
+    for key in message.account_keys().iter() {
+        if sysvar::instructions::check_id(key) {
+            continue;
+        }
+        let Some(account) = bank.get_account(key) else {
+            continue;
+        };
+        size = size.saturating_add(account.data().len());
+        if account.executable() && bpf_loader_upgradeable::check_id(account.owner()) {
+            if let Ok(UpgradeableLoaderState::Program {
+                programdata_address,
+            }) = bincode::deserialize(account.data())
+            {
+                let programdata_len = bank
+                    .get_account(&programdata_address)
+                    .map(|a| a.data().len())
+                    .unwrap_or_default();
+                size = size.saturating_add(programdata_len);
+            }
+        }
+    }
+    let loaders: HashSet<Pubkey> = message
+        .program_instructions_iter()
+        .filter_map(|(program_id, _)| bank.get_account(program_id))
+        .map(|program| *program.owner())
+        .filter(|owner| !solana_sdk::native_loader::check_id(owner))
+        .collect();
+    for loader in loaders {
+        if let Some(account) = bank.get_account(&loader) {
+            size = size.saturating_add(account.data().len());
+        }
+    }
+    size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TransactionSimulator;
+    use solana_program::{
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+        system_instruction, system_program,
+    };
+    use solana_sdk::{account::Account, message::VersionedMessage};
+
+    #[test]
+    fn tightened_limits_are_enforced() {
+        let payer = Pubkey::new_unique();
+        let large = Pubkey::new_unique();
+        let simulator = TransactionSimulator::new_with_accounts([
+            (&payer, &Account::new(1_000_000_000, 0, &system_program::ID)),
+            (
+                &large,
+                &Account::new(1_000_000_000, 10_000, &system_program::ID),
+            ),
+        ]);
+        let process = |instructions: &[Instruction]| {
+            simulator.process_message(VersionedMessage::Legacy(Message::new(
+                instructions,
+                Some(&payer),
+            )))
+        };
+        let transfer = system_instruction::transfer(&payer, &large, 1);
+        let heap = ComputeBudgetInstruction::request_heap_frame(64 * 1024);
+        assert!(process(&[heap.clone(), transfer.clone()])
+            .unwrap()
+            .success());
+
+        simulator.set_runtime_limits(RuntimeLimits {
+            max_account_locks: 4,
+            max_loaded_accounts_data_size: 8_000,
+            max_heap_frame_bytes: 32 * 1024,
+        });
+        assert_eq!(
+            process(&[heap, transfer.clone()]).unwrap_err(),
+            TransactionError::InstructionError(0, InstructionError::InvalidInstructionData)
+        );
+        assert_eq!(
+            process(std::slice::from_ref(&transfer)).unwrap_err(),
+            TransactionError::MaxLoadedAccountsDataSizeExceeded
+        );
+        let extra_accounts = Instruction::new_with_bytes(
+            system_program::ID,
+            &[],
+            vec![
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+            ],
+        );
+        assert_eq!(
+            process(&[transfer, extra_accounts]).unwrap_err(),
+            TransactionError::TooManyAccountLocks
+        );
+    }
+}
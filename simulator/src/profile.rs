@@ -0,0 +1,103 @@
+//! Per-instruction compute unit consumption of a processed message,
+//! see [crate::TransactionSimulator::profile_message].
+use solana_program_runtime::timings::ProgramTiming;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+pub use solana_devtools_execution_result::profile::{
+    InstructionProfile, ProfileReport, ProgramUnits,
+};
+
+/// The average units of each program as a top-level instruction, as recorded in the
+/// bank's execution timings. Used for builtins, which do not log their consumption.
+pub(crate) fn builtin_units(
+    per_program_timings: &HashMap<Pubkey, ProgramTiming>,
+) -> HashMap<Pubkey, u64> {
+    per_program_timings
+        .iter()
+        .filter(|(_, timing)| timing.count > 0)
+        .map(|(program_id, timing)| (*program_id, timing.accumulated_units / timing.count as u64))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spl_artifacts::{SPL_ASSOCIATED_TOKEN_ACCOUNT, SPL_TOKEN};
+    use crate::TransactionSimulator;
+    use solana_sdk::{
+        account::Account,
+        instruction::{AccountMeta, Instruction},
+        message::{Message, VersionedMessage},
+        pubkey::Pubkey,
+        rent::Rent,
+        system_instruction, system_program,
+    };
+
+    #[test]
+    fn units_are_profiled_per_instruction_and_program() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_program = SPL_TOKEN.program_id;
+        let ata_program = SPL_ASSOCIATED_TOKEN_ACCOUNT.program_id;
+        let (ata, _) = Pubkey::find_program_address(
+            &[payer.as_ref(), token_program.as_ref(), mint.as_ref()],
+            &ata_program,
+        );
+        let payer_account = Account::new(1_000_000_000, 0, &system_program::ID);
+        let mut mint_data = vec![0; 82];
+        mint_data[45] = 1;
+        let mint_account = Account {
+            lamports: Rent::default().minimum_balance(mint_data.len()),
+            data: mint_data,
+            owner: token_program,
+            executable: false,
+            rent_epoch: 0,
+        };
+        let simulator = TransactionSimulator::new_with_accounts([
+            (&payer, &payer_account),
+            (&mint, &mint_account),
+        ]);
+        let create = Instruction::new_with_bytes(
+            ata_program,
+            &[],
+            vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new(ata, false),
+                AccountMeta::new_readonly(payer, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(token_program, false),
+            ],
+        );
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1_000_000);
+        let message = VersionedMessage::Legacy(Message::new(&[create, transfer], Some(&payer)));
+
+        let report = simulator.profile_message(message.clone()).unwrap();
+        assert_eq!(report, simulator.profile_message(message).unwrap());
+        assert_eq!(report.instructions.len(), 2);
+        assert_eq!(
+            report.instructions.iter().map(|ix| ix.units).sum::<u64>(),
+            report.total_units
+        );
+        for instruction in &report.instructions {
+            assert_eq!(
+                instruction.programs.values().map(|p| p.units).sum::<u64>(),
+                instruction.units
+            );
+        }
+
+        let create = &report.instructions[0];
+        assert_eq!(create.program_id, ata_program);
+        assert!(create.programs[&token_program].invocations > 1);
+        let transfer = &report.instructions[1];
+        assert_eq!(transfer.program_id, system_program::ID);
+        assert!(transfer.units > 0);
+        assert_eq!(
+            report.by_program()[&system_program::ID].invocations,
+            create.programs[&system_program::ID].invocations + 1
+        );
+        assert!(report
+            .to_string()
+            .contains(&format!("#1 {}", system_program::ID)));
+    }
+}
@@ -4,8 +4,8 @@ use solana_accounts_db::accounts_index::ZeroLamport;
 use solana_sdk::account::ReadableAccount;
 use syscall_stubs::*;
 use {
+    crate::spl_artifacts,
     log::*,
-    solana_program_test::programs,
     solana_runtime::{
         bank::Bank, bank_forks::BankForks, genesis_utils::create_genesis_config_with_leader_ex,
         runtime_config::RuntimeConfig,
@@ -21,30 +21,35 @@ use {
     },
     solana_vote_program::vote_state::VoteState,
     std::{
+        collections::HashSet,
         sync::{Arc, RwLock},
         time::Duration,
     },
 };
 
-/// Copied from private method [ProgramTest::setup_bank],
-/// but only returns a [BankForks] and is less configurable. These limitations
-/// are due to the fact that we cannot directly use many private fields on a [ProgramTest].
-/// Specifically, no feature deactivation, no runtime config, and no user built-ins.
-/// User provided programs must be BPF programs added directly as account data.
+/// Install the simulator's syscall stubs, once per process.
+pub fn install_syscall_stubs() {
+    use std::sync::Once;
+    static ONCE: Once = Once::new();
+
+    ONCE.call_once(|| {
+        solana_sdk::program_stubs::set_syscall_stubs(Box::new(SyscallStubs {}));
+    });
+}
+
+/// Copied from private method [ProgramTest::setup_bank], but only returns a [BankForks].
+/// Features in `deactivated_features` are removed from the genesis config before the bank
+/// is created. There are no user built-ins, so user provided programs must be BPF programs
+/// added directly as account data.
 pub fn setup_bank<'a, T>(
     accounts: impl IntoIterator<Item = (&'a Pubkey, &'a T)>,
+    deactivated_features: &HashSet<Pubkey>,
+    runtime_config: RuntimeConfig,
 ) -> Arc<RwLock<BankForks>>
 where
     T: ReadableAccount + Sync + ZeroLamport + 'a,
 {
-    {
-        use std::sync::Once;
-        static ONCE: Once = Once::new();
-
-        ONCE.call_once(|| {
-            solana_sdk::program_stubs::set_syscall_stubs(Box::new(SyscallStubs {}));
-        });
-    }
+    install_syscall_stubs();
 
     let rent = Rent::default();
     let fee_rate_governor = FeeRateGovernor {
@@ -75,17 +80,24 @@ where
 
     let target_tick_duration = Duration::from_micros(100);
     genesis_config.poh_config = PohConfig::new_sleep(target_tick_duration);
+    for feature in deactivated_features {
+        if genesis_config.accounts.remove(feature).is_none() {
+            warn!(
+                "Feature {} set for deactivation is not active in genesis, ignored",
+                feature
+            );
+        }
+    }
     debug!("Payer address: {}", mint_keypair.pubkey());
     debug!("Genesis config: {}", genesis_config);
 
-    let bank = Bank::new_with_runtime_config_for_tests(
-        &genesis_config,
-        Arc::new(RuntimeConfig::default()),
-    );
+    let bank = Bank::new_with_runtime_config_for_tests(&genesis_config, Arc::new(runtime_config));
 
     // Add commonly-used SPL programs as a convenience to the user
-    for (program_id, account) in programs::spl_programs(&Rent::default()).iter() {
-        bank.store_account(program_id, account);
+    for artifact in spl_artifacts::ALL {
+        for (program_id, account) in artifact.accounts(&Rent::default()) {
+            bank.store_account(&program_id, &account);
+        }
     }
 
     for (pubkey, account) in accounts {
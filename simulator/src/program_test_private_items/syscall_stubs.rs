@@ -1,6 +1,10 @@
-//! Directly copied from private items in [solana_program_test].
+//! Directly copied from private items in [solana_program_test],
+//! except that [crate::syscalls::SyscallOverrides] are answered first.
 use {
-    solana_program_runtime::{ic_msg, invoke_context::InvokeContext, stable_log, timings::ExecuteTimings},
+    crate::syscalls::syscall_overrides,
+    solana_program_runtime::{
+        ic_msg, invoke_context::InvokeContext, stable_log, timings::ExecuteTimings,
+    },
     solana_sdk::{
         account_info::AccountInfo,
         entrypoint::{ProgramResult, SUCCESS},
@@ -36,6 +40,13 @@ fn get_sysvar<T: Default + Sysvar + Sized + serde::de::DeserializeOwned + Clone>
         panic!("Exceeded compute budget");
     }
 
+    set_sysvar(sysvar, var_addr)
+}
+
+fn set_sysvar<T: Sysvar + Clone>(
+    sysvar: Result<Arc<T>, InstructionError>,
+    var_addr: *mut u8,
+) -> u64 {
     match sysvar {
         Ok(sysvar_data) => unsafe {
             *(var_addr as *mut _ as *mut T) = T::clone(&sysvar_data);
@@ -48,6 +59,9 @@ fn get_sysvar<T: Default + Sysvar + Sized + serde::de::DeserializeOwned + Clone>
 pub struct SyscallStubs {}
 impl solana_sdk::program_stubs::SyscallStubs for SyscallStubs {
     fn sol_log(&self, message: &str) {
+        if let Some(log_sink) = syscall_overrides().log_sink {
+            return log_sink(message);
+        }
         let invoke_context = get_invoke_context();
         ic_msg!(invoke_context, "Program log: {}", message);
     }
@@ -185,6 +199,9 @@ impl solana_sdk::program_stubs::SyscallStubs for SyscallStubs {
     }
 
     fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        if let Some(clock) = syscall_overrides().clock {
+            return set_sysvar(Ok(Arc::new(clock)), var_addr);
+        }
         get_sysvar(
             get_invoke_context().get_sysvar_cache().get_clock(),
             var_addr,
@@ -192,6 +209,9 @@ impl solana_sdk::program_stubs::SyscallStubs for SyscallStubs {
     }
 
     fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        if let Some(epoch_schedule) = syscall_overrides().epoch_schedule {
+            return set_sysvar(Ok(Arc::new(epoch_schedule)), var_addr);
+        }
         get_sysvar(
             get_invoke_context().get_sysvar_cache().get_epoch_schedule(),
             var_addr,
@@ -204,6 +224,9 @@ impl solana_sdk::program_stubs::SyscallStubs for SyscallStubs {
     }
 
     fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        if let Some(rent) = syscall_overrides().rent {
+            return set_sysvar(Ok(Arc::new(rent)), var_addr);
+        }
         get_sysvar(get_invoke_context().get_sysvar_cache().get_rent(), var_addr)
     }
 
@@ -0,0 +1,602 @@
+//! An [RpcSender] that answers requests from an in-process [TransactionSimulator],
+//! so code written against [solana_rpc_client::nonblocking::rpc_client::RpcClient]
+//! runs in unit tests without a network or a validator.
+//!
+//! Sent transactions are simulated for preflight, then committed immediately, so they are
+//! finalized by the time `sendTransaction` returns, and their fee is charged to the fee payer.
+//! A failed transaction sent with `skipPreflight` only charges its fee. Resending a transaction
+//! with the same signature does not execute it again. Transactions must be signed and use a
+//! recent blockhash of the working bank, but signatures are not verified.
+
+// Every handler returns the `RpcSender` error type.
+#![allow(clippy::result_large_err)]
+use crate::{try_sanitize_unsigned_transaction, TransactionSimulator};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_rpc_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_rpc_client_api::client_error::{Error as ClientError, Result as ClientResult};
+use solana_rpc_client_api::config::{
+    RpcAccountInfoConfig, RpcEncodingConfigWrapper, RpcSendTransactionConfig,
+    RpcSimulateTransactionConfig, RpcTransactionConfig,
+};
+use solana_rpc_client_api::custom_error::{
+    JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+    JSON_RPC_SERVER_ERROR_TRANSACTION_SIGNATURE_VERIFICATION_FAILURE,
+};
+use solana_rpc_client_api::request::{RpcError, RpcRequest, RpcResponseErrorData};
+use solana_rpc_client_api::response::RpcSimulateTransactionResult;
+use solana_sdk::account::{AccountSharedData, ReadableAccount, WritableAccount};
+use solana_sdk::bs58;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{TransactionError, VersionedTransaction};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionConfirmationStatus, TransactionStatus,
+    TransactionStatusMeta, UiTransactionEncoding, VersionedTransactionWithStatusMeta,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+const JSON_RPC_INVALID_PARAMS: i64 = -32602;
+const JSON_RPC_METHOD_NOT_FOUND: i64 = -32601;
+
+/// A transaction received by `sendTransaction`, kept to answer `getSignatureStatuses`
+/// and `getTransaction`.
+#[derive(Debug, Clone)]
+struct SentTransaction {
+    slot: Slot,
+    transaction: VersionedTransaction,
+    meta: TransactionStatusMeta,
+}
+
+/// Answers `getAccountInfo`, `getMultipleAccounts`, `getBalance`, `getLatestBlockhash`,
+/// `isBlockhashValid`, `getSlot`, `getBlockHeight`, `getMinimumBalanceForRentExemption`,
+/// `getVersion`, `sendTransaction`, `simulateTransaction`, `getSignatureStatuses`
+/// and `getTransaction`. Other methods fail with a "Method not found" error.
+pub struct SimulatedRpcSender {
+    simulator: Arc<TransactionSimulator>,
+    transactions: RwLock<HashMap<Signature, SentTransaction>>,
+    stats: RwLock<RpcTransportStats>,
+}
+
+impl SimulatedRpcSender {
+    pub fn new(simulator: Arc<TransactionSimulator>) -> Self {
+        Self {
+            simulator,
+            transactions: RwLock::new(HashMap::new()),
+            stats: RwLock::new(RpcTransportStats::default()),
+        }
+    }
+
+    /// The simulator backing this sender, e.g. to set up accounts or warp slots between requests.
+    pub fn simulator(&self) -> &Arc<TransactionSimulator> {
+        &self.simulator
+    }
+
+    fn slot(&self) -> Slot {
+        self.simulator.working_bank().slot()
+    }
+
+    fn with_context(&self, value: impl serde::Serialize) -> ClientResult<Value> {
+        Ok(json!({ "context": { "slot": self.slot() }, "value": value }))
+    }
+
+    fn get_account(&self, pubkey: &Pubkey, config: &RpcAccountInfoConfig) -> Option<UiAccount> {
+        let account = self.simulator.get_account(pubkey)?;
+        Some(UiAccount::encode(
+            pubkey,
+            &account,
+            config.encoding.unwrap_or(UiAccountEncoding::Binary),
+            None,
+            config.data_slice,
+        ))
+    }
+
+    fn send_transaction(&self, params: &[Value]) -> ClientResult<Value> {
+        let config: RpcSendTransactionConfig = optional_param(params, 1)?;
+        let transaction = decode_transaction(params, config.encoding)?;
+        // Unsigned transactions would all share the default signature.
+        if transaction.signatures.is_empty()
+            || transaction.signatures.contains(&Signature::default())
+        {
+            return Err(RpcError::RpcResponseError {
+                code: JSON_RPC_SERVER_ERROR_TRANSACTION_SIGNATURE_VERIFICATION_FAILURE,
+                message: "Transaction did not pass signature verification".to_string(),
+                data: RpcResponseErrorData::Empty,
+            }
+            .into());
+        }
+        let signature = transaction.signatures[0];
+        // A resent transaction is not executed again, as on a validator.
+        if self.transactions.read().unwrap().contains_key(&signature) {
+            return Ok(json!(signature.to_string()));
+        }
+        if !self
+            .simulator
+            .working_bank()
+            .is_blockhash_valid(transaction.message.recent_blockhash())
+        {
+            return Err(preflight_failure(
+                TransactionError::BlockhashNotFound,
+                vec![],
+                None,
+            ));
+        }
+        let fee = self.fee(&transaction)?;
+        let processed = self
+            .simulator
+            .process_message(transaction.message.clone())?;
+        if let (false, Some(err)) = (config.skip_preflight, &processed.execution_error) {
+            return Err(preflight_failure(
+                err.clone(),
+                processed.logs.clone(),
+                Some(processed.compute_units),
+            ));
+        }
+        let post_accounts = match processed.execution_error {
+            // Loading the transaction already charged the fee to the fee payer.
+            None => {
+                self.simulator.store_processed_accounts(&processed);
+                processed.accounts.clone()
+            }
+            // A failed transaction only charges its fee.
+            Some(_) => {
+                let mut post_accounts = processed.pre_accounts.clone();
+                let fee_payer = transaction.message.static_account_keys()[0];
+                if let Some(payer) = post_accounts.get_mut(&fee_payer) {
+                    payer.set_lamports(payer.lamports().saturating_sub(fee));
+                    self.simulator.update_account(&fee_payer, payer);
+                }
+                post_accounts
+            }
+        };
+        let balances = |accounts: &HashMap<Pubkey, AccountSharedData>| -> Vec<u64> {
+            transaction
+                .message
+                .static_account_keys()
+                .iter()
+                .map(|pubkey| accounts.get(pubkey).map_or(0, |a| a.lamports()))
+                .collect()
+        };
+        let meta = TransactionStatusMeta {
+            status: processed.execution_error.clone().map_or(Ok(()), Err),
+            fee,
+            pre_balances: balances(&processed.pre_accounts),
+            post_balances: balances(&post_accounts),
+            log_messages: Some(processed.logs.clone()),
+            compute_units_consumed: Some(processed.compute_units),
+            ..TransactionStatusMeta::default()
+        };
+        self.transactions.write().unwrap().insert(
+            signature,
+            SentTransaction {
+                slot: processed.slot,
+                transaction,
+                meta,
+            },
+        );
+        Ok(json!(signature.to_string()))
+    }
+
+    /// The fee charged for the transaction at the working bank's fee rate.
+    fn fee(&self, transaction: &VersionedTransaction) -> ClientResult<u64> {
+        let bank = self.simulator.working_bank();
+        let sanitized = try_sanitize_unsigned_transaction(transaction.clone(), &bank)?;
+        Ok(bank.get_fee_for_message_with_lamports_per_signature(
+            sanitized.message(),
+            bank.get_lamports_per_signature(),
+        ))
+    }
+
+    fn simulate_transaction(&self, params: &[Value]) -> ClientResult<Value> {
+        let config: RpcSimulateTransactionConfig = optional_param(params, 1)?;
+        let transaction = decode_transaction(params, config.encoding)?;
+        let processed = self.simulator.process_message(transaction.message)?;
+        let accounts = match config.accounts {
+            Some(accounts_config) => {
+                let encoding = accounts_config
+                    .encoding
+                    .unwrap_or(UiAccountEncoding::Base64);
+                let accounts = accounts_config
+                    .addresses
+                    .iter()
+                    .map(|address| {
+                        let pubkey = Pubkey::from_str(address).map_err(invalid_params)?;
+                        Ok(processed.accounts.get(&pubkey).map(|account| {
+                            UiAccount::encode(&pubkey, account, encoding, None, None)
+                        }))
+                    })
+                    .collect::<ClientResult<_>>()?;
+                Some(accounts)
+            }
+            None => None,
+        };
+        self.with_context(RpcSimulateTransactionResult {
+            err: processed.execution_error,
+            logs: Some(processed.logs),
+            accounts,
+            units_consumed: Some(processed.compute_units),
+            return_data: None,
+        })
+    }
+
+    fn signature_status(&self, signature: &Signature) -> Option<TransactionStatus> {
+        let transactions = self.transactions.read().unwrap();
+        let sent = transactions.get(signature)?;
+        Some(TransactionStatus {
+            slot: sent.slot,
+            confirmations: None,
+            status: sent.meta.status.clone(),
+            err: sent.meta.status.clone().err(),
+            confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+        })
+    }
+
+    fn get_transaction(&self, params: &[Value]) -> ClientResult<Value> {
+        let signature: Signature = required_param::<String>(params, 0)?
+            .parse()
+            .map_err(invalid_params)?;
+        // Older clients send just the encoding, as `getConfirmedTransaction` did.
+        let config: RpcTransactionConfig =
+            optional_param::<Option<RpcEncodingConfigWrapper<_>>>(params, 1)?
+                .map(|config| config.convert_to_current())
+                .unwrap_or_default();
+        let Some(sent) = self.transactions.read().unwrap().get(&signature).cloned() else {
+            return Ok(Value::Null);
+        };
+        let transaction = VersionedTransactionWithStatusMeta {
+            transaction: sent.transaction,
+            meta: sent.meta,
+        }
+        .encode(
+            config.encoding.unwrap_or(UiTransactionEncoding::Json),
+            config.max_supported_transaction_version,
+            true,
+        )
+        .map_err(invalid_params)?;
+        Ok(json!(EncodedConfirmedTransactionWithStatusMeta {
+            slot: sent.slot,
+            transaction,
+            block_time: Some(self.simulator.get_clock().unix_timestamp),
+        }))
+    }
+}
+
+impl From<TransactionSimulator> for SimulatedRpcSender {
+    fn from(simulator: TransactionSimulator) -> Self {
+        Self::new(Arc::new(simulator))
+    }
+}
+
+#[async_trait]
+impl RpcSender for SimulatedRpcSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        self.stats.write().unwrap().request_count += 1;
+        let params = match params {
+            Value::Array(params) => params,
+            Value::Null => vec![],
+            param => vec![param],
+        };
+        let bank = self.simulator.working_bank();
+        match request {
+            RpcRequest::GetAccountInfo => {
+                let pubkey = required_pubkey(&params, 0)?;
+                let config = optional_param(&params, 1)?;
+                self.with_context(self.get_account(&pubkey, &config))
+            }
+            RpcRequest::GetMultipleAccounts => {
+                let pubkeys: Vec<String> = required_param(&params, 0)?;
+                let config = optional_param(&params, 1)?;
+                let accounts = pubkeys
+                    .iter()
+                    .map(|pubkey| {
+                        let pubkey = Pubkey::from_str(pubkey).map_err(invalid_params)?;
+                        Ok(self.get_account(&pubkey, &config))
+                    })
+                    .collect::<ClientResult<Vec<_>>>()?;
+                self.with_context(accounts)
+            }
+            RpcRequest::GetBalance => {
+                let pubkey = required_pubkey(&params, 0)?;
+                self.with_context(bank.get_balance(&pubkey))
+            }
+            RpcRequest::GetLatestBlockhash => {
+                let blockhash = bank.last_blockhash();
+                let last_valid_block_height = bank
+                    .get_blockhash_last_valid_block_height(&blockhash)
+                    .unwrap_or_default();
+                self.with_context(json!({
+                    "blockhash": blockhash.to_string(),
+                    "lastValidBlockHeight": last_valid_block_height,
+                }))
+            }
+            RpcRequest::IsBlockhashValid => {
+                let blockhash = required_param::<String>(&params, 0)?
+                    .parse()
+                    .map_err(invalid_params)?;
+                self.with_context(bank.is_blockhash_valid(&blockhash))
+            }
+            RpcRequest::GetSlot => Ok(json!(bank.slot())),
+            RpcRequest::GetBlockHeight => Ok(json!(bank.block_height())),
+            RpcRequest::GetMinimumBalanceForRentExemption => {
+                let data_len: usize = required_param(&params, 0)?;
+                Ok(json!(bank.get_minimum_balance_for_rent_exemption(data_len)))
+            }
+            RpcRequest::GetVersion => {
+                let version = solana_version::Version::default();
+                Ok(json!({
+                    "solana-core": format!("{}.{}.{}", version.major, version.minor, version.patch),
+                    "feature-set": version.feature_set,
+                }))
+            }
+            RpcRequest::SendTransaction => self.send_transaction(&params),
+            RpcRequest::SimulateTransaction => self.simulate_transaction(&params),
+            RpcRequest::GetSignatureStatuses => {
+                let signatures: Vec<String> = required_param(&params, 0)?;
+                let statuses = signatures
+                    .iter()
+                    .map(|signature| {
+                        let signature = signature.parse().map_err(invalid_params)?;
+                        Ok(self.signature_status(&signature))
+                    })
+                    .collect::<ClientResult<Vec<_>>>()?;
+                self.with_context(statuses)
+            }
+            RpcRequest::GetTransaction => self.get_transaction(&params),
+            request => Err(RpcError::RpcResponseError {
+                code: JSON_RPC_METHOD_NOT_FOUND,
+                message: format!("Method not found: {} is not simulated", request),
+                data: RpcResponseErrorData::Empty,
+            }
+            .into()),
+        }
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.stats.read().unwrap().clone()
+    }
+
+    fn url(&self) -> String {
+        "simulated".to_string()
+    }
+}
+
+/// A `sendTransaction` preflight failure, as reported by a validator.
+fn preflight_failure(
+    err: TransactionError,
+    logs: Vec<String>,
+    units_consumed: Option<u64>,
+) -> ClientError {
+    RpcError::RpcResponseError {
+        code: JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+        message: format!("Transaction simulation failed: {}", err),
+        data: RpcResponseErrorData::SendTransactionPreflightFailure(RpcSimulateTransactionResult {
+            err: Some(err),
+            logs: Some(logs),
+            accounts: None,
+            units_consumed,
+            return_data: None,
+        }),
+    }
+    .into()
+}
+
+fn invalid_params(e: impl ToString) -> RpcError {
+    RpcError::RpcResponseError {
+        code: JSON_RPC_INVALID_PARAMS,
+        message: format!("Invalid params: {}", e.to_string()),
+        data: RpcResponseErrorData::Empty,
+    }
+}
+
+fn required_param<T: DeserializeOwned>(params: &[Value], index: usize) -> ClientResult<T> {
+    let param = params
+        .get(index)
+        .ok_or_else(|| invalid_params(format!("missing parameter {}", index)))?;
+    Ok(serde_json::from_value(param.clone()).map_err(invalid_params)?)
+}
+
+/// A missing or `null` parameter is read as the default, e.g. of a config.
+fn optional_param<T: DeserializeOwned + Default>(
+    params: &[Value],
+    index: usize,
+) -> ClientResult<T> {
+    match params.get(index) {
+        None | Some(Value::Null) => Ok(T::default()),
+        Some(param) => Ok(serde_json::from_value(param.clone()).map_err(invalid_params)?),
+    }
+}
+
+fn required_pubkey(params: &[Value], index: usize) -> ClientResult<Pubkey> {
+    Ok(required_param::<String>(params, index)?
+        .parse()
+        .map_err(invalid_params)?)
+}
+
+/// The serialized transaction in the first parameter, in base58 unless `encoding` says otherwise.
+fn decode_transaction(
+    params: &[Value],
+    encoding: Option<UiTransactionEncoding>,
+) -> ClientResult<VersionedTransaction> {
+    let encoded: String = required_param(params, 0)?;
+    let bytes = match encoding.unwrap_or(UiTransactionEncoding::Base58) {
+        UiTransactionEncoding::Base58 => {
+            bs58::decode(encoded).into_vec().map_err(invalid_params)?
+        }
+        UiTransactionEncoding::Base64 => STANDARD.decode(encoded).map_err(invalid_params)?,
+        encoding => {
+            return Err(invalid_params(format!("unsupported encoding: {}", encoding)).into())
+        }
+    };
+    Ok(bincode::deserialize(&bytes).map_err(invalid_params)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+    use solana_sdk::account::Account;
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use solana_sdk::signature::{Keypair, Signer};
+    use solana_sdk::system_instruction;
+    use solana_sdk::transaction::Transaction;
+
+    const LAMPORTS: u64 = 10_000_000;
+
+    #[tokio::test]
+    async fn rpc_client_runs_against_the_simulator() {
+        let payer = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let funded = Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        };
+        let simulator = Arc::new(TransactionSimulator::new_with_accounts([(
+            &payer.pubkey(),
+            &funded,
+        )]));
+        let client = RpcClient::new_sender(
+            SimulatedRpcSender::new(simulator.clone()),
+            solana_rpc_client::rpc_client::RpcClientConfig::with_commitment(
+                CommitmentConfig::confirmed(),
+            ),
+        );
+
+        let blockhash = client.get_latest_blockhash().await.unwrap();
+        let transfer = |lamports| {
+            Transaction::new_signed_with_payer(
+                &[system_instruction::transfer(
+                    &payer.pubkey(),
+                    &recipient,
+                    lamports,
+                )],
+                Some(&payer.pubkey()),
+                &[&payer],
+                blockhash,
+            )
+        };
+        let simulated = client
+            .simulate_transaction(&transfer(LAMPORTS))
+            .await
+            .unwrap();
+        assert!(simulated.value.err.is_none());
+        assert_eq!(client.get_balance(&recipient).await.unwrap(), 0);
+
+        let signature = client
+            .send_and_confirm_transaction(&transfer(LAMPORTS))
+            .await
+            .unwrap();
+        assert_eq!(client.get_balance(&recipient).await.unwrap(), LAMPORTS);
+        let transaction = client
+            .get_transaction(&signature, UiTransactionEncoding::Base64)
+            .await
+            .unwrap();
+        let meta = transaction.transaction.meta.unwrap();
+        // Successful transactions pay their fee too.
+        let fee = simulator.working_bank().get_lamports_per_signature();
+        assert!(fee > 0);
+        assert_eq!(meta.fee, fee);
+        assert_eq!(meta.post_balances[1], LAMPORTS);
+        assert_eq!(
+            client.get_balance(&payer.pubkey()).await.unwrap(),
+            1_000_000_000 - LAMPORTS - fee
+        );
+        assert_eq!(
+            meta.post_balances[0],
+            client.get_balance(&payer.pubkey()).await.unwrap()
+        );
+
+        // Unsigned transactions, and transactions with an unknown blockhash, are rejected.
+        let unsigned = Transaction::new_with_payer(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &recipient,
+                LAMPORTS,
+            )],
+            Some(&payer.pubkey()),
+        );
+        let err = client.send_transaction(&unsigned).await.unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            solana_rpc_client_api::client_error::ErrorKind::RpcError(RpcError::RpcResponseError {
+                code: JSON_RPC_SERVER_ERROR_TRANSACTION_SIGNATURE_VERIFICATION_FAILURE,
+                ..
+            })
+        ));
+        let stale = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &recipient,
+                LAMPORTS,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            solana_sdk::hash::Hash::new_unique(),
+        );
+        let err = client.send_transaction(&stale).await.unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            solana_rpc_client_api::client_error::ErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(
+                    RpcSimulateTransactionResult {
+                        err: Some(TransactionError::BlockhashNotFound),
+                        ..
+                    }
+                ),
+                ..
+            })
+        ));
+        assert_eq!(client.get_balance(&recipient).await.unwrap(), LAMPORTS);
+
+        // Preflight failures are reported like a validator's.
+        let err = client
+            .send_transaction(&transfer(10_000_000_000))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            solana_rpc_client_api::client_error::ErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(_),
+                ..
+            })
+        ));
+        assert_eq!(client.get_balance(&recipient).await.unwrap(), LAMPORTS);
+
+        // A resent transaction is not executed twice.
+        let resent = client.send_transaction(&transfer(LAMPORTS)).await.unwrap();
+        assert_eq!(resent, signature);
+        assert_eq!(client.get_balance(&recipient).await.unwrap(), LAMPORTS);
+
+        // Without preflight, a failed transaction is recorded and charges the fee payer.
+        let payer_balance = client.get_balance(&payer.pubkey()).await.unwrap();
+        let failed = transfer(10_000_000_000);
+        let config = RpcSendTransactionConfig {
+            skip_preflight: true,
+            ..RpcSendTransactionConfig::default()
+        };
+        let signature = client
+            .send_transaction_with_config(&failed, config)
+            .await
+            .unwrap();
+        assert_eq!(
+            client.get_balance(&payer.pubkey()).await.unwrap(),
+            payer_balance - fee
+        );
+        assert_eq!(client.get_balance(&recipient).await.unwrap(), LAMPORTS);
+        let meta = client
+            .get_transaction(&signature, UiTransactionEncoding::Base64)
+            .await
+            .unwrap()
+            .transaction
+            .meta
+            .unwrap();
+        assert!(meta.err.is_some());
+        assert_eq!(meta.pre_balances[0], payer_balance);
+        assert_eq!(meta.post_balances[0], payer_balance - fee);
+    }
+}
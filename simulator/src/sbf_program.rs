@@ -0,0 +1,399 @@
+//! Locate a program's `target/deploy/<crate_name>.so` built by `cargo build-sbf`, with its
+//! declared program id, and deploy it into a [ProgramTest] or a [TransactionSimulator].
+//! [SbfProgram::find_all] finds every program of a workspace at once.
+//! A [SbfProgramWatcher] redeploys the program whenever it is rebuilt.
+use crate::error::{Result, SimulatorError};
+use crate::{
+    upgradeable_program_data, upgradeable_program_metadata, upgradeable_programdata_address,
+    TransactionSimulator,
+};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    bpf_loader_upgradeable::UpgradeableLoaderState, pubkey::Pubkey, rent::Rent, signature::Keypair,
+    signer::Signer,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// A program binary produced by `cargo build-sbf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbfProgram {
+    /// The library name, i.e. the crate name with dashes replaced by underscores.
+    pub name: String,
+    pub program_id: Pubkey,
+    pub so_path: PathBuf,
+}
+
+impl SbfProgram {
+    /// Find a crate's program binary in `$CARGO_TARGET_DIR/deploy`, or else in the
+    /// `target/deploy` directory of the nearest workspace above `$CARGO_MANIFEST_DIR`,
+    /// or the working directory.
+    pub fn find(crate_name: &str) -> Result<Self> {
+        let name = crate_name.replace("-", "_");
+        let start = std::env::var("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .or_else(|_| std::env::current_dir())
+            .unwrap_or_default();
+        let root = start
+            .ancestors()
+            .find(|dir| deploy_dir(dir).join(format!("{}.so", name)).exists())
+            .ok_or(SimulatorError::SbfProgramNotFound(name))?;
+        Self::find_in(root, crate_name)
+    }
+
+    /// Find a crate's program binary in the `target/deploy` directory of a workspace.
+    /// The program id is parsed from the `declare_id!` of the crate, if its source is found
+    /// at `<root>`, `<root>/<crate_name>` or `<root>/programs/<crate_name>`.
+    /// Otherwise it is read from `target/deploy/<name>-keypair.json`.
+    pub fn find_in(workspace_root: impl AsRef<Path>, crate_name: &str) -> Result<Self> {
+        let root = workspace_root.as_ref();
+        Self::find_in_deploy_dir(root, &deploy_dir(root), crate_name)
+    }
+
+    fn find_in_deploy_dir(root: &Path, deploy_dir: &Path, crate_name: &str) -> Result<Self> {
+        let name = crate_name.replace("-", "_");
+        Self::load(root, deploy_dir, name, &[crate_name])
+    }
+
+    /// Find every program binary in `$CARGO_TARGET_DIR/deploy`, or else in the
+    /// `target/deploy` directory of the nearest workspace above `$CARGO_MANIFEST_DIR`,
+    /// or the working directory, that has one.
+    pub fn find_all() -> Result<Vec<Self>> {
+        let start = std::env::var("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .or_else(|_| std::env::current_dir())
+            .unwrap_or_default();
+        let root = start
+            .ancestors()
+            .find(|dir| deploy_dir(dir).is_dir())
+            .ok_or(SimulatorError::SbfProgramNotFound(
+                deploy_dir(&start).display().to_string(),
+            ))?;
+        Self::find_all_in(root)
+    }
+
+    /// Find every program binary in the `target/deploy` directory of a workspace, sorted by name.
+    /// The program ids are found as in [SbfProgram::find_in], where the crate name is the
+    /// library name with underscores replaced by dashes, or the library name itself.
+    /// Binaries without a declared id or keypair file are skipped with a warning.
+    pub fn find_all_in(workspace_root: impl AsRef<Path>) -> Result<Vec<Self>> {
+        let root = workspace_root.as_ref();
+        Self::find_all_in_deploy_dir(root, &deploy_dir(root))
+    }
+
+    fn find_all_in_deploy_dir(root: &Path, deploy_dir: &Path) -> Result<Vec<Self>> {
+        let entries = fs::read_dir(deploy_dir).map_err(|e| read_error(deploy_dir, e))?;
+        let mut names = vec![];
+        for entry in entries {
+            let path = entry.map_err(|e| read_error(deploy_dir, e))?.path();
+            if path.extension().is_some_and(|extension| extension == "so") {
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        let mut programs = vec![];
+        for name in names {
+            let crate_name = name.replace("_", "-");
+            match Self::load(root, deploy_dir, name.clone(), &[&crate_name, &name]) {
+                Ok(program) => programs.push(program),
+                // e.g. a third-party program dumped into `target/deploy`, without a keypair.
+                Err(SimulatorError::ProgramIdNotFound(_)) => {
+                    log::warn!("Skipping {}.so, its program id was not found", name);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(programs)
+    }
+
+    /// The program id is declared by the first of `crate_names` whose source is found,
+    /// or else read from the keypair file.
+    fn load(root: &Path, deploy_dir: &Path, name: String, crate_names: &[&str]) -> Result<Self> {
+        let so_path = deploy_dir.join(format!("{}.so", name));
+        if !so_path.exists() {
+            return Err(SimulatorError::SbfProgramNotFound(
+                so_path.display().to_string(),
+            ));
+        }
+        let mut program_id = None;
+        for crate_name in crate_names {
+            program_id = declared_id(root, crate_name)?;
+            if program_id.is_some() {
+                break;
+            }
+        }
+        let program_id = match program_id {
+            Some(program_id) => program_id,
+            None => keypair_file_id(&deploy_dir.join(format!("{}-keypair.json", name)))?.ok_or(
+                SimulatorError::ProgramIdNotFound(crate_names[0].to_string()),
+            )?,
+        };
+        Ok(Self {
+            name,
+            program_id,
+            so_path,
+        })
+    }
+
+    pub fn elf(&self) -> Result<Vec<u8>> {
+        fs::read(&self.so_path).map_err(|e| read_error(&self.so_path, e))
+    }
+
+    pub fn modified(&self) -> Result<SystemTime> {
+        fs::metadata(&self.so_path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| read_error(&self.so_path, e))
+    }
+
+    /// Add the program as a BPF upgradeable program.
+    pub fn add_to_program_test(&self, program_test: &mut ProgramTest) -> Result<()> {
+        let elf = self.elf()?;
+        let programdata_address = upgradeable_programdata_address(&self.program_id);
+        let lamports =
+            Rent::default().minimum_balance(UpgradeableLoaderState::size_of_programdata(elf.len()));
+        program_test.add_account(
+            self.program_id,
+            upgradeable_program_metadata(programdata_address),
+        );
+        program_test.add_account(
+            programdata_address,
+            upgradeable_program_data(&elf, lamports),
+        );
+        Ok(())
+    }
+
+    /// Deploy the program as a BPF upgradeable program, replacing any previous deployment.
+    pub fn add_to_simulator(&self, simulator: &TransactionSimulator) -> Result<()> {
+        simulator.add_bpf_upgradeable(self.program_id, &self.elf()?);
+        Ok(())
+    }
+}
+
+/// Redeploys a [SbfProgram] into a [TransactionSimulator] whenever its binary changes.
+#[derive(Debug, Clone)]
+pub struct SbfProgramWatcher {
+    program: SbfProgram,
+    last_modified: Option<SystemTime>,
+}
+
+impl SbfProgramWatcher {
+    /// The first [SbfProgramWatcher::redeploy_if_changed] always deploys the program.
+    pub fn new(program: SbfProgram) -> Self {
+        Self {
+            program,
+            last_modified: None,
+        }
+    }
+
+    pub fn program(&self) -> &SbfProgram {
+        &self.program
+    }
+
+    /// Returns whether the program was deployed.
+    pub fn redeploy_if_changed(&mut self, simulator: &TransactionSimulator) -> Result<bool> {
+        let modified = self.program.modified()?;
+        if self.last_modified == Some(modified) {
+            return Ok(false);
+        }
+        self.program.add_to_simulator(simulator)?;
+        self.last_modified = Some(modified);
+        Ok(true)
+    }
+
+    /// Poll for rebuilds, calling `on_deploy` after each deployment, until it returns `false`.
+    /// The binary may briefly be missing while `cargo build-sbf` writes it,
+    /// so read errors are retried on the next poll.
+    pub fn watch(
+        mut self,
+        simulator: &TransactionSimulator,
+        poll_interval: Duration,
+        mut on_deploy: impl FnMut(&SbfProgram) -> bool,
+    ) {
+        loop {
+            if let Ok(true) = self.redeploy_if_changed(simulator) {
+                if !on_deploy(&self.program) {
+                    return;
+                }
+            }
+            thread::sleep(poll_interval);
+        }
+    }
+}
+
+fn deploy_dir(root: &Path) -> PathBuf {
+    match std::env::var("CARGO_TARGET_DIR") {
+        Ok(target_dir) => PathBuf::from(target_dir).join("deploy"),
+        Err(_) => root.join("target").join("deploy"),
+    }
+}
+
+fn read_error(path: &Path, e: std::io::Error) -> SimulatorError {
+    SimulatorError::FileReadError(path.display().to_string(), e)
+}
+
+/// The `declare_id!` in the crate's `src/lib.rs`, if the crate is found.
+fn declared_id(root: &Path, crate_name: &str) -> Result<Option<Pubkey>> {
+    let crate_dirs = [
+        root.to_path_buf(),
+        root.join(crate_name),
+        root.join("programs").join(crate_name),
+    ];
+    let Some(crate_dir) = crate_dirs
+        .into_iter()
+        .find(|dir| has_package_name(&dir.join("Cargo.toml"), crate_name))
+    else {
+        return Ok(None);
+    };
+    let lib_rs = crate_dir.join("src").join("lib.rs");
+    let source = match fs::read_to_string(&lib_rs) {
+        Ok(source) => source,
+        Err(_) => return Ok(None),
+    };
+    parse_declare_id(&source)
+        .map(|id| {
+            Pubkey::from_str(id).map_err(|_| {
+                SimulatorError::ProgramIdNotFound(format!("{}: {}", lib_rs.display(), id))
+            })
+        })
+        .transpose()
+}
+
+fn has_package_name(cargo_toml: &Path, crate_name: &str) -> bool {
+    let quoted = format!("\"{}\"", crate_name);
+    fs::read_to_string(cargo_toml).is_ok_and(|manifest| {
+        manifest.lines().any(|line| {
+            let line = line.trim();
+            line.starts_with("name") && line.ends_with(&quoted)
+        })
+    })
+}
+
+/// The string literal passed to the first `declare_id!` outside of a comment.
+fn parse_declare_id(source: &str) -> Option<&str> {
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let code = line.split("//").next().unwrap_or_default();
+        let trimmed = code.trim_start();
+        if !trimmed.starts_with("/*") && !trimmed.starts_with('*') {
+            if let Some(index) = code.find("declare_id!") {
+                let args = &source[offset + index + "declare_id!".len()..];
+                let start = args.find('"')? + 1;
+                let end = start + args[start..].find('"')?;
+                return Some(&args[start..end]);
+            }
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn keypair_file_id(path: &Path) -> Result<Option<Pubkey>> {
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(_) => return Ok(None),
+    };
+    let invalid = || SimulatorError::InvalidKeypairFile(path.display().to_string());
+    let bytes: Vec<u8> = serde_json::from_str(&json).map_err(|_| invalid())?;
+    let keypair = Keypair::from_bytes(&bytes).map_err(|_| invalid())?;
+    Ok(Some(keypair.pubkey()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spl_artifacts::SPL_MEMO_3;
+    use solana_sdk::account::ReadableAccount;
+    use tempfile::TempDir;
+
+    #[test]
+    fn programs_are_found_and_redeployed() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+        // Not `deploy_dir`, which would be a shared `$CARGO_TARGET_DIR/deploy` if it is set.
+        let deploy = root.join("target").join("deploy");
+        let crate_dir = root.join("programs").join("my-program");
+        fs::create_dir_all(&deploy).unwrap();
+        fs::create_dir_all(crate_dir.join("src")).unwrap();
+        fs::write(deploy.join("my_program.so"), SPL_MEMO_3.elf()).unwrap();
+        let keypair = Keypair::new();
+        fs::write(
+            deploy.join("my_program-keypair.json"),
+            serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap(),
+        )
+        .unwrap();
+
+        // Without the crate's source, the id comes from the keypair file.
+        let program = SbfProgram::find_in_deploy_dir(root, &deploy, "my-program").unwrap();
+        assert_eq!(program.name, "my_program");
+        assert_eq!(program.program_id, keypair.pubkey());
+
+        let declared = Pubkey::new_unique();
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"my-program\"\n",
+        )
+        .unwrap();
+        fs::write(
+            crate_dir.join("src").join("lib.rs"),
+            format!("declare_id!(\"{}\");\n", declared),
+        )
+        .unwrap();
+        let program = SbfProgram::find_in_deploy_dir(root, &deploy, "my-program").unwrap();
+        assert_eq!(program.program_id, declared);
+
+        let simulator = TransactionSimulator::new();
+        let mut watcher = SbfProgramWatcher::new(program);
+        assert!(watcher.redeploy_if_changed(&simulator).unwrap());
+        assert!(!watcher.redeploy_if_changed(&simulator).unwrap());
+        let programdata = simulator
+            .get_account(&upgradeable_programdata_address(&declared))
+            .unwrap();
+        assert!(programdata.data().ends_with(SPL_MEMO_3.elf()));
+
+        let so = fs::File::options()
+            .write(true)
+            .open(deploy.join("my_program.so"))
+            .unwrap();
+        so.set_modified(SystemTime::now() + Duration::from_secs(1))
+            .unwrap();
+        assert!(watcher.redeploy_if_changed(&simulator).unwrap());
+
+        assert!(SbfProgram::find_in_deploy_dir(root, &deploy, "other-program").is_err());
+
+        let other = Keypair::new();
+        fs::write(deploy.join("other_program.so"), SPL_MEMO_3.elf()).unwrap();
+        fs::write(
+            deploy.join("other_program-keypair.json"),
+            serde_json::to_string(&other.to_bytes().to_vec()).unwrap(),
+        )
+        .unwrap();
+        // Without a declared id or keypair file, a program is skipped.
+        fs::write(deploy.join("dumped_program.so"), SPL_MEMO_3.elf()).unwrap();
+        let programs = SbfProgram::find_all_in_deploy_dir(root, &deploy).unwrap();
+        let ids: Vec<Pubkey> = programs.iter().map(|p| p.program_id).collect();
+        assert_eq!(ids, vec![declared, other.pubkey()]);
+        let simulator = TransactionSimulator::new();
+        for program in &programs {
+            program.add_to_simulator(&simulator).unwrap();
+        }
+        assert!(simulator.get_account(&other.pubkey()).unwrap().executable());
+    }
+
+    #[test]
+    fn commented_out_ids_are_skipped() {
+        let id = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS";
+        let source = format!(
+            "// declare_id!(\"11111111111111111111111111111111\");\n\
+             /*\n * declare_id!(\"11111111111111111111111111111111\");\n */\n\
+             declare_id!(\n    \"{}\"\n); // declare_id!(\"other\")\n",
+            id
+        );
+        assert_eq!(parse_declare_id(&source), Some(id));
+        assert_eq!(parse_declare_id("// declare_id!(\"x\");\n"), None);
+    }
+}
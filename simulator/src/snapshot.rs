@@ -0,0 +1,174 @@
+//! Capture the accounts of a [crate::TransactionSimulator] and roll back to them later,
+//! see [crate::TransactionSimulator::snapshot].
+use crate::error::{Result, SimulatorError};
+use solana_runtime::bank::Bank;
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable,
+    clock::Slot,
+    loader_v4,
+    pubkey::Pubkey,
+    sysvar,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Every non-sysvar account in the working bank at the time the snapshot was taken.
+/// Cheap to clone relative to the bank, since account data is reference counted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatorSnapshot {
+    slot: Slot,
+    accounts: HashMap<Pubkey, AccountSharedData>,
+}
+
+impl SimulatorSnapshot {
+    pub(crate) fn capture(bank: &Bank) -> Result<Self> {
+        let accounts = bank
+            .get_all_accounts()
+            .map_err(SimulatorError::AccountScan)?
+            .into_iter()
+            .filter(|(pubkey, ..)| !sysvar::is_sysvar_id(pubkey))
+            .map(|(pubkey, account, _)| (pubkey, account))
+            .collect();
+        Ok(Self {
+            slot: bank.slot(),
+            accounts,
+        })
+    }
+
+    /// Store every account in the snapshot, and close any account created since.
+    /// If any program changed, the bank's program cache is cleared of every program.
+    pub(crate) fn restore(&self, bank: &Bank) -> Result<()> {
+        let current = bank
+            .get_all_accounts()
+            .map_err(SimulatorError::AccountScan)?;
+        let mut programs: HashSet<Pubkey> = self
+            .accounts
+            .iter()
+            .filter(|(_, account)| is_loader(account.owner()))
+            .map(|(pubkey, _)| *pubkey)
+            .collect();
+        let mut programs_changed = false;
+        for (pubkey, account, _) in current {
+            if sysvar::is_sysvar_id(&pubkey) {
+                continue;
+            }
+            if is_loader(account.owner()) {
+                programs.insert(pubkey);
+            }
+            match self.accounts.get(&pubkey) {
+                Some(snapshot) if *snapshot == account => continue,
+                Some(snapshot) => bank.store_account(&pubkey, snapshot),
+                None => bank.store_account(&pubkey, &AccountSharedData::default()),
+            }
+            programs_changed |= programs.contains(&pubkey);
+        }
+        for (pubkey, account) in &self.accounts {
+            if bank.get_account(pubkey).is_none() {
+                bank.store_account(pubkey, account);
+                programs_changed |= programs.contains(pubkey);
+            }
+        }
+        // The cache is keyed by program id and deployment slot, which a redeployment
+        // by storing accounts directly does not change.
+        if programs_changed {
+            bank.loaded_programs_cache
+                .write()
+                .unwrap()
+                .remove_programs(programs.into_iter());
+        }
+        Ok(())
+    }
+
+    /// The slot of the working bank when the snapshot was taken.
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<&AccountSharedData> {
+        self.accounts.get(pubkey)
+    }
+
+    pub fn accounts(&self) -> impl Iterator<Item = (&Pubkey, &AccountSharedData)> {
+        self.accounts.iter()
+    }
+}
+
+fn is_loader(owner: &Pubkey) -> bool {
+    bpf_loader::check_id(owner)
+        || bpf_loader_deprecated::check_id(owner)
+        || bpf_loader_upgradeable::check_id(owner)
+        || loader_v4::check_id(owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spl_artifacts::{SPL_MEMO_3, SPL_TOKEN};
+    use crate::TransactionSimulator;
+    use solana_sdk::{
+        account::{Account, ReadableAccount},
+        instruction::Instruction,
+        message::{Message, VersionedMessage},
+        pubkey::Pubkey,
+        system_instruction, system_program,
+    };
+
+    #[test]
+    fn restore_rolls_back_accounts() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let payer_account = Account::new(1_000_000_000, 0, &system_program::ID);
+        let simulator = TransactionSimulator::new_with_accounts([(&payer, &payer_account)]);
+        let transfer = |lamports| {
+            VersionedMessage::Legacy(Message::new(
+                &[system_instruction::transfer(&payer, &recipient, lamports)],
+                Some(&payer),
+            ))
+        };
+
+        let snapshot = simulator.snapshot().unwrap();
+        assert_eq!(
+            snapshot.get_account(&payer).unwrap().lamports(),
+            1_000_000_000
+        );
+        assert!(snapshot.get_account(&recipient).is_none());
+
+        // Explore two branches from the same state.
+        for lamports in [1_000_000, 2_000_000] {
+            simulator
+                .process_message_and_update_accounts(transfer(lamports))
+                .unwrap();
+            assert_eq!(
+                simulator.get_account(&recipient).unwrap().lamports(),
+                lamports
+            );
+            simulator.restore(&snapshot).unwrap();
+            assert!(simulator.get_account(&recipient).is_none());
+            assert_eq!(
+                simulator.get_account(&payer).unwrap().lamports(),
+                1_000_000_000
+            );
+        }
+        assert_eq!(simulator.snapshot().unwrap(), snapshot);
+    }
+
+    #[test]
+    fn restored_programs_are_executed() {
+        let payer = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let payer_account = Account::new(1_000_000_000, 0, &system_program::ID);
+        let simulator = TransactionSimulator::new_with_accounts([(&payer, &payer_account)]);
+        let memo = VersionedMessage::Legacy(Message::new(
+            &[Instruction::new_with_bytes(program_id, b"hello", vec![])],
+            Some(&payer),
+        ));
+
+        simulator.add_bpf_upgradeable(program_id, SPL_MEMO_3.elf());
+        let snapshot = simulator.snapshot().unwrap();
+        // Redeploy another program at the same address, and load it into the cache.
+        simulator.add_bpf_upgradeable(program_id, SPL_TOKEN.elf());
+        assert!(!simulator.process_message(memo.clone()).unwrap().success());
+
+        simulator.restore(&snapshot).unwrap();
+        assert!(simulator.process_message(memo).unwrap().success());
+    }
+}
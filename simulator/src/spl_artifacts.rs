@@ -0,0 +1,169 @@
+//! The SPL program binaries bundled with [solana_program_test], with their ids, loaders
+//! and versions, and helpers to load them into a [ProgramTest] or a [TransactionSimulator].
+//! Every [TransactionSimulator] already contains all of them.
+use crate::TransactionSimulator;
+use lazy_static::lazy_static;
+use solana_program_test::{programs::spl_programs, ProgramTest};
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount, WritableAccount},
+    bpf_loader, bpf_loader_upgradeable,
+    bpf_loader_upgradeable::UpgradeableLoaderState,
+    pubkey,
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use std::collections::HashMap;
+
+/// A bundled SPL program binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplArtifact {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub program_id: Pubkey,
+    /// The loader that owns the program account.
+    pub loader: Pubkey,
+}
+
+pub const SPL_TOKEN: SplArtifact = SplArtifact {
+    name: "spl_token",
+    version: "3.5.0",
+    program_id: pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+    loader: bpf_loader::ID,
+};
+
+pub const SPL_TOKEN_2022: SplArtifact = SplArtifact {
+    name: "spl_token_2022",
+    version: "0.9.0",
+    program_id: pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"),
+    loader: bpf_loader_upgradeable::ID,
+};
+
+pub const SPL_MEMO_1: SplArtifact = SplArtifact {
+    name: "spl_memo",
+    version: "1.0.0",
+    program_id: pubkey!("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo"),
+    loader: bpf_loader::ID,
+};
+
+pub const SPL_MEMO_3: SplArtifact = SplArtifact {
+    name: "spl_memo",
+    version: "3.0.0",
+    program_id: pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"),
+    loader: bpf_loader::ID,
+};
+
+pub const SPL_ASSOCIATED_TOKEN_ACCOUNT: SplArtifact = SplArtifact {
+    name: "spl_associated_token_account",
+    version: "1.1.1",
+    program_id: pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"),
+    loader: bpf_loader::ID,
+};
+
+pub const ALL: &[SplArtifact] = &[
+    SPL_TOKEN,
+    SPL_TOKEN_2022,
+    SPL_MEMO_1,
+    SPL_MEMO_3,
+    SPL_ASSOCIATED_TOKEN_ACCOUNT,
+];
+
+lazy_static! {
+    /// The program accounts, built once with the default rent. [AccountSharedData] keeps its
+    /// data behind an `Arc`, so clones of these accounts share the ELF bytes.
+    static ref ACCOUNTS: HashMap<Pubkey, AccountSharedData> =
+        spl_programs(&Rent::default()).into_iter().collect();
+}
+
+impl SplArtifact {
+    /// The artifact with the given program id, if it is bundled.
+    pub fn find(program_id: &Pubkey) -> Option<Self> {
+        ALL.iter().find(|a| a.program_id == *program_id).copied()
+    }
+
+    /// `<name>-<version>.so`
+    pub fn file_name(&self) -> String {
+        format!("{}-{}.so", self.name, self.version)
+    }
+
+    pub fn elf(&self) -> &'static [u8] {
+        let address = if self.loader == bpf_loader_upgradeable::ID {
+            self.programdata_address()
+        } else {
+            self.program_id
+        };
+        &ACCOUNTS[&address].data()[self.elf_offset()..]
+    }
+
+    /// The program's data account, which is only used by upgradeable programs.
+    pub fn programdata_address(&self) -> Pubkey {
+        Pubkey::find_program_address(&[self.program_id.as_ref()], &self.loader).0
+    }
+
+    /// The program account, and for upgradeable programs, the program data account.
+    /// The ELF is shared with the bundled accounts rather than copied.
+    pub fn accounts(&self, rent: &Rent) -> Vec<(Pubkey, AccountSharedData)> {
+        let mut addresses = vec![self.program_id];
+        if self.loader == bpf_loader_upgradeable::ID {
+            addresses.push(self.programdata_address());
+        }
+        addresses
+            .into_iter()
+            .map(|address| {
+                let mut account = ACCOUNTS[&address].clone();
+                account.set_lamports(rent.minimum_balance(account.data().len()).max(1));
+                (address, account)
+            })
+            .collect()
+    }
+
+    pub fn add_to_program_test(&self, program_test: &mut ProgramTest) {
+        for (pubkey, account) in self.accounts(&Rent::default()) {
+            program_test.add_account(pubkey, account.into());
+        }
+    }
+
+    /// Reload the program into a simulator, e.g. after it was overwritten.
+    pub fn add_to_simulator(&self, simulator: &TransactionSimulator) {
+        for (pubkey, account) in self.accounts(&Rent::default()) {
+            simulator.update_account(&pubkey, &account);
+        }
+    }
+
+    fn elf_offset(&self) -> usize {
+        if self.loader == bpf_loader_upgradeable::ID {
+            UpgradeableLoaderState::size_of_programdata_metadata()
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn artifacts_are_bundled() {
+        for artifact in ALL {
+            // Every ELF starts with the magic bytes `\x7fELF`.
+            assert_eq!(&artifact.elf()[..4], b"\x7fELF", "{}", artifact.file_name());
+            assert_eq!(SplArtifact::find(&artifact.program_id), Some(*artifact));
+        }
+        assert_eq!(SPL_TOKEN_2022.accounts(&Rent::default()).len(), 2);
+        let rent = Rent {
+            lamports_per_byte_year: 1,
+            ..Rent::default()
+        };
+        for (pubkey, account) in SPL_TOKEN_2022.accounts(&rent) {
+            assert_eq!(
+                account.lamports(),
+                rent.minimum_balance(account.data().len()),
+                "{pubkey}"
+            );
+        }
+
+        let simulator = TransactionSimulator::new();
+        let program = simulator.get_account(&SPL_TOKEN.program_id).unwrap();
+        assert_eq!(program.data(), SPL_TOKEN.elf());
+    }
+}
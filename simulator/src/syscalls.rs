@@ -0,0 +1,115 @@
+//! Overrides for the syscall stubs that the simulator installs with
+//! [solana_sdk::program_stubs::set_syscall_stubs].
+//!
+//! The stubs only serve code compiled for the host, e.g. calls to `Clock::get()` or `msg!`
+//! from test code or native programs. BPF programs use the runtime's syscalls, and read
+//! sysvars from the bank, which can be set with [crate::TransactionSimulator::set_sysvar].
+use crate::program_test_private_items::install_syscall_stubs;
+use solana_sdk::{clock::Clock, epoch_schedule::EpochSchedule, rent::Rent};
+use std::sync::{Arc, RwLock};
+
+/// Receives messages passed to `sol_log`.
+pub type LogSink = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Syscalls to answer with fixed values instead of the values of the running bank.
+/// Unset fields keep the default behavior.
+#[derive(Clone, Default)]
+pub struct SyscallOverrides {
+    pub clock: Option<Clock>,
+    pub rent: Option<Rent>,
+    pub epoch_schedule: Option<EpochSchedule>,
+    pub log_sink: Option<LogSink>,
+}
+
+static SYSCALL_OVERRIDES: RwLock<Option<SyscallOverrides>> = RwLock::new(None);
+
+/// Replace the process-wide syscall overrides, installing the simulator's stubs if needed.
+pub fn set_syscall_overrides(overrides: SyscallOverrides) {
+    install_syscall_stubs();
+    *SYSCALL_OVERRIDES.write().unwrap() = Some(overrides);
+}
+
+/// Restore the default behavior of every syscall.
+pub fn clear_syscall_overrides() {
+    *SYSCALL_OVERRIDES.write().unwrap() = None;
+}
+
+pub(crate) fn syscall_overrides() -> SyscallOverrides {
+    SYSCALL_OVERRIDES
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Serializes the tests that set the process-wide overrides.
+#[cfg(test)]
+static OVERRIDES_TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Sets the overrides for the duration of a test, and clears them when dropped,
+/// even if the test panics.
+#[cfg(test)]
+pub(crate) struct OverridesGuard(#[allow(dead_code)] std::sync::MutexGuard<'static, ()>);
+
+#[cfg(test)]
+impl OverridesGuard {
+    pub(crate) fn set(overrides: SyscallOverrides) -> Self {
+        let lock = OVERRIDES_TEST_LOCK
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        set_syscall_overrides(overrides);
+        Self(lock)
+    }
+}
+
+#[cfg(test)]
+impl Drop for OverridesGuard {
+    fn drop(&mut self) {
+        clear_syscall_overrides();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TransactionSimulator;
+    use solana_program::{msg, sysvar::Sysvar};
+    use solana_sdk::{
+        account::{Account, ReadableAccount},
+        pubkey::Pubkey,
+        system_program, sysvar,
+    };
+    use std::sync::Mutex;
+
+    #[test]
+    fn overridden_syscalls_are_answered() {
+        let logs: Arc<Mutex<Vec<String>>> = Arc::default();
+        let sink = logs.clone();
+        let clock = Clock {
+            slot: 42,
+            unix_timestamp: 1_700_000_000,
+            ..Clock::default()
+        };
+        let overrides = OverridesGuard::set(SyscallOverrides {
+            clock: Some(clock.clone()),
+            log_sink: Some(Arc::new(move |message| {
+                sink.lock().unwrap().push(message.to_string())
+            })),
+            ..Default::default()
+        });
+        assert_eq!(Clock::get().unwrap(), clock);
+        msg!("hello {}", 1);
+        assert_eq!(*logs.lock().unwrap(), vec!["hello 1".to_string()]);
+        drop(overrides);
+
+        let payer = Account::new(1_000_000_000, 0, &system_program::ID);
+        let simulator = TransactionSimulator::new_with_accounts([(&Pubkey::new_unique(), &payer)]);
+        let rent = Rent {
+            lamports_per_byte_year: 1,
+            ..Rent::default()
+        };
+        simulator.set_sysvar(&rent);
+        let account = simulator.get_account(&sysvar::rent::ID).unwrap();
+        assert_eq!(bincode::deserialize::<Rent>(account.data()).unwrap(), rent);
+    }
+}
@@ -0,0 +1,87 @@
+//! SPL token balances of the accounts in a processed message, as in the
+//! `preTokenBalances` and `postTokenBalances` of RPC transaction metadata.
+pub use solana_devtools_execution_result::token_balances::{token_balances, TokenBalance};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spl_artifacts::SPL_TOKEN;
+    use crate::TransactionSimulator;
+    use solana_program::program_pack::Pack;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::{
+        account::Account,
+        message::{Message, VersionedMessage},
+        rent::Rent,
+        system_program,
+    };
+    use spl_token::state::{Account as TokenAccount, AccountState};
+
+    fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount {
+            mint,
+            owner,
+            amount,
+            state: AccountState::Initialized,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: SPL_TOKEN.program_id,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn token_balances_are_tracked() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let (source, destination) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let payer_account = Account::new(1_000_000_000, 0, &system_program::ID);
+        let source_account = token_account(mint, payer, 100);
+        let destination_account = token_account(mint, recipient, 0);
+        let simulator = TransactionSimulator::new_with_accounts([
+            (&payer, &payer_account),
+            (&source, &source_account),
+            (&destination, &destination_account),
+        ]);
+
+        let transfer = spl_token::instruction::transfer(
+            &SPL_TOKEN.program_id,
+            &source,
+            &destination,
+            &payer,
+            &[],
+            40,
+        )
+        .unwrap();
+        let processed = simulator
+            .process_message(VersionedMessage::Legacy(Message::new(
+                &[transfer],
+                Some(&payer),
+            )))
+            .unwrap();
+        assert!(processed.success(), "{:?}", processed.logs);
+
+        assert_eq!(processed.pre_token_balances.len(), 2);
+        assert_eq!(
+            processed.post_token_balances[&destination],
+            TokenBalance {
+                mint,
+                owner: recipient,
+                amount: 40,
+                program_id: SPL_TOKEN.program_id,
+            }
+        );
+        assert_eq!(processed.pre_token_balances[&source].amount, 100);
+        assert_eq!(processed.post_token_balances[&source].amount, 60);
+        assert_eq!(processed.token_balance_change(&source), -40);
+        assert_eq!(processed.token_balance_change(&destination), 40);
+        assert_eq!(processed.token_balance_change(&payer), 0);
+    }
+}
@@ -0,0 +1,49 @@
+//! Starting a `ProgramTest` replaces the process-wide syscall stubs,
+//! so these tests run in their own process.
+use solana_devtools_simulator::{ProgramTestBackend, SimulationBackend, TransactionSimulator};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::{Account, ReadableAccount},
+    message::{Message, VersionedMessage},
+    pubkey::Pubkey,
+    signature::Signer,
+    system_instruction, system_program,
+};
+
+fn transfer_suite<B: SimulationBackend>(backend: &mut B, payer: Pubkey) {
+    let recipient = Pubkey::new_unique();
+    backend.update_account(
+        &recipient,
+        &Account::new(1_000_000, 0, &system_program::ID).into(),
+    );
+    let transfer = VersionedMessage::Legacy(Message::new(
+        &[system_instruction::transfer(&payer, &recipient, 1_000_000)],
+        Some(&payer),
+    ));
+    let processed = backend.process_message(transfer).unwrap();
+    assert!(processed.success(), "{:?}", processed.logs);
+    assert_eq!(processed.accounts[&recipient].lamports(), 2_000_000);
+    assert_eq!(
+        backend.get_account(&recipient).unwrap().lamports(),
+        2_000_000
+    );
+
+    let mut clock = backend.get_clock();
+    clock.unix_timestamp += 3_600;
+    backend.set_clock(clock.clone());
+    assert_eq!(backend.get_clock(), clock);
+}
+
+#[test]
+fn suites_run_against_every_backend() {
+    let payer = Pubkey::new_unique();
+    let mut simulator = TransactionSimulator::new_with_accounts([(
+        &payer,
+        &Account::new(1_000_000_000, 0, &system_program::ID),
+    )]);
+    transfer_suite(&mut simulator, payer);
+
+    let mut program_test = ProgramTestBackend::new(ProgramTest::default()).unwrap();
+    let payer = program_test.context().payer.pubkey();
+    transfer_suite(&mut program_test, payer);
+}
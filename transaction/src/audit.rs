@@ -0,0 +1,362 @@
+//! Review a message before signing it: which accounts it writes to, who signs it, which programs
+//! it invokes, and any instructions that hand over control of accounts or move lamports
+//! somewhere unexpected.
+use crate::decompile_instructions::{
+    extract_instructions_with_lookup_tables, lookup_addresses_from_tables,
+};
+use solana_program::message::v0::{LoadedAddresses, LoadedMessage};
+use solana_program::message::AddressLoaderError;
+use solana_program::program_option::COption;
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::bpf_loader_upgradeable;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::loader_upgradeable_instruction::UpgradeableLoaderInstruction;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::system_program;
+use spl_token_2022::instruction::{AuthorityType, TokenInstruction};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// A pattern worth a second look before signing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Finding {
+    /// A token program `SetAuthority` on a mint or token account.
+    SetAuthority {
+        instruction_index: usize,
+        program_id: Pubkey,
+        account: Pubkey,
+        authority_type: AuthorityType,
+        new_authority: Option<Pubkey>,
+    },
+    /// A change of the upgrade authority of a program or buffer.
+    SetUpgradeAuthority {
+        instruction_index: usize,
+        account: Pubkey,
+        new_authority: Option<Pubkey>,
+    },
+    /// A system program `Assign`, changing the program that owns an account.
+    OwnerChange {
+        instruction_index: usize,
+        account: Pubkey,
+        new_owner: Pubkey,
+    },
+    /// A system transfer above the threshold, to a recipient that was not expected.
+    Transfer {
+        instruction_index: usize,
+        from: Pubkey,
+        to: Pubkey,
+        lamports: u64,
+    },
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SetAuthority {
+                instruction_index,
+                account,
+                authority_type,
+                new_authority,
+                ..
+            } => write!(
+                f,
+                "Instruction {}: sets the {:?} authority of {} to {}",
+                instruction_index,
+                authority_type,
+                account,
+                display_authority(new_authority)
+            ),
+            Self::SetUpgradeAuthority {
+                instruction_index,
+                account,
+                new_authority,
+            } => write!(
+                f,
+                "Instruction {}: sets the upgrade authority of {} to {}",
+                instruction_index,
+                account,
+                display_authority(new_authority)
+            ),
+            Self::OwnerChange {
+                instruction_index,
+                account,
+                new_owner,
+            } => write!(
+                f,
+                "Instruction {}: assigns {} to program {}",
+                instruction_index, account, new_owner
+            ),
+            Self::Transfer {
+                instruction_index,
+                from,
+                to,
+                lamports,
+            } => write!(
+                f,
+                "Instruction {}: transfers {} lamports from {} to {}",
+                instruction_index, lamports, from, to
+            ),
+        }
+    }
+}
+
+fn display_authority(authority: &Option<Pubkey>) -> String {
+    authority.map_or("none".to_string(), |authority| authority.to_string())
+}
+
+/// What a message does to accounts, see [MessageAuditor::audit].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageAudit {
+    /// In the order of the message's account keys.
+    pub signers: Vec<Pubkey>,
+    /// Accounts the runtime will lock as writable, in the order of the message's account keys.
+    pub writable: Vec<Pubkey>,
+    /// Programs invoked by top-level instructions, in the order they are first invoked.
+    pub programs: Vec<Pubkey>,
+    pub findings: Vec<Finding>,
+}
+
+/// Audits messages, flagging authority changes, owner changes, and system transfers
+/// above `transfer_threshold` lamports to recipients not in `expected_recipients`.
+#[derive(Debug, Clone, Default)]
+pub struct MessageAuditor {
+    pub transfer_threshold: u64,
+    pub expected_recipients: Vec<Pubkey>,
+}
+
+impl MessageAuditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only flag transfers of more than `lamports`.
+    pub fn with_transfer_threshold(mut self, lamports: u64) -> Self {
+        self.transfer_threshold = lamports;
+        self
+    }
+
+    /// Never flag transfers to `recipient`.
+    pub fn with_expected_recipient(mut self, recipient: Pubkey) -> Self {
+        self.expected_recipients.push(recipient);
+        self
+    }
+
+    /// Audit a message, resolving the address table lookups of a v0 message from `tables`.
+    pub fn audit(
+        &self,
+        message: &VersionedMessage,
+        tables: &[AddressLookupTableAccount],
+    ) -> Result<MessageAudit, AddressLoaderError> {
+        let instructions = extract_instructions_with_lookup_tables(message, tables)?;
+        let tables: HashMap<Pubkey, Vec<Pubkey>> = tables
+            .iter()
+            .map(|table| (table.key, table.addresses.clone()))
+            .collect();
+        let loaded: LoadedAddresses = lookup_addresses_from_tables(message, &tables)?
+            .into_iter()
+            .collect();
+        // Demoted as the runtime would, including sysvars and builtins from lookup tables.
+        let is_writable: Box<dyn Fn(usize) -> bool> = match message {
+            VersionedMessage::Legacy(message) => Box::new(|index| message.is_writable(index)),
+            VersionedMessage::V0(message) => {
+                let message = LoadedMessage::new_borrowed(message, &loaded);
+                Box::new(move |index| message.is_writable(index))
+            }
+        };
+        let keys = message
+            .static_account_keys()
+            .iter()
+            .chain(&loaded.writable)
+            .chain(&loaded.readonly)
+            .enumerate();
+
+        let mut audit = MessageAudit {
+            signers: vec![],
+            writable: vec![],
+            programs: vec![],
+            findings: vec![],
+        };
+        for (index, key) in keys {
+            if message.is_signer(index) {
+                audit.signers.push(*key);
+            }
+            if is_writable(index) {
+                audit.writable.push(*key);
+            }
+        }
+        for (index, ix) in instructions.iter().enumerate() {
+            if !audit.programs.contains(&ix.program_id) {
+                audit.programs.push(ix.program_id);
+            }
+            audit.findings.extend(self.inspect(index, ix));
+        }
+        Ok(audit)
+    }
+
+    fn inspect(&self, instruction_index: usize, ix: &Instruction) -> Option<Finding> {
+        let account = |position: usize| ix.accounts.get(position).map(|meta| meta.pubkey);
+        if system_program::check_id(&ix.program_id) {
+            return match bincode::deserialize::<SystemInstruction>(&ix.data).ok()? {
+                SystemInstruction::Assign { owner }
+                | SystemInstruction::AssignWithSeed { owner, .. } => Some(Finding::OwnerChange {
+                    instruction_index,
+                    account: account(0)?,
+                    new_owner: owner,
+                }),
+                SystemInstruction::Transfer { lamports } => {
+                    self.transfer(instruction_index, account(0)?, account(1)?, lamports)
+                }
+                SystemInstruction::TransferWithSeed { lamports, .. } => {
+                    self.transfer(instruction_index, account(0)?, account(2)?, lamports)
+                }
+                _ => None,
+            };
+        }
+        if bpf_loader_upgradeable::check_id(&ix.program_id) {
+            return match bincode::deserialize::<UpgradeableLoaderInstruction>(&ix.data).ok()? {
+                UpgradeableLoaderInstruction::SetAuthority
+                | UpgradeableLoaderInstruction::SetAuthorityChecked => {
+                    Some(Finding::SetUpgradeAuthority {
+                        instruction_index,
+                        account: account(0)?,
+                        new_authority: account(2),
+                    })
+                }
+                _ => None,
+            };
+        }
+        if spl_token_2022::check_spl_token_program_account(&ix.program_id).is_ok() {
+            if let TokenInstruction::SetAuthority {
+                authority_type,
+                new_authority,
+            } = TokenInstruction::unpack(&ix.data).ok()?
+            {
+                return Some(Finding::SetAuthority {
+                    instruction_index,
+                    program_id: ix.program_id,
+                    account: account(0)?,
+                    authority_type,
+                    new_authority: match new_authority {
+                        COption::Some(authority) => Some(authority),
+                        COption::None => None,
+                    },
+                });
+            }
+        }
+        None
+    }
+
+    fn transfer(
+        &self,
+        instruction_index: usize,
+        from: Pubkey,
+        to: Pubkey,
+        lamports: u64,
+    ) -> Option<Finding> {
+        (lamports > self.transfer_threshold && !self.expected_recipients.contains(&to)).then_some(
+            Finding::Transfer {
+                instruction_index,
+                from,
+                to,
+                lamports,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::v0;
+    use solana_sdk::{system_instruction, sysvar};
+
+    #[test]
+    fn suspicious_instructions_are_flagged() {
+        let payer = Pubkey::new_unique();
+        let (merchant, stranger) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (token_account, attacker) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: vec![stranger, token_account, sysvar::clock::id()],
+        };
+        let instructions = [
+            system_instruction::transfer(&payer, &merchant, 5_000_000),
+            system_instruction::transfer(&payer, &stranger, 1_000),
+            system_instruction::transfer(&payer, &stranger, 5_000_000),
+            spl_token_2022::instruction::set_authority(
+                &spl_token::id(),
+                &token_account,
+                Some(&attacker),
+                AuthorityType::AccountOwner,
+                &payer,
+                &[],
+            )
+            .unwrap(),
+            system_instruction::assign(&payer, &attacker),
+            Instruction::new_with_bytes(
+                spl_memo::id(),
+                b"hello world",
+                vec![solana_sdk::instruction::AccountMeta::new(
+                    sysvar::clock::id(),
+                    false,
+                )],
+            ),
+        ];
+        let message = VersionedMessage::V0(
+            v0::Message::try_compile(
+                &payer,
+                &instructions,
+                std::slice::from_ref(&table),
+                Hash::default(),
+            )
+            .unwrap(),
+        );
+
+        let audit = MessageAuditor::new()
+            .with_transfer_threshold(10_000)
+            .with_expected_recipient(merchant)
+            .audit(&message, &[table])
+            .unwrap();
+        assert_eq!(audit.signers, vec![payer]);
+        // The clock sysvar is requested as writable through the lookup table,
+        // but locked as read-only.
+        assert_eq!(
+            audit.writable,
+            vec![payer, merchant, stranger, token_account]
+        );
+        assert_eq!(
+            audit.programs,
+            vec![system_program::id(), spl_token::id(), spl_memo::id()]
+        );
+        assert_eq!(
+            audit.findings,
+            vec![
+                Finding::Transfer {
+                    instruction_index: 2,
+                    from: payer,
+                    to: stranger,
+                    lamports: 5_000_000,
+                },
+                Finding::SetAuthority {
+                    instruction_index: 3,
+                    program_id: spl_token::id(),
+                    account: token_account,
+                    authority_type: AuthorityType::AccountOwner,
+                    new_authority: Some(attacker),
+                },
+                Finding::OwnerChange {
+                    instruction_index: 4,
+                    account: payer,
+                    new_owner: attacker,
+                },
+            ]
+        );
+        assert_eq!(
+            audit.findings[2].to_string(),
+            format!("Instruction 4: assigns {} to program {}", payer, attacker)
+        );
+    }
+}
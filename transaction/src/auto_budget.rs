@@ -0,0 +1,154 @@
+//! Set a transaction's compute unit limit from the units it consumes in simulation,
+//! plus a safety margin.
+use crate::compute_budget::is_set_compute_unit_limit;
+#[cfg(feature = "client")]
+use solana_rpc_client::rpc_client::RpcClient;
+#[cfg(feature = "client")]
+use solana_rpc_client_api::{
+    client_error::{Error as ClientError, ErrorKind as ClientErrorKind},
+    config::RpcSimulateTransactionConfig,
+};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::{Message, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+#[cfg(feature = "client")]
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+
+/// The largest compute unit limit a transaction can request.
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Measures the compute units consumed by an unsigned message.
+pub trait ComputeUnitSimulator {
+    type Error;
+
+    fn simulate_compute_units(&self, message: VersionedMessage) -> Result<u64, Self::Error>;
+}
+
+/// Added to the simulated units, since execution can take a different path
+/// when the transaction lands, e.g. because an account changed in the meantime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafetyMargin {
+    pub percent: u64,
+    pub extra_units: u32,
+}
+
+impl Default for SafetyMargin {
+    /// 10%, plus 1000 units.
+    fn default() -> Self {
+        Self {
+            percent: 10,
+            extra_units: 1_000,
+        }
+    }
+}
+
+impl SafetyMargin {
+    /// The compute unit limit for a transaction that consumed `units` in simulation.
+    pub fn limit(&self, units: u64) -> u32 {
+        let limit = units
+            .saturating_add(units.saturating_mul(self.percent) / 100)
+            .saturating_add(self.extra_units as u64);
+        limit.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+    }
+}
+
+/// Prepend a `SetComputeUnitLimit` instruction, replacing any already present.
+pub fn with_compute_unit_limit(instructions: Vec<Instruction>, limit: u32) -> Vec<Instruction> {
+    let mut with_limit = vec![ComputeBudgetInstruction::set_compute_unit_limit(limit)];
+    with_limit.extend(
+        instructions
+            .into_iter()
+            .filter(|ix| !is_set_compute_unit_limit(ix)),
+    );
+    with_limit
+}
+
+/// Simulate the instructions with the maximum compute unit limit, and return them
+/// with a `SetComputeUnitLimit` instruction for the consumed units plus `margin`.
+pub fn auto_compute_unit_limit<S: ComputeUnitSimulator>(
+    instructions: Vec<Instruction>,
+    payer: &Pubkey,
+    simulator: &S,
+    margin: SafetyMargin,
+) -> Result<Vec<Instruction>, S::Error> {
+    let instructions = with_compute_unit_limit(instructions, MAX_COMPUTE_UNIT_LIMIT);
+    let message = VersionedMessage::Legacy(Message::new(&instructions, Some(payer)));
+    let units = simulator.simulate_compute_units(message)?;
+    Ok(with_compute_unit_limit(instructions, margin.limit(units)))
+}
+
+/// Simulates without signature verification, with the node's latest blockhash.
+#[cfg(feature = "client")]
+impl ComputeUnitSimulator for RpcClient {
+    type Error = ClientError;
+
+    fn simulate_compute_units(&self, message: VersionedMessage) -> Result<u64, Self::Error> {
+        let tx = VersionedTransaction {
+            signatures: vec![
+                Signature::default();
+                message.header().num_required_signatures as usize
+            ],
+            message,
+        };
+        let result = self
+            .simulate_transaction_with_config(
+                &tx,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    replace_recent_blockhash: true,
+                    ..Default::default()
+                },
+            )?
+            .value;
+        if let Some(err) = result.err {
+            return Err(ClientErrorKind::TransactionError(err).into());
+        }
+        result.units_consumed.ok_or_else(|| {
+            ClientErrorKind::Custom("simulation did not report consumed units".to_string()).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    struct FixedUnits(u64);
+
+    impl ComputeUnitSimulator for FixedUnits {
+        type Error = Infallible;
+
+        fn simulate_compute_units(&self, message: VersionedMessage) -> Result<u64, Infallible> {
+            // Simulated with the maximum limit.
+            let limit = ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT);
+            assert_eq!(message.instructions()[0].data, limit.data);
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn limit_is_simulated_units_plus_margin() {
+        let payer = Pubkey::new_unique();
+        let ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+        let instructions = auto_compute_unit_limit(
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_limit(1),
+                ix.clone(),
+            ],
+            &payer,
+            &FixedUnits(20_000),
+            SafetyMargin::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            instructions,
+            vec![ComputeBudgetInstruction::set_compute_unit_limit(23_000), ix]
+        );
+        assert_eq!(
+            SafetyMargin::default().limit(2_000_000),
+            MAX_COMPUTE_UNIT_LIMIT
+        );
+    }
+}
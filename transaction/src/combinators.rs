@@ -0,0 +1,153 @@
+//! Schemas built from other schemas, returned by the combinator methods of [TransactionSchema],
+//! e.g. `deposit.then(stake).with_memo("order 42").with_compute_budget(200_000, 1_000)`.
+//! Each is itself a [TransactionSchema], and only builds its instructions when they are needed.
+use crate::priority_fee;
+use crate::TransactionSchema;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+
+/// The SPL Memo program, version 2.
+pub const MEMO_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// A memo instruction without signers.
+pub fn memo_instruction(memo: &str) -> Instruction {
+    Instruction::new_with_bytes(MEMO_PROGRAM_ID, memo.as_bytes(), vec![])
+}
+
+/// The instructions of `first`, followed by those of `second`.
+#[derive(Debug, Clone)]
+pub struct Then<A, B> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A: TransactionSchema, B: TransactionSchema> From<Then<A, B>> for Vec<Instruction> {
+    fn from(value: Then<A, B>) -> Self {
+        let mut instructions = value.first.instructions();
+        instructions.extend(value.second.instructions());
+        instructions
+    }
+}
+
+/// The instructions of `first` and `second`, alternating, starting with `first`.
+/// Once one runs out, the rest of the other follow in order.
+#[derive(Debug, Clone)]
+pub struct Interleave<A, B> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A: TransactionSchema, B: TransactionSchema> From<Interleave<A, B>> for Vec<Instruction> {
+    fn from(value: Interleave<A, B>) -> Self {
+        let mut first = value.first.instructions().into_iter();
+        let mut second = value.second.instructions().into_iter();
+        let mut instructions = vec![];
+        loop {
+            match (first.next(), second.next()) {
+                (None, None) => return instructions,
+                (a, b) => instructions.extend(a.into_iter().chain(b)),
+            }
+        }
+    }
+}
+
+/// The instructions of `inner`, preceded by compute budget instructions.
+/// See [priority_fee::with_compute_budget].
+#[derive(Debug, Clone)]
+pub struct WithComputeBudget<T> {
+    pub inner: T,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+}
+
+impl<T: TransactionSchema> From<WithComputeBudget<T>> for Vec<Instruction> {
+    fn from(value: WithComputeBudget<T>) -> Self {
+        priority_fee::with_compute_budget(
+            value.inner.instructions(),
+            value.compute_unit_price,
+            Some(value.compute_unit_limit),
+        )
+    }
+}
+
+/// The instructions of `inner`, followed by a memo.
+#[derive(Debug, Clone)]
+pub struct WithMemo<T> {
+    pub inner: T,
+    pub memo: String,
+}
+
+impl<T: TransactionSchema> From<WithMemo<T>> for Vec<Instruction> {
+    fn from(value: WithMemo<T>) -> Self {
+        let mut instructions = value.inner.instructions();
+        instructions.push(memo_instruction(&value.memo));
+        instructions
+    }
+}
+
+/// The instructions of `inner`, preceded by a guard instruction, typically a signature
+/// verification by the ed25519 or secp256k1 program that a later instruction checks for
+/// through the instructions sysvar. See `solana_sdk::ed25519_instruction` and
+/// `solana_sdk::secp256k1_instruction`.
+#[derive(Debug, Clone)]
+pub struct WithGuard<T> {
+    pub inner: T,
+    pub guard: Instruction,
+}
+
+impl<T: TransactionSchema> From<WithGuard<T>> for Vec<Instruction> {
+    fn from(value: WithGuard<T>) -> Self {
+        let mut instructions = vec![value.guard];
+        instructions.extend(value.inner.instructions());
+        instructions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::compute_budget::ComputeBudgetInstruction;
+    use spl_memo::build_memo;
+
+    #[derive(Clone, Copy)]
+    struct Memo(&'static str);
+
+    impl From<Memo> for Vec<Instruction> {
+        fn from(value: Memo) -> Self {
+            vec![build_memo(value.0.as_bytes(), &[])]
+        }
+    }
+
+    #[test]
+    fn combinators_compose() {
+        assert_eq!(memo_instruction("a"), build_memo(b"a", &[]));
+        let guard = Instruction::new_with_bytes(solana_sdk::ed25519_program::id(), &[], vec![]);
+        let instructions = Memo("a")
+            .then(vec![build_memo(b"b", &[]), build_memo(b"c", &[])])
+            .with_memo("d")
+            .with_guard(guard.clone())
+            .with_compute_budget(100_000, 5)
+            .instructions();
+        assert_eq!(
+            instructions,
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_price(5),
+                ComputeBudgetInstruction::set_compute_unit_limit(100_000),
+                guard,
+                build_memo(b"a", &[]),
+                build_memo(b"b", &[]),
+                build_memo(b"c", &[]),
+                build_memo(b"d", &[]),
+            ]
+        );
+
+        let interleaved = Memo("a")
+            .then(Memo("b"))
+            .then(Memo("c"))
+            .interleave(Memo("1").then(Memo("2")))
+            .instructions();
+        let memos: Vec<&[u8]> = interleaved.iter().map(|ix| ix.data.as_slice()).collect();
+        assert_eq!(memos, [b"a", b"1", b"b", b"2", b"c"]);
+    }
+}
@@ -0,0 +1,60 @@
+//! Recognize compute budget instructions, and price the compute units they request.
+use solana_sdk::borsh0_10::try_from_slice_unchecked;
+use solana_sdk::compute_budget::{self, ComputeBudgetInstruction};
+use solana_sdk::instruction::Instruction;
+
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// The compute budget instruction `ix` encodes, if it invokes the compute budget program.
+pub fn parse(ix: &Instruction) -> Option<ComputeBudgetInstruction> {
+    if !compute_budget::check_id(&ix.program_id) {
+        return None;
+    }
+    try_from_slice_unchecked(&ix.data).ok()
+}
+
+pub fn is_set_compute_unit_price(ix: &Instruction) -> bool {
+    matches!(
+        parse(ix),
+        Some(ComputeBudgetInstruction::SetComputeUnitPrice(_))
+    )
+}
+
+pub fn is_set_compute_unit_limit(ix: &Instruction) -> bool {
+    matches!(
+        parse(ix),
+        Some(ComputeBudgetInstruction::SetComputeUnitLimit(_))
+    )
+}
+
+/// The priority fee in lamports, rounded up like the runtime does,
+/// for `compute_unit_limit` units at `compute_unit_price` micro-lamports.
+pub fn priority_fee(compute_unit_price: u64, compute_unit_limit: u32) -> u64 {
+    (compute_unit_price as u128 * compute_unit_limit as u128).div_ceil(MICRO_LAMPORTS_PER_LAMPORT)
+        as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn compute_budget_instructions_are_recognized() {
+        let price = ComputeBudgetInstruction::set_compute_unit_price(5);
+        let limit = ComputeBudgetInstruction::set_compute_unit_limit(100);
+        // The same data, sent to another program.
+        let other = Instruction::new_with_bytes(Pubkey::new_unique(), &price.data, vec![]);
+        assert_eq!(
+            parse(&price),
+            Some(ComputeBudgetInstruction::SetComputeUnitPrice(5))
+        );
+        assert_eq!(parse(&other), None);
+        assert!(is_set_compute_unit_price(&price) && !is_set_compute_unit_price(&limit));
+        assert!(is_set_compute_unit_limit(&limit) && !is_set_compute_unit_limit(&other));
+
+        assert_eq!(priority_fee(1, 1), 1);
+        assert_eq!(priority_fee(1_000, 200_000), 200);
+        assert_eq!(priority_fee(0, 200_000), 0);
+    }
+}
@@ -1,15 +1,18 @@
 #[cfg(any(feature = "async_client", feature = "client"))]
-use solana_address_lookup_table_program::state::AddressLookupTable;
-#[cfg(any(feature = "async_client", feature = "client"))]
-use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_program::address_lookup_table::state::AddressLookupTable;
+use solana_program::message::v0::{LoadedAddresses, LoadedMessage};
+use solana_program::message::AddressLoaderError;
 #[cfg(feature = "async_client")]
-use solana_client::nonblocking::rpc_client;
+use solana_rpc_client::nonblocking::rpc_client;
 #[cfg(feature = "client")]
-use solana_client::rpc_client::RpcClient;
-use solana_program::message::v0::{LoadedAddresses, LoadedMessage};
+use solana_rpc_client::rpc_client::RpcClient;
+#[cfg(any(feature = "async_client", feature = "client"))]
+use solana_rpc_client_api::client_error::{Error as ClientError, ErrorKind as ClientErrorKind};
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
 use solana_sdk::instruction::{AccountMeta, Instruction};
-use solana_sdk::message::{Message, SanitizedMessage, VersionedMessage};
+use solana_sdk::message::{Message, MessageHeader, SanitizedMessage, VersionedMessage};
 use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
 
 /// Decompile a [VersionedMessage] back into its instructions.
 pub fn extract_instructions_from_versioned_message(
@@ -81,6 +84,110 @@ pub fn extract_instructions_from_message(message: &Message) -> Vec<Instruction>
         .collect()
 }
 
+/// Decompile a [VersionedMessage] offline, resolving the address table lookups of a v0 message
+/// from `tables`. Unlike [extract_instructions_from_versioned_message], accounts keep the
+/// writable flags requested by the message, even sysvars and programs that the runtime would
+/// lock as read-only, so compiling the instructions with the same tables reproduces the message,
+/// e.g. to sign it again.
+pub fn extract_instructions_with_lookup_tables(
+    message: &VersionedMessage,
+    tables: &[AddressLookupTableAccount],
+) -> Result<Vec<Instruction>, AddressLoaderError> {
+    let tables: HashMap<Pubkey, Vec<Pubkey>> = tables
+        .iter()
+        .map(|table| (table.key, table.addresses.clone()))
+        .collect();
+    let loaded: LoadedAddresses = lookup_addresses_from_tables(message, &tables)?
+        .into_iter()
+        .collect();
+    let header = message.header();
+    let static_keys = message.static_account_keys();
+    let keys: Vec<Pubkey> = static_keys
+        .iter()
+        .chain(&loaded.writable)
+        .chain(&loaded.readonly)
+        .copied()
+        .collect();
+    let meta = |index: u8| -> Result<AccountMeta, AddressLoaderError> {
+        let index = index as usize;
+        let key = keys
+            .get(index)
+            .copied()
+            .ok_or(AddressLoaderError::InvalidLookupIndex)?;
+        let is_signer = index < header.num_required_signatures as usize;
+        match is_requested_writable(header, static_keys.len(), &loaded, index) {
+            true => Ok(AccountMeta::new(key, is_signer)),
+            false => Ok(AccountMeta::new_readonly(key, is_signer)),
+        }
+    };
+    message
+        .instructions()
+        .iter()
+        .map(|ix| {
+            let program_id = meta(ix.program_id_index)?.pubkey;
+            let accounts = ix
+                .accounts
+                .iter()
+                .map(|index| meta(*index))
+                .collect::<Result<_, _>>()?;
+            Ok(Instruction::new_with_bytes(program_id, &ix.data, accounts))
+        })
+        .collect()
+}
+
+/// Whether the account at `index` of a message, with its loaded addresses appended
+/// to its `num_static` static keys, was requested as writable.
+fn is_requested_writable(
+    header: &MessageHeader,
+    num_static: usize,
+    loaded: &LoadedAddresses,
+    index: usize,
+) -> bool {
+    let num_signed = header.num_required_signatures as usize;
+    if index >= num_static {
+        index - num_static < loaded.writable.len()
+    } else if index >= num_signed {
+        let num_writable_unsigned = (num_static - num_signed)
+            .saturating_sub(header.num_readonly_unsigned_accounts as usize);
+        index - num_signed < num_writable_unsigned
+    } else {
+        index < num_signed.saturating_sub(header.num_readonly_signed_accounts as usize)
+    }
+}
+
+/// Resolve a message's address table lookups offline, from the addresses of each table.
+pub fn lookup_addresses_from_tables(
+    message: &VersionedMessage,
+    tables: &HashMap<Pubkey, Vec<Pubkey>>,
+) -> Result<Vec<LoadedAddresses>, AddressLoaderError> {
+    let Some(lookups) = message.address_table_lookups() else {
+        return Ok(vec![]);
+    };
+    lookups
+        .iter()
+        .map(|lookup| {
+            let addresses = tables
+                .get(&lookup.account_key)
+                .ok_or(AddressLoaderError::LookupTableAccountNotFound)?;
+            let load = |indexes: &[u8]| {
+                indexes
+                    .iter()
+                    .map(|idx| {
+                        addresses
+                            .get(*idx as usize)
+                            .copied()
+                            .ok_or(AddressLoaderError::InvalidLookupIndex)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            Ok(LoadedAddresses {
+                writable: load(&lookup.writable_indexes)?,
+                readonly: load(&lookup.readonly_indexes)?,
+            })
+        })
+        .collect()
+}
+
 #[cfg(feature = "async_client")]
 pub async fn lookup_addresses(
     client: &rpc_client::RpcClient,
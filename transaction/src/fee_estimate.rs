@@ -0,0 +1,387 @@
+//! Estimate what a message will cost its fee payer: the signature fee, the priority fee from its
+//! compute budget instructions, and the rent of the accounts it creates through the system
+//! program or the associated token account program.
+use crate::auto_budget::MAX_COMPUTE_UNIT_LIMIT;
+use crate::compute_budget::{self as budget, priority_fee};
+#[cfg(feature = "client")]
+use crate::decompile_instructions::extract_instructions_with_lookup_tables;
+#[cfg(feature = "async_client")]
+use solana_rpc_client::nonblocking::rpc_client;
+#[cfg(feature = "client")]
+use solana_rpc_client::rpc_client::RpcClient;
+#[cfg(feature = "client")]
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_sdk::compute_budget::{self, ComputeBudgetInstruction};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::system_program;
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "client")]
+use {
+    solana_program::message::AddressLoaderError,
+    solana_sdk::address_lookup_table_account::AddressLookupTableAccount,
+    solana_sdk::message::VersionedMessage, std::collections::HashMap, thiserror::Error,
+};
+
+/// The associated token account program.
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+const TOKEN_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// The size of an SPL Token account.
+pub const TOKEN_ACCOUNT_SIZE: usize = 165;
+/// The size of a Token-2022 account with the `ImmutableOwner` extension, which associated
+/// token accounts always have. Mints with extensions such as transfer fees make it larger.
+pub const TOKEN_2022_ACCOUNT_SIZE: usize = 170;
+
+/// The compute unit limit per instruction, when there is no `SetComputeUnitLimit` instruction.
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+
+#[cfg(feature = "client")]
+#[derive(Debug, Error)]
+pub enum FeeEstimateError {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error(transparent)]
+    AddressLoader(#[from] AddressLoaderError),
+}
+
+/// An account created by one of the instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewAccount {
+    pub address: Pubkey,
+    pub owner: Pubkey,
+    pub space: usize,
+    /// The lamports it is funded with, for associated token accounts the rent-exempt minimum.
+    pub lamports: u64,
+}
+
+/// The expected cost of a message to its fee payer, in lamports unless noted otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeEstimate {
+    /// The base fee for the message's signatures.
+    pub signature_fee: u64,
+    pub compute_unit_limit: u32,
+    /// In micro-lamports per compute unit.
+    pub compute_unit_price: u64,
+    /// The compute unit price times the limit.
+    pub priority_fee: u64,
+    pub new_accounts: Vec<NewAccount>,
+}
+
+impl FeeEstimate {
+    /// Estimate the cost of `instructions` offline, given the signature fee of their message.
+    /// Associated token accounts are funded with the rent-exempt minimum under `rent`.
+    pub fn new(signature_fee: u64, instructions: &[Instruction], rent: &Rent) -> Self {
+        Self::with_minimum_balance(signature_fee, instructions, |space| {
+            rent.minimum_balance(space)
+        })
+    }
+
+    fn with_minimum_balance(
+        signature_fee: u64,
+        instructions: &[Instruction],
+        minimum_balance: impl Fn(usize) -> u64,
+    ) -> Self {
+        let (compute_unit_limit, compute_unit_price) = compute_budget(instructions);
+        let priority_fee = priority_fee(compute_unit_price, compute_unit_limit);
+        let new_accounts = instructions
+            .iter()
+            .filter_map(|ix| new_account(ix, &minimum_balance))
+            .collect();
+        Self {
+            signature_fee,
+            compute_unit_limit,
+            compute_unit_price,
+            priority_fee,
+            new_accounts,
+        }
+    }
+
+    /// The signature fee plus the priority fee.
+    pub fn fees(&self) -> u64 {
+        self.signature_fee.saturating_add(self.priority_fee)
+    }
+
+    /// The lamports that fund new accounts.
+    pub fn rent(&self) -> u64 {
+        self.new_accounts
+            .iter()
+            .fold(0, |rent, account| rent.saturating_add(account.lamports))
+    }
+
+    pub fn total(&self) -> u64 {
+        self.fees().saturating_add(self.rent())
+    }
+}
+
+impl Display for FeeEstimate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Signature fee: {} lamports", self.signature_fee)?;
+        writeln!(
+            f,
+            "Priority fee: {} lamports ({} units at {} micro-lamports)",
+            self.priority_fee, self.compute_unit_limit, self.compute_unit_price
+        )?;
+        writeln!(
+            f,
+            "Rent: {} lamports ({} new accounts)",
+            self.rent(),
+            self.new_accounts.len()
+        )?;
+        write!(f, "Total: {} lamports", self.total())
+    }
+}
+
+/// The compute unit limit and price, in micro-lamports, requested by the instructions.
+pub fn compute_budget(instructions: &[Instruction]) -> (u32, u64) {
+    let (mut limit, mut price) = (None, 0);
+    let mut num_instructions = 0u32;
+    for ix in instructions {
+        if !compute_budget::check_id(&ix.program_id) {
+            num_instructions += 1;
+            continue;
+        }
+        match budget::parse(ix) {
+            Some(ComputeBudgetInstruction::SetComputeUnitLimit(units)) => limit = Some(units),
+            Some(ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports)) => {
+                price = micro_lamports
+            }
+            _ => {}
+        }
+    }
+    let limit =
+        limit.unwrap_or(num_instructions.saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT));
+    (limit.min(MAX_COMPUTE_UNIT_LIMIT), price)
+}
+
+fn new_account(ix: &Instruction, minimum_balance: impl Fn(usize) -> u64) -> Option<NewAccount> {
+    let account = |position: usize| ix.accounts.get(position).map(|meta| meta.pubkey);
+    if system_program::check_id(&ix.program_id) {
+        return match bincode::deserialize::<SystemInstruction>(&ix.data).ok()? {
+            SystemInstruction::CreateAccount {
+                lamports,
+                space,
+                owner,
+            }
+            | SystemInstruction::CreateAccountWithSeed {
+                lamports,
+                space,
+                owner,
+                ..
+            } => Some(NewAccount {
+                address: account(1)?,
+                owner,
+                space: space as usize,
+                lamports,
+            }),
+            _ => None,
+        };
+    }
+    let (address, owner, space) = associated_token_account(ix)?;
+    Some(NewAccount {
+        address,
+        owner,
+        space,
+        lamports: minimum_balance(space),
+    })
+}
+
+/// The address, token program and size of an associated token account created by `ix`.
+fn associated_token_account(ix: &Instruction) -> Option<(Pubkey, Pubkey, usize)> {
+    // `Create` and `CreateIdempotent`. The latter creates nothing if the account exists.
+    if ix.program_id != ASSOCIATED_TOKEN_PROGRAM_ID || !matches!(ix.data.as_slice(), [] | [0] | [1])
+    {
+        return None;
+    }
+    let address = ix.accounts.get(1)?.pubkey;
+    let owner = ix.accounts.get(5)?.pubkey;
+    let space = match owner == TOKEN_PROGRAM_ID {
+        true => TOKEN_ACCOUNT_SIZE,
+        false => TOKEN_2022_ACCOUNT_SIZE,
+    };
+    Some((address, owner, space))
+}
+
+/// The sizes of the associated token accounts the instructions create,
+/// whose rent-exempt minimum must be looked up.
+#[cfg(feature = "client")]
+fn associated_token_account_sizes(instructions: &[Instruction]) -> Vec<usize> {
+    let mut sizes: Vec<usize> = instructions
+        .iter()
+        .filter_map(associated_token_account)
+        .map(|(_, _, space)| space)
+        .collect();
+    sizes.sort_unstable();
+    sizes.dedup();
+    sizes
+}
+
+/// The message without its compute budget instructions, whose fee is the signature fee alone.
+#[cfg(feature = "client")]
+fn without_compute_budget(message: &VersionedMessage) -> VersionedMessage {
+    let mut message = message.clone();
+    let keys = message.static_account_keys().to_vec();
+    let is_budget = |program_id_index: u8| {
+        keys.get(program_id_index as usize)
+            .is_some_and(compute_budget::check_id)
+    };
+    match &mut message {
+        VersionedMessage::Legacy(message) => message
+            .instructions
+            .retain(|ix| !is_budget(ix.program_id_index)),
+        VersionedMessage::V0(message) => message
+            .instructions
+            .retain(|ix| !is_budget(ix.program_id_index)),
+    }
+    message
+}
+
+/// Estimate the cost of a message, whose blockhash must still be valid. The signature fee comes
+/// from `getFeeForMessage`, and the address table lookups of a v0 message are resolved
+/// from `tables`.
+#[cfg(feature = "client")]
+// Matches the `ClientError` returned by the blocking `RpcClient`.
+#[allow(clippy::result_large_err)]
+pub fn estimate_fee_blocking(
+    client: &RpcClient,
+    message: &VersionedMessage,
+    tables: &[AddressLookupTableAccount],
+) -> Result<FeeEstimate, FeeEstimateError> {
+    let instructions = extract_instructions_with_lookup_tables(message, tables)?;
+    let signature_fee = match without_compute_budget(message) {
+        VersionedMessage::Legacy(message) => client.get_fee_for_message(&message)?,
+        VersionedMessage::V0(message) => client.get_fee_for_message(&message)?,
+    };
+    let mut minimum_balances = HashMap::new();
+    for space in associated_token_account_sizes(&instructions) {
+        minimum_balances.insert(space, client.get_minimum_balance_for_rent_exemption(space)?);
+    }
+    Ok(FeeEstimate::with_minimum_balance(
+        signature_fee,
+        &instructions,
+        |space| minimum_balances[&space],
+    ))
+}
+
+#[cfg(feature = "async_client")]
+pub async fn estimate_fee(
+    client: &rpc_client::RpcClient,
+    message: &VersionedMessage,
+    tables: &[AddressLookupTableAccount],
+) -> Result<FeeEstimate, FeeEstimateError> {
+    let instructions = extract_instructions_with_lookup_tables(message, tables)?;
+    let signature_fee = match without_compute_budget(message) {
+        VersionedMessage::Legacy(message) => client.get_fee_for_message(&message).await?,
+        VersionedMessage::V0(message) => client.get_fee_for_message(&message).await?,
+    };
+    let mut minimum_balances = HashMap::new();
+    for space in associated_token_account_sizes(&instructions) {
+        minimum_balances.insert(
+            space,
+            client.get_minimum_balance_for_rent_exemption(space).await?,
+        );
+    }
+    Ok(FeeEstimate::with_minimum_balance(
+        signature_fee,
+        &instructions,
+        |space| minimum_balances[&space],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+    use solana_sdk::system_instruction;
+
+    /// An associated token account creation, as built by `spl-associated-token-account`.
+    fn create_associated_token_account(payer: &Pubkey, token_program: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            ASSOCIATED_TOKEN_PROGRAM_ID,
+            &[1],
+            vec![
+                AccountMeta::new(*payer, true),
+                AccountMeta::new(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(*token_program, false),
+            ],
+        )
+    }
+
+    #[test]
+    fn costs_are_broken_down() {
+        let payer = Pubkey::new_unique();
+        let (new, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let rent = Rent::default();
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(1_500),
+            system_instruction::create_account(&payer, &new, 1_000_000, 10, &owner),
+            create_associated_token_account(&payer, &TOKEN_PROGRAM_ID),
+            system_instruction::transfer(&payer, &new, 5),
+        ];
+
+        let estimate = FeeEstimate::new(5_000, &instructions, &rent);
+        assert_eq!(estimate.compute_unit_limit, 600_000);
+        assert_eq!(estimate.compute_unit_price, 1_500);
+        assert_eq!(estimate.priority_fee, 900);
+        assert_eq!(
+            estimate.new_accounts[0],
+            NewAccount {
+                address: new,
+                owner,
+                space: 10,
+                lamports: 1_000_000,
+            }
+        );
+        assert_eq!(estimate.new_accounts[1].space, TOKEN_ACCOUNT_SIZE);
+        assert_eq!(
+            estimate.rent(),
+            1_000_000 + rent.minimum_balance(TOKEN_ACCOUNT_SIZE)
+        );
+        assert_eq!(estimate.total(), 5_900 + estimate.rent());
+
+        let mut instructions = instructions.to_vec();
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(1_001));
+        let estimate = FeeEstimate::new(5_000, &instructions, &rent);
+        // Rounded up to the next lamport.
+        assert_eq!(estimate.priority_fee, 2);
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn signature_fee_excludes_priority_fee() {
+        use serde_json::json;
+        use solana_rpc_client_api::request::RpcRequest;
+        use solana_sdk::message::Message;
+
+        let client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            HashMap::from([
+                (
+                    RpcRequest::GetFeeForMessage,
+                    json!({ "context": { "slot": 1 }, "value": 5_000 }),
+                ),
+                (RpcRequest::GetMinimumBalanceForRentExemption, json!(2_000)),
+            ]),
+        );
+        let payer = Pubkey::new_unique();
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+            ComputeBudgetInstruction::set_compute_unit_limit(1_000),
+            create_associated_token_account(&payer, &Pubkey::new_unique()),
+        ];
+        let message = VersionedMessage::Legacy(Message::new(&instructions, Some(&payer)));
+        assert_eq!(without_compute_budget(&message).instructions().len(), 1);
+
+        let estimate = estimate_fee_blocking(&client, &message, &[]).unwrap();
+        assert_eq!(estimate.signature_fee, 5_000);
+        assert_eq!(estimate.priority_fee, 1_000);
+        assert_eq!(estimate.new_accounts[0].space, TOKEN_2022_ACCOUNT_SIZE);
+        assert_eq!(estimate.total(), 8_000);
+    }
+}
@@ -1,13 +1,13 @@
 use crate::decompile_instructions::extract_instructions_from_versioned_message;
-#[cfg(feature = "async_client")]
-use solana_client::nonblocking::rpc_client::RpcClient;
-#[cfg(feature = "client")]
-use solana_client::rpc_client;
-#[cfg(any(feature = "client", feature = "async_client"))]
-use solana_client::{client_error::ClientError, rpc_config::RpcTransactionConfig};
 use solana_program::instruction::CompiledInstruction;
 use solana_program::message::v0::{LoadedAddresses, LoadedMessage};
 use solana_program::message::VersionedMessage;
+#[cfg(feature = "async_client")]
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+#[cfg(feature = "client")]
+use solana_rpc_client::rpc_client;
+#[cfg(any(feature = "client", feature = "async_client"))]
+use solana_rpc_client_api::{client_error::Error as ClientError, config::RpcTransactionConfig};
 use solana_sdk::bs58;
 use solana_sdk::instruction::AccountMeta;
 use solana_sdk::instruction::Instruction;
@@ -20,6 +20,7 @@ use solana_transaction_status::UiTransactionEncoding;
 use solana_transaction_status::{
     EncodedConfirmedTransactionWithStatusMeta, EncodedTransactionWithStatusMeta,
     UiInnerInstructions, UiInstruction, UiLoadedAddresses, UiTransactionStatusMeta,
+    UiTransactionTokenBalance,
 };
 use std::collections::HashMap;
 use std::str::FromStr;
@@ -39,6 +40,20 @@ pub struct HistoricalTransaction {
     pub inner_instructions: HashMap<u8, Vec<CompiledInstruction>>,
 
     pub loaded_addresses: Option<Vec<LoadedAddresses>>,
+    /// The program logs recorded by the runtime, empty if the transaction was not executed.
+    pub log_messages: Vec<String>,
+    /// The fee charged to the fee payer, zero if the transaction was not executed.
+    pub fee: u64,
+    /// Lamport balances before and after execution, in the order of [Self::account_keys].
+    pub pre_balances: Vec<u64>,
+    pub post_balances: Vec<u64>,
+    /// Token balances before and after execution, for the token accounts the transaction loaded.
+    pub pre_token_balances: Vec<UiTransactionTokenBalance>,
+    pub post_token_balances: Vec<UiTransactionTokenBalance>,
+    /// `None` if the transaction was not executed, or the RPC node did not record it.
+    pub compute_units_consumed: Option<u64>,
+    /// The error the transaction failed with, if any.
+    pub err: Option<TransactionError>,
 }
 
 impl HistoricalTransaction {
@@ -47,6 +62,27 @@ impl HistoricalTransaction {
             message,
             inner_instructions: Default::default(),
             loaded_addresses,
+            log_messages: vec![],
+            fee: 0,
+            pre_balances: vec![],
+            post_balances: vec![],
+            pre_token_balances: vec![],
+            post_token_balances: vec![],
+            compute_units_consumed: None,
+            err: None,
+        }
+    }
+
+    /// The message's account keys, followed by any addresses loaded from lookup tables.
+    pub fn account_keys(&self) -> Vec<Pubkey> {
+        let loaded_addresses =
+            LoadedAddresses::from_iter(self.loaded_addresses.clone().unwrap_or_default());
+        match &self.message {
+            VersionedMessage::Legacy(message) => message.account_keys.clone(),
+            VersionedMessage::V0(message) => {
+                let message = LoadedMessage::new_borrowed(message, &loaded_addresses);
+                message.account_keys().iter().copied().collect()
+            }
         }
     }
 
@@ -95,9 +131,21 @@ impl TryFrom<EncodedConfirmedTransactionWithStatusMeta> for HistoricalTransactio
                 },
             ..
         } = value;
-        let (inner_instructions, loaded_addresses) = if let Some(UiTransactionStatusMeta {
+        let transaction = transaction
+            .decode()
+            .ok_or(TransactionError::SanitizeFailure)?;
+        let mut historical_transaction = Self::new(transaction.message, None);
+        if let Some(UiTransactionStatusMeta {
+            err,
+            fee,
+            pre_balances,
+            post_balances,
             inner_instructions,
+            log_messages,
+            pre_token_balances,
+            post_token_balances,
             loaded_addresses,
+            compute_units_consumed,
             ..
         }) = meta
         {
@@ -119,18 +167,26 @@ impl TryFrom<EncodedConfirmedTransactionWithStatusMeta> for HistoricalTransactio
                         .collect(),
                 }]
             });
-            (inner_instructions, loaded_addresses)
-        } else {
-            (HashMap::<u8, Vec<CompiledInstruction>>::new(), None)
-        };
-        let transaction = transaction
-            .decode()
-            .ok_or(TransactionError::SanitizeFailure)?;
-        Ok(Self {
-            message: transaction.message,
-            inner_instructions,
-            loaded_addresses,
-        })
+            let log_messages: Option<Vec<String>> = log_messages.into();
+            let pre_token_balances: Option<Vec<UiTransactionTokenBalance>> =
+                pre_token_balances.into();
+            let post_token_balances: Option<Vec<UiTransactionTokenBalance>> =
+                post_token_balances.into();
+            historical_transaction = Self {
+                inner_instructions,
+                loaded_addresses,
+                log_messages: log_messages.unwrap_or_default(),
+                fee,
+                pre_balances,
+                post_balances,
+                pre_token_balances: pre_token_balances.unwrap_or_default(),
+                post_token_balances: post_token_balances.unwrap_or_default(),
+                compute_units_consumed: compute_units_consumed.into(),
+                err,
+                ..historical_transaction
+            };
+        }
+        Ok(historical_transaction)
     }
 }
 
@@ -182,15 +238,15 @@ impl DecompiledMessageAndInnerIx {
 
 impl From<HistoricalTransaction> for DecompiledMessageAndInnerIx {
     fn from(value: HistoricalTransaction) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl From<&HistoricalTransaction> for DecompiledMessageAndInnerIx {
+    fn from(value: &HistoricalTransaction) -> Self {
+        let addrs = value.account_keys();
         let loaded_addresses =
-            LoadedAddresses::from_iter(value.loaded_addresses.unwrap_or_default());
-        let addrs: Vec<Pubkey> = match &value.message {
-            VersionedMessage::Legacy(message) => message.account_keys.clone(),
-            VersionedMessage::V0(message) => {
-                let message = LoadedMessage::new_borrowed(message, &loaded_addresses);
-                message.account_keys().iter().map(|p| *p).collect()
-            }
-        };
+            LoadedAddresses::from_iter(value.loaded_addresses.clone().unwrap_or_default());
         let is_writable = |idx| match &value.message {
             VersionedMessage::Legacy(m) => m.is_writable(idx),
             VersionedMessage::V0(m) => {
@@ -208,7 +264,7 @@ impl From<HistoricalTransaction> for DecompiledMessageAndInnerIx {
             extract_instructions_from_versioned_message(&value.message, &loaded_addresses);
 
         let mut inner_instructions = HashMap::new();
-        for (idx, compiled_instructions) in value.inner_instructions {
+        for (idx, compiled_instructions) in &value.inner_instructions {
             let inner_ix = compiled_instructions
                 .iter()
                 .map(|ix| {
@@ -230,7 +286,7 @@ impl From<HistoricalTransaction> for DecompiledMessageAndInnerIx {
                     Instruction::new_with_bytes(*program, &ix.data, account_metas)
                 })
                 .collect();
-            inner_instructions.insert(idx, inner_ix);
+            inner_instructions.insert(*idx, inner_ix);
         }
 
         DecompiledMessageAndInnerIx {
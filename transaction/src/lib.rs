@@ -1,7 +1,21 @@
+#[cfg(feature = "audit")]
+pub mod audit;
+pub mod auto_budget;
+pub mod combinators;
+pub mod compute_budget;
 pub mod decompile_instructions;
+pub mod fee_estimate;
+#[cfg(feature = "history")]
 pub mod inner_instructions;
 pub mod mutated_instruction;
+pub mod packet_size;
+pub mod priority_fee;
 
+use auto_budget::{ComputeUnitSimulator, SafetyMargin};
+use combinators::{Interleave, Then, WithComputeBudget, WithGuard, WithMemo};
+use packet_size::SplitError;
+#[cfg(feature = "derive")]
+pub use solana_devtools_macros::TransactionSchema;
 use solana_program::message::CompileError;
 /// Define a struct representing a transaction schema.
 /// Implementing [TransactionSchema] allows for a number of
@@ -14,6 +28,11 @@ use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signer::SignerError;
 use solana_sdk::signers::Signers;
 use solana_sdk::transaction::{Transaction, VersionedTransaction};
+#[cfg(feature = "client")]
+use {
+    priority_fee::PriorityFeeStrategy, solana_rpc_client::rpc_client::RpcClient,
+    solana_rpc_client_api::client_error::Error as ClientError,
+};
 
 /// Facilitates the creation of (un-)signed transactions, potentially serialized,
 /// or lists of serialized instructions.
@@ -93,6 +112,55 @@ pub trait TransactionSchema: Sized {
     /// Return the instructions.
     fn instructions(self) -> Vec<Instruction>;
 
+    /// Return the instructions, preceded by compute budget instructions.
+    /// See [priority_fee::with_compute_budget].
+    fn instructions_with_compute_budget(
+        self,
+        compute_unit_price: u64,
+        compute_unit_limit: Option<u32>,
+    ) -> Vec<Instruction> {
+        priority_fee::with_compute_budget(
+            self.instructions(),
+            compute_unit_price,
+            compute_unit_limit,
+        )
+    }
+
+    /// Return the instructions, preceded by a compute unit limit measured by `simulator`.
+    /// See [auto_budget::auto_compute_unit_limit].
+    fn instructions_with_compute_unit_limit<S: ComputeUnitSimulator>(
+        self,
+        payer: &Pubkey,
+        simulator: &S,
+        margin: SafetyMargin,
+    ) -> Result<Vec<Instruction>, S::Error> {
+        auto_budget::auto_compute_unit_limit(self.instructions(), payer, simulator, margin)
+    }
+
+    /// Return a signed transaction, with a compute unit price chosen by `strategy`
+    /// from the fees recently paid to write-lock the same accounts.
+    #[cfg(feature = "client")]
+    fn transaction_with_priority_fee(
+        self,
+        client: &RpcClient,
+        strategy: PriorityFeeStrategy,
+        compute_unit_limit: Option<u32>,
+        blockhash: Hash,
+        payer: Option<&Pubkey>,
+        signers: &impl Signers,
+    ) -> Result<VersionedTransaction, ClientError> {
+        let instructions = self.instructions();
+        let mut writable_accounts = priority_fee::writable_accounts(&instructions);
+        if let Some(payer) = payer {
+            writable_accounts.insert(0, *payer);
+        }
+        let price =
+            priority_fee::estimate_priority_fee_blocking(client, &writable_accounts, strategy)?;
+        let instructions =
+            priority_fee::with_compute_budget(instructions, price, compute_unit_limit);
+        Ok(instructions.transaction(blockhash, payer, signers))
+    }
+
     /// Return the instructions in serialized form.
     fn instructions_serialized(self) -> Vec<Vec<u8>> {
         let ixs: Vec<Instruction> = self.instructions();
@@ -101,12 +169,71 @@ pub trait TransactionSchema: Sized {
             .collect()
     }
 
+    /// Split the instructions into batches that each fit in a transaction packet.
+    /// See [packet_size::split_instructions].
+    fn split_into_packets(
+        self,
+        payer: &Pubkey,
+        lookups: &[AddressLookupTableAccount],
+    ) -> Result<Vec<Vec<Instruction>>, SplitError> {
+        packet_size::split_instructions(self.instructions(), payer, lookups)
+    }
+
+    /// Follow these instructions with those of `other`.
+    fn then<T: TransactionSchema>(self, other: T) -> Then<Self, T> {
+        Then {
+            first: self,
+            second: other,
+        }
+    }
+
+    /// Alternate these instructions with those of `other`. See [combinators::Interleave].
+    fn interleave<T: TransactionSchema>(self, other: T) -> Interleave<Self, T> {
+        Interleave {
+            first: self,
+            second: other,
+        }
+    }
+
+    /// Precede these instructions with compute budget instructions, replacing any already present.
+    fn with_compute_budget(
+        self,
+        compute_unit_limit: u32,
+        compute_unit_price: u64,
+    ) -> WithComputeBudget<Self> {
+        WithComputeBudget {
+            inner: self,
+            compute_unit_limit,
+            compute_unit_price,
+        }
+    }
+
+    /// Follow these instructions with an SPL memo.
+    fn with_memo<M: Into<String>>(self, memo: M) -> WithMemo<Self> {
+        WithMemo {
+            inner: self,
+            memo: memo.into(),
+        }
+    }
+
+    /// Precede these instructions with `guard`, e.g. an ed25519 signature verification.
+    /// See [combinators::WithGuard].
+    fn with_guard(self, guard: Instruction) -> WithGuard<Self> {
+        WithGuard { inner: self, guard }
+    }
+
     fn programs(self) -> Vec<Pubkey> {
         let ixs: Vec<Instruction> = self.instructions();
         ixs.into_iter().map(|ix| ix.program_id).collect()
     }
 }
 
+/// Used by code generated with `#[derive(TransactionSchema)]`.
+#[doc(hidden)]
+pub mod __private {
+    pub use solana_sdk::instruction::Instruction;
+}
+
 impl<T: Sized> TransactionSchema for T
 where
     T: Into<Vec<Instruction>>,
@@ -251,4 +378,152 @@ mod tests {
         let _ = ixs.clone().instructions();
         let _ = ixs.clone().instructions_serialized();
     }
+
+    #[test]
+    fn lookups_resolve_from_tables() {
+        use crate::decompile_instructions::{
+            extract_instructions_from_versioned_message, lookup_addresses_from_tables,
+        };
+        use solana_program::message::AddressLoaderError;
+        use solana_sdk::instruction::AccountMeta;
+        use std::collections::HashMap;
+
+        let payer = Pubkey::new_unique();
+        let looked_up = Pubkey::new_unique();
+        let memo = Instruction::new_with_bytes(
+            spl_memo::id(),
+            b"hello world",
+            vec![AccountMeta::new_readonly(looked_up, false)],
+        );
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: vec![Pubkey::new_unique(), looked_up],
+        };
+        let message = VersionedMessage::V0(
+            v0::Message::try_compile(
+                &payer,
+                std::slice::from_ref(&memo),
+                std::slice::from_ref(&table),
+                Hash::default(),
+            )
+            .unwrap(),
+        );
+
+        let tables = HashMap::from([(table.key, table.addresses)]);
+        let loaded = lookup_addresses_from_tables(&message, &tables).unwrap();
+        assert_eq!(loaded[0].readonly, vec![looked_up]);
+        assert_eq!(
+            extract_instructions_from_versioned_message(&message, &loaded[0]),
+            vec![memo]
+        );
+        assert_eq!(
+            lookup_addresses_from_tables(&message, &HashMap::new()),
+            Err(AddressLoaderError::LookupTableAccountNotFound)
+        );
+    }
+
+    #[test]
+    fn v0_messages_decompile_offline() {
+        use crate::decompile_instructions::extract_instructions_with_lookup_tables;
+        use solana_sdk::instruction::AccountMeta;
+
+        let payer = Pubkey::new_unique();
+        let signer = Pubkey::new_unique();
+        let (writable, readonly) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let tables = [
+            AddressLookupTableAccount {
+                key: Pubkey::new_unique(),
+                addresses: vec![Pubkey::new_unique(), writable],
+            },
+            AddressLookupTableAccount {
+                key: Pubkey::new_unique(),
+                addresses: vec![readonly, Pubkey::new_unique()],
+            },
+        ];
+        let instructions = vec![
+            Instruction::new_with_bytes(
+                spl_memo::id(),
+                b"hello world",
+                vec![
+                    AccountMeta::new(writable, false),
+                    AccountMeta::new_readonly(signer, true),
+                ],
+            ),
+            Instruction::new_with_bytes(
+                spl_memo::id(),
+                b"hola mundo",
+                vec![
+                    AccountMeta::new_readonly(readonly, false),
+                    AccountMeta::new(writable, false),
+                    // Requested writable, though the runtime locks sysvars as read-only.
+                    AccountMeta::new(solana_sdk::sysvar::clock::id(), false),
+                ],
+            ),
+        ];
+        let message =
+            v0::Message::try_compile(&payer, &instructions, &tables, Hash::default()).unwrap();
+        assert_eq!(message.address_table_lookups.len(), 2);
+        let message = VersionedMessage::V0(message);
+
+        let decompiled = extract_instructions_with_lookup_tables(&message, &tables).unwrap();
+        assert_eq!(decompiled, instructions);
+        let recompiled =
+            v0::Message::try_compile(&payer, &decompiled, &tables, Hash::default()).unwrap();
+        assert_eq!(VersionedMessage::V0(recompiled), message);
+
+        assert!(extract_instructions_with_lookup_tables(&message, &tables[..1]).is_err());
+    }
+
+    #[test]
+    fn legacy_messages_decompile_offline() {
+        use crate::decompile_instructions::extract_instructions_with_lookup_tables;
+        use solana_sdk::instruction::AccountMeta;
+
+        let payer = Pubkey::new_unique();
+        let instructions = vec![Instruction::new_with_bytes(
+            spl_memo::id(),
+            b"hello world",
+            vec![
+                AccountMeta::new(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), true),
+                // Requested writable, though the runtime locks sysvars and programs as read-only.
+                AccountMeta::new(solana_sdk::sysvar::clock::id(), false),
+                AccountMeta::new(spl_memo::id(), false),
+            ],
+        )];
+        let message = VersionedMessage::Legacy(Message::new(&instructions, Some(&payer)));
+
+        let decompiled = extract_instructions_with_lookup_tables(&message, &[]).unwrap();
+        assert_eq!(decompiled, instructions);
+        let recompiled = Message::new(&decompiled, Some(&payer));
+        assert_eq!(VersionedMessage::Legacy(recompiled), message);
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn priority_fee_from_recent_fees() {
+        use solana_sdk::compute_budget::ComputeBudgetInstruction;
+
+        let client = RpcClient::new_mock("succeeds".to_string());
+        let key = Keypair::new();
+        let tx = vec![build_memo(b"hello world", &[])]
+            .transaction_with_priority_fee(
+                &client,
+                PriorityFeeStrategy::Median,
+                Some(10_000),
+                Hash::new_unique(),
+                Some(&key.pubkey()),
+                &[&key],
+            )
+            .unwrap();
+        let message = tx.into_legacy_transaction().unwrap().message;
+        let instructions = extract_instructions_from_message(&message);
+        assert_eq!(
+            instructions[..2],
+            [
+                ComputeBudgetInstruction::set_compute_unit_price(10_000),
+                ComputeBudgetInstruction::set_compute_unit_limit(10_000),
+            ]
+        );
+    }
 }
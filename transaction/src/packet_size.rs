@@ -0,0 +1,195 @@
+//! Estimate the serialized size of a transaction, and split instructions into batches
+//! that each fit in a single packet.
+use solana_program::message::CompileError;
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::{v0, Message, VersionedMessage};
+pub use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::collections::HashSet;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SplitError {
+    /// The instruction does not fit in a transaction on its own.
+    #[error("instruction {index} needs a {size} byte transaction on its own, over the {PACKET_DATA_SIZE} byte limit")]
+    InstructionTooLarge { index: usize, size: usize },
+    #[error("instruction {index} does not compile on its own: {error}")]
+    Compile { index: usize, error: CompileError },
+}
+
+/// Serialized sizes of the same instructions, in bytes, including signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeEstimate {
+    /// A legacy message, or `None` if it has too many accounts to compile.
+    pub legacy: Option<usize>,
+    /// A v0 message with the given lookup tables, or `None` if it fails to compile.
+    pub with_lookups: Option<usize>,
+}
+
+impl SizeEstimate {
+    pub fn fits(&self) -> bool {
+        self.smallest().is_some_and(|size| size <= PACKET_DATA_SIZE)
+    }
+
+    /// The smaller of the sizes, or `None` if neither message compiles.
+    pub fn smallest(&self) -> Option<usize> {
+        [self.legacy, self.with_lookups].into_iter().flatten().min()
+    }
+}
+
+/// The serialized size of a transaction with the message and its required signatures.
+pub fn serialized_size(message: VersionedMessage) -> usize {
+    let signatures = message.header().num_required_signatures as usize;
+    let tx = VersionedTransaction {
+        signatures: vec![Signature::default(); signatures],
+        message,
+    };
+    bincode::serialized_size(&tx).expect("transaction failed to serialize") as usize
+}
+
+/// Fails instead of panicking like [Message::new] if there are too many accounts to index.
+pub fn legacy_size(instructions: &[Instruction], payer: &Pubkey) -> Result<usize, CompileError> {
+    let mut accounts = HashSet::from([payer]);
+    for ix in instructions {
+        accounts.insert(&ix.program_id);
+        accounts.extend(ix.accounts.iter().map(|meta| &meta.pubkey));
+    }
+    // The header counts are `u8`s, and such a message is far over the packet size anyway.
+    if accounts.len() > u8::MAX as usize {
+        return Err(CompileError::AccountIndexOverflow);
+    }
+    Ok(serialized_size(VersionedMessage::Legacy(Message::new(
+        instructions,
+        Some(payer),
+    ))))
+}
+
+pub fn v0_size(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    lookups: &[AddressLookupTableAccount],
+) -> Result<usize, CompileError> {
+    let message = v0::Message::try_compile(payer, instructions, lookups, Hash::default())?;
+    Ok(serialized_size(VersionedMessage::V0(message)))
+}
+
+pub fn estimate_size(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    lookups: &[AddressLookupTableAccount],
+) -> SizeEstimate {
+    SizeEstimate {
+        legacy: legacy_size(instructions, payer).ok(),
+        with_lookups: v0_size(instructions, payer, lookups).ok(),
+    }
+}
+
+/// Split instructions, in order, into as few consecutive batches as fit in a packet each,
+/// as v0 transactions with `lookups`, or as legacy transactions if `lookups` is empty.
+/// Instructions that must execute atomically should not be split this way.
+pub fn split_instructions(
+    instructions: Vec<Instruction>,
+    payer: &Pubkey,
+    lookups: &[AddressLookupTableAccount],
+) -> Result<Vec<Vec<Instruction>>, SplitError> {
+    let size = |batch: &[Instruction]| {
+        if lookups.is_empty() {
+            legacy_size(batch, payer)
+        } else {
+            v0_size(batch, payer, lookups)
+        }
+    };
+    let mut batches: Vec<Vec<Instruction>> = vec![];
+    let mut batch: Vec<Instruction> = vec![];
+    for (index, ix) in instructions.into_iter().enumerate() {
+        batch.push(ix);
+        // Too many accounts to index also means the batch is too large.
+        if size(&batch).is_ok_and(|size| size <= PACKET_DATA_SIZE) {
+            continue;
+        }
+        let ix = batch.pop().unwrap();
+        match size(std::slice::from_ref(&ix)) {
+            Ok(size) if size > PACKET_DATA_SIZE => {
+                return Err(SplitError::InstructionTooLarge { index, size })
+            }
+            Err(error) => return Err(SplitError::Compile { index, error }),
+            Ok(_) => {}
+        }
+        batches.push(std::mem::replace(&mut batch, vec![ix]));
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+
+    #[test]
+    fn instructions_split_into_packets() {
+        let payer = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let accounts: Vec<Pubkey> = (0..40).map(|_| Pubkey::new_unique()).collect();
+        let ix = |account: Pubkey| {
+            Instruction::new_with_bytes(program, &[0; 10], vec![AccountMeta::new(account, false)])
+        };
+        let instructions: Vec<Instruction> = accounts.iter().copied().map(ix).collect();
+
+        let estimate = estimate_size(&instructions, &payer, &[]);
+        assert!(!estimate.fits());
+        assert_eq!(estimate.with_lookups, Some(estimate.legacy.unwrap() + 2));
+
+        let batches = split_instructions(instructions.clone(), &payer, &[]).unwrap();
+        assert!(batches.len() > 1);
+        assert_eq!(batches.concat(), instructions);
+        for batch in &batches {
+            assert!(legacy_size(batch, &payer).unwrap() <= PACKET_DATA_SIZE);
+        }
+
+        // Looking up the accounts saves 31 bytes each, so fewer batches are needed.
+        let lookups = [AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: accounts,
+        }];
+        let estimate = estimate_size(&instructions, &payer, &lookups);
+        assert!(estimate.with_lookups.unwrap() < estimate.legacy.unwrap());
+        let with_lookups = split_instructions(instructions, &payer, &lookups).unwrap();
+        assert!(with_lookups.len() < batches.len());
+
+        let too_large = Instruction::new_with_bytes(program, &[0; PACKET_DATA_SIZE], vec![]);
+        assert!(matches!(
+            split_instructions(vec![too_large], &payer, &[]),
+            Err(SplitError::InstructionTooLarge { index: 0, .. })
+        ));
+
+        // Too many accounts to compile is an error rather than a panic.
+        let too_many = Instruction::new_with_bytes(
+            program,
+            &[],
+            (0..300)
+                .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+                .collect(),
+        );
+        assert_eq!(
+            estimate_size(std::slice::from_ref(&too_many), &payer, &[]),
+            SizeEstimate {
+                legacy: None,
+                with_lookups: None,
+            }
+        );
+        assert_eq!(
+            split_instructions(vec![ix(Pubkey::new_unique()), too_many], &payer, &[]),
+            Err(SplitError::Compile {
+                index: 1,
+                error: CompileError::AccountIndexOverflow,
+            })
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Price a transaction's compute units from the fees recently paid by transactions
+//! that write-locked the same accounts, as reported by `getRecentPrioritizationFees`.
+use crate::compute_budget::{is_set_compute_unit_limit, is_set_compute_unit_price};
+#[cfg(feature = "async_client")]
+use solana_rpc_client::nonblocking::rpc_client;
+#[cfg(feature = "client")]
+use solana_rpc_client::rpc_client::RpcClient;
+#[cfg(feature = "client")]
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+
+/// How to choose a price from the fees paid over recent slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorityFeeStrategy {
+    Median,
+    /// From 0 to 100, e.g. 75.
+    Percentile(u8),
+    Max,
+}
+
+impl PriorityFeeStrategy {
+    /// The nearest-rank percentile of `fees`, or zero if there are none.
+    pub fn price(&self, fees: impl IntoIterator<Item = u64>) -> u64 {
+        let rank = match self {
+            Self::Median => 50,
+            Self::Percentile(rank) => *rank,
+            Self::Max => 100,
+        };
+        percentile(fees, rank)
+    }
+}
+
+/// The nearest-rank `percentile` of `values`, from 0 to 100, or zero if there are none.
+pub fn percentile(values: impl IntoIterator<Item = u64>, percentile: u8) -> u64 {
+    let mut values: Vec<u64> = values.into_iter().collect();
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    let rank = (values.len() * percentile.min(100) as usize).div_ceil(100);
+    values[rank.saturating_sub(1)]
+}
+
+/// The accounts the instructions write-lock, which determine the fees they compete with.
+pub fn writable_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut writable = vec![];
+    for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+        if meta.is_writable && !writable.contains(&meta.pubkey) {
+            writable.push(meta.pubkey);
+        }
+    }
+    writable
+}
+
+/// Prepend compute budget instructions to `instructions`,
+/// replacing any compute unit price or limit instructions already present.
+pub fn with_compute_budget(
+    instructions: Vec<Instruction>,
+    compute_unit_price: u64,
+    compute_unit_limit: Option<u32>,
+) -> Vec<Instruction> {
+    let mut with_budget = vec![ComputeBudgetInstruction::set_compute_unit_price(
+        compute_unit_price,
+    )];
+    if let Some(limit) = compute_unit_limit {
+        with_budget.push(ComputeBudgetInstruction::set_compute_unit_limit(limit));
+    }
+    with_budget.extend(
+        instructions
+            .into_iter()
+            .filter(|ix| !is_set_compute_unit_price(ix) && !is_set_compute_unit_limit(ix)),
+    );
+    with_budget
+}
+
+#[cfg(feature = "client")]
+pub fn estimate_priority_fee_blocking(
+    client: &RpcClient,
+    writable_accounts: &[Pubkey],
+    strategy: PriorityFeeStrategy,
+) -> Result<u64, ClientError> {
+    let fees = client.get_recent_prioritization_fees(writable_accounts)?;
+    Ok(strategy.price(fees.into_iter().map(|fee| fee.prioritization_fee)))
+}
+
+#[cfg(feature = "async_client")]
+pub async fn estimate_priority_fee(
+    client: &rpc_client::RpcClient,
+    writable_accounts: &[Pubkey],
+    strategy: PriorityFeeStrategy,
+) -> Result<u64, ClientError> {
+    let fees = client
+        .get_recent_prioritization_fees(writable_accounts)
+        .await?;
+    Ok(strategy.price(fees.into_iter().map(|fee| fee.prioritization_fee)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+
+    #[test]
+    fn compute_budget_is_replaced() {
+        assert_eq!(PriorityFeeStrategy::Median.price([]), 0);
+        assert_eq!(PriorityFeeStrategy::Median.price([5, 1, 4, 2, 3]), 3);
+        assert_eq!(
+            PriorityFeeStrategy::Percentile(75).price([5, 1, 4, 2, 3]),
+            4
+        );
+        assert_eq!(PriorityFeeStrategy::Max.price([5, 1, 4, 2, 3]), 5);
+        assert_eq!(percentile([5, 1, 4, 2, 3], 0), 1);
+        assert_eq!(percentile([5, 1, 4, 2, 3], 200), 5);
+
+        let account = Pubkey::new_unique();
+        let ix = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![AccountMeta::new(account, false)],
+        );
+        let instructions = with_compute_budget(
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_price(1),
+                ix.clone(),
+            ],
+            500,
+            Some(200_000),
+        );
+        assert_eq!(
+            instructions,
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_price(500),
+                ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+                ix,
+            ]
+        );
+        assert_eq!(writable_accounts(&instructions), vec![account]);
+    }
+}